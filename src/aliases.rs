@@ -0,0 +1,143 @@
+//! Display names for dependencies.
+//!
+//! Some crates are better known by another name than the one they are published
+//! under, such as `serde_json` for "Serde JSON". An aliases file maps registry names
+//! to display names in its `[aliases]` table:
+//!
+//! ```toml
+//! [aliases]
+//! serde_json = "Serde JSON"
+//! tokio-util = "Tokio utilities"
+//! ```
+//!
+//! The display names title the code banks and the index, while the bank files keep
+//! the registry names, which are unique.
+
+use crate::Dependency;
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Reads the `[aliases]` table of an aliases file.
+///
+/// # Arguments
+///
+/// * `path` - Path to the TOML aliases file
+///
+/// # Returns
+///
+/// * `Result<BTreeMap<String, String>>` - The display names, by registry name. Empty if
+///   the file has no `[aliases]` table
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or parsed, or if an alias is not a
+/// non-empty string
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use depbank::read_aliases_file;
+/// use std::path::Path;
+///
+/// let aliases = read_aliases_file(Path::new("aliases.toml")).unwrap();
+/// println!("{} aliases", aliases.len());
+/// ```
+pub fn read_aliases_file(path: &Path) -> Result<BTreeMap<String, String>> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read aliases file: {}", path.display()))?;
+    let table: toml::Table = toml::from_str(&content)
+        .with_context(|| format!("Failed to parse aliases file: {}", path.display()))?;
+
+    let Some(aliases) = table.get("aliases") else {
+        return Ok(BTreeMap::new());
+    };
+    let aliases = aliases.as_table().ok_or_else(|| {
+        anyhow::anyhow!(
+            "[aliases] is not a table in aliases file: {}",
+            path.display()
+        )
+    })?;
+
+    aliases
+        .iter()
+        .map(|(name, alias)| match alias.as_str().map(str::trim) {
+            Some(alias) if !alias.is_empty() => Ok((name.clone(), alias.to_string())),
+            _ => Err(anyhow::anyhow!(
+                "The alias of {} is not a non-empty string in aliases file: {}",
+                name,
+                path.display()
+            )),
+        })
+        .collect()
+}
+
+/// Returns the name a dependency is shown under: its alias, if it has one, or its
+/// registry name.
+///
+/// # Examples
+///
+/// ```rust
+/// use depbank::{Dependency, display_name};
+/// use std::collections::BTreeMap;
+///
+/// let aliases = BTreeMap::from([("serde_json".to_string(), "Serde JSON".to_string())]);
+/// assert_eq!(display_name(&Dependency::new("serde_json", "1.0.0"), &aliases), "Serde JSON");
+/// assert_eq!(display_name(&Dependency::new("serde", "1.0.0"), &aliases), "serde");
+/// ```
+pub fn display_name(dep: &Dependency, aliases: &BTreeMap<String, String>) -> String {
+    aliases
+        .get(&dep.name)
+        .cloned()
+        .unwrap_or_else(|| dep.name.clone())
+}
+
+/// Titles a code bank with the alias of its dependency, keeping the registry name,
+/// which is the one to write in code.
+pub(crate) fn alias_bank_title(markdown: &str, alias: &str, name: &str) -> String {
+    let title = format!("# Code Bank: {} (`{}`)", alias, name);
+    match markdown.split_once('\n') {
+        Some((first, rest)) if first.starts_with("# ") => format!("{}\n{}", title, rest),
+        _ => format!("{}\n\n{}", title, markdown),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_read_aliases_file() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let path = temp_dir.path().join("aliases.toml");
+        fs::write(
+            &path,
+            "[aliases]\nserde_json = \"Serde JSON\"\ntokio-util = \" Tokio utilities \"\n",
+        )?;
+
+        let aliases = read_aliases_file(&path)?;
+        assert_eq!(aliases["serde_json"], "Serde JSON");
+        assert_eq!(aliases["tokio-util"], "Tokio utilities");
+
+        fs::write(&path, "[aliases]\nserde_json = 1\n")?;
+        assert!(read_aliases_file(&path).is_err());
+        fs::write(&path, "[other]\nkey = 1\n")?;
+        assert!(read_aliases_file(&path)?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_alias_bank_title() {
+        assert_eq!(
+            alias_bank_title("# Code Bank\n## src/lib.rs\n", "Serde JSON", "serde_json"),
+            "# Code Bank: Serde JSON (`serde_json`)\n## src/lib.rs\n"
+        );
+        assert_eq!(
+            alias_bank_title("## src/lib.rs\n", "Serde JSON", "serde_json"),
+            "# Code Bank: Serde JSON (`serde_json`)\n\n## src/lib.rs\n"
+        );
+    }
+}
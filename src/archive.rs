@@ -0,0 +1,478 @@
+//! Archiving of generated code banks.
+//!
+//! The files of an output directory can be packed into a single tar archive,
+//! gzipped when its name ends in `.gz` or `.tgz`, to cache or ship them as one
+//! artifact. Archives are byte-reproducible: entries are sorted by path, and their
+//! metadata does not depend on the machine or on when they were written. Every
+//! entry gets the same modification time, taken from `SOURCE_DATE_EPOCH` when set,
+//! so CI can compare archives by their hash.
+//!
+//! Banks can also be streamed into an archive as they are generated, see
+//! [`generate_all_to_archive`], without writing them to an output directory.
+
+use crate::{
+    DependencyCollection, GenerateOptions, VersionConflictPolicy, Warning, WarningKind,
+    emit_warning, generate_code_bank_to_writer, resolve_version_conflicts,
+};
+use anyhow::{Context, Result};
+use flate2::{Compression, GzBuilder};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Component, Path, PathBuf};
+use tar::{Builder, EntryType, Header};
+
+/// The environment variable holding the timestamp of reproducible builds, in seconds
+/// since the Unix epoch
+pub const SOURCE_DATE_EPOCH_VAR: &str = "SOURCE_DATE_EPOCH";
+
+/// Returns the modification time given to archive entries: `SOURCE_DATE_EPOCH` if
+/// it holds a timestamp, the Unix epoch otherwise
+///
+/// # Errors
+///
+/// Returns an error if `SOURCE_DATE_EPOCH` is after 2106-02-07, which gzip headers
+/// cannot hold
+pub fn archive_mtime() -> Result<u32> {
+    match env::var(SOURCE_DATE_EPOCH_VAR) {
+        Ok(epoch) => parse_source_date_epoch(&epoch),
+        Err(_) => Ok(0),
+    }
+}
+
+fn parse_source_date_epoch(epoch: &str) -> Result<u32> {
+    let Ok(seconds) = epoch.trim().parse::<u64>() else {
+        return Ok(0);
+    };
+    u32::try_from(seconds).map_err(|_| {
+        anyhow::anyhow!(
+            "{} is too large for an archive timestamp, the maximum is {}: {}",
+            SOURCE_DATE_EPOCH_VAR,
+            u32::MAX,
+            seconds
+        )
+    })
+}
+
+/// Packs files of a directory into a reproducible tar archive.
+///
+/// The archive is gzipped if its name ends in `.gz` or `.tgz`. Entries are sorted
+/// by path, owned by root with mode `0644`, and all get the same modification time,
+/// so packing the same files twice gives the same bytes.
+///
+/// # Arguments
+///
+/// * `dir` - The directory holding the files
+/// * `files` - The files to pack, relative to `dir`
+/// * `replaced` - Content to pack instead of that of some of the files, by their path
+///   relative to `dir`, e.g. an `index.json` without generation times
+/// * `archive_path` - Path of the archive to write
+/// * `mtime` - Modification time of the entries, e.g. from [`archive_mtime`]
+///
+/// # Returns
+///
+/// * `Result<PathBuf>` - Path to the written archive
+///
+/// # Errors
+///
+/// Returns an error for a `.zip` archive, which is not supported, if a file cannot
+/// be read, or if the archive cannot be written
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use depbank::{archive_mtime, write_archive};
+/// use std::collections::BTreeMap;
+/// use std::path::{Path, PathBuf};
+///
+/// let files = vec![PathBuf::from("README.md"), PathBuf::from("serde.md")];
+/// write_archive(
+///     Path::new(".codebank"),
+///     &files,
+///     &BTreeMap::new(),
+///     Path::new("codebank.tar.gz"),
+///     archive_mtime().unwrap(),
+/// )
+/// .unwrap();
+/// ```
+pub fn write_archive(
+    dir: &Path,
+    files: &[PathBuf],
+    replaced: &BTreeMap<PathBuf, Vec<u8>>,
+    archive_path: &Path,
+    mtime: u32,
+) -> Result<PathBuf> {
+    let mut entries: Vec<(String, &PathBuf)> =
+        files.iter().map(|file| (entry_name(file), file)).collect();
+    entries.sort();
+    entries.dedup_by(|a, b| a.0 == b.0);
+
+    write_archive_file(archive_path, mtime, |writer| {
+        let mut builder = Builder::new(writer);
+        for (name, file) in &entries {
+            match replaced.get(*file) {
+                Some(data) => append_entry(&mut builder, name, data, mtime)?,
+                None => {
+                    let path = dir.join(file);
+                    let data = fs::read(&path)
+                        .with_context(|| format!("Failed to read file: {}", path.display()))?;
+                    append_entry(&mut builder, name, &data, mtime)?;
+                }
+            }
+        }
+        builder.finish().context("Failed to finish archive")
+    })?;
+
+    Ok(archive_path.to_path_buf())
+}
+
+/// Creates an archive file and hands `write` the stream to write the tar archive
+/// to, gzipped with a fixed timestamp if the name ends in `.gz` or `.tgz`.
+///
+/// # Arguments
+///
+/// * `archive_path` - Path of the archive to write
+/// * `mtime` - Timestamp of the gzip header, e.g. from [`archive_mtime`]
+/// * `write` - Writes the tar archive
+///
+/// # Returns
+///
+/// * `Result<T>` - What `write` returned
+///
+/// # Errors
+///
+/// Returns an error for a `.zip` archive, which is not supported, if the archive
+/// cannot be written, or if `write` fails
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use depbank::write_archive_file;
+/// use std::io::Write;
+/// use std::path::Path;
+///
+/// write_archive_file(Path::new("empty.tar.gz"), 0, |writer| {
+///     writer.write_all(&[0; 1024])?;
+///     Ok(())
+/// })
+/// .unwrap();
+/// ```
+pub fn write_archive_file<T>(
+    archive_path: &Path,
+    mtime: u32,
+    write: impl FnOnce(&mut dyn Write) -> Result<T>,
+) -> Result<T> {
+    let name = archive_path.to_string_lossy();
+    if name.ends_with(".zip") {
+        return Err(anyhow::anyhow!(
+            "Zip archives are not supported, use a .tar or .tar.gz archive: {}",
+            archive_path.display()
+        ));
+    }
+
+    let file = File::create(archive_path)
+        .with_context(|| format!("Failed to create archive: {}", archive_path.display()))?;
+    let mut writer = BufWriter::new(file);
+    let result = if name.ends_with(".gz") || name.ends_with(".tgz") {
+        // A fixed gzip timestamp too, the default would be the current time
+        let mut encoder = GzBuilder::new()
+            .mtime(mtime)
+            .write(&mut writer, Compression::default());
+        let result = write(&mut encoder)?;
+        encoder.finish().context("Failed to finish gzip stream")?;
+        result
+    } else {
+        write(&mut writer)?
+    };
+    writer
+        .flush()
+        .with_context(|| format!("Failed to write archive: {}", archive_path.display()))?;
+
+    Ok(result)
+}
+
+/// Appends a regular file entry owned by root with mode `0644`, so that the archive
+/// only depends on the entries' paths and content
+fn append_entry<W: Write>(
+    builder: &mut Builder<W>,
+    path: &str,
+    data: &[u8],
+    mtime: u32,
+) -> Result<()> {
+    let mut header = Header::new_ustar();
+    header.set_entry_type(EntryType::Regular);
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    header.set_uid(0);
+    header.set_gid(0);
+    header.set_mtime(u64::from(mtime));
+    builder
+        .append_data(&mut header, path, data)
+        .with_context(|| format!("Failed to write archive entry: {}", path))
+}
+
+/// The outcome of streaming code banks into an archive, see
+/// [`generate_all_to_archive`]
+#[derive(Debug, Clone, Default)]
+pub struct ArchiveOutcome {
+    /// The names of the dependencies whose bank was archived, sorted
+    pub archived: Vec<String>,
+    /// The names of the dependencies whose bank could not be generated, sorted
+    pub failed: Vec<String>,
+}
+
+/// Generates the code banks of dependencies straight into a tar archive, without
+/// writing any file to disk.
+///
+/// Each bank is generated in memory and appended as a `<name>.md` entry, one at a
+/// time, so only one bank is held at once. Entries are sorted by name and dated
+/// [`archive_mtime`], as in [`write_archive`]. A dependency listed at several
+/// versions is archived once, at the highest version, see
+/// [`resolve_version_conflicts`]. The tar stream is written as is; see
+/// [`write_archive_file`] for a gzipped archive file. Dependencies that are not in
+/// the registry, or whose bank fails to generate, are reported as warnings and left
+/// out.
+///
+/// # Arguments
+///
+/// * `dependencies` - The dependencies to generate code banks for
+/// * `registry_path` - Path to the cargo registry holding their sources
+/// * `writer` - The destination of the tar archive
+/// * `options` - Options controlling the generation
+///
+/// # Returns
+///
+/// * `Result<ArchiveOutcome>` - The dependencies that were archived and those that
+///   failed
+///
+/// # Errors
+///
+/// Returns an error if writing to `writer` fails
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use depbank::{
+///     Dependency, DependencyCollection, GenerateOptions, generate_all_to_archive,
+///     resolve_registry_path,
+/// };
+///
+/// let mut dependencies = DependencyCollection::new();
+/// dependencies.add(Dependency::new("serde", "1.0.197"));
+/// let mut archive = Vec::new();
+/// let outcome = generate_all_to_archive(
+///     &dependencies,
+///     &resolve_registry_path().unwrap(),
+///     &mut archive,
+///     &GenerateOptions::default(),
+/// )
+/// .unwrap();
+/// println!(
+///     "Archived {} banks in {} bytes",
+///     outcome.archived.len(),
+///     archive.len()
+/// );
+/// ```
+pub fn generate_all_to_archive(
+    dependencies: &DependencyCollection,
+    registry_path: &Path,
+    writer: &mut dyn Write,
+    options: &GenerateOptions,
+) -> Result<ArchiveOutcome> {
+    // Entries are named after the dependencies, so only one version of each fits
+    let dependencies = resolve_version_conflicts(dependencies, VersionConflictPolicy::Highest)?;
+    let mut sorted: Vec<_> = dependencies.iter().collect();
+    sorted.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mtime = archive_mtime()?;
+    let mut tar = Builder::new(writer);
+    let mut outcome = ArchiveOutcome::default();
+    for dependency in sorted {
+        let dependency_path = dependency.get_registry_path(registry_path);
+        let mut content = Vec::new();
+        let result = if dependency_path.is_dir() {
+            generate_code_bank_to_writer(&dependency_path, &dependency.name, options, &mut content)
+        } else {
+            Err(anyhow::anyhow!(
+                "Dependency not found: {}",
+                dependency_path.display()
+            ))
+        };
+        match result {
+            Ok(()) => {
+                append_entry(
+                    &mut tar,
+                    &format!("{}.md", dependency.name),
+                    &content,
+                    mtime,
+                )?;
+                outcome.archived.push(dependency.name.clone());
+            }
+            Err(e) => {
+                emit_warning(Warning::new(
+                    WarningKind::GenerationFailed,
+                    Some(&dependency.name),
+                    format!(
+                        "Failed to generate code bank for {}: {}",
+                        dependency.name, e
+                    ),
+                ));
+                outcome.failed.push(dependency.name.clone());
+            }
+        }
+    }
+    tar.finish().context("Failed to finish archive")?;
+
+    Ok(outcome)
+}
+
+/// The name of a file in the archive, with `/` separators on every platform
+fn entry_name(path: &Path) -> String {
+    path.components()
+        .filter_map(|component| match component {
+            Component::Normal(part) => Some(part.to_string_lossy()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+    use tar::Archive;
+    use tempfile::tempdir;
+
+    /// The paths, modification times and contents of the entries of a tar archive
+    fn read_entries(tar: &[u8]) -> Result<Vec<(String, u64, String)>> {
+        let mut entries = Vec::new();
+        for entry in Archive::new(tar).entries()? {
+            let mut entry = entry?;
+            let path = entry.path()?.to_string_lossy().to_string();
+            let mtime = entry.header().mtime()?;
+            let mut content = String::new();
+            entry.read_to_string(&mut content)?;
+            entries.push((path, mtime, content));
+        }
+        Ok(entries)
+    }
+
+    #[test]
+    fn test_write_archive_is_reproducible() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let dir = temp_dir.path().join("banks");
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join("serde.md"), "# serde\n")?;
+        fs::write(dir.join("anyhow.md"), "# anyhow\n".repeat(100))?;
+        fs::write(dir.join("README.md"), "# Code Bank Summary\n")?;
+        fs::write(dir.join("index.json"), "{\"total_generation_secs\": 1.5}")?;
+        let files = vec![
+            PathBuf::from("serde.md"),
+            PathBuf::from("README.md"),
+            PathBuf::from("index.json"),
+            PathBuf::from("anyhow.md"),
+        ];
+        let mut reversed = files.clone();
+        reversed.reverse();
+        let replaced = BTreeMap::from([(PathBuf::from("index.json"), b"{}".to_vec())]);
+
+        for name in ["banks.tar", "banks.tar.gz"] {
+            let first = write_archive(
+                &dir,
+                &files,
+                &replaced,
+                &temp_dir.path().join(name),
+                1_700_000_000,
+            )?;
+            let first = fs::read(first)?;
+
+            // Rewritten files and another listing order give the same bytes
+            fs::write(dir.join("serde.md"), "# serde\n")?;
+            let second = temp_dir.path().join(format!("second-{}", name));
+            write_archive(&dir, &reversed, &replaced, &second, 1_700_000_000)?;
+            assert_eq!(first, fs::read(second)?);
+        }
+
+        // Entries are sorted and dated, and the gzipped archive holds the same tar
+        let tar = fs::read(temp_dir.path().join("banks.tar"))?;
+        let entries = read_entries(&tar)?;
+        let names: Vec<&str> = entries.iter().map(|(name, _, _)| name.as_str()).collect();
+        assert_eq!(names, ["README.md", "anyhow.md", "index.json", "serde.md"]);
+        assert!(entries.iter().all(|(_, mtime, _)| *mtime == 1_700_000_000));
+        assert_eq!(entries[2].2, "{}");
+        let mut unzipped = Vec::new();
+        GzDecoder::new(fs::read(temp_dir.path().join("banks.tar.gz"))?.as_slice())
+            .read_to_end(&mut unzipped)?;
+        assert_eq!(unzipped, tar);
+
+        assert!(
+            write_archive(
+                &dir,
+                &files,
+                &BTreeMap::new(),
+                &temp_dir.path().join("banks.zip"),
+                0
+            )
+            .is_err()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_source_date_epoch() -> Result<()> {
+        assert_eq!(parse_source_date_epoch("1700000000")?, 1_700_000_000);
+        assert_eq!(parse_source_date_epoch(" 42\n")?, 42);
+        assert_eq!(parse_source_date_epoch("yesterday")?, 0);
+        assert_eq!(parse_source_date_epoch("4294967295")?, u32::MAX);
+        assert!(parse_source_date_epoch("4294967296").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_generate_all_to_archive() -> Result<()> {
+        use crate::Dependency;
+
+        let temp_dir = tempdir()?;
+        let registry = temp_dir.path();
+        for name in ["beta", "alpha"] {
+            let crate_dir = registry.join(format!("{}-0.1.0", name));
+            fs::create_dir_all(crate_dir.join("src"))?;
+            fs::write(
+                crate_dir.join("Cargo.toml"),
+                format!("[package]\nname = \"{}\"\nversion = \"0.1.0\"\n", name),
+            )?;
+            fs::write(
+                crate_dir.join("src").join("lib.rs"),
+                format!("/// The {} item.\npub struct Item;\n", name),
+            )?;
+        }
+        let mut dependencies = DependencyCollection::new();
+        dependencies.add(Dependency::new("beta", "0.1.0"));
+        dependencies.add(Dependency::new("missing", "0.1.0"));
+        dependencies.add(Dependency::new("alpha", "0.1.0"));
+        // Only the highest version of a crate is archived, older ones are no failure
+        dependencies.add(Dependency::new("alpha", "0.0.9"));
+
+        let mut archive = Vec::new();
+        let outcome = generate_all_to_archive(
+            &dependencies,
+            registry,
+            &mut archive,
+            &GenerateOptions::default(),
+        )?;
+        assert_eq!(outcome.archived, ["alpha", "beta"]);
+        assert_eq!(outcome.failed, ["missing"]);
+
+        let entries = read_entries(&archive)?;
+        let names: Vec<&str> = entries.iter().map(|(name, _, _)| name.as_str()).collect();
+        assert_eq!(names, ["alpha.md", "beta.md"]);
+        assert!(entries[0].2.contains("The alpha item."));
+        assert!(entries[1].2.contains("pub struct Item"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,362 @@
+//! Selection of the code banks that fit a token budget.
+//!
+//! Rather than failing when the banks exceed a budget, generation can be limited to
+//! a subset of the dependencies: each bank is estimated in memory first, then banks
+//! are picked greedily, direct dependencies before transitive ones, until the budget
+//! is used up. A priority list of crates decides which banks are picked first, and
+//! also orders generation in general.
+
+use crate::{
+    Dependency, DependencyCollection, GenerateOptions, TokenOptions, Warning, WarningKind,
+    emit_warning, estimate_generation_tokens, normalize_crate_name, parallel,
+};
+use anyhow::{Context, Result};
+use rayon::prelude::*;
+use std::fs;
+use std::path::Path;
+
+/// The estimated tokens of a dependency's code bank
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenEstimate {
+    /// The dependency
+    pub dependency: Dependency,
+    /// Tokens its code bank is expected to have
+    pub tokens: usize,
+    /// Whether the project declares the dependency itself, rather than pulling it in
+    /// through another dependency
+    pub direct: bool,
+}
+
+/// The order in which dependencies are considered for a budget.
+///
+/// Direct dependencies always come before transitive ones. Within each group, the
+/// listed `crates` come first in the order given, followed by the others, smallest
+/// first with `smallest_first` and in their original order otherwise.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BudgetPriority {
+    /// Consider the smallest banks first, fitting as many banks as possible
+    pub smallest_first: bool,
+    /// Crates to consider before all others of their group
+    pub crates: Vec<String>,
+}
+
+/// Estimates the tokens of the code banks of dependencies by generating them in memory.
+///
+/// The banks are generated in parallel on the shared thread pool. Dependencies whose
+/// banks cannot be generated are skipped with a warning.
+///
+/// # Arguments
+///
+/// * `dependencies` - The dependencies to estimate
+/// * `registry_path` - Path to the cargo registry directory
+/// * `options` - Options controlling the generation
+/// * `token_options` - Options controlling how tokens are counted
+///
+/// # Returns
+///
+/// * `Result<Vec<(Dependency, usize)>>` - The tokens of each bank, in the order of
+///   `dependencies`
+///
+/// # Errors
+///
+/// Returns an error if the thread pool cannot be created
+pub fn estimate_dependency_tokens(
+    dependencies: &DependencyCollection,
+    registry_path: &Path,
+    options: &GenerateOptions,
+    token_options: &TokenOptions,
+) -> Result<Vec<(Dependency, usize)>> {
+    let results: Vec<Option<(Dependency, usize)>> = parallel::install(|| {
+        dependencies
+            .as_slice()
+            .par_iter()
+            .map(|dependency| {
+                let source_path = dependency.get_registry_path(registry_path);
+                match estimate_generation_tokens(
+                    &source_path,
+                    &dependency.name,
+                    options,
+                    token_options,
+                ) {
+                    Ok(tokens) => Some((dependency.clone(), tokens)),
+                    Err(e) => {
+                        emit_warning(Warning::new(
+                            WarningKind::GenerationFailed,
+                            Some(&dependency.name),
+                            format!("{:#}", e),
+                        ));
+                        None
+                    }
+                }
+            })
+            .collect()
+    })?;
+
+    Ok(results.into_iter().flatten().collect())
+}
+
+/// Selects the dependencies whose code banks fit a token budget.
+///
+/// Dependencies are considered in the order of `priority` and each one is selected
+/// if its bank still fits in what is left of the budget, so a large bank that does
+/// not fit does not prevent smaller ones after it from being selected.
+///
+/// # Arguments
+///
+/// * `estimates` - The estimated tokens of each dependency's bank
+/// * `budget` - The maximum total tokens of the selected banks
+/// * `priority` - The order in which dependencies are considered
+///
+/// # Returns
+///
+/// * `Vec<Dependency>` - The selected dependencies, in the order they were selected
+///
+/// # Examples
+///
+/// ```rust
+/// use depbank::{BudgetPriority, Dependency, TokenEstimate, select_within_budget};
+///
+/// let estimates = vec![
+///     TokenEstimate { dependency: Dependency::new("serde", "1.0.219"), tokens: 800, direct: true },
+///     TokenEstimate { dependency: Dependency::new("itoa", "1.0.15"), tokens: 300, direct: false },
+/// ];
+/// let selected = select_within_budget(&estimates, 1000, &BudgetPriority::default());
+/// assert_eq!(selected, vec![Dependency::new("serde", "1.0.219")]);
+/// ```
+pub fn select_within_budget(
+    estimates: &[TokenEstimate],
+    budget: usize,
+    priority: &BudgetPriority,
+) -> Vec<Dependency> {
+    let listed_position = |estimate: &TokenEstimate| {
+        priority
+            .crates
+            .iter()
+            .position(|name| *name == estimate.dependency.name)
+    };
+
+    let mut ordered: Vec<&TokenEstimate> = estimates.iter().collect();
+    // The sort is stable, so ties keep their original order
+    ordered.sort_by_key(|estimate| {
+        (
+            !estimate.direct,
+            listed_position(estimate).unwrap_or(usize::MAX),
+            if priority.smallest_first {
+                estimate.tokens
+            } else {
+                0
+            },
+        )
+    });
+
+    let mut remaining = budget;
+    let mut selected = Vec::new();
+    for estimate in ordered {
+        if estimate.tokens <= remaining {
+            remaining -= estimate.tokens;
+            selected.push(estimate.dependency.clone());
+        }
+    }
+    selected
+}
+
+/// Reads a priority list of crate names from a file.
+///
+/// The file lists one crate name per line, most important first. Blank lines and
+/// lines starting with `#` are ignored.
+///
+/// # Arguments
+///
+/// * `path` - Path to the priority file
+///
+/// # Returns
+///
+/// * `Result<Vec<String>>` - The crate names, in priority order
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use depbank::read_priority_file;
+/// use std::path::Path;
+///
+/// let priority = read_priority_file(Path::new("priority.txt")).unwrap();
+/// println!("{} crates listed", priority.len());
+/// ```
+pub fn read_priority_file(path: &Path) -> Result<Vec<String>> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read priority file: {}", path.display()))?;
+    Ok(content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect())
+}
+
+/// Orders dependencies by a priority list of crate names.
+///
+/// The listed crates come first, in the order of the list, followed by all others
+/// alphabetically. Names are compared after normalization, so `serde-json` matches
+/// `serde_json`. Several versions of a crate are ordered by version.
+///
+/// # Arguments
+///
+/// * `dependencies` - The dependencies to order
+/// * `priority` - Crate names, most important first
+///
+/// # Returns
+///
+/// * `Vec<Dependency>` - The dependencies in priority order
+///
+/// # Examples
+///
+/// ```rust
+/// use depbank::{Dependency, DependencyCollection, order_by_priority};
+///
+/// let mut dependencies = DependencyCollection::new();
+/// for name in ["anyhow", "tokio", "serde", "clap"] {
+///     dependencies.add(Dependency::new(name, "1.0.0"));
+/// }
+/// let ordered = order_by_priority(&dependencies, &["tokio".to_string(), "serde".to_string()]);
+/// let names: Vec<&str> = ordered.iter().map(|dep| dep.name.as_str()).collect();
+/// assert_eq!(names, vec!["tokio", "serde", "anyhow", "clap"]);
+/// ```
+pub fn order_by_priority(
+    dependencies: &DependencyCollection,
+    priority: &[String],
+) -> Vec<Dependency> {
+    let priority: Vec<String> = priority
+        .iter()
+        .map(|name| normalize_crate_name(name))
+        .collect();
+    let mut ordered: Vec<Dependency> = dependencies.iter().cloned().collect();
+    ordered.sort_by_cached_key(|dep| {
+        let name = normalize_crate_name(&dep.name);
+        (
+            priority
+                .iter()
+                .position(|listed| *listed == name)
+                .unwrap_or(usize::MAX),
+            dep.name.clone(),
+            dep.version.clone(),
+        )
+    });
+    ordered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn estimate(name: &str, tokens: usize, direct: bool) -> TokenEstimate {
+        TokenEstimate {
+            dependency: Dependency::new(name, "1.0.0"),
+            tokens,
+            direct,
+        }
+    }
+
+    fn names(selected: &[Dependency]) -> Vec<&str> {
+        selected.iter().map(|dep| dep.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_order_by_priority() {
+        let mut dependencies = DependencyCollection::new();
+        for (name, version) in [
+            ("tokio", "1.44.2"),
+            ("anyhow", "1.0.98"),
+            ("serde_json", "1.0.140"),
+            ("rand", "0.8.5"),
+            ("clap", "4.5.37"),
+            ("rand", "0.7.3"),
+        ] {
+            dependencies.add(Dependency::new(name, version));
+        }
+
+        let priority = vec![
+            "serde-json".to_string(),
+            "rand".to_string(),
+            "tokio".to_string(),
+            "not-a-dependency".to_string(),
+        ];
+        let ordered = order_by_priority(&dependencies, &priority);
+        assert_eq!(
+            names(&ordered),
+            vec!["serde_json", "rand", "rand", "tokio", "anyhow", "clap"]
+        );
+        assert_eq!(ordered[1].version, "0.7.3");
+
+        // Without a priority list, dependencies are ordered alphabetically
+        let ordered = order_by_priority(&dependencies, &[]);
+        assert_eq!(
+            names(&ordered),
+            vec!["anyhow", "clap", "rand", "rand", "serde_json", "tokio"]
+        );
+    }
+
+    #[test]
+    fn test_read_priority_file() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let path = temp_dir.path().join("priority.txt");
+        fs::write(&path, "# Most important first\ntokio\n\n  serde  \n")?;
+
+        assert_eq!(read_priority_file(&path)?, vec!["tokio", "serde"]);
+        assert!(read_priority_file(&temp_dir.path().join("missing.txt")).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_select_within_budget() {
+        let estimates = vec![
+            estimate("regex-syntax", 400, false),
+            estimate("tokio", 700, true),
+            estimate("serde", 300, true),
+            estimate("anyhow", 100, true),
+            estimate("itoa", 50, false),
+        ];
+        let total = |selected: &[Dependency]| -> usize {
+            selected
+                .iter()
+                .map(|dep| {
+                    estimates
+                        .iter()
+                        .find(|estimate| estimate.dependency == *dep)
+                        .unwrap()
+                        .tokens
+                })
+                .sum()
+        };
+
+        // Direct dependencies first, skipping those that no longer fit
+        let selected = select_within_budget(&estimates, 1050, &BudgetPriority::default());
+        assert_eq!(names(&selected), vec!["tokio", "serde", "itoa"]);
+        assert!(total(&selected) <= 1050);
+
+        let smallest_first = BudgetPriority {
+            smallest_first: true,
+            ..Default::default()
+        };
+        let selected = select_within_budget(&estimates, 1050, &smallest_first);
+        assert_eq!(
+            names(&selected),
+            vec!["anyhow", "serde", "itoa", "regex-syntax"]
+        );
+        assert!(total(&selected) <= 1050);
+
+        let listed = BudgetPriority {
+            crates: vec!["anyhow".to_string(), "regex-syntax".to_string()],
+            ..Default::default()
+        };
+        let selected = select_within_budget(&estimates, 900, &listed);
+        assert_eq!(names(&selected), vec!["anyhow", "tokio", "itoa"]);
+        assert!(total(&selected) <= 900);
+
+        assert!(select_within_budget(&estimates, 0, &BudgetPriority::default()).is_empty());
+    }
+}
@@ -1,5 +1,13 @@
-use clap::{Parser, Subcommand};
+use anyhow::Result;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use depbank::{
+    DEFAULT_BANK_IGNORED_DIRS, DEFAULT_MIN_DOC_TOKENS, DEFAULT_TOKENIZER_MODEL, DiscoveryOptions,
+    FeatureSelection, GenerateOptions, IGNORE_DIRS_ENV_VAR, ItemKinds, ResolveOptions,
+    TransitiveOptions, UnresolvableVersionPolicy, VersionConflictPolicy, WatchOptions,
+    parse_ignore_dirs, read_aliases_file,
+};
 use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
 #[derive(Parser)]
 #[command(
@@ -8,26 +16,246 @@ use std::path::PathBuf;
     version
 )]
 pub struct Cli {
+    /// Number of parallel jobs for generation and tokenization [default: number of logical CPUs]
+    #[arg(short, long, global = true)]
+    pub jobs: Option<usize>,
+
+    /// How warnings are written to stderr
+    #[arg(long, value_enum, global = true, default_value_t = WarningsFormat::Text)]
+    pub warnings_format: WarningsFormat,
+
+    /// When to color the output; NO_COLOR disables automatic color
+    #[arg(long, value_enum, global = true, default_value_t = ColorChoice::Auto)]
+    pub color: ColorChoice,
+
     #[command(subcommand)]
     pub command: Commands,
 }
 
+/// Output format for commands producing structured results
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable text
+    #[default]
+    Text,
+    /// JSON document
+    Json,
+}
+
+/// Output formats of the `list` command
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum ListFormat {
+    /// Human-readable text
+    #[default]
+    Text,
+    /// JSON document
+    Json,
+    /// One JSON object per dependency and line, written as each is listed
+    Jsonl,
+    /// CSV with name, version, resolved_version, available, kind and license columns
+    Csv,
+}
+
+/// How the `list` command groups dependencies
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ListGroupBy {
+    /// By where they come from: crates.io, another registry, git or a local path
+    Source,
+}
+
+/// Formats of Software Bills of Materials
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum SbomFormat {
+    /// CycloneDX 1.5 JSON
+    #[default]
+    Cyclonedx,
+    /// SPDX 2.3 JSON
+    Spdx,
+}
+
+/// Bundles of generation settings for common use cases
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Preset {
+    /// Public signatures only, without doc(hidden) and pub(crate) items
+    Minimal,
+    /// Public signatures only (the default settings)
+    Balanced,
+    /// All items with their implementations
+    Full,
+}
+
+impl From<Preset> for depbank::Preset {
+    fn from(preset: Preset) -> Self {
+        match preset {
+            Preset::Minimal => depbank::Preset::Minimal,
+            Preset::Balanced => depbank::Preset::Balanced,
+            Preset::Full => depbank::Preset::Full,
+        }
+    }
+}
+
+/// How much of each dependency's source code goes into its code bank
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Strategy {
+    /// Public items only, with function bodies omitted
+    Summary,
+    /// All items with their implementations, except tests
+    NoTests,
+    /// All items with their implementations
+    Full,
+}
+
+impl From<Strategy> for depbank::Strategy {
+    fn from(strategy: Strategy) -> Self {
+        match strategy {
+            Strategy::Summary => depbank::Strategy::Summary,
+            Strategy::NoTests => depbank::Strategy::NoTests,
+            Strategy::Full => depbank::Strategy::Full,
+        }
+    }
+}
+
+/// Line endings for generated code banks
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum LineEndings {
+    /// Unix line endings (\n)
+    #[default]
+    Lf,
+    /// Windows line endings (\r\n)
+    Crlf,
+    /// The line endings of the current platform
+    Native,
+}
+
+impl From<LineEndings> for depbank::LineEndings {
+    fn from(line_endings: LineEndings) -> Self {
+        match line_endings {
+            LineEndings::Lf => depbank::LineEndings::Lf,
+            LineEndings::Crlf => depbank::LineEndings::Crlf,
+            LineEndings::Native => depbank::LineEndings::Native,
+        }
+    }
+}
+
+/// How to pick the version of a crate used at several versions
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum OnVersionConflict {
+    /// Keep the highest version, with a warning
+    #[default]
+    Highest,
+    /// Keep the lowest version, with a warning
+    Lowest,
+    /// Fail
+    Error,
+}
+
+impl From<OnVersionConflict> for VersionConflictPolicy {
+    fn from(policy: OnVersionConflict) -> Self {
+        match policy {
+            OnVersionConflict::Highest => VersionConflictPolicy::Highest,
+            OnVersionConflict::Lowest => VersionConflictPolicy::Lowest,
+            OnVersionConflict::Error => VersionConflictPolicy::Error,
+        }
+    }
+}
+
+/// How to handle dependencies declared without a version that Cargo.lock does not list
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum OnUnresolvable {
+    /// Skip them, with a warning
+    #[default]
+    Skip,
+    /// Use the highest version extracted in the registry, with a warning
+    BestAvailable,
+    /// Fail
+    Error,
+}
+
+impl From<OnUnresolvable> for UnresolvableVersionPolicy {
+    fn from(policy: OnUnresolvable) -> Self {
+        match policy {
+            OnUnresolvable::Skip => UnresolvableVersionPolicy::Skip,
+            OnUnresolvable::BestAvailable => UnresolvableVersionPolicy::BestAvailable,
+            OnUnresolvable::Error => UnresolvableVersionPolicy::Error,
+        }
+    }
+}
+
+/// Kinds of items that can be kept in or removed from generated code banks
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ItemKind {
+    /// Structs and unions
+    Structs,
+    /// Enums
+    Enums,
+    /// Traits
+    Traits,
+    /// Functions and methods
+    Functions,
+    /// macro_rules! macros
+    Macros,
+}
+
+impl From<ItemKind> for ItemKinds {
+    fn from(kind: ItemKind) -> Self {
+        match kind {
+            ItemKind::Structs => ItemKinds::STRUCTS,
+            ItemKind::Enums => ItemKinds::ENUMS,
+            ItemKind::Traits => ItemKinds::TRAITS,
+            ItemKind::Functions => ItemKinds::FUNCTIONS,
+            ItemKind::Macros => ItemKinds::MACROS,
+        }
+    }
+}
+
+/// When to color the output
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    /// Only when writing to a terminal, and NO_COLOR is not set
+    #[default]
+    Auto,
+    /// Always, even when piping
+    Always,
+    /// Never
+    Never,
+}
+
+impl From<ColorChoice> for depbank::ColorChoice {
+    fn from(choice: ColorChoice) -> Self {
+        match choice {
+            ColorChoice::Auto => depbank::ColorChoice::Auto,
+            ColorChoice::Always => depbank::ColorChoice::Always,
+            ColorChoice::Never => depbank::ColorChoice::Never,
+        }
+    }
+}
+
+/// Format of the warnings written to stderr
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum WarningsFormat {
+    /// `Warning: <message>` lines
+    #[default]
+    Text,
+    /// One JSON object per line, with `kind`, `dependency` and `message` fields
+    Json,
+}
+
+impl From<WarningsFormat> for depbank::WarningsFormat {
+    fn from(format: WarningsFormat) -> Self {
+        match format {
+            WarningsFormat::Text => depbank::WarningsFormat::Text,
+            WarningsFormat::Json => depbank::WarningsFormat::Json,
+        }
+    }
+}
+
 #[derive(Subcommand)]
 pub enum Commands {
     /// Generate code banks for dependencies in a Rust project
-    Generate {
-        /// Path to the project root directory
-        #[arg(short, long, default_value = ".")]
-        path: PathBuf,
-
-        /// Output directory for generated code banks
-        #[arg(short, long, default_value = ".codebank")]
-        output: PathBuf,
+    Generate(Box<GenerateArgs>),
 
-        /// Only calculate tokens without generating code banks
-        #[arg(short, long)]
-        dry_run: bool,
-    },
+    /// Watch a project and regenerate its code banks when its manifests or lockfile change
+    Watch(WatchArgs),
 
     /// Calculate tokens for files or directories
     Tokens {
@@ -38,6 +266,44 @@ pub enum Commands {
         /// Filter by file extension (e.g., "md")
         #[arg(short, long)]
         extension: Option<String>,
+
+        /// Also estimate the total tokens after deduplicating repeated lines across files
+        #[arg(long)]
+        dedup: bool,
+
+        /// Include the special tokens (e.g. BOS/EOS) the tokenizer adds in the counts
+        #[arg(long)]
+        with_special_tokens: bool,
+
+        /// Pretrained model name or path to a tokenizer.json file to count tokens with
+        #[arg(long, value_name = "NAME|PATH", default_value = DEFAULT_TOKENIZER_MODEL)]
+        tokenizer: String,
+
+        /// Truncate each file at this many tokens, to check what fits a model's context
+        #[arg(long, value_name = "N")]
+        max_length: Option<usize>,
+
+        /// Tokenize every file of a directory, rather than reusing the counts cached in
+        /// the .tokens-cache.json of depbank output directories for unchanged files
+        #[arg(long)]
+        no_token_cache: bool,
+
+        /// Also count the files of a directory's subdirectories, down to N levels below it
+        #[arg(long, value_name = "N", default_value_t = 0)]
+        depth: usize,
+
+        /// Print an estimated cost at this price, in dollars per 1K tokens
+        #[arg(long, value_name = "DOLLARS_PER_1K", conflicts_with = "model")]
+        price: Option<f64>,
+
+        /// Print an estimated cost at the default price of a known model (e.g. gpt-4o)
+        #[arg(long, value_name = "NAME")]
+        model: Option<String>,
+
+        /// Print the analyzed path relative to the current directory, rather than as
+        /// given, so the output holds no machine-specific paths
+        #[arg(long)]
+        relative_paths: bool,
     },
 
     /// List dependencies in a Rust project
@@ -49,5 +315,811 @@ pub enum Commands {
         /// Show detailed information including versions
         #[arg(short, long)]
         detailed: bool,
+
+        /// Path to the Cargo.lock to use instead of discovering it, or - to read it from stdin
+        #[arg(long)]
+        lock: Option<PathBuf>,
+
+        /// List every package locked in the Cargo.lock, including transitive
+        /// dependencies, instead of the dependencies declared in Cargo.toml files
+        #[arg(long, conflicts_with_all = ["detailed", "check_yanked"])]
+        from_lock: bool,
+
+        /// Print the dependency tree recorded in Cargo.lock, rooted at the dependencies
+        /// declared in Cargo.toml files, like `cargo tree`
+        #[arg(
+            long,
+            conflicts_with_all = ["detailed", "from_lock", "check_yanked", "min_version", "max_version", "format"]
+        )]
+        tree: bool,
+
+        /// Mark resolved versions that have been yanked from crates.io (needs network)
+        #[arg(long)]
+        check_yanked: bool,
+
+        /// Section the dependencies by where they come from, with counts per section
+        #[arg(
+            long,
+            value_enum,
+            conflicts_with_all = ["detailed", "from_lock", "tree", "check_yanked", "format"]
+        )]
+        group_by: Option<ListGroupBy>,
+
+        /// Only list dependencies resolved to this version or newer, e.g. 1.0.0
+        #[arg(long, value_name = "VERSION")]
+        min_version: Option<semver::Version>,
+
+        /// Only list dependencies resolved to this version or older, e.g. 1.0.0
+        #[arg(long, value_name = "VERSION")]
+        max_version: Option<semver::Version>,
+
+        #[command(flatten)]
+        discovery: DiscoveryArgs,
+
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = ListFormat::Text)]
+        format: ListFormat,
+    },
+
+    /// Inspect the crates extracted in the local cargo registry
+    Registry {
+        #[command(subcommand)]
+        command: RegistryCommands,
+    },
+
+    /// Check that the declared dependencies are all locked in Cargo.lock with matching versions
+    Check {
+        /// Path to the project root directory
+        #[arg(short, long, default_value = ".")]
+        path: PathBuf,
+
+        /// Path to the Cargo.lock to use instead of discovering it, or - to read it from stdin
+        #[arg(long)]
+        lock: Option<PathBuf>,
+
+        #[command(flatten)]
+        discovery: DiscoveryArgs,
+
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
     },
+
+    /// Compare the tokens of the code banks generated with two strategies, in memory
+    CompareStrategies {
+        /// Path to the project root directory
+        #[arg(short, long, default_value = ".")]
+        path: PathBuf,
+
+        /// Path to the Cargo.lock to use instead of discovering it, or - to read it from stdin
+        #[arg(long)]
+        lock: Option<PathBuf>,
+
+        /// The strategy to compare against
+        #[arg(long, value_enum, default_value_t = Strategy::Summary)]
+        baseline: Strategy,
+
+        /// The strategy being considered
+        #[arg(long, value_enum, default_value_t = Strategy::Full)]
+        candidate: Strategy,
+
+        #[command(flatten)]
+        discovery: DiscoveryArgs,
+
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+
+    /// Print a hash of the resolved dependencies and generation options, to key caches of
+    /// generated code banks on; accepts the same options as generate
+    Fingerprint(Box<GenerateArgs>),
+
+    /// Write a Software Bill of Materials of the resolved dependencies, with their licenses
+    Sbom {
+        /// Path to the project root directory
+        #[arg(short, long, default_value = ".")]
+        path: PathBuf,
+
+        /// Path to the Cargo.lock to use instead of discovering it, or - to read it from stdin
+        #[arg(long)]
+        lock: Option<PathBuf>,
+
+        /// Also list the dependencies of dependencies, as locked in Cargo.lock
+        #[arg(long)]
+        transitive: bool,
+
+        /// Follow dev-dependency edges of dependencies too when collecting transitive
+        /// dependencies
+        #[arg(long, requires = "transitive")]
+        include_dev_transitive: bool,
+
+        #[command(flatten)]
+        discovery: DiscoveryArgs,
+
+        /// SBOM format
+        #[arg(short, long, value_enum, default_value_t = SbomFormat::Cyclonedx)]
+        format: SbomFormat,
+
+        /// Write the SBOM to this file instead of stdout
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+
+    /// Print the metadata of the resolved dependencies as JSON, such as their license,
+    /// edition, features and build script, without generating code banks
+    Meta {
+        /// Path to the project root directory
+        #[arg(short, long, default_value = ".")]
+        path: PathBuf,
+
+        /// Path to the Cargo.lock to use instead of discovering it, or - to read it from stdin
+        #[arg(long)]
+        lock: Option<PathBuf>,
+
+        /// Also list the dependencies of dependencies, as locked in Cargo.lock
+        #[arg(long)]
+        transitive: bool,
+
+        /// Follow dev-dependency edges of dependencies too when collecting transitive
+        /// dependencies
+        #[arg(long, requires = "transitive")]
+        include_dev_transitive: bool,
+
+        #[command(flatten)]
+        discovery: DiscoveryArgs,
+
+        /// Write the metadata to this file instead of stdout
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+
+    /// Show dependencies added, removed, or version-bumped between two projects or revisions
+    DepsDiff {
+        /// Path to the (old) project root directory
+        #[arg(required = true)]
+        path: PathBuf,
+
+        /// Path to the new project root directory to compare against
+        #[arg(required_unless_present = "against", conflicts_with = "against")]
+        other: Option<PathBuf>,
+
+        /// Git revision of PATH to use as the old side, comparing it with the working tree
+        #[arg(long)]
+        against: Option<String>,
+
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum RegistryCommands {
+    /// Report the extracted registry crates a project does not use (never deletes anything)
+    Prune {
+        /// Path to the project root directory
+        #[arg(short, long, default_value = ".")]
+        path: PathBuf,
+
+        /// Path to the Cargo.lock to use instead of discovering it, or - to read it from stdin
+        #[arg(long)]
+        lock: Option<PathBuf>,
+
+        /// Only report the unused crates; required, since deleting is not supported
+        #[arg(long, required = true)]
+        dry_run: bool,
+
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+}
+
+/// Arguments controlling which directories are searched for Cargo.toml files
+#[derive(Args)]
+pub struct DiscoveryArgs {
+    /// Skip directories with this name when searching for Cargo.toml files; repeatable
+    #[arg(long = "ignore-dir", value_name = "DIR")]
+    pub ignore_dirs: Vec<String>,
+
+    /// Also search the directories skipped by default, such as target
+    #[arg(long)]
+    pub no_default_ignore: bool,
+
+    /// Use this Cargo.toml instead of searching for manifests; repeatable
+    #[arg(long = "manifest", value_name = "PATH")]
+    pub manifests: Vec<PathBuf>,
+
+    /// Also use the manifests of local crates reached through path dependencies,
+    /// failing on path dependency cycles
+    #[arg(long)]
+    pub follow_path_deps: bool,
+}
+
+impl DiscoveryArgs {
+    /// Builds the discovery options from the flags
+    pub fn discovery_options(&self) -> DiscoveryOptions {
+        DiscoveryOptions {
+            ignore_dirs: self.ignore_dirs.clone(),
+            no_default_ignore: self.no_default_ignore,
+            manifests: self.manifests.clone(),
+            follow_path_deps: self.follow_path_deps,
+        }
+    }
+
+    /// Builds the discovery options of the generating commands, which skip the
+    /// directories named in [`IGNORE_DIRS_ENV_VAR`] only when no `--ignore-dir` is
+    /// given. Either set is added to [`depbank::DEFAULT_IGNORED_DIRS`] rather than
+    /// replacing it
+    pub fn discovery_options_with_env(&self) -> DiscoveryOptions {
+        let mut discovery = self.discovery_options();
+        if discovery.ignore_dirs.is_empty()
+            && let Ok(value) = std::env::var(IGNORE_DIRS_ENV_VAR)
+        {
+            discovery.ignore_dirs = parse_ignore_dirs(&value);
+        }
+        discovery
+    }
+}
+
+/// Returns the directories left out of code banks when no `--bank-ignore-dir` is
+/// given: those named in [`IGNORE_DIRS_ENV_VAR`] in addition to
+/// [`DEFAULT_BANK_IGNORED_DIRS`], or `None` to use the defaults alone
+fn bank_ignore_dirs_from_env() -> Option<Vec<String>> {
+    let dirs = parse_ignore_dirs(&std::env::var(IGNORE_DIRS_ENV_VAR).ok()?);
+    (!dirs.is_empty()).then(|| {
+        DEFAULT_BANK_IGNORED_DIRS
+            .iter()
+            .map(|dir| dir.to_string())
+            .chain(dirs)
+            .collect()
+    })
+}
+
+/// Arguments for the `generate` command
+#[derive(Args)]
+pub struct GenerateArgs {
+    /// Path to the project root directory; repeat to combine the dependencies of
+    /// several projects into one output directory
+    #[arg(short = 'p', long = "path", value_name = "PATH", default_value = ".")]
+    pub paths: Vec<PathBuf>,
+
+    /// Output directory for generated code banks
+    #[arg(short, long, default_value = ".codebank")]
+    pub output: PathBuf,
+
+    /// Write the code banks of each project into <output>/<project name> instead of
+    /// combining them, generating the projects in parallel
+    #[arg(long)]
+    pub per_project_output: bool,
+
+    /// Only calculate tokens without generating code banks
+    #[arg(short, long)]
+    pub dry_run: bool,
+
+    /// Instead of generating, list the source files of this dependency by their
+    /// tokens in its sources and in its code bank
+    #[arg(long, value_name = "CRATE", conflicts_with = "per_project_output")]
+    pub explain_size: Option<String>,
+
+    /// Leave directories with this name out of each dependency's code bank, instead
+    /// of examples, tests and benches and those named in DEPBANK_IGNORE_DIRS. Can be
+    /// repeated
+    #[arg(long = "bank-ignore-dir", value_name = "DIR")]
+    pub bank_ignore_dirs: Vec<String>,
+
+    /// Before generating, report how many tokens leaving the directories ignored in
+    /// code banks out saves, per dependency
+    #[arg(long, conflicts_with = "per_project_output")]
+    pub report_savings: bool,
+
+    /// Path to the Cargo.lock to use instead of discovering it, with a single project
+    #[arg(long)]
+    pub lock: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub discovery: DiscoveryArgs,
+
+    /// Start from a bundle of settings; other flags override individual settings
+    #[arg(long, value_enum)]
+    pub preset: Option<Preset>,
+
+    /// How much of each dependency's source code to include [default: summary]
+    #[arg(long, value_enum)]
+    pub strategy: Option<Strategy>,
+
+    /// Remove #[doc(hidden)] and pub(crate)-style items from the generated banks
+    #[arg(long, overrides_with = "no_strip_hidden")]
+    pub strip_hidden: bool,
+
+    /// Keep #[doc(hidden)] and pub(crate)-style items, even with --preset minimal
+    #[arg(long, overrides_with = "strip_hidden")]
+    pub no_strip_hidden: bool,
+
+    /// Remove re-exports of std, core and alloc items from the generated banks
+    #[arg(long)]
+    pub strip_std_reexports: bool,
+
+    /// Comma-separated kinds of items to keep, e.g. structs,traits [default: all]
+    #[arg(long, value_enum, value_name = "KINDS", value_delimiter = ',')]
+    pub include_items: Vec<ItemKind>,
+
+    /// Comma-separated kinds of items to remove, e.g. macros
+    #[arg(long, value_enum, value_name = "KINDS", value_delimiter = ',')]
+    pub exclude_items: Vec<ItemKind>,
+
+    /// Only document source files at most N directories deep, e.g. 1 for src/*.rs only
+    #[arg(long, value_name = "N")]
+    pub source_depth: Option<usize>,
+
+    /// Comma-separated extensions of the source files to read, e.g. rs,toml
+    #[arg(long, value_name = "EXTS", value_delimiter = ',', default_value = "rs")]
+    pub source_extensions: Vec<String>,
+
+    /// Only write an outline of each dependency's modules and item names
+    #[arg(long)]
+    pub outline: bool,
+
+    /// Only write the doc comments of each dependency, without signatures or bodies
+    #[arg(long, conflicts_with = "outline")]
+    pub docs_only: bool,
+
+    /// Document each dependency after expanding its macros with cargo expand, which
+    /// must be installed; slow, as it compiles every dependency
+    #[arg(long)]
+    pub expand_macros: bool,
+
+    /// Tag deprecated items with a "⚠ deprecated" marker and their deprecation note
+    #[arg(long)]
+    pub mark_deprecated: bool,
+
+    /// Append each dependency's build script (build.rs), if it has one, in a section
+    /// of its own
+    #[arg(long)]
+    pub include_build_script: bool,
+
+    /// Shift every heading of the banks down by N levels, e.g. to embed them in a
+    /// larger document
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub heading_offset: usize,
+
+    /// Comma-separated list of features to document, recorded in the bank headers
+    #[arg(long, value_name = "FEATURES", value_delimiter = ',')]
+    pub features: Vec<String>,
+
+    /// Document all features, recorded in the bank headers
+    #[arg(long)]
+    pub all_features: bool,
+
+    /// Document without the default features, recorded in the bank headers
+    #[arg(long)]
+    pub no_default_features: bool,
+
+    /// Line endings used in the generated code banks
+    #[arg(long, value_enum, default_value_t = LineEndings::Lf)]
+    pub line_endings: LineEndings,
+
+    /// TOML file whose [aliases] table maps crate names to the display names titling
+    /// their code banks and index entries; files keep the crate names
+    #[arg(long, value_name = "FILE")]
+    pub aliases: Option<PathBuf>,
+
+    /// Skip writing the README.md and index.json indexes of generated code banks
+    #[arg(long)]
+    pub no_index_md: bool,
+
+    /// Write a <name>.meta.json file next to each code bank, with the dependency's name
+    /// and version and the bank's token count, size and content hash
+    #[arg(long)]
+    pub sidecar: bool,
+
+    /// Write paths relative to the output directory in the README.md and index.json
+    /// indexes, rather than absolute, so they can be shared
+    #[arg(long)]
+    pub relative_paths: bool,
+
+    /// Resume an interrupted run, skipping dependencies it already completed
+    #[arg(long)]
+    pub resume: bool,
+
+    /// Remove the files a previous run generated in the output directory first,
+    /// leaving other files intact
+    #[arg(long, conflicts_with = "resume")]
+    pub clean: bool,
+
+    /// Best-effort cap on the memory held by code banks generated in parallel, in MB
+    #[arg(long, value_name = "N")]
+    pub max_memory_mb: Option<u64>,
+
+    /// Only generate code banks for crates imported by this Rust source file
+    #[arg(long, value_name = "FILE")]
+    pub imports_of: Option<PathBuf>,
+
+    /// Also generate code banks for the dependencies of dependencies, as locked in
+    /// Cargo.lock
+    #[arg(long)]
+    pub transitive: bool,
+
+    /// Follow dev-dependency edges of dependencies too when collecting transitive
+    /// dependencies
+    #[arg(long, requires = "transitive")]
+    pub include_dev_transitive: bool,
+
+    /// Also generate code banks for the workspace's own members that other members
+    /// depend on, from their local sources
+    #[arg(long)]
+    pub include_members: bool,
+
+    /// Also generate a code bank of the project's own package, from its src directory
+    #[arg(long)]
+    pub include_self: bool,
+
+    /// Which version to document when workspace members or projects use a crate at
+    /// several versions
+    #[arg(long, value_enum, value_name = "POLICY", default_value_t = OnVersionConflict::Highest)]
+    pub on_version_conflict: OnVersionConflict,
+
+    /// How to handle dependencies declared without a version (`*` or `workspace`) that
+    /// Cargo.lock does not list
+    #[arg(long, value_enum, value_name = "POLICY", default_value_t = OnUnresolvable::Skip)]
+    pub on_unresolvable: OnUnresolvable,
+
+    /// Mark code banks of versions that have been yanked from crates.io (needs network)
+    #[arg(long)]
+    pub check_yanked: bool,
+
+    /// Append to each code bank snippets showing how the project uses the dependency
+    #[arg(long)]
+    pub with_usage: bool,
+
+    /// Gzip code banks larger than this many bytes into <name>.md.gz
+    #[arg(long, value_name = "BYTES")]
+    pub compress_over: Option<u64>,
+
+    /// Also pack the generated files into a reproducible tar archive, gzipped if the
+    /// name ends in .gz or .tgz; entries are dated SOURCE_DATE_EPOCH, or 1970
+    #[arg(long, value_name = "PATH", conflicts_with = "per_project_output")]
+    pub archive: Option<PathBuf>,
+
+    /// Stream the code banks straight into the --archive archive instead of writing
+    /// them, or any index, to the output directory
+    #[arg(
+        long,
+        requires = "archive",
+        conflicts_with_all = [
+            "include_self", "with_usage", "compress_over", "sidecar", "clean", "resume",
+            "check_yanked", "skip_over_bytes", "max_total_tokens", "timings", "deadline_secs",
+        ]
+    )]
+    pub archive_only: bool,
+
+    /// Only print the N dependencies with the most tokens in the summary
+    #[arg(long, value_name = "N")]
+    pub top: Option<usize>,
+
+    /// Print the N dependencies that took the longest to generate, and the total
+    /// generation time
+    #[arg(long, value_name = "N", num_args = 0..=1, default_missing_value = "10")]
+    pub timings: Option<usize>,
+
+    /// Report code banks with fewer than N tokens of doc comments as undocumented
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_MIN_DOC_TOKENS)]
+    pub min_doc_tokens: usize,
+
+    /// Only generate the code banks that fit in this many tokens, estimated in memory
+    /// first; direct dependencies are kept before transitive ones
+    #[arg(long, value_name = "N")]
+    pub fit_budget: Option<usize>,
+
+    /// Skip the dependencies whose sources in the registry are larger than this many
+    /// bytes, without generating their code banks
+    #[arg(long, value_name = "N")]
+    pub skip_over_bytes: Option<u64>,
+
+    /// With --fit-budget, keep the smallest code banks first to fit as many as possible
+    #[arg(long, requires = "fit_budget")]
+    pub smallest_first: bool,
+
+    /// Only generate the code banks of the dependencies listed in this file, one name
+    /// or name@version per line
+    #[arg(long, value_name = "FILE")]
+    pub deps_file: Option<PathBuf>,
+
+    /// File listing crate names, one per line, most important first: they are generated
+    /// first and kept first with --fit-budget
+    #[arg(long, value_name = "FILE")]
+    pub priority: Option<PathBuf>,
+
+    /// Only generate the code banks of the first N dependencies, after ordering them
+    /// with --priority
+    #[arg(long, value_name = "N")]
+    pub max_deps: Option<usize>,
+
+    /// Fail with exit code 4 if the generated code banks exceed this many tokens
+    #[arg(long, value_name = "N")]
+    pub max_total_tokens: Option<usize>,
+
+    /// Stop starting new code banks N seconds after the run started, then report the
+    /// completed banks and fail with exit code 5
+    #[arg(long, value_name = "N")]
+    pub deadline_secs: Option<u64>,
+
+    /// When the run started, which the deadline counts from
+    #[arg(skip = Instant::now())]
+    pub started: Instant,
+
+    /// Treat warnings as errors: exit with 2 if any dependency is missing and with 3
+    /// if any generation failed
+    #[arg(long)]
+    pub strict: bool,
+}
+
+/// Arguments for the `watch` command
+#[derive(Args)]
+pub struct WatchArgs {
+    /// Path to the project root directory
+    #[arg(short, long, default_value = ".")]
+    pub path: PathBuf,
+
+    /// Output directory for generated code banks
+    #[arg(short, long, default_value = ".codebank")]
+    pub output: PathBuf,
+
+    /// Path to the Cargo.lock to use instead of discovering it. Reading it from stdin is
+    /// not supported, since it is watched for changes
+    #[arg(long)]
+    pub lock: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub discovery: DiscoveryArgs,
+
+    /// Which version to document when workspace members use a crate at several
+    /// versions
+    #[arg(long, value_enum, value_name = "POLICY", default_value_t = OnVersionConflict::Highest)]
+    pub on_version_conflict: OnVersionConflict,
+
+    /// How to handle dependencies declared without a version (`*` or `workspace`) that
+    /// Cargo.lock does not list
+    #[arg(long, value_enum, value_name = "POLICY", default_value_t = OnUnresolvable::Skip)]
+    pub on_unresolvable: OnUnresolvable,
+
+    /// Start from a bundle of settings; other flags override individual settings
+    #[arg(long, value_enum)]
+    pub preset: Option<Preset>,
+
+    /// How much of each dependency's source code to include [default: summary]
+    #[arg(long, value_enum)]
+    pub strategy: Option<Strategy>,
+
+    /// Keep #[doc(hidden)] and pub(crate)-style items, even with --preset minimal
+    #[arg(long)]
+    pub no_strip_hidden: bool,
+
+    /// How long changes must settle before regenerating, in milliseconds
+    #[arg(long, value_name = "MS", default_value_t = 500)]
+    pub debounce_ms: u64,
+}
+
+impl WatchArgs {
+    /// Builds the watch options, applying explicit flags on top of the preset
+    pub fn watch_options(&self) -> WatchOptions {
+        let mut generate = self
+            .preset
+            .map(|preset| GenerateOptions::from_preset(preset.into()))
+            .unwrap_or_default();
+        if let Some(strategy) = self.strategy {
+            generate.strategy = strategy.into();
+        }
+        if self.no_strip_hidden {
+            generate.strip_hidden = false;
+        }
+        generate.ignore_dirs = bank_ignore_dirs_from_env();
+
+        WatchOptions {
+            output_dir: self.output.clone(),
+            lock: self.lock.clone(),
+            registry_path: None,
+            discovery: self.discovery.discovery_options_with_env(),
+            resolve: ResolveOptions {
+                on_version_conflict: Some(self.on_version_conflict.into()),
+                on_unresolvable: self.on_unresolvable.into(),
+                ..Default::default()
+            },
+            generate,
+            debounce: Duration::from_millis(self.debounce_ms),
+        }
+    }
+}
+
+impl GenerateArgs {
+    /// Builds the generation options, applying explicit flags on top of the preset
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the aliases file cannot be read
+    pub fn generate_options(&self) -> Result<GenerateOptions> {
+        let mut options = self
+            .preset
+            .map(|preset| GenerateOptions::from_preset(preset.into()))
+            .unwrap_or_default();
+
+        if let Some(strategy) = self.strategy {
+            options.strategy = strategy.into();
+        }
+        if self.strip_hidden {
+            options.strip_hidden = true;
+        }
+        if self.no_strip_hidden {
+            options.strip_hidden = false;
+        }
+        if self.strip_std_reexports {
+            options.strip_std_reexports = true;
+        }
+        if !self.include_items.is_empty() {
+            options.item_kinds = self
+                .include_items
+                .iter()
+                .copied()
+                .map(ItemKinds::from)
+                .collect();
+        }
+        for &kind in &self.exclude_items {
+            options.item_kinds.remove(kind.into());
+        }
+        options.ignore_dirs = if self.bank_ignore_dirs.is_empty() {
+            bank_ignore_dirs_from_env()
+        } else {
+            Some(self.bank_ignore_dirs.clone())
+        };
+        options.source_depth = self.source_depth;
+        options.source_extensions = self
+            .source_extensions
+            .iter()
+            .map(|extension| extension.trim_start_matches('.').to_string())
+            .collect();
+        if self.outline {
+            options.outline = true;
+        }
+        if self.docs_only {
+            options.docs_only = true;
+        }
+        if self.mark_deprecated {
+            options.mark_deprecated = true;
+        }
+        options.expand_macros = self.expand_macros;
+        options.include_build_script = self.include_build_script;
+        options.heading_offset = self.heading_offset;
+        options.features = FeatureSelection {
+            features: self.features.clone(),
+            all_features: self.all_features,
+            no_default_features: self.no_default_features,
+        };
+        options.line_endings = self.line_endings.into();
+        options.resume = self.resume;
+        options.max_memory_mb = self.max_memory_mb;
+        options.deadline = self
+            .deadline_secs
+            .map(|secs| self.started + Duration::from_secs(secs));
+        if let Some(aliases) = &self.aliases {
+            options.aliases = read_aliases_file(aliases)?;
+        }
+
+        Ok(options)
+    }
+
+    /// Builds the transitive discovery options, if transitive dependencies are wanted
+    pub fn transitive_options(&self) -> Option<TransitiveOptions> {
+        self.transitive.then_some(TransitiveOptions {
+            include_dev: self.include_dev_transitive,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generate_options(args: &[&str]) -> GenerateOptions {
+        let cli = Cli::try_parse_from(["depbank", "generate"].iter().chain(args)).unwrap();
+        match cli.command {
+            Commands::Generate(args) => args.generate_options().unwrap(),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_bank_ignore_dirs() {
+        assert_eq!(generate_options(&[]).ignore_dirs, None);
+
+        let options = generate_options(&[
+            "--bank-ignore-dir",
+            "fixtures",
+            "--bank-ignore-dir",
+            "tests",
+        ]);
+        assert_eq!(
+            options.ignore_dirs,
+            Some(vec!["fixtures".to_string(), "tests".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_preset_expansion() {
+        let options = generate_options(&[]);
+        assert_eq!(options.strategy, depbank::Strategy::Summary);
+        assert!(!options.strip_hidden);
+
+        let options = generate_options(&["--preset", "minimal"]);
+        assert_eq!(options.strategy, depbank::Strategy::Summary);
+        assert!(options.strip_hidden);
+
+        let options = generate_options(&["--preset", "full"]);
+        assert_eq!(options.strategy, depbank::Strategy::Full);
+        assert!(!options.strip_hidden);
+    }
+
+    #[test]
+    fn test_explicit_flags_override_preset() {
+        let options = generate_options(&["--preset", "minimal", "--strategy", "no-tests"]);
+        assert_eq!(options.strategy, depbank::Strategy::NoTests);
+        assert!(options.strip_hidden);
+
+        let options = generate_options(&["--preset", "full", "--strip-hidden", "--resume"]);
+        assert_eq!(options.strategy, depbank::Strategy::Full);
+        assert!(options.strip_hidden);
+        assert!(options.resume);
+
+        // Boolean settings of a preset can be turned off, and the last flag wins
+        let options = generate_options(&["--preset", "minimal", "--no-strip-hidden"]);
+        assert!(!options.strip_hidden);
+        let options = generate_options(&["--no-strip-hidden", "--strip-hidden"]);
+        assert!(options.strip_hidden);
+    }
+
+    #[test]
+    fn test_feature_flags() {
+        let options = generate_options(&["--features", "rt,macros", "--no-default-features"]);
+        assert_eq!(options.features.features, vec!["rt", "macros"]);
+        assert!(options.features.no_default_features);
+        assert!(!options.features.all_features);
+
+        assert!(generate_options(&[]).features.is_default());
+    }
+
+    #[test]
+    fn test_item_kind_flags() {
+        assert_eq!(generate_options(&[]).item_kinds, ItemKinds::all());
+
+        let options = generate_options(&["--include-items", "structs,traits"]);
+        assert_eq!(options.item_kinds, ItemKinds::STRUCTS | ItemKinds::TRAITS);
+
+        let options = generate_options(&["--exclude-items", "macros"]);
+        assert_eq!(options.item_kinds, ItemKinds::all() - ItemKinds::MACROS);
+
+        let options = generate_options(&[
+            "--include-items",
+            "structs,functions",
+            "--exclude-items",
+            "functions",
+        ]);
+        assert_eq!(options.item_kinds, ItemKinds::STRUCTS);
+    }
+
+    #[test]
+    fn test_multiple_project_paths() {
+        let cli = Cli::try_parse_from(["depbank", "generate", "-p", "a", "--path", "b"]).unwrap();
+        match cli.command {
+            Commands::Generate(args) => {
+                assert_eq!(args.paths, vec![PathBuf::from("a"), PathBuf::from("b")]);
+            }
+            _ => unreachable!(),
+        }
+
+        let cli = Cli::try_parse_from(["depbank", "generate"]).unwrap();
+        match cli.command {
+            Commands::Generate(args) => assert_eq!(args.paths, vec![PathBuf::from(".")]),
+            _ => unreachable!(),
+        }
+    }
 }
@@ -0,0 +1,143 @@
+//! Coloring of the output meant for people.
+//!
+//! Colors only make warnings, errors and summary headings stand out. Whether they
+//! are used is set once at startup with [`set_color_choice`]: by default, only when
+//! the output goes to a terminal and the `NO_COLOR` environment variable is not set.
+
+use std::env;
+use std::io::{self, IsTerminal};
+use std::sync::RwLock;
+
+static COLOR_CHOICE: RwLock<ColorChoice> = RwLock::new(ColorChoice::Auto);
+
+/// When to color the output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// Color output going to a terminal, unless `NO_COLOR` is set
+    #[default]
+    Auto,
+    /// Always color, even when piping and with `NO_COLOR` set
+    Always,
+    /// Never color
+    Never,
+}
+
+impl ColorChoice {
+    /// Decides whether to color an output
+    ///
+    /// # Arguments
+    ///
+    /// * `is_terminal` - Whether the output goes to a terminal
+    /// * `no_color` - Whether the `NO_COLOR` environment variable is set
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depbank::ColorChoice;
+    ///
+    /// assert!(ColorChoice::Auto.enabled(true, false));
+    /// assert!(!ColorChoice::Auto.enabled(true, true));
+    /// assert!(ColorChoice::Always.enabled(false, true));
+    /// ```
+    pub fn enabled(self, is_terminal: bool, no_color: bool) -> bool {
+        match self {
+            ColorChoice::Auto => is_terminal && !no_color,
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+}
+
+/// The output a text is written to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputStream {
+    /// Standard output, where results and summaries go
+    Stdout,
+    /// Standard error, where warnings and errors go
+    Stderr,
+}
+
+/// How a text is colored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorStyle {
+    /// Bold yellow, for warnings
+    Warning,
+    /// Bold red, for errors
+    Error,
+    /// Bold, for section headings
+    Heading,
+}
+
+impl ColorStyle {
+    fn ansi_code(self) -> &'static str {
+        match self {
+            ColorStyle::Warning => "1;33",
+            ColorStyle::Error => "1;31",
+            ColorStyle::Heading => "1",
+        }
+    }
+}
+
+/// Sets when the output written afterwards is colored
+///
+/// # Examples
+///
+/// ```rust
+/// use depbank::{ColorChoice, set_color_choice};
+///
+/// set_color_choice(ColorChoice::Never);
+/// ```
+pub fn set_color_choice(choice: ColorChoice) {
+    *COLOR_CHOICE.write().unwrap_or_else(|e| e.into_inner()) = choice;
+}
+
+/// Checks whether the text written to a stream is colored, following the choice
+/// set with [`set_color_choice`] and the `NO_COLOR` environment variable
+pub fn is_colored(stream: OutputStream) -> bool {
+    let choice = *COLOR_CHOICE.read().unwrap_or_else(|e| e.into_inner());
+    let is_terminal = match stream {
+        OutputStream::Stdout => io::stdout().is_terminal(),
+        OutputStream::Stderr => io::stderr().is_terminal(),
+    };
+    // As specified by no-color.org, an empty value does not count
+    let no_color = env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+
+    choice.enabled(is_terminal, no_color)
+}
+
+/// Colors a text written to a stream, if that stream is colored
+///
+/// # Examples
+///
+/// ```rust
+/// use depbank::{ColorChoice, OutputStream, ColorStyle, paint, set_color_choice};
+///
+/// set_color_choice(ColorChoice::Never);
+/// assert_eq!(paint("Warning:", ColorStyle::Warning, OutputStream::Stderr), "Warning:");
+/// ```
+pub fn paint(text: &str, style: ColorStyle, stream: OutputStream) -> String {
+    if is_colored(stream) {
+        format!("\x1b[{}m{}\x1b[0m", style.ansi_code(), text)
+    } else {
+        text.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_color_choice_enabled() {
+        for (is_terminal, no_color) in [(false, false), (false, true), (true, false), (true, true)]
+        {
+            assert!(ColorChoice::Always.enabled(is_terminal, no_color));
+            assert!(!ColorChoice::Never.enabled(is_terminal, no_color));
+        }
+
+        // Automatic color needs a terminal, and no NO_COLOR
+        assert!(ColorChoice::Auto.enabled(true, false));
+        assert!(!ColorChoice::Auto.enabled(false, false));
+        assert!(!ColorChoice::Auto.enabled(true, true));
+    }
+}
@@ -0,0 +1,324 @@
+//! Token comparison of code banks generated with different options.
+//!
+//! Choosing between strategies such as `summary` and `full` is a trade-off between
+//! detail and context budget. Generating each dependency's bank in memory under both
+//! settings and tokenizing the results shows what the richer banks cost, without
+//! writing anything to disk.
+
+use crate::{
+    DependencyCollection, GenerateOptions, TokenOptions, Warning, WarningKind, calculate_tokens,
+    emit_warning, generate_code_bank_to_writer, parallel,
+};
+use anyhow::Result;
+use rayon::prelude::*;
+use serde::Serialize;
+use std::path::Path;
+
+/// Token counts of one dependency's code bank under two sets of generation options
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StrategyComparison {
+    /// Name of the dependency
+    pub name: String,
+    /// Resolved version of the dependency
+    pub version: String,
+    /// Tokens in the bank generated with the baseline options
+    pub baseline_tokens: usize,
+    /// Tokens in the bank generated with the candidate options
+    pub candidate_tokens: usize,
+}
+
+impl StrategyComparison {
+    /// The candidate's tokens minus the baseline's, negative when the candidate is smaller
+    pub fn difference(&self) -> i64 {
+        self.candidate_tokens as i64 - self.baseline_tokens as i64
+    }
+}
+
+/// Estimates the tokens of a code bank by generating it in memory.
+///
+/// # Arguments
+///
+/// * `source_path` - Path to the dependency's source code
+/// * `dependency_name` - Name of the dependency
+/// * `options` - Options controlling the generation
+/// * `token_options` - Options controlling how tokens are counted
+///
+/// # Returns
+///
+/// * `Result<usize>` - The number of tokens the code bank would have
+///
+/// # Errors
+///
+/// Returns an error if the code bank cannot be generated or tokenized
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use depbank::{GenerateOptions, Strategy, TokenOptions, estimate_generation_tokens};
+/// use std::path::Path;
+///
+/// let options = GenerateOptions {
+///     strategy: Strategy::Full,
+///     ..Default::default()
+/// };
+/// let tokens = estimate_generation_tokens(
+///     Path::new("/path/to/registry/serde-1.0.197"),
+///     "serde",
+///     &options,
+///     &TokenOptions::default(),
+/// )
+/// .unwrap();
+/// println!("A full bank of serde has {} tokens", tokens);
+/// ```
+pub fn estimate_generation_tokens(
+    source_path: &Path,
+    dependency_name: &str,
+    options: &GenerateOptions,
+    token_options: &TokenOptions,
+) -> Result<usize> {
+    let mut buffer = Vec::new();
+    generate_code_bank_to_writer(source_path, dependency_name, options, &mut buffer)?;
+    calculate_tokens(&String::from_utf8_lossy(&buffer), token_options)
+}
+
+/// Compares the tokens of the code banks of dependencies under two sets of options.
+///
+/// Both banks of every dependency are generated in memory, in parallel on the shared
+/// thread pool. Dependencies whose banks cannot be generated are skipped with a
+/// warning.
+///
+/// # Arguments
+///
+/// * `dependencies` - The dependencies to compare, with resolved versions
+/// * `registry_path` - Path to the cargo registry directory
+/// * `baseline` - The options to compare against
+/// * `candidate` - The options being considered
+/// * `token_options` - Options controlling how tokens are counted
+///
+/// # Returns
+///
+/// * `Result<Vec<StrategyComparison>>` - One comparison per dependency, in the order
+///   of `dependencies`
+///
+/// # Errors
+///
+/// Returns an error if the thread pool cannot be created
+pub fn compare_strategies(
+    dependencies: &DependencyCollection,
+    registry_path: &Path,
+    baseline: &GenerateOptions,
+    candidate: &GenerateOptions,
+    token_options: &TokenOptions,
+) -> Result<Vec<StrategyComparison>> {
+    let results: Vec<Option<StrategyComparison>> = parallel::install(|| {
+        dependencies
+            .as_slice()
+            .par_iter()
+            .map(|dependency| {
+                let source_path = dependency.get_registry_path(registry_path);
+                let estimate = |options| {
+                    estimate_generation_tokens(
+                        &source_path,
+                        &dependency.name,
+                        options,
+                        token_options,
+                    )
+                };
+                match estimate(baseline)
+                    .and_then(|baseline_tokens| Ok((baseline_tokens, estimate(candidate)?)))
+                {
+                    Ok((baseline_tokens, candidate_tokens)) => Some(StrategyComparison {
+                        name: dependency.name.clone(),
+                        version: dependency.version.clone(),
+                        baseline_tokens,
+                        candidate_tokens,
+                    }),
+                    Err(e) => {
+                        emit_warning(Warning::new(
+                            WarningKind::GenerationFailed,
+                            Some(&dependency.name),
+                            format!("{:#}", e),
+                        ));
+                        None
+                    }
+                }
+            })
+            .collect()
+    })?;
+
+    Ok(results.into_iter().flatten().collect())
+}
+
+/// Measures the tokens that leaving directories such as `tests` and `examples` out
+/// of code banks saves.
+///
+/// Each dependency's bank is generated in memory with and without the ignored
+/// directories of `options`, see [`GenerateOptions::ignore_dirs`], by
+/// [`compare_strategies`]. The baseline of each comparison is the bank without any
+/// ignored directory, and the candidate the bank as configured, so the tokens saved
+/// are the baseline's tokens minus the candidate's.
+///
+/// # Arguments
+///
+/// * `dependencies` - The dependencies to measure, with resolved versions
+/// * `registry_path` - Path to the cargo registry directory
+/// * `options` - The options the banks are generated with
+/// * `token_options` - Options controlling how tokens are counted
+///
+/// # Returns
+///
+/// * `Result<Vec<StrategyComparison>>` - One comparison per dependency, in the order
+///   of `dependencies`
+///
+/// # Errors
+///
+/// Returns an error if the thread pool cannot be created
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use depbank::{
+///     DependencyCollection, GenerateOptions, TokenOptions, estimate_ignore_savings,
+/// };
+/// use std::path::Path;
+///
+/// let dependencies = DependencyCollection::new();
+/// let comparisons = estimate_ignore_savings(
+///     &dependencies,
+///     Path::new("/path/to/registry"),
+///     &GenerateOptions::default(),
+///     &TokenOptions::default(),
+/// )
+/// .unwrap();
+/// let saved: i64 = comparisons.iter().map(|c| -c.difference()).sum();
+/// println!("Ignoring tests, examples and benches saves {} tokens", saved);
+/// ```
+pub fn estimate_ignore_savings(
+    dependencies: &DependencyCollection,
+    registry_path: &Path,
+    options: &GenerateOptions,
+    token_options: &TokenOptions,
+) -> Result<Vec<StrategyComparison>> {
+    let unfiltered = GenerateOptions {
+        ignore_dirs: Some(Vec::new()),
+        ..options.clone()
+    };
+    compare_strategies(
+        dependencies,
+        registry_path,
+        &unfiltered,
+        options,
+        token_options,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Dependency, Strategy};
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_compare_strategies() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let crate_dir = temp_dir.path().join("sample-0.1.0");
+        fs::create_dir_all(crate_dir.join("src"))?;
+        fs::write(
+            crate_dir.join("Cargo.toml"),
+            "[package]\nname = \"sample\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+        )?;
+        fs::write(
+            crate_dir.join("src").join("lib.rs"),
+            r#"
+/// Adds up the squares of the numbers
+pub fn sum_of_squares(numbers: &[i64]) -> i64 {
+    let mut total = 0;
+    for number in numbers {
+        total += square(*number);
+    }
+    total
+}
+
+fn square(number: i64) -> i64 {
+    number * number
+}
+"#,
+        )?;
+
+        let mut dependencies = DependencyCollection::new();
+        dependencies.add(Dependency::new("sample", "0.1.0"));
+        // Missing dependencies are skipped
+        dependencies.add(Dependency::new("missing", "1.0.0"));
+
+        let full = GenerateOptions {
+            strategy: Strategy::Full,
+            ..Default::default()
+        };
+        let comparisons = compare_strategies(
+            &dependencies,
+            temp_dir.path(),
+            &GenerateOptions::default(),
+            &full,
+            &TokenOptions::default(),
+        )?;
+
+        assert_eq!(comparisons.len(), 1);
+        let comparison = &comparisons[0];
+        assert_eq!(comparison.name, "sample");
+        assert_eq!(
+            comparison.baseline_tokens,
+            estimate_generation_tokens(
+                &crate_dir,
+                "sample",
+                &GenerateOptions::default(),
+                &TokenOptions::default()
+            )?
+        );
+        // The full bank includes the function bodies and the private helper
+        assert!(comparison.candidate_tokens > comparison.baseline_tokens);
+        assert_eq!(
+            comparison.difference(),
+            (comparison.candidate_tokens - comparison.baseline_tokens) as i64
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_estimate_ignore_savings() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let crate_dir = temp_dir.path().join("sample-0.1.0");
+        fs::create_dir_all(crate_dir.join("src"))?;
+        fs::create_dir_all(crate_dir.join("tests"))?;
+        fs::write(
+            crate_dir.join("Cargo.toml"),
+            "[package]\nname = \"sample\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+        )?;
+        fs::write(
+            crate_dir.join("src").join("lib.rs"),
+            "/// Adds two numbers\npub fn add(a: i64, b: i64) -> i64 {\n    a + b\n}\n",
+        )?;
+        fs::write(
+            crate_dir.join("tests").join("common.rs"),
+            "/// Builds the pairs of numbers the integration tests add up\n\
+pub fn sample_pairs() -> Vec<(i64, i64)> {\n    vec![(1, 2), (3, 4)]\n}\n",
+        )?;
+
+        let mut dependencies = DependencyCollection::new();
+        dependencies.add(Dependency::new("sample", "0.1.0"));
+        let comparisons = estimate_ignore_savings(
+            &dependencies,
+            temp_dir.path(),
+            &GenerateOptions::default(),
+            &TokenOptions::default(),
+        )?;
+
+        assert_eq!(comparisons.len(), 1);
+        // Only the bank without ignored directories documents the tests directory
+        assert!(comparisons[0].baseline_tokens > comparisons[0].candidate_tokens);
+        assert!(comparisons[0].difference() < 0);
+
+        Ok(())
+    }
+}
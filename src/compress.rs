@@ -0,0 +1,204 @@
+//! Gzip compression of large code banks.
+//!
+//! Banks of big dependencies can take up a lot of space when many of them are kept
+//! around. Banks above a size threshold can be gzipped individually into
+//! `<name>.md.gz`, and readers of the output directory use [`read_bank`] to handle
+//! compressed and uncompressed banks alike.
+
+use anyhow::{Context, Result};
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Extension appended to the file name of compressed banks
+pub const COMPRESSED_BANK_EXTENSION: &str = "gz";
+
+/// Checks whether a bank file is gzip-compressed, judging by its extension
+pub fn is_compressed_bank(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext == COMPRESSED_BANK_EXTENSION)
+}
+
+/// Gzips a code bank into `<path>.gz` and removes the uncompressed file.
+///
+/// # Arguments
+///
+/// * `path` - Path to the code bank file
+///
+/// # Returns
+///
+/// * `Result<PathBuf>` - Path to the compressed file
+///
+/// # Errors
+///
+/// Returns an error if the bank cannot be read, or the compressed file cannot be
+/// written
+pub fn compress_bank(path: &Path) -> Result<PathBuf> {
+    let content = fs::read(path)
+        .with_context(|| format!("Failed to read code bank file: {}", path.display()))?;
+    let compressed_path = compressed_path(path);
+
+    let file = File::create(&compressed_path).with_context(|| {
+        format!(
+            "Failed to create compressed code bank: {}",
+            compressed_path.display()
+        )
+    })?;
+    let mut encoder = GzEncoder::new(BufWriter::new(file), Compression::default());
+    encoder
+        .write_all(&content)
+        .and_then(|_| encoder.finish()?.flush())
+        .with_context(|| {
+            format!(
+                "Failed to write compressed code bank: {}",
+                compressed_path.display()
+            )
+        })?;
+
+    fs::remove_file(path)
+        .with_context(|| format!("Failed to remove code bank file: {}", path.display()))?;
+
+    Ok(compressed_path)
+}
+
+/// Gzips every code bank larger than a threshold.
+///
+/// The paths of compressed banks are replaced by the paths of their `.gz` files.
+/// A compressed copy left over from a previous run is removed for banks that stay
+/// uncompressed, so the output directory never holds both versions of a bank.
+///
+/// # Arguments
+///
+/// * `code_bank_files` - Mapping of dependency names to their code bank file paths
+/// * `threshold` - Size in bytes a bank must exceed to be compressed
+///
+/// # Returns
+///
+/// * `Result<usize>` - The number of banks compressed
+///
+/// # Errors
+///
+/// Returns an error if a bank cannot be compressed
+pub fn compress_banks_over(
+    code_bank_files: &mut HashMap<String, PathBuf>,
+    threshold: u64,
+) -> Result<usize> {
+    let mut compressed = 0;
+
+    for path in code_bank_files.values_mut() {
+        if is_compressed_bank(path) {
+            continue;
+        }
+
+        let size = fs::metadata(&*path)
+            .with_context(|| format!("Failed to read code bank file: {}", path.display()))?
+            .len();
+        if size > threshold {
+            *path = compress_bank(path)?;
+            compressed += 1;
+        } else {
+            let stale = compressed_path(path);
+            if stale.is_file() {
+                fs::remove_file(&stale).with_context(|| {
+                    format!("Failed to remove compressed code bank: {}", stale.display())
+                })?;
+            }
+        }
+    }
+
+    Ok(compressed)
+}
+
+/// Reads a code bank, decompressing it if it is gzipped.
+///
+/// # Arguments
+///
+/// * `path` - Path to a `.md` or `.md.gz` code bank file
+///
+/// # Returns
+///
+/// * `Result<String>` - The markdown content of the bank
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or decompressed, or is not UTF-8
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use depbank::read_bank;
+/// use std::path::Path;
+///
+/// let content = read_bank(Path::new(".codebank/tokio.md.gz")).unwrap();
+/// println!("{}", content);
+/// ```
+pub fn read_bank(path: &Path) -> Result<String> {
+    if !is_compressed_bank(path) {
+        return fs::read_to_string(path)
+            .with_context(|| format!("Failed to read code bank file: {}", path.display()));
+    }
+
+    let file = File::open(path)
+        .with_context(|| format!("Failed to read code bank file: {}", path.display()))?;
+    let mut content = String::new();
+    GzDecoder::new(file)
+        .read_to_string(&mut content)
+        .with_context(|| format!("Failed to decompress code bank file: {}", path.display()))?;
+
+    Ok(content)
+}
+
+/// Returns the path of a bank's compressed file, `<path>.gz`
+fn compressed_path(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".");
+    file_name.push(COMPRESSED_BANK_EXTENSION);
+    path.with_file_name(file_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_compress_banks_over_threshold() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let large = temp_dir.path().join("tokio.md");
+        let small = temp_dir.path().join("itoa.md");
+        let large_content = "# tokio\n\npub fn spawn() {}\n".repeat(100);
+        fs::write(&large, &large_content)?;
+        fs::write(&small, "# itoa\n\npub fn format() {}\n")?;
+        // Left over from a run where the small bank was compressed
+        fs::write(temp_dir.path().join("itoa.md.gz"), "stale")?;
+
+        let mut code_bank_files = HashMap::from([
+            ("tokio".to_string(), large.clone()),
+            ("itoa".to_string(), small.clone()),
+        ]);
+        let compressed = compress_banks_over(&mut code_bank_files, 1024)?;
+
+        assert_eq!(compressed, 1);
+        assert_eq!(
+            code_bank_files["tokio"],
+            temp_dir.path().join("tokio.md.gz")
+        );
+        assert_eq!(code_bank_files["itoa"], small);
+        assert!(!large.exists());
+        assert!(!temp_dir.path().join("itoa.md.gz").exists());
+
+        // Both kinds of banks read back the same way
+        assert_eq!(read_bank(&code_bank_files["tokio"])?, large_content);
+        assert!(read_bank(&code_bank_files["itoa"])?.starts_with("# itoa"));
+
+        // Compressed banks are not compressed again
+        assert_eq!(compress_banks_over(&mut code_bank_files, 0)?, 1);
+        assert_eq!(code_bank_files["itoa"], temp_dir.path().join("itoa.md.gz"));
+
+        Ok(())
+    }
+}
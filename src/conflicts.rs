@@ -0,0 +1,362 @@
+//! Detection of crates locked at several incompatible versions.
+//!
+//! Cargo can only unify versions that are semver compatible, so a diamond dependency
+//! on `rand 0.7` and `rand 0.8` locks both. Their APIs differ, which easily confuses
+//! readers of the code banks, human or AI, so these conflicts are worth pointing out.
+//!
+//! Only one code bank is generated per crate, though, so when workspace members or
+//! combined projects use a crate at different versions, a [`VersionConflictPolicy`]
+//! picks the version to document.
+
+use crate::{
+    DepBankError, Dependency, DependencyCollection, ParsedLock, Warning, WarningKind, emit_warning,
+    normalize_crate_name,
+};
+use anyhow::Result;
+use semver::Version;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// How to pick the version of a crate used at several versions
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VersionConflictPolicy {
+    /// Keep the highest version, with a warning
+    #[default]
+    Highest,
+    /// Keep the lowest version, with a warning
+    Lowest,
+    /// Fail with [`DepBankError::VersionConflict`]
+    Error,
+}
+
+/// Finds the crates locked at more than one semver-incompatible version.
+///
+/// Versions are incompatible when they differ in their first non-zero component,
+/// following cargo: `1.x` and `2.x` are, and so are `0.7.x` and `0.8.x`, while
+/// `1.2.0` and `1.5.3` are not. Versions that are not valid semver are ignored.
+///
+/// # Arguments
+///
+/// * `cargo_lock_path` - Path to the Cargo.lock file
+///
+/// # Returns
+///
+/// * `Result<HashMap<String, Vec<Version>>>` - Each conflicting crate mapped to all
+///   of its locked versions, sorted
+///
+/// # Errors
+///
+/// Returns an error if the Cargo.lock file cannot be read or parsed
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use depbank::find_major_version_conflicts;
+///
+/// for (name, versions) in find_major_version_conflicts("Cargo.lock").unwrap() {
+///     println!("{} is locked at {} incompatible versions", name, versions.len());
+/// }
+/// ```
+pub fn find_major_version_conflicts<P: AsRef<Path>>(
+    cargo_lock_path: P,
+) -> Result<HashMap<String, Vec<Version>>> {
+    Ok(find_major_version_conflicts_from_lock(&ParsedLock::read(
+        cargo_lock_path,
+    )?))
+}
+
+/// Finds the crates locked at more than one semver-incompatible version, from
+/// Cargo.lock content held in memory.
+///
+/// This is the same as [`find_major_version_conflicts`], but takes the lockfile
+/// content directly instead of a path.
+///
+/// # Arguments
+///
+/// * `content` - The content of a Cargo.lock file
+///
+/// # Returns
+///
+/// * `Result<HashMap<String, Vec<Version>>>` - Each conflicting crate mapped to all
+///   of its locked versions, sorted
+///
+/// # Errors
+///
+/// Returns an error if the content cannot be parsed as a valid Cargo.lock
+///
+/// # Examples
+///
+/// ```rust
+/// use depbank::find_major_version_conflicts_from_str;
+///
+/// let lock = r#"
+/// [[package]]
+/// name = "rand"
+/// version = "0.7.3"
+///
+/// [[package]]
+/// name = "rand"
+/// version = "0.8.5"
+/// "#;
+/// let conflicts = find_major_version_conflicts_from_str(lock).unwrap();
+/// assert_eq!(conflicts["rand"].len(), 2);
+/// ```
+pub fn find_major_version_conflicts_from_str(
+    content: &str,
+) -> Result<HashMap<String, Vec<Version>>> {
+    Ok(find_major_version_conflicts_from_lock(&ParsedLock::parse(
+        content,
+    )?))
+}
+
+/// Finds the crates locked at more than one semver-incompatible version, in an
+/// already parsed Cargo.lock.
+///
+/// This is the same as [`find_major_version_conflicts`], for a lockfile that other
+/// steps of an analysis read as well.
+///
+/// # Arguments
+///
+/// * `cargo_lock` - The parsed Cargo.lock
+///
+/// # Returns
+///
+/// * `HashMap<String, Vec<Version>>` - Each conflicting crate mapped to all of its
+///   locked versions, sorted
+pub fn find_major_version_conflicts_from_lock(
+    cargo_lock: &ParsedLock,
+) -> HashMap<String, Vec<Version>> {
+    let mut versions: HashMap<String, Vec<Version>> = HashMap::new();
+    for package in &cargo_lock.package {
+        if let Ok(version) = Version::parse(&package.version) {
+            versions
+                .entry(package.name.clone())
+                .or_default()
+                .push(version);
+        }
+    }
+
+    versions
+        .into_iter()
+        .filter_map(|(name, mut versions)| {
+            versions.sort();
+            versions.dedup();
+            let first = compatibility_key(versions.first()?);
+            versions
+                .iter()
+                .any(|version| compatibility_key(version) != first)
+                .then_some((name, versions))
+        })
+        .collect()
+}
+
+/// Keeps a single version of each crate in a collection of dependencies.
+///
+/// Dependencies aggregated from several workspace members or projects may hold a
+/// crate more than once, possibly at different versions. Exact duplicates are
+/// dropped, and when versions differ, `policy` picks the one to keep. Versions are
+/// compared by semver precedence, or as strings when they are not valid semver.
+/// Crates keep the position of their first occurrence.
+///
+/// # Arguments
+///
+/// * `dependencies` - The aggregated dependencies, with their resolved versions
+/// * `policy` - How to pick the version of a crate used at several versions
+///
+/// # Returns
+///
+/// * `Result<DependencyCollection>` - The dependencies, one per crate
+///
+/// # Errors
+///
+/// Returns [`DepBankError::VersionConflict`] for the first crate used at several
+/// versions, if `policy` is [`VersionConflictPolicy::Error`]
+///
+/// # Examples
+///
+/// ```rust
+/// use depbank::{Dependency, DependencyCollection, VersionConflictPolicy, resolve_version_conflicts};
+///
+/// let mut deps = DependencyCollection::new();
+/// deps.add(Dependency::new("rand", "0.8.5"));
+/// deps.add(Dependency::new("rand", "0.7.3"));
+///
+/// let lowest = resolve_version_conflicts(&deps, VersionConflictPolicy::Lowest).unwrap();
+/// assert_eq!(lowest.get_version("rand"), Some(&"0.7.3".to_string()));
+/// assert!(resolve_version_conflicts(&deps, VersionConflictPolicy::Error).is_err());
+/// ```
+pub fn resolve_version_conflicts(
+    dependencies: &DependencyCollection,
+    policy: VersionConflictPolicy,
+) -> Result<DependencyCollection> {
+    // The distinct versions of each crate, in order of first occurrence
+    let mut crates: Vec<Vec<&Dependency>> = Vec::new();
+    let mut positions: HashMap<String, usize> = HashMap::new();
+    for dep in dependencies.iter() {
+        let position = *positions
+            .entry(normalize_crate_name(&dep.name))
+            .or_insert_with(|| {
+                crates.push(Vec::new());
+                crates.len() - 1
+            });
+        let versions = &mut crates[position];
+        if !versions
+            .iter()
+            .any(|existing| existing.version == dep.version)
+        {
+            versions.push(dep);
+        }
+    }
+
+    let mut resolved = DependencyCollection::new();
+    for mut versions in crates {
+        if versions.len() > 1 {
+            versions.sort_by(|a, b| compare_versions(&a.version, &b.version));
+            let name = &versions[0].name;
+            let listed: Vec<String> = versions.iter().map(|dep| dep.version.clone()).collect();
+            let kept = match policy {
+                VersionConflictPolicy::Highest => versions[versions.len() - 1],
+                VersionConflictPolicy::Lowest => versions[0],
+                VersionConflictPolicy::Error => {
+                    return Err(DepBankError::VersionConflict {
+                        name: name.clone(),
+                        versions: listed,
+                    }
+                    .into());
+                }
+            };
+            emit_warning(Warning::new(
+                WarningKind::VersionConflict,
+                Some(name),
+                format!(
+                    "{} is used at versions {}, generating the code bank for {}",
+                    name,
+                    listed.join(", "),
+                    kept.version
+                ),
+            ));
+            resolved.add(kept.clone());
+        } else {
+            resolved.add(versions[0].clone());
+        }
+    }
+    Ok(resolved)
+}
+
+/// Compares versions by semver precedence, falling back to comparing them as strings
+pub(crate) fn compare_versions(a: &str, b: &str) -> Ordering {
+    match (Version::parse(a), Version::parse(b)) {
+        (Ok(a), Ok(b)) => a.cmp(&b),
+        _ => a.cmp(b),
+    }
+}
+
+/// The part of a version that must match for cargo to consider two versions
+/// compatible: the major version, or the first non-zero component for `0.x`
+fn compatibility_key(version: &Version) -> (u64, u64, u64) {
+    match (version.major, version.minor) {
+        (0, 0) => (0, 0, version.patch),
+        (0, minor) => (0, minor, 0),
+        (major, _) => (major, 0, 0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_find_major_version_conflicts() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let lock_path = temp_dir.path().join("Cargo.lock");
+        fs::write(
+            &lock_path,
+            r#"version = 3
+
+[[package]]
+name = "rand"
+version = "0.8.5"
+
+[[package]]
+name = "rand"
+version = "0.7.3"
+
+[[package]]
+name = "syn"
+version = "1.0.109"
+
+[[package]]
+name = "syn"
+version = "2.0.100"
+
+[[package]]
+name = "hashbrown"
+version = "0.15.2"
+
+[[package]]
+name = "itoa"
+version = "1.0.11"
+
+[[package]]
+name = "itoa"
+version = "1.0.15"
+"#,
+        )?;
+
+        let conflicts = find_major_version_conflicts(&lock_path)?;
+        assert_eq!(conflicts.len(), 2);
+        assert_eq!(
+            conflicts["rand"],
+            vec![Version::new(0, 7, 3), Version::new(0, 8, 5)]
+        );
+        assert_eq!(
+            conflicts["syn"],
+            vec![Version::new(1, 0, 109), Version::new(2, 0, 100)]
+        );
+        // Semver-compatible versions do not conflict
+        assert!(!conflicts.contains_key("itoa"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_version_conflicts() -> Result<()> {
+        let mut deps = DependencyCollection::new();
+        deps.add(Dependency::new("rand", "0.8.5"));
+        deps.add(Dependency::new("serde", "1.0.219"));
+        deps.add(Dependency::new("rand", "0.7.3"));
+        deps.add(Dependency::new("serde", "1.0.219"));
+
+        let highest = resolve_version_conflicts(&deps, VersionConflictPolicy::Highest)?;
+        let listed: Vec<String> = highest
+            .iter()
+            .map(|dep| format!("{} {}", dep.name, dep.version))
+            .collect();
+        assert_eq!(listed, vec!["rand 0.8.5", "serde 1.0.219"]);
+
+        let lowest = resolve_version_conflicts(&deps, VersionConflictPolicy::Lowest)?;
+        assert_eq!(lowest.get_version("rand"), Some(&"0.7.3".to_string()));
+        assert_eq!(lowest.len(), 2);
+
+        let error = resolve_version_conflicts(&deps, VersionConflictPolicy::Error).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<DepBankError>(),
+            Some(DepBankError::VersionConflict { name, versions })
+                if name == "rand" && versions == &["0.7.3", "0.8.5"]
+        ));
+
+        // Exact duplicates are not conflicts
+        let mut duplicates = DependencyCollection::new();
+        duplicates.add(Dependency::new("serde", "1.0.219"));
+        duplicates.add(Dependency::new("serde", "1.0.219"));
+        assert_eq!(
+            resolve_version_conflicts(&duplicates, VersionConflictPolicy::Error)?.len(),
+            1
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,71 @@
+//! Cost estimates for feeding token counts into a model API.
+
+/// Default prices of known models, in dollars per 1,000 input tokens
+pub const KNOWN_MODEL_PRICES: &[(&str, f64)] = &[
+    ("gpt-4o", 0.0025),
+    ("gpt-4o-mini", 0.00015),
+    ("gpt-4-turbo", 0.01),
+    ("claude-3-opus", 0.015),
+    ("claude-3-5-sonnet", 0.003),
+    ("claude-3-5-haiku", 0.0008),
+    ("gemini-1.5-pro", 0.00125),
+    ("gemini-1.5-flash", 0.000075),
+];
+
+/// Estimates the cost of a number of tokens at a given rate.
+///
+/// # Arguments
+///
+/// * `tokens` - The number of tokens
+/// * `rate_per_1k` - The price in dollars per 1,000 tokens
+///
+/// # Returns
+///
+/// * `f64` - The estimated cost in dollars
+///
+/// # Examples
+///
+/// ```rust
+/// use depbank::estimate_cost;
+///
+/// assert_eq!(estimate_cost(20_000, 0.5), 10.0);
+/// ```
+pub fn estimate_cost(tokens: usize, rate_per_1k: f64) -> f64 {
+    tokens as f64 / 1000.0 * rate_per_1k
+}
+
+/// Looks up the default price of a known model.
+///
+/// # Arguments
+///
+/// * `model` - The model name, compared case-insensitively
+///
+/// # Returns
+///
+/// * `Option<f64>` - The price in dollars per 1,000 input tokens, if the model is known
+pub fn model_price_per_1k(model: &str) -> Option<f64> {
+    KNOWN_MODEL_PRICES
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(model))
+        .map(|(_, price)| *price)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_cost() {
+        assert_eq!(estimate_cost(0, 0.003), 0.0);
+        assert_eq!(estimate_cost(1000, 0.003), 0.003);
+        assert_eq!(estimate_cost(2500, 2.0), 5.0);
+        assert!((estimate_cost(123_456, 0.0025) - 0.30864).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_model_price_per_1k() {
+        assert_eq!(model_price_per_1k("gpt-4o"), Some(0.0025));
+        assert_eq!(model_price_per_1k("Claude-3-5-Sonnet"), Some(0.003));
+        assert_eq!(model_price_per_1k("unknown-model"), None);
+    }
+}
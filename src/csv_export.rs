@@ -0,0 +1,155 @@
+//! CSV export of dependency listings for spreadsheet-based audits.
+//!
+//! Every row has all the columns of [`DEPENDENCY_CSV_HEADER`], in that order, with
+//! empty cells where a value is not known, so spreadsheets and scripts can rely on
+//! the layout. Fields are quoted following RFC 4180.
+
+use anyhow::{Context, Result};
+use std::io::Write;
+
+/// The columns of a dependency CSV, in order
+pub const DEPENDENCY_CSV_HEADER: [&str; 6] = [
+    "name",
+    "version",
+    "resolved_version",
+    "available",
+    "kind",
+    "license",
+];
+
+/// One dependency in a CSV listing
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DependencyCsvRow {
+    /// Name of the dependency
+    pub name: String,
+    /// The declared version requirement
+    pub version: String,
+    /// The version resolved from Cargo.lock, if known
+    pub resolved_version: Option<String>,
+    /// Whether the resolved version is available in the local cargo registry, if known
+    pub available: Option<bool>,
+    /// The kinds the dependency is declared as, e.g. `normal;dev`
+    pub kind: String,
+    /// The license of the resolved version, if known
+    pub license: Option<String>,
+}
+
+impl DependencyCsvRow {
+    fn fields(&self) -> [&str; 6] {
+        [
+            &self.name,
+            &self.version,
+            self.resolved_version.as_deref().unwrap_or_default(),
+            match self.available {
+                Some(true) => "true",
+                Some(false) => "false",
+                None => "",
+            },
+            &self.kind,
+            self.license.as_deref().unwrap_or_default(),
+        ]
+    }
+}
+
+/// Writes dependencies as CSV, starting with the [`DEPENDENCY_CSV_HEADER`] row.
+///
+/// # Arguments
+///
+/// * `rows` - The dependencies to write
+/// * `writer` - Where to write the CSV
+///
+/// # Errors
+///
+/// Returns an error if writing fails
+///
+/// # Examples
+///
+/// ```rust
+/// use depbank::{DependencyCsvRow, write_dependency_csv};
+///
+/// let row = DependencyCsvRow {
+///     name: "serde".to_string(),
+///     version: "1.0".to_string(),
+///     resolved_version: Some("1.0.219".to_string()),
+///     kind: "normal".to_string(),
+///     ..Default::default()
+/// };
+/// let mut csv = Vec::new();
+/// write_dependency_csv(&[row], &mut csv).unwrap();
+/// assert_eq!(
+///     String::from_utf8(csv).unwrap(),
+///     "name,version,resolved_version,available,kind,license\r\nserde,1.0,1.0.219,,normal,\r\n"
+/// );
+/// ```
+pub fn write_dependency_csv(rows: &[DependencyCsvRow], writer: &mut dyn Write) -> Result<()> {
+    let mut csv = csv::WriterBuilder::new()
+        .terminator(csv::Terminator::CRLF)
+        .from_writer(writer);
+    csv.write_record(DEPENDENCY_CSV_HEADER)
+        .context("Failed to write CSV")?;
+    for row in rows {
+        csv.write_record(row.fields())
+            .context("Failed to write CSV")?;
+    }
+    csv.flush().context("Failed to write CSV")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_dependency_csv() -> Result<()> {
+        let rows = vec![
+            DependencyCsvRow {
+                name: "serde".to_string(),
+                version: ">=1.0, <2".to_string(),
+                resolved_version: Some("1.0.219".to_string()),
+                available: Some(true),
+                kind: "normal;dev".to_string(),
+                license: Some("MIT OR Apache-2.0".to_string()),
+            },
+            DependencyCsvRow {
+                name: "internal".to_string(),
+                version: "*".to_string(),
+                kind: "build".to_string(),
+                license: Some("LicenseRef-\"Proprietary\"".to_string()),
+                ..Default::default()
+            },
+        ];
+        let mut csv = Vec::new();
+        write_dependency_csv(&rows, &mut csv)?;
+
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .from_reader(csv.as_slice());
+        let records: Vec<Vec<String>> = reader.deserialize().collect::<Result<_, _>>()?;
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0], DEPENDENCY_CSV_HEADER);
+        assert_eq!(
+            records[1],
+            vec![
+                "serde",
+                ">=1.0, <2",
+                "1.0.219",
+                "true",
+                "normal;dev",
+                "MIT OR Apache-2.0"
+            ]
+        );
+        // Unknown metadata still takes up its cells
+        assert_eq!(
+            records[2],
+            vec![
+                "internal",
+                "*",
+                "",
+                "",
+                "build",
+                "LicenseRef-\"Proprietary\""
+            ]
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,309 @@
+//! Marking of deprecated items in code banks.
+//!
+//! An assistant reading a code bank should avoid suggesting deprecated APIs. The
+//! `#[deprecated]` attributes are read from the dependency's sources with syn, and
+//! the matching items in the bank are tagged with a `⚠ deprecated` marker and the
+//! deprecation note, which also survives filters that drop attributes, such as the
+//! outline.
+
+use crate::filters::{is_rust_fence, outline_item};
+use crate::sources::collect_rust_files;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use syn::{Attribute, ImplItem, Item, Meta, TraitItem};
+
+/// Marker placed on deprecated items
+const DEPRECATED_MARKER: &str = "⚠ deprecated";
+
+/// A deprecated item declared in a source file
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Deprecation {
+    /// The kind of item, as named by the outline, e.g. `fn` or `struct`
+    kind: &'static str,
+    /// The name of the item
+    name: String,
+    /// The deprecation note, including the version it was deprecated in
+    note: Option<String>,
+}
+
+/// Marks the items of a code bank that are deprecated in the dependency's sources.
+///
+/// Every item with a `#[deprecated]` attribute in the sources under `source_path` is
+/// looked up in the bank section of its file. In `rust` code blocks a comment such
+/// as `// ⚠ deprecated: use from_debug instead` is inserted above its declaration,
+/// and in outlines the marker is appended to the item's entry. Items are matched by
+/// kind and name within a file, so same-named methods of different types in one file
+/// are all marked if any of them is deprecated. Files that cannot be parsed are
+/// skipped.
+///
+/// # Arguments
+///
+/// * `source_path` - Path to the dependency's source code
+/// * `markdown` - The generated code bank content
+///
+/// # Returns
+///
+/// * `String` - The code bank with deprecated items marked
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use depbank::annotate_deprecations;
+/// use std::path::Path;
+///
+/// let bank = std::fs::read_to_string(".codebank/log.md").unwrap();
+/// let source = Path::new("/home/user/.cargo/registry/src/index.crates.io-1949cf8c6b5b557f/log-0.4.27");
+/// println!("{}", annotate_deprecations(source, &bank));
+/// ```
+pub fn annotate_deprecations(source_path: &Path, markdown: &str) -> String {
+    let deprecations = collect_deprecations(source_path);
+    if deprecations.is_empty() {
+        return markdown.to_string();
+    }
+
+    let mut output = String::with_capacity(markdown.len());
+    let mut in_code_block = false;
+    let mut in_rust_block = false;
+    let mut section: &[Deprecation] = &[];
+
+    for line in markdown.lines() {
+        let trimmed = line.trim();
+
+        if trimmed.starts_with("```") {
+            in_code_block = !in_code_block;
+            in_rust_block = in_code_block && is_rust_fence(trimmed);
+        } else if in_rust_block {
+            if let Some(deprecation) = outline_item(trimmed)
+                .and_then(|(kind, name)| find_deprecation(section, kind, &name))
+            {
+                let indent = &line[..line.len() - line.trim_start().len()];
+                output.push_str(&format!("{}// {}\n", indent, marker(deprecation)));
+            }
+        } else if !in_code_block {
+            if let Some(path) = trimmed.strip_prefix("## ") {
+                section = deprecations.get(path).map_or(&[], Vec::as_slice);
+            } else if let Some(deprecation) = trimmed
+                .strip_prefix("- ")
+                .and_then(|entry| entry.split_once(' '))
+                .and_then(|(kind, name)| find_deprecation(section, kind, name))
+            {
+                // An outline entry, marked on the same line
+                output.push_str(&format!("{} ({})\n", line, marker(deprecation)));
+                continue;
+            }
+        }
+
+        output.push_str(line);
+        output.push('\n');
+    }
+
+    output
+}
+
+fn find_deprecation<'a>(
+    deprecations: &'a [Deprecation],
+    kind: &str,
+    name: &str,
+) -> Option<&'a Deprecation> {
+    deprecations
+        .iter()
+        .find(|deprecation| deprecation.kind == kind && deprecation.name == name)
+}
+
+fn marker(deprecation: &Deprecation) -> String {
+    match &deprecation.note {
+        Some(note) => format!("{}: {}", DEPRECATED_MARKER, note),
+        None => DEPRECATED_MARKER.to_string(),
+    }
+}
+
+/// Collects the deprecated items of every Rust file, keyed by the path relative to
+/// the crate root with forward slashes, as in the bank section headings
+fn collect_deprecations(source_path: &Path) -> HashMap<String, Vec<Deprecation>> {
+    let mut deprecations = HashMap::new();
+    for file in collect_rust_files(source_path) {
+        let Ok(content) = fs::read_to_string(&file) else {
+            continue;
+        };
+        let Ok(syntax) = syn::parse_file(&content) else {
+            continue;
+        };
+
+        let mut found = Vec::new();
+        collect_from_items(&syntax.items, &mut found);
+        if !found.is_empty() {
+            let relative = file.strip_prefix(source_path).unwrap_or(&file);
+            let key = relative
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            deprecations.insert(key, found);
+        }
+    }
+
+    deprecations
+}
+
+fn collect_from_items(items: &[Item], found: &mut Vec<Deprecation>) {
+    for item in items {
+        let (kind, name, attrs) = match item {
+            Item::Fn(item) => ("fn", item.sig.ident.to_string(), &item.attrs),
+            Item::Struct(item) => ("struct", item.ident.to_string(), &item.attrs),
+            Item::Enum(item) => ("enum", item.ident.to_string(), &item.attrs),
+            Item::Union(item) => ("union", item.ident.to_string(), &item.attrs),
+            Item::Type(item) => ("type", item.ident.to_string(), &item.attrs),
+            Item::Const(item) => ("const", item.ident.to_string(), &item.attrs),
+            Item::Static(item) => ("static", item.ident.to_string(), &item.attrs),
+            Item::Macro(item) => match &item.ident {
+                Some(ident) => ("macro", ident.to_string(), &item.attrs),
+                None => continue,
+            },
+            Item::Trait(item) => {
+                collect_from_trait_items(&item.items, found);
+                ("trait", item.ident.to_string(), &item.attrs)
+            }
+            Item::Mod(item) => {
+                if let Some((_, items)) = &item.content {
+                    collect_from_items(items, found);
+                }
+                ("mod", item.ident.to_string(), &item.attrs)
+            }
+            Item::Impl(item) => {
+                collect_from_impl_items(&item.items, found);
+                continue;
+            }
+            _ => continue,
+        };
+        push_if_deprecated(found, kind, name, attrs);
+    }
+}
+
+fn collect_from_impl_items(items: &[ImplItem], found: &mut Vec<Deprecation>) {
+    for item in items {
+        match item {
+            ImplItem::Fn(item) => {
+                push_if_deprecated(found, "fn", item.sig.ident.to_string(), &item.attrs)
+            }
+            ImplItem::Const(item) => {
+                push_if_deprecated(found, "const", item.ident.to_string(), &item.attrs)
+            }
+            ImplItem::Type(item) => {
+                push_if_deprecated(found, "type", item.ident.to_string(), &item.attrs)
+            }
+            _ => {}
+        }
+    }
+}
+
+fn collect_from_trait_items(items: &[TraitItem], found: &mut Vec<Deprecation>) {
+    for item in items {
+        match item {
+            TraitItem::Fn(item) => {
+                push_if_deprecated(found, "fn", item.sig.ident.to_string(), &item.attrs)
+            }
+            TraitItem::Const(item) => {
+                push_if_deprecated(found, "const", item.ident.to_string(), &item.attrs)
+            }
+            TraitItem::Type(item) => {
+                push_if_deprecated(found, "type", item.ident.to_string(), &item.attrs)
+            }
+            _ => {}
+        }
+    }
+}
+
+fn push_if_deprecated(
+    found: &mut Vec<Deprecation>,
+    kind: &'static str,
+    name: String,
+    attrs: &[Attribute],
+) {
+    if let Some(attr) = attrs.iter().find(|attr| attr.path().is_ident("deprecated")) {
+        found.push(Deprecation {
+            kind,
+            name,
+            note: deprecation_note(attr),
+        });
+    }
+}
+
+/// Reads the note of `#[deprecated = "note"]` or `#[deprecated(since, note)]`
+fn deprecation_note(attr: &Attribute) -> Option<String> {
+    let string_value = |expr: &syn::Expr| match expr {
+        syn::Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Str(value),
+            ..
+        }) => Some(value.value()),
+        _ => None,
+    };
+
+    match &attr.meta {
+        Meta::Path(_) => None,
+        Meta::NameValue(meta) => string_value(&meta.value),
+        Meta::List(_) => {
+            let mut since = None;
+            let mut note = None;
+            let _ = attr.parse_nested_meta(|meta| {
+                let value = meta.value()?.parse::<syn::Expr>()?;
+                if meta.path.is_ident("since") {
+                    since = string_value(&value);
+                } else if meta.path.is_ident("note") {
+                    note = string_value(&value);
+                }
+                Ok(())
+            });
+            match (since, note) {
+                (Some(since), Some(note)) => Some(format!("since {}, {}", since, note)),
+                (Some(since), None) => Some(format!("since {}", since)),
+                (None, note) => note,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_annotate_deprecations() -> std::io::Result<()> {
+        let temp_dir = tempdir()?;
+        fs::create_dir_all(temp_dir.path().join("src"))?;
+        fs::write(
+            temp_dir.path().join("src").join("lib.rs"),
+            r#"
+#[deprecated(since = "1.2.0", note = "use `parse` instead")]
+pub fn from_str(s: &str) -> Value { todo!() }
+
+pub fn parse(s: &str) -> Value { todo!() }
+
+pub struct Value;
+
+impl Value {
+    #[deprecated]
+    pub fn is_null(&self) -> bool { false }
+}
+"#,
+        )?;
+
+        let bank = "# Code Bank\n## src/lib.rs\n```rust\n#[deprecated(since = \"1.2.0\", note = \"use `parse` instead\")]\npub fn from_str(s: &str) -> Value { ... }\npub fn parse(s: &str) -> Value { ... }\npub struct Value;\nimpl Value {\n    pub fn is_null(&self) -> bool { ... }\n}\n```\n";
+        let annotated = annotate_deprecations(temp_dir.path(), bank);
+
+        assert_eq!(
+            annotated,
+            "# Code Bank\n## src/lib.rs\n```rust\n#[deprecated(since = \"1.2.0\", note = \"use `parse` instead\")]\n// ⚠ deprecated: since 1.2.0, use `parse` instead\npub fn from_str(s: &str) -> Value { ... }\npub fn parse(s: &str) -> Value { ... }\npub struct Value;\nimpl Value {\n    // ⚠ deprecated\n    pub fn is_null(&self) -> bool { ... }\n}\n```\n"
+        );
+
+        // Outline entries are marked in place
+        let outline = "## src/lib.rs\n\n- fn from_str\n- fn parse\n- impl Value\n  - fn is_null\n";
+        assert_eq!(
+            annotate_deprecations(temp_dir.path(), outline),
+            "## src/lib.rs\n\n- fn from_str (⚠ deprecated: since 1.2.0, use `parse` instead)\n- fn parse\n- impl Value\n  - fn is_null (⚠ deprecated)\n"
+        );
+
+        Ok(())
+    }
+}
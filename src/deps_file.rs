@@ -0,0 +1,153 @@
+//! Explicit lists of the dependencies to generate.
+//!
+//! CI systems often know which dependencies changed, e.g. from a diff of Cargo.lock,
+//! and only want those banks regenerated. A deps file lists them one per line, as
+//! `name` or `name@version`:
+//!
+//! ```text
+//! # Bumped in this change
+//! serde@1.0.219
+//! tokio
+//! ```
+//!
+//! The listed dependencies are matched against the resolved ones, so entries that the
+//! project does not depend on are left out rather than generated.
+
+use crate::{DependencyCollection, normalize_crate_name};
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+
+/// Reads a deps file listing dependencies as `name` or `name@version`.
+///
+/// Blank lines and lines starting with `#` are ignored.
+///
+/// # Arguments
+///
+/// * `path` - Path to the deps file
+///
+/// # Returns
+///
+/// * `Result<Vec<(String, Option<String>)>>` - The listed names, each with its
+///   version if one is given, in file order
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read, or if an entry has an empty name or
+/// version
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use depbank::load_deps_file;
+/// use std::path::Path;
+///
+/// for (name, version) in load_deps_file(Path::new("changed-deps.txt")).unwrap() {
+///     println!("{} {}", name, version.as_deref().unwrap_or("(any version)"));
+/// }
+/// ```
+pub fn load_deps_file(path: &Path) -> Result<Vec<(String, Option<String>)>> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read deps file: {}", path.display()))?;
+
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let (name, version) = match line.split_once('@') {
+                Some((name, version)) => (name.trim(), Some(version.trim())),
+                None => (line, None),
+            };
+            if name.is_empty() || version.is_some_and(str::is_empty) {
+                return Err(anyhow::anyhow!(
+                    "Invalid entry \"{}\" in deps file {}, expected name or name@version",
+                    line,
+                    path.display()
+                ));
+            }
+            Ok((name.to_string(), version.map(str::to_string)))
+        })
+        .collect()
+}
+
+/// Keeps the dependencies listed in a deps file, see [`load_deps_file`].
+///
+/// Names are compared after normalization, so `serde-json` matches `serde_json`. An
+/// entry with a version only matches that version.
+///
+/// # Arguments
+///
+/// * `dependencies` - The resolved dependencies
+/// * `entries` - The listed names, each with its version if one is given
+///
+/// # Returns
+///
+/// * `DependencyCollection` - The listed dependencies, in their original order
+///
+/// # Examples
+///
+/// ```rust
+/// use depbank::{Dependency, DependencyCollection, filter_to_deps_list};
+///
+/// let mut dependencies = DependencyCollection::new();
+/// dependencies.add(Dependency::new("serde", "1.0.219"));
+/// dependencies.add(Dependency::new("serde_json", "1.0.140"));
+/// dependencies.add(Dependency::new("tokio", "1.44.2"));
+/// let entries = vec![
+///     ("serde-json".to_string(), None),
+///     ("tokio".to_string(), Some("1.0.0".to_string())),
+/// ];
+///
+/// let listed = filter_to_deps_list(&dependencies, &entries);
+/// let names: Vec<&str> = listed.iter().map(|dep| dep.name.as_str()).collect();
+/// assert_eq!(names, vec!["serde_json"]);
+/// ```
+pub fn filter_to_deps_list(
+    dependencies: &DependencyCollection,
+    entries: &[(String, Option<String>)],
+) -> DependencyCollection {
+    let entries: Vec<(String, Option<&str>)> = entries
+        .iter()
+        .map(|(name, version)| (normalize_crate_name(name), version.as_deref()))
+        .collect();
+
+    let mut filtered = DependencyCollection::new();
+    for dependency in dependencies.iter() {
+        let name = normalize_crate_name(&dependency.name);
+        if entries.iter().any(|(listed, version)| {
+            *listed == name && version.is_none_or(|version| version == dependency.version)
+        }) {
+            filtered.add(dependency.clone());
+        }
+    }
+    filtered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_load_deps_file() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let path = temp_dir.path().join("deps.txt");
+        fs::write(&path, "# Changed\nserde@1.0.219\n\n  tokio  \n")?;
+
+        assert_eq!(
+            load_deps_file(&path)?,
+            vec![
+                ("serde".to_string(), Some("1.0.219".to_string())),
+                ("tokio".to_string(), None),
+            ]
+        );
+
+        fs::write(&path, "serde@\n")?;
+        assert!(load_deps_file(&path).is_err());
+        fs::write(&path, "@1.0.0\n")?;
+        assert!(load_deps_file(&path).is_err());
+
+        Ok(())
+    }
+}
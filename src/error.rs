@@ -0,0 +1,141 @@
+//! Errors callers may want to tell apart.
+//!
+//! Most failures in depbank are reported as [`anyhow::Error`] with context describing
+//! what was being done. The cases here are raised as typed errors instead, so that
+//! they can be recognized with [`anyhow::Error::downcast_ref`] and given a dedicated
+//! message or exit code.
+
+use std::path::PathBuf;
+use thiserror::Error;
+
+/// A specific depbank failure
+#[derive(Debug, Error)]
+pub enum DepBankError {
+    /// A custom tokenizer file could not be loaded
+    #[error("Failed to load tokenizer from {}: {reason}", path.display())]
+    TokenizerLoad {
+        /// Path to the tokenizer file
+        path: PathBuf,
+        /// Why the file could not be used as a tokenizer
+        reason: String,
+    },
+
+    /// A pretrained tokenizer model name is not known
+    #[error("Unknown pretrained tokenizer model '{name}': {reason}")]
+    UnknownTokenizerModel {
+        /// The requested model name
+        name: String,
+        /// Why the model could not be found
+        reason: String,
+    },
+
+    /// Dependencies are not available in the local Cargo registry
+    #[error("{count} dependencies are not available in the local Cargo registry")]
+    MissingDependencies {
+        /// Number of missing dependencies
+        count: usize,
+    },
+
+    /// Code banks could not be generated for some dependencies
+    #[error("Failed to generate code banks for {count} dependencies")]
+    GenerationFailed {
+        /// Number of dependencies whose generation failed
+        count: usize,
+    },
+
+    /// The generated code banks exceed the token budget
+    #[error("Generated code banks have {total} tokens, exceeding the budget of {budget}")]
+    TokenBudgetExceeded {
+        /// Total tokens of the generated banks
+        total: usize,
+        /// The token budget
+        budget: usize,
+    },
+
+    /// The run's deadline passed before every code bank was generated
+    #[error("Deadline of {deadline_secs}s reached, skipped {count} dependencies")]
+    DeadlineExceeded {
+        /// Number of dependencies that were not generated
+        count: usize,
+        /// The deadline, in seconds from the start of the run
+        deadline_secs: u64,
+    },
+
+    /// A crate is used at several versions and the conflict policy is to fail
+    #[error("{name} is used at conflicting versions {}", versions.join(", "))]
+    VersionConflict {
+        /// The name of the crate
+        name: String,
+        /// The versions the crate is used at, sorted
+        versions: Vec<String>,
+    },
+
+    /// A dependency declared without a version is not in Cargo.lock and the policy is
+    /// to fail
+    #[error("{name} has no version requirement ({version}) and is not in Cargo.lock")]
+    UnresolvableVersion {
+        /// The name of the dependency
+        name: String,
+        /// The declared version placeholder, `*` or `workspace`
+        version: String,
+    },
+
+    /// Local crates depend on each other in a cycle through path dependencies
+    #[error("dependency cycle: {}", cycle.join(" -> "))]
+    DependencyCycle {
+        /// The names of the crates in the cycle, starting and ending with the same crate
+        cycle: Vec<String>,
+    },
+}
+
+/// Process exit code for success
+pub const EXIT_SUCCESS: u8 = 0;
+/// Process exit code for errors without a more specific code
+pub const EXIT_FAILURE: u8 = 1;
+/// Process exit code when dependencies are missing from the local registry
+pub const EXIT_MISSING_DEPENDENCIES: u8 = 2;
+/// Process exit code when code bank generation failed for some dependencies
+pub const EXIT_GENERATION_FAILED: u8 = 3;
+/// Process exit code when the generated code banks exceed the token budget
+pub const EXIT_TOKEN_BUDGET_EXCEEDED: u8 = 4;
+/// Process exit code when the run's deadline passed before generation completed
+pub const EXIT_DEADLINE_EXCEEDED: u8 = 5;
+
+impl DepBankError {
+    /// Returns the process exit code reporting this error.
+    ///
+    /// These codes are a stable contract for CI integrations.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Self::MissingDependencies { .. } => EXIT_MISSING_DEPENDENCIES,
+            Self::GenerationFailed { .. } => EXIT_GENERATION_FAILED,
+            Self::TokenBudgetExceeded { .. } => EXIT_TOKEN_BUDGET_EXCEEDED,
+            Self::DeadlineExceeded { .. } => EXIT_DEADLINE_EXCEEDED,
+            Self::TokenizerLoad { .. }
+            | Self::UnknownTokenizerModel { .. }
+            | Self::VersionConflict { .. }
+            | Self::UnresolvableVersion { .. }
+            | Self::DependencyCycle { .. } => EXIT_FAILURE,
+        }
+    }
+}
+
+/// Returns the process exit code reporting an error.
+///
+/// Errors raised as a [`DepBankError`] map to their dedicated code, all others to
+/// [`EXIT_FAILURE`].
+///
+/// # Examples
+///
+/// ```rust
+/// use depbank::{DepBankError, EXIT_FAILURE, EXIT_MISSING_DEPENDENCIES, exit_code};
+///
+/// let error = anyhow::Error::from(DepBankError::MissingDependencies { count: 2 });
+/// assert_eq!(exit_code(&error), EXIT_MISSING_DEPENDENCIES);
+/// assert_eq!(exit_code(&anyhow::anyhow!("other")), EXIT_FAILURE);
+/// ```
+pub fn exit_code(error: &anyhow::Error) -> u8 {
+    error
+        .downcast_ref::<DepBankError>()
+        .map_or(EXIT_FAILURE, DepBankError::exit_code)
+}
@@ -0,0 +1,182 @@
+//! Macro expansion of dependencies with `cargo expand`.
+//!
+//! Part of some crates' public API is generated by macros, such as items declared
+//! through `macro_rules!` or derived trait impls, which a plain read of the sources
+//! misses. `cargo expand` builds the crate and prints its source after expansion,
+//! which can be documented instead. This is slow, as it compiles the crate, and needs
+//! the `cargo-expand` tool to be installed.
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::sync::OnceLock;
+use tempfile::TempDir;
+
+/// Whether `cargo expand` is installed, checked once per process
+static CARGO_EXPAND_AVAILABLE: OnceLock<bool> = OnceLock::new();
+
+/// Checks whether the `cargo expand` subcommand is installed.
+///
+/// The check runs `cargo expand --version` the first time only; later calls reuse
+/// its result.
+pub fn is_cargo_expand_available() -> bool {
+    *CARGO_EXPAND_AVAILABLE.get_or_init(|| {
+        Command::new("cargo")
+            .args(["expand", "--version"])
+            .output()
+            .is_ok_and(|output| output.status.success())
+    })
+}
+
+/// Expands the macros of a crate's library with `cargo expand`.
+///
+/// A copy of the crate is expanded, built in a temporary target directory, so cargo
+/// writes no Cargo.lock or build outputs into the sources, such as those of the cargo
+/// registry.
+///
+/// # Arguments
+///
+/// * `source_path` - Path to the crate's source directory, holding its Cargo.toml
+///
+/// # Returns
+///
+/// * `Result<String>` - The expanded source of the crate's library, as one file
+///
+/// # Errors
+///
+/// Returns an error if `cargo expand` is not installed, or if it fails, e.g. because
+/// the crate does not compile or its dependencies cannot be fetched
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use depbank::expand_crate;
+/// use std::path::Path;
+///
+/// let expanded = expand_crate(Path::new("/path/to/registry/serde-1.0.219")).unwrap();
+/// println!("Expanded to {} bytes", expanded.len());
+/// ```
+pub fn expand_crate(source_path: &Path) -> Result<String> {
+    if !is_cargo_expand_available() {
+        return Err(anyhow::anyhow!(
+            "cargo expand is not available, install it with `cargo install cargo-expand`"
+        ));
+    }
+
+    if !source_path.join("Cargo.toml").is_file() {
+        return Err(anyhow::anyhow!(
+            "Cannot expand a crate without a Cargo.toml: {}",
+            source_path.display()
+        ));
+    }
+
+    let staging_dir = TempDir::new().context("Failed to create staging directory")?;
+    copy_crate(source_path, staging_dir.path())?;
+    let manifest_path = staging_dir.path().join("Cargo.toml");
+    let target_dir = TempDir::new().context("Failed to create target directory")?;
+    let output = Command::new("cargo")
+        .arg("expand")
+        .arg("--lib")
+        .arg("--manifest-path")
+        .arg(&manifest_path)
+        .arg("--target-dir")
+        .arg(target_dir.path())
+        .output()
+        .context("Failed to run cargo expand")?;
+
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "cargo expand failed for {}: {}",
+            source_path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    String::from_utf8(output.stdout).context("cargo expand printed invalid UTF-8")
+}
+
+/// Copies the files of a crate into a directory, leaving out its build outputs
+fn copy_crate(source: &Path, target: &Path) -> Result<()> {
+    let entries = fs::read_dir(source)
+        .with_context(|| format!("Failed to read directory: {}", source.display()))?;
+    for entry in entries {
+        let entry =
+            entry.with_context(|| format!("Failed to read directory: {}", source.display()))?;
+        let path = entry.path();
+        let copied = target.join(entry.file_name());
+        if path.is_dir() {
+            if entry.file_name() == "target" {
+                continue;
+            }
+            fs::create_dir_all(&copied)
+                .with_context(|| format!("Failed to create directory: {}", copied.display()))?;
+            copy_crate(&path, &copied)?;
+        } else {
+            fs::copy(&path, &copied)
+                .with_context(|| format!("Failed to copy file: {}", path.display()))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_copy_crate() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let crate_dir = temp_dir.path().join("copied-1.0.0");
+        fs::create_dir_all(crate_dir.join("src").join("nested"))?;
+        fs::create_dir_all(crate_dir.join("target").join("debug"))?;
+        fs::write(
+            crate_dir.join("Cargo.toml"),
+            "[package]\nname = \"copied\"\n",
+        )?;
+        fs::write(crate_dir.join("src").join("nested").join("mod.rs"), "")?;
+        fs::write(crate_dir.join("target").join("debug").join("out"), "")?;
+
+        let copy = temp_dir.path().join("copy");
+        fs::create_dir_all(&copy)?;
+        copy_crate(&crate_dir, &copy)?;
+
+        assert!(copy.join("Cargo.toml").is_file());
+        assert!(copy.join("src").join("nested").join("mod.rs").is_file());
+        assert!(!copy.join("target").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_expand_crate() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let crate_dir = temp_dir.path().join("generated-1.0.0");
+        fs::create_dir_all(crate_dir.join("src"))?;
+        fs::write(
+            crate_dir.join("Cargo.toml"),
+            "[package]\nname = \"generated\"\nversion = \"1.0.0\"\nedition = \"2021\"\n\n[workspace]\n",
+        )?;
+        fs::write(
+            crate_dir.join("src").join("lib.rs"),
+            "macro_rules! getter {\n    ($name:ident) => {\n        pub fn $name() -> u32 { 1 }\n    };\n}\ngetter!(generated_value);\n",
+        )?;
+
+        if !is_cargo_expand_available() {
+            // Without the tool, the error says how to install it
+            let err = expand_crate(&crate_dir).unwrap_err();
+            assert!(err.to_string().contains("cargo install cargo-expand"));
+            return Ok(());
+        }
+
+        let expanded = expand_crate(&crate_dir)?;
+        assert!(expanded.contains("pub fn generated_value() -> u32"));
+        // Cargo wrote nothing into the sources
+        assert!(!crate_dir.join("Cargo.lock").exists());
+        assert!(!crate_dir.join("target").exists());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,1307 @@
+//! Post-processing filters for generated code bank markdown.
+//!
+//! Code banks produced by codebank are markdown documents where each source file
+//! becomes a `## <path>` section holding a fenced `rust` block of items. The filters
+//! in this module operate on that shape line by line, so they work on any bank
+//! regardless of how it was generated.
+
+/// Removes items that are not part of a crate's real public API from a code bank.
+///
+/// An item is considered hidden when it is annotated with `#[doc(hidden)]` or has a
+/// restricted visibility such as `pub(crate)`, `pub(super)` or `pub(in path)`. The
+/// item is removed together with its doc comments and attributes, and for items with
+/// a body (modules, impl blocks, multi-line functions) everything up to the matching
+/// closing brace is removed as well.
+///
+/// Only lines inside fenced `rust` code blocks are considered; prose and other
+/// sections are left untouched.
+///
+/// # Arguments
+///
+/// * `markdown` - The generated code bank content
+///
+/// # Returns
+///
+/// * `String` - The code bank with hidden items removed
+///
+/// # Examples
+///
+/// ```rust
+/// use depbank::strip_hidden_items;
+///
+/// let bank = "## src/lib.rs\n```rust\n#[doc(hidden)]\npub fn internal() { ... }\npub fn api() { ... }\n```\n";
+/// let filtered = strip_hidden_items(bank);
+/// assert!(!filtered.contains("internal"));
+/// assert!(filtered.contains("pub fn api()"));
+/// ```
+pub fn strip_hidden_items(markdown: &str) -> String {
+    remove_items(markdown, |preamble, line| {
+        preamble.iter().any(|l| is_doc_hidden(l)) || has_restricted_visibility(line)
+    })
+}
+
+/// Removes re-exports of standard library items from a code bank.
+///
+/// Crates sometimes re-export items from `std`, `core` or `alloc` (for example
+/// `pub use std::collections::HashMap;`), which adds noise to a bank without telling
+/// anything about the crate's own API. This removes such `pub use` statements,
+/// including grouped and multi-line ones, together with their doc comments and
+/// attributes.
+///
+/// Only lines inside fenced `rust` code blocks are considered; prose and other
+/// sections are left untouched.
+///
+/// # Arguments
+///
+/// * `markdown` - The generated code bank content
+///
+/// # Returns
+///
+/// * `String` - The code bank with standard library re-exports removed
+///
+/// # Examples
+///
+/// ```rust
+/// use depbank::strip_std_reexports;
+///
+/// let bank = "## src/lib.rs\n```rust\npub use std::collections::HashMap;\npub use crate::map::Map;\n```\n";
+/// let filtered = strip_std_reexports(bank);
+/// assert!(!filtered.contains("HashMap"));
+/// assert!(filtered.contains("pub use crate::map::Map;"));
+/// ```
+pub fn strip_std_reexports(markdown: &str) -> String {
+    remove_items(markdown, |_, line| is_std_reexport(line))
+}
+
+bitflags::bitflags! {
+    /// Kinds of items kept in code banks by [`filter_item_kinds`]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct ItemKinds: u8 {
+        /// `struct` and `union` definitions
+        const STRUCTS = 1;
+        /// `enum` definitions
+        const ENUMS = 1 << 1;
+        /// `trait` definitions
+        const TRAITS = 1 << 2;
+        /// Functions, including methods in impl blocks and traits
+        const FUNCTIONS = 1 << 3;
+        /// `macro_rules!` macros
+        const MACROS = 1 << 4;
+    }
+}
+
+impl Default for ItemKinds {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+impl ItemKinds {
+    /// The kind of an item, from its outline keyword, if it is one of the kinds
+    fn of_outline_kind(kind: &str) -> Option<Self> {
+        match kind {
+            "struct" | "union" => Some(Self::STRUCTS),
+            "enum" => Some(Self::ENUMS),
+            "trait" => Some(Self::TRAITS),
+            "fn" => Some(Self::FUNCTIONS),
+            "macro" => Some(Self::MACROS),
+            _ => None,
+        }
+    }
+}
+
+/// Keeps only the structs, enums, traits, functions and macros of the given kinds in
+/// a code bank.
+///
+/// Items are removed together with their doc comments, attributes and bodies. Other
+/// items, such as modules, impl blocks, constants or re-exports, are always kept, so
+/// the methods of kept impl blocks are still filtered by [`ItemKinds::FUNCTIONS`].
+///
+/// Only lines inside fenced `rust` code blocks are considered; prose and other
+/// sections are left untouched.
+///
+/// # Arguments
+///
+/// * `markdown` - The generated code bank content
+/// * `kinds` - The kinds of items to keep
+///
+/// # Returns
+///
+/// * `String` - The code bank with only the requested kinds of items
+///
+/// # Examples
+///
+/// ```rust
+/// use depbank::{ItemKinds, filter_item_kinds};
+///
+/// let bank = "## src/lib.rs\n```rust\npub struct Config;\npub fn load() -> Config { ... }\n```\n";
+/// let filtered = filter_item_kinds(bank, ItemKinds::STRUCTS);
+/// assert!(filtered.contains("pub struct Config;"));
+/// assert!(!filtered.contains("fn load"));
+/// ```
+pub fn filter_item_kinds(markdown: &str, kinds: ItemKinds) -> String {
+    remove_items(markdown, |_, line| {
+        outline_item(line.trim())
+            .and_then(|(kind, _)| ItemKinds::of_outline_kind(kind))
+            .is_some_and(|kind| !kinds.contains(kind))
+    })
+}
+
+/// Reduces a code bank to an outline of its modules and item names.
+///
+/// Each source file section becomes a nested list of the items it declares, such as
+/// `- fn parse` or `- struct Config`. Items inside modules, impl blocks and traits
+/// are nested below them, while signatures, bodies, fields and doc comments are left
+/// out. This is the most compact representation of a dependency, meant for
+/// navigation rather than for reading its API.
+///
+/// Sections without any items, such as the package file, are dropped.
+///
+/// # Arguments
+///
+/// * `markdown` - The generated code bank content
+///
+/// # Returns
+///
+/// * `String` - The outline as markdown
+///
+/// # Examples
+///
+/// ```rust
+/// use depbank::extract_outline;
+///
+/// let bank = "## src/lib.rs\n```rust\n/// Docs\npub mod de {\n    pub fn from_str(s: &str) -> Value { ... }\n}\n```\n";
+/// assert_eq!(extract_outline(bank), "## src/lib.rs\n\n- mod de\n  - fn from_str\n");
+/// ```
+pub fn extract_outline(markdown: &str) -> String {
+    let lines: Vec<&str> = markdown.lines().collect();
+    let mut output = String::new();
+    let mut in_code_block = false;
+    let mut in_rust_block = false;
+    // Heading of the current file section, written once it turns out to hold items
+    let mut pending_heading: Option<&str> = None;
+    let mut depth: i64 = 0;
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+        let trimmed = line.trim();
+
+        if trimmed.starts_with("```") {
+            in_code_block = !in_code_block;
+            in_rust_block = in_code_block && is_rust_fence(trimmed);
+            depth = 0;
+            i += 1;
+            continue;
+        }
+
+        if !in_rust_block {
+            if in_code_block {
+                // Other code blocks, such as the package file, are not outlined
+            } else if trimmed.starts_with("# ") {
+                push_line(&mut output, trimmed);
+            } else if trimmed.starts_with("## ") {
+                pending_heading = Some(trimmed);
+            }
+            i += 1;
+            continue;
+        }
+
+        if is_preamble_line(trimmed) || trimmed.starts_with("//") {
+            i += 1;
+            continue;
+        }
+
+        let Some((kind, name)) = outline_item(trimmed) else {
+            depth = (depth + brace_balance(trimmed)).max(0);
+            i += 1;
+            continue;
+        };
+
+        if let Some(heading) = pending_heading.take() {
+            if !output.is_empty() {
+                output.push('\n');
+            }
+            push_line(&mut output, heading);
+            output.push('\n');
+        }
+        let indent = "  ".repeat(depth as usize);
+        push_line(&mut output, &format!("{}- {} {}", indent, kind, name));
+
+        if matches!(kind, "mod" | "impl" | "trait") {
+            // Containers list their items nested below them
+            depth = (depth + brace_balance(trimmed)).max(0);
+            i += 1;
+        } else {
+            // Skip the rest of the item, such as fields, variants or a body
+            i = item_end(&lines, i) + 1;
+        }
+    }
+
+    output
+}
+
+/// Reduces a code bank to the prose of its doc comments.
+///
+/// Headings are kept, and the `///` and `//!` comments of each file section become
+/// plain markdown paragraphs, while signatures, bodies and attributes are dropped.
+/// Each item's docs are labeled with its kind and name, such as `` **`fn parse`** ``,
+/// so the text still reads as a narrative of the dependency's API. Headings inside
+/// doc comments are nested below the file sections, and hidden doctest lines are
+/// left out.
+///
+/// Sections without any doc comments, such as the package file, are dropped.
+///
+/// # Arguments
+///
+/// * `markdown` - The generated code bank content
+///
+/// # Returns
+///
+/// * `String` - The doc comments as markdown
+///
+/// # Examples
+///
+/// ```rust
+/// use depbank::extract_docs_only;
+///
+/// let bank = "## src/lib.rs\n```rust\n/// Parses a value.\npub fn parse(s: &str) -> Value { ... }\n```\n";
+/// assert_eq!(
+///     extract_docs_only(bank),
+///     "## src/lib.rs\n\n**`fn parse`**\n\nParses a value.\n"
+/// );
+/// ```
+pub fn extract_docs_only(markdown: &str) -> String {
+    let mut output = String::new();
+    let mut in_code_block = false;
+    let mut in_rust_block = false;
+    // Heading of the current file section, written once it turns out to hold docs
+    let mut pending_heading: Option<&str> = None;
+    // Doc comment lines seen since the last item, and whether they are inner docs
+    let mut docs: Vec<&str> = Vec::new();
+    let mut inner_docs = false;
+
+    for line in markdown.lines() {
+        let trimmed = line.trim();
+
+        if trimmed.starts_with("```") {
+            push_docs(&mut output, &mut pending_heading, None, &docs);
+            docs.clear();
+            in_code_block = !in_code_block;
+            in_rust_block = in_code_block && is_rust_fence(trimmed);
+            continue;
+        }
+
+        if !in_code_block {
+            if trimmed.starts_with("# ") {
+                push_line(&mut output, trimmed);
+            } else if trimmed.starts_with("## ") {
+                pending_heading = Some(trimmed);
+            }
+            continue;
+        }
+
+        if !in_rust_block {
+            continue;
+        }
+
+        let (doc, inner) = if let Some(doc) = trimmed.strip_prefix("//!") {
+            (Some(doc), true)
+        } else {
+            (trimmed.strip_prefix("///"), false)
+        };
+
+        match doc {
+            Some(doc) => {
+                if inner != inner_docs {
+                    // Inner and outer docs are separate paragraphs
+                    push_docs(&mut output, &mut pending_heading, None, &docs);
+                    docs.clear();
+                    inner_docs = inner;
+                }
+                docs.push(doc.strip_prefix(' ').unwrap_or(doc));
+            }
+            // Attributes sit between an item's docs and its declaration
+            None if trimmed.starts_with("#[") => {}
+            None => {
+                if !docs.is_empty() {
+                    let label = if inner_docs {
+                        None
+                    } else {
+                        outline_item(trimmed)
+                    };
+                    push_docs(&mut output, &mut pending_heading, label, &docs);
+                    docs.clear();
+                }
+            }
+        }
+    }
+    push_docs(&mut output, &mut pending_heading, None, &docs);
+
+    output
+}
+
+/// Line endings to use in generated code banks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LineEndings {
+    /// Unix line endings (`\n`)
+    #[default]
+    Lf,
+    /// Windows line endings (`\r\n`)
+    Crlf,
+    /// The line endings of the current platform
+    Native,
+}
+
+/// Drops the file sections of a code bank nested deeper than a directory depth.
+///
+/// The depth of a `## <path>` section is the number of directories in its path,
+/// relative to the crate root: `src/lib.rs` is at depth 1 and `src/de/value.rs` at
+/// depth 2. A bank limited to depth 1 thus only documents a crate's top-level
+/// modules, giving a shallow overview of a huge crate. Sections whose heading is not
+/// a path, such as the package file, and content before the first section are kept.
+///
+/// # Arguments
+///
+/// * `markdown` - The generated code bank content
+/// * `max_depth` - The deepest directory depth of the sections to keep
+///
+/// # Returns
+///
+/// * `String` - The code bank without the deeper sections
+///
+/// # Examples
+///
+/// ```rust
+/// use depbank::limit_section_depth;
+///
+/// let bank = "## src/lib.rs\n```rust\npub mod de;\n```\n## src/de/value.rs\n```rust\npub enum Value {}\n```\n";
+/// assert_eq!(limit_section_depth(bank, 1), "## src/lib.rs\n```rust\npub mod de;\n```\n");
+/// ```
+pub fn limit_section_depth(markdown: &str, max_depth: usize) -> String {
+    let mut output = String::new();
+    let mut in_code_block = false;
+    let mut dropping = false;
+
+    for line in markdown.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") {
+            in_code_block = !in_code_block;
+        } else if !in_code_block && trimmed.starts_with('#') {
+            dropping = trimmed
+                .strip_prefix("## ")
+                .is_some_and(|path| section_depth(path) > max_depth);
+        }
+
+        if !dropping {
+            push_line(&mut output, line);
+        }
+    }
+
+    output
+}
+
+/// The number of directories in a section's path, 0 for headings that are not paths
+fn section_depth(path: &str) -> usize {
+    if path.contains(' ') {
+        return 0;
+    }
+    path.matches('/').count()
+}
+
+/// Shifts the level of every markdown heading of a code bank.
+///
+/// This lets a bank be embedded in a larger document, below that document's own
+/// headings: with an offset of 2, the `# Code Bank` title becomes `### Code Bank`.
+/// Only ATX headings outside code blocks are shifted, so `#` comments and attributes
+/// in code are kept as they are. Markdown has six heading levels, so deeper headings
+/// stay at level 6.
+///
+/// # Arguments
+///
+/// * `markdown` - The generated code bank content
+/// * `offset` - How many levels to shift the headings down by
+///
+/// # Returns
+///
+/// * `String` - The code bank with shifted headings
+///
+/// # Examples
+///
+/// ```rust
+/// use depbank::shift_headings;
+///
+/// let bank = "# Code Bank\n## src/main.sh\n```sh\n# Not a heading\n```\n";
+/// assert_eq!(
+///     shift_headings(bank, 2),
+///     "### Code Bank\n#### src/main.sh\n```sh\n# Not a heading\n```\n"
+/// );
+/// ```
+pub fn shift_headings(markdown: &str, offset: usize) -> String {
+    if offset == 0 {
+        return markdown.to_string();
+    }
+
+    let mut output = String::with_capacity(markdown.len());
+    // The character and length of the fence that opened the current code block
+    let mut open_fence: Option<(char, usize)> = None;
+    for line in markdown.lines() {
+        let fence = code_fence(line.trim_start());
+        if let Some((fence_char, fence_len)) = fence {
+            match open_fence {
+                None => open_fence = Some((fence_char, fence_len)),
+                // Only a fence of the same character, at least as long and without an
+                // info string, closes the block
+                Some((open_char, open_len))
+                    if fence_char == open_char
+                        && fence_len >= open_len
+                        && line.trim()[fence_len..].is_empty() =>
+                {
+                    open_fence = None
+                }
+                Some(_) => {}
+            }
+        } else if open_fence.is_none()
+            && let Some(level) = heading_level(line)
+        {
+            output.push_str(&"#".repeat((level + offset).min(MAX_HEADING_LEVEL)));
+            push_line(&mut output, &line[level..]);
+            continue;
+        }
+        push_line(&mut output, line);
+    }
+
+    output
+}
+
+/// The character and length of a code fence line, such as `` ('`', 3) `` for ```` ```rust ````
+fn code_fence(trimmed: &str) -> Option<(char, usize)> {
+    let fence_char = trimmed.chars().next().filter(|&c| c == '`' || c == '~')?;
+    let len = trimmed.chars().take_while(|&c| c == fence_char).count();
+    (len >= 3).then_some((fence_char, len))
+}
+
+/// The deepest markdown heading level
+const MAX_HEADING_LEVEL: usize = 6;
+
+/// The level of an ATX heading line, such as 2 for `## src/lib.rs`
+fn heading_level(line: &str) -> Option<usize> {
+    let level = line.bytes().take_while(|&byte| byte == b'#').count();
+    let rest = &line[level..];
+    ((1..=MAX_HEADING_LEVEL).contains(&level)
+        && (rest.is_empty() || rest.starts_with(' ') || rest.starts_with('\t')))
+    .then_some(level)
+}
+
+/// Converts all line endings in the content to the given style.
+///
+/// Both `\n` and `\r\n` line endings in the input are recognized, so content with
+/// mixed line endings ends up consistent.
+///
+/// # Arguments
+///
+/// * `content` - The content to normalize
+/// * `mode` - The line endings to use
+///
+/// # Returns
+///
+/// * `String` - The content with normalized line endings
+///
+/// # Examples
+///
+/// ```rust
+/// use depbank::{LineEndings, normalize_line_endings};
+///
+/// assert_eq!(normalize_line_endings("a\r\nb\n", LineEndings::Lf), "a\nb\n");
+/// assert_eq!(normalize_line_endings("a\nb\n", LineEndings::Crlf), "a\r\nb\r\n");
+/// ```
+pub fn normalize_line_endings(content: &str, mode: LineEndings) -> String {
+    let lf = content.replace("\r\n", "\n");
+
+    let crlf = match mode {
+        LineEndings::Lf => false,
+        LineEndings::Crlf => true,
+        LineEndings::Native => cfg!(windows),
+    };
+
+    if crlf { lf.replace('\n', "\r\n") } else { lf }
+}
+
+/// Inserts a note below a code bank's title, separated by blank lines.
+pub(crate) fn insert_header_note(markdown: &str, note: &str) -> String {
+    let (title, rest) = match markdown.split_once('\n') {
+        Some((title, rest)) => (title, rest.trim_start_matches('\n')),
+        None => (markdown, ""),
+    };
+
+    format!("{}\n\n{}\n\n{}", title, note, rest)
+}
+
+/// Removes every item for which `is_removed` returns true from a code bank.
+///
+/// The predicate receives the item's preamble (doc comments and attributes) and its
+/// first line. Removed items are dropped together with their preamble and body.
+fn remove_items<F>(markdown: &str, is_removed: F) -> String
+where
+    F: Fn(&[&str], &str) -> bool,
+{
+    let lines: Vec<&str> = markdown.lines().collect();
+    let mut output = String::with_capacity(markdown.len());
+    let mut in_rust_block = false;
+    // Doc comments and attributes seen since the last item, kept until we know
+    // whether the item they belong to is removed
+    let mut preamble: Vec<&str> = Vec::new();
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+        let trimmed = line.trim();
+
+        if trimmed.starts_with("```") {
+            flush_lines(&mut output, &preamble);
+            preamble.clear();
+            in_rust_block = !in_rust_block && is_rust_fence(trimmed);
+            push_line(&mut output, line);
+            i += 1;
+            continue;
+        }
+
+        if !in_rust_block {
+            push_line(&mut output, line);
+            i += 1;
+            continue;
+        }
+
+        if is_preamble_line(trimmed) {
+            preamble.push(line);
+            i += 1;
+            continue;
+        }
+
+        if is_removed(&preamble, line) {
+            // Skip the item (and its body, if any) along with its preamble
+            i = item_end(&lines, i) + 1;
+        } else {
+            flush_lines(&mut output, &preamble);
+            push_line(&mut output, line);
+            i += 1;
+        }
+        preamble.clear();
+    }
+
+    flush_lines(&mut output, &preamble);
+    output
+}
+
+/// Returns the index of the last line of the item starting at `start`.
+///
+/// The item ends on the first line where all opened brackets are closed again and the
+/// line terminates the item: with `}` or codebank's `{ ... }` placeholder for items
+/// with a body, or with `;` or `,` for statements, fields and variants. The scan never
+/// crosses the closing brace of the enclosing block or the end of the code block, so
+/// removing a member of a struct, enum or impl keeps the members after it.
+fn item_end(lines: &[&str], start: usize) -> usize {
+    let mut depth: i64 = 0;
+
+    for (offset, line) in lines[start..].iter().enumerate() {
+        let trimmed = line.trim();
+        if offset > 0 && trimmed.starts_with("```") {
+            return start + offset - 1;
+        }
+
+        depth += bracket_balance(trimmed);
+        if depth < 0 {
+            // The enclosing block closes without the item being terminated
+            return (start + offset).saturating_sub(1).max(start);
+        }
+
+        if depth == 0
+            && (trimmed.ends_with('}') || trimmed.ends_with(';') || trimmed.ends_with(','))
+        {
+            return start + offset;
+        }
+    }
+
+    lines.len() - 1
+}
+
+/// The number of brackets of any kind a line opens minus the number it closes
+fn bracket_balance(trimmed: &str) -> i64 {
+    trimmed
+        .chars()
+        .map(|c| match c {
+            '{' | '(' | '[' => 1,
+            '}' | ')' | ']' => -1,
+            _ => 0,
+        })
+        .sum()
+}
+
+/// Checks whether a fence line opens a rust code block
+pub(crate) fn is_rust_fence(trimmed: &str) -> bool {
+    trimmed.trim_start_matches('`').trim() == "rust"
+}
+
+/// Doc comments and outer attributes that belong to the following item
+fn is_preamble_line(trimmed: &str) -> bool {
+    trimmed.starts_with("///") || trimmed.starts_with("#[")
+}
+
+/// Checks for a `#[doc(hidden)]` attribute, ignoring whitespace differences
+fn is_doc_hidden(line: &str) -> bool {
+    let compact: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+    compact.starts_with("#[doc(hidden)]")
+}
+
+/// Checks whether an item line declares a visibility narrower than `pub`
+fn has_restricted_visibility(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    ["pub(crate)", "pub(super)", "pub(self)", "pub(in "]
+        .iter()
+        .any(|prefix| trimmed.starts_with(prefix))
+}
+
+/// Checks whether an item line re-exports something from the standard library
+fn is_std_reexport(line: &str) -> bool {
+    let Some(path) = line.trim_start().strip_prefix("pub use ") else {
+        return false;
+    };
+    let path = path.trim_start().trim_start_matches("::");
+
+    ["std", "core", "alloc"].iter().any(|root| {
+        path.strip_prefix(root)
+            .is_some_and(|rest| rest.starts_with("::") || rest.starts_with(';'))
+    })
+}
+
+/// Parses the kind and name of an item from its first line.
+///
+/// Visibility and qualifiers such as `async`, `unsafe` or `extern "C"` are skipped.
+/// Lines that do not start an item, such as struct fields, return `None`.
+pub(crate) fn outline_item(trimmed: &str) -> Option<(&'static str, String)> {
+    let mut rest = trimmed;
+    loop {
+        rest = rest.trim_start();
+        if let Some(after) = rest.strip_prefix("pub(") {
+            rest = after.split_once(')')?.1;
+        } else if let Some(after) = ["pub ", "default ", "async ", "unsafe ", "extern \"C\" "]
+            .iter()
+            .find_map(|qualifier| rest.strip_prefix(qualifier))
+        {
+            rest = after;
+        } else if rest.starts_with("const fn ") || rest.starts_with("const unsafe fn ") {
+            rest = &rest["const ".len()..];
+        } else {
+            break;
+        }
+    }
+
+    if let Some(name) = rest.strip_prefix("macro_rules!") {
+        return Some(("macro", identifier(name.trim_start())));
+    }
+
+    let (keyword, tail) = rest.split_once(char::is_whitespace)?;
+    let tail = tail.trim_start();
+    let kind = match keyword {
+        "fn" => "fn",
+        "struct" => "struct",
+        "enum" => "enum",
+        "union" => "union",
+        "trait" => "trait",
+        "type" => "type",
+        "mod" => "mod",
+        "const" => "const",
+        "static" => "static",
+        "impl" => "impl",
+        "use" => "use",
+        _ if keyword.starts_with("impl<") => "impl",
+        _ => return None,
+    };
+
+    let name = match kind {
+        "impl" => {
+            let target = if keyword == "impl" {
+                tail
+            } else {
+                // Drop the generic parameters of `impl<T> Trait for Type`
+                skip_generics(&rest["impl".len()..])
+            };
+            target
+                .split(" where ")
+                .next()
+                .unwrap_or(target)
+                .trim_end_matches("{ ... }")
+                .trim_end_matches('{')
+                .trim()
+                .to_string()
+        }
+        "use" => tail.trim_end_matches(';').trim().to_string(),
+        "static" => identifier(tail.strip_prefix("mut ").unwrap_or(tail)),
+        _ => identifier(tail),
+    };
+
+    (!name.is_empty()).then_some((kind, name))
+}
+
+/// Returns the leading identifier of a string
+fn identifier(s: &str) -> String {
+    s.chars()
+        .take_while(|c| c.is_alphanumeric() || *c == '_')
+        .collect()
+}
+
+/// Skips a leading balanced `<...>` group
+fn skip_generics(s: &str) -> &str {
+    let mut depth = 0;
+    for (index, c) in s.char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => {
+                depth -= 1;
+                if depth == 0 {
+                    return s[index + 1..].trim_start();
+                }
+            }
+            _ => {}
+        }
+    }
+    s
+}
+
+/// Net number of braces opened by a line
+fn brace_balance(trimmed: &str) -> i64 {
+    trimmed.matches('{').count() as i64 - trimmed.matches('}').count() as i64
+}
+
+fn push_line(output: &mut String, line: &str) {
+    output.push_str(line);
+    output.push('\n');
+}
+
+/// Writes a block of doc comment text as a paragraph, preceded by the pending section
+/// heading and the label of the item it documents
+fn push_docs(
+    output: &mut String,
+    pending_heading: &mut Option<&str>,
+    label: Option<(&'static str, String)>,
+    docs: &[&str],
+) {
+    if docs.iter().all(|doc| doc.trim().is_empty()) {
+        return;
+    }
+
+    if let Some(heading) = pending_heading.take() {
+        if !output.is_empty() {
+            output.push('\n');
+        }
+        push_line(output, heading);
+    }
+    output.push('\n');
+    if let Some((kind, name)) = label {
+        push_line(output, &format!("**`{} {}`**", kind, name));
+        output.push('\n');
+    }
+
+    let mut in_example = false;
+    for doc in docs {
+        let trimmed = doc.trim_start();
+        if trimmed.starts_with("```") {
+            in_example = !in_example;
+        } else if in_example && (trimmed == "#" || trimmed.starts_with("# ")) {
+            // Hidden doctest lines
+            continue;
+        } else if !in_example && trimmed.starts_with('#') {
+            // Doc headings go below the file section and item label
+            push_line(output, &format!("###{}", doc));
+            continue;
+        }
+        push_line(output, doc.trim_end());
+    }
+}
+
+fn flush_lines(output: &mut String, lines: &[&str]) {
+    for line in lines {
+        push_line(output, line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_hidden_items() {
+        let bank = r#"# Code Bank
+## src/lib.rs
+```rust
+/// A public function.
+pub fn visible() -> u32 { ... }
+/// Internal helper used by macros.
+#[doc(hidden)]
+#[inline]
+pub fn __private_helper() { ... }
+#[doc(hidden)]
+pub mod __private {
+    pub fn format_err() { ... }
+}
+pub(crate) fn crate_only() { ... }
+impl Error {
+    pub fn new() -> Self { ... }
+    #[doc(hidden)]
+    pub fn thiserror_provide<'a>(
+            &'a self,
+        ) { ... }
+    pub(super) fn parent_only(&self) { ... }
+}
+```
+#[doc(hidden)] outside of code stays
+"#;
+
+        let filtered = strip_hidden_items(bank);
+
+        // Hidden items and their preamble are removed
+        assert!(!filtered.contains("__private_helper"));
+        assert!(!filtered.contains("Internal helper used by macros"));
+        assert!(!filtered.contains("#[inline]"));
+        assert!(!filtered.contains("__private"));
+        assert!(!filtered.contains("format_err"));
+        assert!(!filtered.contains("crate_only"));
+        assert!(!filtered.contains("thiserror_provide"));
+        assert!(!filtered.contains("&'a self"));
+        assert!(!filtered.contains("parent_only"));
+
+        // Public items and the surrounding structure are preserved
+        assert!(filtered.contains("/// A public function."));
+        assert!(filtered.contains("pub fn visible() -> u32 { ... }"));
+        assert!(filtered.contains("impl Error {\n    pub fn new() -> Self { ... }\n}"));
+        assert!(filtered.contains("#[doc(hidden)] outside of code stays"));
+    }
+
+    #[test]
+    fn test_strip_std_reexports() {
+        let bank = r#"# Code Bank
+## src/lib.rs
+```rust
+/// Re-exported for convenience.
+pub use std::collections::HashMap;
+pub use ::core::fmt::Debug;
+pub use alloc::{
+    string::String,
+    vec::Vec,
+};
+pub use crate::map::OrderedMap;
+pub use stdext::Extension;
+pub fn new_map() -> HashMap<String, String> { ... }
+```
+"#;
+
+        let filtered = strip_std_reexports(bank);
+
+        assert!(!filtered.contains("pub use std::collections::HashMap;"));
+        assert!(!filtered.contains("Re-exported for convenience."));
+        assert!(!filtered.contains("Debug"));
+        assert!(!filtered.contains("alloc"));
+        assert!(!filtered.contains("vec::Vec"));
+
+        assert!(filtered.contains("pub use crate::map::OrderedMap;"));
+        assert!(filtered.contains("pub use stdext::Extension;"));
+        assert!(filtered.contains("pub fn new_map() -> HashMap<String, String> { ... }"));
+    }
+
+    #[test]
+    fn test_extract_outline() {
+        let bank = r#"# Code Bank
+
+## Package File
+```toml
+# Automatically generated by cargo
+[package]
+name = "demo"
+```
+
+## src/lib.rs
+```rust
+//! Crate docs.
+/// A configuration.
+#[derive(Debug)]
+pub struct Config {
+    pub name: String,
+}
+pub mod de {
+    /// Parses a value.
+    pub fn from_str(s: &str) -> Result<Value, Error> { ... }
+    pub(crate) const MAX_DEPTH: usize = 128;
+}
+impl<T: Clone> Display for Wrapper<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result { ... }
+}
+pub trait Visitor {
+    type Value;
+    fn visit_str(
+        &self,
+        v: &str,
+    ) -> Self::Value { ... }
+}
+pub use crate::de::from_str;
+pub async unsafe fn run() { ... }
+macro_rules! ensure { ... }
+```
+
+## src/error.rs
+```rust
+pub enum Error {
+    Io(std::io::Error),
+    Syntax,
+}
+```
+"#;
+
+        let outline = extract_outline(bank);
+
+        assert_eq!(
+            outline,
+            "# Code Bank
+
+## src/lib.rs
+
+- struct Config
+- mod de
+  - fn from_str
+  - const MAX_DEPTH
+- impl Display for Wrapper<T>
+  - fn fmt
+- trait Visitor
+  - type Value
+  - fn visit_str
+- use crate::de::from_str
+- fn run
+- macro ensure
+
+## src/error.rs
+
+- enum Error
+"
+        );
+
+        // Only names remain, no signatures, fields or docs
+        assert!(!outline.contains("->"));
+        assert!(!outline.contains("pub name"));
+        assert!(!outline.contains("Parses a value"));
+        assert!(!outline.contains("Package File"));
+    }
+
+    #[test]
+    fn test_insert_header_note() {
+        assert_eq!(
+            insert_header_note("# Code Bank\n\n## src/lib.rs\n", "> Note"),
+            "# Code Bank\n\n> Note\n\n## src/lib.rs\n"
+        );
+        assert_eq!(
+            insert_header_note("# Code Bank", "> Note"),
+            "# Code Bank\n\n> Note\n\n"
+        );
+    }
+
+    #[test]
+    fn test_filter_item_kinds_excludes_macros() {
+        let bank = r#"# Code Bank
+## src/lib.rs
+```rust
+/// Creates a map.
+#[macro_export]
+macro_rules! map {
+    ($($key:expr => $value:expr),*) => {{ ... }};
+}
+
+/// A configuration.
+pub struct Config {
+    pub name: String,
+}
+
+impl Config {
+    pub fn new() -> Self { ... }
+}
+
+pub enum Mode { Fast, Slow }
+
+pub trait Load {
+    fn load(&self) -> Config;
+}
+```
+"#;
+
+        let filtered = filter_item_kinds(bank, ItemKinds::all() - ItemKinds::MACROS);
+        assert!(!filtered.contains("macro_rules!"));
+        assert!(!filtered.contains("Creates a map"));
+        assert!(!filtered.contains("#[macro_export]"));
+        assert!(filtered.contains("/// A configuration.\npub struct Config {"));
+        assert!(filtered.contains("pub fn new() -> Self { ... }"));
+        assert!(filtered.contains("pub enum Mode"));
+        assert!(filtered.contains("pub trait Load"));
+
+        // Only structs and traits, impl blocks are kept without their methods
+        let filtered = filter_item_kinds(bank, ItemKinds::STRUCTS | ItemKinds::TRAITS);
+        assert!(filtered.contains("pub struct Config"));
+        assert!(filtered.contains("pub trait Load"));
+        assert!(filtered.contains("impl Config {"));
+        assert!(!filtered.contains("fn new"));
+        assert!(!filtered.contains("fn load"));
+        assert!(!filtered.contains("enum Mode"));
+
+        // All kinds keep the bank as it is
+        assert_eq!(filter_item_kinds(bank, ItemKinds::default()), bank);
+    }
+
+    #[test]
+    fn test_limit_section_depth() {
+        let bank = r#"# Code Bank
+
+## Package File
+```toml
+[package]
+name = "huge"
+```
+
+## build.rs
+```rust
+fn main() {}
+```
+
+## src/lib.rs
+```rust
+pub mod de;
+```
+
+## src/de/mod.rs
+```rust
+pub mod value;
+```
+
+## src/de/value/number.rs
+```rust
+// ## src/not/a/heading.rs
+pub struct Number;
+```
+"#;
+
+        let limited = limit_section_depth(bank, 1);
+        assert!(limited.contains("## Package File"));
+        assert!(limited.contains("fn main() {}"));
+        assert!(limited.contains("pub mod de;"));
+        assert!(!limited.contains("src/de/mod.rs"));
+        assert!(!limited.contains("pub mod value;"));
+        assert!(!limited.contains("Number"));
+
+        let limited = limit_section_depth(bank, 2);
+        assert!(limited.contains("pub mod value;"));
+        assert!(!limited.contains("Number"));
+
+        // The top-level modules are at depth 1, files at the crate root at depth 0
+        let limited = limit_section_depth(bank, 0);
+        assert!(limited.contains("fn main() {}"));
+        assert!(!limited.contains("pub mod de;"));
+
+        assert_eq!(limit_section_depth(bank, 3), bank);
+    }
+
+    #[test]
+    fn test_normalize_line_endings() {
+        let mixed = "# Code Bank\r\n\n```rust\npub fn api() { ... }\r\n```\n";
+
+        assert_eq!(
+            normalize_line_endings(mixed, LineEndings::Lf),
+            "# Code Bank\n\n```rust\npub fn api() { ... }\n```\n"
+        );
+        assert_eq!(
+            normalize_line_endings(mixed, LineEndings::Crlf),
+            "# Code Bank\r\n\r\n```rust\r\npub fn api() { ... }\r\n```\r\n"
+        );
+
+        let expected = if cfg!(windows) {
+            LineEndings::Crlf
+        } else {
+            LineEndings::Lf
+        };
+        assert_eq!(
+            normalize_line_endings(mixed, LineEndings::Native),
+            normalize_line_endings(mixed, expected)
+        );
+    }
+
+    #[test]
+    fn test_extract_docs_only() {
+        let bank = r#"# Code Bank
+
+## Package File
+```toml
+[package]
+name = "demo"
+```
+
+## src/lib.rs
+```rust
+//! Crate docs.
+/// Parses a value from a string.
+///
+/// # Examples
+///
+/// ```
+/// # use demo::parse;
+/// let value = parse("1");
+/// ```
+#[inline]
+pub fn parse(s: &str) -> Result<Value, Error> { ... }
+pub struct Value;
+impl Value {
+    /// Checks whether the value is null.
+    pub fn is_null(&self) -> bool { ... }
+}
+```
+
+## src/error.rs
+```rust
+pub enum Error {
+    Syntax,
+}
+```
+"#;
+
+        let docs = extract_docs_only(bank);
+
+        assert_eq!(
+            docs,
+            r#"# Code Bank
+
+## src/lib.rs
+
+Crate docs.
+
+**`fn parse`**
+
+Parses a value from a string.
+
+#### Examples
+
+```
+let value = parse("1");
+```
+
+**`fn is_null`**
+
+Checks whether the value is null.
+"#
+        );
+        assert!(!docs.contains("pub fn parse(s: &str)"));
+        assert!(!docs.contains("src/error.rs"));
+    }
+
+    #[test]
+    fn test_strip_hidden_members_keeps_following_members() {
+        let bank = r#"# Code Bank
+## src/lib.rs
+```rust
+pub struct Config {
+    pub name: String,
+    pub(crate) cache: Cache,
+    pub retries: u32,
+}
+pub enum Mode {
+    Fast,
+    #[doc(hidden)]
+    __Internal { id: u32 },
+    Slow(u32),
+}
+impl Config {
+    pub(crate) fn reset(&mut self) { ... }
+    pub fn build(
+        self,
+    ) -> Client { ... }
+}
+pub fn after() { ... }
+```
+"#;
+
+        let filtered = strip_hidden_items(bank);
+
+        assert!(!filtered.contains("cache"));
+        assert!(!filtered.contains("__Internal"));
+        assert!(!filtered.contains("reset"));
+        assert!(
+            filtered
+                .contains("pub struct Config {\n    pub name: String,\n    pub retries: u32,\n}\n")
+        );
+        assert!(filtered.contains("pub enum Mode {\n    Fast,\n    Slow(u32),\n}\n"));
+        assert!(filtered.contains(
+            "impl Config {\n    pub fn build(\n        self,\n    ) -> Client { ... }\n}\n"
+        ));
+        assert!(filtered.contains("pub fn after() { ... }"));
+    }
+
+    #[test]
+    fn test_strip_std_reexports_inside_module() {
+        // A removed item that is not the last member leaves the rest of the block
+        let bank = "## src/lib.rs\n```rust\npub mod prelude {\n    pub use std::fmt::Debug;\n    pub use crate::Config;\n}\npub fn after() { ... }\n```\n";
+
+        assert_eq!(
+            strip_std_reexports(bank),
+            "## src/lib.rs\n```rust\npub mod prelude {\n    pub use crate::Config;\n}\npub fn after() { ... }\n```\n"
+        );
+    }
+
+    #[test]
+    fn test_strip_hidden_items_without_hidden_items() {
+        let bank = "# Code Bank\n## src/lib.rs\n```rust\n/// Docs\npub fn api() { ... }\n```\n";
+        assert_eq!(strip_hidden_items(bank), bank);
+    }
+
+    #[test]
+    fn test_shift_headings() {
+        let bank = r#"# Code Bank
+## src/lib.rs
+Headings in docs:
+##### Deep
+```rust
+# fn hidden_doctest_line() {}
+#[derive(Debug)]
+pub struct Config;
+```
+## build.rs
+~~~sh
+# comment
+~~~
+#hashtag
+"#;
+
+        assert_eq!(
+            shift_headings(bank, 2),
+            r#"### Code Bank
+#### src/lib.rs
+Headings in docs:
+###### Deep
+```rust
+# fn hidden_doctest_line() {}
+#[derive(Debug)]
+pub struct Config;
+```
+#### build.rs
+~~~sh
+# comment
+~~~
+#hashtag
+"#
+        );
+        assert_eq!(shift_headings(bank, 0), bank);
+    }
+
+    #[test]
+    fn test_shift_headings_nested_fences() {
+        // Fences of the other character or shorter ones do not close a block
+        let bank =
+            "# Bank\n````md\n```rust\n# inside\n```\n````\n```text\n~~~\n# inside\n```\n## After\n";
+
+        assert_eq!(
+            shift_headings(bank, 1),
+            "## Bank\n````md\n```rust\n# inside\n```\n````\n```text\n~~~\n# inside\n```\n### After\n"
+        );
+    }
+}
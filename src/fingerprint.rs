@@ -0,0 +1,398 @@
+//! A stable fingerprint of a project's dependencies and generation options.
+//!
+//! External caches, such as CI artifact caches, can key generated code banks on the
+//! fingerprint: as long as it is unchanged, so are the banks. It is computed from a
+//! canonical text listing the resolved dependencies and the options that change the
+//! generated content, hashed with 64-bit FNV-1a, whose output does not depend on the
+//! platform or the Rust version.
+//!
+//! Registry crates never change once published, so their name, version and source
+//! stand for their content. Local crates, such as workspace members or patches to a
+//! path, do change, so their sources are hashed too.
+
+use crate::{DependencyCollection, GenerateOptions};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Version of the canonical fingerprint text, bumped whenever its layout changes
+const FINGERPRINT_FORMAT: &str = "depbank-fingerprint-v2";
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+/// Computes a fingerprint of the resolved dependencies and the generation options.
+///
+/// The fingerprint covers each dependency's name, version and source, regardless of
+/// their order or duplicates, the sources of dependencies with a local `file://`
+/// source (see [`source_fingerprint`]), the options changing the content of the
+/// banks, and the DepBank version, since generated banks can differ between
+/// releases. Options that only affect how a run proceeds (`resume`, `max_memory_mb`
+/// and `deadline`) are left out, and so is the `post_process` hook, which cannot be
+/// compared: callers using one should pass their own key for it in `settings`.
+///
+/// # Arguments
+///
+/// * `dependencies` - The resolved dependencies of the project
+/// * `options` - The options the code banks are generated with
+/// * `settings` - Other settings changing the generated files, such as the
+///   sections a caller appends to the banks, by name
+///
+/// # Returns
+///
+/// * `String` - The fingerprint, as 16 lowercase hexadecimal digits
+///
+/// # Examples
+///
+/// ```rust
+/// use depbank::{Dependency, DependencyCollection, GenerateOptions, project_fingerprint};
+/// use std::collections::BTreeMap;
+///
+/// let mut dependencies = DependencyCollection::new();
+/// dependencies.add(Dependency::new("serde", "1.0.219"));
+/// let fingerprint =
+///     project_fingerprint(&dependencies, &GenerateOptions::default(), &BTreeMap::new());
+/// assert_eq!(fingerprint.len(), 16);
+/// ```
+pub fn project_fingerprint(
+    dependencies: &DependencyCollection,
+    options: &GenerateOptions,
+    settings: &BTreeMap<String, String>,
+) -> String {
+    format!(
+        "{:016x}",
+        fnv1a(canonical_text(dependencies, options, settings).as_bytes())
+    )
+}
+
+/// Computes a fingerprint of the generation options alone.
+///
+/// It covers the same options as [`project_fingerprint`] and the DepBank version, but
+/// no dependencies, so banks generated with the same options share it. The progress
+/// log records it with every bank (see [`ProgressEntry`](crate::ProgressEntry)), so
+/// that resuming never reuses banks generated with other options.
+///
+/// # Arguments
+///
+/// * `options` - The options the code banks are generated with
+///
+/// # Returns
+///
+/// * `String` - The fingerprint, as 16 lowercase hexadecimal digits
+///
+/// # Examples
+///
+/// ```rust
+/// use depbank::{GenerateOptions, Strategy, options_fingerprint};
+///
+/// let outline = GenerateOptions {
+///     strategy: Strategy::NoTests,
+///     ..Default::default()
+/// };
+/// assert_ne!(
+///     options_fingerprint(&GenerateOptions::default()),
+///     options_fingerprint(&outline)
+/// );
+/// ```
+pub fn options_fingerprint(options: &GenerateOptions) -> String {
+    let mut text = String::new();
+    writeln!(text, "{}", FINGERPRINT_FORMAT).unwrap();
+    writeln!(text, "depbank {}", env!("CARGO_PKG_VERSION")).unwrap();
+    write_options(&mut text, options);
+    format!("{:016x}", fnv1a(text.as_bytes()))
+}
+
+/// Computes a fingerprint of the files under a directory, such as a local crate.
+///
+/// Every file's path relative to the directory and content are hashed, in path
+/// order. Hidden files and directories and `target` directories are skipped, and so
+/// are files that cannot be read.
+///
+/// # Arguments
+///
+/// * `dir` - The directory to fingerprint
+///
+/// # Returns
+///
+/// * `String` - The fingerprint, as 16 lowercase hexadecimal digits
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use depbank::source_fingerprint;
+/// use std::path::Path;
+///
+/// println!("{}", source_fingerprint(Path::new(".")));
+/// ```
+pub fn source_fingerprint(dir: &Path) -> String {
+    let mut files = Vec::new();
+    collect_files(dir, dir, &mut files);
+    files.sort();
+
+    let mut bytes = Vec::new();
+    for (relative, path) in files {
+        let Ok(content) = fs::read(&path) else {
+            continue;
+        };
+        bytes.extend_from_slice(relative.as_bytes());
+        bytes.push(0);
+        bytes.extend_from_slice(&(content.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&content);
+    }
+    format!("{:016x}", fnv1a(&bytes))
+}
+
+/// Collects the files under a directory with their `/`-separated paths relative to
+/// `root`, skipping hidden entries and `target` directories
+fn collect_files(root: &Path, dir: &Path, files: &mut Vec<(String, PathBuf)>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name.starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        if path.is_dir() {
+            if name != "target" {
+                collect_files(root, &path, files);
+            }
+        } else if let Ok(relative) = path.strip_prefix(root) {
+            let relative: Vec<String> = relative
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy().into_owned())
+                .collect();
+            files.push((relative.join("/"), path));
+        }
+    }
+}
+
+/// The text the fingerprint is the hash of, one `key value` line per item
+fn canonical_text(
+    dependencies: &DependencyCollection,
+    options: &GenerateOptions,
+    settings: &BTreeMap<String, String>,
+) -> String {
+    let mut dependencies: Vec<String> = dependencies
+        .iter()
+        .map(|dep| {
+            let mut line = format!(
+                "{}@{} {}",
+                dep.name,
+                dep.version,
+                dep.source.as_deref().unwrap_or_default()
+            );
+            // Local sources change without a new version
+            if let Some(path) = dep.local_source_path().filter(|path| path.is_dir()) {
+                line.push_str(&format!(" {}", source_fingerprint(&path)));
+            }
+            line
+        })
+        .collect();
+    dependencies.sort();
+    dependencies.dedup();
+
+    let mut text = String::new();
+    writeln!(text, "{}", FINGERPRINT_FORMAT).unwrap();
+    writeln!(text, "depbank {}", env!("CARGO_PKG_VERSION")).unwrap();
+    for dependency in &dependencies {
+        writeln!(text, "dependency {}", dependency).unwrap();
+    }
+    write_options(&mut text, options);
+    for (name, value) in settings {
+        writeln!(text, "setting {} {}", name, value).unwrap();
+    }
+    text
+}
+
+/// Writes the options changing the content of the banks, one `key value` line each
+fn write_options(text: &mut String, options: &GenerateOptions) {
+    let mut features = options.features.features.clone();
+    features.sort();
+
+    writeln!(text, "strategy {:?}", options.strategy).unwrap();
+    writeln!(text, "strip_hidden {}", options.strip_hidden).unwrap();
+    writeln!(text, "strip_std_reexports {}", options.strip_std_reexports).unwrap();
+    writeln!(text, "item_kinds {}", options.item_kinds.bits()).unwrap();
+    if let Some(ignore_dirs) = &options.ignore_dirs {
+        writeln!(text, "ignore_dirs {}", ignore_dirs.join(",")).unwrap();
+    }
+    writeln!(text, "source_depth {:?}", options.source_depth).unwrap();
+    writeln!(
+        text,
+        "source_extensions {}",
+        options.source_extensions.join(",")
+    )
+    .unwrap();
+    writeln!(text, "outline {}", options.outline).unwrap();
+    writeln!(text, "docs_only {}", options.docs_only).unwrap();
+    writeln!(text, "mark_deprecated {}", options.mark_deprecated).unwrap();
+    writeln!(text, "expand_macros {}", options.expand_macros).unwrap();
+    writeln!(
+        text,
+        "include_build_script {}",
+        options.include_build_script
+    )
+    .unwrap();
+    writeln!(text, "features {}", features.join(",")).unwrap();
+    writeln!(text, "all_features {}", options.features.all_features).unwrap();
+    writeln!(
+        text,
+        "no_default_features {}",
+        options.features.no_default_features
+    )
+    .unwrap();
+    writeln!(text, "heading_offset {}", options.heading_offset).unwrap();
+    writeln!(text, "line_endings {:?}", options.line_endings).unwrap();
+    for (name, alias) in &options.aliases {
+        writeln!(text, "alias {} {}", name, alias).unwrap();
+    }
+    for (name, depth) in &options.dependency_depths {
+        writeln!(text, "depth {} {}", name, depth).unwrap();
+    }
+    for name in &options.yanked {
+        writeln!(text, "yanked {}", name).unwrap();
+    }
+    for (name, usages) in &options.usages {
+        for usage in usages {
+            writeln!(
+                text,
+                "usage {} {}:{} {:x}",
+                name,
+                usage.file.display(),
+                usage.line,
+                fnv1a(usage.snippet.as_bytes())
+            )
+            .unwrap();
+        }
+    }
+}
+
+/// The 64-bit FNV-1a hash of some bytes
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(FNV_OFFSET_BASIS, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Dependency, PATH_SOURCE_PREFIX, Strategy};
+    use anyhow::Result;
+    use tempfile::tempdir;
+
+    fn dependencies(versions: &[(&str, &str)]) -> DependencyCollection {
+        let mut dependencies = DependencyCollection::new();
+        for &(name, version) in versions {
+            dependencies.add(Dependency::new(name, version));
+        }
+        dependencies
+    }
+
+    #[test]
+    fn test_fnv1a() {
+        // Reference values of the 64-bit FNV-1a hash
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(fnv1a(b"foobar"), 0x8594_4171_f739_67e8);
+    }
+
+    #[test]
+    fn test_project_fingerprint() {
+        let options = GenerateOptions::default();
+        let fingerprint = project_fingerprint(
+            &dependencies(&[("serde", "1.0.219"), ("tokio", "1.44.2")]),
+            &options,
+            &BTreeMap::new(),
+        );
+
+        // Stable across runs, and independent of the order and duplicates
+        assert_eq!(
+            project_fingerprint(
+                &dependencies(&[
+                    ("tokio", "1.44.2"),
+                    ("serde", "1.0.219"),
+                    ("tokio", "1.44.2")
+                ]),
+                &options,
+                &BTreeMap::new()
+            ),
+            fingerprint
+        );
+        assert!(
+            fingerprint
+                .chars()
+                .all(|c| c.is_ascii_hexdigit() && !c.is_ascii_uppercase())
+        );
+
+        // Changes with a version
+        assert_ne!(
+            project_fingerprint(
+                &dependencies(&[("serde", "1.0.219"), ("tokio", "1.45.0")]),
+                &options,
+                &BTreeMap::new()
+            ),
+            fingerprint
+        );
+
+        // Changes with the options that change the banks, but not with the others
+        let full = GenerateOptions {
+            strategy: Strategy::Full,
+            ..Default::default()
+        };
+        let deps = dependencies(&[("serde", "1.0.219"), ("tokio", "1.44.2")]);
+        assert_ne!(
+            project_fingerprint(&deps, &full, &BTreeMap::new()),
+            fingerprint
+        );
+        let resumed = GenerateOptions {
+            resume: true,
+            max_memory_mb: Some(512),
+            ..Default::default()
+        };
+        assert_eq!(
+            project_fingerprint(&deps, &resumed, &BTreeMap::new()),
+            fingerprint
+        );
+
+        // And with the other settings
+        let settings = BTreeMap::from([("with_usage".to_string(), "true".to_string())]);
+        assert_ne!(project_fingerprint(&deps, &options, &settings), fingerprint);
+    }
+
+    #[test]
+    fn test_project_fingerprint_hashes_local_sources() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let crate_dir = temp_dir.path().join("mylib");
+        fs::create_dir_all(crate_dir.join("src"))?;
+        fs::create_dir_all(crate_dir.join("target"))?;
+        fs::write(crate_dir.join("src").join("lib.rs"), "pub fn api() {}\n")?;
+
+        let mut deps = DependencyCollection::new();
+        deps.add(Dependency::new("mylib", "0.1.0").with_source(format!(
+            "{}{}",
+            PATH_SOURCE_PREFIX,
+            crate_dir.display()
+        )));
+        let options = GenerateOptions::default();
+        let fingerprint = project_fingerprint(&deps, &options, &BTreeMap::new());
+
+        // Build outputs don't count, sources do
+        fs::write(crate_dir.join("target").join("out.rlib"), "binary")?;
+        assert_eq!(
+            project_fingerprint(&deps, &options, &BTreeMap::new()),
+            fingerprint
+        );
+        fs::write(crate_dir.join("src").join("lib.rs"), "pub fn api2() {}\n")?;
+        assert_ne!(
+            project_fingerprint(&deps, &options, &BTreeMap::new()),
+            fingerprint
+        );
+
+        Ok(())
+    }
+}
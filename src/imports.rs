@@ -0,0 +1,140 @@
+//! Discovery of the crates a Rust source file imports.
+//!
+//! This powers focused generation: producing code banks only for the dependencies
+//! a particular file actually `use`s.
+
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use syn::{Item, UseTree};
+
+/// Path roots that refer to the current crate rather than an external one
+const LOCAL_PATH_ROOTS: [&str; 4] = ["crate", "self", "super", "Self"];
+
+/// Extracts the names of external crates referenced by a file's imports.
+///
+/// Only top-level `use` and `extern crate` items are considered. Paths rooted in the
+/// current crate (`crate::`, `self::`, `super::`) are ignored. Leading `::` is allowed,
+/// and grouped imports like `use {serde::Serialize, tokio::fs};` contribute every
+/// crate in the group. Renames (`extern crate foo as bar`) report the original name.
+///
+/// Crate names are returned as they appear in code, i.e. with underscores; compare
+/// them against dependency names using [`normalize_crate_name`](crate::normalize_crate_name).
+///
+/// # Arguments
+///
+/// * `file` - Path to the Rust source file
+///
+/// # Returns
+///
+/// * `Result<HashSet<String>>` - The names of the referenced crates
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or is not valid Rust
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use depbank::crates_referenced_in;
+/// use std::path::Path;
+///
+/// let crates = crates_referenced_in(Path::new("src/main.rs")).unwrap();
+/// for name in &crates {
+///     println!("{}", name);
+/// }
+/// ```
+pub fn crates_referenced_in(file: &Path) -> Result<HashSet<String>> {
+    let content = fs::read_to_string(file)
+        .with_context(|| format!("Failed to read file: {}", file.display()))?;
+    let syntax = syn::parse_file(&content)
+        .with_context(|| format!("Failed to parse Rust file: {}", file.display()))?;
+
+    let mut crates = HashSet::new();
+    for item in &syntax.items {
+        match item {
+            Item::Use(item_use) => collect_use_roots(&item_use.tree, &mut crates),
+            Item::ExternCrate(extern_crate) => {
+                crates.insert(extern_crate.ident.to_string());
+            }
+            _ => {}
+        }
+    }
+
+    crates.retain(|name| !LOCAL_PATH_ROOTS.contains(&name.as_str()));
+    Ok(crates)
+}
+
+/// Adds the first path segment of every import in a use tree
+fn collect_use_roots(tree: &UseTree, crates: &mut HashSet<String>) {
+    match tree {
+        UseTree::Path(path) => {
+            crates.insert(path.ident.to_string());
+        }
+        UseTree::Name(name) => {
+            crates.insert(name.ident.to_string());
+        }
+        UseTree::Rename(rename) => {
+            crates.insert(rename.ident.to_string());
+        }
+        UseTree::Group(group) => {
+            for tree in &group.items {
+                collect_use_roots(tree, crates);
+            }
+        }
+        UseTree::Glob(_) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_crates_referenced_in() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let file = temp_dir.path().join("main.rs");
+        fs::write(
+            &file,
+            r#"
+extern crate serde_json as json;
+
+use serde::{Deserialize, Serialize};
+use ::tokio::sync::Mutex;
+use {anyhow::Result, std::fs};
+use crate::config::Config;
+use self::helpers::*;
+use super::parent;
+
+mod inner {
+    use regex::Regex;
+}
+
+fn main() {}
+"#,
+        )?;
+
+        let crates = crates_referenced_in(&file)?;
+        let expected: HashSet<String> = ["serde", "tokio", "serde_json", "anyhow", "std"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        assert_eq!(crates, expected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_crates_referenced_in_invalid_file() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let file = temp_dir.path().join("broken.rs");
+        fs::write(&file, "use serde::{")?;
+
+        assert!(crates_referenced_in(&file).is_err());
+        assert!(crates_referenced_in(&temp_dir.path().join("missing.rs")).is_err());
+
+        Ok(())
+    }
+}
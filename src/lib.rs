@@ -28,15 +28,15 @@
  * ```rust,no_run
  * use anyhow::Result;
  * use depbank::{
- *     extract_dependency_info, find_cargo_lock, find_cargo_toml_files,
- *     generate_all_code_banks, is_dependency_available, resolve_dependency_versions,
- *     resolve_registry_path,
+ *     DiscoveryOptions, GenerateOptions, extract_dependency_info, find_cargo_lock,
+ *     find_cargo_toml_files, generate_all_code_banks, is_dependency_available,
+ *     resolve_dependency_versions, resolve_registry_path,
  * };
  * use std::path::{Path, PathBuf};
  *
  * fn generate_docs(project_path: &Path, output_dir: &Path) -> Result<()> {
  *     // Find Cargo.toml files
- *     let cargo_toml_files = find_cargo_toml_files(project_path)?;
+ *     let cargo_toml_files = find_cargo_toml_files(project_path, &DiscoveryOptions::default())?;
  *
  *     // Extract dependency information
  *     let first_cargo_toml = &cargo_toml_files[0];
@@ -50,7 +50,12 @@
  *     let registry_path = resolve_registry_path()?;
  *
  *     // Generate code banks
- *     let code_bank_files = generate_all_code_banks(&resolved_versions, &registry_path, output_dir)?;
+ *     let code_bank_files = generate_all_code_banks(
+ *         &resolved_versions,
+ *         &registry_path,
+ *         output_dir,
+ *         &GenerateOptions::default(),
+ *     )?;
  *
  *     println!("Generated {} code bank files", code_bank_files.len());
  *     Ok(())
@@ -60,17 +65,17 @@
  * ### Calculate tokens for files
  *
  * ```rust,no_run
- * use depbank::{calculate_directory_tokens, calculate_file_tokens};
+ * use depbank::{TokenOptions, calculate_directory_tokens, calculate_file_tokens};
  * use std::path::Path;
  *
  * fn count_tokens(path: &Path) -> anyhow::Result<()> {
  *     if path.is_file() {
  *         // Count tokens for a single file
- *         let token_count = calculate_file_tokens(path)?;
+ *         let token_count = calculate_file_tokens(path, &TokenOptions::default())?;
  *         println!("{}: {} tokens", path.display(), token_count);
  *     } else if path.is_dir() {
  *         // Count tokens for a directory
- *         let file_stats = calculate_directory_tokens(path, Some("md"))?;
+ *         let file_stats = calculate_directory_tokens(path, Some("md"), &TokenOptions::default())?;
  *         println!("Total files: {}", file_stats.len());
  *
  *         // Calculate total tokens
@@ -83,22 +88,171 @@
  * ```
  */
 
+mod aliases;
+mod archive;
+mod budget;
+mod color;
+mod compare;
+mod compress;
+mod conflicts;
+mod cost;
+mod csv_export;
+mod deprecations;
+mod deps_file;
+mod error;
+mod expand;
+mod filters;
+mod fingerprint;
+mod imports;
+mod lockcheck;
+mod lockfile;
+mod logging;
+mod manifest;
+mod members;
+mod memory;
+mod meta;
+mod package_files;
+mod parallel;
+mod patches;
+mod path_deps;
+mod progress;
+mod registry;
+mod report;
+mod resolve;
+mod retry;
+mod sbom;
+mod sizes;
+mod sources;
+mod targets;
+mod token_cache;
+mod tokenizer;
+mod transitive;
+mod tree;
+mod usage;
+mod warnings;
+mod watch;
+mod yanked;
+
+use aliases::alias_bank_title;
+pub use aliases::{display_name, read_aliases_file};
+pub use archive::{
+    ArchiveOutcome, SOURCE_DATE_EPOCH_VAR, archive_mtime, generate_all_to_archive, write_archive,
+    write_archive_file,
+};
+pub use budget::{
+    BudgetPriority, TokenEstimate, estimate_dependency_tokens, order_by_priority,
+    read_priority_file, select_within_budget,
+};
+pub use color::{ColorChoice, ColorStyle, OutputStream, is_colored, paint, set_color_choice};
+pub use compare::{
+    StrategyComparison, compare_strategies, estimate_generation_tokens, estimate_ignore_savings,
+};
+pub use compress::{
+    COMPRESSED_BANK_EXTENSION, compress_bank, compress_banks_over, is_compressed_bank, read_bank,
+};
+pub use conflicts::{
+    VersionConflictPolicy, find_major_version_conflicts, find_major_version_conflicts_from_lock,
+    find_major_version_conflicts_from_str, resolve_version_conflicts,
+};
+pub use cost::{KNOWN_MODEL_PRICES, estimate_cost, model_price_per_1k};
+pub use csv_export::{DEPENDENCY_CSV_HEADER, DependencyCsvRow, write_dependency_csv};
+pub use deprecations::annotate_deprecations;
+pub use deps_file::{filter_to_deps_list, load_deps_file};
+pub use error::{
+    DepBankError, EXIT_DEADLINE_EXCEEDED, EXIT_FAILURE, EXIT_GENERATION_FAILED,
+    EXIT_MISSING_DEPENDENCIES, EXIT_SUCCESS, EXIT_TOKEN_BUDGET_EXCEEDED, exit_code,
+};
+pub use expand::{expand_crate, is_cargo_expand_available};
+use filters::insert_header_note;
+pub use filters::{
+    ItemKinds, LineEndings, extract_docs_only, extract_outline, filter_item_kinds,
+    limit_section_depth, normalize_line_endings, shift_headings, strip_hidden_items,
+    strip_std_reexports,
+};
+pub use fingerprint::{options_fingerprint, project_fingerprint, source_fingerprint};
+pub use imports::crates_referenced_in;
+pub use lockcheck::{
+    Inconsistency, validate_against_lock, validate_against_lock_str, validate_against_parsed_lock,
+};
+pub(crate) use lockfile::CargoLockPackage;
+pub use lockfile::ParsedLock;
+pub use logging::{
+    LOG_TARGET_DISCOVERY, LOG_TARGET_GENERATE, LOG_TARGET_RESOLVE, LOG_TARGET_TOKENS,
+};
+pub use manifest::{ManifestCache, ParsedManifest};
+pub use members::{WorkspaceMember, is_workspace_member_dep, workspace_members};
+pub use memory::{MemoryBudget, MemoryPermit};
+pub use meta::{DependencyMetadata, dependency_metadata};
+pub use package_files::{FileFilter, package_file_filter};
+pub use parallel::set_thread_pool;
+pub use patches::{
+    PATH_SOURCE_PREFIX, PatchSource, apply_patches, locate_patch_source, parse_patches,
+};
+pub use path_deps::follow_path_dependencies;
+pub use progress::{
+    PROGRESS_LOG_FILE, ProgressEntry, ProgressLog, read_progress_log, remove_progress_log,
+};
+pub use registry::{
+    UnresolvableVersionPolicy, best_available_matching, find_version_mismatch,
+    find_version_mismatches, list_registry_crates, registry_source_dirs,
+    resolve_unresolvable_versions, unused_registry_crates,
+};
+pub use report::{
+    DEFAULT_MIN_DOC_TOKENS, GeneratedBank, GenerationReport, INDEX_JSON_FILE, SIDECAR_EXTENSION,
+    build_generation_report, clean_output_dir, find_undocumented_banks, index_json,
+    is_effectively_empty, read_owned_files, relativize, sidecar_path, write_index_json,
+    write_index_markdown, write_sidecar,
+};
+pub use resolve::{ResolveOptions, ResolvedDependencies, resolve_project_dependencies};
+pub use retry::{TransientError, with_retries};
+pub use sbom::{SbomComponent, to_cyclonedx, to_spdx};
+pub use sizes::{analyze_source_sizes, bank_tokens_by_file};
+pub use targets::{binary_sources, build_script, has_lib_target, is_no_std};
+pub use token_cache::TOKEN_CACHE_FILE;
+use token_cache::TokenCache;
+pub use tokenizer::{
+    DEFAULT_TOKENIZER_MODEL, TokenizerModel, load_tokenizer, set_max_length, shared_tokenizer,
+};
+pub use transitive::{
+    TransitiveOptions, collect_transitive_dependencies, collect_transitive_dependencies_from_graph,
+    collect_transitive_dependencies_from_str,
+};
+pub use tree::{
+    DependencyGraph, build_dependency_graph, build_dependency_graph_from_lock,
+    build_dependency_graph_from_str, compute_dependency_depths, render_dependency_tree,
+};
+pub use usage::{MAX_USAGE_SNIPPETS, ProjectSources, UsageSnippet, find_usages, usage_section};
+pub use warnings::{Warning, WarningKind, WarningsFormat, emit_warning, set_warnings_format};
+pub use watch::{WatchOptions, WatchRun, watch_project};
+pub use yanked::{
+    CRATES_IO_SPARSE_INDEX, cached_yanked_status, is_version_yanked, is_version_yanked_at,
+};
+
 use anyhow::{Context, Result};
 use codebank::{Bank, BankConfig, BankStrategy, CodeBank};
-use serde::Deserialize;
-use std::collections::{HashMap, HashSet};
+use rayon::prelude::*;
+use retry::DEFAULT_RETRY_ATTEMPTS;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt;
 use std::fs;
+use std::io::{BufWriter, Write};
 use std::path::{Path, PathBuf};
-use std::time::SystemTime;
-use tokenizers::tokenizer::Tokenizer;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use tempfile::TempDir;
 
 /// A dependency with its name and version
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
 pub struct Dependency {
     /// The name of the dependency
     pub name: String,
     /// The version specification of the dependency
     pub version: String,
+    /// Where the dependency comes from as recorded in Cargo.lock, e.g.
+    /// `registry+https://github.com/rust-lang/crates.io-index`, if known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
 }
 
 impl Dependency {
@@ -107,12 +261,79 @@ impl Dependency {
         Self {
             name: name.into(),
             version: version.into(),
+            source: None,
+        }
+    }
+
+    /// Sets the source the dependency comes from, as recorded in Cargo.lock
+    pub fn with_source(mut self, source: impl Into<String>) -> Self {
+        self.source = Some(source.into());
+        self
+    }
+
+    /// Returns the local directory of a dependency with a `file://` source.
+    ///
+    /// A `path+file://` source, such as a crate patched to a local path (see
+    /// [`apply_patches`]), is the crate directory itself. Other `file://` sources,
+    /// such as `registry+file://` or `sparse+file://` registries kept on disk, hold
+    /// the crate in a `<name>-<version>` or `<name>` directory (see
+    /// [`resolve_local_source`]).
+    pub fn local_source_path(&self) -> Option<PathBuf> {
+        let source = self.source.as_deref()?;
+        if let Some(path) = source.strip_prefix(PATH_SOURCE_PREFIX) {
+            return Some(PathBuf::from(path));
         }
+
+        let dir = resolve_local_source(source)?;
+        [
+            dir.join(format!("{}-{}", self.name, self.version)),
+            dir.join(&self.name),
+        ]
+        .into_iter()
+        .find(|path| path.join("Cargo.toml").is_file())
+    }
+
+    /// Whether this dependency is built from a local path, such as a crate patched to
+    /// a path, whose sources may change without its version changing
+    pub(crate) fn has_path_source(&self) -> bool {
+        self.source
+            .as_deref()
+            .is_some_and(|source| source.starts_with(PATH_SOURCE_PREFIX))
     }
 
     /// Get the full path to this dependency in the cargo registry
+    ///
+    /// If no directory matches the name exactly, a directory whose name only differs
+    /// in casing or hyphens/underscores is used instead (see [`normalize_crate_name`]).
+    /// Dependencies with a local source are located there instead of in the registry.
+    /// Dependencies from another registry than `registry_base_path` are located in the
+    /// directory of their registry next to it, when it exists (see
+    /// [`registry_dir_for_source`]).
     pub fn get_registry_path(&self, registry_base_path: &Path) -> PathBuf {
-        registry_base_path.join(format!("{}-{}", self.name, self.version))
+        if let Some(path) = self.local_source_path() {
+            return path;
+        }
+
+        let registry_path = self
+            .source
+            .as_deref()
+            .zip(registry_base_path.parent())
+            .and_then(|(source, registry_base)| registry_dir_for_source(source, registry_base))
+            .unwrap_or_else(|| registry_base_path.to_path_buf());
+
+        let path = registry_path.join(format!("{}-{}", self.name, self.version));
+        if path.is_dir() {
+            return path;
+        }
+
+        self.find_normalized_registry_path(&registry_path)
+            .unwrap_or(path)
+    }
+
+    /// Returns the kind of place the dependency comes from, see
+    /// [`DependencySource::from_lock_source`]
+    pub fn source_kind(&self) -> DependencySource {
+        DependencySource::from_lock_source(self.source.as_deref())
     }
 
     /// Check if this dependency is available in the cargo registry
@@ -120,23 +341,119 @@ impl Dependency {
         let path = self.get_registry_path(registry_base_path);
         path.exists() && path.is_dir()
     }
+
+    /// Looks for a registry directory matching this dependency after name normalization,
+    /// in the listing of the registry shared by all lookups
+    fn find_normalized_registry_path(&self, registry_base_path: &Path) -> Option<PathBuf> {
+        registry::registry_listing(registry_base_path)?
+            .find_normalized(&format!("{}-{}", self.name, self.version))
+            .map(Path::to_path_buf)
+    }
+}
+
+/// The kind of place a dependency comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DependencySource {
+    /// The crates.io registry
+    CratesIo,
+    /// Another registry, e.g. a company registry or one kept on disk
+    Registry,
+    /// A git repository
+    Git,
+    /// A local path, such as a workspace member or a `path` dependency
+    Path,
+}
+
+impl DependencySource {
+    /// Classifies a source as recorded in Cargo.lock.
+    ///
+    /// Cargo.lock records no source for local path dependencies, so a missing source
+    /// is a path dependency.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depbank::DependencySource;
+    ///
+    /// assert_eq!(
+    ///     DependencySource::from_lock_source(Some("git+https://github.com/serde-rs/serde#abc")),
+    ///     DependencySource::Git
+    /// );
+    /// assert_eq!(DependencySource::from_lock_source(None), DependencySource::Path);
+    /// ```
+    pub fn from_lock_source(source: Option<&str>) -> Self {
+        match source {
+            None => DependencySource::Path,
+            Some(source) if source.starts_with(PATH_SOURCE_PREFIX) => DependencySource::Path,
+            Some(source) if source.starts_with("git+") => DependencySource::Git,
+            Some(CRATES_IO_SOURCE | CRATES_IO_SPARSE_SOURCE) => DependencySource::CratesIo,
+            Some(_) => DependencySource::Registry,
+        }
+    }
+
+    /// A human-readable name of the source kind
+    pub fn label(self) -> &'static str {
+        match self {
+            DependencySource::CratesIo => "crates.io",
+            DependencySource::Registry => "alternative registry",
+            DependencySource::Git => "git",
+            DependencySource::Path => "path",
+        }
+    }
+}
+
+/// Normalizes a crate name for comparison.
+///
+/// Cargo treats crate names that only differ in casing or in hyphens versus
+/// underscores as the same crate, e.g. a dependency declared as `foo_bar` is resolved
+/// to the `foo-bar` package. This maps all such spellings to a single form: lowercase
+/// with hyphens.
+///
+/// # Arguments
+///
+/// * `name` - The crate name to normalize
+///
+/// # Returns
+///
+/// * `String` - The normalized crate name
+///
+/// # Examples
+///
+/// ```rust
+/// use depbank::normalize_crate_name;
+///
+/// assert_eq!(normalize_crate_name("Foo_Bar"), "foo-bar");
+/// assert_eq!(normalize_crate_name("foo-bar"), normalize_crate_name("foo_bar"));
+/// ```
+pub fn normalize_crate_name(name: &str) -> String {
+    name.to_ascii_lowercase().replace('_', "-")
 }
 
 /// A collection of dependencies with helper methods
+///
+/// Lookups by name ignore casing and hyphens versus underscores, like cargo (see
+/// [`normalize_crate_name`]), while the dependencies keep their names as declared.
 #[derive(Debug, Default)]
 pub struct DependencyCollection {
     /// The dependencies in this collection
     deps: Vec<Dependency>,
+    /// The positions of the dependencies in `deps`, by normalized name
+    positions: HashMap<String, Vec<usize>>,
 }
 
 impl DependencyCollection {
     /// Create a new empty dependency collection
     pub fn new() -> Self {
-        Self { deps: Vec::new() }
+        Self::default()
     }
 
     /// Add a dependency to this collection
     pub fn add(&mut self, dep: Dependency) {
+        self.positions
+            .entry(normalize_crate_name(&dep.name))
+            .or_default()
+            .push(self.deps.len());
         self.deps.push(dep);
     }
 
@@ -156,8 +473,27 @@ impl DependencyCollection {
     }
 
     /// Get a dependency by name, if it exists
+    ///
+    /// A dependency with exactly this name is preferred over one whose name only
+    /// differs in casing or hyphens versus underscores.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depbank::{Dependency, DependencyCollection};
+    ///
+    /// let mut deps = DependencyCollection::new();
+    /// deps.add(Dependency::new("OpenSSL", "0.10.72"));
+    ///
+    /// assert_eq!(deps.get("openssl").unwrap().name, "OpenSSL");
+    /// ```
     pub fn get(&self, name: &str) -> Option<&Dependency> {
-        self.deps.iter().find(|dep| dep.name == name)
+        let positions = self.positions.get(&normalize_crate_name(name))?;
+        let mut matches = positions.iter().map(|&position| &self.deps[position]);
+        matches
+            .clone()
+            .find(|dep| dep.name == name)
+            .or_else(|| matches.next())
     }
 
     /// Get a dependency's version by name, if it exists
@@ -182,9 +518,33 @@ impl DependencyCollection {
         collection
     }
 
-    /// Check if this collection contains a dependency with the given name
+    /// Check if this collection contains a dependency with the given name, ignoring
+    /// casing and hyphens versus underscores
     pub fn contains_name(&self, name: &str) -> bool {
-        self.deps.iter().any(|dep| dep.name == name)
+        self.positions.contains_key(&normalize_crate_name(name))
+    }
+
+    /// Keeps the first `len` dependencies of this collection, dropping the others
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depbank::{Dependency, DependencyCollection};
+    ///
+    /// let mut deps = DependencyCollection::new();
+    /// deps.add(Dependency::new("anyhow", "1.0.98"));
+    /// deps.add(Dependency::new("serde", "1.0.219"));
+    /// deps.truncate(1);
+    ///
+    /// assert!(deps.contains_name("anyhow"));
+    /// assert!(!deps.contains_name("serde"));
+    /// ```
+    pub fn truncate(&mut self, len: usize) {
+        self.deps.truncate(len);
+        self.positions.retain(|_, positions| {
+            positions.retain(|&position| position < len);
+            !positions.is_empty()
+        });
     }
 
     /// Check if this collection contains a dependency with the exact name and version
@@ -205,20 +565,252 @@ impl DependencyCollection {
         result
     }
 
+    /// Filter this collection to the dependencies whose version lies in a range.
+    ///
+    /// The range includes both bounds, like the requirement `>=min, <=max`, and is
+    /// open-ended on either side when a bound is `None`.
+    /// Versions are compared by semver precedence, so `1.0.0-rc.1` is below `1.0.0`.
+    /// Dependencies whose version is not valid semver, such as the `workspace`
+    /// placeholder of unresolved workspace dependencies, are excluded with a warning.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depbank::{Dependency, DependencyCollection};
+    /// use semver::Version;
+    ///
+    /// let mut deps = DependencyCollection::new();
+    /// deps.add(Dependency::new("anyhow", "1.0.98"));
+    /// deps.add(Dependency::new("toml", "0.8.22"));
+    ///
+    /// let up_to_one = deps.filter_version_range(None, Some(&Version::new(1, 0, 98)));
+    /// assert!(up_to_one.contains_name("toml"));
+    /// assert!(up_to_one.contains_name("anyhow"));
+    ///
+    /// let below_one = deps.filter_version_range(None, Some(&Version::new(0, 9, 0)));
+    /// assert!(!below_one.contains_name("anyhow"));
+    /// ```
+    pub fn filter_version_range(
+        &self,
+        min: Option<&semver::Version>,
+        max: Option<&semver::Version>,
+    ) -> Self {
+        let mut result = Self::new();
+        for dep in &self.deps {
+            let Ok(version) = semver::Version::parse(&dep.version) else {
+                emit_warning(Warning::new(
+                    WarningKind::InvalidVersion,
+                    Some(&dep.name),
+                    format!(
+                        "Excluding {} from the version filter, {:?} is not a valid version",
+                        dep.name, dep.version
+                    ),
+                ));
+                continue;
+            };
+            if min.is_none_or(|min| version >= *min) && max.is_none_or(|max| version <= *max) {
+                result.add(dep.clone());
+            }
+        }
+        result
+    }
+
+    /// Groups the dependencies by the kind of place they come from.
+    ///
+    /// The sources are those recorded in Cargo.lock, so the collection should hold
+    /// resolved dependencies, e.g. from [`resolve_dependency_versions`].
+    ///
+    /// # Returns
+    ///
+    /// * `BTreeMap<DependencySource, Vec<&Dependency>>` - The dependencies of each
+    ///   source kind found, in collection order
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depbank::{DependencySource, locked_packages_from_str};
+    ///
+    /// let lock = "version = 3\n\n[[package]]\nname = \"local\"\nversion = \"0.1.0\"\n";
+    /// let packages = locked_packages_from_str(lock).unwrap();
+    /// let groups = packages.group_by_source();
+    /// assert_eq!(groups[&DependencySource::Path].len(), 1);
+    /// ```
+    pub fn group_by_source(&self) -> BTreeMap<DependencySource, Vec<&Dependency>> {
+        let mut groups: BTreeMap<DependencySource, Vec<&Dependency>> = BTreeMap::new();
+        for dep in &self.deps {
+            groups.entry(dep.source_kind()).or_default().push(dep);
+        }
+        groups
+    }
+
     /// Get a reference to the underlying vector of dependencies
     pub fn as_slice(&self) -> &[Dependency] {
         &self.deps
     }
 }
 
+/// A dependency whose version differs between two dependency sets
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VersionChange {
+    /// The name of the dependency
+    pub name: String,
+    /// The version in the old set
+    pub old_version: String,
+    /// The version in the new set
+    pub new_version: String,
+}
+
+/// The differences between two dependency sets
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct DepDiff {
+    /// Dependencies only present in the new set
+    pub added: Vec<Dependency>,
+    /// Dependencies only present in the old set
+    pub removed: Vec<Dependency>,
+    /// Dependencies present in both sets with different versions
+    pub changed: Vec<VersionChange>,
+}
+
+impl DepDiff {
+    /// Check if the two dependency sets are identical
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Computes which dependencies were added, removed, or changed version between two sets.
+///
+/// Dependencies are matched by name. This is meant for comparing two resolved
+/// dependency sets, e.g. the same project at two git revisions. If a name appears
+/// more than once in a collection, the first occurrence is used. All lists in the
+/// result are sorted by dependency name.
+///
+/// # Arguments
+///
+/// * `old` - The dependency set before the change
+/// * `new` - The dependency set after the change
+///
+/// # Returns
+///
+/// * `DepDiff` - The added, removed, and version-changed dependencies
+///
+/// # Examples
+///
+/// ```rust
+/// use depbank::{Dependency, DependencyCollection, diff_dependency_collections};
+///
+/// let mut old = DependencyCollection::new();
+/// old.add(Dependency::new("serde", "1.0.150"));
+/// let mut new = DependencyCollection::new();
+/// new.add(Dependency::new("serde", "1.0.197"));
+/// new.add(Dependency::new("anyhow", "1.0.80"));
+///
+/// let diff = diff_dependency_collections(&old, &new);
+/// assert_eq!(diff.added.len(), 1);
+/// assert_eq!(diff.changed[0].new_version, "1.0.197");
+/// ```
+pub fn diff_dependency_collections(
+    old: &DependencyCollection,
+    new: &DependencyCollection,
+) -> DepDiff {
+    let mut diff = DepDiff::default();
+
+    for dep in new.iter() {
+        match old.get(&dep.name) {
+            None => diff.added.push(dep.clone()),
+            Some(old_dep) if old_dep.version != dep.version => {
+                diff.changed.push(VersionChange {
+                    name: dep.name.clone(),
+                    old_version: old_dep.version.clone(),
+                    new_version: dep.version.clone(),
+                });
+            }
+            Some(_) => {}
+        }
+    }
+
+    for dep in old.iter() {
+        if !new.contains_name(&dep.name) {
+            diff.removed.push(dep.clone());
+        }
+    }
+
+    // Collections may repeat a name, only report each dependency once
+    diff.added.sort_by(|a, b| a.name.cmp(&b.name));
+    diff.added.dedup_by(|a, b| a.name == b.name);
+    diff.removed.sort_by(|a, b| a.name.cmp(&b.name));
+    diff.removed.dedup_by(|a, b| a.name == b.name);
+    diff.changed.sort_by(|a, b| a.name.cmp(&b.name));
+    diff.changed.dedup_by(|a, b| a.name == b.name);
+
+    diff
+}
+
+/// Directory names skipped when searching for Cargo.toml files, unless disabled with
+/// [`DiscoveryOptions::no_default_ignore`]
+pub const DEFAULT_IGNORED_DIRS: &[&str] = &["target"];
+
+/// The environment variable holding comma-separated names of directories skipped
+/// when `generate` searches for Cargo.toml files, unless `--ignore-dir` is given, and
+/// left out of code banks in addition to [`DEFAULT_BANK_IGNORED_DIRS`], unless
+/// `--bank-ignore-dir` is given
+pub const IGNORE_DIRS_ENV_VAR: &str = "DEPBANK_IGNORE_DIRS";
+
+/// Parses a comma-separated list of directory names, such as the value of
+/// [`IGNORE_DIRS_ENV_VAR`]. Blank names are left out.
+///
+/// # Examples
+///
+/// ```rust
+/// use depbank::parse_ignore_dirs;
+///
+/// assert_eq!(parse_ignore_dirs("vendor, third_party,,"), ["vendor", "third_party"]);
+/// assert!(parse_ignore_dirs(" ").is_empty());
+/// ```
+pub fn parse_ignore_dirs(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|dir| !dir.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Options controlling which directories are searched for Cargo.toml files.
+#[derive(Debug, Clone, Default)]
+pub struct DiscoveryOptions {
+    /// Names of additional directories to skip, e.g. `vendor`
+    pub ignore_dirs: Vec<String>,
+    /// Search the directories in [`DEFAULT_IGNORED_DIRS`] too, such as `target`
+    pub no_default_ignore: bool,
+    /// Use exactly these Cargo.toml files instead of searching the directory tree
+    pub manifests: Vec<PathBuf>,
+    /// Also use the manifests of the crates reached through path dependencies, see
+    /// [`follow_path_dependencies`]
+    pub follow_path_deps: bool,
+}
+
+impl DiscoveryOptions {
+    /// Checks whether a directory with this name is skipped
+    pub fn is_ignored(&self, dir_name: &str) -> bool {
+        (!self.no_default_ignore && DEFAULT_IGNORED_DIRS.contains(&dir_name))
+            || self.ignore_dirs.iter().any(|dir| dir == dir_name)
+    }
+}
+
 /// Recursively finds all Cargo.toml files in the given directory.
 ///
 /// This function walks through a directory tree, finding all Cargo.toml files.
-/// It automatically skips hidden directories (those starting with a dot).
+/// It automatically skips hidden directories (those starting with a dot) and the
+/// directories selected by `options`, by default those in [`DEFAULT_IGNORED_DIRS`],
+/// such as build output in `target`. Directories are matched by name, at any depth
+/// below `root_dir`. When `options.manifests` is not empty, the tree is not searched
+/// at all, and exactly those manifests are returned, see [`validate_manifest_paths`].
 ///
 /// # Arguments
 ///
 /// * `root_dir` - The root directory to start searching from
+/// * `options` - Which directories to skip, or which manifests to use instead
 ///
 /// # Returns
 ///
@@ -230,15 +822,16 @@ impl DependencyCollection {
 /// - The root directory does not exist
 /// - The path is not a directory
 /// - There are permission issues accessing directories
+/// - An explicit manifest is invalid
 ///
 /// # Examples
 ///
 /// ```rust,no_run
-/// use depbank::find_cargo_toml_files;
+/// use depbank::{DiscoveryOptions, find_cargo_toml_files};
 /// use std::path::Path;
 ///
 /// let project_dir = Path::new("./my_project");
-/// match find_cargo_toml_files(project_dir) {
+/// match find_cargo_toml_files(project_dir, &DiscoveryOptions::default()) {
 ///     Ok(files) => {
 ///         println!("Found {} Cargo.toml files", files.len());
 ///         for file in files {
@@ -247,8 +840,19 @@ impl DependencyCollection {
 ///     },
 ///     Err(e) => eprintln!("Error: {}", e),
 /// }
+///
+/// // Also scan the manifests of packaged crates in target/package
+/// let options = DiscoveryOptions {
+///     no_default_ignore: true,
+///     ..Default::default()
+/// };
+/// let files = find_cargo_toml_files(project_dir, &options).unwrap();
+/// println!("Found {} Cargo.toml files", files.len());
 /// ```
-pub fn find_cargo_toml_files<P: AsRef<Path>>(root_dir: P) -> Result<Vec<PathBuf>> {
+pub fn find_cargo_toml_files<P: AsRef<Path>>(
+    root_dir: P,
+    options: &DiscoveryOptions,
+) -> Result<Vec<PathBuf>> {
     let root_dir = root_dir.as_ref();
     let mut cargo_toml_files = Vec::new();
 
@@ -268,14 +872,76 @@ pub fn find_cargo_toml_files<P: AsRef<Path>>(root_dir: P) -> Result<Vec<PathBuf>
         ));
     }
 
-    // Recursively walk through the directory
-    find_cargo_toml_files_recursive(root_dir, &mut cargo_toml_files)?;
+    // Explicit manifests replace discovery
+    if !options.manifests.is_empty() {
+        cargo_toml_files = validate_manifest_paths(&options.manifests)?;
+    } else {
+        // Recursively walk through the directory
+        find_cargo_toml_files_recursive(root_dir, options, &mut cargo_toml_files)?;
+    }
+
+    if options.follow_path_deps {
+        return follow_path_dependencies(&cargo_toml_files);
+    }
 
     Ok(cargo_toml_files)
 }
 
+/// Checks that explicitly given manifest paths are existing Cargo.toml files.
+///
+/// # Arguments
+///
+/// * `manifests` - The manifest paths
+///
+/// # Returns
+///
+/// * `Result<Vec<PathBuf>>` - The manifests, in the given order, without duplicates
+///
+/// # Errors
+///
+/// Returns an error if a path is not named `Cargo.toml` or is not an existing file
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use depbank::validate_manifest_paths;
+/// use std::path::PathBuf;
+///
+/// let manifests = vec![
+///     PathBuf::from("crates/api/Cargo.toml"),
+///     PathBuf::from("crates/worker/Cargo.toml"),
+/// ];
+/// let manifests = validate_manifest_paths(&manifests).unwrap();
+/// println!("Using {} manifests", manifests.len());
+/// ```
+pub fn validate_manifest_paths(manifests: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut validated: Vec<PathBuf> = Vec::new();
+    for manifest in manifests {
+        if manifest.file_name().is_none_or(|name| name != "Cargo.toml") {
+            return Err(anyhow::anyhow!(
+                "Manifest is not named Cargo.toml: {}",
+                manifest.display()
+            ));
+        }
+        if !manifest.is_file() {
+            return Err(anyhow::anyhow!(
+                "Manifest does not exist: {}",
+                manifest.display()
+            ));
+        }
+        if !validated.contains(manifest) {
+            validated.push(manifest.clone());
+        }
+    }
+    Ok(validated)
+}
+
 /// Helper function to recursively walk through directories to find Cargo.toml files
-fn find_cargo_toml_files_recursive(dir: &Path, cargo_toml_files: &mut Vec<PathBuf>) -> Result<()> {
+fn find_cargo_toml_files_recursive(
+    dir: &Path,
+    options: &DiscoveryOptions,
+    cargo_toml_files: &mut Vec<PathBuf>,
+) -> Result<()> {
     // Check each entry in the directory
     for entry in
         fs::read_dir(dir).with_context(|| format!("Failed to read directory: {}", dir.display()))?
@@ -297,12 +963,20 @@ fn find_cargo_toml_files_recursive(dir: &Path, cargo_toml_files: &mut Vec<PathBu
 
         // If the entry is a Cargo.toml file, add it to the list
         if path.file_name().is_some_and(|name| name == "Cargo.toml") {
+            log::debug!(target: LOG_TARGET_DISCOVERY, "Found {}", path.display());
             cargo_toml_files.push(path.clone());
         }
 
-        // If the entry is a directory, recursively search it
+        // If the entry is a directory that is not ignored, recursively search it
         if path.is_dir() {
-            find_cargo_toml_files_recursive(&path, cargo_toml_files)?;
+            if path
+                .file_name()
+                .is_some_and(|name| options.is_ignored(&name.to_string_lossy()))
+            {
+                log::trace!(target: LOG_TARGET_DISCOVERY, "Skipping ignored directory {}", path.display());
+            } else {
+                find_cargo_toml_files_recursive(&path, options, cargo_toml_files)?;
+            }
         }
     }
 
@@ -310,24 +984,29 @@ fn find_cargo_toml_files_recursive(dir: &Path, cargo_toml_files: &mut Vec<PathBu
 }
 
 /// Represents a dependency in Cargo.toml
+///
+/// Dotted keys such as `serde.version = "1"` are parsed into a `Detailed` table by
+/// TOML itself. Any other shape ends up in `Other` instead of failing the parse of
+/// the whole manifest.
 #[derive(Debug, Deserialize)]
 #[serde(untagged)]
-enum CargoDepSpec {
+pub(crate) enum CargoDepSpec {
     Simple(String),
     Detailed(HashMap<String, toml::Value>),
+    Other(toml::Value),
 }
 
 /// Structure for parsing Cargo.toml
 #[derive(Debug, Deserialize)]
-struct CargoToml {
+pub(crate) struct CargoToml {
     #[serde(default)]
-    dependencies: HashMap<String, CargoDepSpec>,
+    pub(crate) dependencies: HashMap<String, CargoDepSpec>,
     #[serde(default)]
     #[serde(rename = "dev-dependencies")]
-    dev_dependencies: HashMap<String, CargoDepSpec>,
+    pub(crate) dev_dependencies: HashMap<String, CargoDepSpec>,
     #[serde(default)]
     #[serde(rename = "build-dependencies")]
-    build_dependencies: HashMap<String, CargoDepSpec>,
+    pub(crate) build_dependencies: HashMap<String, CargoDepSpec>,
 }
 
 /// Collects all dependencies from found Cargo.toml files into a HashSet.
@@ -340,7 +1019,33 @@ struct CargoToml {
 ///
 /// * `Result<HashSet<String>>` - A HashSet containing all unique dependency names
 pub fn collect_dependencies(cargo_toml_files: &[PathBuf]) -> Result<HashSet<String>> {
-    let mut dependencies = HashSet::new();
+    Ok(collect_dependency_provenance(cargo_toml_files)?
+        .into_keys()
+        .collect())
+}
+
+/// Collects all dependencies from found Cargo.toml files, along with the manifests
+/// declaring each of them.
+///
+/// This answers "why is this dependency here?" in projects with several manifests.
+/// Regular, dev and build dependencies are all included.
+///
+/// # Arguments
+///
+/// * `cargo_toml_files` - A slice of paths to Cargo.toml files
+///
+/// # Returns
+///
+/// * `Result<BTreeMap<String, Vec<PathBuf>>>` - Each dependency name mapped to the
+///   manifests that declare it, in the order they were given
+///
+/// # Errors
+///
+/// Returns an error if a Cargo.toml file cannot be read or parsed
+pub fn collect_dependency_provenance(
+    cargo_toml_files: &[PathBuf],
+) -> Result<BTreeMap<String, Vec<PathBuf>>> {
+    let mut provenance: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
 
     for path in cargo_toml_files {
         let cargo_toml_content = fs::read_to_string(path)
@@ -349,23 +1054,88 @@ pub fn collect_dependencies(cargo_toml_files: &[PathBuf]) -> Result<HashSet<Stri
         let cargo_toml: CargoToml = toml::from_str(&cargo_toml_content)
             .with_context(|| format!("Failed to parse Cargo.toml file: {}", path.display()))?;
 
-        // Add regular dependencies
-        for dep_name in cargo_toml.dependencies.keys() {
-            dependencies.insert(dep_name.clone());
+        // Regular, dev and build dependencies
+        let dep_names = cargo_toml
+            .dependencies
+            .keys()
+            .chain(cargo_toml.dev_dependencies.keys())
+            .chain(cargo_toml.build_dependencies.keys());
+
+        for dep_name in dep_names {
+            let sources = provenance.entry(dep_name.clone()).or_default();
+            if !sources.contains(path) {
+                sources.push(path.clone());
+            }
         }
+    }
+
+    Ok(provenance)
+}
 
-        // Add dev dependencies
-        for dep_name in cargo_toml.dev_dependencies.keys() {
-            dependencies.insert(dep_name.clone());
+/// The section of a Cargo.toml a dependency is declared in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DependencyKind {
+    /// `[dependencies]`
+    Normal,
+    /// `[dev-dependencies]`
+    Dev,
+    /// `[build-dependencies]`
+    Build,
+}
+
+impl DependencyKind {
+    /// The name of the kind, as cargo reports it
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DependencyKind::Normal => "normal",
+            DependencyKind::Dev => "dev",
+            DependencyKind::Build => "build",
         }
+    }
+}
+
+/// Collects the kinds each dependency is declared as across Cargo.toml files.
+///
+/// A dependency can be declared with several kinds, e.g. as a normal dependency of
+/// one member and a dev-dependency of another.
+///
+/// # Arguments
+///
+/// * `cargo_toml_files` - A slice of paths to Cargo.toml files
+///
+/// # Returns
+///
+/// * `Result<BTreeMap<String, BTreeSet<DependencyKind>>>` - Each dependency name
+///   mapped to the kinds it is declared as
+///
+/// # Errors
+///
+/// Returns an error if a Cargo.toml file cannot be read or parsed
+pub fn collect_dependency_kinds(
+    cargo_toml_files: &[PathBuf],
+) -> Result<BTreeMap<String, BTreeSet<DependencyKind>>> {
+    let mut kinds: BTreeMap<String, BTreeSet<DependencyKind>> = BTreeMap::new();
 
-        // Add build dependencies
-        for dep_name in cargo_toml.build_dependencies.keys() {
-            dependencies.insert(dep_name.clone());
+    for path in cargo_toml_files {
+        let cargo_toml_content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read Cargo.toml file: {}", path.display()))?;
+        let cargo_toml: CargoToml = toml::from_str(&cargo_toml_content)
+            .with_context(|| format!("Failed to parse Cargo.toml file: {}", path.display()))?;
+
+        let sections = [
+            (&cargo_toml.dependencies, DependencyKind::Normal),
+            (&cargo_toml.dev_dependencies, DependencyKind::Dev),
+            (&cargo_toml.build_dependencies, DependencyKind::Build),
+        ];
+        for (deps, kind) in sections {
+            for name in deps.keys() {
+                kinds.entry(name.clone()).or_default().insert(kind);
+            }
         }
     }
 
-    Ok(dependencies)
+    Ok(kinds)
 }
 
 /// Extracts dependency information from a single Cargo.toml file.
@@ -406,8 +1176,6 @@ pub fn collect_dependencies(cargo_toml_files: &[PathBuf]) -> Result<HashSet<Stri
 /// }
 /// ```
 pub fn extract_dependency_info(cargo_toml_path: &Path) -> Result<DependencyCollection> {
-    let mut dependencies = DependencyCollection::new();
-
     let cargo_toml_content = fs::read_to_string(cargo_toml_path).with_context(|| {
         format!(
             "Failed to read Cargo.toml file: {}",
@@ -415,999 +1183,4450 @@ pub fn extract_dependency_info(cargo_toml_path: &Path) -> Result<DependencyColle
         )
     })?;
 
-    // Alternative approach using the CargoToml struct
-    let cargo_toml: CargoToml = toml::from_str(&cargo_toml_content).with_context(|| {
-        format!(
-            "Failed to parse Cargo.toml file: {}",
-            cargo_toml_path.display()
-        )
-    })?;
+    let dependencies =
+        extract_dependency_info_from_str(&cargo_toml_content).with_context(|| {
+            format!(
+                "Failed to parse Cargo.toml file: {}",
+                cargo_toml_path.display()
+            )
+        })?;
+    log::debug!(
+        target: LOG_TARGET_DISCOVERY,
+        "Read {} dependencies from {}",
+        dependencies.len(),
+        cargo_toml_path.display()
+    );
+    Ok(dependencies)
+}
+
+/// Extracts dependency information from Cargo.toml content held in memory.
+///
+/// This is the same as [`extract_dependency_info`], but takes the manifest content
+/// directly, e.g. when it was fetched from a registry API rather than read from disk.
+///
+/// # Arguments
+///
+/// * `content` - The content of a Cargo.toml file
+///
+/// # Returns
+///
+/// * `Result<DependencyCollection>` - A collection of dependencies from the manifest
+///
+/// # Errors
+///
+/// Returns an error if the content cannot be parsed as a valid Cargo.toml
+///
+/// # Examples
+///
+/// ```rust
+/// use depbank::extract_dependency_info_from_str;
+///
+/// let manifest = r#"
+/// [package]
+/// name = "demo"
+/// version = "0.1.0"
+///
+/// [dependencies]
+/// serde = "1.0"
+/// "#;
+///
+/// let dependencies = extract_dependency_info_from_str(manifest).unwrap();
+/// assert_eq!(dependencies.get_version("serde"), Some(&"1.0".to_string()));
+/// ```
+pub fn extract_dependency_info_from_str(content: &str) -> Result<DependencyCollection> {
+    let mut dependencies = DependencyCollection::new();
+
+    let cargo_toml: CargoToml =
+        toml::from_str(content).context("Failed to parse Cargo.toml content")?;
 
     // Process regular dependencies
     for (name, spec) in &cargo_toml.dependencies {
         let version = extract_version_from_spec(spec);
-        dependencies.add(Dependency::new(name, version));
+        dependencies.add(Dependency::new(package_name_from_spec(name, spec), version));
     }
 
     // Process dev dependencies
     for (name, spec) in &cargo_toml.dev_dependencies {
         let version = extract_version_from_spec(spec);
-        dependencies.add(Dependency::new(name, version));
+        dependencies.add(Dependency::new(package_name_from_spec(name, spec), version));
     }
 
     // Process build dependencies
     for (name, spec) in &cargo_toml.build_dependencies {
         let version = extract_version_from_spec(spec);
-        dependencies.add(Dependency::new(name, version));
+        dependencies.add(Dependency::new(package_name_from_spec(name, spec), version));
     }
 
     Ok(dependencies)
 }
 
-/// Helper function to extract version from a CargoDepSpec
-fn extract_version_from_spec(spec: &CargoDepSpec) -> String {
-    match spec {
-        CargoDepSpec::Simple(version) => version.clone(),
-        CargoDepSpec::Detailed(table) => {
-            // Check for workspace = true first
-            if let Some(workspace) = table.get("workspace") {
-                if workspace.as_bool().unwrap_or(false) {
-                    // Use a placeholder. The actual version comes from Cargo.lock or workspace definition.
-                    // For `resolve_dependency_versions`, we just need the name.
-                    // If we needed the *constraint* from the workspace root, we'd need to parse that too.
-                    return "workspace".to_string();
-                }
-            }
-            // Otherwise, look for an inline version
-            if let Some(version) = table.get("version") {
-                if let Some(v) = version.as_str() {
-                    return v.to_string();
-                }
-            }
-            // Default if neither workspace nor version is specified clearly
-            "*".to_string()
-        }
-    }
-}
-
-/// Represents a package in Cargo.lock
-#[derive(Debug, Deserialize)]
-struct CargoLockPackage {
-    name: String,
-    version: String,
-    #[allow(dead_code)] // Kept for compatibility with Cargo.lock format
-    source: Option<String>,
-}
-
-/// Structure for parsing Cargo.lock
-#[derive(Debug, Deserialize)]
-struct CargoLock {
-    #[serde(default)]
-    package: Vec<CargoLockPackage>,
-}
-
-/// Resolves exact dependency versions from Cargo.lock file.
+/// Extracts the dependencies declared by a crate extracted in the cargo registry.
 ///
-/// This function reads the Cargo.lock file to resolve the exact versions of dependencies
-/// based on their version requirements specified in Cargo.toml. It helps find the precise
-/// version being used in your project.
+/// This reads the published `Cargo.toml` at `<registry>/<name>-<version>/Cargo.toml`,
+/// so a crate's own dependencies can be discovered from its sources, e.g. to document
+/// a crate together with the crates it builds on. Only the dependencies needed to use
+/// the crate are returned: regular and build dependencies, but not dev-dependencies.
+///
+/// The returned versions are the requirements declared by the crate, not resolved
+/// versions.
 ///
 /// # Arguments
 ///
-/// * `cargo_lock_path` - Path to the Cargo.lock file
-/// * `dependencies` - DependencyCollection containing dependency information from Cargo.toml
+/// * `registry_path` - Path to the cargo registry directory
+/// * `dependency` - The crate whose dependencies to read
 ///
 /// # Returns
 ///
-/// * `Result<DependencyCollection>` - A collection of dependencies with resolved exact versions
+/// * `Result<DependencyCollection>` - The dependencies declared by the crate
 ///
 /// # Errors
 ///
-/// Returns an error if:
-/// - The Cargo.lock file does not exist
-/// - The Cargo.lock file cannot be read
-/// - The Cargo.lock file cannot be parsed as a valid TOML format
+/// Returns an error if the crate's Cargo.toml cannot be read or parsed
 ///
 /// # Examples
 ///
 /// ```rust,no_run
-/// use depbank::{extract_dependency_info, resolve_dependency_versions};
-/// use std::path::Path;
+/// use depbank::{Dependency, dependencies_of_crate, resolve_registry_path};
 ///
-/// let cargo_toml = Path::new("./Cargo.toml");
-/// let cargo_lock = Path::new("./Cargo.lock");
-///
-/// let dependency_info = extract_dependency_info(cargo_toml).unwrap();
-/// let resolved_versions = resolve_dependency_versions(cargo_lock, &dependency_info).unwrap();
-///
-/// println!("Resolved {} dependency versions", resolved_versions.len());
-/// for dep in resolved_versions.as_slice() {
+/// let registry_path = resolve_registry_path().unwrap();
+/// let serde_json = Dependency::new("serde_json", "1.0.140");
+/// for dep in dependencies_of_crate(&registry_path, &serde_json).unwrap().iter() {
 ///     println!("  {} - {}", dep.name, dep.version);
 /// }
 /// ```
-pub fn resolve_dependency_versions<P: AsRef<Path>>(
-    cargo_lock_path: P,
-    dependencies: &DependencyCollection,
+pub fn dependencies_of_crate(
+    registry_path: &Path,
+    dependency: &Dependency,
 ) -> Result<DependencyCollection> {
-    let cargo_lock_path = cargo_lock_path.as_ref();
-
-    // Check if Cargo.lock exists
-    if !cargo_lock_path.exists() {
-        return Err(anyhow::anyhow!(
-            "Cargo.lock file does not exist: {}",
-            cargo_lock_path.display()
-        ));
-    }
-
-    // Read and parse the Cargo.lock file
-    let cargo_lock_content = fs::read_to_string(cargo_lock_path).with_context(|| {
+    let cargo_toml_path = dependency
+        .get_registry_path(registry_path)
+        .join("Cargo.toml");
+    let cargo_toml_content = fs::read_to_string(&cargo_toml_path).with_context(|| {
         format!(
-            "Failed to read Cargo.lock file: {}",
-            cargo_lock_path.display()
+            "Failed to read Cargo.toml file: {}",
+            cargo_toml_path.display()
         )
     })?;
-
-    let cargo_lock: CargoLock = toml::from_str(&cargo_lock_content).with_context(|| {
+    let cargo_toml: CargoToml = toml::from_str(&cargo_toml_content).with_context(|| {
         format!(
-            "Failed to parse Cargo.lock file: {}",
-            cargo_lock_path.display()
+            "Failed to parse Cargo.toml file: {}",
+            cargo_toml_path.display()
         )
     })?;
 
-    // Create a mapping of dependency names to their exact versions
-    let mut resolved_versions = DependencyCollection::new();
-    let mut package_versions: HashMap<String, Vec<String>> = HashMap::new();
+    let mut dependencies = DependencyCollection::new();
+    for (name, spec) in cargo_toml
+        .dependencies
+        .iter()
+        .chain(&cargo_toml.build_dependencies)
+    {
+        dependencies.add(Dependency::new(
+            package_name_from_spec(name, spec),
+            extract_version_from_spec(spec),
+        ));
+    }
 
-    // First, collect all versions for each package
-    for package in &cargo_lock.package {
-        package_versions
-            .entry(package.name.clone())
-            .or_default()
-            .push(package.version.clone());
+    Ok(dependencies)
+}
+
+/// The name of the package a dependency refers to: its `package` key when the
+/// dependency is renamed, e.g. `rand_old = { package = "rand", version = "0.7" }`,
+/// its key otherwise
+fn package_name_from_spec<'a>(name: &'a str, spec: &'a CargoDepSpec) -> &'a str {
+    match spec {
+        CargoDepSpec::Detailed(table) => table
+            .get("package")
+            .and_then(toml::Value::as_str)
+            .unwrap_or(name),
+        _ => name,
     }
+}
 
-    // Now, resolve each dependency
-    for dep in dependencies.as_slice() {
-        if let Some(versions) = package_versions.get(&dep.name) {
-            // Get the most recent version (assuming they are sorted, which might not always be true)
-            // For a more accurate approach, we would need to parse and compare semver
-            if let Some(version) = versions.last() {
-                resolved_versions.add(Dependency::new(&dep.name, version));
+/// Helper function to extract version from a CargoDepSpec
+fn extract_version_from_spec(spec: &CargoDepSpec) -> String {
+    match spec {
+        CargoDepSpec::Simple(version) => version.clone(),
+        CargoDepSpec::Detailed(table) => {
+            // Check for workspace = true first
+            if let Some(workspace) = table.get("workspace")
+                && workspace.as_bool().unwrap_or(false)
+            {
+                // Use a placeholder. The actual version comes from Cargo.lock or workspace definition.
+                // For `resolve_dependency_versions`, we just need the name.
+                // If we needed the *constraint* from the workspace root, we'd need to parse that too.
+                return "workspace".to_string();
             }
+            // Otherwise, look for an inline version
+            if let Some(version) = table.get("version").and_then(version_from_value) {
+                return version;
+            }
+            // Default if neither workspace nor version is specified clearly
+            "*".to_string()
         }
+        // Not a valid dependency for cargo, but not worth failing the whole manifest
+        CargoDepSpec::Other(value) => version_from_value(value).unwrap_or_else(|| "*".to_string()),
     }
+}
 
-    Ok(resolved_versions)
+/// Reads a version requirement from a value that may not be a string, such as a
+/// bare number or a nested `{ workspace = true }` table
+fn version_from_value(value: &toml::Value) -> Option<String> {
+    match value {
+        toml::Value::String(version) => Some(version.clone()),
+        toml::Value::Integer(version) => Some(version.to_string()),
+        toml::Value::Float(version) => Some(version.to_string()),
+        toml::Value::Table(table)
+            if table
+                .get("workspace")
+                .and_then(toml::Value::as_bool)
+                .unwrap_or(false) =>
+        {
+            Some("workspace".to_string())
+        }
+        _ => None,
+    }
 }
 
-/// Finds the Cargo.lock file in the workspace.
+/// Resolves exact dependency versions from Cargo.lock file.
 ///
-/// This function looks for Cargo.lock in the current directory and parent directories.
+/// This function reads the Cargo.lock file to resolve the exact versions of dependencies
+/// based on their version requirements specified in Cargo.toml. It helps find the precise
+/// version being used in your project.
+///
+/// Dependencies that Cargo.lock has no package of are left out, with a
+/// [`WarningKind::NotInLockfile`] warning. Those whose requirement no locked version
+/// satisfies resolve to the last locked version, with a
+/// [`WarningKind::NoMatchingLockedVersion`] warning.
 ///
 /// # Arguments
 ///
-/// * `start_dir` - The directory to start searching from
+/// * `cargo_lock_path` - Path to the Cargo.lock file
+/// * `dependencies` - DependencyCollection containing dependency information from Cargo.toml
 ///
 /// # Returns
 ///
-/// * `Result<PathBuf>` - Path to the found Cargo.lock file
-pub fn find_cargo_lock<P: AsRef<Path>>(start_dir: P) -> Result<PathBuf> {
-    let start_dir = start_dir.as_ref();
-    let mut current_dir = start_dir.to_path_buf();
-
-    // Check the current directory first
-    let cargo_lock = current_dir.join("Cargo.lock");
-    if cargo_lock.exists() {
-        return Ok(cargo_lock);
-    }
+/// * `Result<DependencyCollection>` - A collection of dependencies with resolved exact versions
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The Cargo.lock file does not exist
+/// - The Cargo.lock file cannot be read
+/// - The Cargo.lock file cannot be parsed as a valid TOML format
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use depbank::{extract_dependency_info, resolve_dependency_versions};
+/// use std::path::Path;
+///
+/// let cargo_toml = Path::new("./Cargo.toml");
+/// let cargo_lock = Path::new("./Cargo.lock");
+///
+/// let dependency_info = extract_dependency_info(cargo_toml).unwrap();
+/// let resolved_versions = resolve_dependency_versions(cargo_lock, &dependency_info).unwrap();
+///
+/// println!("Resolved {} dependency versions", resolved_versions.len());
+/// for dep in resolved_versions.as_slice() {
+///     println!("  {} - {}", dep.name, dep.version);
+/// }
+/// ```
+pub fn resolve_dependency_versions<P: AsRef<Path>>(
+    cargo_lock_path: P,
+    dependencies: &DependencyCollection,
+) -> Result<DependencyCollection> {
+    let cargo_lock_path = cargo_lock_path.as_ref();
 
-    // Then check parent directories
-    while let Some(parent) = current_dir.parent() {
-        current_dir = parent.to_path_buf();
-        let cargo_lock = current_dir.join("Cargo.lock");
-        if cargo_lock.exists() {
-            return Ok(cargo_lock);
-        }
+    // Check if Cargo.lock exists
+    if !cargo_lock_path.exists() {
+        return Err(anyhow::anyhow!(
+            "Cargo.lock file does not exist: {}",
+            cargo_lock_path.display()
+        ));
     }
 
-    Err(anyhow::anyhow!(
-        "Cargo.lock file not found in current directory or its parents"
+    Ok(resolve_dependency_versions_from_lock(
+        &ParsedLock::read(cargo_lock_path)?,
+        dependencies,
     ))
 }
 
-/// Resolves the path to the Cargo registry directory.
+/// Resolves exact dependency versions from Cargo.lock content held in memory.
 ///
-/// This function locates the local Cargo registry where dependency source code is stored.
-/// It finds the most recently modified registry index directory, which is typically the active one.
+/// This is the same as [`resolve_dependency_versions`], but takes the lockfile
+/// content directly instead of a path.
+///
+/// # Arguments
+///
+/// * `content` - The content of a Cargo.lock file
+/// * `dependencies` - DependencyCollection containing dependency information from Cargo.toml
 ///
 /// # Returns
 ///
-/// * `Result<PathBuf>` - Path to the cargo registry directory
+/// * `Result<DependencyCollection>` - A collection of dependencies with resolved exact versions
 ///
 /// # Errors
 ///
-/// Returns an error if:
-/// - The home directory cannot be found
-/// - The Cargo registry directory does not exist
-/// - There are permission issues accessing the directory
-/// - No registry directories are found
+/// Returns an error if the content cannot be parsed as a valid Cargo.lock
 ///
 /// # Examples
 ///
-/// ```rust,no_run
-/// use depbank::resolve_registry_path;
+/// ```rust
+/// use depbank::{extract_dependency_info_from_str, resolve_dependency_versions_from_str};
 ///
-/// match resolve_registry_path() {
-///     Ok(path) => println!("Cargo registry found at: {}", path.display()),
-///     Err(e) => eprintln!("Error: {}", e),
-/// }
+/// let manifest = "[dependencies]\nserde = \"1.0\"\n";
+/// let lock = "version = 3\n\n[[package]]\nname = \"serde\"\nversion = \"1.0.197\"\n";
+///
+/// let dependency_info = extract_dependency_info_from_str(manifest).unwrap();
+/// let resolved = resolve_dependency_versions_from_str(lock, &dependency_info).unwrap();
+/// assert_eq!(resolved.get_version("serde"), Some(&"1.0.197".to_string()));
 /// ```
-pub fn resolve_registry_path() -> Result<PathBuf> {
-    // Get the home directory
-    let home_dir =
-        dirs::home_dir().ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
+pub fn resolve_dependency_versions_from_str(
+    content: &str,
+    dependencies: &DependencyCollection,
+) -> Result<DependencyCollection> {
+    Ok(resolve_dependency_versions_from_lock(
+        &ParsedLock::parse(content)?,
+        dependencies,
+    ))
+}
 
-    // Construct the path to the cargo registry src directory
-    let registry_dir = home_dir.join(".cargo").join("registry").join("src");
+/// Resolves exact dependency versions from an already parsed Cargo.lock.
+///
+/// This is the same as [`resolve_dependency_versions`], for a lockfile that other
+/// steps of an analysis read as well.
+///
+/// # Arguments
+///
+/// * `cargo_lock` - The parsed Cargo.lock
+/// * `dependencies` - DependencyCollection containing dependency information from Cargo.toml
+///
+/// # Returns
+///
+/// * `DependencyCollection` - A collection of dependencies with resolved exact versions
+///
+/// # Examples
+///
+/// ```rust
+/// use depbank::{ParsedLock, extract_dependency_info_from_str, resolve_dependency_versions_from_lock};
+///
+/// let manifest = "[dependencies]\nserde = \"1.0\"\n";
+/// let lock = ParsedLock::parse("version = 3\n\n[[package]]\nname = \"serde\"\nversion = \"1.0.197\"\n").unwrap();
+///
+/// let dependency_info = extract_dependency_info_from_str(manifest).unwrap();
+/// let resolved = resolve_dependency_versions_from_lock(&lock, &dependency_info);
+/// assert_eq!(resolved.get_version("serde"), Some(&"1.0.197".to_string()));
+/// ```
+pub fn resolve_dependency_versions_from_lock(
+    cargo_lock: &ParsedLock,
+    dependencies: &DependencyCollection,
+) -> DependencyCollection {
+    // Create a mapping of dependency names to their exact versions
+    let mut resolved_versions = DependencyCollection::new();
+    let mut package_versions: HashMap<String, Vec<&CargoLockPackage>> = HashMap::new();
 
-    // Check if the registry directory exists
-    if !registry_dir.exists() {
-        return Err(anyhow::anyhow!(
-            "Cargo registry directory not found: {}",
-            registry_dir.display()
-        ));
+    // First, collect all versions for each package, keyed by normalized name so that
+    // e.g. a declared `foo_bar` matches the locked `foo-bar` package
+    for package in &cargo_lock.package {
+        package_versions
+            .entry(normalize_crate_name(&package.name))
+            .or_default()
+            .push(package);
     }
 
-    // Find all directories in the registry
-    let entries = fs::read_dir(&registry_dir).with_context(|| {
-        format!(
-            "Failed to read cargo registry directory: {}",
-            registry_dir.display()
-        )
-    })?;
-
-    // Find the most recently modified directory
-    let mut latest_dir: Option<(PathBuf, SystemTime)> = None;
-
-    for entry in entries {
-        let entry = entry?;
-        let path = entry.path();
+    // Now, resolve each dependency. Manifests often declare the same requirement, so
+    // each unlocked requirement is only warned about once
+    let mut warned = HashSet::new();
+    for dep in dependencies.as_slice() {
+        let packages = package_versions.get(&normalize_crate_name(&dep.name));
+        let locked_versions: Vec<&str> = packages
+            .into_iter()
+            .flatten()
+            .map(|package| package.version.as_str())
+            .collect();
+        if warned.insert((normalize_crate_name(&dep.name), dep.version.clone()))
+            && let Some(warning) = unlocked_dependency_warning(dep, &locked_versions)
+        {
+            emit_warning(warning);
+        }
 
-        if path.is_dir() {
-            if let Ok(metadata) = fs::metadata(&path) {
-                if let Ok(modified) = metadata.modified() {
-                    match &latest_dir {
-                        Some((_, latest_modified)) if modified > *latest_modified => {
-                            latest_dir = Some((path, modified));
-                        }
-                        None => {
-                            latest_dir = Some((path, modified));
-                        }
-                        _ => {}
-                    }
-                }
+        if let Some(packages) = packages {
+            // Of several locked versions, take the last one the requirement matches,
+            // e.g. `rand 0.7.3` for `0.7` when `rand 0.8.5` is locked too. Cargo.lock
+            // lists versions in ascending order
+            let requirement = semver::VersionReq::parse(&dep.version).ok();
+            let matching = requirement.and_then(|requirement| {
+                packages.iter().rev().find(|package| {
+                    semver::Version::parse(&package.version)
+                        .is_ok_and(|version| requirement.matches(&version))
+                })
+            });
+            if let Some(package) = matching.or_else(|| packages.last()) {
+                log::debug!(
+                    target: LOG_TARGET_RESOLVE,
+                    "Resolved {} {} to {} {}",
+                    dep.name,
+                    dep.version,
+                    package.name,
+                    package.version
+                );
+                // Use the package's canonical name, which is what the registry uses
+                let mut resolved = Dependency::new(&package.name, &package.version);
+                resolved.source.clone_from(&package.source);
+                resolved_versions.add(resolved);
             }
+        } else {
+            log::debug!(target: LOG_TARGET_RESOLVE, "{} {} is not in Cargo.lock", dep.name, dep.version);
         }
     }
 
-    // Return the most recently modified directory
-    match latest_dir {
-        Some((dir, _)) => Ok(dir),
-        None => Err(anyhow::anyhow!(
-            "No registry directories found in: {}",
-            registry_dir.display()
-        )),
+    resolved_versions
+}
+
+/// The warning for a declared dependency without a matching package in Cargo.lock,
+/// which usually means a stale lockfile or a misspelled name.
+///
+/// Dependencies declared without a version requirement (`*` or `workspace`) are
+/// left to [`resolve_unresolvable_versions`], and invalid requirements to the
+/// warnings about them.
+fn unlocked_dependency_warning(dep: &Dependency, locked_versions: &[&str]) -> Option<Warning> {
+    if matches!(dep.version.as_str(), "*" | "workspace") {
+        return None;
     }
+
+    let Some(last) = locked_versions.last() else {
+        return Some(Warning::new(
+            WarningKind::NotInLockfile,
+            Some(&dep.name),
+            format!(
+                "{} {} is declared, but Cargo.lock has no package named {}; the lockfile \
+                 may be stale or the name misspelled",
+                dep.name, dep.version, dep.name
+            ),
+        ));
+    };
+
+    let requirement = semver::VersionReq::parse(&dep.version).ok()?;
+    let satisfied = locked_versions.iter().any(|version| {
+        semver::Version::parse(version).is_ok_and(|version| requirement.matches(&version))
+    });
+    (!satisfied).then(|| {
+        Warning::new(
+            WarningKind::NoMatchingLockedVersion,
+            Some(&dep.name),
+            format!(
+                "{} {} is declared, but Cargo.lock only has {} {}, which the requirement \
+                 does not allow; using {}",
+                dep.name,
+                dep.version,
+                dep.name,
+                locked_versions.join(", "),
+                last
+            ),
+        )
+    })
 }
 
-/// Constructs the full path to a dependency's source code.
+/// Lists every package locked in a Cargo.lock, i.e. all direct and transitive
+/// dependencies of the project along with the project's own packages.
 ///
 /// # Arguments
 ///
-/// * `registry_path` - Path to the cargo registry directory
-/// * `dependency_name` - Name of the dependency
-/// * `dependency_version` - Version of the dependency
+/// * `cargo_lock_path` - Path to the Cargo.lock file
 ///
 /// # Returns
 ///
-/// * `PathBuf` - Full path to the dependency's source code
-pub fn construct_dependency_path(
-    registry_path: &Path,
-    dependency_name: &str,
-    dependency_version: &str,
-) -> PathBuf {
-    registry_path.join(format!("{}-{}", dependency_name, dependency_version))
-}
-
-/// Checks if a dependency is available locally in the cargo registry.
+/// * `Result<DependencyCollection>` - The locked packages with their versions and sources
 ///
-/// # Arguments
+/// # Errors
 ///
-/// * `registry_path` - Path to the cargo registry directory
-/// * `dependency` - The dependency to check
+/// Returns an error if the Cargo.lock file cannot be read or parsed
 ///
-/// # Returns
+/// # Examples
 ///
-/// * `bool` - True if the dependency is available locally, false otherwise
-pub fn is_dependency_available(registry_path: &Path, dependency: &Dependency) -> bool {
-    dependency.is_available_in_registry(registry_path)
+/// ```rust,no_run
+/// use depbank::locked_packages;
+///
+/// let packages = locked_packages("Cargo.lock").unwrap();
+/// println!("{} packages locked", packages.len());
+/// ```
+pub fn locked_packages<P: AsRef<Path>>(cargo_lock_path: P) -> Result<DependencyCollection> {
+    Ok(ParsedLock::read(cargo_lock_path)?.packages())
 }
 
-/// Overloaded version of is_dependency_available that takes separate name and version strings.
+/// Lists every package locked in Cargo.lock content held in memory.
+///
+/// This is the same as [`locked_packages`], but takes the lockfile content directly
+/// instead of a path.
 ///
 /// # Arguments
 ///
-/// * `registry_path` - Path to the cargo registry directory
-/// * `dependency_name` - Name of the dependency
-/// * `dependency_version` - Version of the dependency
+/// * `content` - The content of a Cargo.lock file
 ///
 /// # Returns
 ///
-/// * `bool` - True if the dependency is available locally, false otherwise
-pub fn is_dependency_available_by_parts(
-    registry_path: &Path,
-    dependency_name: &str,
-    dependency_version: &str,
-) -> bool {
-    let dependency = Dependency::new(dependency_name, dependency_version);
-    is_dependency_available(registry_path, &dependency)
+/// * `Result<DependencyCollection>` - The locked packages with their versions and sources
+///
+/// # Errors
+///
+/// Returns an error if the content cannot be parsed as a valid Cargo.lock
+///
+/// # Examples
+///
+/// ```rust
+/// use depbank::locked_packages_from_str;
+///
+/// let lock = "version = 3\n\n[[package]]\nname = \"serde\"\nversion = \"1.0.197\"\n";
+/// let packages = locked_packages_from_str(lock).unwrap();
+/// assert_eq!(packages.get_version("serde"), Some(&"1.0.197".to_string()));
+/// ```
+pub fn locked_packages_from_str(content: &str) -> Result<DependencyCollection> {
+    Ok(ParsedLock::parse(content)?.packages())
 }
 
-/// Resolves the paths for all dependencies.
+/// Resolves the version of a single crate used by a project.
+///
+/// This finds the project's Cargo.lock and looks the crate up in it, without running
+/// the rest of the pipeline. When the lockfile contains several versions of the crate
+/// (e.g. `rand` 0.7 and 0.8 pulled in by different dependencies), the highest version
+/// matching the requirement declared in the project's manifests is returned. If the
+/// project doesn't declare the crate itself, the highest locked version is returned.
 ///
 /// # Arguments
 ///
-/// * `dependencies` - HashMap containing dependency names and their versions
+/// * `project_root` - The root directory of the project
+/// * `crate_name` - The name of the crate to look up
 ///
 /// # Returns
 ///
-/// * `Result<HashMap<String, PathBuf>>` - HashMap mapping dependency names to their local paths
-pub fn resolve_dependency_paths(
-    dependencies: &HashMap<String, String>,
-) -> Result<HashMap<String, PathBuf>> {
-    let registry_path = resolve_registry_path()?;
-    let mut dependency_paths = HashMap::new();
-
-    for (name, version) in dependencies {
-        let dependency_path = construct_dependency_path(&registry_path, name, version);
-
-        if dependency_path.exists() && dependency_path.is_dir() {
-            dependency_paths.insert(name.clone(), dependency_path);
-        }
-    }
-
-    Ok(dependency_paths)
-}
-
-/// Generates code bank for a dependency.
+/// * `Result<Option<String>>` - The resolved version, or `None` if the crate is not in the lockfile
 ///
-/// # Arguments
+/// # Errors
 ///
-/// * `source_path` - Path to the dependency's source code
-/// * `output_dir` - Path to the output directory for code bank files
-/// * `dependency_name` - Name of the dependency
+/// Returns an error if:
+/// - No Cargo.lock can be found for the project
+/// - The Cargo.lock or a Cargo.toml file cannot be read or parsed
 ///
-/// # Returns
+/// # Examples
 ///
-/// * `Result<PathBuf>` - Path to the generated code bank file
-pub fn generate_code_bank(
-    source_path: &Path,
-    output_dir: &Path,
-    dependency_name: &str,
-) -> Result<PathBuf> {
-    // Check if source path exists
-    if !source_path.exists() || !source_path.is_dir() {
-        return Err(anyhow::anyhow!(
-            "Source path does not exist or is not a directory: {}",
-            source_path.display()
-        ));
+/// ```rust,no_run
+/// use depbank::resolve_single_version;
+///
+/// match resolve_single_version(".", "serde").unwrap() {
+///     Some(version) => println!("serde {}", version),
+///     None => println!("serde is not used"),
+/// }
+/// ```
+pub fn resolve_single_version<P: AsRef<Path>>(
+    project_root: P,
+    crate_name: &str,
+) -> Result<Option<String>> {
+    let project_root = project_root.as_ref();
+    let cargo_lock = ParsedLock::read(find_cargo_lock(project_root)?)?;
+
+    let mut versions: Vec<semver::Version> = cargo_lock
+        .package
+        .iter()
+        .filter(|package| normalize_crate_name(&package.name) == normalize_crate_name(crate_name))
+        .filter_map(|package| semver::Version::parse(&package.version).ok())
+        .collect();
+    versions.sort();
+
+    if versions.len() <= 1 {
+        return Ok(versions.pop().map(|version| version.to_string()));
     }
 
-    // Check if output directory exists, create it if not
-    if !output_dir.exists() {
-        fs::create_dir_all(output_dir).with_context(|| {
-            format!(
-                "Failed to create output directory: {}",
-                output_dir.display()
-            )
-        })?;
-    }
+    // Several versions are locked, pick the one selected for the declared requirement
+    let requirements = declared_requirements(project_root, crate_name)?;
+    let selected = versions
+        .iter()
+        .rev()
+        .find(|version| requirements.iter().any(|req| req.matches(version)))
+        .or_else(|| versions.last());
 
-    // Create the output file path
-    let output_file = output_dir.join(format!("{}.md", dependency_name));
+    Ok(selected.map(|version| version.to_string()))
+}
 
-    // Create a new code bank generator
-    let code_bank = CodeBank::try_new().with_context(|| "Failed to create CodeBank instance")?;
+/// Collects the version requirements for a crate declared in a project's manifests
+fn declared_requirements(project_root: &Path, crate_name: &str) -> Result<Vec<semver::VersionReq>> {
+    let mut requirements = Vec::new();
 
-    // Generate documentation for the source directory
-    // ignore directories: examples, tests, benches
-    let ignore_dirs = vec![
-        "examples".to_string(),
-        "tests".to_string(),
-        "benches".to_string(),
-    ];
-    let config = BankConfig::new(source_path, BankStrategy::Summary, ignore_dirs);
-    let content = code_bank.generate(&config).with_context(|| {
-        format!(
-            "Failed to generate code bank for: {}",
-            source_path.display()
-        )
-    })?;
+    for cargo_toml_path in find_cargo_toml_files(project_root, &DiscoveryOptions::default())? {
+        let content = fs::read_to_string(&cargo_toml_path).with_context(|| {
+            format!(
+                "Failed to read Cargo.toml file: {}",
+                cargo_toml_path.display()
+            )
+        })?;
+        let manifest: toml::Value = toml::from_str(&content).with_context(|| {
+            format!(
+                "Failed to parse Cargo.toml file: {}",
+                cargo_toml_path.display()
+            )
+        })?;
 
-    // Write the content to the output file
-    fs::write(&output_file, content).with_context(|| {
-        format!(
-            "Failed to write code bank to file: {}",
-            output_file.display()
-        )
-    })?;
+        // Requirements may be declared directly or centrally for the workspace
+        let tables = [
+            manifest.get("dependencies"),
+            manifest.get("dev-dependencies"),
+            manifest.get("build-dependencies"),
+            manifest
+                .get("workspace")
+                .and_then(|workspace| workspace.get("dependencies")),
+        ];
+        for table in tables.into_iter().flatten() {
+            let Some(spec) = table.get(crate_name) else {
+                continue;
+            };
+            let requirement = match spec {
+                toml::Value::String(version) => Some(version.as_str()),
+                toml::Value::Table(table) => table.get("version").and_then(|v| v.as_str()),
+                _ => None,
+            };
+            if let Some(req) = requirement.and_then(|req| semver::VersionReq::parse(req).ok()) {
+                requirements.push(req);
+            }
+        }
+    }
 
-    Ok(output_file)
+    Ok(requirements)
 }
 
-/// Generates code banks for all available dependencies.
+/// Derives a name for a project from its root directory.
 ///
-/// This function creates code bank documentation files for each dependency using the codebank library.
-/// It processes each dependency, generates a summary documentation, and saves it to the specified output directory.
+/// The name is the `[package] name` of the root Cargo.toml. Workspaces without a root
+/// package, and projects whose manifest cannot be read, are named after their
+/// directory.
 ///
 /// # Arguments
 ///
-/// * `dependencies` - Collection of dependencies with their versions
-/// * `registry_path` - Path to the cargo registry directory
-/// * `output_dir` - Path to the output directory for code bank files
+/// * `project_path` - Path to the project root directory
 ///
 /// # Returns
 ///
-/// * `Result<HashMap<String, PathBuf>>` - HashMap mapping dependency names to their code bank file paths
-///
-/// # Errors
-///
-/// Returns an error if:
-/// - The code bank generator cannot be initialized
-/// - There are issues generating documentation for dependencies
-/// - The output directory cannot be created
+/// * `String` - The name of the project
 ///
 /// # Examples
 ///
 /// ```rust,no_run
-/// use depbank::{Dependency, DependencyCollection, generate_all_code_banks, resolve_registry_path};
+/// use depbank::project_name;
 /// use std::path::Path;
 ///
-/// let mut dependencies = DependencyCollection::new();
-/// dependencies.add(Dependency::new("serde", "1.0.152"));
-/// dependencies.add(Dependency::new("anyhow", "1.0.70"));
-///
-/// let registry_path = resolve_registry_path().unwrap();
-/// let output_dir = Path::new("./.codebank");
-///
-/// match generate_all_code_banks(&dependencies, &registry_path, output_dir) {
-///     Ok(files) => println!("Generated {} code bank files", files.len()),
-///     Err(e) => eprintln!("Error: {}", e),
-/// }
+/// println!("Generating for {}", project_name(Path::new(".")));
 /// ```
-pub fn generate_all_code_banks(
-    dependencies: &DependencyCollection,
-    registry_path: &Path,
-    output_dir: &Path,
-) -> Result<HashMap<String, PathBuf>> {
-    let mut code_bank_files = HashMap::new();
-    let mut errors = Vec::new();
+pub fn project_name(project_path: &Path) -> String {
+    let package_name = fs::read_to_string(project_path.join("Cargo.toml"))
+        .ok()
+        .and_then(|content| toml::from_str::<toml::Value>(&content).ok())
+        .and_then(|manifest| {
+            manifest
+                .get("package")?
+                .get("name")?
+                .as_str()
+                .map(str::to_string)
+        });
+    if let Some(name) = package_name {
+        return name;
+    }
 
-    for dependency in dependencies.as_slice() {
-        let dependency_path = dependency.get_registry_path(registry_path);
+    // `.` and `..` have no file name of their own
+    let directory = project_path
+        .canonicalize()
+        .unwrap_or_else(|_| project_path.to_path_buf());
+    directory
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "project".to_string())
+}
 
-        if dependency_path.exists() && dependency_path.is_dir() {
-            match generate_code_bank(&dependency_path, output_dir, &dependency.name) {
-                Ok(code_bank_file) => {
-                    code_bank_files.insert(dependency.name.clone(), code_bank_file);
-                }
-                Err(e) => {
-                    errors.push(format!(
-                        "Failed to generate code bank for {}: {}",
-                        dependency.name, e
-                    ));
-                }
-            }
-        } else {
-            errors.push(format!(
-                "Dependency not found: {}",
-                dependency_path.display()
-            ));
-        }
+/// Finds the Cargo.lock file in the workspace.
+///
+/// This function looks for Cargo.lock in the current directory and parent directories.
+///
+/// # Arguments
+///
+/// * `start_dir` - The directory to start searching from
+///
+/// # Returns
+///
+/// * `Result<PathBuf>` - Path to the found Cargo.lock file
+pub fn find_cargo_lock<P: AsRef<Path>>(start_dir: P) -> Result<PathBuf> {
+    let start_dir = start_dir.as_ref();
+    let mut current_dir = start_dir.to_path_buf();
+
+    // Check the current directory first
+    let cargo_lock = current_dir.join("Cargo.lock");
+    if cargo_lock.exists() {
+        return Ok(cargo_lock);
     }
 
-    // If there were errors, log them but don't fail the operation
-    if !errors.is_empty() {
-        for error in &errors {
-            eprintln!("Warning: {}", error);
+    // Then check parent directories
+    while let Some(parent) = current_dir.parent() {
+        current_dir = parent.to_path_buf();
+        let cargo_lock = current_dir.join("Cargo.lock");
+        if cargo_lock.exists() {
+            return Ok(cargo_lock);
         }
     }
 
-    Ok(code_bank_files)
+    Err(anyhow::anyhow!(
+        "Cargo.lock file not found in current directory or its parents"
+    ))
 }
 
-/// Calculates the number of tokens in a text.
+/// Validates an explicitly provided Cargo.lock path.
+///
+/// This is used instead of [`find_cargo_lock`] when the lockfile location is known
+/// up front (e.g. passed on the command line), so that a typo fails early with a
+/// clear message rather than surfacing later as a resolution error.
 ///
 /// # Arguments
 ///
-/// * `text` - The text to tokenize
+/// * `lock_path` - Path to the Cargo.lock file
 ///
 /// # Returns
 ///
-/// * `Result<usize>` - The number of tokens in the text
-pub fn calculate_tokens(text: &str) -> Result<usize> {
-    // Load a pretrained tokenizer model (BERT)
-    let tokenizer = Tokenizer::from_pretrained("bert-base-cased", None)
-        .map_err(|e| anyhow::anyhow!("Failed to load tokenizer: {}", e))?;
+/// * `Result<PathBuf>` - The validated path to the Cargo.lock file
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The path does not exist or is not a file
+/// - The file cannot be read
+/// - The file is not a valid Cargo.lock
+pub fn validate_cargo_lock<P: AsRef<Path>>(lock_path: P) -> Result<PathBuf> {
+    let lock_path = lock_path.as_ref();
 
-    // Tokenize the text
-    let encoding = tokenizer
-        .encode(text, false)
-        .map_err(|e| anyhow::anyhow!("Failed to tokenize text: {}", e))?;
+    if !lock_path.is_file() {
+        return Err(anyhow::anyhow!(
+            "Cargo.lock file does not exist or is not a file: {}",
+            lock_path.display()
+        ));
+    }
+
+    let content = fs::read_to_string(lock_path)
+        .with_context(|| format!("Failed to read Cargo.lock file: {}", lock_path.display()))?;
 
-    // Return the number of tokens
-    Ok(encoding.get_tokens().len())
+    validate_cargo_lock_str(&content)
+        .with_context(|| format!("Invalid Cargo.lock file: {}", lock_path.display()))?;
+
+    Ok(lock_path.to_path_buf())
 }
 
-/// Calculates tokens for a file.
+/// Validates Cargo.lock content held in memory, e.g. read from stdin.
+///
+/// This is the same check as [`validate_cargo_lock`], without the file access.
 ///
 /// # Arguments
 ///
-/// * `file_path` - Path to the file
+/// * `content` - The content of a Cargo.lock file
 ///
 /// # Returns
 ///
-/// * `Result<usize>` - The number of tokens in the file
-pub fn calculate_file_tokens(file_path: &Path) -> Result<usize> {
-    // Read the file content
-    let content = fs::read_to_string(file_path)
-        .with_context(|| format!("Failed to read file: {}", file_path.display()))?;
-
-    // Calculate tokens
-    calculate_tokens(&content)
-}
+/// * `Result<()>` - Success if the content looks like a Cargo.lock
+///
+/// # Errors
+///
+/// Returns an error if the content is not valid TOML or not a Cargo.lock
+///
+/// # Examples
+///
+/// ```rust
+/// use depbank::validate_cargo_lock_str;
+///
+/// assert!(validate_cargo_lock_str("version = 4\n").is_ok());
+/// assert!(validate_cargo_lock_str("[package]\nname = \"app\"\n").is_err());
+/// ```
+pub fn validate_cargo_lock_str(content: &str) -> Result<()> {
+    let lock: toml::Table =
+        toml::from_str(content).context("Failed to parse Cargo.lock content")?;
+
+    // Every lockfile cargo writes has either a version marker or a `[[package]]` list
+    let has_packages = lock.get("package").is_some_and(|p| p.is_array());
+    if !has_packages && !lock.contains_key("version") {
+        return Err(anyhow::anyhow!("Content is not a Cargo.lock"));
+    }
 
-/// Represents file statistics including token count.
-#[derive(Debug)]
-pub struct FileStats {
-    pub path: PathBuf,
-    pub size_bytes: usize,
-    pub token_count: usize,
+    Ok(())
 }
 
-/// Calculates tokens for all files in a directory.
-///
-/// This function traverses a directory and calculates token counts for each file,
-/// optionally filtering by file extension. It uses a transformer-based tokenizer to
-/// determine the token count, similar to how GPT models tokenize text.
-///
-/// # Arguments
+/// Resolves the path to the Cargo registry directory.
 ///
-/// * `dir_path` - Path to the directory
-/// * `extension` - Optional file extension filter (e.g., "md")
+/// This function locates the local Cargo registry where dependency source code is stored.
+/// It finds the most recently modified registry index directory, which is typically the active one.
+/// The registry is looked up in `CARGO_HOME` when it is set, and in the default cargo home
+/// directory of the OS otherwise, as cargo does.
 ///
 /// # Returns
 ///
-/// * `Result<HashMap<String, FileStats>>` - HashMap mapping filenames to their stats
+/// * `Result<PathBuf>` - Path to the cargo registry directory
 ///
 /// # Errors
 ///
 /// Returns an error if:
-/// - The directory does not exist
-/// - There are permission issues accessing files
-/// - There are issues reading file contents
-/// - The tokenizer fails to process a file
+/// - Neither `CARGO_HOME` nor the home directory can be found
+/// - The Cargo registry directory does not exist
+/// - There are permission issues accessing the directory
+/// - No registry directories are found
 ///
 /// # Examples
 ///
 /// ```rust,no_run
-/// use depbank::calculate_directory_tokens;
-/// use std::path::Path;
-///
-/// // Calculate tokens for all markdown files in a directory
-/// let stats = calculate_directory_tokens(Path::new("./docs"), Some("md")).unwrap();
-///
-/// let total_tokens: usize = stats.values().map(|stat| stat.token_count).sum();
-/// println!("Total: {} tokens across {} files", total_tokens, stats.len());
+/// use depbank::resolve_registry_path;
 ///
-/// // Print individual file stats
-/// for (name, stat) in stats {
-///     println!("{}: {} tokens, {} bytes", name, stat.token_count, stat.size_bytes);
+/// match resolve_registry_path() {
+///     Ok(path) => println!("Cargo registry found at: {}", path.display()),
+///     Err(e) => eprintln!("Error: {}", e),
 /// }
 /// ```
-pub fn calculate_directory_tokens(
-    dir_path: &Path,
-    extension: Option<&str>,
-) -> Result<HashMap<String, FileStats>> {
-    let mut file_stats = HashMap::new();
+pub fn resolve_registry_path() -> Result<PathBuf> {
+    latest_registry_dir(&registry_src_dir()?)
+}
 
-    // Check if directory exists
-    if !dir_path.exists() || !dir_path.is_dir() {
+/// Returns cargo's home directory: `CARGO_HOME` if set, or `.cargo` in the user's
+/// home directory, as found by cargo itself on every OS
+pub(crate) fn cargo_home() -> Result<PathBuf> {
+    home::cargo_home().context("Could not find the cargo home directory")
+}
+
+/// Returns cargo's directory of extracted registry sources, `$CARGO_HOME/registry/src`
+pub(crate) fn registry_src_dir() -> Result<PathBuf> {
+    registry_src_dir_in(&cargo_home()?)
+}
+
+/// Returns the directory of extracted registry sources of a cargo home directory
+fn registry_src_dir_in(cargo_home: &Path) -> Result<PathBuf> {
+    let registry_dir = cargo_home.join("registry").join("src");
+
+    // Check if the registry directory exists
+    if !registry_dir.exists() {
         return Err(anyhow::anyhow!(
-            "Directory does not exist or is not a directory: {}",
-            dir_path.display()
+            "Cargo registry directory not found: {}",
+            registry_dir.display()
         ));
     }
 
-    // Read directory entries
-    for entry in fs::read_dir(dir_path)
-        .with_context(|| format!("Failed to read directory: {}", dir_path.display()))?
-    {
+    Ok(registry_dir)
+}
+
+/// Finds the most recently modified registry directory, which is typically the active one
+fn latest_registry_dir(registry_dir: &Path) -> Result<PathBuf> {
+    // Find all directories in the registry, retrying transient failures on network mounts
+    let entries = with_retries(DEFAULT_RETRY_ATTEMPTS, || fs::read_dir(registry_dir))
+        .with_context(|| {
+            format!(
+                "Failed to read cargo registry directory: {}",
+                registry_dir.display()
+            )
+        })?;
+
+    // Find the most recently modified directory
+    let mut latest_dir: Option<(PathBuf, SystemTime)> = None;
+
+    for entry in entries {
         let entry = entry?;
         let path = entry.path();
 
-        // Skip directories and files that don't match the extension
-        if path.is_dir() {
-            continue;
+        if path.is_dir()
+            && let Ok(metadata) = fs::metadata(&path)
+            && let Ok(modified) = metadata.modified()
+        {
+            match &latest_dir {
+                Some((_, latest_modified)) if modified > *latest_modified => {
+                    latest_dir = Some((path, modified));
+                }
+                None => {
+                    latest_dir = Some((path, modified));
+                }
+                _ => {}
+            }
         }
+    }
 
-        if let Some(ext) = extension {
-            #[allow(clippy::nonminimal_bool)]
-            if !path.extension().is_some_and(|e| e == ext) {
-                continue;
+    // Return the most recently modified directory
+    match latest_dir {
+        Some((dir, _)) => {
+            log::debug!(target: LOG_TARGET_RESOLVE, "Using cargo registry {}", dir.display());
+            Ok(dir)
+        }
+        None => Err(anyhow::anyhow!(
+            "No registry directories found in: {}",
+            registry_dir.display()
+        )),
+    }
+}
+
+/// Source recorded in Cargo.lock for crates from crates.io
+const CRATES_IO_SOURCE: &str = "registry+https://github.com/rust-lang/crates.io-index";
+const CRATES_IO_SPARSE_SOURCE: &str = "sparse+https://index.crates.io/";
+
+/// Finds the registry source directory holding the crates of a Cargo.lock source.
+///
+/// Cargo extracts the crates of each registry into its own directory named after the
+/// registry host and a hash of its kind and URL, e.g.
+/// `index.crates.io-1949cf8c6b5b557f`. The hash function changed in cargo 1.85, so
+/// the directory names of both older and newer cargo versions are tried, and the most
+/// recently modified one is used if several exist. Crates from crates.io are recorded
+/// with the URL of its git index, but are looked up in the directory of its sparse
+/// index first, which cargo uses by default.
+///
+/// # Arguments
+///
+/// * `source_url` - The source of a package in Cargo.lock, e.g.
+///   `registry+https://github.com/rust-lang/crates.io-index`
+/// * `registry_base` - The directory containing all registry source directories,
+///   usually `~/.cargo/registry/src`
+///
+/// # Returns
+///
+/// * `Option<PathBuf>` - The registry source directory, or `None` if the source is not
+///   a registry or its directory does not exist
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use depbank::registry_dir_for_source;
+/// use std::path::Path;
+///
+/// let registry_base = Path::new("/home/user/.cargo/registry/src");
+/// let source = "registry+https://github.com/rust-lang/crates.io-index";
+/// if let Some(dir) = registry_dir_for_source(source, registry_base) {
+///     println!("crates.io sources are in {}", dir.display());
+/// }
+/// ```
+pub fn registry_dir_for_source(source_url: &str, registry_base: &Path) -> Option<PathBuf> {
+    let sources = if source_url == CRATES_IO_SOURCE {
+        vec![CRATES_IO_SPARSE_SOURCE, CRATES_IO_SOURCE]
+    } else {
+        vec![source_url]
+    };
+
+    sources.into_iter().find_map(|source| {
+        registry_dir_names(source)?
+            .into_iter()
+            .map(|name| registry_base.join(name))
+            .filter_map(|path| {
+                let modified = fs::metadata(&path)
+                    .ok()
+                    .filter(|metadata| metadata.is_dir())?
+                    .modified()
+                    .ok()?;
+                Some((path, modified))
+            })
+            .max_by_key(|(_, modified)| *modified)
+            .map(|(path, _)| path)
+    })
+}
+
+/// Returns the names cargo gives the source directory of a registry, `<host>-<hash>`,
+/// as hashed by cargo before 1.85 and since, or `None` if the source is not a registry
+fn registry_dir_names(source_url: &str) -> Option<Vec<String>> {
+    // Cargo hashes the kind of the source and its URL, which keeps the `sparse+` prefix
+    let (kind, url) = if let Some(url) = source_url.strip_prefix("registry+") {
+        (REGISTRY_SOURCE_KIND, url)
+    } else if source_url.starts_with("sparse+") {
+        (SPARSE_REGISTRY_SOURCE_KIND, source_url)
+    } else {
+        return None;
+    };
+    let host = url_host(url)?;
+
+    // `SourceKind` hashes its discriminant and `str` ends its bytes with 0xff
+    let mut legacy = (kind as i64).to_le_bytes().to_vec();
+    legacy.extend_from_slice(url.as_bytes());
+    legacy.push(0xff);
+    let mut stable = vec![kind];
+    stable.extend_from_slice(url.as_bytes());
+    stable.push(0xff);
+
+    #[allow(deprecated)]
+    let legacy_hash = {
+        use std::hash::Hasher;
+        let mut hasher = std::hash::SipHasher::new();
+        hasher.write(&legacy);
+        hasher.finish()
+    };
+    let (first, second) = sip_hash_13_128(&stable);
+    let stable_hash = first.wrapping_mul(3).wrapping_add(second);
+
+    Some(
+        [legacy_hash, stable_hash]
+            .iter()
+            .map(|hash| {
+                let hex: String = hash
+                    .to_le_bytes()
+                    .iter()
+                    .map(|byte| format!("{:02x}", byte))
+                    .collect();
+                format!("{}-{}", host, hex)
+            })
+            .collect(),
+    )
+}
+
+/// Discriminants of cargo's `SourceKind` for git and sparse registries
+const REGISTRY_SOURCE_KIND: u8 = 2;
+const SPARSE_REGISTRY_SOURCE_KIND: u8 = 3;
+
+/// SipHash-1-3 with 128-bit output and zero keys, as cargo's `StableHasher` computes it
+fn sip_hash_13_128(data: &[u8]) -> (u64, u64) {
+    fn round(v: &mut [u64; 4]) {
+        v[0] = v[0].wrapping_add(v[1]);
+        v[1] = v[1].rotate_left(13) ^ v[0];
+        v[0] = v[0].rotate_left(32);
+        v[2] = v[2].wrapping_add(v[3]);
+        v[3] = v[3].rotate_left(16) ^ v[2];
+        v[0] = v[0].wrapping_add(v[3]);
+        v[3] = v[3].rotate_left(21) ^ v[0];
+        v[2] = v[2].wrapping_add(v[1]);
+        v[1] = v[1].rotate_left(17) ^ v[2];
+        v[2] = v[2].rotate_left(32);
+    }
+
+    let mut v = [
+        0x736f_6d65_7073_6575,
+        0x646f_7261_6e64_6f6d ^ 0xee,
+        0x6c79_6765_6e65_7261,
+        0x7465_6462_7974_6573,
+    ];
+    let mut chunks = data.chunks_exact(8);
+    for chunk in &mut chunks {
+        let word = u64::from_le_bytes(chunk.try_into().expect("chunks are 8 bytes"));
+        v[3] ^= word;
+        round(&mut v);
+        v[0] ^= word;
+    }
+    let mut last = (data.len() as u64 & 0xff) << 56;
+    for (index, byte) in chunks.remainder().iter().enumerate() {
+        last |= u64::from(*byte) << (8 * index);
+    }
+    v[3] ^= last;
+    round(&mut v);
+    v[0] ^= last;
+
+    v[2] ^= 0xee;
+    for _ in 0..3 {
+        round(&mut v);
+    }
+    let first = v[0] ^ v[1] ^ v[2] ^ v[3];
+    v[1] ^= 0xdd;
+    for _ in 0..3 {
+        round(&mut v);
+    }
+    (first, v[0] ^ v[1] ^ v[2] ^ v[3])
+}
+
+/// Resolves a `file://` source of Cargo.lock to its local directory.
+///
+/// Sources of any kind are supported, e.g. `path+file:///src/my-crate`,
+/// `registry+file:///srv/registry` or `sparse+file:///srv/index`, as well as plain
+/// `file://` URLs. Percent-encoded characters in the path are decoded. Sources on
+/// the network, such as `sparse+https://...`, are not local.
+///
+/// # Arguments
+///
+/// * `source_url` - The source of a package in Cargo.lock
+///
+/// # Returns
+///
+/// * `Option<PathBuf>` - The directory of the source, or `None` if the source is not
+///   a `file://` URL or the directory does not exist
+///
+/// # Examples
+///
+/// ```rust
+/// use depbank::resolve_local_source;
+///
+/// assert_eq!(resolve_local_source("sparse+https://index.crates.io/"), None);
+/// let dir = std::env::temp_dir();
+/// let source = format!("registry+file://{}", dir.display());
+/// assert!(resolve_local_source(&source).is_some());
+/// ```
+pub fn resolve_local_source(source_url: &str) -> Option<PathBuf> {
+    let url = match source_url.split_once('+') {
+        Some((_, url)) if !source_url.starts_with("file:") => url,
+        _ => source_url,
+    };
+    let path = url.strip_prefix("file://")?;
+    // Drop a query or fragment, e.g. the revision of a git source
+    let path = path.split(['?', '#']).next()?;
+    let path = PathBuf::from(percent_decode(path));
+    path.is_dir().then_some(path)
+}
+
+/// Decodes the `%XX` escapes of a URL path, leaving invalid escapes as they are
+fn percent_decode(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let escaped = (bytes[index] == b'%')
+            .then(|| path.get(index + 1..index + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            None => {
+                decoded.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Extracts the host of a URL, without user info or port
+fn url_host(url: &str) -> Option<&str> {
+    let (_, rest) = url.split_once("://")?;
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit('@').next()?;
+    let host = host.split(':').next()?;
+    (!host.is_empty()).then_some(host)
+}
+
+/// Constructs the full path to a dependency's source code.
+///
+/// # Arguments
+///
+/// * `registry_path` - Path to the cargo registry directory
+/// * `dependency_name` - Name of the dependency
+/// * `dependency_version` - Version of the dependency
+///
+/// # Returns
+///
+/// * `PathBuf` - Full path to the dependency's source code
+pub fn construct_dependency_path(
+    registry_path: &Path,
+    dependency_name: &str,
+    dependency_version: &str,
+) -> PathBuf {
+    registry_path.join(format!("{}-{}", dependency_name, dependency_version))
+}
+
+/// Checks if a dependency is available locally in the cargo registry.
+///
+/// # Arguments
+///
+/// * `registry_path` - Path to the cargo registry directory
+/// * `dependency` - The dependency to check
+///
+/// # Returns
+///
+/// * `bool` - True if the dependency is available locally, false otherwise
+pub fn is_dependency_available(registry_path: &Path, dependency: &Dependency) -> bool {
+    dependency.is_available_in_registry(registry_path)
+}
+
+/// Overloaded version of is_dependency_available that takes separate name and version strings.
+///
+/// # Arguments
+///
+/// * `registry_path` - Path to the cargo registry directory
+/// * `dependency_name` - Name of the dependency
+/// * `dependency_version` - Version of the dependency
+///
+/// # Returns
+///
+/// * `bool` - True if the dependency is available locally, false otherwise
+pub fn is_dependency_available_by_parts(
+    registry_path: &Path,
+    dependency_name: &str,
+    dependency_version: &str,
+) -> bool {
+    let dependency = Dependency::new(dependency_name, dependency_version);
+    is_dependency_available(registry_path, &dependency)
+}
+
+/// Resolves the paths for all dependencies.
+///
+/// The dependencies are looked up in the most recently modified registry directory.
+/// Use [`resolve_dependency_paths_in`] to locate dependencies with a known `source` in
+/// the directory of their registry.
+///
+/// # Arguments
+///
+/// * `dependencies` - HashMap of dependency names to versions
+///
+/// # Returns
+///
+/// * `Result<HashMap<String, PathBuf>>` - HashMap mapping dependency names to their local paths
+///
+/// # Errors
+///
+/// Returns an error if the cargo registry directory cannot be found or read
+pub fn resolve_dependency_paths(
+    dependencies: &HashMap<String, String>,
+) -> Result<HashMap<String, PathBuf>> {
+    let mut collection = DependencyCollection::new();
+    for (name, version) in dependencies {
+        collection.add(Dependency::new(name, version));
+    }
+    Ok(resolve_dependency_paths_in(
+        &registry_src_dir()?,
+        &collection,
+    ))
+}
+
+/// Resolves the paths for all dependencies within the given registry directories.
+///
+/// Dependencies with a known registry `source` are looked up in the directory of that
+/// registry (see [`registry_dir_for_source`]), all others in the most recently
+/// modified registry directory.
+///
+/// # Arguments
+///
+/// * `registry_base` - The directory containing all registry source directories
+/// * `dependencies` - The dependencies to locate
+///
+/// # Returns
+///
+/// * `HashMap<String, PathBuf>` - HashMap mapping dependency names to their local paths
+pub fn resolve_dependency_paths_in(
+    registry_base: &Path,
+    dependencies: &DependencyCollection,
+) -> HashMap<String, PathBuf> {
+    // Only needed for dependencies without a known source
+    let mut default_registry: Option<Option<PathBuf>> = None;
+    let mut dependency_paths = HashMap::new();
+
+    for dep in dependencies.iter() {
+        let registry_path = match dep
+            .source
+            .as_deref()
+            .and_then(|source| registry_dir_for_source(source, registry_base))
+        {
+            Some(registry_path) => registry_path,
+            None => match default_registry
+                .get_or_insert_with(|| latest_registry_dir(registry_base).ok())
+            {
+                Some(registry_path) => registry_path.clone(),
+                None => continue,
+            },
+        };
+
+        let dependency_path = dep.get_registry_path(&registry_path);
+        if dependency_path.is_dir() {
+            dependency_paths.insert(dep.name.clone(), dependency_path);
+        }
+    }
+
+    dependency_paths
+}
+
+/// Directory names of a dependency left out of its code bank, unless other
+/// directories are set in [`GenerateOptions::ignore_dirs`]
+pub const DEFAULT_BANK_IGNORED_DIRS: &[&str] = &["examples", "tests", "benches"];
+
+/// How much of a dependency's source code goes into its code bank.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Strategy {
+    /// Public items only, with function bodies omitted
+    #[default]
+    Summary,
+    /// All items with their implementations, except tests
+    NoTests,
+    /// All items with their implementations
+    Full,
+}
+
+impl Strategy {
+    fn bank_strategy(self) -> BankStrategy {
+        match self {
+            Strategy::Summary => BankStrategy::Summary,
+            Strategy::NoTests => BankStrategy::NoTests,
+            Strategy::Full => BankStrategy::Default,
+        }
+    }
+}
+
+/// Named bundles of generation settings for common use cases.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preset {
+    /// The smallest banks: public signatures only, without hidden items
+    Minimal,
+    /// The default settings: public signatures only
+    Balanced,
+    /// The most complete banks: all items with their implementations
+    Full,
+}
+
+/// Cargo feature flags requested for generation.
+///
+/// codebank documents every item regardless of `#[cfg(feature = ...)]` gates, so
+/// banks always reflect all features. The requested flags are recorded in the bank
+/// header, so readers know which features they meant to document.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FeatureSelection {
+    /// Features to enable, as with cargo's `--features`
+    pub features: Vec<String>,
+    /// Enable all features, as with cargo's `--all-features`
+    pub all_features: bool,
+    /// Disable the default features, as with cargo's `--no-default-features`
+    pub no_default_features: bool,
+}
+
+impl FeatureSelection {
+    /// Returns true if no feature flags were requested
+    pub fn is_default(&self) -> bool {
+        self.features.is_empty() && !self.all_features && !self.no_default_features
+    }
+
+    /// Formats the selection as cargo command-line flags
+    pub fn to_cargo_flags(&self) -> String {
+        let mut flags = Vec::new();
+        if self.all_features {
+            flags.push("--all-features".to_string());
+        }
+        if self.no_default_features {
+            flags.push("--no-default-features".to_string());
+        }
+        if !self.features.is_empty() {
+            flags.push(format!("--features {}", self.features.join(",")));
+        }
+        flags.join(" ")
+    }
+
+    /// Builds the bank header note recording the requested features
+    fn header_note(&self) -> Option<String> {
+        (!self.is_default()).then(|| {
+            format!(
+                "> **Features:** `{}` requested. Generated assuming all features, since \
+                 feature-gated items are not filtered out.",
+                self.to_cargo_flags()
+            )
+        })
+    }
+}
+
+/// A transformation of each generated bank's content, such as redaction or
+/// reformatting, set by library users in [`GenerateOptions::post_process`].
+///
+/// The hook receives the dependency, with the version from its manifest, and the
+/// bank's content, and returns the content to write. An error fails the generation
+/// of that bank.
+///
+/// # Examples
+///
+/// ```rust
+/// use depbank::{GenerateOptions, PostProcessHook};
+///
+/// let options = GenerateOptions {
+///     post_process: Some(PostProcessHook::new(|_dependency, content| {
+///         Ok(content.replace("internal.example.com", "[redacted]"))
+///     })),
+///     ..Default::default()
+/// };
+/// assert!(options.post_process.is_some());
+/// ```
+#[derive(Clone)]
+pub struct PostProcessHook(Arc<PostProcessFn>);
+
+type PostProcessFn = dyn Fn(&Dependency, String) -> Result<String> + Send + Sync;
+
+impl PostProcessHook {
+    /// Wraps a function as a post-processing hook
+    pub fn new(
+        hook: impl Fn(&Dependency, String) -> Result<String> + Send + Sync + 'static,
+    ) -> Self {
+        Self(Arc::new(hook))
+    }
+
+    /// Applies the hook to a bank's content
+    pub fn apply(&self, dependency: &Dependency, content: String) -> Result<String> {
+        (self.0)(dependency, content)
+    }
+}
+
+impl fmt::Debug for PostProcessHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("PostProcessHook")
+    }
+}
+
+/// Options controlling how code banks are generated.
+#[derive(Debug, Clone, Default)]
+pub struct GenerateOptions {
+    /// How much of each dependency's source code to include
+    pub strategy: Strategy,
+    /// Remove `#[doc(hidden)]` and restricted-visibility items from the generated banks
+    pub strip_hidden: bool,
+    /// Remove re-exports of `std`, `core` and `alloc` items from the generated banks
+    pub strip_std_reexports: bool,
+    /// Kinds of items to keep in the generated banks, see [`filter_item_kinds`]
+    pub item_kinds: ItemKinds,
+    /// Names of the directories of a dependency left out of its bank, or `None` for
+    /// [`DEFAULT_BANK_IGNORED_DIRS`]
+    pub ignore_dirs: Option<Vec<String>>,
+    /// Only document the source files at most this many directories deep, see
+    /// [`limit_section_depth`]
+    pub source_depth: Option<usize>,
+    /// Only read the source files with these extensions, e.g. `["rs"]`, see
+    /// [`stage_filtered_source`]. When empty, every language codebank documents is
+    /// read. The sources are only staged when a crate holds files with other
+    /// extensions, so `["rs"]`, the CLI's default, costs nothing for pure Rust crates
+    pub source_extensions: Vec<String>,
+    /// Reduce the generated banks to an outline of module and item names
+    pub outline: bool,
+    /// Reduce the generated banks to the prose of their doc comments, see
+    /// [`extract_docs_only`]
+    pub docs_only: bool,
+    /// Document each library after expanding its macros with `cargo expand`, see
+    /// [`expand_crate`]. Off by default, since it compiles every dependency
+    pub expand_macros: bool,
+    /// Tag items that are `#[deprecated]` in the sources, see [`annotate_deprecations`].
+    /// Off by default, since it parses every source file of a dependency a second time
+    pub mark_deprecated: bool,
+    /// Append the dependency's build script, if it has one, in a section of its own,
+    /// see [`build_script`]
+    pub include_build_script: bool,
+    /// Cargo feature flags recorded in the bank header
+    pub features: FeatureSelection,
+    /// Display names of dependencies by registry name, titling their banks, see
+    /// [`display_name`]
+    pub aliases: BTreeMap<String, String>,
+    /// Depths of dependencies below the project by name, recorded in the bank header,
+    /// see [`compute_dependency_depths`]. Dependencies without a depth get no note
+    pub dependency_depths: BTreeMap<String, usize>,
+    /// Names of dependencies whose documented version has been yanked, warned about
+    /// right below their bank's title
+    pub yanked: BTreeSet<String>,
+    /// How the project uses dependencies by name, appended to their banks in a "Used in
+    /// this project" section, see [`ProjectSources::find_usages`]
+    pub usages: BTreeMap<String, Vec<UsageSnippet>>,
+    /// Shift every heading of the banks down by this many levels, to embed them in a
+    /// larger document, see [`shift_headings`]
+    pub heading_offset: usize,
+    /// Line endings used in the written banks
+    pub line_endings: LineEndings,
+    /// Skip dependencies recorded as completed in the output directory's progress log
+    pub resume: bool,
+    /// Best-effort cap, in megabytes, on the memory held by banks generated in parallel
+    pub max_memory_mb: Option<u64>,
+    /// Stop starting new banks once this instant has passed. Banks in progress are
+    /// completed, and the dependencies not started are skipped
+    pub deadline: Option<Instant>,
+    /// A custom transformation of each bank's content. It runs after the built-in
+    /// filters and the feature header note, and before line endings are normalized,
+    /// so it sees the final content with LF line endings
+    pub post_process: Option<PostProcessHook>,
+}
+
+impl GenerateOptions {
+    /// Creates the options for a preset.
+    ///
+    /// | Preset     | Strategy  | Strip hidden items |
+    /// |------------|-----------|--------------------|
+    /// | `Minimal`  | `Summary` | yes                |
+    /// | `Balanced` | `Summary` | no                 |
+    /// | `Full`     | `Full`    | no                 |
+    ///
+    /// Settings that are not part of a preset keep their default values, and any field
+    /// can be changed afterwards to override the preset.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depbank::{GenerateOptions, Preset, Strategy};
+    ///
+    /// let options = GenerateOptions::from_preset(Preset::Minimal);
+    /// assert_eq!(options.strategy, Strategy::Summary);
+    /// assert!(options.strip_hidden);
+    /// ```
+    pub fn from_preset(preset: Preset) -> Self {
+        match preset {
+            Preset::Minimal => Self {
+                strategy: Strategy::Summary,
+                strip_hidden: true,
+                ..Default::default()
+            },
+            Preset::Balanced => Self::default(),
+            Preset::Full => Self {
+                strategy: Strategy::Full,
+                strip_hidden: false,
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Applies the configured post-processing filters and hook to generated bank content
+    fn apply_post_processing(
+        &self,
+        mut content: String,
+        source_path: &Path,
+        dependency: &Dependency,
+    ) -> Result<String> {
+        if let Some(max_depth) = self.source_depth {
+            content = limit_section_depth(&content, max_depth);
+        }
+        if self.strip_hidden {
+            content = strip_hidden_items(&content);
+        }
+        if self.strip_std_reexports {
+            content = strip_std_reexports(&content);
+        }
+        if self.item_kinds != ItemKinds::all() {
+            content = filter_item_kinds(&content, self.item_kinds);
+        }
+        if self.outline {
+            content = extract_outline(&content);
+        }
+        if self.docs_only {
+            content = extract_docs_only(&content);
+        }
+        if self.mark_deprecated {
+            content = annotate_deprecations(source_path, &content);
+        }
+        // The build script is added after the filters, which would reduce it to nothing
+        if self.include_build_script
+            && let Some(section) = targets::build_script_section(source_path)?
+        {
+            if !content.ends_with('\n') {
+                content.push('\n');
+            }
+            content.push('\n');
+            content.push_str(&section);
+        }
+        if let Some(usages) = self.usages.get(&dependency.name)
+            && !usages.is_empty()
+        {
+            if !content.is_empty() && !content.ends_with('\n') {
+                content.push('\n');
             }
+            content.push('\n');
+            content.push_str(&usage_section(&dependency.name, usages));
+        }
+        if let Some(alias) = self.aliases.get(&dependency.name) {
+            content = alias_bank_title(&content, alias, &dependency.name);
+        }
+        if let Some(note) = self.features.header_note() {
+            content = insert_header_note(&content, &note);
         }
+        if let Some(&depth) = self.dependency_depths.get(&dependency.name) {
+            content = insert_header_note(&content, &depth_header_note(depth));
+        }
+        // Inserted last, so the warning comes first below the title
+        if self.yanked.contains(&dependency.name) {
+            let note = format!(
+                "> **Warning:** {} {} has been yanked from crates.io.",
+                dependency.name, dependency.version
+            );
+            content = insert_header_note(&content, &note);
+        }
+        // Headings are shifted once the bank has all its sections and notes
+        content = shift_headings(&content, self.heading_offset);
+        if let Some(hook) = &self.post_process {
+            content = hook
+                .apply(dependency, content)
+                .with_context(|| format!("Post-processing hook failed for: {}", dependency.name))?;
+        }
+        // Line endings are normalized last, so they apply to the final content
+        Ok(normalize_line_endings(&content, self.line_endings))
+    }
+}
+
+/// The bank header note recording how deep a dependency sits below the project
+fn depth_header_note(depth: usize) -> String {
+    let kind = if depth == 0 { "direct" } else { "transitive" };
+    format!("> **Depth:** {} ({} dependency)", depth, kind)
+}
+
+/// Generates code bank for a dependency.
+///
+/// The post-processing configured in `options` is applied to the generated content
+/// before it is written.
+///
+/// # Arguments
+///
+/// * `source_path` - Path to the dependency's source code
+/// * `output_dir` - Path to the output directory for code bank files
+/// * `dependency_name` - Name of the dependency
+/// * `options` - Options controlling the generation
+///
+/// # Returns
+///
+/// * `Result<PathBuf>` - Path to the generated code bank file
+pub fn generate_code_bank(
+    source_path: &Path,
+    output_dir: &Path,
+    dependency_name: &str,
+    options: &GenerateOptions,
+) -> Result<PathBuf> {
+    // Check if source path exists
+    if !source_path.exists() || !source_path.is_dir() {
+        return Err(anyhow::anyhow!(
+            "Source path does not exist or is not a directory: {}",
+            source_path.display()
+        ));
+    }
+
+    // Check if output directory exists, create it if not
+    if !output_dir.exists() {
+        fs::create_dir_all(output_dir).with_context(|| {
+            format!(
+                "Failed to create output directory: {}",
+                output_dir.display()
+            )
+        })?;
+    }
+
+    // Create the output file path
+    let output_file = output_dir.join(format!("{}.md", dependency_name));
+
+    // Write the content to a temporary file next to the bank and only rename it over
+    // the bank once generation succeeded, so a failed regeneration keeps the previous
+    // bank
+    let temp_file = output_dir.join(format!(".{}.md.tmp", dependency_name));
+    let file = fs::File::create(&temp_file).with_context(|| {
+        format!(
+            "Failed to write code bank to file: {}",
+            output_file.display()
+        )
+    })?;
+    let mut writer = BufWriter::new(file);
+    let result = generate_code_bank_to_writer(source_path, dependency_name, options, &mut writer)
+        .and_then(|_| {
+            writer.flush().with_context(|| {
+                format!(
+                    "Failed to write code bank to file: {}",
+                    output_file.display()
+                )
+            })
+        });
+    drop(writer);
+    if let Err(e) = result.and_then(|_| {
+        fs::rename(&temp_file, &output_file).with_context(|| {
+            format!(
+                "Failed to write code bank to file: {}",
+                output_file.display()
+            )
+        })
+    }) {
+        let _ = fs::remove_file(&temp_file);
+        return Err(e);
+    }
+
+    Ok(output_file)
+}
+
+/// Generates code bank for a dependency and writes it to an arbitrary writer.
+///
+/// This is the same as [`generate_code_bank`], but instead of creating a
+/// file in an output directory, the content is written to `writer`. This allows
+/// streaming banks to in-memory buffers, network connections or object storage
+/// without touching the local filesystem.
+///
+/// # Arguments
+///
+/// * `source_path` - Path to the dependency's source code
+/// * `dependency_name` - Name of the dependency
+/// * `options` - Options controlling the generation
+/// * `writer` - The destination for the generated content
+///
+/// # Returns
+///
+/// * `Result<()>` - Success or an error if generation or writing fails
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The source path does not exist or is not a directory
+/// - The code bank cannot be generated
+/// - Writing to `writer` fails
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use depbank::{GenerateOptions, generate_code_bank_to_writer};
+/// use std::path::Path;
+///
+/// let mut buffer = Vec::new();
+/// generate_code_bank_to_writer(
+///     Path::new("/path/to/registry/serde-1.0.197"),
+///     "serde",
+///     &GenerateOptions::default(),
+///     &mut buffer,
+/// )
+/// .unwrap();
+/// println!("Generated {} bytes", buffer.len());
+/// ```
+pub fn generate_code_bank_to_writer(
+    source_path: &Path,
+    dependency_name: &str,
+    options: &GenerateOptions,
+    writer: &mut dyn Write,
+) -> Result<()> {
+    // Check if source path exists
+    if !source_path.exists() || !source_path.is_dir() {
+        return Err(anyhow::anyhow!(
+            "Source path does not exist or is not a directory: {}",
+            source_path.display()
+        ));
+    }
+    log::debug!(
+        target: LOG_TARGET_GENERATE,
+        "Generating code bank for {} from {} with the {:?} strategy",
+        dependency_name,
+        source_path.display(),
+        options.strategy
+    );
+
+    // Create a new code bank generator
+    let code_bank = CodeBank::try_new().with_context(|| "Failed to create CodeBank instance")?;
+
+    // Generate documentation for the source directory, without examples, tests and
+    // benches unless other directories are ignored
+    let ignore_dirs = options.ignore_dirs.clone().unwrap_or_else(|| {
+        DEFAULT_BANK_IGNORED_DIRS
+            .iter()
+            .map(|dir| dir.to_string())
+            .collect()
+    });
+
+    // The manifest is parsed once for the package files and the targets
+    let manifest = ParsedManifest::read(source_path)?;
+
+    // Only document the files that are part of the crate's package
+    let filter = package_files::manifest_file_filter(&manifest)?;
+
+    // Binary-only crates have no public API to summarize, so document their binary
+    // sources instead
+    let binaries: Vec<PathBuf> = if targets::manifest_has_lib_target(source_path, &manifest) {
+        Vec::new()
+    } else {
+        targets::manifest_binary_sources(source_path, &manifest)?
+            .into_iter()
+            .filter(|source| filter.matches(source))
+            .collect()
+    };
+    let content = if !binaries.is_empty() {
+        let staging_dir = stage_sources(source_path, &binaries)?;
+        // Binaries have no public items, so summaries would be empty
+        let strategy = match options.strategy {
+            Strategy::Summary => BankStrategy::NoTests,
+            strategy => strategy.bank_strategy(),
+        };
+        let config = BankConfig::new(staging_dir.path(), strategy, ignore_dirs);
+        with_retries(DEFAULT_RETRY_ATTEMPTS, || code_bank.generate(&config))
+    } else if options.expand_macros {
+        // The expanded library replaces the sources, as a single file
+        let expanded = expand_crate(source_path)?;
+        let staging_dir = stage_sources(source_path, &[])?;
+        let lib_path = staging_dir.path().join("src").join("lib.rs");
+        fs::create_dir_all(staging_dir.path().join("src"))
+            .context("Failed to create staging directory")?;
+        fs::write(&lib_path, expanded)
+            .with_context(|| format!("Failed to write expanded source: {}", lib_path.display()))?;
+        let config = BankConfig::new(
+            staging_dir.path(),
+            options.strategy.bank_strategy(),
+            ignore_dirs,
+        );
+        with_retries(DEFAULT_RETRY_ATTEMPTS, || code_bank.generate(&config))
+    } else if let Some(sources) = sources_to_stage(
+        source_path,
+        &filter,
+        &ignore_dirs,
+        &options.source_extensions,
+    )? {
+        let staging_dir = stage_sources(source_path, &sources)?;
+        let config = BankConfig::new(
+            staging_dir.path(),
+            options.strategy.bank_strategy(),
+            ignore_dirs,
+        );
+        with_retries(DEFAULT_RETRY_ATTEMPTS, || code_bank.generate(&config))
+    } else {
+        let config = BankConfig::new(source_path, options.strategy.bank_strategy(), ignore_dirs);
+        with_retries(DEFAULT_RETRY_ATTEMPTS, || code_bank.generate(&config))
+    }
+    .with_context(|| {
+        format!(
+            "Failed to generate code bank for: {}",
+            source_path.display()
+        )
+    })?;
+
+    // Apply post-processing filters and the custom hook
+    let version = manifest
+        .package_field("version")
+        .and_then(|version| version.as_str())
+        .unwrap_or_default();
+    let dependency = Dependency::new(dependency_name, version);
+    let content = options.apply_post_processing(content, source_path, &dependency)?;
+
+    writer
+        .write_all(content.as_bytes())
+        .with_context(|| format!("Failed to write code bank for: {}", dependency_name))?;
+
+    Ok(())
+}
+
+/// Lists the sources of a crate to copy into a staging directory before generating its
+/// bank, or `None` when the package filter and the extensions keep every file codebank
+/// documents, so the crate can be documented in place
+fn sources_to_stage(
+    source_path: &Path,
+    filter: &FileFilter,
+    ignore_dirs: &[String],
+    extensions: &[String],
+) -> Result<Option<Vec<PathBuf>>> {
+    if filter.is_empty() && extensions.is_empty() {
+        return Ok(None);
+    }
+
+    let selected = package_files::filtered_sources(source_path, filter, ignore_dirs, extensions)?;
+    Ok(selected.filtered_out.then_some(selected.sources))
+}
+
+/// Copies the manifest and the given sources of a crate into a temporary directory.
+///
+/// This lets codebank document exactly the selected files, such as the binary targets
+/// or the files of the published package, with their paths relative to the crate
+/// root preserved.
+fn stage_sources(source_path: &Path, sources: &[PathBuf]) -> Result<TempDir> {
+    let staging_dir = TempDir::new().context("Failed to create staging directory")?;
+
+    let manifest = source_path.join("Cargo.toml");
+    if manifest.is_file() {
+        fs::copy(&manifest, staging_dir.path().join("Cargo.toml"))
+            .with_context(|| format!("Failed to copy manifest: {}", manifest.display()))?;
+    }
+
+    for source in sources {
+        let target = staging_dir.path().join(source);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+        }
+        let source = source_path.join(source);
+        fs::copy(&source, &target)
+            .with_context(|| format!("Failed to copy source file: {}", source.display()))?;
+    }
+
+    Ok(staging_dir)
+}
+
+/// Copies the manifest and the files with the given extensions of a crate into a
+/// temporary directory.
+///
+/// Path and vendored dependencies may hold large files that are not sources, such as
+/// data files or fixtures. Generating from the staged copy leaves them out, while
+/// paths relative to the crate root are preserved. The manifest is always copied.
+///
+/// # Arguments
+///
+/// * `source` - Path to the crate's source directory
+/// * `extensions` - Extensions of the files to copy, without the dot, e.g. `["rs"]`.
+///   When empty, the files of every language codebank documents are copied
+///
+/// # Returns
+///
+/// * `Result<TempDir>` - The staging directory, removed when dropped
+///
+/// # Errors
+///
+/// Returns an error if the crate's directory cannot be read, or the files cannot be
+/// copied
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use depbank::stage_filtered_source;
+/// use std::path::Path;
+///
+/// let staged = stage_filtered_source(Path::new("vendor/my-crate"), &["rs".to_string()]).unwrap();
+/// println!("Staged into {}", staged.path().display());
+/// ```
+pub fn stage_filtered_source(source: &Path, extensions: &[String]) -> Result<TempDir> {
+    let selected =
+        package_files::filtered_sources(source, &FileFilter::default(), &[], extensions)?;
+    stage_sources(source, &selected.sources)
+}
+
+/// Generates a code bank of a project's own root package, from its `src` directory.
+///
+/// The bank documents the project itself rather than a dependency, e.g. to give an AI
+/// assistant an overview of the project's API. It is written as `<package name>.md`,
+/// named after the root manifest's `[package]`. Only the manifest and the files under
+/// `src` are documented, leaving out build outputs in `target` and the directories of
+/// other workspace members.
+///
+/// # Arguments
+///
+/// * `project_path` - Path to the project root directory
+/// * `output_dir` - Path to the output directory for code bank files
+/// * `options` - Options controlling the generation
+///
+/// # Returns
+///
+/// * `Result<(Dependency, PathBuf)>` - The project's package, with a path source, and
+///   the path to the generated code bank file
+///
+/// # Errors
+///
+/// Returns an error if the root manifest has no `[package]` section, e.g. in a virtual
+/// workspace, or if the code bank cannot be generated
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use depbank::{GenerateOptions, generate_self_code_bank};
+/// use std::path::Path;
+///
+/// let (package, bank) =
+///     generate_self_code_bank(Path::new("."), Path::new(".codebank"), &GenerateOptions::default())
+///         .unwrap();
+/// println!("Documented {} in {}", package.name, bank.display());
+/// ```
+pub fn generate_self_code_bank(
+    project_path: &Path,
+    output_dir: &Path,
+    options: &GenerateOptions,
+) -> Result<(Dependency, PathBuf)> {
+    let manifest = ParsedManifest::read(project_path)?;
+    let name = manifest
+        .package_field("name")
+        .and_then(|name| name.as_str())
+        .with_context(|| {
+            format!(
+                "No [package] in {} to generate a code bank for",
+                project_path.join("Cargo.toml").display()
+            )
+        })?;
+    // Inherited `version.workspace = true` versions are not resolved
+    let version = manifest
+        .package_field("version")
+        .and_then(|version| version.as_str())
+        .unwrap_or("0.0.0");
+
+    let src_dir = project_path.join("src");
+    let sources: Vec<PathBuf> = package_files::filtered_sources(
+        &src_dir,
+        &FileFilter::default(),
+        &[],
+        &options.source_extensions,
+    )
+    .with_context(|| format!("Failed to read the sources of {}", name))?
+    .sources
+    .into_iter()
+    .map(|source| Path::new("src").join(source))
+    .collect();
+    let staging_dir = stage_sources(project_path, &sources)?;
+    let bank = generate_code_bank(staging_dir.path(), output_dir, name, options)?;
+
+    let path = project_path
+        .canonicalize()
+        .unwrap_or_else(|_| project_path.to_path_buf());
+    let package = Dependency::new(name, version).with_source(format!(
+        "{}{}",
+        PATH_SOURCE_PREFIX,
+        path.display()
+    ));
+    Ok((package, bank))
+}
+
+/// Generates code banks for all available dependencies.
+///
+/// This function creates code bank documentation files for each dependency using the codebank library.
+/// It processes each dependency, generates a summary documentation, and saves it to the specified output directory.
+///
+/// Every completed bank is recorded in the output directory's progress log (see
+/// [`ProgressLog`]). With `options.resume`, dependencies already recorded there by a
+/// previous run with the same options (see [`options_fingerprint`]), whose bank files
+/// still exist, are not generated again but are still included in the result.
+///
+/// With `options.max_memory_mb`, each generation reserves an estimate of its memory
+/// use (based on the size of the crate's sources) from a shared [`MemoryBudget`], and
+/// waits while the banks in flight would exceed it. This is best-effort: estimates
+/// can be off, and a single crate larger than the cap is still generated, on its own.
+///
+/// # Arguments
+///
+/// * `dependencies` - Collection of dependencies with their versions
+/// * `registry_path` - Path to the cargo registry directory
+/// * `output_dir` - Path to the output directory for code bank files
+/// * `options` - Options controlling the generation
+///
+/// # Returns
+///
+/// * `Result<HashMap<String, PathBuf>>` - HashMap mapping dependency names to their code bank file paths
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The code bank generator cannot be initialized
+/// - There are issues generating documentation for dependencies
+/// - The output directory cannot be created
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use depbank::{
+///     Dependency, DependencyCollection, GenerateOptions, generate_all_code_banks,
+///     resolve_registry_path,
+/// };
+/// use std::path::Path;
+///
+/// let mut dependencies = DependencyCollection::new();
+/// dependencies.add(Dependency::new("serde", "1.0.152"));
+/// dependencies.add(Dependency::new("anyhow", "1.0.70"));
+///
+/// let registry_path = resolve_registry_path().unwrap();
+/// let output_dir = Path::new("./.codebank");
+///
+/// let options = GenerateOptions::default();
+/// match generate_all_code_banks(&dependencies, &registry_path, output_dir, &options) {
+///     Ok(files) => println!("Generated {} code bank files", files.len()),
+///     Err(e) => eprintln!("Error: {}", e),
+/// }
+/// ```
+pub fn generate_all_code_banks(
+    dependencies: &DependencyCollection,
+    registry_path: &Path,
+    output_dir: &Path,
+    options: &GenerateOptions,
+) -> Result<HashMap<String, PathBuf>> {
+    generate_code_banks_with_outcome(dependencies, registry_path, output_dir, options)
+        .map(|outcome| outcome.code_bank_files)
+}
+
+/// Reason recorded for dependencies skipped because the run's deadline passed
+pub const SKIPPED_DEADLINE: &str = "skipped (deadline)";
+
+/// Reason recorded for dependencies skipped because their sources are too large
+pub const SKIPPED_TOO_LARGE: &str = "skipped (too large)";
+
+/// Calculates the total size of the files below a directory, recursively.
+///
+/// Symbolic links are not followed, and entries that cannot be read count as empty,
+/// so a missing directory has a size of 0.
+///
+/// # Arguments
+///
+/// * `path` - Path to the directory, or to a single file
+///
+/// # Returns
+///
+/// * `u64` - The total size of the files, in bytes
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use depbank::source_total_size;
+/// use std::path::Path;
+///
+/// let size = source_total_size(Path::new("~/.cargo/registry/src/index.crates.io-xxx/serde-1.0.219"));
+/// println!("{} bytes", size);
+/// ```
+pub fn source_total_size(path: &Path) -> u64 {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return if metadata.is_file() {
+            metadata.len()
+        } else {
+            0
+        };
+    }
+
+    fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| source_total_size(&entry.path()))
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+/// Removes the dependencies whose sources are larger than a threshold.
+///
+/// This measures the sources in the registry before generation, which is much
+/// cheaper than generating the code banks of giant crates only to drop them.
+///
+/// # Arguments
+///
+/// * `dependencies` - Collection of dependencies with their versions
+/// * `registry_path` - Path to the cargo registry directory
+/// * `max_bytes` - The largest total source size to keep
+///
+/// # Returns
+///
+/// * `(DependencyCollection, Vec<(String, u64)>)` - The dependencies to generate, and
+///   the names and source sizes of the skipped ones, in order
+pub fn skip_oversized_dependencies(
+    dependencies: &DependencyCollection,
+    registry_path: &Path,
+    max_bytes: u64,
+) -> (DependencyCollection, Vec<(String, u64)>) {
+    let mut kept = DependencyCollection::new();
+    let mut skipped = Vec::new();
+    for dependency in dependencies.iter() {
+        let size = source_total_size(&dependency.get_registry_path(registry_path));
+        if size > max_bytes {
+            skipped.push((dependency.name.clone(), size));
+        } else {
+            kept.add(dependency.clone());
+        }
+    }
+
+    (kept, skipped)
+}
+
+/// The outcome of generating the code banks of several dependencies
+#[derive(Debug, Clone, Default)]
+pub struct GenerationOutcome {
+    /// Mapping of dependency names to their code bank file paths
+    pub code_bank_files: HashMap<String, PathBuf>,
+    /// Dependencies that were not generated because `options.deadline` passed, sorted
+    pub skipped: Vec<String>,
+    /// How long the generation of each bank took. Banks reused from a previous run
+    /// with `options.resume` are not included
+    pub durations: HashMap<String, Duration>,
+}
+
+/// A generated code bank file and how long it took to generate
+type TimedBank = (PathBuf, Duration);
+
+/// Generates code banks for all available dependencies using the given options, and
+/// reports the dependencies skipped by the deadline.
+///
+/// This is the same as [`generate_all_code_banks`], but also returns the
+/// dependencies skipped because `options.deadline` passed before their generation
+/// started. The deadline is checked between dependencies: banks already in progress
+/// are completed, so a run can overrun its deadline by the time of its slowest bank.
+///
+/// # Arguments
+///
+/// * `dependencies` - Collection of dependencies with their versions
+/// * `registry_path` - Path to the cargo registry directory
+/// * `output_dir` - Path to the output directory for code bank files
+/// * `options` - Options controlling the generation
+///
+/// # Returns
+///
+/// * `Result<GenerationOutcome>` - The generated code bank files, the skipped
+///   dependencies, and how long each bank took to generate
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use depbank::{
+///     DependencyCollection, GenerateOptions, generate_code_banks_with_outcome,
+///     resolve_registry_path,
+/// };
+/// use std::path::Path;
+/// use std::time::{Duration, Instant};
+///
+/// let dependencies = DependencyCollection::new();
+/// let options = GenerateOptions {
+///     deadline: Some(Instant::now() + Duration::from_secs(300)),
+///     ..Default::default()
+/// };
+/// let outcome = generate_code_banks_with_outcome(
+///     &dependencies,
+///     &resolve_registry_path().unwrap(),
+///     Path::new(".codebank"),
+///     &options,
+/// )
+/// .unwrap();
+/// println!("Skipped {} dependencies", outcome.skipped.len());
+/// ```
+pub fn generate_code_banks_with_outcome(
+    dependencies: &DependencyCollection,
+    registry_path: &Path,
+    output_dir: &Path,
+    options: &GenerateOptions,
+) -> Result<GenerationOutcome> {
+    let mut code_bank_files = HashMap::new();
+
+    // Skip dependencies completed by a previous, possibly interrupted, run with the
+    // same options. Those built from local paths are always generated again, as their
+    // sources may have changed
+    let fingerprint = options_fingerprint(options);
+    let completed = if options.resume {
+        read_progress_log(output_dir)?
+    } else {
+        Vec::new()
+    };
+    let mut pending = Vec::new();
+    for dependency in dependencies.iter() {
+        match completed.iter().find(|entry| {
+            !dependency.has_path_source()
+                && entry.name == dependency.name
+                && entry.version == dependency.version
+                && entry.options == fingerprint
+                && entry.path.exists()
+        }) {
+            Some(entry) => {
+                code_bank_files.insert(entry.name.clone(), entry.path.clone());
+            }
+            None => pending.push(dependency),
+        }
+    }
+
+    // A bank of other sources would not document the locked version
+    let mismatches = find_version_mismatches(dependencies, registry_path);
+    for dependency in &pending {
+        if let Some(found) = mismatches.get(&dependency.name) {
+            emit_warning(Warning::new(
+                WarningKind::VersionMismatch,
+                Some(&dependency.name),
+                format!(
+                    "{} {} is locked, but only {} is available locally",
+                    dependency.name,
+                    dependency.version,
+                    found.join(", ")
+                ),
+            ));
+        }
+    }
+
+    let progress_log = ProgressLog::open(output_dir, options.resume)?;
+    let memory_budget = options.max_memory_mb.map(MemoryBudget::from_megabytes);
+
+    // Generate the banks in parallel on the shared thread pool
+    let results: Vec<(String, Option<Result<TimedBank>>)> = parallel::install(|| {
+        pending
+            .par_iter()
+            .map(|dependency| {
+                // Don't start new banks once the deadline has passed
+                if options
+                    .deadline
+                    .is_some_and(|deadline| Instant::now() >= deadline)
+                {
+                    return (dependency.name.clone(), None);
+                }
+
+                let dependency_path = dependency.get_registry_path(registry_path);
+                let result = if dependency_path.exists() && dependency_path.is_dir() {
+                    // Hold part of the memory budget until the bank has been written out
+                    let _permit = memory_budget.as_ref().map(|budget| {
+                        budget.acquire(memory::estimate_generation_memory(&dependency_path))
+                    });
+                    // Waiting for memory is not part of the bank's generation time
+                    let started = Instant::now();
+                    generate_code_bank(&dependency_path, output_dir, &dependency.name, options)
+                        .map(|path| (path, started.elapsed()))
+                        .map_err(|e| {
+                            anyhow::anyhow!(
+                                "Failed to generate code bank for {}: {}",
+                                dependency.name,
+                                e
+                            )
+                        })
+                } else {
+                    Err(anyhow::anyhow!(
+                        "Dependency not found: {}",
+                        dependency_path.display()
+                    ))
+                };
+
+                // Record completed banks right away, so an interrupted run can resume
+                if let Ok((path, _)) = &result {
+                    let entry = ProgressEntry {
+                        name: dependency.name.clone(),
+                        version: dependency.version.clone(),
+                        path: path.clone(),
+                        options: fingerprint.clone(),
+                    };
+                    if let Err(e) = progress_log.record(&entry) {
+                        emit_warning(Warning::new(
+                            WarningKind::ProgressLog,
+                            Some(&dependency.name),
+                            format!("{:#}", e),
+                        ));
+                    }
+                }
+
+                (dependency.name.clone(), Some(result))
+            })
+            .collect()
+    })?;
+
+    let mut errors = Vec::new();
+    let mut skipped = Vec::new();
+    let mut durations = HashMap::new();
+    for (name, result) in results {
+        match result {
+            Some(Ok((code_bank_file, duration))) => {
+                durations.insert(name.clone(), duration);
+                code_bank_files.insert(name, code_bank_file);
+            }
+            Some(Err(e)) => errors.push((name, e.to_string())),
+            None => skipped.push(name),
+        }
+    }
+
+    // If there were errors, log them but don't fail the operation
+    for (name, error) in &errors {
+        emit_warning(Warning::new(
+            WarningKind::GenerationFailed,
+            Some(name),
+            error.as_str(),
+        ));
+    }
+    skipped.sort();
+    if !skipped.is_empty() {
+        emit_warning(Warning::new(
+            WarningKind::DeadlineExceeded,
+            None,
+            format!(
+                "Deadline reached, skipped {} dependencies: {}",
+                skipped.len(),
+                skipped.join(", ")
+            ),
+        ));
+    }
+
+    Ok(GenerationOutcome {
+        code_bank_files,
+        skipped,
+        durations,
+    })
+}
+
+/// Options controlling how tokens are counted.
+#[derive(Debug, Clone, Default)]
+pub struct TokenOptions {
+    /// Count the special tokens (e.g. `[CLS]`/`[SEP]` or BOS/EOS) the tokenizer adds
+    /// around the text, matching APIs that bill for them
+    pub include_special_tokens: bool,
+    /// The tokenizer to count with
+    pub model: TokenizerModel,
+    /// Truncate inputs to this many tokens, like a model with this context length,
+    /// to check what fits rather than count every token
+    pub max_length: Option<usize>,
+    /// Reuse the counts cached in a directory's [`TOKEN_CACHE_FILE`] for the files
+    /// that did not change, and update the cache, when counting the tokens of a
+    /// depbank output directory, one holding an [`INDEX_JSON_FILE`]. Other
+    /// directories are never written to
+    pub use_cache: bool,
+    /// How many levels of subdirectories to count the files of when counting a
+    /// directory's tokens. 0, the default, counts the directory's own files only
+    pub max_depth: usize,
+}
+
+/// Calculates the number of tokens in a text.
+///
+/// # Arguments
+///
+/// * `text` - The text to tokenize
+/// * `options` - Options controlling how tokens are counted
+///
+/// # Returns
+///
+/// * `Result<usize>` - The number of tokens in the text
+pub fn calculate_tokens(text: &str, options: &TokenOptions) -> Result<usize> {
+    // Inputs are only truncated when checking what fits a context length
+    let tokenizer = shared_tokenizer(&options.model, options.max_length)?;
+
+    // Tokenize the text
+    let encoding = tokenizer
+        .encode(text, options.include_special_tokens)
+        .map_err(|e| anyhow::anyhow!("Failed to tokenize text: {}", e))?;
+
+    let tokens = encoding.get_tokens().len();
+    log::trace!(
+        target: LOG_TARGET_TOKENS,
+        "Counted {} tokens in {} bytes with {:?}",
+        tokens,
+        text.len(),
+        options.model
+    );
+    Ok(tokens)
+}
+
+/// Calculates tokens for a file.
+///
+/// # Arguments
+///
+/// * `file_path` - Path to the file
+/// * `options` - Options controlling how tokens are counted
+///
+/// # Returns
+///
+/// * `Result<usize>` - The number of tokens in the file
+pub fn calculate_file_tokens(file_path: &Path, options: &TokenOptions) -> Result<usize> {
+    // Read the file content
+    let content = fs::read_to_string(file_path)
+        .with_context(|| format!("Failed to read file: {}", file_path.display()))?;
+
+    // Calculate tokens
+    calculate_tokens(&content, options)
+}
+
+/// Represents file statistics including token count.
+#[derive(Debug)]
+pub struct FileStats {
+    pub path: PathBuf,
+    pub size_bytes: usize,
+    pub token_count: usize,
+}
+
+impl FileStats {
+    /// Makes the path of the file relative to a base directory, see [`relativize`], so
+    /// that the statistics hold no machine-specific paths
+    pub fn relativize_path(&mut self, base: &Path) {
+        self.path = relativize(&self.path, base);
+    }
+}
+
+/// Calculates tokens for all files in a directory.
+///
+/// This function traverses a directory and calculates token counts for each file,
+/// optionally filtering by file extension. It uses a transformer-based tokenizer to
+/// determine the token count, similar to how GPT models tokenize text.
+///
+/// # Arguments
+///
+/// * `dir_path` - Path to the directory
+/// * `extension` - Optional file extension filter (e.g., "md")
+/// * `options` - Options controlling how tokens are counted
+///
+/// # Returns
+///
+/// * `Result<HashMap<String, FileStats>>` - HashMap mapping filenames to their stats.
+///   Files in subdirectories, counted with [`TokenOptions::max_depth`], are named by
+///   their path relative to the directory, such as `nested/serde`
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The directory does not exist
+/// - There are permission issues accessing files
+/// - There are issues reading file contents
+/// - The tokenizer fails to process a file
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use depbank::{TokenOptions, calculate_directory_tokens};
+/// use std::path::Path;
+///
+/// // Calculate tokens for all markdown files in a directory
+/// let options = TokenOptions::default();
+/// let stats = calculate_directory_tokens(Path::new("./docs"), Some("md"), &options).unwrap();
+///
+/// let total_tokens: usize = stats.values().map(|stat| stat.token_count).sum();
+/// println!("Total: {} tokens across {} files", total_tokens, stats.len());
+///
+/// // Print individual file stats
+/// for (name, stat) in stats {
+///     println!("{}: {} tokens, {} bytes", name, stat.token_count, stat.size_bytes);
+/// }
+/// ```
+pub fn calculate_directory_tokens(
+    dir_path: &Path,
+    extension: Option<&str>,
+    options: &TokenOptions,
+) -> Result<HashMap<String, FileStats>> {
+    // Check if directory exists
+    if !dir_path.exists() || !dir_path.is_dir() {
+        return Err(anyhow::anyhow!(
+            "Directory does not exist or is not a directory: {}",
+            dir_path.display()
+        ));
+    }
+
+    // Read directory entries, down to the maximum depth
+    let mut paths = Vec::new();
+    collect_token_files(
+        dir_path,
+        extension,
+        options.max_depth,
+        &mut HashSet::new(),
+        &mut paths,
+    )?;
+
+    // Counts are only cached in depbank output directories, so counting the tokens
+    // of any other directory leaves it untouched
+    let cache = (options.use_cache && dir_path.join(INDEX_JSON_FILE).is_file())
+        .then(|| Mutex::new(TokenCache::load(dir_path, options)));
+
+    // Tokenize the files in parallel on the shared thread pool
+    let file_stats = parallel::install(|| {
+        paths
+            .into_par_iter()
+            .map(|path| {
+                // Name the file by its path below the directory, without extension
+                let relative = path.strip_prefix(dir_path).unwrap_or(&path);
+                let file_name = slash_path(&relative.with_extension(""));
+
+                // Get file size
+                let metadata = fs::metadata(&path)?;
+                let size_bytes = metadata.len() as usize;
+
+                // Calculate tokens for the file, unless its count is cached
+                let token_count = match &cache {
+                    Some(cache) => {
+                        let content = fs::read_to_string(&path)
+                            .with_context(|| format!("Failed to read file: {}", path.display()))?;
+                        let cache_key = slash_path(relative);
+                        let cached = cache
+                            .lock()
+                            .unwrap_or_else(|e| e.into_inner())
+                            .get(&cache_key, &content);
+                        match cached {
+                            Some(token_count) => token_count,
+                            None => {
+                                let token_count = calculate_tokens(&content, options)?;
+                                cache.lock().unwrap_or_else(|e| e.into_inner()).insert(
+                                    &cache_key,
+                                    &content,
+                                    token_count,
+                                );
+                                token_count
+                            }
+                        }
+                    }
+                    None => calculate_file_tokens(&path, options)?,
+                };
+
+                // Create file stats
+                let stats = FileStats {
+                    path,
+                    size_bytes,
+                    token_count,
+                };
+
+                Ok((file_name, stats))
+            })
+            .collect::<Result<HashMap<_, _>>>()
+    })??;
+
+    // A cache that cannot be written only makes the next run slower
+    if let Some(cache) = cache {
+        let cache = cache.into_inner().unwrap_or_else(|e| e.into_inner());
+        if let Err(e) = cache.save(dir_path) {
+            emit_warning(Warning::new(
+                WarningKind::TokenCache,
+                None,
+                format!("{:#}", e),
+            ));
+        }
+    }
+
+    Ok(file_stats)
+}
+
+/// Collects the files to count the tokens of in a directory, and in its subdirectories
+/// down to `depth` levels below it.
+///
+/// Hidden subdirectories and the token cache are skipped. Directories are walked once
+/// by their canonical path, so symlinks looping back to a parent end the walk.
+fn collect_token_files(
+    dir_path: &Path,
+    extension: Option<&str>,
+    depth: usize,
+    visited: &mut HashSet<PathBuf>,
+    paths: &mut Vec<PathBuf>,
+) -> Result<()> {
+    let canonical = dir_path
+        .canonicalize()
+        .with_context(|| format!("Failed to resolve directory: {}", dir_path.display()))?;
+    if !visited.insert(canonical) {
+        return Ok(());
+    }
+
+    for entry in fs::read_dir(dir_path)
+        .with_context(|| format!("Failed to read directory: {}", dir_path.display()))?
+    {
+        let entry = entry?;
+        let path = entry.path();
+
+        if path.is_dir() {
+            if depth > 0 && !entry.file_name().to_string_lossy().starts_with('.') {
+                collect_token_files(&path, extension, depth - 1, visited, paths)?;
+            }
+            continue;
+        }
+
+        // Skip the token cache and files that don't match the extension
+        if entry.file_name() == TOKEN_CACHE_FILE {
+            continue;
+        }
+        if let Some(ext) = extension {
+            #[allow(clippy::nonminimal_bool)]
+            if !path.extension().is_some_and(|e| e == ext) {
+                continue;
+            }
+        }
+
+        paths.push(path);
+    }
+
+    Ok(())
+}
+
+/// A relative path with `/` separators on every platform
+fn slash_path(path: &Path) -> String {
+    path.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Sums the token counts of files by their extension.
+///
+/// This breaks down the result of [`calculate_directory_tokens`] for directories
+/// mixing several kinds of files, e.g. to see how many tokens are in `.md` files
+/// compared with `.rs` files.
+///
+/// # Arguments
+///
+/// * `stats` - The stats of the files, e.g. the values returned by
+///   [`calculate_directory_tokens`]
+///
+/// # Returns
+///
+/// * `HashMap<String, usize>` - The total tokens of each extension, without the dot.
+///   Files without an extension are counted under an empty string
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use depbank::{TokenOptions, calculate_directory_tokens, tokens_by_extension};
+/// use std::path::Path;
+///
+/// let stats = calculate_directory_tokens(Path::new("./docs"), None, &TokenOptions::default()).unwrap();
+/// for (extension, tokens) in tokens_by_extension(stats.values()) {
+///     println!(".{}: {} tokens", extension, tokens);
+/// }
+/// ```
+pub fn tokens_by_extension<'a>(
+    stats: impl IntoIterator<Item = &'a FileStats>,
+) -> HashMap<String, usize> {
+    let mut totals = HashMap::new();
+    for stats in stats {
+        let extension = stats
+            .path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_string())
+            .unwrap_or_default();
+        *totals.entry(extension).or_insert(0) += stats.token_count;
+    }
+    totals
+}
+
+/// Estimates the combined token count of several code banks after deduplication.
+///
+/// Banks generated for different dependencies often share boilerplate such as common
+/// trait impls, imports, or license headers. This function concatenates the banks while
+/// keeping only the first occurrence of each non-blank line (compared after trimming),
+/// and counts the tokens of the result. Pass the banks in a stable order, such as by
+/// path, for the estimate to be the same on every run.
+///
+/// The result is an estimate of the "concatenate and dedup" footprint: line-level
+/// deduplication may also remove short structural lines (like a closing brace) that a
+/// real consumer would still need, so treat it as a lower bound rather than an exact count.
+///
+/// # Arguments
+///
+/// * `banks` - The contents of the code banks to aggregate
+///
+/// # Returns
+///
+/// * `Result<usize>` - The estimated number of tokens after deduplication
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use depbank::aggregate_unique_tokens;
+///
+/// let banks = ["impl Default for Foo {}\nfn foo() {}", "impl Default for Foo {}\nfn bar() {}"];
+/// let unique_tokens = aggregate_unique_tokens(&banks).unwrap();
+/// println!("Estimated tokens after dedup: {}", unique_tokens);
+/// ```
+pub fn aggregate_unique_tokens<S: AsRef<str>>(banks: &[S]) -> Result<usize> {
+    let mut seen = HashSet::new();
+    let mut combined = String::new();
+
+    for bank in banks {
+        for line in bank.as_ref().lines() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || !seen.insert(trimmed) {
+                continue;
+            }
+            combined.push_str(line);
+            combined.push('\n');
+        }
+    }
+
+    calculate_tokens(&combined, &TokenOptions::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::io::Write;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_find_cargo_toml_files() -> Result<()> {
+        // Create a temporary directory structure
+        let temp_dir = tempdir()?;
+        let temp_path = temp_dir.path();
+
+        // Create a Cargo.toml file in the root directory
+        let root_cargo_toml = temp_path.join("Cargo.toml");
+        File::create(&root_cargo_toml)?
+            .write_all(b"[package]\nname = \"test\"\nversion = \"0.1.0\"\n")?;
+
+        // Create a subdirectory with a Cargo.toml file
+        let sub_dir = temp_path.join("sub_project");
+        fs::create_dir(&sub_dir)?;
+        let sub_cargo_toml = sub_dir.join("Cargo.toml");
+        File::create(&sub_cargo_toml)?
+            .write_all(b"[package]\nname = \"sub_test\"\nversion = \"0.1.0\"\n")?;
+
+        // Create a nested subdirectory with a Cargo.toml file
+        let nested_dir = sub_dir.join("nested_project");
+        fs::create_dir(&nested_dir)?;
+        let nested_cargo_toml = nested_dir.join("Cargo.toml");
+        File::create(&nested_cargo_toml)?
+            .write_all(b"[package]\nname = \"nested_test\"\nversion = \"0.1.0\"\n")?;
+
+        // Create a hidden directory that should be skipped
+        let hidden_dir = temp_path.join(".hidden");
+        fs::create_dir(&hidden_dir)?;
+        let hidden_cargo_toml = hidden_dir.join("Cargo.toml");
+        File::create(&hidden_cargo_toml)?
+            .write_all(b"[package]\nname = \"hidden_test\"\nversion = \"0.1.0\"\n")?;
+
+        // Find all Cargo.toml files
+        let cargo_toml_files = find_cargo_toml_files(temp_path, &DiscoveryOptions::default())?;
+
+        // We should find 3 Cargo.toml files (not including the one in the hidden directory)
+        assert_eq!(cargo_toml_files.len(), 3);
+
+        // Check that the expected files are included
+        assert!(cargo_toml_files.contains(&root_cargo_toml));
+        assert!(cargo_toml_files.contains(&sub_cargo_toml));
+        assert!(cargo_toml_files.contains(&nested_cargo_toml));
+
+        // Check that the hidden file is not included
+        assert!(!cargo_toml_files.contains(&hidden_cargo_toml));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_cargo_toml_files_ignored_dirs() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let temp_path = temp_dir.path();
+        let root_cargo_toml = temp_path.join("Cargo.toml");
+        fs::write(&root_cargo_toml, "[package]\nname = \"test\"\n")?;
+
+        // The manifest of a packaged crate in the build output
+        let packaged_dir = temp_path.join("target").join("package").join("test-0.1.0");
+        fs::create_dir_all(&packaged_dir)?;
+        let packaged_cargo_toml = packaged_dir.join("Cargo.toml");
+        fs::write(&packaged_cargo_toml, "[package]\nname = \"test\"\n")?;
+
+        let vendor_dir = temp_path.join("vendor").join("dep");
+        fs::create_dir_all(&vendor_dir)?;
+        let vendor_cargo_toml = vendor_dir.join("Cargo.toml");
+        fs::write(&vendor_cargo_toml, "[package]\nname = \"dep\"\n")?;
+
+        // target is skipped by default
+        let mut cargo_toml_files = find_cargo_toml_files(temp_path, &DiscoveryOptions::default())?;
+        cargo_toml_files.sort();
+        assert_eq!(
+            cargo_toml_files,
+            vec![root_cargo_toml.clone(), vendor_cargo_toml.clone()]
+        );
+
+        let options = DiscoveryOptions {
+            no_default_ignore: true,
+            ..Default::default()
+        };
+        let mut cargo_toml_files = find_cargo_toml_files(temp_path, &options)?;
+        cargo_toml_files.sort();
+        assert_eq!(
+            cargo_toml_files,
+            vec![
+                root_cargo_toml.clone(),
+                packaged_cargo_toml.clone(),
+                vendor_cargo_toml
+            ]
+        );
+
+        // Explicitly ignored directories are still skipped without the defaults
+        let options = DiscoveryOptions {
+            ignore_dirs: vec!["vendor".to_string()],
+            no_default_ignore: true,
+            ..Default::default()
+        };
+        let mut cargo_toml_files = find_cargo_toml_files(temp_path, &options)?;
+        cargo_toml_files.sort();
+        assert_eq!(cargo_toml_files, vec![root_cargo_toml, packaged_cargo_toml]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_cargo_toml_files_explicit_manifests() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let temp_path = temp_dir.path();
+        let mut manifests = Vec::new();
+        for (dir, dependency) in [("api", "serde"), ("worker", "tokio"), ("cli", "clap")] {
+            let crate_dir = temp_path.join(dir);
+            fs::create_dir_all(&crate_dir)?;
+            let manifest = crate_dir.join("Cargo.toml");
+            fs::write(
+                &manifest,
+                format!(
+                    "[package]\nname = \"{}\"\n\n[dependencies]\n{} = \"1\"\n",
+                    dir, dependency
+                ),
+            )?;
+            manifests.push(manifest);
+        }
+
+        // Only the given manifests are used, without searching the tree
+        let options = DiscoveryOptions {
+            manifests: vec![manifests[2].clone(), manifests[0].clone()],
+            ..Default::default()
+        };
+        let cargo_toml_files = find_cargo_toml_files(temp_path, &options)?;
+        assert_eq!(cargo_toml_files, options.manifests);
+        let mut dependencies: Vec<String> = collect_dependencies(&cargo_toml_files)?
+            .into_iter()
+            .collect();
+        dependencies.sort();
+        assert_eq!(dependencies, vec!["clap", "serde"]);
+
+        // Paths must be existing Cargo.toml files
+        let readme = temp_path.join("api").join("README.md");
+        fs::write(&readme, "# api\n")?;
+        let err = validate_manifest_paths(&[readme]).unwrap_err();
+        assert!(err.to_string().contains("not named Cargo.toml"));
+        let err =
+            validate_manifest_paths(&[temp_path.join("missing").join("Cargo.toml")]).unwrap_err();
+        assert!(err.to_string().contains("does not exist"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_cargo_toml_files_nonexistent_dir() {
+        let result = find_cargo_toml_files(
+            Path::new("/nonexistent/directory"),
+            &DiscoveryOptions::default(),
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_collect_dependency_provenance_workspace() -> Result<()> {
+        let workspace = Path::new("fixtures/workspace_project");
+        let cargo_toml_files = find_cargo_toml_files(workspace, &DiscoveryOptions::default())?;
+        let provenance = collect_dependency_provenance(&cargo_toml_files)?;
+
+        // `log` is declared by both members
+        let log_sources = &provenance["log"];
+        assert_eq!(log_sources.len(), 2);
+        assert!(log_sources.contains(&workspace.join("core").join("Cargo.toml")));
+        assert!(log_sources.contains(&workspace.join("utils").join("Cargo.toml")));
+
+        // Member-specific dependencies come from a single manifest
+        assert_eq!(
+            provenance["tokio"],
+            vec![workspace.join("core").join("Cargo.toml")]
+        );
+        assert_eq!(
+            provenance["chrono"],
+            vec![workspace.join("utils").join("Cargo.toml")]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_cargo_toml_files_file_as_dir() -> Result<()> {
+        // Create a temporary file
+        let temp_dir = tempdir()?;
+        let temp_file = temp_dir.path().join("file.txt");
+        File::create(&temp_file)?.write_all(b"content")?;
+
+        // Try to use the file as a directory
+        let result = find_cargo_toml_files(&temp_file, &DiscoveryOptions::default());
+        assert!(result.is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_collect_dependency_kinds() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let app = temp_dir.path().join("Cargo.toml");
+        fs::write(
+            &app,
+            "[dependencies]\nserde = \"1\"\n\n[dev-dependencies]\ntokio = \"1\"\n\n[build-dependencies]\ncc = \"1\"\n",
+        )?;
+        let member = temp_dir.path().join("member.toml");
+        fs::write(&member, "[dependencies]\ntokio = \"1\"\n")?;
+
+        let kinds = collect_dependency_kinds(&[app, member])?;
+        assert_eq!(kinds["serde"], BTreeSet::from([DependencyKind::Normal]));
+        assert_eq!(
+            kinds["tokio"],
+            BTreeSet::from([DependencyKind::Normal, DependencyKind::Dev])
+        );
+        assert_eq!(kinds["cc"], BTreeSet::from([DependencyKind::Build]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_collect_dependencies() -> Result<()> {
+        // Create a temporary directory with a Cargo.toml file
+        let temp_dir = tempdir()?;
+        let cargo_toml_path = temp_dir.path().join("Cargo.toml");
+
+        // Create a sample Cargo.toml file with dependencies
+        let cargo_toml_content = r#"
+[package]
+name = "test-project"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+anyhow = "1.0"
+serde = { version = "1.0", features = ["derive"] }
+tokio = { version = "1.0", features = ["full"] }
+
+[dev-dependencies]
+tempfile = "3.0"
+
+[build-dependencies]
+cc = "1.0"
+"#;
+
+        File::create(&cargo_toml_path)?.write_all(cargo_toml_content.as_bytes())?;
+
+        // Collect dependencies from the Cargo.toml file
+        let dependencies = collect_dependencies(&[cargo_toml_path])?;
+
+        // Check that all dependencies were collected
+        assert!(dependencies.contains("anyhow"));
+        assert!(dependencies.contains("serde"));
+        assert!(dependencies.contains("tokio"));
+        assert!(dependencies.contains("tempfile"));
+        assert!(dependencies.contains("cc"));
+
+        // Check that the total number of dependencies is correct
+        assert_eq!(dependencies.len(), 5);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_dependency_info() -> Result<()> {
+        // Create a temporary directory with a Cargo.toml file
+        let temp_dir = tempdir()?;
+        let cargo_toml_path = temp_dir.path().join("Cargo.toml");
+
+        // Create a sample Cargo.toml file with dependencies
+        let cargo_toml_content = r#"
+[package]
+name = "test-project"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+anyhow = "1.0"
+serde = { version = "1.0", features = ["derive"] }
+tokio = { version = "1.0", features = ["full"] }
+
+[dev-dependencies]
+tempfile = "3.0"
+
+[build-dependencies]
+cc = "1.0"
+"#;
+
+        File::create(&cargo_toml_path)?.write_all(cargo_toml_content.as_bytes())?;
+
+        // Extract dependency info from the Cargo.toml file
+        let dependency_info = extract_dependency_info(&cargo_toml_path)?;
+
+        // Check that all dependencies have correct versions
+        let dep1 = dependency_info.get("anyhow").unwrap();
+        assert_eq!(dep1.version, "1.0");
+        let dep2 = dependency_info.get("serde").unwrap();
+        assert_eq!(dep2.version, "1.0");
+        let dep3 = dependency_info.get("tokio").unwrap();
+        assert_eq!(dep3.version, "1.0");
+        let dep4 = dependency_info.get("tempfile").unwrap();
+        assert_eq!(dep4.version, "3.0");
+        let dep5 = dependency_info.get("cc").unwrap();
+        assert_eq!(dep5.version, "1.0");
+
+        // Check that the total number of dependencies is correct
+        assert_eq!(dependency_info.len(), 5);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_dependency_versions() -> Result<()> {
+        let temp_dir = tempdir()?;
+
+        // Create a sample Cargo.lock file
+        let cargo_lock_path = temp_dir.path().join("Cargo.lock");
+        let cargo_lock_content = r#"
+# This file is automatically @generated by Cargo.
+# It is not intended for manual editing.
+version = 4
+
+[[package]]
+name = "anyhow"
+version = "1.0.68"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "e6b88822cbe49de4185e3a4cbf8321dd487cf5fe0c5c65695fef6346371e9c48"
+
+[[package]]
+name = "anyhow"
+version = "1.0.75"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "a4668cab20f66d8d020e1fbc0ebe47217433c1b6c8f2040faf858554e394ace6"
+
+[[package]]
+name = "serde"
+version = "1.0.150"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "e6b88822cbe49de4185e3a4cbf8321dd487cf5fe0c5c65695fef6346371e9c48"
+
+[[package]]
+name = "toml"
+version = "0.8.4"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "e6b88822cbe49de4185e3a4cbf8321dd487cf5fe0c5c65695fef6346371e9c48"
+"#;
+        File::create(&cargo_lock_path)?.write_all(cargo_lock_content.as_bytes())?;
+
+        // Create a sample dependencies DependencyCollection
+        let mut dependencies = DependencyCollection::new();
+        dependencies.add(Dependency::new("anyhow", "1.0.75"));
+        dependencies.add(Dependency::new("serde", "1.0.150"));
+        dependencies.add(Dependency::new("toml", "0.8.4"));
+        dependencies.add(Dependency::new("nonexistent", "1.0"));
+
+        // Resolve versions
+        let resolved = resolve_dependency_versions(cargo_lock_path, &dependencies)?;
+
+        // Check the resolved versions
+        let dep1 = resolved.get("anyhow").unwrap();
+        assert_eq!(dep1.version, "1.0.75");
+        let dep2 = resolved.get("serde").unwrap();
+        assert_eq!(dep2.version, "1.0.150");
+        let dep3 = resolved.get("toml").unwrap();
+        assert_eq!(dep3.version, "0.8.4");
+        assert!(!resolved.contains_name("nonexistent"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_dependency_versions_matches_requirement() -> Result<()> {
+        let cargo_lock = r#"version = 4
+
+[[package]]
+name = "rand"
+version = "0.7.3"
+
+[[package]]
+name = "rand"
+version = "0.8.5"
+"#;
+        let mut dependencies = DependencyCollection::new();
+        dependencies.add(Dependency::new("rand", "0.7"));
+        dependencies.add(Dependency::new("rand", "0.8.1"));
+        dependencies.add(Dependency::new("rand", "workspace"));
+
+        // Each requirement resolves to the locked version it matches, others to the last
+        let resolved = resolve_dependency_versions_from_str(cargo_lock, &dependencies)?;
+        let versions: Vec<&str> = resolved.iter().map(|dep| dep.version.as_str()).collect();
+        assert_eq!(versions, vec!["0.7.3", "0.8.5", "0.8.5"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_cargo_lock() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let cargo_lock_path = temp_dir.path().join("Cargo.lock");
+
+        // Create an empty Cargo.lock file
+        File::create(&cargo_lock_path)?;
+
+        // Test finding it in the current directory
+        let found_path = find_cargo_lock(temp_dir.path())?;
+        assert_eq!(found_path, cargo_lock_path);
+
+        // Test finding it from a subdirectory
+        let sub_dir = temp_dir.path().join("subdir");
+        fs::create_dir(&sub_dir)?;
+        let found_from_subdir = find_cargo_lock(&sub_dir)?;
+        assert_eq!(found_from_subdir, cargo_lock_path);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_project_name() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let package = temp_dir.path().join("checkout");
+        fs::create_dir(&package)?;
+        fs::write(
+            package.join("Cargo.toml"),
+            "[package]\nname = \"api-server\"\nversion = \"0.1.0\"\n",
+        )?;
+        assert_eq!(project_name(&package), "api-server");
+
+        // Workspaces without a root package are named after their directory
+        let workspace = temp_dir.path().join("platform");
+        fs::create_dir_all(workspace.join("core"))?;
+        fs::write(
+            workspace.join("Cargo.toml"),
+            "[workspace]\nmembers = [\"core\"]\n",
+        )?;
+        assert_eq!(project_name(&workspace), "platform");
+        assert_eq!(project_name(&workspace.join("core").join("..")), "platform");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_cargo_lock() -> Result<()> {
+        let temp_dir = tempdir()?;
+
+        // A lockfile in a non-standard location with a non-standard name is accepted
+        let lock_dir = temp_dir.path().join("locks");
+        fs::create_dir(&lock_dir)?;
+        let lock_path = lock_dir.join("pinned.lock");
+        fs::write(
+            &lock_path,
+            "version = 4\n\n[[package]]\nname = \"anyhow\"\nversion = \"1.0.75\"\n",
+        )?;
+        assert_eq!(validate_cargo_lock(&lock_path)?, lock_path);
+
+        // Missing files and directories are rejected
+        assert!(validate_cargo_lock(temp_dir.path().join("missing.lock")).is_err());
+        assert!(validate_cargo_lock(&lock_dir).is_err());
+
+        // A TOML file that isn't a lockfile is rejected
+        let not_lock = temp_dir.path().join("Cargo.toml");
+        fs::write(&not_lock, "[package]\nname = \"test\"\n")?;
+        assert!(validate_cargo_lock(&not_lock).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unlocked_dependency_warning() {
+        let kind = |name: &str, version: &str, locked: &[&str]| {
+            unlocked_dependency_warning(&Dependency::new(name, version), locked)
+                .map(|warning| warning.kind)
+        };
+
+        // No package of the name at all, e.g. a typo
+        assert_eq!(
+            kind("serde_jsno", "1.0", &[]),
+            Some(WarningKind::NotInLockfile)
+        );
+        let warning = unlocked_dependency_warning(&Dependency::new("serde_jsno", "1.0"), &[]);
+        assert_eq!(warning.unwrap().dependency.as_deref(), Some("serde_jsno"));
+
+        // Packages of the name, but none of an allowed version, e.g. a stale lockfile
+        assert_eq!(
+            kind("rand", "0.9", &["0.7.3", "0.8.5"]),
+            Some(WarningKind::NoMatchingLockedVersion)
+        );
+        let warning =
+            unlocked_dependency_warning(&Dependency::new("rand", "0.9"), &["0.7.3", "0.8.5"]);
+        assert!(
+            warning
+                .unwrap()
+                .message
+                .contains("only has rand 0.7.3, 0.8.5")
+        );
+
+        // Matching packages, and requirements left to other warnings
+        assert_eq!(kind("rand", "0.8", &["0.7.3", "0.8.5"]), None);
+        assert_eq!(kind("rand", "*", &[]), None);
+        assert_eq!(kind("rand", "workspace", &[]), None);
+        assert_eq!(kind("rand", "not a version", &["0.8.5"]), None);
+    }
+
+    #[test]
+    fn test_resolve_dependency_versions_nonexistent_file() {
+        let result = resolve_dependency_versions(
+            Path::new("/nonexistent/Cargo.lock"),
+            &DependencyCollection::new(),
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_extract_dependency_info_from_str() -> Result<()> {
+        let cargo_toml_content = r#"
+[package]
+name = "test-project"
+version = "0.1.0"
+
+[dependencies]
+anyhow = "1.0"
+serde = { version = "1.0", features = ["derive"] }
+shared = { workspace = true }
+
+[dev-dependencies]
+tempfile = "3.0"
+"#;
+
+        let dependency_info = extract_dependency_info_from_str(cargo_toml_content)?;
+
+        assert_eq!(dependency_info.len(), 4);
+        assert_eq!(dependency_info.get("anyhow").unwrap().version, "1.0");
+        assert_eq!(dependency_info.get("serde").unwrap().version, "1.0");
+        assert_eq!(dependency_info.get("shared").unwrap().version, "workspace");
+        assert_eq!(dependency_info.get("tempfile").unwrap().version, "3.0");
+
+        // Renamed dependencies are named after the package they refer to
+        let renamed = extract_dependency_info_from_str(
+            "[dependencies]\nrand_old = { package = \"rand\", version = \"0.7\" }\n",
+        )?;
+        assert_eq!(renamed.get("rand").unwrap().version, "0.7");
+        assert!(renamed.get("rand_old").is_none());
+
+        // Invalid manifests are reported as errors
+        assert!(extract_dependency_info_from_str("[dependencies\nserde = ").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_dependency_info_dotted_keys() -> Result<()> {
+        let cargo_toml_content = r#"
+dependencies.anyhow.version = "1.0"
+
+[package]
+name = "test-project"
+version = "0.1.0"
+
+[dev-dependencies]
+serde.version = "1.0.197"
+serde.features = ["derive"]
+tempfile.workspace = true
+"#;
+
+        let dependency_info = extract_dependency_info_from_str(cargo_toml_content)?;
+
+        assert_eq!(dependency_info.len(), 3);
+        assert_eq!(dependency_info.get("anyhow").unwrap().version, "1.0");
+        assert_eq!(dependency_info.get("serde").unwrap().version, "1.0.197");
+        assert_eq!(
+            dependency_info.get("tempfile").unwrap().version,
+            "workspace"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_dependency_info_unusual_versions() -> Result<()> {
+        let cargo_toml_content = r#"
+[package]
+name = "test-project"
+version = "0.1.0"
+
+[dependencies]
+shared = { version = { workspace = true }, features = ["full"] }
+numeric = { version = 1 }
+bare = 2
+flag = true
+nested = { version = { min = "1" } }
+"#;
+
+        // Unusual shapes fall back to a version instead of failing the whole parse
+        let dependency_info = extract_dependency_info_from_str(cargo_toml_content)?;
+
+        assert_eq!(dependency_info.len(), 5);
+        assert_eq!(dependency_info.get("shared").unwrap().version, "workspace");
+        assert_eq!(dependency_info.get("numeric").unwrap().version, "1");
+        assert_eq!(dependency_info.get("bare").unwrap().version, "2");
+        assert_eq!(dependency_info.get("flag").unwrap().version, "*");
+        assert_eq!(dependency_info.get("nested").unwrap().version, "*");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_group_by_source() -> Result<()> {
+        let cargo_lock_content = r#"
+version = 4
+
+[[package]]
+name = "anyhow"
+version = "1.0.98"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "serde"
+version = "1.0.219"
+source = "sparse+https://index.crates.io/"
+
+[[package]]
+name = "internal-auth"
+version = "2.1.0"
+source = "sparse+https://crates.example.com/index/"
+
+[[package]]
+name = "tokio"
+version = "1.36.0"
+source = "git+https://github.com/tokio-rs/tokio?branch=master#0123abcd"
+
+[[package]]
+name = "shared"
+version = "0.1.0"
+
+[[package]]
+name = "vendored"
+version = "0.3.0"
+source = "path+file:///work/vendor/vendored"
+"#;
+
+        let packages = locked_packages_from_str(cargo_lock_content)?;
+        let groups = packages.group_by_source();
+        let names = |source: DependencySource| -> Vec<&str> {
+            groups[&source]
+                .iter()
+                .map(|dep| dep.name.as_str())
+                .collect()
+        };
+
+        assert_eq!(groups.len(), 4);
+        assert_eq!(names(DependencySource::CratesIo), ["anyhow", "serde"]);
+        assert_eq!(names(DependencySource::Registry), ["internal-auth"]);
+        assert_eq!(names(DependencySource::Git), ["tokio"]);
+        assert_eq!(names(DependencySource::Path), ["shared", "vendored"]);
+
+        // Groups come in a stable order, from crates.io to local paths
+        assert_eq!(
+            groups.keys().copied().collect::<Vec<_>>(),
+            [
+                DependencySource::CratesIo,
+                DependencySource::Registry,
+                DependencySource::Git,
+                DependencySource::Path
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_dependency_versions_from_str() -> Result<()> {
+        let cargo_lock_content = r#"
+version = 4
+
+[[package]]
+name = "anyhow"
+version = "1.0.75"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "serde"
+version = "1.0.150"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#;
+
+        let mut dependencies = DependencyCollection::new();
+        dependencies.add(Dependency::new("anyhow", "1.0"));
+        dependencies.add(Dependency::new("serde", "1.0"));
+        dependencies.add(Dependency::new("nonexistent", "1.0"));
+
+        let resolved = resolve_dependency_versions_from_str(cargo_lock_content, &dependencies)?;
+
+        assert_eq!(resolved.len(), 2);
+        assert_eq!(resolved.get("anyhow").unwrap().version, "1.0.75");
+        assert_eq!(resolved.get("serde").unwrap().version, "1.0.150");
+        assert!(resolved.get("nonexistent").is_none());
+
+        assert!(resolve_dependency_versions_from_str("[[package]", &dependencies).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_single_version() -> Result<()> {
+        let workspace = Path::new("fixtures/workspace_project");
+        assert_eq!(
+            resolve_single_version(workspace, "serde")?,
+            Some("1.0.197".to_string())
+        );
+        assert_eq!(
+            resolve_single_version(workspace, "env_logger")?,
+            Some("0.10.2".to_string())
+        );
+        assert_eq!(resolve_single_version(workspace, "nonexistent")?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_single_version_multiple_versions() -> Result<()> {
+        let temp_dir = tempdir()?;
+        fs::write(
+            temp_dir.path().join("Cargo.toml"),
+            "[package]\nname = \"demo\"\nversion = \"0.1.0\"\n\n[dependencies]\nrand = \"0.7\"\n",
+        )?;
+        fs::write(
+            temp_dir.path().join("Cargo.lock"),
+            r#"
+version = 4
+
+[[package]]
+name = "rand"
+version = "0.8.5"
+
+[[package]]
+name = "rand"
+version = "0.7.3"
+
+[[package]]
+name = "rand_core"
+version = "0.5.1"
+
+[[package]]
+name = "rand_core"
+version = "0.6.4"
+"#,
+        )?;
+
+        // The version matching the declared requirement wins over the newest one
+        assert_eq!(
+            resolve_single_version(temp_dir.path(), "rand")?,
+            Some("0.7.3".to_string())
+        );
+        // Without a declared requirement, the newest locked version is used
+        assert_eq!(
+            resolve_single_version(temp_dir.path(), "rand_core")?,
+            Some("0.6.4".to_string())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalize_crate_name() {
+        assert_eq!(normalize_crate_name("serde_json"), "serde-json");
+        assert_eq!(normalize_crate_name("Serde-JSON"), "serde-json");
+        assert_eq!(normalize_crate_name("anyhow"), "anyhow");
+    }
+
+    #[test]
+    fn test_resolve_underscore_name_to_hyphenated_registry_crate() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let registry_dir = temp_dir.path().join("registry");
+        fs::create_dir_all(registry_dir.join("foo-bar-1.2.3"))?;
+
+        let dependencies = extract_dependency_info_from_str("[dependencies]\nfoo_bar = \"1.2\"\n")?;
+        let lock = "[[package]]\nname = \"foo-bar\"\nversion = \"1.2.3\"\n";
+        let resolved = resolve_dependency_versions_from_str(lock, &dependencies)?;
+
+        assert_eq!(resolved.len(), 1);
+        let dependency = &resolved.as_slice()[0];
+        assert_eq!(dependency.name, "foo-bar");
+        assert_eq!(dependency.version, "1.2.3");
+        assert!(is_dependency_available(&registry_dir, dependency));
+
+        // The registry lookup itself also tolerates the declared spelling
+        let declared = Dependency::new("foo_bar", "1.2.3");
+        assert!(declared.is_available_in_registry(&registry_dir));
+        assert_eq!(
+            declared.get_registry_path(&registry_dir),
+            registry_dir.join("foo-bar-1.2.3")
+        );
+        assert!(!Dependency::new("foo_bar", "2.0.0").is_available_in_registry(&registry_dir));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_dependency_lookup_ignores_casing() -> Result<()> {
+        let temp_dir = tempdir()?;
+        fs::create_dir_all(temp_dir.path().join("openssl-0.10.72"))?;
+
+        let mut dependencies = DependencyCollection::new();
+        dependencies.add(Dependency::new("OpenSSL", "0.10.72"));
+        dependencies.add(Dependency::new("serde_json", "1.0.140"));
+
+        // Lookups match whatever the casing, and keep the declared name for display
+        assert!(dependencies.contains_name("openssl"));
+        assert_eq!(dependencies.get("OPENSSL").unwrap().name, "OpenSSL");
+        assert_eq!(
+            dependencies.get_version("Serde-JSON"),
+            Some(&"1.0.140".to_string())
+        );
+        assert!(dependencies.get("openssl-sys").is_none());
+
+        // The declared casing finds the registry directory
+        let available = dependencies.filter_available(temp_dir.path());
+        assert_eq!(available.len(), 1);
+        let openssl = available.get("openssl").unwrap();
+        assert_eq!(openssl.name, "OpenSSL");
+        assert_eq!(
+            openssl.get_registry_path(temp_dir.path()),
+            temp_dir.path().join("openssl-0.10.72")
+        );
+
+        // An exact name is preferred over another spelling
+        let mut spellings = DependencyCollection::new();
+        spellings.add(Dependency::new("foo-bar", "1.0.0"));
+        spellings.add(Dependency::new("foo_bar", "2.0.0"));
+        assert_eq!(spellings.get_version("foo_bar"), Some(&"2.0.0".to_string()));
+        assert_eq!(spellings.get_version("Foo_Bar"), Some(&"1.0.0".to_string()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_dependencies_of_crate() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let registry_dir = temp_dir.path();
+        let crate_dir = registry_dir.join("serde_json-1.0.140");
+        fs::create_dir_all(&crate_dir)?;
+
+        // Published manifests are normalized by cargo into one table per dependency
+        fs::write(
+            crate_dir.join("Cargo.toml"),
+            r#"
+[package]
+edition = "2021"
+name = "serde_json"
+version = "1.0.140"
+
+[dependencies.itoa]
+version = "1.0"
+
+[dependencies.serde]
+version = "1.0.194"
+default-features = false
+
+[dev-dependencies.trybuild]
+version = "1.0.49"
+
+[build-dependencies.autocfg]
+version = "1"
+"#,
+        )?;
+
+        let dependencies =
+            dependencies_of_crate(registry_dir, &Dependency::new("serde_json", "1.0.140"))?;
+
+        assert_eq!(dependencies.len(), 3);
+        assert_eq!(dependencies.get_version("itoa"), Some(&"1.0".to_string()));
+        assert_eq!(
+            dependencies.get_version("serde"),
+            Some(&"1.0.194".to_string())
+        );
+        assert_eq!(dependencies.get_version("autocfg"), Some(&"1".to_string()));
+        assert!(!dependencies.contains_name("trybuild"));
+
+        // Crates missing from the registry are reported as errors
+        assert!(dependencies_of_crate(registry_dir, &Dependency::new("missing", "1.0.0")).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_construct_dependency_path() {
+        let registry_path = Path::new("/home/user/.cargo/registry/src/index.crates.io-12345");
+        let dependency_name = "serde";
+        let dependency_version = "1.0.150";
 
-        // Get file name as string
-        let file_name = path
-            .file_stem()
-            .and_then(|name| name.to_str())
-            .unwrap_or_default()
-            .to_string();
+        let expected_path =
+            PathBuf::from("/home/user/.cargo/registry/src/index.crates.io-12345/serde-1.0.150");
+        let actual_path =
+            construct_dependency_path(registry_path, dependency_name, dependency_version);
 
-        // Get file size
-        let metadata = fs::metadata(&path)?;
-        let size_bytes = metadata.len() as usize;
+        assert_eq!(actual_path, expected_path);
+    }
 
-        // Calculate tokens for the file
-        let token_count = calculate_file_tokens(&path)?;
+    #[test]
+    fn test_resolve_registry_path_with_mock() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let mock_cargo_home = temp_dir.path().join("cargo-home");
 
-        // Create file stats
-        let stats = FileStats {
-            path: path.clone(),
-            size_bytes,
-            token_count,
-        };
+        // Without a registry in the cargo home, resolution fails
+        fs::create_dir_all(&mock_cargo_home)?;
+        assert!(registry_src_dir_in(&mock_cargo_home).is_err());
 
-        // Add to the map
-        file_stats.insert(file_name, stats);
-    }
+        // Create mock registry structure
+        let registry_src = mock_cargo_home.join("registry").join("src");
+        fs::create_dir_all(&registry_src)?;
 
-    Ok(file_stats)
-}
+        // Create two registry directories with different modification times
+        let old_registry = registry_src.join("index.crates.io-old");
+        let new_registry = registry_src.join("index.crates.io-new");
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::fs::File;
-    use std::io::Write;
-    use tempfile::tempdir;
+        fs::create_dir(&old_registry)?;
 
-    #[test]
-    fn test_find_cargo_toml_files() -> Result<()> {
-        // Create a temporary directory structure
-        let temp_dir = tempdir()?;
-        let temp_path = temp_dir.path();
+        // Sleep to ensure different modification times
+        std::thread::sleep(std::time::Duration::from_millis(10));
 
-        // Create a Cargo.toml file in the root directory
-        let root_cargo_toml = temp_path.join("Cargo.toml");
-        File::create(&root_cargo_toml)?
-            .write_all(b"[package]\nname = \"test\"\nversion = \"0.1.0\"\n")?;
+        fs::create_dir(&new_registry)?;
 
-        // Create a subdirectory with a Cargo.toml file
-        let sub_dir = temp_path.join("sub_project");
-        fs::create_dir(&sub_dir)?;
-        let sub_cargo_toml = sub_dir.join("Cargo.toml");
-        File::create(&sub_cargo_toml)?
-            .write_all(b"[package]\nname = \"sub_test\"\nversion = \"0.1.0\"\n")?;
+        // The most recently modified directory is the active one
+        let registry_dir = registry_src_dir_in(&mock_cargo_home)?;
+        assert_eq!(registry_dir, registry_src);
+        assert_eq!(latest_registry_dir(&registry_dir)?, new_registry);
 
-        // Create a nested subdirectory with a Cargo.toml file
-        let nested_dir = sub_dir.join("nested_project");
-        fs::create_dir(&nested_dir)?;
-        let nested_cargo_toml = nested_dir.join("Cargo.toml");
-        File::create(&nested_cargo_toml)?
-            .write_all(b"[package]\nname = \"nested_test\"\nversion = \"0.1.0\"\n")?;
+        Ok(())
+    }
 
-        // Create a hidden directory that should be skipped
-        let hidden_dir = temp_path.join(".hidden");
-        fs::create_dir(&hidden_dir)?;
-        let hidden_cargo_toml = hidden_dir.join("Cargo.toml");
-        File::create(&hidden_cargo_toml)?
-            .write_all(b"[package]\nname = \"hidden_test\"\nversion = \"0.1.0\"\n")?;
+    #[test]
+    fn test_generate_code_bank() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let source_dir = temp_dir.path().join("source");
+        let output_dir = temp_dir.path().join("output");
 
-        // Find all Cargo.toml files
-        let cargo_toml_files = find_cargo_toml_files(temp_path)?;
+        // Create source directory structure
+        fs::create_dir_all(&source_dir)?;
 
-        // We should find 3 Cargo.toml files (not including the one in the hidden directory)
-        assert_eq!(cargo_toml_files.len(), 3);
+        // Create a sample source file
+        let source_file = source_dir.join("lib.rs");
+        let source_content = r#"
+/// This is a test function.
+pub fn test_function() -> String {
+    "Hello, world!".to_string()
+}
+"#;
+        fs::write(&source_file, source_content)?;
 
-        // Check that the expected files are included
-        assert!(cargo_toml_files.contains(&root_cargo_toml));
-        assert!(cargo_toml_files.contains(&sub_cargo_toml));
-        assert!(cargo_toml_files.contains(&nested_cargo_toml));
+        // Generate code bank
+        let code_bank_file = generate_code_bank(
+            &source_dir,
+            &output_dir,
+            "test_dependency",
+            &GenerateOptions::default(),
+        )?;
 
-        // Check that the hidden file is not included
-        assert!(!cargo_toml_files.contains(&hidden_cargo_toml));
+        // Check that the code bank file was created
+        assert!(code_bank_file.exists());
+
+        // Check that the file contains some content
+        let content = fs::read_to_string(&code_bank_file)?;
+        assert!(!content.is_empty());
 
         Ok(())
     }
 
     #[test]
-    fn test_find_cargo_toml_files_nonexistent_dir() {
-        let result = find_cargo_toml_files(Path::new("/nonexistent/directory"));
-        assert!(result.is_err());
+    fn test_generate_code_bank_failure_keeps_previous_bank() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let source_dir = temp_dir.path().join("source");
+        let output_dir = temp_dir.path().join("output");
+        fs::create_dir_all(&source_dir)?;
+        fs::write(source_dir.join("lib.rs"), "/// Docs.\npub fn api() {}\n")?;
+        let bank =
+            generate_code_bank(&source_dir, &output_dir, "dep", &GenerateOptions::default())?;
+        let previous = fs::read_to_string(&bank)?;
+
+        // An unparsable manifest makes the regeneration fail
+        fs::write(source_dir.join("Cargo.toml"), "[package")?;
+        assert!(
+            generate_code_bank(&source_dir, &output_dir, "dep", &GenerateOptions::default())
+                .is_err()
+        );
+
+        assert_eq!(fs::read_to_string(&bank)?, previous);
+        assert_eq!(fs::read_dir(&output_dir)?.count(), 1);
+
+        Ok(())
     }
 
     #[test]
-    fn test_find_cargo_toml_files_file_as_dir() -> Result<()> {
-        // Create a temporary file
+    fn test_generate_code_bank_to_writer() -> Result<()> {
         let temp_dir = tempdir()?;
-        let temp_file = temp_dir.path().join("file.txt");
-        File::create(&temp_file)?.write_all(b"content")?;
-
-        // Try to use the file as a directory
-        let result = find_cargo_toml_files(&temp_file);
+        let source_dir = temp_dir.path().join("source");
+        fs::create_dir_all(&source_dir)?;
+        fs::write(
+            source_dir.join("lib.rs"),
+            "/// Adds two numbers.\npub fn add(a: u32, b: u32) -> u32 {\n    a + b\n}\n",
+        )?;
+
+        let mut buffer = Vec::new();
+        generate_code_bank_to_writer(
+            &source_dir,
+            "adder",
+            &GenerateOptions::default(),
+            &mut buffer,
+        )?;
+        let content = String::from_utf8(buffer)?;
+
+        assert!(content.starts_with("# Code Bank"));
+        assert!(content.contains("pub fn add(a: u32, b: u32) -> u32"));
+        // Nothing is written to the filesystem
+        assert_eq!(fs::read_dir(temp_dir.path())?.count(), 1);
+
+        // Invalid sources are reported without writing anything
+        let mut buffer = Vec::new();
+        let result = generate_code_bank_to_writer(
+            &temp_dir.path().join("missing"),
+            "missing",
+            &GenerateOptions::default(),
+            &mut buffer,
+        );
         assert!(result.is_err());
+        assert!(buffer.is_empty());
 
         Ok(())
     }
 
     #[test]
-    fn test_collect_dependencies() -> Result<()> {
-        // Create a temporary directory with a Cargo.toml file
+    fn test_generate_code_bank_with_alias() -> Result<()> {
         let temp_dir = tempdir()?;
-        let cargo_toml_path = temp_dir.path().join("Cargo.toml");
-
-        // Create a sample Cargo.toml file with dependencies
-        let cargo_toml_content = r#"
-[package]
-name = "test-project"
-version = "0.1.0"
-edition = "2021"
+        fs::write(
+            temp_dir.path().join("lib.rs"),
+            "/// Adds two numbers.\npub fn add(a: u32, b: u32) -> u32 {\n    a + b\n}\n",
+        )?;
+        let options = GenerateOptions {
+            aliases: BTreeMap::from([("num_add".to_string(), "Number adder".to_string())]),
+            ..Default::default()
+        };
 
-[dependencies]
-anyhow = "1.0"
-serde = { version = "1.0", features = ["derive"] }
-tokio = { version = "1.0", features = ["full"] }
+        let mut buffer = Vec::new();
+        generate_code_bank_to_writer(temp_dir.path(), "num_add", &options, &mut buffer)?;
+        let content = String::from_utf8(buffer)?;
+        assert!(content.starts_with("# Code Bank: Number adder (`num_add`)\n"));
+        assert!(content.contains("pub fn add(a: u32, b: u32) -> u32"));
 
-[dev-dependencies]
-tempfile = "3.0"
+        // Dependencies without an alias keep the usual title
+        let mut buffer = Vec::new();
+        generate_code_bank_to_writer(temp_dir.path(), "adder", &options, &mut buffer)?;
+        assert!(String::from_utf8(buffer)?.starts_with("# Code Bank\n"));
 
-[build-dependencies]
-cc = "1.0"
-"#;
+        Ok(())
+    }
 
-        File::create(&cargo_toml_path)?.write_all(cargo_toml_content.as_bytes())?;
+    #[test]
+    fn test_generate_code_bank_with_usages_and_yanked_warning() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let source_dir = temp_dir.path().join("num_add-0.1.0");
+        fs::create_dir_all(&source_dir)?;
+        fs::write(
+            source_dir.join("Cargo.toml"),
+            "[package]\nname = \"num_add\"\nversion = \"0.1.0\"\n",
+        )?;
+        fs::write(
+            source_dir.join("lib.rs"),
+            "/// Adds two numbers.\npub fn add(a: u32, b: u32) -> u32 {\n    a + b\n}\n",
+        )?;
+        let usage = UsageSnippet {
+            file: PathBuf::from("src/main.rs"),
+            line: 1,
+            snippet: "use num_add::add;".to_string(),
+            is_import: true,
+        };
+        let options = GenerateOptions {
+            yanked: BTreeSet::from(["num_add".to_string()]),
+            usages: BTreeMap::from([("num_add".to_string(), vec![usage])]),
+            dependency_depths: BTreeMap::from([("num_add".to_string(), 0)]),
+            heading_offset: 1,
+            ..Default::default()
+        };
 
-        // Collect dependencies from the Cargo.toml file
-        let dependencies = collect_dependencies(&[cargo_toml_path])?;
+        let mut buffer = Vec::new();
+        generate_code_bank_to_writer(&source_dir, "num_add", &options, &mut buffer)?;
+        let content = String::from_utf8(buffer)?;
 
-        // Check that all dependencies were collected
-        assert!(dependencies.contains("anyhow"));
-        assert!(dependencies.contains("serde"));
-        assert!(dependencies.contains("tokio"));
-        assert!(dependencies.contains("tempfile"));
-        assert!(dependencies.contains("cc"));
+        // The warning comes first below the title, before the other notes
+        assert!(content.starts_with(
+            "## Code Bank\n\n> **Warning:** num_add 0.1.0 has been yanked from crates.io.\n\n> **Depth:** 0"
+        ));
+        // The usage section ends the bank, with its heading shifted like the others
+        assert!(
+            content.ends_with("### Used in this project\n\nHow the project currently uses `num_add`:\n\n`src/main.rs:1`\n\n```rust\nuse num_add::add;\n```\n")
+        );
 
-        // Check that the total number of dependencies is correct
-        assert_eq!(dependencies.len(), 5);
+        // Other dependencies get neither
+        let mut buffer = Vec::new();
+        generate_code_bank_to_writer(&source_dir, "adder", &options, &mut buffer)?;
+        let content = String::from_utf8(buffer)?;
+        assert!(!content.contains("**Warning:**"));
+        assert!(!content.contains("Used in this project"));
 
         Ok(())
     }
 
     #[test]
-    fn test_extract_dependency_info() -> Result<()> {
-        // Create a temporary directory with a Cargo.toml file
+    fn test_generate_code_bank_respects_package_exclude() -> Result<()> {
+        // A path dependency whose manifest keeps a directory out of the package
         let temp_dir = tempdir()?;
-        let cargo_toml_path = temp_dir.path().join("Cargo.toml");
-
-        // Create a sample Cargo.toml file with dependencies
-        let cargo_toml_content = r#"
-[package]
-name = "test-project"
-version = "0.1.0"
-edition = "2021"
-
-[dependencies]
-anyhow = "1.0"
-serde = { version = "1.0", features = ["derive"] }
-tokio = { version = "1.0", features = ["full"] }
-
-[dev-dependencies]
-tempfile = "3.0"
-
-[build-dependencies]
-cc = "1.0"
-"#;
+        let crate_dir = temp_dir.path().join("vendor").join("mylib");
+        fs::create_dir_all(crate_dir.join("src").join("internal"))?;
+        fs::write(
+            crate_dir.join("Cargo.toml"),
+            "[package]\nname = \"mylib\"\nversion = \"0.1.0\"\nexclude = [\"src/internal/\"]\n",
+        )?;
+        fs::write(
+            crate_dir.join("src").join("lib.rs"),
+            "/// Published API.\npub fn published() {}\n",
+        )?;
+        fs::write(
+            crate_dir.join("src").join("internal").join("mod.rs"),
+            "/// Not published.\npub fn unpublished_helper() {}\n",
+        )?;
+
+        let mut buffer = Vec::new();
+        generate_code_bank_to_writer(
+            &crate_dir,
+            "mylib",
+            &GenerateOptions::default(),
+            &mut buffer,
+        )?;
+        let content = String::from_utf8(buffer)?;
+
+        assert!(content.contains("pub fn published()"));
+        assert!(content.contains("## src/lib.rs"));
+        assert!(!content.contains("unpublished_helper"));
+        assert!(!content.contains("## src/internal"));
 
-        File::create(&cargo_toml_path)?.write_all(cargo_toml_content.as_bytes())?;
+        Ok(())
+    }
 
-        // Extract dependency info from the Cargo.toml file
-        let dependency_info = extract_dependency_info(&cargo_toml_path)?;
+    #[test]
+    fn test_generate_code_bank_flags_undocumented_crate() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let crate_dir = temp_dir.path().join("bare");
+        fs::create_dir_all(crate_dir.join("src"))?;
+        fs::write(
+            crate_dir.join("Cargo.toml"),
+            "[package]\nname = \"bare\"\nversion = \"0.1.0\"\n",
+        )?;
+        fs::write(
+            crate_dir.join("src").join("lib.rs"),
+            "pub struct Config {\n    pub name: String,\n}\n\npub fn load(path: &str) -> Config {\n    Config { name: path.to_string() }\n}\n",
+        )?;
+
+        let mut buffer = Vec::new();
+        generate_code_bank_to_writer(&crate_dir, "bare", &GenerateOptions::default(), &mut buffer)?;
+        let content = String::from_utf8(buffer)?;
+        assert!(content.contains("pub fn load"));
+        assert!(is_effectively_empty(&content, DEFAULT_MIN_DOC_TOKENS));
+
+        // Documenting the crate clears the flag
+        fs::write(
+            crate_dir.join("src").join("lib.rs"),
+            "//! Loading of configuration files, with their defaults filled in.\n\n/// A loaded configuration, ready to be used by the application.\npub struct Config {\n    /// The name of the configuration\n    pub name: String,\n}\n\n/// Loads the configuration file at a path, filling in the defaults.\npub fn load(path: &str) -> Config {\n    Config { name: path.to_string() }\n}\n",
+        )?;
+        let mut buffer = Vec::new();
+        generate_code_bank_to_writer(&crate_dir, "bare", &GenerateOptions::default(), &mut buffer)?;
+        let content = String::from_utf8(buffer)?;
+        assert!(!is_effectively_empty(&content, DEFAULT_MIN_DOC_TOKENS));
 
-        // Check that all dependencies have correct versions
-        let dep1 = dependency_info.get("anyhow").unwrap();
-        assert_eq!(dep1.version, "1.0");
-        let dep2 = dependency_info.get("serde").unwrap();
-        assert_eq!(dep2.version, "1.0");
-        let dep3 = dependency_info.get("tokio").unwrap();
-        assert_eq!(dep3.version, "1.0");
-        let dep4 = dependency_info.get("tempfile").unwrap();
-        assert_eq!(dep4.version, "3.0");
-        let dep5 = dependency_info.get("cc").unwrap();
-        assert_eq!(dep5.version, "1.0");
+        Ok(())
+    }
 
-        // Check that the total number of dependencies is correct
-        assert_eq!(dependency_info.len(), 5);
+    #[test]
+    fn test_generate_self_code_bank() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let output_dir = temp_dir.path().join("banks");
+
+        let (package, bank) = generate_self_code_bank(
+            Path::new("fixtures/simple_project"),
+            &output_dir,
+            &GenerateOptions::default(),
+        )?;
+        assert_eq!(package.name, "simple_project");
+        assert_eq!(package.version, "0.1.0");
+        assert!(package.source.unwrap().starts_with(PATH_SOURCE_PREFIX));
+        assert_eq!(bank, output_dir.join("simple_project.md"));
+        let content = fs::read_to_string(&bank)?;
+        assert!(content.contains("## src/main.rs"));
+        assert!(content.contains("struct Person"));
+
+        // A virtual workspace has no package of its own
+        assert!(
+            generate_self_code_bank(
+                Path::new("fixtures/workspace_project"),
+                &output_dir,
+                &GenerateOptions::default(),
+            )
+            .is_err()
+        );
 
         Ok(())
     }
 
     #[test]
-    fn test_resolve_dependency_versions() -> Result<()> {
+    fn test_stage_filtered_source() -> Result<()> {
+        // A vendored crate shipping a large data file next to its sources
         let temp_dir = tempdir()?;
+        let crate_dir = temp_dir.path().join("vendor").join("mylib");
+        fs::create_dir_all(crate_dir.join("src"))?;
+        fs::create_dir_all(crate_dir.join("data"))?;
+        fs::write(
+            crate_dir.join("Cargo.toml"),
+            "[package]\nname = \"mylib\"\nversion = \"0.1.0\"\n",
+        )?;
+        fs::write(crate_dir.join("src").join("lib.rs"), "pub fn api() {}\n")?;
+        fs::write(crate_dir.join("data").join("config.toml"), "key = 1\n")?;
+        let json = format!("[{}0]", "0,".repeat(512 * 1024));
+        fs::write(crate_dir.join("data").join("fixture.json"), json)?;
+
+        let staged = stage_filtered_source(&crate_dir, &["rs".to_string()])?;
+        assert!(staged.path().join("Cargo.toml").is_file());
+        assert!(staged.path().join("src").join("lib.rs").is_file());
+        assert!(!staged.path().join("data").join("fixture.json").exists());
+        assert!(!staged.path().join("data").join("config.toml").exists());
+
+        let staged = stage_filtered_source(&crate_dir, &["rs".to_string(), "toml".to_string()])?;
+        assert!(staged.path().join("data").join("config.toml").is_file());
+        assert!(!staged.path().join("data").join("fixture.json").exists());
+
+        let options = GenerateOptions {
+            source_extensions: vec!["rs".to_string()],
+            ..GenerateOptions::default()
+        };
+        let mut buffer = Vec::new();
+        generate_code_bank_to_writer(&crate_dir, "mylib", &options, &mut buffer)?;
+        let content = String::from_utf8(buffer)?;
+        assert!(content.contains("pub fn api()"));
+        assert!(!content.contains("fixture.json"));
 
-        // Create a sample Cargo.lock file
-        let cargo_lock_path = temp_dir.path().join("Cargo.lock");
-        let cargo_lock_content = r#"
-# This file is automatically @generated by Cargo.
-# It is not intended for manual editing.
-version = 4
-
-[[package]]
-name = "anyhow"
-version = "1.0.68"
-source = "registry+https://github.com/rust-lang/crates.io-index"
-checksum = "e6b88822cbe49de4185e3a4cbf8321dd487cf5fe0c5c65695fef6346371e9c48"
+        Ok(())
+    }
 
-[[package]]
-name = "anyhow"
-version = "1.0.75"
-source = "registry+https://github.com/rust-lang/crates.io-index"
-checksum = "a4668cab20f66d8d020e1fbc0ebe47217433c1b6c8f2040faf858554e394ace6"
+    #[test]
+    fn test_generate_code_bank_strip_hidden() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let source_dir = temp_dir.path().join("source");
+        let output_dir = temp_dir.path().join("output");
+        fs::create_dir_all(&source_dir)?;
 
-[[package]]
-name = "serde"
-version = "1.0.150"
-source = "registry+https://github.com/rust-lang/crates.io-index"
-checksum = "e6b88822cbe49de4185e3a4cbf8321dd487cf5fe0c5c65695fef6346371e9c48"
+        let source_content = r#"
+/// This is part of the public API.
+pub fn public_function() -> String {
+    "Hello, world!".to_string()
+}
 
-[[package]]
-name = "toml"
-version = "0.8.4"
-source = "registry+https://github.com/rust-lang/crates.io-index"
-checksum = "e6b88822cbe49de4185e3a4cbf8321dd487cf5fe0c5c65695fef6346371e9c48"
+/// Only exported for macro use.
+#[doc(hidden)]
+pub fn hidden_function() -> String {
+    "secret".to_string()
+}
 "#;
-        File::create(&cargo_lock_path)?.write_all(cargo_lock_content.as_bytes())?;
+        fs::write(source_dir.join("lib.rs"), source_content)?;
+
+        // Without the option the hidden function is documented
+        let default_file = generate_code_bank(
+            &source_dir,
+            &output_dir,
+            "default_dep",
+            &GenerateOptions::default(),
+        )?;
+        let content = fs::read_to_string(&default_file)?;
+        assert!(content.contains("hidden_function"));
+
+        // With the option it is stripped while public items remain
+        let options = GenerateOptions {
+            strip_hidden: true,
+            ..Default::default()
+        };
+        let stripped_file = generate_code_bank(&source_dir, &output_dir, "stripped_dep", &options)?;
+        let content = fs::read_to_string(&stripped_file)?;
+        assert!(!content.contains("hidden_function"));
+        assert!(!content.contains("Only exported for macro use"));
+        assert!(content.contains("public_function"));
 
-        // Create a sample dependencies DependencyCollection
-        let mut dependencies = DependencyCollection::new();
-        dependencies.add(Dependency::new("anyhow", "1.0.75"));
-        dependencies.add(Dependency::new("serde", "1.0.150"));
-        dependencies.add(Dependency::new("toml", "0.8.4"));
-        dependencies.add(Dependency::new("nonexistent", "1.0"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_generate_code_bank_strip_std_reexports() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let source_dir = temp_dir.path().join("source");
+        let output_dir = temp_dir.path().join("output");
+        fs::create_dir_all(&source_dir)?;
 
-        // Resolve versions
-        let resolved = resolve_dependency_versions(cargo_lock_path, &dependencies)?;
+        let source_content = r#"
+pub use std::collections::HashMap;
 
-        // Check the resolved versions
-        let dep1 = resolved.get("anyhow").unwrap();
-        assert_eq!(dep1.version, "1.0.75");
-        let dep2 = resolved.get("serde").unwrap();
-        assert_eq!(dep2.version, "1.0.150");
-        let dep3 = resolved.get("toml").unwrap();
-        assert_eq!(dep3.version, "0.8.4");
-        assert!(!resolved.contains_name("nonexistent"));
+/// Builds an index of the given words.
+pub fn index(words: &[&str]) -> HashMap<String, usize> {
+    HashMap::new()
+}
+"#;
+        fs::write(source_dir.join("lib.rs"), source_content)?;
+
+        let options = GenerateOptions {
+            strip_std_reexports: true,
+            ..Default::default()
+        };
+        let code_bank_file = generate_code_bank(&source_dir, &output_dir, "reexports", &options)?;
+        let content = fs::read_to_string(&code_bank_file)?;
+
+        assert!(!content.contains("pub use std::collections::HashMap"));
+        assert!(content.contains("pub fn index"));
 
         Ok(())
     }
 
     #[test]
-    fn test_find_cargo_lock() -> Result<()> {
+    fn test_generate_code_bank_records_features() -> Result<()> {
         let temp_dir = tempdir()?;
-        let cargo_lock_path = temp_dir.path().join("Cargo.lock");
+        let source_dir = temp_dir.path().join("source");
+        fs::create_dir_all(&source_dir)?;
+        fs::write(
+            source_dir.join("lib.rs"),
+            "#[cfg(feature = \"rt\")]\npub fn spawn() {}\n",
+        )?;
+
+        let options = GenerateOptions {
+            features: FeatureSelection {
+                features: vec!["rt".to_string(), "macros".to_string()],
+                no_default_features: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut buffer = Vec::new();
+        generate_code_bank_to_writer(&source_dir, "demo", &options, &mut buffer)?;
+        let content = String::from_utf8(buffer)?;
 
-        // Create an empty Cargo.lock file
-        File::create(&cargo_lock_path)?;
+        assert!(content.starts_with(
+            "# Code Bank\n\n> **Features:** `--no-default-features --features rt,macros` requested."
+        ));
+        assert!(content.contains("Generated assuming all features"));
+        assert!(content.contains("pub fn spawn()"));
+
+        // Without feature flags the header is unchanged
+        let mut buffer = Vec::new();
+        generate_code_bank_to_writer(
+            &source_dir,
+            "demo",
+            &GenerateOptions::default(),
+            &mut buffer,
+        )?;
+        assert!(!String::from_utf8(buffer)?.contains("**Features:**"));
 
-        // Test finding it in the current directory
-        let found_path = find_cargo_lock(temp_dir.path())?;
-        assert_eq!(found_path, cargo_lock_path);
+        Ok(())
+    }
 
-        // Test finding it from a subdirectory
-        let sub_dir = temp_dir.path().join("subdir");
-        fs::create_dir(&sub_dir)?;
-        let found_from_subdir = find_cargo_lock(&sub_dir)?;
-        assert_eq!(found_from_subdir, cargo_lock_path);
+    #[test]
+    fn test_generate_code_bank_includes_build_script() -> Result<()> {
+        // A crate laid out as in the registry, with a build script
+        let temp_dir = tempdir()?;
+        let source_dir = temp_dir.path().join("demo-sys-0.1.0");
+        fs::create_dir_all(source_dir.join("src"))?;
+        fs::write(
+            source_dir.join("Cargo.toml"),
+            "[package]\nname = \"demo-sys\"\nversion = \"0.1.0\"\n",
+        )?;
+        fs::write(
+            source_dir.join("src").join("lib.rs"),
+            "/// Links the demo library.\npub fn link() {}\n",
+        )?;
+        fs::write(
+            source_dir.join("build.rs"),
+            "fn main() {\n    println!(\"cargo:rerun-if-env-changed=DEMO_DIR\");\n}\n",
+        )?;
+
+        let options = GenerateOptions {
+            include_build_script: true,
+            strategy: Strategy::Summary,
+            ..Default::default()
+        };
+        let mut buffer = Vec::new();
+        generate_code_bank_to_writer(&source_dir, "demo-sys", &options, &mut buffer)?;
+        let content = String::from_utf8(buffer)?;
+
+        let (_, section) = content
+            .split_once("## Build script: `build.rs`")
+            .expect("missing build script section");
+        assert!(section.contains(
+            "```rust\nfn main() {\n    println!(\"cargo:rerun-if-env-changed=DEMO_DIR\");\n}\n```\n"
+        ));
+        assert!(content.contains("pub fn link()"));
+
+        // Without the option, the bank has no such section
+        let mut buffer = Vec::new();
+        generate_code_bank_to_writer(
+            &source_dir,
+            "demo-sys",
+            &GenerateOptions::default(),
+            &mut buffer,
+        )?;
+        assert!(!String::from_utf8(buffer)?.contains("## Build script"));
 
         Ok(())
     }
 
     #[test]
-    fn test_resolve_dependency_versions_nonexistent_file() {
-        let result = resolve_dependency_versions(
-            Path::new("/nonexistent/Cargo.lock"),
-            &DependencyCollection::new(),
+    fn test_generate_code_bank_crlf_line_endings() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let source_dir = temp_dir.path().join("source");
+        let output_dir = temp_dir.path().join("output");
+        fs::create_dir_all(&source_dir)?;
+        fs::write(
+            source_dir.join("lib.rs"),
+            "/// Greets.\npub fn greet() -> String {\n    String::new()\n}\n",
+        )?;
+
+        // LF is the default
+        let lf_file = generate_code_bank(
+            &source_dir,
+            &output_dir,
+            "lf_dep",
+            &GenerateOptions::default(),
+        )?;
+        assert!(!fs::read_to_string(&lf_file)?.contains('\r'));
+
+        let options = GenerateOptions {
+            line_endings: LineEndings::Crlf,
+            ..Default::default()
+        };
+        let crlf_file = generate_code_bank(&source_dir, &output_dir, "crlf_dep", &options)?;
+        let content = fs::read_to_string(&crlf_file)?;
+
+        assert!(content.contains("pub fn greet() -> String"));
+        assert!(content.contains("\r\n"));
+        assert_eq!(
+            content.matches('\n').count(),
+            content.matches("\r\n").count()
         );
-        assert!(result.is_err());
+
+        Ok(())
     }
 
     #[test]
-    fn test_construct_dependency_path() {
-        let registry_path = Path::new("/home/user/.cargo/registry/src/index.crates.io-12345");
-        let dependency_name = "serde";
-        let dependency_version = "1.0.150";
+    fn test_generate_code_bank_post_process_hook() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let source_dir = temp_dir.path().join("source");
+        let output_dir = temp_dir.path().join("output");
+        fs::create_dir_all(&source_dir)?;
+        fs::write(
+            source_dir.join("Cargo.toml"),
+            "[package]\nname = \"shout\"\nversion = \"0.2.0\"\n",
+        )?;
+        fs::write(
+            source_dir.join("lib.rs"),
+            "/// Greets.\npub fn greet() -> String {\n    String::new()\n}\n",
+        )?;
+
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let hook_seen = Arc::clone(&seen);
+        let options = GenerateOptions {
+            line_endings: LineEndings::Crlf,
+            post_process: Some(PostProcessHook::new(move |dependency, content| {
+                hook_seen
+                    .lock()
+                    .unwrap()
+                    .push(format!("{} {}", dependency.name, dependency.version));
+                // Line endings are normalized after the hook
+                assert!(!content.contains('\r'));
+                Ok(content.to_uppercase())
+            })),
+            ..Default::default()
+        };
+        let file = generate_code_bank(&source_dir, &output_dir, "shout", &options)?;
+        let content = fs::read_to_string(&file)?;
+
+        assert!(content.contains("PUB FN GREET() -> STRING"));
+        assert!(!content.contains("pub fn greet"));
+        assert!(content.contains("\r\n"));
+        assert_eq!(*seen.lock().unwrap(), vec!["shout 0.2.0"]);
+
+        // A failing hook fails the bank without leaving a file behind
+        let options = GenerateOptions {
+            post_process: Some(PostProcessHook::new(|_, _| {
+                Err(anyhow::anyhow!("redaction failed"))
+            })),
+            ..Default::default()
+        };
+        let result = generate_code_bank(&source_dir, &output_dir, "broken", &options);
+        assert!(format!("{:#}", result.unwrap_err()).contains("redaction failed"));
+        assert!(!output_dir.join("broken.md").exists());
 
-        let expected_path =
-            PathBuf::from("/home/user/.cargo/registry/src/index.crates.io-12345/serde-1.0.150");
-        let actual_path =
-            construct_dependency_path(registry_path, dependency_name, dependency_version);
+        Ok(())
+    }
 
-        assert_eq!(actual_path, expected_path);
+    #[test]
+    fn test_generate_code_bank_binary_only_crate() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let crate_dir = temp_dir.path().join("registry").join("sometool-0.3.1");
+        let output_dir = temp_dir.path().join("output");
+        fs::create_dir_all(crate_dir.join("src").join("bin"))?;
+        fs::create_dir_all(crate_dir.join("tests"))?;
+
+        fs::write(
+            crate_dir.join("Cargo.toml"),
+            "[package]\nname = \"sometool\"\nversion = \"0.3.1\"\n",
+        )?;
+        fs::write(
+            crate_dir.join("src").join("main.rs"),
+            "mod config;\n\n/// Runs the tool.\nfn main() {\n    config::load();\n}\n",
+        )?;
+        fs::write(
+            crate_dir.join("src").join("config.rs"),
+            "/// Loads the tool configuration.\nfn load_config() {}\n",
+        )?;
+        fs::write(
+            crate_dir.join("src").join("bin").join("sometool-helper.rs"),
+            "/// Entry point of the helper binary.\nfn main() {}\n",
+        )?;
+        fs::write(
+            crate_dir.join("tests").join("cli.rs"),
+            "#[test]\nfn runs_tool() {}\n",
+        )?;
+
+        let code_bank_file = generate_code_bank(
+            &crate_dir,
+            &output_dir,
+            "sometool",
+            &GenerateOptions::default(),
+        )?;
+        let content = fs::read_to_string(&code_bank_file)?;
+
+        // The binary sources are documented even though they have no public items
+        assert!(content.contains("name = \"sometool\""));
+        assert!(content.contains("src/main.rs"));
+        assert!(content.contains("fn main()"));
+        assert!(content.contains("src/config.rs"));
+        assert!(content.contains("fn load_config()"));
+        assert!(content.contains("src/bin/sometool-helper.rs"));
+        assert!(!content.contains("runs_tool"));
+
+        Ok(())
     }
 
     #[test]
-    fn test_resolve_registry_path_with_mock() -> Result<()> {
+    fn test_generate_all_code_banks_in_parallel() -> Result<()> {
         let temp_dir = tempdir()?;
-        let mock_home = temp_dir.path();
+        let registry_dir = temp_dir.path().join("registry");
+        let output_dir = temp_dir.path().join("output");
 
-        // Create mock registry structure
-        let registry_src = mock_home.join(".cargo").join("registry").join("src");
-        fs::create_dir_all(&registry_src)?;
+        // Create a registry-style layout with several small crates
+        let mut dependencies = DependencyCollection::new();
+        for name in ["alpha", "beta", "gamma", "delta"] {
+            let crate_dir = registry_dir.join(format!("{}-1.0.0", name));
+            fs::create_dir_all(&crate_dir)?;
+            fs::write(
+                crate_dir.join("lib.rs"),
+                format!("/// Entry point of {name}.\npub fn {name}() {{}}\n"),
+            )?;
+            dependencies.add(Dependency::new(name, "1.0.0"));
+        }
 
-        // Create two registry directories with different modification times
-        let old_registry = registry_src.join("index.crates.io-old");
-        let new_registry = registry_src.join("index.crates.io-new");
+        let files = generate_all_code_banks(
+            &dependencies,
+            &registry_dir,
+            &output_dir,
+            &GenerateOptions::default(),
+        )?;
+        assert_eq!(files.len(), 4);
+        for name in ["alpha", "beta", "gamma", "delta"] {
+            let content = fs::read_to_string(&files[name])?;
+            assert!(content.contains(&format!("pub fn {name}()")));
+        }
 
-        fs::create_dir(&old_registry)?;
+        Ok(())
+    }
 
-        // Sleep to ensure different modification times
-        std::thread::sleep(std::time::Duration::from_millis(10));
+    #[test]
+    fn test_generate_all_code_banks_resume() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let registry_dir = temp_dir.path().join("registry");
+        let output_dir = temp_dir.path().join("output");
 
-        fs::create_dir(&new_registry)?;
+        let mut dependencies = DependencyCollection::new();
+        for name in ["alpha", "beta", "gamma"] {
+            let crate_dir = registry_dir.join(format!("{}-1.0.0", name));
+            fs::create_dir_all(&crate_dir)?;
+            fs::write(crate_dir.join("lib.rs"), format!("pub fn {name}() {{}}\n"))?;
+            dependencies.add(Dependency::new(name, "1.0.0"));
+        }
 
-        // Override the home directory for testing
-        // Note: In a real test, we would use environment variables or mocking,
-        // but for simplicity in this example we'll just verify the behavior manually
+        // Simulate a run interrupted after generating alpha, while writing beta's entry
+        fs::create_dir_all(&output_dir)?;
+        let alpha_bank = output_dir.join("alpha.md");
+        fs::write(&alpha_bank, "previously generated")?;
+        let options = GenerateOptions {
+            resume: true,
+            ..Default::default()
+        };
+        let entry = ProgressEntry {
+            name: "alpha".to_string(),
+            version: "1.0.0".to_string(),
+            path: alpha_bank.clone(),
+            options: options_fingerprint(&options),
+        };
+        fs::write(
+            output_dir.join(PROGRESS_LOG_FILE),
+            format!("{}\n{{\"name\":\"be", serde_json::to_string(&entry)?),
+        )?;
+        let files = generate_all_code_banks(&dependencies, &registry_dir, &output_dir, &options)?;
+
+        // The completed dependency is skipped but still reported
+        assert_eq!(files.len(), 3);
+        assert_eq!(fs::read_to_string(&alpha_bank)?, "previously generated");
+        assert!(fs::read_to_string(&files["beta"])?.contains("pub fn beta()"));
+        assert!(fs::read_to_string(&files["gamma"])?.contains("pub fn gamma()"));
+
+        let mut logged: Vec<String> = read_progress_log(&output_dir)?
+            .into_iter()
+            .map(|entry| entry.name)
+            .collect();
+        logged.sort();
+        assert_eq!(logged, ["alpha", "beta", "gamma"]);
+
+        // Banks generated with other options are not reused
+        let options = GenerateOptions {
+            strip_hidden: true,
+            ..options
+        };
+        generate_all_code_banks(&dependencies, &registry_dir, &output_dir, &options)?;
+        assert!(fs::read_to_string(&alpha_bank)?.contains("pub fn alpha()"));
+
+        // A fresh run starts a new log and regenerates everything
+        generate_all_code_banks(
+            &dependencies,
+            &registry_dir,
+            &output_dir,
+            &GenerateOptions::default(),
+        )?;
+        assert!(fs::read_to_string(&alpha_bank)?.contains("pub fn alpha()"));
+        assert_eq!(read_progress_log(&output_dir)?.len(), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_skip_oversized_dependencies() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let registry_dir = temp_dir.path().join("registry");
+
+        let small_dir = registry_dir.join("small-1.0.0");
+        fs::create_dir_all(small_dir.join("src"))?;
+        fs::write(small_dir.join("src/lib.rs"), "pub fn small() {}\n")?;
+        let large_dir = registry_dir.join("large-2.0.0");
+        fs::create_dir_all(large_dir.join("src/nested"))?;
+        fs::write(large_dir.join("src/lib.rs"), "pub mod nested;\n")?;
+        fs::write(large_dir.join("src/nested/mod.rs"), "x".repeat(4096))?;
 
-        // The most recently modified directory should be index.crates.io-new
-        assert!(new_registry.exists());
-        assert!(old_registry.exists());
+        assert_eq!(source_total_size(&small_dir), 18);
+        assert_eq!(source_total_size(&large_dir), 16 + 4096);
+        assert_eq!(source_total_size(&registry_dir.join("missing-1.0.0")), 0);
+
+        let mut dependencies = DependencyCollection::new();
+        dependencies.add(Dependency::new("small", "1.0.0"));
+        dependencies.add(Dependency::new("large", "2.0.0"));
+        let (kept, skipped) = skip_oversized_dependencies(&dependencies, &registry_dir, 1024);
 
-        // We can't easily test the actual function without mocking dirs::home_dir(),
-        // so this is more of a manual verification
+        assert_eq!(kept.len(), 1);
+        assert!(kept.iter().any(|dep| dep.name == "small"));
+        assert_eq!(skipped, [("large".to_string(), 4112)]);
 
         Ok(())
     }
 
     #[test]
-    fn test_generate_code_bank() -> Result<()> {
+    fn test_generate_code_banks_records_durations() -> Result<()> {
         let temp_dir = tempdir()?;
-        let source_dir = temp_dir.path().join("source");
+        let registry_dir = temp_dir.path().join("registry");
         let output_dir = temp_dir.path().join("output");
 
-        // Create source directory structure
-        fs::create_dir_all(&source_dir)?;
+        let mut dependencies = DependencyCollection::new();
+        for name in ["alpha", "beta"] {
+            let crate_dir = registry_dir.join(format!("{}-1.0.0", name));
+            fs::create_dir_all(&crate_dir)?;
+            fs::write(crate_dir.join("lib.rs"), format!("pub fn {name}() {{}}\n"))?;
+            dependencies.add(Dependency::new(name, "1.0.0"));
+        }
+        dependencies.add(Dependency::new("missing", "1.0.0"));
+
+        let outcome = generate_code_banks_with_outcome(
+            &dependencies,
+            &registry_dir,
+            &output_dir,
+            &GenerateOptions::default(),
+        )?;
+        assert_eq!(outcome.durations.len(), 2);
+        assert!(
+            outcome
+                .durations
+                .values()
+                .all(|duration| !duration.is_zero())
+        );
 
-        // Create a sample source file
-        let source_file = source_dir.join("lib.rs");
-        let source_content = r#"
-/// This is a test function.
-pub fn test_function() -> String {
-    "Hello, world!".to_string()
-}
-"#;
-        fs::write(&source_file, source_content)?;
+        let mut report = build_generation_report(&dependencies, &outcome.code_bank_files)?;
+        report.record_durations(&outcome.durations, Duration::from_millis(5));
+        assert!(
+            report
+                .banks
+                .iter()
+                .all(|bank| bank.generation_secs.is_some_and(|secs| secs > 0.0))
+        );
+        assert_eq!(report.total_generation_secs, Some(0.005));
+        assert_eq!(report.slowest_banks(1).len(), 1);
 
-        // Generate code bank
-        let code_bank_file = generate_code_bank(&source_dir, &output_dir, "test_dependency")?;
+        let index_path = write_index_json(&report, &output_dir)?;
+        let index: serde_json::Value = serde_json::from_str(&fs::read_to_string(index_path)?)?;
+        assert!(index["banks"][0]["generation_secs"].as_f64().unwrap() > 0.0);
+        assert_eq!(index["total_generation_secs"], 0.005);
 
-        // Check that the code bank file was created
-        assert!(code_bank_file.exists());
+        Ok(())
+    }
 
-        // Check that the file contains some content
-        let content = fs::read_to_string(&code_bank_file)?;
-        assert!(!content.is_empty());
+    #[test]
+    fn test_generate_code_banks_deadline() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let registry_dir = temp_dir.path().join("registry");
+        let output_dir = temp_dir.path().join("output");
+
+        let mut dependencies = DependencyCollection::new();
+        for name in ["alpha", "beta", "gamma"] {
+            let crate_dir = registry_dir.join(format!("{}-1.0.0", name));
+            fs::create_dir_all(&crate_dir)?;
+            fs::write(crate_dir.join("lib.rs"), format!("pub fn {name}() {{}}\n"))?;
+            dependencies.add(Dependency::new(name, "1.0.0"));
+        }
+
+        // A first run only completes alpha
+        let mut completed = DependencyCollection::new();
+        completed.add(Dependency::new("alpha", "1.0.0"));
+        generate_all_code_banks(
+            &completed,
+            &registry_dir,
+            &output_dir,
+            &GenerateOptions::default(),
+        )?;
+
+        // With a deadline already passed, no new bank is started
+        let options = GenerateOptions {
+            resume: true,
+            deadline: Some(Instant::now()),
+            ..Default::default()
+        };
+        let outcome =
+            generate_code_banks_with_outcome(&dependencies, &registry_dir, &output_dir, &options)?;
+        assert_eq!(outcome.skipped, ["beta", "gamma"]);
+        assert!(!output_dir.join("beta.md").exists());
+
+        // The report covers what completed
+        let report = build_generation_report(&dependencies, &outcome.code_bank_files)?;
+        let names: Vec<&str> = report.banks.iter().map(|bank| bank.name.as_str()).collect();
+        assert_eq!(names, ["alpha"]);
 
         Ok(())
     }
@@ -1415,7 +5634,7 @@ pub fn test_function() -> String {
     #[test]
     fn test_calculate_tokens() -> Result<()> {
         let text = "Hello, world! This is a test.";
-        let token_count = calculate_tokens(text)?;
+        let token_count = calculate_tokens(text, &TokenOptions::default())?;
 
         // The exact token count may depend on the tokenizer,
         // but it should be a reasonable number greater than 0
@@ -1425,6 +5644,53 @@ pub fn test_function() -> String {
         Ok(())
     }
 
+    #[test]
+    fn test_calculate_tokens_beyond_model_max_length() -> Result<()> {
+        // Far longer than the 512 tokens bert-base-cased models accept
+        let sentence = "The quick brown fox jumps over the lazy dog. ";
+        let sentence_tokens = calculate_tokens(sentence, &TokenOptions::default())?;
+        let text = sentence.repeat(200);
+
+        // Every token is counted
+        assert_eq!(
+            calculate_tokens(&text, &TokenOptions::default())?,
+            sentence_tokens * 200
+        );
+        assert!(calculate_tokens(&text, &TokenOptions::default())? > 512);
+
+        // Honoring a context length caps the count at it
+        let options = TokenOptions {
+            max_length: Some(512),
+            ..Default::default()
+        };
+        assert_eq!(calculate_tokens(&text, &options)?, 512);
+        assert_eq!(calculate_tokens(sentence, &options)?, sentence_tokens);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_calculate_tokens_with_special_tokens() -> Result<()> {
+        let text = "Hello, world! This is a test.";
+        let without_special = calculate_tokens(text, &TokenOptions::default())?;
+        let with_special = calculate_tokens(
+            text,
+            &TokenOptions {
+                include_special_tokens: true,
+                ..Default::default()
+            },
+        )?;
+
+        // The default count is unchanged and special tokens add to it
+        assert_eq!(
+            without_special,
+            calculate_tokens(text, &TokenOptions::default())?
+        );
+        assert!(with_special > without_special);
+
+        Ok(())
+    }
+
     #[test]
     fn test_calculate_file_tokens() -> Result<()> {
         let temp_dir = tempdir()?;
@@ -1436,7 +5702,7 @@ pub fn test_function() -> String {
         File::create(&file_path)?.write_all(content.as_bytes())?;
 
         // Calculate tokens
-        let token_count = calculate_file_tokens(&file_path)?;
+        let token_count = calculate_file_tokens(&file_path, &TokenOptions::default())?;
 
         // Similar to the previous test, verify reasonable bounds
         assert!(token_count > 0);
@@ -1459,7 +5725,8 @@ pub fn test_function() -> String {
         File::create(&file3_path)?.write_all(b"This is file 3.")?;
 
         // Calculate tokens for .md files only
-        let stats = calculate_directory_tokens(temp_dir.path(), Some("md"))?;
+        let stats =
+            calculate_directory_tokens(temp_dir.path(), Some("md"), &TokenOptions::default())?;
 
         // Should only include the two .md files
         assert_eq!(stats.len(), 2);
@@ -1474,7 +5741,8 @@ pub fn test_function() -> String {
         }
 
         // Calculate tokens for all files
-        let all_stats = calculate_directory_tokens(temp_dir.path(), None)?;
+        let all_stats =
+            calculate_directory_tokens(temp_dir.path(), None, &TokenOptions::default())?;
 
         // Should include all three files
         assert_eq!(all_stats.len(), 3);
@@ -1482,6 +5750,169 @@ pub fn test_function() -> String {
         Ok(())
     }
 
+    #[test]
+    fn test_calculate_directory_tokens_relative_paths() -> Result<()> {
+        let temp_dir = tempdir()?;
+        fs::create_dir_all(temp_dir.path().join("nested"))?;
+        fs::write(temp_dir.path().join("file1.md"), "This is file 1.")?;
+        fs::write(temp_dir.path().join("nested/file2.md"), "This is file 2.")?;
+
+        let options = TokenOptions {
+            max_depth: 1,
+            ..Default::default()
+        };
+        let mut stats = calculate_directory_tokens(temp_dir.path(), Some("md"), &options)?;
+        assert!(stats.values().all(|stat| stat.path.is_absolute()));
+
+        // Paths can be made relative to the directory
+        for stat in stats.values_mut() {
+            stat.relativize_path(temp_dir.path());
+        }
+        assert_eq!(stats["file1"].path, PathBuf::from("file1.md"));
+        assert_eq!(
+            stats["nested/file2"].path,
+            Path::new("nested").join("file2.md")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_calculate_directory_tokens_with_depth() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let deep = temp_dir.path().join("one").join("two");
+        fs::create_dir_all(&deep)?;
+        fs::create_dir_all(temp_dir.path().join(".hidden"))?;
+        fs::write(temp_dir.path().join("top.md"), "At the top.")?;
+        fs::write(
+            temp_dir.path().join("one").join("middle.md"),
+            "One level down.",
+        )?;
+        fs::write(deep.join("bottom.md"), "Two levels down.")?;
+        fs::write(temp_dir.path().join(".hidden").join("secret.md"), "Hidden.")?;
+        // A symlink back to the top must not be followed forever
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(temp_dir.path(), deep.join("loop"))?;
+
+        let names = |max_depth| -> Result<Vec<String>> {
+            let options = TokenOptions {
+                max_depth,
+                ..Default::default()
+            };
+            let stats = calculate_directory_tokens(temp_dir.path(), Some("md"), &options)?;
+            let mut names: Vec<String> = stats.into_keys().collect();
+            names.sort();
+            Ok(names)
+        };
+
+        // Files deeper than the limit are left out
+        assert_eq!(names(0)?, ["top"]);
+        assert_eq!(names(1)?, ["one/middle", "top"]);
+        assert_eq!(names(5)?, ["one/middle", "one/two/bottom", "top"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_calculate_directory_tokens_with_cache() -> Result<()> {
+        let temp_dir = tempdir()?;
+        fs::write(
+            temp_dir.path().join("stable.md"),
+            "This file never changes.",
+        )?;
+        fs::write(temp_dir.path().join("edited.md"), "This file is edited.")?;
+        let options = TokenOptions {
+            use_cache: true,
+            ..Default::default()
+        };
+
+        // Only output directories get a cache
+        calculate_directory_tokens(temp_dir.path(), Some("md"), &options)?;
+        let cache_path = temp_dir.path().join(TOKEN_CACHE_FILE);
+        assert!(!cache_path.exists());
+
+        fs::write(temp_dir.path().join(INDEX_JSON_FILE), "{}")?;
+        let first = calculate_directory_tokens(temp_dir.path(), Some("md"), &options)?;
+        assert!(cache_path.is_file());
+        // The cache is not counted as one of the directory's files
+        assert_eq!(first.len(), 2);
+
+        // Forge the cached count of the unchanged file: only a cached count gives it
+        let mut cache: serde_json::Value = serde_json::from_str(&fs::read_to_string(&cache_path)?)?;
+        cache["files"]["stable.md"]["token_count"] = 1000.into();
+        fs::write(&cache_path, cache.to_string())?;
+        fs::write(
+            temp_dir.path().join("edited.md"),
+            "This file is edited, then edited again with more words.",
+        )?;
+
+        let second = calculate_directory_tokens(temp_dir.path(), Some("md"), &options)?;
+        assert_eq!(second["stable"].token_count, 1000);
+        // The changed file is tokenized again
+        assert!(second["edited"].token_count > first["edited"].token_count);
+
+        // Bypassing the cache tokenizes every file
+        let uncached =
+            calculate_directory_tokens(temp_dir.path(), Some("md"), &TokenOptions::default())?;
+        assert_eq!(uncached["stable"].token_count, first["stable"].token_count);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_tokens_by_extension() -> Result<()> {
+        let temp_dir = tempdir()?;
+        fs::write(
+            temp_dir.path().join("guide.md"),
+            "# Guide\n\nRead this first.",
+        )?;
+        fs::write(
+            temp_dir.path().join("api.md"),
+            "# API\n\nAll the functions.",
+        )?;
+        fs::write(temp_dir.path().join("notes.txt"), "Some plain notes.")?;
+        fs::write(temp_dir.path().join("LICENSE"), "MIT")?;
+
+        let stats = calculate_directory_tokens(temp_dir.path(), None, &TokenOptions::default())?;
+        let totals = tokens_by_extension(stats.values());
+
+        let tokens = |name: &str| stats[name].token_count;
+        assert_eq!(totals.len(), 3);
+        assert_eq!(totals["md"], tokens("guide") + tokens("api"));
+        assert_eq!(totals["txt"], tokens("notes"));
+        assert_eq!(totals[""], tokens("LICENSE"));
+        assert_eq!(
+            totals.values().sum::<usize>(),
+            stats.values().map(|stats| stats.token_count).sum::<usize>()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_aggregate_unique_tokens() -> Result<()> {
+        // Two banks sharing an identical block of boilerplate
+        let shared = "impl Default for Config {\n    fn default() -> Self { ... }\n}\n/// Licensed under MIT or Apache-2.0.\n";
+        let bank1 = format!("{}pub fn alpha_function() -> String {{ ... }}\n", shared);
+        let bank2 = format!("{}pub fn beta_function() -> usize {{ ... }}\n", shared);
+
+        let naive_total = calculate_tokens(&bank1, &TokenOptions::default())?
+            + calculate_tokens(&bank2, &TokenOptions::default())?;
+        let unique_total = aggregate_unique_tokens(&[&bank1, &bank2])?;
+
+        // The shared block should only be counted once
+        assert!(unique_total < naive_total);
+        assert!(unique_total >= calculate_tokens(&bank1, &TokenOptions::default())?);
+
+        // A single bank has nothing to deduplicate against
+        assert_eq!(
+            aggregate_unique_tokens(&[&bank1])?,
+            calculate_tokens(&bank1, &TokenOptions::default())?
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_extract_dependency_info_with_all_dependency_types() -> Result<()> {
         // Create a temporary directory
@@ -1624,7 +6055,7 @@ dep3 = "1.5"
         assert!(fixture_path.exists(), "Workspace fixture does not exist");
 
         // Find all Cargo.toml files within the fixture
-        let cargo_toml_files = find_cargo_toml_files(fixture_path)?;
+        let cargo_toml_files = find_cargo_toml_files(fixture_path, &DiscoveryOptions::default())?;
 
         // Expecting 3: root, core, utils
         assert_eq!(cargo_toml_files.len(), 3);
@@ -1678,6 +6109,230 @@ dep3 = "1.5"
         Ok(())
     }
 
+    #[test]
+    fn test_diff_dependency_collections() {
+        let mut old = DependencyCollection::new();
+        old.add(Dependency::new("anyhow", "1.0.75"));
+        old.add(Dependency::new("serde", "1.0.150"));
+        old.add(Dependency::new("log", "0.4.20"));
+
+        let mut new = DependencyCollection::new();
+        new.add(Dependency::new("anyhow", "1.0.75"));
+        new.add(Dependency::new("serde", "1.0.197"));
+        new.add(Dependency::new("tokio", "1.36.0"));
+        new.add(Dependency::new("tokio", "1.36.0"));
+
+        let diff = diff_dependency_collections(&old, &new);
+
+        assert_eq!(diff.added, vec![Dependency::new("tokio", "1.36.0")]);
+        assert_eq!(diff.removed, vec![Dependency::new("log", "0.4.20")]);
+        assert_eq!(
+            diff.changed,
+            vec![VersionChange {
+                name: "serde".to_string(),
+                old_version: "1.0.150".to_string(),
+                new_version: "1.0.197".to_string(),
+            }]
+        );
+        assert!(!diff.is_empty());
+
+        // Identical sets have no differences
+        assert!(diff_dependency_collections(&new, &new).is_empty());
+    }
+
+    #[test]
+    fn test_registry_dir_names() {
+        // The directories of crates.io as named by cargo before and since 1.85
+        assert_eq!(
+            registry_dir_names(CRATES_IO_SPARSE_SOURCE),
+            Some(vec![
+                "index.crates.io-6f17d22bba15001f".to_string(),
+                "index.crates.io-1949cf8c6b5b557f".to_string(),
+            ])
+        );
+        assert_eq!(
+            registry_dir_names(CRATES_IO_SOURCE),
+            Some(vec![
+                "github.com-1ecc6299db9ec823".to_string(),
+                "github.com-25cdd57fae9f0462".to_string(),
+            ])
+        );
+        assert_eq!(
+            registry_dir_names("git+https://github.com/serde-rs/serde#abc"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_registry_dir_for_source() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let registry_base = temp_dir.path();
+        let crates_io = registry_base.join("index.crates.io-1949cf8c6b5b557f");
+        let internal = registry_base.join("crates.example.com-60d098bf9c8c22cf");
+        fs::create_dir_all(&crates_io)?;
+        fs::create_dir_all(&internal)?;
+        // Another registry on the same host has another hash
+        fs::create_dir_all(registry_base.join("crates.example.com-0123456789abcdef"))?;
+
+        assert_eq!(
+            registry_dir_for_source(CRATES_IO_SOURCE, registry_base),
+            Some(crates_io.clone())
+        );
+        assert_eq!(
+            registry_dir_for_source(CRATES_IO_SPARSE_SOURCE, registry_base),
+            Some(crates_io)
+        );
+        assert_eq!(
+            registry_dir_for_source(
+                "registry+https://crates.example.com/git/index",
+                registry_base
+            ),
+            Some(internal)
+        );
+
+        // Git sources are not registries, and registries without a directory are unknown
+        assert_eq!(
+            registry_dir_for_source("git+https://github.com/serde-rs/serde#abc", registry_base),
+            None
+        );
+        assert_eq!(
+            registry_dir_for_source("sparse+https://crates.example.com/index/", registry_base),
+            None
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_dependency_paths_by_source() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let registry_base = temp_dir.path();
+        let crates_io = registry_base.join("index.crates.io-1949cf8c6b5b557f");
+        let internal = registry_base.join("crates.example.com-1574ff2167f861a8");
+        fs::create_dir_all(crates_io.join("serde-1.0.197"))?;
+        fs::create_dir_all(internal.join("billing-0.3.0"))?;
+        // A crate of the same name and version in the other registry must not be used
+        fs::create_dir_all(internal.join("anyhow-1.0.98"))?;
+
+        let mut dependencies = DependencyCollection::new();
+        dependencies.add(Dependency::new("serde", "1.0.197").with_source(CRATES_IO_SOURCE));
+        let billing = Dependency::new("billing", "0.3.0")
+            .with_source("sparse+https://crates.example.com/index/");
+        dependencies.add(billing.clone());
+        dependencies.add(Dependency::new("anyhow", "1.0.98").with_source(CRATES_IO_SOURCE));
+
+        let paths = resolve_dependency_paths_in(registry_base, &dependencies);
+
+        assert_eq!(paths.len(), 2);
+        assert_eq!(paths["serde"], crates_io.join("serde-1.0.197"));
+        assert_eq!(paths["billing"], internal.join("billing-0.3.0"));
+
+        // Given the crates.io directory, crates of other registries are found in theirs
+        assert_eq!(
+            billing.get_registry_path(&crates_io),
+            internal.join("billing-0.3.0")
+        );
+        assert!(billing.is_available_in_registry(&crates_io));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_file_sources() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let registry_dir = temp_dir.path().join("local registry");
+        let crate_dir = registry_dir.join("billing-0.3.0");
+        fs::create_dir_all(&crate_dir)?;
+        fs::write(
+            crate_dir.join("Cargo.toml"),
+            "[package]\nname = \"billing\"\nversion = \"0.3.0\"\n",
+        )?;
+
+        let registry_url = format!(
+            "file://{}",
+            registry_dir.display().to_string().replace(' ', "%20")
+        );
+        let manifest = "[dependencies]\nbilling = \"0.3\"\n";
+        let lock = format!(
+            "version = 3\n\n[[package]]\nname = \"billing\"\nversion = \"0.3.0\"\nsource = \"sparse+{}\"\n",
+            registry_url
+        );
+        let resolved = resolve_dependency_versions_from_str(
+            &lock,
+            &extract_dependency_info_from_str(manifest)?,
+        )?;
+        let billing = resolved.get("billing").unwrap();
+
+        assert_eq!(
+            resolve_local_source(billing.source.as_deref().unwrap()),
+            Some(registry_dir.clone())
+        );
+        // The crate is generated from the local registry, whatever the cargo registry
+        assert_eq!(billing.local_source_path(), Some(crate_dir.clone()));
+        assert_eq!(billing.get_registry_path(Path::new("/registry")), crate_dir);
+        assert!(is_dependency_available(Path::new("/registry"), billing));
+
+        assert_eq!(
+            resolve_local_source(&format!("registry+{}", registry_url)),
+            Some(registry_dir)
+        );
+        assert_eq!(resolve_local_source("file:///does/not/exist"), None);
+        assert_eq!(resolve_local_source(CRATES_IO_SOURCE), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_dependency_versions_records_source() -> Result<()> {
+        let manifest = "[dependencies]\nserde = \"1.0\"\nlocal = { path = \"../local\" }\n";
+        let lock = format!(
+            "version = 3\n\n[[package]]\nname = \"serde\"\nversion = \"1.0.197\"\nsource = \"{}\"\n\n[[package]]\nname = \"local\"\nversion = \"0.1.0\"\n",
+            CRATES_IO_SOURCE
+        );
+
+        let dependency_info = extract_dependency_info_from_str(manifest)?;
+        let resolved = resolve_dependency_versions_from_str(&lock, &dependency_info)?;
+
+        assert_eq!(
+            resolved.get("serde").unwrap().source.as_deref(),
+            Some(CRATES_IO_SOURCE)
+        );
+        assert_eq!(resolved.get("local").unwrap().source, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_filter_version_range() {
+        let mut dependencies = DependencyCollection::new();
+        dependencies.add(Dependency::new("anyhow", "1.0.98"));
+        dependencies.add(Dependency::new("toml", "0.8.22"));
+        dependencies.add(Dependency::new("clap", "4.5.38"));
+        dependencies.add(Dependency::new("beta", "1.0.0-rc.1"));
+        dependencies.add(Dependency::new("env_logger", "workspace"));
+
+        let names = |deps: &DependencyCollection| {
+            let mut names: Vec<_> = deps.iter().map(|dep| dep.name.clone()).collect();
+            names.sort();
+            names
+        };
+        let v = |major, minor, patch| semver::Version::new(major, minor, patch);
+
+        // Both bounds are inclusive
+        let filtered = dependencies.filter_version_range(Some(&v(1, 0, 98)), Some(&v(4, 5, 38)));
+        assert_eq!(names(&filtered), vec!["anyhow", "clap"]);
+        let filtered = dependencies.filter_version_range(Some(&v(1, 0, 99)), Some(&v(4, 5, 37)));
+        assert!(filtered.is_empty());
+
+        // Pre-releases sort below their release
+        let filtered = dependencies.filter_version_range(None, Some(&v(1, 0, 0)));
+        assert_eq!(names(&filtered), vec!["beta", "toml"]);
+
+        // Unparseable versions are excluded even without bounds
+        let filtered = dependencies.filter_version_range(None, None);
+        assert_eq!(names(&filtered), vec!["anyhow", "beta", "clap", "toml"]);
+    }
+
     #[test]
     fn test_is_dependency_available() {
         // Create a mock registry directory
@@ -0,0 +1,267 @@
+//! Consistency checks of declared dependencies against a lockfile.
+//!
+//! Resolution picks the locked version of each dependency and quietly skips what it
+//! cannot find. Before generating, it is worth knowing whether the lockfile is stale:
+//! a dependency declared since the last `cargo update` is missing from it, and a
+//! requirement bumped in a manifest may no longer match the locked version.
+
+use crate::{DependencyCollection, ParsedLock, normalize_crate_name};
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+/// A declared dependency that does not agree with the lockfile
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Inconsistency {
+    /// No package of the dependency is locked
+    MissingFromLock {
+        /// Name of the dependency
+        name: String,
+        /// The declared version requirement
+        requirement: String,
+    },
+    /// None of the locked versions of the dependency satisfies its requirement
+    UnsatisfiedRequirement {
+        /// Name of the dependency
+        name: String,
+        /// The declared version requirement
+        requirement: String,
+        /// The locked versions of the dependency
+        locked: Vec<String>,
+    },
+}
+
+impl fmt::Display for Inconsistency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Inconsistency::MissingFromLock { name, requirement } => {
+                write!(f, "{} {} is not in Cargo.lock", name, requirement)
+            }
+            Inconsistency::UnsatisfiedRequirement {
+                name,
+                requirement,
+                locked,
+            } => write!(
+                f,
+                "{} {} is not satisfied by the locked version(s) {}",
+                name,
+                requirement,
+                locked.join(", ")
+            ),
+        }
+    }
+}
+
+/// Checks declared dependencies against a Cargo.lock.
+///
+/// Every declared dependency must have a locked package of the same name, after
+/// normalization, and its version requirement must be satisfied by at least one
+/// locked version. Requirements that are not semver requirements, such as the
+/// `workspace` placeholder of workspace-inherited dependencies, are only checked for
+/// presence.
+///
+/// # Arguments
+///
+/// * `declared` - The dependencies declared in Cargo.toml files, with their
+///   version requirements
+/// * `lock_path` - Path to the Cargo.lock file
+///
+/// # Returns
+///
+/// * `Result<Vec<Inconsistency>>` - The inconsistencies found, sorted by name; empty
+///   if the lockfile agrees with the declarations
+///
+/// # Errors
+///
+/// Returns an error if the Cargo.lock file cannot be read or parsed
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use depbank::{extract_dependency_info, validate_against_lock};
+/// use std::path::Path;
+///
+/// let declared = extract_dependency_info(Path::new("Cargo.toml")).unwrap();
+/// for inconsistency in validate_against_lock(&declared, "Cargo.lock").unwrap() {
+///     println!("{}", inconsistency);
+/// }
+/// ```
+pub fn validate_against_lock<P: AsRef<Path>>(
+    declared: &DependencyCollection,
+    lock_path: P,
+) -> Result<Vec<Inconsistency>> {
+    Ok(validate_against_parsed_lock(
+        declared,
+        &ParsedLock::read(lock_path)?,
+    ))
+}
+
+/// Checks declared dependencies against Cargo.lock content held in memory.
+///
+/// This is the same as [`validate_against_lock`], but takes the lockfile content
+/// directly instead of a path.
+///
+/// # Arguments
+///
+/// * `declared` - The dependencies declared in Cargo.toml files, with their
+///   version requirements
+/// * `content` - The content of a Cargo.lock file
+///
+/// # Returns
+///
+/// * `Result<Vec<Inconsistency>>` - The inconsistencies found, sorted by name
+///
+/// # Errors
+///
+/// Returns an error if the content cannot be parsed as a valid Cargo.lock
+///
+/// # Examples
+///
+/// ```rust
+/// use depbank::{Inconsistency, extract_dependency_info_from_str, validate_against_lock_str};
+///
+/// let declared = extract_dependency_info_from_str("[dependencies]\nserde = \"1.0\"\n").unwrap();
+/// let lock = "version = 3\n";
+/// assert_eq!(
+///     validate_against_lock_str(&declared, lock).unwrap(),
+///     vec![Inconsistency::MissingFromLock {
+///         name: "serde".to_string(),
+///         requirement: "1.0".to_string(),
+///     }]
+/// );
+/// ```
+pub fn validate_against_lock_str(
+    declared: &DependencyCollection,
+    content: &str,
+) -> Result<Vec<Inconsistency>> {
+    Ok(validate_against_parsed_lock(
+        declared,
+        &ParsedLock::parse(content)?,
+    ))
+}
+
+/// Checks declared dependencies against an already parsed Cargo.lock.
+///
+/// This is the same as [`validate_against_lock`], for a lockfile that other steps
+/// of an analysis read as well.
+///
+/// # Arguments
+///
+/// * `declared` - The dependencies declared in Cargo.toml files, with their
+///   version requirements
+/// * `cargo_lock` - The parsed Cargo.lock
+///
+/// # Returns
+///
+/// * `Vec<Inconsistency>` - The inconsistencies found, sorted by name
+pub fn validate_against_parsed_lock(
+    declared: &DependencyCollection,
+    cargo_lock: &ParsedLock,
+) -> Vec<Inconsistency> {
+    let mut locked: HashMap<String, Vec<&str>> = HashMap::new();
+    for package in &cargo_lock.package {
+        locked
+            .entry(normalize_crate_name(&package.name))
+            .or_default()
+            .push(&package.version);
+    }
+
+    let mut inconsistencies = Vec::new();
+    for dep in declared.iter() {
+        let Some(versions) = locked.get(&normalize_crate_name(&dep.name)) else {
+            inconsistencies.push(Inconsistency::MissingFromLock {
+                name: dep.name.clone(),
+                requirement: dep.version.clone(),
+            });
+            continue;
+        };
+
+        let Ok(requirement) = semver::VersionReq::parse(&dep.version) else {
+            continue;
+        };
+        let satisfied = versions.iter().any(|version| {
+            semver::Version::parse(version).is_ok_and(|version| requirement.matches(&version))
+        });
+        if !satisfied {
+            inconsistencies.push(Inconsistency::UnsatisfiedRequirement {
+                name: dep.name.clone(),
+                requirement: dep.version.clone(),
+                locked: versions.iter().map(|version| version.to_string()).collect(),
+            });
+        }
+    }
+
+    // The same dependency is often declared by several manifests
+    inconsistencies.sort_by_key(ToString::to_string);
+    inconsistencies.dedup();
+    inconsistencies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Dependency;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_validate_against_lock() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let lock_path = temp_dir.path().join("Cargo.lock");
+        fs::write(
+            &lock_path,
+            r#"version = 3
+
+[[package]]
+name = "serde"
+version = "1.0.197"
+
+[[package]]
+name = "rand"
+version = "0.7.3"
+
+[[package]]
+name = "rand"
+version = "0.8.5"
+
+[[package]]
+name = "tokio"
+version = "1.37.0"
+
+[[package]]
+name = "serde_json"
+version = "1.0.140"
+"#,
+        )?;
+
+        let mut declared = DependencyCollection::new();
+        declared.add(Dependency::new("serde", "1.0"));
+        declared.add(Dependency::new("rand", "0.8"));
+        declared.add(Dependency::new("serde-json", "workspace"));
+        // Declared since the lockfile was last updated
+        declared.add(Dependency::new("anyhow", "1.0"));
+        // Bumped in the manifest without updating the lockfile
+        declared.add(Dependency::new("tokio", "^1.40"));
+        declared.add(Dependency::new("tokio", "^1.40"));
+
+        assert_eq!(
+            validate_against_lock(&declared, &lock_path)?,
+            vec![
+                Inconsistency::MissingFromLock {
+                    name: "anyhow".to_string(),
+                    requirement: "1.0".to_string(),
+                },
+                Inconsistency::UnsatisfiedRequirement {
+                    name: "tokio".to_string(),
+                    requirement: "^1.40".to_string(),
+                    locked: vec!["1.37.0".to_string()],
+                },
+            ]
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,157 @@
+//! Parsing of Cargo.lock files, read once and shared.
+//!
+//! Resolving versions, collecting transitive dependencies, building the dependency
+//! graph, finding version conflicts and checking declared requirements all read the
+//! locked packages. A [`ParsedLock`] is parsed once and handed to each of them, so
+//! an analysis never reads or parses the same lockfile twice.
+
+use crate::{Dependency, DependencyCollection};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+/// Represents a package in Cargo.lock
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct CargoLockPackage {
+    pub(crate) name: String,
+    pub(crate) version: String,
+    pub(crate) source: Option<String>,
+    /// The packages this one depends on, as `name`, or `name version` when several
+    /// versions of the package are locked
+    #[serde(default)]
+    pub(crate) dependencies: Vec<String>,
+}
+
+/// A project's parsed Cargo.lock
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ParsedLock {
+    #[serde(default)]
+    pub(crate) package: Vec<CargoLockPackage>,
+}
+
+impl ParsedLock {
+    /// Reads and parses a Cargo.lock file.
+    ///
+    /// # Arguments
+    ///
+    /// * `cargo_lock_path` - Path to the Cargo.lock file
+    ///
+    /// # Returns
+    ///
+    /// * `Result<ParsedLock>` - The parsed lockfile
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or parsed as a valid Cargo.lock
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use depbank::ParsedLock;
+    ///
+    /// let lock = ParsedLock::read("Cargo.lock").unwrap();
+    /// println!("{} locked packages", lock.len());
+    /// ```
+    pub fn read<P: AsRef<Path>>(cargo_lock_path: P) -> Result<Self> {
+        let cargo_lock_path = cargo_lock_path.as_ref();
+        let content = fs::read_to_string(cargo_lock_path).with_context(|| {
+            format!(
+                "Failed to read Cargo.lock file: {}",
+                cargo_lock_path.display()
+            )
+        })?;
+        Self::parse(&content).with_context(|| {
+            format!(
+                "Failed to parse Cargo.lock file: {}",
+                cargo_lock_path.display()
+            )
+        })
+    }
+
+    /// Parses Cargo.lock content held in memory, e.g. read from stdin.
+    ///
+    /// # Arguments
+    ///
+    /// * `content` - The content of a Cargo.lock file
+    ///
+    /// # Returns
+    ///
+    /// * `Result<ParsedLock>` - The parsed lockfile
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the content cannot be parsed as a valid Cargo.lock
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depbank::ParsedLock;
+    ///
+    /// let lock = ParsedLock::parse("version = 3\n\n[[package]]\nname = \"serde\"\nversion = \"1.0.197\"\n").unwrap();
+    /// assert_eq!(lock.len(), 1);
+    /// ```
+    pub fn parse(content: &str) -> Result<Self> {
+        toml::from_str(content).context("Failed to parse Cargo.lock content")
+    }
+
+    /// The number of locked packages
+    pub fn len(&self) -> usize {
+        self.package.len()
+    }
+
+    /// Whether no package is locked
+    pub fn is_empty(&self) -> bool {
+        self.package.is_empty()
+    }
+
+    /// The locked packages with their versions and sources
+    pub fn packages(&self) -> DependencyCollection {
+        let mut packages = DependencyCollection::new();
+        for package in &self.package {
+            let mut dependency = Dependency::new(&package.name, &package.version);
+            dependency.source.clone_from(&package.source);
+            packages.add(dependency);
+        }
+        packages
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_parsed_lock_packages() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let lock_path = temp_dir.path().join("Cargo.lock");
+        fs::write(
+            &lock_path,
+            r#"
+version = 3
+
+[[package]]
+name = "app"
+version = "0.1.0"
+dependencies = ["serde"]
+
+[[package]]
+name = "serde"
+version = "1.0.197"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#,
+        )?;
+
+        let lock = ParsedLock::read(&lock_path)?;
+        assert_eq!(lock.len(), 2);
+        let packages = lock.packages();
+        assert_eq!(packages.get_version("serde"), Some(&"1.0.197".to_string()));
+        let serde = packages.iter().find(|dep| dep.name == "serde").unwrap();
+        assert!(serde.source.is_some());
+
+        assert!(ParsedLock::parse("[[package]]\nname = \"app\"\n").is_err());
+        assert!(ParsedLock::read(temp_dir.path().join("missing.lock")).is_err());
+        Ok(())
+    }
+}
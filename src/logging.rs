@@ -0,0 +1,86 @@
+//! Targets of the library's diagnostic log records.
+//!
+//! Diagnostics go through the `log` facade, under one target per area of the library
+//! rather than per source module. Applications embedding the library can then tune
+//! each area on its own, e.g. `RUST_LOG=depbank::resolve=debug` with `env_logger`.
+
+/// Finding Cargo.toml files and reading the dependencies they declare
+pub const LOG_TARGET_DISCOVERY: &str = "depbank::discovery";
+
+/// Resolving dependency versions from Cargo.lock and locating their sources
+pub const LOG_TARGET_RESOLVE: &str = "depbank::resolve";
+
+/// Generating code banks
+pub const LOG_TARGET_GENERATE: &str = "depbank::generate";
+
+/// Counting tokens
+pub const LOG_TARGET_TOKENS: &str = "depbank::tokens";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Dependency, DependencyCollection, resolve_dependency_versions_from_str};
+    use log::{Level, Log, Metadata, Record};
+    use std::sync::{Mutex, PoisonError};
+
+    /// Records every log record as `(target, level, message)`
+    struct CapturingLogger {
+        records: Mutex<Vec<(String, Level, String)>>,
+    }
+
+    impl Log for CapturingLogger {
+        fn enabled(&self, _metadata: &Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &Record) {
+            // A failed assertion must not break logging for the other tests
+            self.records
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push((
+                    record.target().to_string(),
+                    record.level(),
+                    record.args().to_string(),
+                ));
+        }
+
+        fn flush(&self) {}
+    }
+
+    static LOGGER: CapturingLogger = CapturingLogger {
+        records: Mutex::new(Vec::new()),
+    };
+
+    #[test]
+    fn test_resolution_logs_use_resolve_target() {
+        // Other tests may log concurrently, so only this test's records are checked.
+        // The logger can only be installed once per process: when another one is
+        // already installed, the records cannot be captured
+        let _ = log::set_logger(&LOGGER);
+        if !std::ptr::addr_eq(log::logger(), &LOGGER) {
+            return;
+        }
+        log::set_max_level(log::LevelFilter::Trace);
+
+        let lock = "version = 3\n\n[[package]]\nname = \"log-target-probe\"\nversion = \"0.3.1\"\n";
+        let mut dependencies = DependencyCollection::new();
+        dependencies.add(Dependency::new("log-target-probe", "0.3"));
+        dependencies.add(Dependency::new("log-target-missing", "1.0"));
+        resolve_dependency_versions_from_str(lock, &dependencies).unwrap();
+
+        let records = LOGGER
+            .records
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let probe_records: Vec<&(String, Level, String)> = records
+            .iter()
+            .filter(|(_, _, message)| message.contains("log-target-"))
+            .collect();
+        assert_eq!(probe_records.len(), 2);
+        for (target, level, _) in probe_records {
+            assert_eq!(target, LOG_TARGET_RESOLVE);
+            assert_eq!(*level, Level::Debug);
+        }
+    }
+}
@@ -1,22 +1,216 @@
 use anyhow::Result;
 use clap::Parser;
+use depbank::{
+    ColorStyle, OutputStream, TokenOptions, TokenizerModel, TransitiveOptions, exit_code, paint,
+    set_color_choice, set_thread_pool, set_warnings_format,
+};
+use std::process::ExitCode;
 
 mod cli;
 mod utils;
 
-use cli::{Cli, Commands};
-use utils::{generate_command, list_command, tokens_command};
+use cli::{Cli, Commands, ListGroupBy, RegistryCommands};
+use utils::{
+    check_command, compare_strategies_command, deps_diff_command, fingerprint_command,
+    generate_command, list_by_source_command, list_command, list_locked_command, list_tree_command,
+    meta_command, registry_prune_command, resolve_model_price, sbom_command, tokens_command,
+    watch_command,
+};
 
-fn main() -> Result<()> {
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!(
+                "{} {:?}",
+                paint("Error:", ColorStyle::Error, OutputStream::Stderr),
+                e
+            );
+            // Distinct exit codes let CI tell failures apart
+            ExitCode::from(exit_code(&e))
+        }
+    }
+}
+
+fn run() -> Result<()> {
     let cli = Cli::parse();
 
+    // Generation and tokenization share a single thread pool
+    set_thread_pool(cli.jobs.unwrap_or(0))?;
+    set_color_choice(cli.color.into());
+    set_warnings_format(cli.warnings_format.into());
+
     match &cli.command {
-        Commands::Generate {
+        Commands::Generate(args) => generate_command(args).map(|_| ()),
+        Commands::Watch(args) => watch_command(args),
+        Commands::Tokens {
+            path,
+            extension,
+            dedup,
+            with_special_tokens,
+            tokenizer,
+            max_length,
+            no_token_cache,
+            depth,
+            price,
+            model,
+            relative_paths,
+        } => {
+            let options = TokenOptions {
+                include_special_tokens: *with_special_tokens,
+                model: TokenizerModel::parse(tokenizer),
+                max_length: *max_length,
+                use_cache: !*no_token_cache,
+                max_depth: *depth,
+            };
+            let price = match model {
+                Some(model) => Some(resolve_model_price(model)?),
+                None => *price,
+            };
+            tokens_command(
+                path,
+                extension.as_deref(),
+                *dedup,
+                &options,
+                price,
+                *relative_paths,
+            )
+        }
+        Commands::List {
+            path,
+            lock,
+            tree: true,
+            discovery,
+            ..
+        } => list_tree_command(path, lock.as_deref(), &discovery.discovery_options()).map(|_| ()),
+        Commands::List {
+            path,
+            lock,
+            from_lock: true,
+            min_version,
+            max_version,
+            format,
+            ..
+        } => list_locked_command(
+            path,
+            lock.as_deref(),
+            (min_version.as_ref(), max_version.as_ref()),
+            *format,
+        )
+        .map(|_| ()),
+        Commands::List {
+            path,
+            lock,
+            group_by: Some(ListGroupBy::Source),
+            min_version,
+            max_version,
+            discovery,
+            ..
+        } => list_by_source_command(
+            path,
+            lock.as_deref(),
+            (min_version.as_ref(), max_version.as_ref()),
+            &discovery.discovery_options(),
+        )
+        .map(|_| ()),
+        Commands::List {
+            path,
+            detailed,
+            lock,
+            check_yanked,
+            min_version,
+            max_version,
+            discovery,
+            format,
+            ..
+        } => list_command(
             path,
+            *detailed,
+            lock.as_deref(),
+            *check_yanked,
+            (min_version.as_ref(), max_version.as_ref()),
+            &discovery.discovery_options(),
+            *format,
+        )
+        .map(|_| ()),
+        Commands::Registry {
+            command:
+                RegistryCommands::Prune {
+                    path,
+                    lock,
+                    dry_run: _,
+                    format,
+                },
+        } => registry_prune_command(path, lock.as_deref(), *format),
+        Commands::Check {
+            path,
+            lock,
+            discovery,
+            format,
+        } => check_command(
+            path,
+            lock.as_deref(),
+            &discovery.discovery_options(),
+            *format,
+        )
+        .map(|_| ()),
+        Commands::CompareStrategies {
+            path,
+            lock,
+            baseline,
+            candidate,
+            discovery,
+            format,
+        } => compare_strategies_command(
+            path,
+            lock.as_deref(),
+            (*baseline, *candidate),
+            &discovery.discovery_options(),
+            *format,
+        )
+        .map(|_| ()),
+        Commands::DepsDiff {
+            path,
+            other,
+            against,
+            format,
+        } => deps_diff_command(path, other.as_deref(), against.as_deref(), *format),
+        Commands::Fingerprint(args) => fingerprint_command(args).map(|_| ()),
+        Commands::Sbom {
+            path,
+            lock,
+            transitive,
+            include_dev_transitive,
+            discovery,
+            format,
             output,
-            dry_run,
-        } => generate_command(path, output, *dry_run),
-        Commands::Tokens { path, extension } => tokens_command(path, extension.as_deref()),
-        Commands::List { path, detailed } => list_command(path, *detailed),
+        } => sbom_command(
+            path,
+            lock.as_deref(),
+            transitive.then_some(TransitiveOptions {
+                include_dev: *include_dev_transitive,
+            }),
+            &discovery.discovery_options(),
+            *format,
+            output.as_deref(),
+        )
+        .map(|_| ()),
+        Commands::Meta {
+            path,
+            lock,
+            transitive,
+            include_dev_transitive,
+            discovery,
+            output,
+        } => meta_command(
+            path,
+            lock.as_deref(),
+            transitive.then_some(TransitiveOptions {
+                include_dev: *include_dev_transitive,
+            }),
+            &discovery.discovery_options(),
+            output.as_deref(),
+        )
+        .map(|_| ()),
     }
 }
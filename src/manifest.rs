@@ -0,0 +1,219 @@
+//! Parsing of dependencies' Cargo.toml files, read once and shared.
+//!
+//! Several steps inspect the manifest of the same dependency: the package file
+//! filter, target detection, and metadata such as the license, edition, features or
+//! whether the crate is a proc-macro. A [`ParsedManifest`] is read once and handed to
+//! each of them, and a [`ManifestCache`] keeps the parsed manifests of many
+//! dependencies, so none is read or parsed twice.
+
+use crate::Dependency;
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A crate's parsed Cargo.toml; empty for crates without a manifest
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParsedManifest {
+    manifest: Option<toml::Value>,
+}
+
+impl ParsedManifest {
+    /// Reads and parses the Cargo.toml of a crate directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `crate_dir` - Path to the crate's source directory
+    ///
+    /// # Returns
+    ///
+    /// * `Result<ParsedManifest>` - The parsed manifest, empty if the crate has none
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the manifest exists but cannot be read or parsed
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use depbank::ParsedManifest;
+    /// use std::path::Path;
+    ///
+    /// let manifest = ParsedManifest::read(Path::new("/path/to/registry/serde-1.0.219")).unwrap();
+    /// println!("license: {:?}", manifest.license());
+    /// ```
+    pub fn read(crate_dir: &Path) -> Result<Self> {
+        Self::read_file(&crate_dir.join("Cargo.toml"))
+    }
+
+    /// Reads and parses a manifest file, empty if the file does not exist
+    pub(crate) fn read_file(path: &Path) -> Result<Self> {
+        if !path.is_file() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read Cargo.toml file: {}", path.display()))?;
+        let manifest = toml::from_str(&content)
+            .with_context(|| format!("Failed to parse Cargo.toml file: {}", path.display()))?;
+        Ok(Self {
+            manifest: Some(manifest),
+        })
+    }
+
+    /// The whole manifest, if the crate has one
+    pub fn value(&self) -> Option<&toml::Value> {
+        self.manifest.as_ref()
+    }
+
+    /// A top-level section of the manifest, such as `lib` or `bin`
+    pub fn section(&self, key: &str) -> Option<&toml::Value> {
+        self.manifest.as_ref()?.get(key)
+    }
+
+    /// A key of the manifest's `[package]` section
+    pub fn package_field(&self, key: &str) -> Option<&toml::Value> {
+        self.section("package")?.get(key)
+    }
+
+    /// The SPDX license expression of the package
+    pub fn license(&self) -> Option<&str> {
+        self.package_field("license")?.as_str()
+    }
+
+    /// The Rust edition of the package, if set explicitly
+    pub fn edition(&self) -> Option<&str> {
+        self.package_field("edition")?.as_str()
+    }
+
+    /// The names of the features the package declares, sorted
+    pub fn features(&self) -> Vec<String> {
+        let mut features: Vec<String> = self
+            .section("features")
+            .and_then(|features| features.as_table())
+            .map(|features| features.keys().cloned().collect())
+            .unwrap_or_default();
+        features.sort();
+        features
+    }
+
+    /// Whether the package's library is a procedural macro
+    pub fn is_proc_macro(&self) -> bool {
+        let lib = self.section("lib");
+        ["proc-macro", "proc_macro"].iter().any(|key| {
+            lib.and_then(|lib| lib.get(key))
+                .and_then(|value| value.as_bool())
+                .unwrap_or(false)
+        })
+    }
+}
+
+/// Parsed manifests of dependencies, each read and parsed only on first use
+#[derive(Debug, Default)]
+pub struct ManifestCache {
+    manifests: HashMap<PathBuf, ParsedManifest>,
+}
+
+impl ManifestCache {
+    /// Creates an empty cache
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the parsed manifest of a dependency, reading it on the first lookup.
+    ///
+    /// Manifests that are missing or cannot be parsed are cached as empty, so they
+    /// are not retried either.
+    ///
+    /// # Arguments
+    ///
+    /// * `registry_path` - Path to the cargo registry directory
+    /// * `dependency` - The dependency whose manifest to look up
+    ///
+    /// # Returns
+    ///
+    /// * `&ParsedManifest` - The dependency's parsed manifest
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use depbank::{Dependency, ManifestCache, resolve_registry_path};
+    ///
+    /// let registry_path = resolve_registry_path().unwrap();
+    /// let serde = Dependency::new("serde", "1.0.219");
+    /// let mut cache = ManifestCache::new();
+    /// let license = cache.get(&registry_path, &serde).license().map(str::to_string);
+    /// // Read from the cache this time
+    /// let edition = cache.get(&registry_path, &serde).edition();
+    /// println!("{:?} {:?}", license, edition);
+    /// ```
+    pub fn get(&mut self, registry_path: &Path, dependency: &Dependency) -> &ParsedManifest {
+        self.manifests
+            .entry(dependency.get_registry_path(registry_path))
+            .or_insert_with_key(|crate_dir| ParsedManifest::read(crate_dir).unwrap_or_default())
+    }
+
+    /// Number of manifests in the cache
+    pub fn len(&self) -> usize {
+        self.manifests.len()
+    }
+
+    /// Returns true if no manifest has been looked up yet
+    pub fn is_empty(&self) -> bool {
+        self.manifests.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_manifest_cache_parses_once() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let crate_dir = temp_dir.path().join("derive-helpers-0.2.0");
+        fs::create_dir_all(&crate_dir)?;
+        fs::write(
+            crate_dir.join("Cargo.toml"),
+            r#"[package]
+name = "derive-helpers"
+version = "0.2.0"
+edition = "2021"
+license = "MIT OR Apache-2.0"
+
+[lib]
+proc-macro = true
+
+[features]
+default = ["std"]
+std = []
+"#,
+        )?;
+
+        let dependency = Dependency::new("derive-helpers", "0.2.0");
+        let mut cache = ManifestCache::new();
+        assert_eq!(
+            cache.get(temp_dir.path(), &dependency).license(),
+            Some("MIT OR Apache-2.0")
+        );
+
+        // Later lookups are served from the cache without reading the file again
+        fs::remove_file(crate_dir.join("Cargo.toml"))?;
+        let manifest = cache.get(temp_dir.path(), &dependency);
+        assert_eq!(manifest.edition(), Some("2021"));
+        assert!(manifest.is_proc_macro());
+        assert_eq!(manifest.features(), vec!["default", "std"]);
+        assert_eq!(cache.len(), 1);
+
+        // A dependency without a manifest is cached as empty
+        let missing = Dependency::new("missing", "1.0.0");
+        assert_eq!(
+            cache.get(temp_dir.path(), &missing),
+            &ParsedManifest::default()
+        );
+        assert_eq!(cache.len(), 2);
+
+        Ok(())
+    }
+}
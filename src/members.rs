@@ -0,0 +1,223 @@
+//! Detection of dependencies on the project's own workspace members.
+//!
+//! Members of a workspace depend on each other through path dependencies, which
+//! Cargo.lock records like any other package. They are part of the project rather
+//! than external dependencies, and are not in the cargo registry either, so they are
+//! told apart from registry crates by their `[package] name` and source.
+
+use crate::{Dependency, PATH_SOURCE_PREFIX, ParsedManifest, normalize_crate_name};
+use std::path::{Path, PathBuf};
+
+/// A package of the project's own workspace
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkspaceMember {
+    /// The `[package] name` of the member
+    pub name: String,
+    /// The directory holding the member's Cargo.toml
+    pub path: PathBuf,
+}
+
+impl WorkspaceMember {
+    /// Returns the dependency on this member, located in its own directory rather
+    /// than in the cargo registry
+    pub fn local_dependency(&self, dependency: &Dependency) -> Dependency {
+        let path = self
+            .path
+            .canonicalize()
+            .unwrap_or_else(|_| self.path.clone());
+        Dependency::new(&dependency.name, &dependency.version).with_source(format!(
+            "{}{}",
+            PATH_SOURCE_PREFIX,
+            path.display()
+        ))
+    }
+}
+
+/// Lists the packages defined by a project's Cargo.toml files.
+///
+/// Manifests without a `[package]` section, such as virtual workspace roots, and
+/// manifests that cannot be parsed are skipped.
+///
+/// # Arguments
+///
+/// * `cargo_toml_files` - The Cargo.toml files found in the project
+///
+/// # Returns
+///
+/// * `Vec<WorkspaceMember>` - The packages, in the order of `cargo_toml_files`
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use depbank::{DiscoveryOptions, find_cargo_toml_files, workspace_members};
+///
+/// let cargo_toml_files = find_cargo_toml_files(".", &DiscoveryOptions::default()).unwrap();
+/// for member in workspace_members(&cargo_toml_files) {
+///     println!("{} in {}", member.name, member.path.display());
+/// }
+/// ```
+pub fn workspace_members(cargo_toml_files: &[PathBuf]) -> Vec<WorkspaceMember> {
+    cargo_toml_files
+        .iter()
+        .filter_map(|cargo_toml_path| {
+            let manifest = ParsedManifest::read_file(cargo_toml_path).ok()?;
+            let name = manifest.package_field("name")?.as_str()?.to_string();
+            let path = cargo_toml_path
+                .parent()
+                .unwrap_or_else(|| Path::new("."))
+                .to_path_buf();
+            Some(WorkspaceMember { name, path })
+        })
+        .collect()
+}
+
+/// Checks whether a dependency is one of the workspace's own members.
+///
+/// A dependency is a member if its resolved source is a path inside a member's
+/// directory, or if its name matches a member's `[package] name`, after
+/// normalization.
+///
+/// # Arguments
+///
+/// * `dep` - The dependency to check
+/// * `members` - The workspace members
+///
+/// # Returns
+///
+/// * `bool` - True if the dependency is a workspace member
+///
+/// # Examples
+///
+/// ```rust
+/// use depbank::{Dependency, WorkspaceMember, is_workspace_member_dep};
+/// use std::path::PathBuf;
+///
+/// let members = vec![WorkspaceMember {
+///     name: "app-core".to_string(),
+///     path: PathBuf::from("crates/core"),
+/// }];
+/// assert!(is_workspace_member_dep(&Dependency::new("app_core", "0.1.0"), &members));
+/// assert!(!is_workspace_member_dep(&Dependency::new("serde", "1.0.219"), &members));
+/// ```
+pub fn is_workspace_member_dep(dep: &Dependency, members: &[WorkspaceMember]) -> bool {
+    let name = normalize_crate_name(&dep.name);
+    if members
+        .iter()
+        .any(|member| normalize_crate_name(&member.name) == name)
+    {
+        return true;
+    }
+
+    let Some(path) = dep
+        .source
+        .as_deref()
+        .and_then(|source| source.strip_prefix(PATH_SOURCE_PREFIX))
+    else {
+        return false;
+    };
+    let path = Path::new(path);
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    members.iter().any(|member| {
+        let member_path = member
+            .path
+            .canonicalize()
+            .unwrap_or_else(|_| member.path.clone());
+        path == member_path
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        DependencyCollection, DiscoveryOptions, extract_dependency_info, find_cargo_toml_files,
+        resolve_dependency_versions,
+    };
+    use anyhow::Result;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_workspace_member_dependency() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let root = temp_dir.path();
+        fs::write(
+            root.join("Cargo.toml"),
+            "[workspace]\nmembers = [\"api\", \"shared\"]\n",
+        )?;
+        fs::create_dir_all(root.join("api"))?;
+        fs::write(
+            root.join("api").join("Cargo.toml"),
+            r#"[package]
+name = "api"
+version = "0.1.0"
+
+[dependencies]
+shared-types = { path = "../shared" }
+serde = "1"
+"#,
+        )?;
+        fs::create_dir_all(root.join("shared"))?;
+        fs::write(
+            root.join("shared").join("Cargo.toml"),
+            "[package]\nname = \"shared-types\"\nversion = \"0.2.0\"\n",
+        )?;
+        fs::write(
+            root.join("Cargo.lock"),
+            r#"version = 3
+
+[[package]]
+name = "api"
+version = "0.1.0"
+dependencies = ["serde", "shared-types"]
+
+[[package]]
+name = "serde"
+version = "1.0.219"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "shared-types"
+version = "0.2.0"
+"#,
+        )?;
+
+        let cargo_toml_files = find_cargo_toml_files(root, &DiscoveryOptions::default())?;
+        let members = workspace_members(&cargo_toml_files);
+        let mut names: Vec<&str> = members.iter().map(|member| member.name.as_str()).collect();
+        names.sort();
+        assert_eq!(names, vec!["api", "shared-types"]);
+
+        let mut declared = DependencyCollection::new();
+        for cargo_toml_path in &cargo_toml_files {
+            for dep in extract_dependency_info(cargo_toml_path)?.iter() {
+                declared.add(dep.clone());
+            }
+        }
+        let resolved = resolve_dependency_versions(root.join("Cargo.lock"), &declared)?;
+        let shared = resolved.get("shared-types").unwrap();
+        assert!(is_workspace_member_dep(shared, &members));
+        assert!(!is_workspace_member_dep(
+            resolved.get("serde").unwrap(),
+            &members
+        ));
+
+        // Generated from the member's own directory when included
+        let member = members
+            .iter()
+            .find(|member| member.name == "shared-types")
+            .unwrap();
+        let local = member.local_dependency(shared);
+        assert_eq!(local.version, "0.2.0");
+        assert_eq!(
+            local.local_source_path(),
+            Some(root.join("shared").canonicalize()?)
+        );
+        assert!(is_workspace_member_dep(
+            &Dependency::new("renamed", "0.2.0").with_source(local.source.clone().unwrap()),
+            &members
+        ));
+
+        Ok(())
+    }
+}
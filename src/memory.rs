@@ -0,0 +1,164 @@
+//! A best-effort cap on the memory used by parallel code bank generation.
+//!
+//! Each bank being generated holds its parsed sources and the generated markdown in
+//! memory until it is written out. With many large crates generated in parallel this
+//! can add up quickly. A [`MemoryBudget`] limits how much estimated memory the banks
+//! in flight may hold at once: a generation waits until enough of the budget has
+//! been released by the ones before it.
+//!
+//! The estimates are based on the size of a crate's sources, so the cap is a soft
+//! limit rather than a guarantee.
+
+use std::fs;
+use std::path::Path;
+use std::sync::{Condvar, Mutex};
+
+/// A budget of memory shared by concurrent generations.
+#[derive(Debug)]
+pub struct MemoryBudget {
+    capacity: u64,
+    used: Mutex<u64>,
+    released: Condvar,
+}
+
+/// A reservation of part of a [`MemoryBudget`], released when dropped.
+#[derive(Debug)]
+pub struct MemoryPermit<'a> {
+    budget: &'a MemoryBudget,
+    bytes: u64,
+}
+
+impl MemoryBudget {
+    /// Creates a budget allowing up to `capacity_bytes` to be reserved at once
+    pub fn new(capacity_bytes: u64) -> Self {
+        Self {
+            capacity: capacity_bytes.max(1),
+            used: Mutex::new(0),
+            released: Condvar::new(),
+        }
+    }
+
+    /// Creates a budget from a cap in megabytes
+    pub fn from_megabytes(megabytes: u64) -> Self {
+        Self::new(megabytes.saturating_mul(1024 * 1024))
+    }
+
+    /// Reserves `bytes` of the budget, blocking until enough of it is available.
+    ///
+    /// Requests larger than the whole budget are clamped to it, so they wait until
+    /// nothing else is in flight and then run alone rather than blocking forever.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The estimated memory needed
+    ///
+    /// # Returns
+    ///
+    /// * `MemoryPermit` - The reservation, released when dropped
+    pub fn acquire(&self, bytes: u64) -> MemoryPermit<'_> {
+        let bytes = bytes.clamp(1, self.capacity);
+        let mut used = self.used.lock().unwrap_or_else(|e| e.into_inner());
+        while *used + bytes > self.capacity {
+            used = self.released.wait(used).unwrap_or_else(|e| e.into_inner());
+        }
+        *used += bytes;
+
+        MemoryPermit {
+            budget: self,
+            bytes,
+        }
+    }
+}
+
+impl Drop for MemoryPermit<'_> {
+    fn drop(&mut self) {
+        let mut used = self.budget.used.lock().unwrap_or_else(|e| e.into_inner());
+        *used -= self.bytes;
+        self.budget.released.notify_all();
+    }
+}
+
+/// Estimates the memory needed to generate a code bank for a source directory.
+///
+/// This is the total size of the Rust sources in the directory; unreadable entries
+/// are ignored.
+pub(crate) fn estimate_generation_memory(source_path: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(source_path) else {
+        return 0;
+    };
+
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| {
+            let path = entry.path();
+            if path.is_dir() {
+                estimate_generation_memory(&path)
+            } else if path.extension().is_some_and(|ext| ext == "rs") {
+                entry.metadata().map(|metadata| metadata.len()).unwrap_or(0)
+            } else {
+                0
+            }
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+    use std::time::Duration;
+    use tempfile::tempdir;
+
+    /// Runs `workers` threads that each hold `bytes` of the budget for a while, and
+    /// returns the highest number of them holding a permit at the same time
+    fn peak_concurrency(budget: &MemoryBudget, workers: usize, bytes: u64) -> usize {
+        let in_flight = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+
+        thread::scope(|scope| {
+            for _ in 0..workers {
+                scope.spawn(|| {
+                    let _permit = budget.acquire(bytes);
+                    let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(current, Ordering::SeqCst);
+                    thread::sleep(Duration::from_millis(20));
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+        });
+
+        peak.load(Ordering::SeqCst)
+    }
+
+    #[test]
+    fn test_memory_budget_throttles_concurrency() {
+        // Room for two reservations at a time
+        let budget = MemoryBudget::new(2_000);
+        assert!(peak_concurrency(&budget, 8, 1_000) <= 2);
+
+        // Reservations larger than the budget run one at a time
+        let budget = MemoryBudget::new(500);
+        assert_eq!(peak_concurrency(&budget, 4, 1_000), 1);
+
+        // Everything was released again
+        assert_eq!(*budget.used.lock().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_estimate_generation_memory() -> Result<()> {
+        let temp_dir = tempdir()?;
+        fs::create_dir_all(temp_dir.path().join("src").join("nested"))?;
+        fs::write(temp_dir.path().join("src").join("lib.rs"), "a".repeat(100))?;
+        fs::write(
+            temp_dir.path().join("src").join("nested").join("mod.rs"),
+            "b".repeat(50),
+        )?;
+        fs::write(temp_dir.path().join("README.md"), "c".repeat(1_000))?;
+
+        assert_eq!(estimate_generation_memory(temp_dir.path()), 150);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,153 @@
+//! Metadata of dependencies, gathered without generating code banks.
+//!
+//! Several features read facts about a dependency from its manifest and sources in
+//! the local registry: the license for SBOMs, the features for bank headers, the
+//! build script, or whether the crate is a proc-macro. [`dependency_metadata`]
+//! gathers all of them at once, to inspect the dependencies of a project as data.
+
+use crate::targets::{manifest_build_script, manifest_is_no_std};
+use crate::{Dependency, ManifestCache, is_dependency_available};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// What is known about a resolved dependency from its manifest and sources
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DependencyMetadata {
+    /// The name of the dependency
+    pub name: String,
+    /// The version resolved from Cargo.lock
+    pub version: String,
+    /// Where the dependency comes from as recorded in Cargo.lock, if known
+    pub source: Option<String>,
+    /// Whether the dependency's sources are in the local cargo registry. The other
+    /// fields are only known for available dependencies
+    pub available: bool,
+    /// The SPDX license expression of the package
+    pub license: Option<String>,
+    /// The Rust edition of the package, if its manifest sets one
+    pub edition: Option<String>,
+    /// The names of the features the package declares, sorted
+    pub features: Vec<String>,
+    /// Whether the package's library is a procedural macro
+    pub proc_macro: bool,
+    /// Whether the package's library can be built without the standard library, see
+    /// [`is_no_std`](crate::is_no_std)
+    pub no_std: bool,
+    /// The path of the package's build script, relative to its directory, see
+    /// [`build_script`](crate::build_script)
+    pub build_script: Option<PathBuf>,
+}
+
+/// Gathers the metadata of a dependency from the local cargo registry.
+///
+/// # Arguments
+///
+/// * `dependency` - The dependency, with its version resolved from Cargo.lock
+/// * `registry_path` - Path to the cargo registry directory
+/// * `manifests` - The cache of parsed manifests, shared across dependencies
+///
+/// # Returns
+///
+/// * `DependencyMetadata` - The dependency's metadata, with only its name, version and
+///   source for dependencies that are not available locally
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use depbank::{Dependency, ManifestCache, dependency_metadata, resolve_registry_path};
+///
+/// let registry_path = resolve_registry_path().unwrap();
+/// let mut manifests = ManifestCache::new();
+/// let serde = Dependency::new("serde", "1.0.219");
+/// let metadata = dependency_metadata(&serde, &registry_path, &mut manifests);
+/// println!("{:?} {:?}", metadata.license, metadata.edition);
+/// ```
+pub fn dependency_metadata(
+    dependency: &Dependency,
+    registry_path: &Path,
+    manifests: &mut ManifestCache,
+) -> DependencyMetadata {
+    let mut metadata = DependencyMetadata {
+        name: dependency.name.clone(),
+        version: dependency.version.clone(),
+        source: dependency.source.clone(),
+        available: false,
+        license: None,
+        edition: None,
+        features: Vec::new(),
+        proc_macro: false,
+        no_std: false,
+        build_script: None,
+    };
+    if !is_dependency_available(registry_path, dependency) {
+        return metadata;
+    }
+
+    let crate_dir = dependency.get_registry_path(registry_path);
+    let manifest = manifests.get(registry_path, dependency);
+    metadata.available = true;
+    metadata.license = manifest.license().map(str::to_string);
+    metadata.edition = manifest.edition().map(str::to_string);
+    metadata.features = manifest.features();
+    metadata.proc_macro = manifest.is_proc_macro();
+    metadata.no_std = manifest_is_no_std(&crate_dir, manifest);
+    metadata.build_script = manifest_build_script(&crate_dir, manifest);
+    metadata
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_dependency_metadata() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let crate_dir = temp_dir.path().join("tinyvec-1.9.0");
+        fs::create_dir_all(crate_dir.join("src"))?;
+        fs::write(
+            crate_dir.join("Cargo.toml"),
+            "[package]\nname = \"tinyvec\"\nversion = \"1.9.0\"\nedition = \"2018\"\n\
+license = \"MIT OR Apache-2.0\"\n\n[features]\nstd = []\nalloc = []\n",
+        )?;
+        fs::write(
+            crate_dir.join("src").join("lib.rs"),
+            "#![cfg_attr(not(feature = \"std\"), no_std)]\npub struct ArrayVec;\n",
+        )?;
+        let mut manifests = ManifestCache::new();
+
+        let metadata = dependency_metadata(
+            &Dependency::new("tinyvec", "1.9.0"),
+            temp_dir.path(),
+            &mut manifests,
+        );
+        assert_eq!(
+            metadata,
+            DependencyMetadata {
+                name: "tinyvec".to_string(),
+                version: "1.9.0".to_string(),
+                source: None,
+                available: true,
+                license: Some("MIT OR Apache-2.0".to_string()),
+                edition: Some("2018".to_string()),
+                features: vec!["alloc".to_string(), "std".to_string()],
+                proc_macro: false,
+                no_std: true,
+                build_script: None,
+            }
+        );
+
+        // Unavailable dependencies only have what Cargo.lock records
+        let missing = dependency_metadata(
+            &Dependency::new("missing", "0.1.0"),
+            temp_dir.path(),
+            &mut manifests,
+        );
+        assert!(!missing.available);
+        assert_eq!(missing.license, None);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,295 @@
+//! Selection of the files that make up a crate's published package.
+//!
+//! A crate's manifest can narrow down what gets published with the `package.include`
+//! and `package.exclude` glob lists. Registry extracts already contain only the
+//! published files, but path and vendored dependencies contain the whole source tree.
+//! A [`FileFilter`] built from the manifest lets generation mirror the published
+//! surface in both cases.
+
+use crate::ParsedManifest;
+use anyhow::{Context, Result};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Extensions of the files codebank can document
+const SOURCE_EXTENSIONS: &[&str] = &[
+    "rs", "py", "ts", "tsx", "js", "jsx", "c", "h", "cpp", "hpp", "go",
+];
+
+/// Decides which files of a crate are part of its package.
+///
+/// Following cargo, an `include` list takes precedence: when present, only matching
+/// files are part of the package and `exclude` is ignored. Otherwise every file not
+/// matching `exclude` is. The manifest itself is always part of the package.
+#[derive(Debug, Clone, Default)]
+pub struct FileFilter {
+    include: Option<GlobSet>,
+    exclude: Option<GlobSet>,
+}
+
+impl FileFilter {
+    /// Returns true if the filter keeps every file
+    pub fn is_empty(&self) -> bool {
+        self.include.is_none() && self.exclude.is_none()
+    }
+
+    /// Checks whether a file, relative to the crate root, is part of the package
+    pub fn matches(&self, relative_path: &Path) -> bool {
+        if relative_path == Path::new("Cargo.toml") {
+            return true;
+        }
+
+        match (&self.include, &self.exclude) {
+            (Some(include), _) => include.is_match(relative_path),
+            (None, Some(exclude)) => !exclude.is_match(relative_path),
+            (None, None) => true,
+        }
+    }
+}
+
+/// Builds the file filter described by a crate's `package.include` and
+/// `package.exclude` settings.
+///
+/// Patterns use cargo's gitignore-like syntax: a pattern without a slash matches at
+/// any depth, a leading slash anchors it to the crate root, and a pattern naming a
+/// directory matches everything below it. A missing manifest, or one without these
+/// settings, yields a filter that keeps every file.
+///
+/// # Arguments
+///
+/// * `manifest` - Path to the crate's Cargo.toml
+///
+/// # Returns
+///
+/// * `Result<FileFilter>` - The filter for the crate's files
+///
+/// # Errors
+///
+/// Returns an error if the manifest cannot be parsed or contains an invalid pattern
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use depbank::package_file_filter;
+/// use std::path::Path;
+///
+/// let filter = package_file_filter(Path::new("vendor/mylib/Cargo.toml")).unwrap();
+/// if !filter.matches(Path::new("src/internal/mod.rs")) {
+///     println!("not published");
+/// }
+/// ```
+pub fn package_file_filter(manifest: &Path) -> Result<FileFilter> {
+    manifest_file_filter(&ParsedManifest::read_file(manifest)?)
+}
+
+/// Builds the file filter described by an already parsed manifest
+pub(crate) fn manifest_file_filter(manifest: &ParsedManifest) -> Result<FileFilter> {
+    let patterns = |key: &str| -> Option<Vec<String>> {
+        manifest.package_field(key)?.as_array().map(|patterns| {
+            patterns
+                .iter()
+                .filter_map(|pattern| pattern.as_str().map(str::to_string))
+                .collect()
+        })
+    };
+
+    Ok(FileFilter {
+        include: patterns("include")
+            .map(|patterns| build_glob_set(&patterns))
+            .transpose()?,
+        exclude: patterns("exclude")
+            .map(|patterns| build_glob_set(&patterns))
+            .transpose()?,
+    })
+}
+
+/// The source files of a crate selected by [`filtered_sources`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct FilteredSources {
+    /// Sorted source file paths, relative to the crate directory
+    pub sources: Vec<PathBuf>,
+    /// Whether the filter or the extensions left out files that codebank would
+    /// otherwise document. When not, the crate can be documented in place
+    pub filtered_out: bool,
+}
+
+/// Lists the source files of a crate that pass a filter.
+///
+/// Directories in `ignore_dirs` are skipped, as codebank would skip them. Only files
+/// with one of `extensions` are listed, or with any extension codebank documents if
+/// `extensions` is empty.
+///
+/// # Returns
+///
+/// * `Result<FilteredSources>` - The listed files, and whether any file codebank
+///   documents was left out
+pub(crate) fn filtered_sources(
+    crate_dir: &Path,
+    filter: &FileFilter,
+    ignore_dirs: &[String],
+    extensions: &[String],
+) -> Result<FilteredSources> {
+    let mut selected = FilteredSources::default();
+    collect_sources(
+        crate_dir,
+        crate_dir,
+        filter,
+        ignore_dirs,
+        extensions,
+        &mut selected,
+    )?;
+    selected.sources.sort();
+
+    Ok(selected)
+}
+
+fn collect_sources(
+    crate_dir: &Path,
+    dir: &Path,
+    filter: &FileFilter,
+    ignore_dirs: &[String],
+    extensions: &[String],
+    selected: &mut FilteredSources,
+) -> Result<()> {
+    for entry in
+        fs::read_dir(dir).with_context(|| format!("Failed to read directory: {}", dir.display()))?
+    {
+        let entry = entry
+            .with_context(|| format!("Failed to read directory entry in {}", dir.display()))?;
+        let path = entry.path();
+        let Ok(relative) = path.strip_prefix(crate_dir) else {
+            continue;
+        };
+
+        if path.is_dir() {
+            let ignored = path
+                .file_name()
+                .is_some_and(|name| ignore_dirs.iter().any(|dir| name == dir.as_str()));
+            if !ignored {
+                collect_sources(crate_dir, &path, filter, ignore_dirs, extensions, selected)?;
+            }
+        } else {
+            let Some(ext) = path.extension() else {
+                continue;
+            };
+            let documented = SOURCE_EXTENSIONS.iter().any(|source| ext == *source);
+            let listed = if extensions.is_empty() {
+                documented
+            } else {
+                extensions.iter().any(|extension| ext == extension.as_str())
+            };
+            if listed && filter.matches(relative) {
+                selected.sources.push(relative.to_path_buf());
+            } else if documented {
+                selected.filtered_out = true;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Compiles cargo-style package patterns into a glob set
+fn build_glob_set(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+
+    for pattern in patterns {
+        let anchored = pattern.starts_with('/');
+        let pattern = pattern.trim_start_matches('/').trim_end_matches('/');
+        let pattern = if anchored || pattern.contains('/') {
+            pattern.to_string()
+        } else {
+            format!("**/{}", pattern)
+        };
+
+        // A pattern may name a directory, which covers everything below it
+        for glob in [pattern.clone(), format!("{}/**", pattern)] {
+            builder.add(
+                Glob::new(&glob)
+                    .with_context(|| format!("Invalid package pattern: {}", pattern))?,
+            );
+        }
+    }
+
+    builder.build().context("Failed to build package patterns")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn filter_for(package: &str) -> Result<FileFilter> {
+        let temp_dir = tempdir()?;
+        let manifest = temp_dir.path().join("Cargo.toml");
+        fs::write(
+            &manifest,
+            format!(
+                "[package]\nname = \"demo\"\nversion = \"0.1.0\"\n{}",
+                package
+            ),
+        )?;
+        package_file_filter(&manifest)
+    }
+
+    #[test]
+    fn test_package_file_filter_exclude() -> Result<()> {
+        let filter = filter_for("exclude = [\"src/internal/\", \"*.snap\", \"/benches\"]\n")?;
+
+        assert!(filter.matches(Path::new("src/lib.rs")));
+        assert!(!filter.matches(Path::new("src/internal/mod.rs")));
+        assert!(!filter.matches(Path::new("src/tests/output.snap")));
+        assert!(!filter.matches(Path::new("benches/bench.rs")));
+        assert!(filter.matches(Path::new("src/benches/mod.rs")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_package_file_filter_include_wins() -> Result<()> {
+        let filter = filter_for("include = [\"src/**/*.rs\"]\nexclude = [\"src/lib.rs\"]\n")?;
+
+        assert!(filter.matches(Path::new("src/lib.rs")));
+        assert!(filter.matches(Path::new("Cargo.toml")));
+        assert!(!filter.matches(Path::new("build.rs")));
+
+        // Without include/exclude nothing is filtered
+        assert!(filter_for("")?.is_empty());
+        assert!(package_file_filter(Path::new("/nonexistent/Cargo.toml"))?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_filtered_sources_reports_filtered_out_files() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let crate_dir = temp_dir.path();
+        fs::create_dir_all(crate_dir.join("src").join("internal"))?;
+        fs::write(crate_dir.join("src").join("lib.rs"), "")?;
+        fs::write(crate_dir.join("src").join("internal").join("mod.rs"), "")?;
+        fs::write(crate_dir.join("data.json"), "{}")?;
+        let rs = ["rs".to_string()];
+
+        // Files codebank does not document anyway need no filtering
+        let selected = filtered_sources(crate_dir, &FileFilter::default(), &[], &rs)?;
+        assert_eq!(
+            selected.sources,
+            [
+                PathBuf::from("src/internal/mod.rs"),
+                PathBuf::from("src/lib.rs")
+            ]
+        );
+        assert!(!selected.filtered_out);
+
+        let filter = filter_for("exclude = [\"src/internal/\"]\n")?;
+        let selected = filtered_sources(crate_dir, &filter, &[], &rs)?;
+        assert_eq!(selected.sources, [PathBuf::from("src/lib.rs")]);
+        assert!(selected.filtered_out);
+
+        fs::write(crate_dir.join("src").join("bindings.py"), "")?;
+        assert!(filtered_sources(crate_dir, &FileFilter::default(), &[], &rs)?.filtered_out);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,139 @@
+//! The thread pool shared by code bank generation and tokenization.
+//!
+//! Both stages run their work on the same rayon pool, so enabling them together
+//! never uses more threads than configured. The pool is sized to the number of
+//! logical CPUs unless [`set_thread_pool`] is called first.
+
+use anyhow::{Context, Result};
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use std::sync::{Arc, RwLock};
+
+static THREAD_POOL: RwLock<Option<Arc<ThreadPool>>> = RwLock::new(None);
+
+/// Configures the thread pool used for generation and tokenization.
+///
+/// This is typically called once at startup. Work that is already running keeps using
+/// the previous pool; everything started afterwards uses the new one.
+///
+/// # Arguments
+///
+/// * `jobs` - Number of worker threads, or `0` to use the number of logical CPUs
+///
+/// # Returns
+///
+/// * `Result<()>` - Success or an error if the pool cannot be built
+///
+/// # Examples
+///
+/// ```rust
+/// use depbank::set_thread_pool;
+///
+/// // Generate and tokenize with at most four threads
+/// set_thread_pool(4).unwrap();
+/// ```
+pub fn set_thread_pool(jobs: usize) -> Result<()> {
+    let pool = build_thread_pool(jobs)?;
+    *THREAD_POOL.write().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(pool));
+    Ok(())
+}
+
+/// Runs `op` on the shared thread pool, creating the default pool if needed.
+pub(crate) fn install<OP, R>(op: OP) -> Result<R>
+where
+    OP: FnOnce() -> R + Send,
+    R: Send,
+{
+    Ok(thread_pool()?.install(op))
+}
+
+fn thread_pool() -> Result<Arc<ThreadPool>> {
+    if let Some(pool) = THREAD_POOL
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_ref()
+    {
+        return Ok(pool.clone());
+    }
+
+    let mut guard = THREAD_POOL.write().unwrap_or_else(|e| e.into_inner());
+    match guard.as_ref() {
+        Some(pool) => Ok(pool.clone()),
+        None => {
+            let pool = Arc::new(build_thread_pool(0)?);
+            *guard = Some(pool.clone());
+            Ok(pool)
+        }
+    }
+}
+
+fn build_thread_pool(jobs: usize) -> Result<ThreadPool> {
+    ThreadPoolBuilder::new()
+        .num_threads(jobs)
+        .thread_name(|index| format!("depbank-worker-{}", index))
+        .build()
+        .context("Failed to build thread pool")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Dependency, DependencyCollection, GenerateOptions, PostProcessHook, generate_all_code_banks,
+    };
+    use std::fs;
+    use std::sync::Mutex;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_build_thread_pool() -> Result<()> {
+        // Built without installing it, so other tests keep the shared pool
+        let pool = build_thread_pool(2)?;
+        assert_eq!(pool.install(rayon::current_num_threads), 2);
+        let name = pool.install(|| std::thread::current().name().map(str::to_string));
+        assert_eq!(
+            name.as_deref()
+                .map(|name| name.starts_with("depbank-worker-")),
+            Some(true)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_generation_runs_on_configured_pool() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let registry_dir = temp_dir.path().join("registry");
+        let mut dependencies = DependencyCollection::new();
+        for name in ["alpha", "beta", "gamma"] {
+            let crate_dir = registry_dir.join(format!("{}-1.0.0", name));
+            fs::create_dir_all(&crate_dir)?;
+            fs::write(crate_dir.join("lib.rs"), format!("pub fn {name}() {{}}\n"))?;
+            dependencies.add(Dependency::new(name, "1.0.0"));
+        }
+
+        // Other tests running meanwhile share the pool too, which is harmless
+        set_thread_pool(2)?;
+
+        // The hook runs inside the generation of each bank
+        let pool_sizes = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&pool_sizes);
+        let options = GenerateOptions {
+            post_process: Some(PostProcessHook::new(move |_, content| {
+                seen.lock().unwrap().push(rayon::current_num_threads());
+                Ok(content)
+            })),
+            ..Default::default()
+        };
+        let files = generate_all_code_banks(
+            &dependencies,
+            &registry_dir,
+            &temp_dir.path().join("output"),
+            &options,
+        )?;
+
+        assert_eq!(files.len(), 3);
+        assert_eq!(*pool_sizes.lock().unwrap(), [2, 2, 2]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,353 @@
+//! Support for `[patch]` sections of project manifests.
+//!
+//! A `[patch.crates-io]` entry replaces a registry crate with a local directory or a
+//! git checkout for the whole dependency graph, so the registry copy of the crate is
+//! not the code the project builds against. Patched crates are located at their patch
+//! source and given a `path+file://` source, which makes generation read them from
+//! there (see [`Dependency::local_source_path`](crate::Dependency::local_source_path)).
+
+use crate::{
+    DependencyCollection, DiscoveryOptions, Warning, WarningKind, cargo_home, emit_warning,
+    find_cargo_toml_files,
+};
+use anyhow::{Context, Result};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Prefix of the sources of dependencies located in a local directory
+pub const PATH_SOURCE_PREFIX: &str = "path+file://";
+
+/// Where a `[patch]` entry redirects a crate to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatchSource {
+    /// A local directory, resolved relative to the declaring manifest
+    Path(PathBuf),
+    /// A git repository, checked out by cargo under `~/.cargo/git/checkouts`
+    Git {
+        /// The repository URL
+        url: String,
+        /// The `rev`, `tag` or `branch` of the entry, if any
+        reference: Option<String>,
+    },
+}
+
+/// Parses the `[patch.<registry>]` tables of a Cargo.toml.
+///
+/// Entries of all patched registries are returned, keyed by the name of the patched
+/// crate, which is the `package` of the entry when it is renamed. Paths are resolved
+/// relative to the manifest's directory. Entries that are neither `path` nor `git`
+/// patches are skipped.
+///
+/// # Arguments
+///
+/// * `cargo_toml` - Path to the Cargo.toml file
+///
+/// # Returns
+///
+/// * `Result<HashMap<String, PatchSource>>` - The patch source of each patched crate
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or is not valid TOML
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use depbank::{PatchSource, parse_patches};
+/// use std::path::Path;
+///
+/// for (name, patch) in parse_patches(Path::new("Cargo.toml")).unwrap() {
+///     if let PatchSource::Path(path) = patch {
+///         println!("{} is patched to {}", name, path.display());
+///     }
+/// }
+/// ```
+pub fn parse_patches(cargo_toml: &Path) -> Result<HashMap<String, PatchSource>> {
+    let content = fs::read_to_string(cargo_toml)
+        .with_context(|| format!("Failed to read Cargo.toml file: {}", cargo_toml.display()))?;
+    let manifest: toml::Value = toml::from_str(&content)
+        .with_context(|| format!("Failed to parse Cargo.toml file: {}", cargo_toml.display()))?;
+    let manifest_dir = cargo_toml.parent().unwrap_or(Path::new("."));
+
+    let mut patches = HashMap::new();
+    let registries = manifest
+        .get("patch")
+        .and_then(|patch| patch.as_table())
+        .into_iter()
+        .flat_map(|registries| registries.values())
+        .filter_map(|entries| entries.as_table());
+
+    for entries in registries {
+        for (name, entry) in entries {
+            let string = |key: &str| entry.get(key).and_then(|value| value.as_str());
+            let source = if let Some(path) = string("path") {
+                PatchSource::Path(manifest_dir.join(path))
+            } else if let Some(url) = string("git") {
+                PatchSource::Git {
+                    url: url.to_string(),
+                    reference: string("rev")
+                        .or_else(|| string("tag"))
+                        .or_else(|| string("branch"))
+                        .map(str::to_string),
+                }
+            } else {
+                continue;
+            };
+            let name = string("package").unwrap_or(name);
+            patches.insert(name.to_string(), source);
+        }
+    }
+
+    Ok(patches)
+}
+
+/// Finds the directory holding a patched crate's sources.
+///
+/// Path patches point at the crate directory. For git patches, the most recently
+/// updated checkout of the repository is searched for the crate, preferring the
+/// checkout of the entry's `rev`, so the repository must have been fetched by cargo.
+///
+/// # Arguments
+///
+/// * `crate_name` - The name of the patched crate
+/// * `patch` - The patch source of the crate
+///
+/// # Returns
+///
+/// * `Option<PathBuf>` - The crate's source directory, if it exists locally
+pub fn locate_patch_source(crate_name: &str, patch: &PatchSource) -> Option<PathBuf> {
+    match patch {
+        PatchSource::Path(path) => fs::canonicalize(path).ok().filter(|path| path.is_dir()),
+        PatchSource::Git { url, reference } => {
+            let checkouts_dir = cargo_home().ok()?.join("git").join("checkouts");
+            locate_git_checkout(&checkouts_dir, url, reference.as_deref())
+                .and_then(|checkout| find_crate_in(&checkout, crate_name))
+        }
+    }
+}
+
+/// Points the patched dependencies at their patch sources.
+///
+/// Dependencies with a patch whose sources are found locally get a `path+file://`
+/// source; the others are returned unchanged, with a warning for patches that cannot
+/// be located.
+///
+/// # Arguments
+///
+/// * `dependencies` - The resolved dependencies
+/// * `patches` - The patch sources, as returned by [`parse_patches`]
+///
+/// # Returns
+///
+/// * `DependencyCollection` - The dependencies with patched sources
+pub fn apply_patches(
+    dependencies: &DependencyCollection,
+    patches: &HashMap<String, PatchSource>,
+) -> DependencyCollection {
+    let mut patched = DependencyCollection::new();
+
+    for dep in dependencies.iter() {
+        let dep = match patches.get(&dep.name) {
+            Some(patch) => match locate_patch_source(&dep.name, patch) {
+                Some(path) => {
+                    dep.clone()
+                        .with_source(format!("{}{}", PATH_SOURCE_PREFIX, path.display()))
+                }
+                None => {
+                    emit_warning(Warning::new(
+                        WarningKind::PatchNotFound,
+                        Some(&dep.name),
+                        format!(
+                            "Patch source of {} not found locally, using the registry",
+                            dep.name
+                        ),
+                    ));
+                    dep.clone()
+                }
+            },
+            None => dep.clone(),
+        };
+        patched.add(dep);
+    }
+
+    patched
+}
+
+/// Finds the checkout of a git repository, `<checkouts>/<repo>-<hash>/<short rev>`
+fn locate_git_checkout(
+    checkouts_dir: &Path,
+    url: &str,
+    reference: Option<&str>,
+) -> Option<PathBuf> {
+    let repo_name = url
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()?
+        .trim_end_matches(".git");
+    let prefix = format!("{}-", repo_name);
+
+    let mut checkouts: Vec<(PathBuf, SystemTime)> = fs::read_dir(checkouts_dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with(&prefix))
+        .filter_map(|entry| fs::read_dir(entry.path()).ok())
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .filter_map(|path| {
+            let modified = fs::metadata(&path).and_then(|meta| meta.modified()).ok()?;
+            Some((path, modified))
+        })
+        .collect();
+    checkouts.sort_by_key(|(_, modified)| Reverse(*modified));
+
+    let is_reference = |path: &Path| {
+        reference.is_some_and(|reference| {
+            path.file_name()
+                .is_some_and(|rev| reference.starts_with(&*rev.to_string_lossy()))
+        })
+    };
+    checkouts
+        .iter()
+        .find(|(path, _)| is_reference(path))
+        .or_else(|| checkouts.first())
+        .map(|(path, _)| path.clone())
+}
+
+/// Finds the directory of a package within a checkout, which may be a workspace
+fn find_crate_in(checkout: &Path, crate_name: &str) -> Option<PathBuf> {
+    find_cargo_toml_files(checkout, &DiscoveryOptions::default())
+        .ok()?
+        .into_iter()
+        .find(|cargo_toml| package_name(cargo_toml).as_deref() == Some(crate_name))
+        .and_then(|cargo_toml| cargo_toml.parent().map(Path::to_path_buf))
+}
+
+fn package_name(cargo_toml: &Path) -> Option<String> {
+    let manifest: toml::Value = toml::from_str(&fs::read_to_string(cargo_toml).ok()?).ok()?;
+    Some(manifest.get("package")?.get("name")?.as_str()?.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Dependency;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_parse_and_apply_path_patch() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let project_dir = temp_dir.path().join("project");
+        let serde_dir = temp_dir.path().join("serde");
+        fs::create_dir_all(&project_dir)?;
+        fs::create_dir_all(serde_dir.join("src"))?;
+        let cargo_toml = project_dir.join("Cargo.toml");
+        fs::write(
+            &cargo_toml,
+            r#"[package]
+name = "project"
+version = "0.1.0"
+
+[dependencies]
+serde = "1.0"
+log = "0.4"
+
+[patch.crates-io]
+serde = { path = "../serde" }
+my-log = { git = "https://github.com/rust-lang/log", rev = "abc1234", package = "log" }
+"#,
+        )?;
+
+        let patches = parse_patches(&cargo_toml)?;
+        assert_eq!(
+            patches["serde"],
+            PatchSource::Path(project_dir.join("../serde"))
+        );
+        assert_eq!(
+            patches["log"],
+            PatchSource::Git {
+                url: "https://github.com/rust-lang/log".to_string(),
+                reference: Some("abc1234".to_string()),
+            }
+        );
+
+        let mut dependencies = DependencyCollection::new();
+        dependencies.add(Dependency::new("serde", "1.0.219"));
+        dependencies.add(Dependency::new("itoa", "1.0.15"));
+        let patched = apply_patches(&dependencies, &patches);
+
+        // The patched crate is generated from the local path, not the registry
+        let serde = patched.iter().find(|dep| dep.name == "serde").unwrap();
+        assert_eq!(
+            serde.local_source_path(),
+            Some(fs::canonicalize(&serde_dir)?)
+        );
+        assert_eq!(
+            serde.get_registry_path(Path::new("/registry")),
+            fs::canonicalize(&serde_dir)?
+        );
+
+        let itoa = patched.iter().find(|dep| dep.name == "itoa").unwrap();
+        assert_eq!(itoa.local_source_path(), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_locate_git_checkout() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let old = temp_dir.path().join("log-1a2b3c4d5e6f7a8b").join("abc1234");
+        let new = temp_dir.path().join("log-1a2b3c4d5e6f7a8b").join("def5678");
+        fs::create_dir_all(&old)?;
+        fs::create_dir_all(&new)?;
+        fs::create_dir_all(
+            temp_dir
+                .path()
+                .join("other-1a2b3c4d5e6f7a8b")
+                .join("0000000"),
+        )?;
+
+        assert_eq!(
+            locate_git_checkout(
+                temp_dir.path(),
+                "https://github.com/rust-lang/log.git",
+                Some("abc1234ffff")
+            ),
+            Some(old.clone())
+        );
+        assert!(
+            locate_git_checkout(temp_dir.path(), "https://github.com/rust-lang/log", None)
+                .is_some()
+        );
+        assert_eq!(
+            locate_git_checkout(
+                temp_dir.path(),
+                "https://github.com/rust-lang/missing",
+                None
+            ),
+            None
+        );
+
+        // A workspace checkout holds the crate in a member directory
+        fs::create_dir_all(old.join("log-derive"))?;
+        fs::write(
+            old.join("Cargo.toml"),
+            "[workspace]\nmembers = [\"log-derive\"]\n",
+        )?;
+        fs::write(
+            old.join("log-derive").join("Cargo.toml"),
+            "[package]\nname = \"log-derive\"\n",
+        )?;
+        assert_eq!(
+            find_crate_in(&old, "log-derive"),
+            Some(old.join("log-derive"))
+        );
+        assert_eq!(find_crate_in(&old, "log"), None);
+
+        Ok(())
+    }
+}
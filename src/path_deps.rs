@@ -0,0 +1,219 @@
+//! Discovery of local crates through path dependencies.
+//!
+//! A project can depend on crates outside its directory tree, such as
+//! `shared = { path = "../shared" }`, whose manifests searching the project
+//! directory does not find. They are found by following the `path` of dependencies,
+//! and of their dependencies in turn. A misconfigured project can have crates
+//! depending on each other in a cycle, which is reported as a
+//! [`DepBankError::DependencyCycle`] instead of being followed forever.
+//!
+//! Only normal and build dependencies are followed: cargo allows cycles through
+//! dev-dependencies, e.g. for a crate testing itself with a helper that depends on it.
+
+use crate::{DepBankError, ParsedManifest};
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// The dependency tables whose path dependencies are followed
+const FOLLOWED_TABLES: &[&str] = &["dependencies", "build-dependencies"];
+
+/// Adds the manifests of the crates reached through path dependencies.
+///
+/// # Arguments
+///
+/// * `cargo_toml_files` - The Cargo.toml files found in the project
+///
+/// # Returns
+///
+/// * `Result<Vec<PathBuf>>` - The given manifests, followed by the manifests of the
+///   crates they reach through path dependencies, each listed once
+///
+/// # Errors
+///
+/// Returns a [`DepBankError::DependencyCycle`] if path dependencies form a cycle, or
+/// an error if a manifest cannot be read or parsed
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use depbank::{DiscoveryOptions, find_cargo_toml_files, follow_path_dependencies};
+///
+/// let cargo_toml_files = find_cargo_toml_files(".", &DiscoveryOptions::default()).unwrap();
+/// let all_manifests = follow_path_dependencies(&cargo_toml_files).unwrap();
+/// println!("{} manifests outside the project", all_manifests.len() - cargo_toml_files.len());
+/// ```
+pub fn follow_path_dependencies(cargo_toml_files: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut walk = PathDependencyWalk::default();
+    for cargo_toml in cargo_toml_files {
+        walk.visit(cargo_toml)?;
+    }
+
+    // The given manifests keep their order, wherever the walk reached them
+    let given: HashSet<PathBuf> = cargo_toml_files
+        .iter()
+        .map(|path| canonical(path))
+        .collect::<Result<_>>()?;
+    let mut manifests = cargo_toml_files.to_vec();
+    manifests.extend(
+        walk.found
+            .into_iter()
+            .filter(|manifest| !given.contains(manifest)),
+    );
+
+    Ok(manifests)
+}
+
+/// A depth-first walk of path dependencies
+#[derive(Default)]
+struct PathDependencyWalk {
+    /// Manifests whose walk started, by canonical path
+    visited: HashSet<PathBuf>,
+    /// The crates being walked, from the first one to the current one, with their
+    /// canonical manifest paths
+    stack: Vec<(PathBuf, String)>,
+    /// Every manifest reached, by canonical path, in the order they were reached
+    found: Vec<PathBuf>,
+}
+
+impl PathDependencyWalk {
+    fn visit(&mut self, cargo_toml: &Path) -> Result<()> {
+        let path = canonical(cargo_toml)?;
+
+        // Reaching a crate still being walked closes a cycle
+        if let Some(start) = self.stack.iter().position(|(walked, _)| *walked == path) {
+            let mut cycle: Vec<String> = self.stack[start..]
+                .iter()
+                .map(|(_, name)| name.clone())
+                .collect();
+            cycle.push(self.stack[start].1.clone());
+            return Err(DepBankError::DependencyCycle { cycle }.into());
+        }
+        if !self.visited.insert(path.clone()) {
+            return Ok(());
+        }
+
+        let manifest = ParsedManifest::read_file(cargo_toml)?;
+        let crate_dir = cargo_toml.parent().unwrap_or_else(|| Path::new("."));
+        let name = manifest
+            .package_field("name")
+            .and_then(|name| name.as_str())
+            .map(str::to_string)
+            .unwrap_or_else(|| crate_dir.display().to_string());
+
+        // Paths like `app/../shared` are listed in their canonical form
+        self.found.push(path.clone());
+        self.stack.push((path, name));
+        for dependency_dir in path_dependency_dirs(&manifest) {
+            let dependency_manifest = crate_dir.join(dependency_dir).join("Cargo.toml");
+            // Cargo reports missing path dependencies better than we could
+            if dependency_manifest.is_file() {
+                self.visit(&dependency_manifest)?;
+            }
+        }
+        self.stack.pop();
+
+        Ok(())
+    }
+}
+
+/// The `path` of the normal and build dependencies of a manifest, including
+/// target-specific ones, sorted
+fn path_dependency_dirs(manifest: &ParsedManifest) -> Vec<PathBuf> {
+    let targets = manifest
+        .section("target")
+        .and_then(|targets| targets.as_table())
+        .into_iter()
+        .flat_map(|targets| targets.values());
+    let tables = FOLLOWED_TABLES
+        .iter()
+        .filter_map(|table| manifest.section(table))
+        .chain(targets.flat_map(|target| {
+            FOLLOWED_TABLES
+                .iter()
+                .filter_map(move |table| target.get(table))
+        }));
+
+    let mut dirs: Vec<PathBuf> = tables
+        .filter_map(|table| table.as_table())
+        .flat_map(|table| table.values())
+        .filter_map(|spec| spec.get("path")?.as_str().map(PathBuf::from))
+        .collect();
+    dirs.sort();
+    dirs.dedup();
+    dirs
+}
+
+fn canonical(path: &Path) -> Result<PathBuf> {
+    path.canonicalize()
+        .with_context(|| format!("Failed to resolve manifest path: {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn write_crate(dir: &Path, name: &str, dependencies: &str) -> Result<PathBuf> {
+        fs::create_dir_all(dir)?;
+        let manifest = dir.join("Cargo.toml");
+        fs::write(
+            &manifest,
+            format!(
+                "[package]\nname = \"{}\"\nversion = \"0.1.0\"\n\n{}",
+                name, dependencies
+            ),
+        )?;
+        Ok(manifest)
+    }
+
+    #[test]
+    fn test_follow_path_dependencies() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let app = write_crate(
+            &temp_dir.path().join("app"),
+            "app",
+            "[dependencies]\nshared = { path = \"../shared\" }\nserde = \"1\"\n\n\
+[dev-dependencies]\ntesting = { path = \"../testing\" }\n",
+        )?;
+        let shared = write_crate(
+            &temp_dir.path().join("shared"),
+            "shared",
+            "[target.'cfg(unix)'.build-dependencies]\nutil = { path = \"../util\" }\n",
+        )?;
+        write_crate(&temp_dir.path().join("util"), "util", "")?;
+        // Cycles through dev-dependencies are allowed, and not followed
+        write_crate(
+            &temp_dir.path().join("testing"),
+            "testing",
+            "[dependencies]\napp = { path = \"../app\" }\n",
+        )?;
+
+        let manifests = follow_path_dependencies(std::slice::from_ref(&app))?;
+
+        let util = temp_dir.path().join("util").join("Cargo.toml");
+        assert_eq!(
+            manifests,
+            [app, shared.canonicalize()?, util.canonicalize()?]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_follow_path_dependencies_cycle() {
+        let alpha = PathBuf::from("fixtures/path_cycle_project/alpha/Cargo.toml");
+
+        let error = follow_path_dependencies(&[alpha]).unwrap_err();
+
+        assert!(matches!(
+            error.downcast_ref::<DepBankError>(),
+            Some(DepBankError::DependencyCycle { cycle }) if cycle == &["alpha", "beta", "alpha"]
+        ));
+        assert_eq!(
+            error.to_string(),
+            "dependency cycle: alpha -> beta -> alpha"
+        );
+    }
+}
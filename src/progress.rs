@@ -0,0 +1,204 @@
+//! An append-only log of completed code bank generations.
+//!
+//! Generating banks for a large dependency set can take a long time. Every bank that
+//! is finished is appended to `.progress.jsonl` in the output directory and flushed
+//! immediately, so an interrupted run can later be resumed without redoing the
+//! dependencies that already completed. Each entry records the fingerprint of the
+//! options its bank was generated with, so banks generated with other options are
+//! never reused.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Name of the progress log file inside the output directory
+pub const PROGRESS_LOG_FILE: &str = ".progress.jsonl";
+
+/// A dependency whose code bank was generated successfully
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProgressEntry {
+    /// The name of the dependency
+    pub name: String,
+    /// The version of the dependency
+    pub version: String,
+    /// Path to the generated code bank file
+    pub path: PathBuf,
+    /// Fingerprint of the options the bank was generated with, see
+    /// [`options_fingerprint`](crate::options_fingerprint); empty in logs written
+    /// before it was recorded
+    #[serde(default)]
+    pub options: String,
+}
+
+/// Writer for the progress log, safe to share between generation threads.
+#[derive(Debug)]
+pub struct ProgressLog {
+    file: Mutex<File>,
+}
+
+impl ProgressLog {
+    /// Opens the progress log in the output directory.
+    ///
+    /// When `resume` is false, any existing log is discarded and a new run starts.
+    /// Otherwise new entries are appended to the existing log.
+    ///
+    /// # Arguments
+    ///
+    /// * `output_dir` - The output directory for code bank files
+    /// * `resume` - Whether to keep the entries of a previous run
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self>` - The opened progress log
+    pub fn open(output_dir: &Path, resume: bool) -> Result<Self> {
+        fs::create_dir_all(output_dir).with_context(|| {
+            format!(
+                "Failed to create output directory: {}",
+                output_dir.display()
+            )
+        })?;
+
+        let path = output_dir.join(PROGRESS_LOG_FILE);
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(resume)
+            .write(true)
+            .truncate(!resume)
+            .open(&path)
+            .with_context(|| format!("Failed to open progress log: {}", path.display()))?;
+
+        // Terminate a line left incomplete by an interrupted run, so that new entries
+        // start on a line of their own
+        if resume && file.metadata()?.len() > 0 && !fs::read(&path)?.ends_with(b"\n") {
+            writeln!(file).context("Failed to write progress log")?;
+        }
+
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Appends an entry to the log and flushes it to disk.
+    ///
+    /// # Arguments
+    ///
+    /// * `entry` - The completed dependency to record
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Success or an error if the entry cannot be written
+    pub fn record(&self, entry: &ProgressEntry) -> Result<()> {
+        let line = serde_json::to_string(entry).context("Failed to serialize progress entry")?;
+
+        // Write each entry as a single line so concurrent writers never interleave
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        writeln!(file, "{}", line).context("Failed to write progress log")?;
+        file.flush().context("Failed to flush progress log")?;
+        file.sync_data().context("Failed to sync progress log")?;
+
+        Ok(())
+    }
+}
+
+/// Removes the progress log of an output directory, once a run has completed and
+/// there is nothing left to resume. A missing log is not an error.
+///
+/// # Arguments
+///
+/// * `output_dir` - The output directory for code bank files
+///
+/// # Returns
+///
+/// * `Result<()>` - Success or an error if the log exists but cannot be removed
+pub fn remove_progress_log(output_dir: &Path) -> Result<()> {
+    let path = output_dir.join(PROGRESS_LOG_FILE);
+    match fs::remove_file(&path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(e).with_context(|| format!("Failed to remove progress log: {}", path.display()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Reads the entries recorded in the progress log of an output directory.
+///
+/// A missing log yields no entries. Lines that cannot be parsed, such as a partially
+/// written last line from a run that was killed, are skipped.
+///
+/// # Arguments
+///
+/// * `output_dir` - The output directory for code bank files
+///
+/// # Returns
+///
+/// * `Result<Vec<ProgressEntry>>` - The completed dependencies, in the order they were recorded
+///
+/// # Errors
+///
+/// Returns an error if the log exists but cannot be read
+pub fn read_progress_log(output_dir: &Path) -> Result<Vec<ProgressEntry>> {
+    let path = output_dir.join(PROGRESS_LOG_FILE);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read progress log: {}", path.display()))?;
+
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn entry(name: &str) -> ProgressEntry {
+        ProgressEntry {
+            name: name.to_string(),
+            version: "1.0.0".to_string(),
+            path: PathBuf::from(format!("{}.md", name)),
+            options: "0123456789abcdef".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_progress_log_round_trip() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let output_dir = temp_dir.path().join("output");
+        assert!(read_progress_log(&output_dir)?.is_empty());
+
+        let log = ProgressLog::open(&output_dir, false)?;
+        log.record(&entry("serde"))?;
+        drop(log);
+
+        // Resuming keeps existing entries, even after a partially written line
+        fs::OpenOptions::new()
+            .append(true)
+            .open(output_dir.join(PROGRESS_LOG_FILE))?
+            .write_all(b"{\"name\":\"tok")?;
+        let log = ProgressLog::open(&output_dir, true)?;
+        log.record(&entry("anyhow"))?;
+        assert_eq!(
+            read_progress_log(&output_dir)?,
+            vec![entry("serde"), entry("anyhow")]
+        );
+
+        // Starting over discards the previous run
+        ProgressLog::open(&output_dir, false)?;
+        assert!(read_progress_log(&output_dir)?.is_empty());
+
+        // A completed run removes its log, and removing it again is no error
+        remove_progress_log(&output_dir)?;
+        assert!(!output_dir.join(PROGRESS_LOG_FILE).exists());
+        remove_progress_log(&output_dir)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,718 @@
+//! Inspection of the crates extracted in cargo's registry.
+//!
+//! Cargo keeps the sources of every crate it ever downloaded, so the registry grows
+//! over time. Comparing its contents with the packages a project locks shows which
+//! extracted crates the project does not need, as candidates for a cleanup with tools
+//! such as `cargo cache`. Nothing here ever modifies the registry.
+//!
+//! The extracted crates can also stand in for Cargo.lock, for dependencies declared
+//! without a version that the lockfile does not resolve.
+
+use crate::conflicts::compare_versions;
+use crate::{
+    DepBankError, Dependency, DependencyCollection, ParsedManifest, Warning, WarningKind,
+    emit_warning, normalize_crate_name, registry_src_dir,
+};
+use anyhow::{Context, Result};
+use semver::{Version, VersionReq};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+/// The registry directories listed so far, with the modification time they had
+static REGISTRY_LISTINGS: Mutex<BTreeMap<PathBuf, (SystemTime, Arc<RegistryListing>)>> =
+    Mutex::new(BTreeMap::new());
+
+/// How to handle dependencies whose version cannot be resolved: those declared
+/// without a version requirement, as `*` or inherited from the workspace, that
+/// Cargo.lock does not list
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnresolvableVersionPolicy {
+    /// Skip them, with a warning
+    #[default]
+    Skip,
+    /// Use the highest version extracted in the registry, with a warning, see
+    /// [`best_available_matching`]
+    BestAvailable,
+    /// Fail with [`DepBankError::UnresolvableVersion`]
+    Error,
+}
+
+/// Lists the registry source directories, one per registry, e.g.
+/// `~/.cargo/registry/src/index.crates.io-1949cf8c6b5b557f`.
+///
+/// # Returns
+///
+/// * `Result<Vec<PathBuf>>` - The registry source directories, sorted by path
+///
+/// # Errors
+///
+/// Returns an error if the cargo registry directory does not exist or cannot be read
+pub fn registry_source_dirs() -> Result<Vec<PathBuf>> {
+    let registry_dir = registry_src_dir()?;
+    let mut dirs: Vec<PathBuf> = fs::read_dir(&registry_dir)
+        .with_context(|| {
+            format!(
+                "Failed to read cargo registry directory: {}",
+                registry_dir.display()
+            )
+        })?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect();
+    dirs.sort();
+    Ok(dirs)
+}
+
+/// Lists the crates extracted in a registry source directory.
+///
+/// Every `<name>-<version>` directory is a crate. Names may contain hyphens themselves,
+/// so the version starts after the first hyphen that is followed by a valid semver
+/// version. Directories that do not follow this pattern are skipped.
+///
+/// # Arguments
+///
+/// * `registry_path` - Path to a registry source directory
+///
+/// # Returns
+///
+/// * `Result<Vec<Dependency>>` - The extracted crates, sorted by name and version
+///
+/// # Errors
+///
+/// Returns an error if the directory cannot be read
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use depbank::{list_registry_crates, resolve_registry_path};
+///
+/// let registry_path = resolve_registry_path().unwrap();
+/// for krate in list_registry_crates(&registry_path).unwrap() {
+///     println!("{} {}", krate.name, krate.version);
+/// }
+/// ```
+pub fn list_registry_crates(registry_path: &Path) -> Result<Vec<Dependency>> {
+    Ok(RegistryListing::read(registry_path)?.crates)
+}
+
+/// The crate directories of a registry source directory
+#[derive(Debug, Default)]
+pub(crate) struct RegistryListing {
+    /// The extracted crates, sorted by name and version
+    crates: Vec<Dependency>,
+    /// The directories, by their name after normalization
+    dirs: HashMap<String, PathBuf>,
+}
+
+impl RegistryListing {
+    /// Reads the crate directories of a registry source directory
+    fn read(registry_path: &Path) -> Result<Self> {
+        let mut dirs: Vec<PathBuf> = fs::read_dir(registry_path)
+            .with_context(|| {
+                format!(
+                    "Failed to read registry directory: {}",
+                    registry_path.display()
+                )
+            })?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir())
+            .collect();
+        // Of directories only differing in casing or separators, the first one is used
+        dirs.sort();
+
+        let mut listing = Self::default();
+        for path in dirs {
+            let Some(dir_name) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            listing.crates.extend(parse_crate_dir_name(dir_name));
+            listing
+                .dirs
+                .entry(normalize_crate_name(dir_name))
+                .or_insert(path);
+        }
+        listing
+            .crates
+            .sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.version.cmp(&b.version)));
+        Ok(listing)
+    }
+
+    /// Finds the directory whose name matches `dir_name` after normalization
+    pub(crate) fn find_normalized(&self, dir_name: &str) -> Option<&Path> {
+        self.dirs
+            .get(&normalize_crate_name(dir_name))
+            .map(PathBuf::as_path)
+    }
+
+    /// The extracted versions of a crate, matched by name after normalization
+    fn versions_of(&self, name: &str) -> impl Iterator<Item = &Dependency> {
+        let name = normalize_crate_name(name);
+        self.crates
+            .iter()
+            .filter(move |krate| normalize_crate_name(&krate.name) == name)
+    }
+}
+
+/// Lists the crate directories of a registry source directory, reading it again only
+/// once it changed.
+///
+/// Looking up every dependency of a run in a registry holding thousands of crates
+/// would otherwise read the whole directory once per lookup. Extracting a crate
+/// changes the directory's modification time, so later runs in the same process,
+/// such as those of [`watch_project`](crate::watch_project), see new crates.
+pub(crate) fn registry_listing(registry_path: &Path) -> Option<Arc<RegistryListing>> {
+    let modified = fs::metadata(registry_path)
+        .and_then(|metadata| metadata.modified())
+        .ok()?;
+    let mut listings = REGISTRY_LISTINGS.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((listed_at, listing)) = listings.get(registry_path)
+        && *listed_at == modified
+    {
+        return Some(Arc::clone(listing));
+    }
+
+    let listing = Arc::new(RegistryListing::read(registry_path).ok()?);
+    listings.insert(
+        registry_path.to_path_buf(),
+        (modified, Arc::clone(&listing)),
+    );
+    Some(listing)
+}
+
+/// Finds the crates extracted in the registries that are not in a dependency set.
+///
+/// A registry crate is used when a dependency with the same name, after
+/// normalization, and the same version is declared. Pass all packages of a lockfile
+/// (see [`locked_packages`](crate::locked_packages)) to include transitive
+/// dependencies. The registries are only read, never modified.
+///
+/// # Arguments
+///
+/// * `declared` - The dependencies in use, with exact versions
+/// * `registry_paths` - The registry source directories to inspect
+///
+/// # Returns
+///
+/// * `Result<Vec<Dependency>>` - The unused crates, by registry and then by name
+///
+/// # Errors
+///
+/// Returns an error if a registry directory cannot be read
+pub fn unused_registry_crates(
+    declared: &DependencyCollection,
+    registry_paths: &[PathBuf],
+) -> Result<Vec<Dependency>> {
+    let used: HashSet<(String, &str)> = declared
+        .iter()
+        .map(|dep| (normalize_crate_name(&dep.name), dep.version.as_str()))
+        .collect();
+
+    let mut unused = Vec::new();
+    for registry_path in registry_paths {
+        for krate in list_registry_crates(registry_path)? {
+            if !used.contains(&(normalize_crate_name(&krate.name), krate.version.as_str())) {
+                unused.push(krate);
+            }
+        }
+    }
+    Ok(unused)
+}
+
+/// Finds the versions of a dependency available locally, when none of them is the
+/// version resolved from Cargo.lock.
+///
+/// When the dependency's sources are found, the version declared in their manifest is
+/// compared with the resolved one, which catches local registries and patch sources
+/// holding another version. Otherwise, the other versions of the crate extracted in
+/// the registry are reported, e.g. `1.0.4` when `1.0.5` is locked but not extracted.
+/// Either way, a code bank generated from local sources would not document the
+/// locked version.
+///
+/// # Arguments
+///
+/// * `dependency` - The dependency, with its version resolved from Cargo.lock
+/// * `registry_path` - Path to the registry source directory to look in
+///
+/// # Returns
+///
+/// * `Option<Vec<String>>` - The versions found locally, sorted, or `None` if the
+///   resolved version is available or no version of the crate is found at all
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use depbank::{Dependency, find_version_mismatch, resolve_registry_path};
+///
+/// let registry_path = resolve_registry_path().unwrap();
+/// let dependency = Dependency::new("serde", "1.0.219");
+/// if let Some(found) = find_version_mismatch(&dependency, &registry_path) {
+///     println!("serde 1.0.219 is locked, but only {} found", found.join(", "));
+/// }
+/// ```
+pub fn find_version_mismatch(dependency: &Dependency, registry_path: &Path) -> Option<Vec<String>> {
+    version_mismatch(
+        dependency,
+        registry_path,
+        registry_listing(registry_path).as_deref(),
+    )
+}
+
+/// Finds the dependencies of which only other versions are available locally.
+///
+/// This is [`find_version_mismatch`] for every dependency, listing the registry
+/// only once.
+///
+/// # Arguments
+///
+/// * `dependencies` - The dependencies, with their versions resolved from Cargo.lock
+/// * `registry_path` - Path to the registry source directory to look in
+///
+/// # Returns
+///
+/// * `BTreeMap<String, Vec<String>>` - The versions found locally by dependency name,
+///   for the dependencies whose resolved version is not available
+pub fn find_version_mismatches(
+    dependencies: &DependencyCollection,
+    registry_path: &Path,
+) -> BTreeMap<String, Vec<String>> {
+    let listing = registry_listing(registry_path);
+    dependencies
+        .iter()
+        .filter_map(|dependency| {
+            let found = version_mismatch(dependency, registry_path, listing.as_deref())?;
+            Some((dependency.name.clone(), found))
+        })
+        .collect()
+}
+
+fn version_mismatch(
+    dependency: &Dependency,
+    registry_path: &Path,
+    listing: Option<&RegistryListing>,
+) -> Option<Vec<String>> {
+    let source_path = dependency.get_registry_path(registry_path);
+    if source_path.is_dir() {
+        // Cargo names the directories it extracts after the crate's version, so only
+        // the manifests of other sources need to be read
+        let dir_name = format!("{}-{}", dependency.name, dependency.version);
+        if listing.and_then(|listing| listing.find_normalized(&dir_name)) == Some(&source_path) {
+            return None;
+        }
+        let manifest = ParsedManifest::read(&source_path).ok()?;
+        // Versions inherited from a workspace are not checked
+        let version = manifest.package_field("version")?.as_str()?;
+        return (version != dependency.version).then(|| vec![version.to_string()]);
+    }
+
+    let mut found: Vec<String> = listing?
+        .versions_of(&dependency.name)
+        .map(|krate| krate.version.clone())
+        .collect();
+    // Directories differing in casing or separators may hold the same version
+    found.sort_by(|a, b| compare_versions(a, b));
+    found.dedup();
+    (!found.is_empty()).then_some(found)
+}
+
+/// Finds the highest version of a crate extracted in the registry that matches a
+/// version requirement.
+///
+/// Requirements that are not valid semver, such as the `workspace` placeholder of
+/// inherited dependencies, match any version. Pre-releases only match requirements
+/// that name a pre-release, as in cargo.
+///
+/// # Arguments
+///
+/// * `name` - The name of the crate, matched after normalization
+/// * `requirement` - The version requirement, e.g. `1.0` or `*`
+/// * `registry_path` - Path to the registry source directory to look in
+///
+/// # Returns
+///
+/// * `Option<Dependency>` - The crate at the highest matching version, or `None` if no
+///   version matches
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use depbank::{best_available_matching, resolve_registry_path};
+///
+/// let registry_path = resolve_registry_path().unwrap();
+/// if let Some(serde) = best_available_matching("serde", "*", &registry_path) {
+///     println!("Using serde {}", serde.version);
+/// }
+/// ```
+pub fn best_available_matching(
+    name: &str,
+    requirement: &str,
+    registry_path: &Path,
+) -> Option<Dependency> {
+    let requirement = VersionReq::parse(requirement).unwrap_or(VersionReq::STAR);
+    registry_listing(registry_path)?
+        .versions_of(name)
+        .filter_map(|krate| {
+            let version = Version::parse(&krate.version).ok()?;
+            requirement.matches(&version).then_some((version, krate))
+        })
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+        .map(|(_, krate)| krate.clone())
+}
+
+/// Handles the declared dependencies whose version Cargo.lock did not resolve.
+///
+/// Only dependencies declared without a version requirement, `*` or `workspace`, are
+/// handled: a lockfile missing a crate with a requirement is out of date, which
+/// resolution already reports. Depending on `policy`, such dependencies are skipped,
+/// resolved to the best version extracted in the registry, or fail the run.
+///
+/// # Arguments
+///
+/// * `declared` - The dependencies declared in the manifests
+/// * `resolved` - The dependencies resolved from Cargo.lock
+/// * `registry_path` - Path to the registry source directory
+/// * `policy` - How to handle the unresolvable dependencies
+///
+/// # Returns
+///
+/// * `Result<DependencyCollection>` - The resolved dependencies, along with the
+///   unresolvable ones resolved from the registry
+///
+/// # Errors
+///
+/// Returns [`DepBankError::UnresolvableVersion`] for the first unresolvable
+/// dependency, if `policy` is [`UnresolvableVersionPolicy::Error`]
+pub fn resolve_unresolvable_versions(
+    declared: &DependencyCollection,
+    resolved: &DependencyCollection,
+    registry_path: &Path,
+    policy: UnresolvableVersionPolicy,
+) -> Result<DependencyCollection> {
+    let resolved_names: HashSet<String> = resolved
+        .iter()
+        .map(|dep| normalize_crate_name(&dep.name))
+        .collect();
+    let mut handled = HashSet::new();
+    let mut result = DependencyCollection::new();
+    for dep in resolved.iter() {
+        result.add(dep.clone());
+    }
+
+    for dep in declared.iter() {
+        let name = normalize_crate_name(&dep.name);
+        if !matches!(dep.version.as_str(), "*" | "workspace")
+            || resolved_names.contains(&name)
+            || !handled.insert(name)
+        {
+            continue;
+        }
+
+        let unresolvable = format!(
+            "{} has no version requirement ({}) and is not in Cargo.lock",
+            dep.name, dep.version
+        );
+        match policy {
+            UnresolvableVersionPolicy::Error => {
+                return Err(DepBankError::UnresolvableVersion {
+                    name: dep.name.clone(),
+                    version: dep.version.clone(),
+                }
+                .into());
+            }
+            UnresolvableVersionPolicy::BestAvailable => {
+                if let Some(best) = best_available_matching(&dep.name, &dep.version, registry_path)
+                {
+                    emit_warning(Warning::new(
+                        WarningKind::UnresolvableVersion,
+                        Some(&dep.name),
+                        format!(
+                            "{}, using {}, the highest version available locally",
+                            unresolvable, best.version
+                        ),
+                    ));
+                    result.add(best);
+                    continue;
+                }
+                emit_warning(Warning::new(
+                    WarningKind::UnresolvableVersion,
+                    Some(&dep.name),
+                    format!(
+                        "{}, and no version is available locally, skipping it",
+                        unresolvable
+                    ),
+                ));
+            }
+            UnresolvableVersionPolicy::Skip => {
+                emit_warning(Warning::new(
+                    WarningKind::UnresolvableVersion,
+                    Some(&dep.name),
+                    format!("{}, skipping it", unresolvable),
+                ));
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+/// Splits a `<name>-<version>` directory name into a crate
+fn parse_crate_dir_name(dir_name: &str) -> Option<Dependency> {
+    dir_name
+        .match_indices('-')
+        .map(|(index, _)| (&dir_name[..index], &dir_name[index + 1..]))
+        .find(|(name, version)| !name.is_empty() && semver::Version::parse(version).is_ok())
+        .map(|(name, version)| Dependency::new(name, version))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_parse_crate_dir_name() {
+        assert_eq!(
+            parse_crate_dir_name("serde-1.0.197"),
+            Some(Dependency::new("serde", "1.0.197"))
+        );
+        assert_eq!(
+            parse_crate_dir_name("wasm-bindgen-0.2.100"),
+            Some(Dependency::new("wasm-bindgen", "0.2.100"))
+        );
+        assert_eq!(
+            parse_crate_dir_name("foo-bar-1.0.0-rc.1"),
+            Some(Dependency::new("foo-bar", "1.0.0-rc.1"))
+        );
+        assert_eq!(parse_crate_dir_name("not-a-crate"), None);
+    }
+
+    #[test]
+    fn test_registry_listing_is_shared_until_the_registry_changes() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let registry_path = temp_dir.path().join("index.crates.io-1949cf8c6b5b557f");
+        fs::create_dir_all(registry_path.join("foo-bar-1.0.0"))?;
+
+        let listing = registry_listing(&registry_path).unwrap();
+        assert_eq!(
+            listing.find_normalized("foo_bar-1.0.0"),
+            Some(registry_path.join("foo-bar-1.0.0").as_path())
+        );
+        assert!(Arc::ptr_eq(
+            &listing,
+            &registry_listing(&registry_path).unwrap()
+        ));
+
+        // Extracting a crate is seen by the next lookup
+        fs::create_dir_all(registry_path.join("serde-1.0.197"))?;
+        let listing = registry_listing(&registry_path).unwrap();
+        assert_eq!(listing.versions_of("serde").count(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_version_mismatch() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let registry_path = temp_dir.path().join("index.crates.io-1949cf8c6b5b557f");
+        for dir in ["serde-1.0.197", "serde-1.0.150", "serde_json-1.0.140"] {
+            fs::create_dir_all(registry_path.join(dir))?;
+        }
+        fs::write(
+            registry_path.join("serde-1.0.197").join("Cargo.toml"),
+            "[package]\nname = \"serde\"\nversion = \"1.0.197\"\n",
+        )?;
+
+        // The locked version is extracted
+        let dependency = Dependency::new("serde", "1.0.197");
+        assert_eq!(find_version_mismatch(&dependency, &registry_path), None);
+
+        // Only other versions are extracted
+        let dependency = Dependency::new("serde", "1.0.219");
+        assert_eq!(
+            find_version_mismatch(&dependency, &registry_path),
+            Some(vec!["1.0.150".to_string(), "1.0.197".to_string()])
+        );
+
+        // No version is extracted at all
+        let dependency = Dependency::new("tokio", "1.44.2");
+        assert_eq!(find_version_mismatch(&dependency, &registry_path), None);
+
+        // A local registry holds another version than the locked one
+        let local_registry = temp_dir.path().join("vendor");
+        fs::create_dir_all(local_registry.join("log"))?;
+        fs::write(
+            local_registry.join("log").join("Cargo.toml"),
+            "[package]\nname = \"log\"\nversion = \"0.4.26\"\n",
+        )?;
+        let mut dependency = Dependency::new("log", "0.4.27");
+        dependency.source = Some(format!("registry+file://{}", local_registry.display()));
+        assert_eq!(
+            find_version_mismatch(&dependency, &registry_path),
+            Some(vec!["0.4.26".to_string()])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_version_mismatches() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let registry_path = temp_dir.path().join("index.crates.io-1949cf8c6b5b557f");
+        for dir in [
+            "serde-1.0.197",
+            "serde_json-1.0.10",
+            "serde-json-1.0.10",
+            "serde_json-1.0.9",
+        ] {
+            fs::create_dir_all(registry_path.join(dir))?;
+        }
+        let mut dependencies = DependencyCollection::new();
+        dependencies.add(Dependency::new("serde", "1.0.197"));
+        dependencies.add(Dependency::new("serde_json", "1.0.140"));
+        dependencies.add(Dependency::new("tokio", "1.44.2"));
+
+        // Versions are in semver order, each listed once
+        assert_eq!(
+            find_version_mismatches(&dependencies, &registry_path),
+            BTreeMap::from([(
+                "serde_json".to_string(),
+                vec!["1.0.9".to_string(), "1.0.10".to_string()]
+            )])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unused_registry_crates() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let registry_path = temp_dir.path().join("index.crates.io-1949cf8c6b5b557f");
+        for dir in [
+            "serde-1.0.197",
+            "serde_json-1.0.140",
+            "serde-1.0.150",
+            "old-crate-0.1.0",
+        ] {
+            fs::create_dir_all(registry_path.join(dir))?;
+        }
+        // Files are not crates
+        fs::write(registry_path.join("notes-1.0.0"), "")?;
+
+        let mut declared = DependencyCollection::new();
+        declared.add(Dependency::new("serde", "1.0.197"));
+        declared.add(Dependency::new("serde-json", "1.0.140"));
+
+        let unused = unused_registry_crates(&declared, &[registry_path])?;
+
+        assert_eq!(
+            unused,
+            vec![
+                Dependency::new("old-crate", "0.1.0"),
+                Dependency::new("serde", "1.0.150"),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_best_available_matching() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let registry_path = temp_dir.path();
+        for dir in [
+            "serde-1.0.9",
+            "serde-1.0.197",
+            "serde-2.0.0-alpha.1",
+            "serde_json-1.0.140",
+            "rand-0.7.3",
+            "rand-0.8.5",
+        ] {
+            fs::create_dir_all(registry_path.join(dir))?;
+        }
+
+        // Versions compare by semver, and pre-releases are left out
+        let best = best_available_matching("serde", "*", registry_path).unwrap();
+        assert_eq!(best, Dependency::new("serde", "1.0.197"));
+        assert_eq!(
+            best_available_matching("rand", "0.7", registry_path).map(|dep| dep.version),
+            Some("0.7.3".to_string())
+        );
+        assert_eq!(
+            best_available_matching("rand", "workspace", registry_path).map(|dep| dep.version),
+            Some("0.8.5".to_string())
+        );
+        assert_eq!(best_available_matching("rand", "0.9", registry_path), None);
+        assert_eq!(best_available_matching("tokio", "*", registry_path), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_unresolvable_versions() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let registry_path = temp_dir.path();
+        for dir in ["log-0.4.26", "log-0.4.27"] {
+            fs::create_dir_all(registry_path.join(dir))?;
+        }
+
+        let mut declared = DependencyCollection::new();
+        declared.add(Dependency::new("serde", "1.0"));
+        declared.add(Dependency::new("log", "*"));
+        declared.add(Dependency::new("env_logger", "workspace"));
+        declared.add(Dependency::new("anyhow", "1.0"));
+        let mut resolved = DependencyCollection::new();
+        resolved.add(Dependency::new("serde", "1.0.219"));
+        let names = |deps: &DependencyCollection| -> Vec<String> {
+            deps.iter()
+                .map(|dep| format!("{} {}", dep.name, dep.version))
+                .collect()
+        };
+
+        // Out-of-date lockfiles missing `anyhow` are not this policy's concern
+        let skipped = resolve_unresolvable_versions(
+            &declared,
+            &resolved,
+            registry_path,
+            UnresolvableVersionPolicy::Skip,
+        )?;
+        assert_eq!(names(&skipped), vec!["serde 1.0.219"]);
+
+        let best = resolve_unresolvable_versions(
+            &declared,
+            &resolved,
+            registry_path,
+            UnresolvableVersionPolicy::BestAvailable,
+        )?;
+        assert_eq!(names(&best), vec!["serde 1.0.219", "log 0.4.27"]);
+
+        let error = resolve_unresolvable_versions(
+            &declared,
+            &resolved,
+            registry_path,
+            UnresolvableVersionPolicy::Error,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<DepBankError>(),
+            Some(DepBankError::UnresolvableVersion { name, version })
+                if name == "log" && version == "*"
+        ));
+
+        // Placeholders resolved by Cargo.lock are left alone
+        resolved.add(Dependency::new("log", "0.4.26"));
+        resolved.add(Dependency::new("env_logger", "0.11.8"));
+        let all = resolve_unresolvable_versions(
+            &declared,
+            &resolved,
+            registry_path,
+            UnresolvableVersionPolicy::Error,
+        )?;
+        assert_eq!(all.len(), 3);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,1233 @@
+//! Reporting on generated code banks.
+//!
+//! After generation, a [`GenerationReport`] records what was produced for each
+//! dependency (file, version, size, and tokens). The report is what the summary
+//! files in the output directory, `README.md` and `index.json`, are rendered from.
+
+use crate::fingerprint::fnv1a;
+use crate::{
+    DependencyCollection, TokenOptions, calculate_file_tokens, calculate_tokens,
+    filters::is_rust_fence, is_compressed_bank, parallel, read_bank,
+};
+use anyhow::{Context, Result};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+
+// Constants for formatting the index markdown
+const INDEX_MARKDOWN_FILE: &str = "README.md";
+/// The name of the machine-readable index in the output directory, see
+/// [`write_index_json`]
+pub const INDEX_JSON_FILE: &str = "index.json";
+const INDEX_HEADER: &str = "# Code Bank Summary\n\n";
+const INDEX_TABLE_HEADER: &str = "| Dependency | Version | Tokens | Size (bytes) |\n";
+const INDEX_TABLE_SEPARATOR: &str = "|------------|---------|--------|-------------|\n";
+const INDEX_USED_BY_TABLE_HEADER: &str =
+    "| Dependency | Version | Tokens | Size (bytes) | Used by |\n";
+const INDEX_USED_BY_TABLE_SEPARATOR: &str =
+    "|------------|---------|--------|-------------|---------|\n";
+const INDEX_ABOUT: &str = "\n## About Code Banks\n\n\
+Code banks are generated summaries of your project's dependencies. \
+They provide an overview of the code structure and key components \
+of each dependency, helping you understand the libraries your project uses \
+without having to browse through the original source code.\n\n\
+Each file contains a summary of the corresponding dependency's source code, \
+including important types, functions, and structures.\n\n\
+Generated by [DepBank](https://github.com/tyrchen/depbank).\n";
+
+/// The fewest tokens of doc comments a code bank has to not be reported as undocumented
+pub const DEFAULT_MIN_DOC_TOKENS: usize = 20;
+
+/// A code bank produced for a single dependency
+#[derive(Debug, Clone, Serialize)]
+pub struct GeneratedBank {
+    /// The name of the dependency
+    pub name: String,
+    /// The name the dependency is shown under in the index, if it has an alias
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    /// The resolved version of the dependency
+    pub version: String,
+    /// Path to the generated code bank file
+    pub path: PathBuf,
+    /// Size of the code bank in bytes, before any compression
+    pub size_bytes: usize,
+    /// Size of the gzipped code bank file in bytes, if the bank was compressed
+    pub compressed_size_bytes: Option<usize>,
+    /// Number of tokens in the code bank
+    pub token_count: usize,
+    /// The projects needing the dependency, when banks were generated for several
+    pub used_by: Vec<PathBuf>,
+    /// How deep the dependency sits below the projects, 0 for direct dependencies, if
+    /// transitive dependencies were collected
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub depth: Option<usize>,
+    /// How long the code bank took to generate, in seconds, unless it was reused
+    /// from a previous run
+    pub generation_secs: Option<f64>,
+}
+
+/// Summary of a code bank generation run
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GenerationReport {
+    /// The projects the code banks were generated for, if known
+    pub project_paths: Vec<PathBuf>,
+    /// The generated code banks
+    pub banks: Vec<GeneratedBank>,
+    /// Documented crates locked at several semver-incompatible versions, with all
+    /// their locked versions
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub major_version_conflicts: BTreeMap<String, Vec<String>>,
+    /// Dependencies that were not generated, with the reason, e.g.
+    /// [`SKIPPED_DEADLINE`](crate::SKIPPED_DEADLINE) or
+    /// [`SKIPPED_TOO_LARGE`](crate::SKIPPED_TOO_LARGE)
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub skipped: BTreeMap<String, String>,
+    /// Dependencies whose banks hold almost no documentation, see
+    /// [`is_effectively_empty`], sorted
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub undocumented: Vec<String>,
+    /// The files depbank wrote into the output directory, relative to it, sorted.
+    /// Only these files are removed when cleaning the output directory, see
+    /// [`clean_output_dir`]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<PathBuf>,
+    /// The wall-clock time the generation of all banks took, in seconds. Banks are
+    /// generated in parallel, so this is usually less than the sum of their times
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_generation_secs: Option<f64>,
+}
+
+impl GenerationReport {
+    /// Total number of tokens across all generated banks
+    pub fn total_tokens(&self) -> usize {
+        self.banks.iter().map(|bank| bank.token_count).sum()
+    }
+
+    /// Total size in bytes of all generated banks
+    pub fn total_size_bytes(&self) -> usize {
+        self.banks.iter().map(|bank| bank.size_bytes).sum()
+    }
+
+    /// The generated banks, heaviest first: by descending token count, then by name
+    pub fn banks_by_tokens(&self) -> Vec<&GeneratedBank> {
+        let mut banks: Vec<&GeneratedBank> = self.banks.iter().collect();
+        banks.sort_by(|a, b| {
+            b.token_count
+                .cmp(&a.token_count)
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        banks
+    }
+
+    /// The number of tokens in each dependency's bank, keyed by dependency name
+    pub fn tokens_by_dependency(&self) -> BTreeMap<String, usize> {
+        self.banks
+            .iter()
+            .map(|bank| (bank.name.clone(), bank.token_count))
+            .collect()
+    }
+
+    /// Records the files depbank owns in the output directory: the banks and their
+    /// sidecars, the index files, and the progress log and token cache if there are.
+    ///
+    /// Files owned by a previous run, as recorded in its `index.json`, stay owned as
+    /// long as they exist, so that banks no longer generated are still cleaned later.
+    /// This must be called before [`relativize_paths`](Self::relativize_paths).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the previous `index.json` cannot be parsed
+    pub fn track_owned_files(&mut self, output_dir: &Path) -> Result<()> {
+        let mut files: Vec<PathBuf> = read_owned_files(output_dir)?
+            .into_iter()
+            .filter(|file| output_dir.join(file).exists())
+            .collect();
+        for bank in &self.banks {
+            files.push(relativize(&bank.path, output_dir));
+            let sidecar = sidecar_path(&bank.path);
+            if sidecar.is_file() {
+                files.push(relativize(&sidecar, output_dir));
+            }
+        }
+        files.extend([INDEX_MARKDOWN_FILE, INDEX_JSON_FILE].map(PathBuf::from));
+        for file in [crate::PROGRESS_LOG_FILE, crate::TOKEN_CACHE_FILE] {
+            if output_dir.join(file).exists() {
+                files.push(PathBuf::from(file));
+            }
+        }
+
+        // Nothing outside the output directory is ever ours to remove
+        files.retain(|file| is_owned_path(file));
+        files.sort();
+        files.dedup();
+        self.files = files;
+
+        Ok(())
+    }
+
+    /// Records how long each bank took to generate, see
+    /// [`GenerationOutcome::durations`](crate::GenerationOutcome::durations), along
+    /// with the wall-clock time of the whole generation
+    pub fn record_durations(&mut self, durations: &HashMap<String, Duration>, total: Duration) {
+        for bank in &mut self.banks {
+            bank.generation_secs = durations
+                .get(&bank.name)
+                .map(|duration| duration.as_secs_f64());
+        }
+        self.total_generation_secs = Some(total.as_secs_f64());
+    }
+
+    /// Forgets the generation times, which differ on every run, e.g. to archive an
+    /// index that only changes with the banks
+    pub fn clear_durations(&mut self) {
+        for bank in &mut self.banks {
+            bank.generation_secs = None;
+        }
+        self.total_generation_secs = None;
+    }
+
+    /// The `n` banks that took the longest to generate, slowest first. Banks without
+    /// a recorded time are left out
+    pub fn slowest_banks(&self, n: usize) -> Vec<&GeneratedBank> {
+        let mut banks: Vec<&GeneratedBank> = self
+            .banks
+            .iter()
+            .filter(|bank| bank.generation_secs.is_some())
+            .collect();
+        banks.sort_by(|a, b| {
+            b.generation_secs
+                .partial_cmp(&a.generation_secs)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        banks.truncate(n);
+        banks
+    }
+
+    /// Shows the banks of dependencies with an alias under their display name in the
+    /// index, see [`display_name`](crate::display_name)
+    pub fn apply_aliases(&mut self, aliases: &BTreeMap<String, String>) {
+        for bank in &mut self.banks {
+            bank.display_name = aliases.get(&bank.name).cloned();
+        }
+    }
+
+    /// Makes the paths of the banks and projects relative to a base directory, see
+    /// [`relativize`], so that the report holds no machine-specific paths
+    pub fn relativize_paths(&mut self, base: &Path) {
+        for path in &mut self.project_paths {
+            *path = relativize(path, base);
+        }
+        for bank in &mut self.banks {
+            bank.path = relativize(&bank.path, base);
+            for path in &mut bank.used_by {
+                *path = relativize(path, base);
+            }
+        }
+    }
+}
+
+/// The content of `index.json`: the report along with its token totals
+#[derive(Serialize)]
+struct IndexJson<'a> {
+    #[serde(flatten)]
+    report: &'a GenerationReport,
+    total_tokens: usize,
+    tokens_by_dependency: BTreeMap<String, usize>,
+}
+
+/// Builds a generation report from the code bank files produced for the dependencies.
+///
+/// This reads each generated file to record its size and token count. Gzipped banks
+/// are decompressed to count their tokens.
+///
+/// # Arguments
+///
+/// * `dependencies` - The dependencies code banks were generated for
+/// * `code_bank_files` - Mapping of dependency names to their code bank file paths, as
+///   returned by [`generate_all_code_banks`](crate::generate_all_code_banks)
+///
+/// # Returns
+///
+/// * `Result<GenerationReport>` - The report, with banks sorted by dependency name
+///
+/// # Errors
+///
+/// Returns an error if a code bank file cannot be read or tokenized
+pub fn build_generation_report(
+    dependencies: &DependencyCollection,
+    code_bank_files: &HashMap<String, PathBuf>,
+) -> Result<GenerationReport> {
+    // Tokenize the banks in parallel on the shared thread pool
+    let mut banks = parallel::install(|| {
+        code_bank_files
+            .par_iter()
+            .map(|(name, path)| {
+                let metadata = fs::metadata(path).with_context(|| {
+                    format!("Failed to read code bank file: {}", path.display())
+                })?;
+                let (size_bytes, compressed_size_bytes, token_count) = if is_compressed_bank(path) {
+                    let content = read_bank(path)?;
+                    let token_count = calculate_tokens(&content, &TokenOptions::default())?;
+                    (content.len(), Some(metadata.len() as usize), token_count)
+                } else {
+                    (
+                        metadata.len() as usize,
+                        None,
+                        calculate_file_tokens(path, &TokenOptions::default())?,
+                    )
+                };
+
+                Ok(GeneratedBank {
+                    name: name.clone(),
+                    display_name: None,
+                    version: dependencies
+                        .get_version(name)
+                        .cloned()
+                        .unwrap_or_else(|| "unknown".to_string()),
+                    path: path.clone(),
+                    size_bytes,
+                    compressed_size_bytes,
+                    token_count,
+                    used_by: Vec::new(),
+                    depth: None,
+                    generation_secs: None,
+                })
+            })
+            .collect::<Result<Vec<_>>>()
+    })??;
+
+    banks.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(GenerationReport {
+        banks,
+        ..Default::default()
+    })
+}
+
+/// Checks whether a code bank holds too little documentation to be useful.
+///
+/// Only the prose of the `///` and `//!` doc comments in the bank's Rust code blocks
+/// counts: a crate without doc comments gives a bank of bare signatures, which tells
+/// an AI assistant little about how to use it.
+///
+/// # Arguments
+///
+/// * `content` - The generated code bank content
+/// * `min_tokens` - The fewest tokens of doc comments a documented bank has
+///
+/// # Returns
+///
+/// * `bool` - True if the bank's doc comments are shorter than `min_tokens` tokens
+///
+/// # Examples
+///
+/// ```rust
+/// use depbank::is_effectively_empty;
+///
+/// let bank = "## src/lib.rs\n```rust\npub fn parse(s: &str) -> u32 { ... }\n```\n";
+/// assert!(is_effectively_empty(bank, 1));
+/// ```
+pub fn is_effectively_empty(content: &str, min_tokens: usize) -> bool {
+    let mut docs = String::new();
+    let mut in_rust_block = false;
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") {
+            in_rust_block = !in_rust_block && is_rust_fence(trimmed);
+            continue;
+        }
+        if !in_rust_block {
+            continue;
+        }
+        if let Some(doc) = trimmed
+            .strip_prefix("///")
+            .or_else(|| trimmed.strip_prefix("//!"))
+        {
+            docs.push_str(doc.trim());
+            docs.push('\n');
+        }
+    }
+
+    // Without a tokenizer, words are a close enough measure
+    let tokens = calculate_tokens(&docs, &TokenOptions::default())
+        .unwrap_or_else(|_| docs.split_whitespace().count());
+    tokens < min_tokens
+}
+
+/// Finds the dependencies whose banks hold almost no documentation.
+///
+/// # Arguments
+///
+/// * `report` - The generation report listing the banks
+/// * `min_tokens` - The fewest tokens of doc comments a documented bank has, see
+///   [`is_effectively_empty`]
+///
+/// # Returns
+///
+/// * `Result<Vec<String>>` - The names of the undocumented dependencies, sorted
+///
+/// # Errors
+///
+/// Returns an error if a code bank file cannot be read
+pub fn find_undocumented_banks(
+    report: &GenerationReport,
+    min_tokens: usize,
+) -> Result<Vec<String>> {
+    let mut undocumented = parallel::install(|| {
+        report
+            .banks
+            .par_iter()
+            .filter_map(|bank| match read_bank(&bank.path) {
+                Ok(content) => {
+                    is_effectively_empty(&content, min_tokens).then(|| Ok(bank.name.clone()))
+                }
+                Err(e) => Some(Err(e)),
+            })
+            .collect::<Result<Vec<_>>>()
+    })??;
+    undocumented.sort();
+    Ok(undocumented)
+}
+
+/// Writes a human-browsable `README.md` index into the output directory.
+///
+/// The index lists every generated bank as a link to its file, along with its version,
+/// token count and size, sorted by dependency name and preceded by a summary of the
+/// totals. Links are relative to the output directory, so they work whether banks are
+/// written directly into it or into nested subdirectories.
+///
+/// # Arguments
+///
+/// * `report` - The generation report to render
+/// * `output_dir` - The output directory the banks were generated into
+///
+/// # Returns
+///
+/// * `Result<PathBuf>` - Path to the written index file
+///
+/// # Errors
+///
+/// Returns an error if the index file cannot be written
+pub fn write_index_markdown(report: &GenerationReport, output_dir: &Path) -> Result<PathBuf> {
+    let index_path = output_dir.join(INDEX_MARKDOWN_FILE);
+    let content = render_index_markdown(report, output_dir);
+
+    fs::write(&index_path, content).with_context(|| {
+        format!(
+            "Failed to write {} to file: {}",
+            INDEX_MARKDOWN_FILE,
+            index_path.display()
+        )
+    })?;
+
+    Ok(index_path)
+}
+
+/// Renders the content of `index.json`, see [`write_index_json`]
+///
+/// # Errors
+///
+/// Returns an error if the report cannot be serialized
+pub fn index_json(report: &GenerationReport) -> Result<String> {
+    let index = IndexJson {
+        report,
+        total_tokens: report.total_tokens(),
+        tokens_by_dependency: report.tokens_by_dependency(),
+    };
+    Ok(serde_json::to_string_pretty(&index)?)
+}
+
+/// Writes a machine-readable `index.json` into the output directory.
+///
+/// The index holds the full report, the total token count and a
+/// `tokens_by_dependency` map from each dependency to the tokens in its bank.
+///
+/// # Arguments
+///
+/// * `report` - The generation report to write
+/// * `output_dir` - The output directory the banks were generated into
+///
+/// # Returns
+///
+/// * `Result<PathBuf>` - Path to the written index file
+///
+/// # Errors
+///
+/// Returns an error if the index file cannot be written
+pub fn write_index_json(report: &GenerationReport, output_dir: &Path) -> Result<PathBuf> {
+    let index_path = output_dir.join(INDEX_JSON_FILE);
+    let content = index_json(report)?;
+
+    fs::write(&index_path, content).with_context(|| {
+        format!(
+            "Failed to write {} to file: {}",
+            INDEX_JSON_FILE,
+            index_path.display()
+        )
+    })?;
+
+    Ok(index_path)
+}
+
+/// The extension of the sidecar files written next to code banks, see
+/// [`write_sidecar`]
+pub const SIDECAR_EXTENSION: &str = "meta.json";
+
+/// The metadata of a code bank written to its sidecar file
+#[derive(Serialize)]
+struct Sidecar<'a> {
+    name: &'a str,
+    version: &'a str,
+    token_count: usize,
+    size_bytes: usize,
+    /// The FNV-1a hash of the bank's markdown content, in hexadecimal
+    content_hash: String,
+}
+
+/// Returns the path of a code bank's sidecar file: `<name>.meta.json` next to a
+/// `<name>.md` or `<name>.md.gz` bank
+///
+/// # Examples
+///
+/// ```rust
+/// use depbank::sidecar_path;
+/// use std::path::{Path, PathBuf};
+///
+/// assert_eq!(sidecar_path(Path::new(".codebank/serde.md")), PathBuf::from(".codebank/serde.meta.json"));
+/// assert_eq!(sidecar_path(Path::new(".codebank/tokio.md.gz")), PathBuf::from(".codebank/tokio.meta.json"));
+/// ```
+pub fn sidecar_path(bank_path: &Path) -> PathBuf {
+    let file_name = bank_path
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    let stem = file_name
+        .strip_suffix(".gz")
+        .unwrap_or(&file_name)
+        .strip_suffix(".md")
+        .unwrap_or(&file_name);
+    bank_path.with_file_name(format!("{}.{}", stem, SIDECAR_EXTENSION))
+}
+
+/// Writes the metadata of a code bank to a sidecar file next to it.
+///
+/// The sidecar, see [`sidecar_path`], holds the dependency's name and version, and the
+/// bank's token count, size in bytes and content hash, so that tools processing banks
+/// one file at a time need not parse `index.json`.
+///
+/// # Arguments
+///
+/// * `path` - Path to the code bank file
+/// * `stats` - The bank's entry in the generation report
+///
+/// # Returns
+///
+/// * `Result<PathBuf>` - Path to the written sidecar file
+///
+/// # Errors
+///
+/// Returns an error if the bank cannot be read, or the sidecar cannot be written
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use depbank::{GenerationReport, write_sidecar};
+///
+/// let report = GenerationReport::default();
+/// for bank in &report.banks {
+///     write_sidecar(&bank.path, bank).unwrap();
+/// }
+/// ```
+pub fn write_sidecar(path: &Path, stats: &GeneratedBank) -> Result<PathBuf> {
+    let content = read_bank(path)?;
+    let sidecar = Sidecar {
+        name: &stats.name,
+        version: &stats.version,
+        token_count: stats.token_count,
+        size_bytes: stats.size_bytes,
+        content_hash: format!("{:016x}", fnv1a(content.as_bytes())),
+    };
+
+    let sidecar_path = sidecar_path(path);
+    fs::write(&sidecar_path, serde_json::to_string_pretty(&sidecar)?)
+        .with_context(|| format!("Failed to write sidecar file: {}", sidecar_path.display()))?;
+
+    Ok(sidecar_path)
+}
+
+/// Reads the files a previous run recorded as its own in the output directory's
+/// `index.json`.
+///
+/// # Arguments
+///
+/// * `output_dir` - The output directory the banks were generated into
+///
+/// # Returns
+///
+/// * `Result<Vec<PathBuf>>` - The owned files, relative to `output_dir`. Empty if
+///   there is no `index.json`, or if it predates ownership tracking
+///
+/// # Errors
+///
+/// Returns an error if `index.json` exists but cannot be read or parsed
+pub fn read_owned_files(output_dir: &Path) -> Result<Vec<PathBuf>> {
+    let index_path = output_dir.join(INDEX_JSON_FILE);
+    if !index_path.is_file() {
+        return Ok(Vec::new());
+    }
+
+    let content = fs::read_to_string(&index_path)
+        .with_context(|| format!("Failed to read {}", index_path.display()))?;
+    let index: serde_json::Value = serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse {}", index_path.display()))?;
+
+    Ok(index["files"]
+        .as_array()
+        .map(|files| {
+            files
+                .iter()
+                .filter_map(|file| file.as_str().map(PathBuf::from))
+                .filter(|file| is_owned_path(file))
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+/// Removes the files depbank generated in an output directory, leaving any other
+/// file, such as hand-written notes, intact.
+///
+/// The generated files are those recorded in `index.json`, see
+/// [`GenerationReport::track_owned_files`]. Directories emptied by the removal are
+/// removed as well, but never the output directory itself.
+///
+/// # Arguments
+///
+/// * `output_dir` - The output directory to clean
+///
+/// # Returns
+///
+/// * `Result<Vec<PathBuf>>` - The removed files, relative to `output_dir`
+///
+/// # Errors
+///
+/// Returns an error if `index.json` cannot be parsed, or if an owned file cannot be
+/// removed
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use depbank::clean_output_dir;
+/// use std::path::Path;
+///
+/// let removed = clean_output_dir(Path::new(".codebank")).unwrap();
+/// println!("Removed {} generated files", removed.len());
+/// ```
+pub fn clean_output_dir(output_dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut removed = Vec::new();
+    for file in read_owned_files(output_dir)? {
+        let path = output_dir.join(&file);
+        if !path.is_file() {
+            continue;
+        }
+        fs::remove_file(&path)
+            .with_context(|| format!("Failed to remove generated file: {}", path.display()))?;
+
+        // Failing to remove a directory only means it is not empty
+        let mut dir = path.parent();
+        while let Some(parent) = dir.filter(|parent| *parent != output_dir) {
+            if fs::remove_dir(parent).is_err() {
+                break;
+            }
+            dir = parent.parent();
+        }
+        removed.push(file);
+    }
+
+    Ok(removed)
+}
+
+/// Checks that a path recorded as owned stays inside the output directory
+fn is_owned_path(path: &Path) -> bool {
+    path.components().count() > 0
+        && path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+}
+
+fn render_index_markdown(report: &GenerationReport, output_dir: &Path) -> String {
+    let mut banks: Vec<&GeneratedBank> = report.banks.iter().collect();
+    banks.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut content = String::from(INDEX_HEADER);
+    match report.project_paths.as_slice() {
+        [] => {}
+        [project_path] => writeln!(
+            content,
+            "Generated for project: {}\n",
+            project_path.display()
+        )
+        .unwrap(),
+        project_paths => {
+            let projects: Vec<String> = project_paths
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect();
+            writeln!(content, "Generated for projects: {}\n", projects.join(", ")).unwrap();
+        }
+    }
+
+    // Summary of the totals
+    writeln!(content, "- **Dependencies:** {}", banks.len()).unwrap();
+    writeln!(content, "- **Total tokens:** {}", report.total_tokens()).unwrap();
+    writeln!(
+        content,
+        "- **Total size:** {} bytes",
+        report.total_size_bytes()
+    )
+    .unwrap();
+
+    // Several major versions are easily mistaken for one another
+    if !report.major_version_conflicts.is_empty() {
+        writeln!(content, "\n## ⚠ Multiple Major Versions\n").unwrap();
+        writeln!(
+            content,
+            "These crates are locked at several incompatible versions, whose APIs differ. \
+Check which version the code you work on uses.\n"
+        )
+        .unwrap();
+        for (name, versions) in &report.major_version_conflicts {
+            writeln!(content, "- **{}**: {}", name, versions.join(", ")).unwrap();
+        }
+    }
+
+    // An incomplete run says what is missing
+    if !report.skipped.is_empty() {
+        writeln!(content, "\n## Skipped Dependencies\n").unwrap();
+        for (name, reason) in &report.skipped {
+            writeln!(content, "- **{}**: {}", name, reason).unwrap();
+        }
+    }
+
+    // Banks an AI assistant will get little context from
+    if !report.undocumented.is_empty() {
+        writeln!(content, "\n## Undocumented Dependencies\n").unwrap();
+        writeln!(
+            content,
+            "These banks have almost no doc comments, only signatures.\n"
+        )
+        .unwrap();
+        for name in &report.undocumented {
+            writeln!(content, "- {}", name).unwrap();
+        }
+    }
+
+    // Table of contents with links to each bank
+    writeln!(content, "\n## Dependencies\n").unwrap();
+    // Banks generated for several projects record which projects need them
+    let with_used_by = banks.iter().any(|bank| !bank.used_by.is_empty());
+    if with_used_by {
+        content.push_str(INDEX_USED_BY_TABLE_HEADER);
+        content.push_str(INDEX_USED_BY_TABLE_SEPARATOR);
+    } else {
+        content.push_str(INDEX_TABLE_HEADER);
+        content.push_str(INDEX_TABLE_SEPARATOR);
+    }
+    for bank in banks {
+        let size = match bank.compressed_size_bytes {
+            Some(compressed) => format!("{} (gzip: {})", bank.size_bytes, compressed),
+            None => bank.size_bytes.to_string(),
+        };
+        write!(
+            content,
+            "| [{}]({}) | {} | {} | {} |",
+            bank.display_name.as_deref().unwrap_or(&bank.name),
+            relative_link(&bank.path, output_dir),
+            bank.version,
+            bank.token_count,
+            size
+        )
+        .unwrap();
+        if with_used_by {
+            let used_by: Vec<String> = bank
+                .used_by
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect();
+            write!(content, " {} |", used_by.join(", ")).unwrap();
+        }
+        content.push('\n');
+    }
+
+    content.push_str(INDEX_ABOUT);
+    content
+}
+
+/// Expresses a path relative to a base directory.
+///
+/// Both paths are made absolute from the current directory first, and `.` and `..`
+/// components are resolved lexically, without touching the filesystem. Paths outside
+/// the base climb out of it with `..`. A path on another root than the base, such as
+/// another drive on Windows, has no relative form and is returned absolute.
+///
+/// # Arguments
+///
+/// * `path` - The path to express relative to `base`
+/// * `base` - The directory the result is relative to
+///
+/// # Returns
+///
+/// * `PathBuf` - The relative path, `.` for the base itself
+///
+/// # Examples
+///
+/// ```rust
+/// use depbank::relativize;
+/// use std::path::{Path, PathBuf};
+///
+/// let base = Path::new("/home/dev/app/.codebank");
+/// assert_eq!(relativize(Path::new("/home/dev/app/.codebank/serde.md"), base), PathBuf::from("serde.md"));
+/// assert_eq!(relativize(Path::new("/home/dev/app"), base), PathBuf::from(".."));
+/// ```
+pub fn relativize(path: &Path, base: &Path) -> PathBuf {
+    let (Ok(path), Ok(base)) = (std::path::absolute(path), std::path::absolute(base)) else {
+        return path.to_path_buf();
+    };
+    let path_components = normalized_components(&path);
+    let base_components = normalized_components(&base);
+    if path_components.first() != base_components.first() {
+        return path;
+    }
+
+    let common = path_components
+        .iter()
+        .zip(&base_components)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut relative = PathBuf::new();
+    for _ in common..base_components.len() {
+        relative.push("..");
+    }
+    for component in &path_components[common..] {
+        relative.push(component);
+    }
+    if relative.as_os_str().is_empty() {
+        relative.push(".");
+    }
+    relative
+}
+
+/// The components of an absolute path, with `.` and `..` resolved lexically
+fn normalized_components(path: &Path) -> Vec<Component<'_>> {
+    let mut components = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                // `..` at the root stays at the root
+                if matches!(components.last(), Some(Component::Normal(_))) {
+                    components.pop();
+                }
+            }
+            component => components.push(component),
+        }
+    }
+    components
+}
+
+/// Builds a markdown link target for a bank relative to the output directory
+fn relative_link(path: &Path, output_dir: &Path) -> String {
+    let relative = path.strip_prefix(output_dir).unwrap_or(path);
+
+    // Markdown links always use forward slashes, regardless of the platform
+    relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn bank(name: &str, version: &str, path: PathBuf, tokens: usize) -> GeneratedBank {
+        GeneratedBank {
+            name: name.to_string(),
+            display_name: None,
+            version: version.to_string(),
+            path,
+            size_bytes: tokens * 4,
+            compressed_size_bytes: None,
+            token_count: tokens,
+            used_by: Vec::new(),
+            depth: None,
+            generation_secs: None,
+        }
+    }
+
+    #[test]
+    fn test_write_index_markdown() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let output_dir = temp_dir.path();
+
+        // One flat bank and one in a nested layout, deliberately out of order
+        let report = GenerationReport {
+            project_paths: vec![PathBuf::from("/projects/demo")],
+            banks: vec![
+                bank(
+                    "tokio",
+                    "1.36.0",
+                    output_dir.join("runtime").join("tokio.md"),
+                    300,
+                ),
+                bank("serde", "1.0.197", output_dir.join("serde.md"), 200),
+            ],
+            ..Default::default()
+        };
+
+        let index_path = write_index_markdown(&report, output_dir)?;
+        assert_eq!(index_path, output_dir.join("README.md"));
+
+        let content = fs::read_to_string(&index_path)?;
+
+        // Each bank is linked relative to the output directory
+        assert!(content.contains("| [serde](serde.md) | 1.0.197 | 200 | 800 |"));
+        assert!(content.contains("| [tokio](runtime/tokio.md) | 1.36.0 | 300 | 1200 |"));
+
+        // Banks are sorted by name and totals are summarized
+        assert!(content.find("[serde]").unwrap() < content.find("[tokio]").unwrap());
+        assert!(content.contains("Generated for project: /projects/demo"));
+        assert!(content.contains("- **Dependencies:** 2"));
+        assert!(content.contains("- **Total tokens:** 500"));
+        assert!(content.contains("- **Total size:** 2000 bytes"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_index_markdown_with_compressed_bank() {
+        let output_dir = Path::new("/banks");
+        let mut compressed = bank("tokio", "1.36.0", output_dir.join("tokio.md.gz"), 300);
+        compressed.compressed_size_bytes = Some(250);
+        let report = GenerationReport {
+            banks: vec![
+                compressed,
+                bank("serde", "1.0.197", output_dir.join("serde.md"), 200),
+            ],
+            ..Default::default()
+        };
+
+        let content = render_index_markdown(&report, output_dir);
+
+        // Compressed banks link to the gzipped file and record both sizes
+        assert!(content.contains("| [tokio](tokio.md.gz) | 1.36.0 | 300 | 1200 (gzip: 250) |"));
+        assert!(content.contains("| [serde](serde.md) | 1.0.197 | 200 | 800 |"));
+        assert!(content.contains("- **Total size:** 2000 bytes"));
+    }
+
+    #[test]
+    fn test_index_markdown_with_several_projects() {
+        let output_dir = Path::new("/banks");
+        let mut serde = bank("serde", "1.0.197", output_dir.join("serde.md"), 200);
+        serde.used_by = vec![PathBuf::from("api"), PathBuf::from("worker")];
+        let mut tokio = bank("tokio", "1.36.0", output_dir.join("tokio.md"), 300);
+        tokio.used_by = vec![PathBuf::from("worker")];
+        let report = GenerationReport {
+            project_paths: vec![PathBuf::from("api"), PathBuf::from("worker")],
+            banks: vec![serde, tokio],
+            ..Default::default()
+        };
+
+        let content = render_index_markdown(&report, output_dir);
+
+        assert!(content.contains("Generated for projects: api, worker"));
+        assert!(content.contains("| Dependency | Version | Tokens | Size (bytes) | Used by |"));
+        assert!(content.contains("| [serde](serde.md) | 1.0.197 | 200 | 800 | api, worker |"));
+        assert!(content.contains("| [tokio](tokio.md) | 1.36.0 | 300 | 1200 | worker |"));
+    }
+
+    #[test]
+    fn test_index_markdown_with_major_version_conflicts() {
+        let output_dir = Path::new("/banks");
+        let report = GenerationReport {
+            banks: vec![bank("rand", "0.8.5", output_dir.join("rand.md"), 200)],
+            major_version_conflicts: BTreeMap::from([(
+                "rand".to_string(),
+                vec!["0.7.3".to_string(), "0.8.5".to_string()],
+            )]),
+            ..Default::default()
+        };
+
+        let content = render_index_markdown(&report, output_dir);
+        assert!(content.contains("## ⚠ Multiple Major Versions"));
+        assert!(content.contains("- **rand**: 0.7.3, 0.8.5"));
+        assert!(
+            content.find("Multiple Major Versions").unwrap()
+                < content.find("## Dependencies").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_clean_output_dir_keeps_user_files() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let output_dir = temp_dir.path();
+        fs::write(output_dir.join("serde.md"), "# serde\n")?;
+        fs::write(output_dir.join("NOTES.md"), "Hand-written notes\n")?;
+        fs::create_dir_all(output_dir.join("notes"))?;
+        fs::write(output_dir.join("notes").join("tokio.md"), "More notes\n")?;
+
+        let mut report = GenerationReport {
+            banks: vec![bank("serde", "1.0.197", output_dir.join("serde.md"), 200)],
+            ..Default::default()
+        };
+        report.track_owned_files(output_dir)?;
+        assert_eq!(
+            report.files,
+            [
+                PathBuf::from("README.md"),
+                PathBuf::from("index.json"),
+                PathBuf::from("serde.md")
+            ]
+        );
+        write_index_markdown(&report, output_dir)?;
+        write_index_json(&report, output_dir)?;
+
+        // A second run no longer generating serde still owns its bank
+        let mut report = GenerationReport::default();
+        report.track_owned_files(output_dir)?;
+        assert!(report.files.contains(&PathBuf::from("serde.md")));
+
+        let removed = clean_output_dir(output_dir)?;
+        assert_eq!(removed.len(), 3);
+        assert!(!output_dir.join("serde.md").exists());
+        assert!(!output_dir.join("README.md").exists());
+        assert!(!output_dir.join("index.json").exists());
+        assert!(output_dir.join("NOTES.md").is_file());
+        assert!(output_dir.join("notes").join("tokio.md").is_file());
+
+        // Without an index, nothing is known to be ours
+        assert!(clean_output_dir(output_dir)?.is_empty());
+        assert!(output_dir.join("NOTES.md").is_file());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_sidecar() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let output_dir = temp_dir.path();
+        fs::write(output_dir.join("serde.md"), "# serde\n")?;
+        fs::write(output_dir.join("tokio.md"), "# tokio\n")?;
+        let mut report = GenerationReport {
+            banks: vec![
+                bank("serde", "1.0.197", output_dir.join("serde.md"), 200),
+                bank("tokio", "1.36.0", output_dir.join("tokio.md"), 300),
+            ],
+            ..Default::default()
+        };
+
+        for bank in &report.banks {
+            let path = write_sidecar(&bank.path, bank)?;
+            assert_eq!(path, output_dir.join(format!("{}.meta.json", bank.name)));
+
+            let sidecar: serde_json::Value = serde_json::from_str(&fs::read_to_string(path)?)?;
+            assert_eq!(sidecar["name"], bank.name.as_str());
+            assert_eq!(sidecar["version"], bank.version.as_str());
+            assert_eq!(sidecar["token_count"], bank.token_count);
+            assert_eq!(sidecar["size_bytes"], bank.size_bytes);
+            let content = format!("# {}\n", bank.name);
+            assert_eq!(
+                sidecar["content_hash"],
+                format!("{:016x}", fnv1a(content.as_bytes()))
+            );
+        }
+
+        // Sidecars are owned like their banks
+        report.track_owned_files(output_dir)?;
+        assert!(report.files.contains(&PathBuf::from("serde.meta.json")));
+        assert!(report.files.contains(&PathBuf::from("tokio.meta.json")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_index_with_skipped_dependencies() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let output_dir = temp_dir.path();
+        let report = GenerationReport {
+            banks: vec![bank("serde", "1.0.197", output_dir.join("serde.md"), 200)],
+            skipped: BTreeMap::from([("tokio".to_string(), crate::SKIPPED_DEADLINE.to_string())]),
+            ..Default::default()
+        };
+
+        let content = render_index_markdown(&report, output_dir);
+        assert!(content.contains("## Skipped Dependencies"));
+        assert!(content.contains("- **tokio**: skipped (deadline)"));
+
+        let index: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(write_index_json(&report, output_dir)?)?)?;
+        assert_eq!(index["skipped"]["tokio"], "skipped (deadline)");
+        assert_eq!(index["total_tokens"], 200);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_tokens_by_dependency() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let output_dir = temp_dir.path();
+        let report = GenerationReport {
+            project_paths: vec![PathBuf::from("/projects/demo")],
+            banks: vec![
+                bank("anyhow", "1.0.98", output_dir.join("anyhow.md"), 200),
+                bank("serde", "1.0.197", output_dir.join("serde.md"), 200),
+                bank("tokio", "1.36.0", output_dir.join("tokio.md"), 300),
+                bank("itoa", "1.0.15", output_dir.join("itoa.md"), 50),
+            ],
+            ..Default::default()
+        };
+
+        // Heaviest first, ties by name
+        let names: Vec<&str> = report
+            .banks_by_tokens()
+            .iter()
+            .map(|bank| bank.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["tokio", "anyhow", "serde", "itoa"]);
+
+        let index_path = write_index_json(&report, output_dir)?;
+        assert_eq!(index_path, output_dir.join("index.json"));
+
+        let index: serde_json::Value = serde_json::from_str(&fs::read_to_string(&index_path)?)?;
+        let tokens_by_dependency = index["tokens_by_dependency"].as_object().unwrap();
+        assert_eq!(tokens_by_dependency["tokio"], 300);
+        let sum: u64 = tokens_by_dependency
+            .values()
+            .map(|tokens| tokens.as_u64().unwrap())
+            .sum();
+        assert_eq!(sum, 750);
+        assert_eq!(index["total_tokens"], 750);
+        assert_eq!(index["banks"].as_array().unwrap().len(), 4);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_effectively_empty() {
+        let undocumented = "# mylib\n\n## src/lib.rs\n```rust\n// Internal note, not a doc comment\npub fn parse(s: &str) -> u32 { ... }\n```\n";
+        assert!(is_effectively_empty(undocumented, 5));
+        assert!(!is_effectively_empty(undocumented, 0));
+
+        let documented = "## src/lib.rs\n```rust\n//! Parsing of numbers written in words.\n/// Parses a number written in English words, such as `forty two`.\npub fn parse(s: &str) -> u32 { ... }\n```\n";
+        assert!(!is_effectively_empty(documented, 5));
+
+        // Doc comments outside Rust code blocks are not the crate's docs
+        let quoted = "## README.md\n```text\n/// Parses a number written in English words.\n```\n";
+        assert!(is_effectively_empty(quoted, 5));
+    }
+
+    #[test]
+    fn test_index_with_undocumented_dependencies() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let output_dir = temp_dir.path();
+        fs::write(
+            output_dir.join("bare.md"),
+            "## src/lib.rs\n```rust\npub fn bare() {}\n```\n",
+        )?;
+        fs::write(
+            output_dir.join("serde.md"),
+            "## src/lib.rs\n```rust\n/// A generic serialization framework, to serialize and deserialize data structures.\npub trait Serialize {}\n```\n",
+        )?;
+        let mut report = GenerationReport {
+            banks: vec![
+                bank("serde", "1.0.197", output_dir.join("serde.md"), 200),
+                bank("bare", "0.1.0", output_dir.join("bare.md"), 10),
+            ],
+            ..Default::default()
+        };
+
+        report.undocumented = find_undocumented_banks(&report, 5)?;
+        assert_eq!(report.undocumented, vec!["bare"]);
+
+        let content = render_index_markdown(&report, output_dir);
+        assert!(content.contains("## Undocumented Dependencies"));
+        assert!(content.contains("- bare\n"));
+
+        let index: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(write_index_json(&report, output_dir)?)?)?;
+        assert_eq!(index["undocumented"][0], "bare");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_relativize() {
+        let base = Path::new("/home/dev/app/banks");
+        assert_eq!(
+            relativize(Path::new("/home/dev/app/banks/net/tokio.md"), base),
+            PathBuf::from("net/tokio.md")
+        );
+        assert_eq!(
+            relativize(Path::new("/home/dev/lib"), base),
+            PathBuf::from("../../lib")
+        );
+        assert_eq!(relativize(base, base), PathBuf::from("."));
+        assert_eq!(
+            relativize(Path::new("/home/dev/app/./banks/../src"), base),
+            PathBuf::from("../src")
+        );
+
+        // Relative paths are relative to the current directory
+        let current_dir = std::env::current_dir().unwrap();
+        assert_eq!(
+            relativize(Path::new("fixtures"), &current_dir.join("target")),
+            PathBuf::from("../fixtures")
+        );
+    }
+
+    #[test]
+    fn test_index_with_relative_paths() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let output_dir = temp_dir.path().join("banks");
+        fs::create_dir_all(&output_dir)?;
+        let project = temp_dir.path().join("api");
+        let mut serde = bank("serde", "1.0.197", output_dir.join("serde.md"), 200);
+        serde.used_by = vec![project.clone()];
+        let mut report = GenerationReport {
+            project_paths: vec![project],
+            banks: vec![
+                serde,
+                bank(
+                    "tokio",
+                    "1.36.0",
+                    output_dir.join("runtime").join("tokio.md"),
+                    300,
+                ),
+            ],
+            ..Default::default()
+        };
+
+        report.relativize_paths(&output_dir);
+        let index: serde_json::Value = serde_json::from_str(&fs::read_to_string(
+            write_index_json(&report, &output_dir)?,
+        )?)?;
+        assert_eq!(index["project_paths"][0], "../api");
+        assert_eq!(index["banks"][0]["path"], "serde.md");
+        assert_eq!(index["banks"][0]["used_by"][0], "../api");
+        assert_eq!(index["banks"][1]["path"], "runtime/tokio.md");
+
+        // The markdown index links the relative paths as they are
+        let content = render_index_markdown(&report, &output_dir);
+        assert!(content.contains("[tokio](runtime/tokio.md)"));
+        assert!(content.contains("Generated for project: ../api"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,238 @@
+//! Resolving the dependencies of a project, shared by every command that generates.
+//!
+//! Declared dependencies are resolved to the versions in Cargo.lock, then version
+//! conflicts are settled, transitive dependencies added, patches applied and
+//! workspace members left out or replaced by their local sources. `generate`,
+//! `fingerprint` and [`watch_project`](crate::watch_project) all go through
+//! [`resolve_project_dependencies`], so they select the same dependencies.
+
+use crate::{
+    DependencyCollection, ParsedLock, TransitiveOptions, UnresolvableVersionPolicy,
+    VersionConflictPolicy, apply_patches, build_dependency_graph_from_lock,
+    collect_transitive_dependencies_from_graph, compute_dependency_depths, is_workspace_member_dep,
+    normalize_crate_name, parse_patches, resolve_dependency_versions_from_lock,
+    resolve_unresolvable_versions, resolve_version_conflicts, workspace_members,
+};
+use anyhow::Result;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// Options controlling how the dependencies of a project are resolved
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ResolveOptions {
+    /// Add the transitive dependencies, following these edges
+    pub transitive: Option<TransitiveOptions>,
+    /// Keep workspace members, as dependencies on their local sources
+    pub include_members: bool,
+    /// Picks one version of crates resolved to several; all are kept without one
+    pub on_version_conflict: Option<VersionConflictPolicy>,
+    /// What to do with dependencies that Cargo.lock cannot resolve
+    pub on_unresolvable: UnresolvableVersionPolicy,
+}
+
+/// The dependencies of a project, resolved without checking the local registry
+#[derive(Debug, Default)]
+pub struct ResolvedDependencies {
+    /// The resolved dependencies, with patches applied
+    pub dependencies: DependencyCollection,
+    /// The names of the dependencies, direct and transitive, to report against
+    pub unique_names: HashSet<String>,
+    /// The depth of each dependency, when transitive dependencies are added
+    pub depths: BTreeMap<String, usize>,
+    /// The number of versions resolved for the declared dependencies
+    pub resolved_count: usize,
+    /// The number of transitive dependencies added
+    pub transitive_count: usize,
+    /// The number of dependencies redirected by `[patch]` sections
+    pub patched_count: usize,
+    /// The names of the workspace members found among the dependencies
+    pub member_names: Vec<String>,
+}
+
+/// Resolves the declared dependencies of a project against its Cargo.lock.
+///
+/// Transitive dependencies are added, patches applied and workspace members left
+/// out or replaced by their local sources, as `options` asks.
+///
+/// # Arguments
+///
+/// * `cargo_toml_files` - The project's Cargo.toml files, for its patches and members
+/// * `declared` - The dependencies declared in those files
+/// * `lock` - The project's parsed Cargo.lock
+/// * `registry_path` - Path to the cargo registry, empty when there is none
+/// * `options` - How the dependencies are resolved
+///
+/// # Returns
+///
+/// * `Result<ResolvedDependencies>` - The resolved dependencies
+///
+/// # Errors
+///
+/// Returns an error if a manifest's patches cannot be read, or if a version
+/// conflict or unresolvable dependency is an error under `options`
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use depbank::{
+///     ParsedLock, ResolveOptions, extract_dependency_info, resolve_project_dependencies,
+/// };
+/// use std::path::{Path, PathBuf};
+///
+/// let manifests = vec![PathBuf::from("Cargo.toml")];
+/// let declared = extract_dependency_info(Path::new("Cargo.toml")).unwrap();
+/// let lock = ParsedLock::read("Cargo.lock").unwrap();
+/// let resolved = resolve_project_dependencies(
+///     &manifests,
+///     &declared,
+///     &lock,
+///     Path::new(""),
+///     &ResolveOptions::default(),
+/// )
+/// .unwrap();
+/// println!("Resolved {} dependencies", resolved.dependencies.len());
+/// ```
+pub fn resolve_project_dependencies(
+    cargo_toml_files: &[PathBuf],
+    declared: &DependencyCollection,
+    lock: &ParsedLock,
+    registry_path: &Path,
+    options: &ResolveOptions,
+) -> Result<ResolvedDependencies> {
+    let mut unique_names: HashSet<String> = declared.iter().map(|dep| dep.name.clone()).collect();
+
+    let resolved_versions = resolve_dependency_versions_from_lock(lock, declared);
+    let mut resolved_versions = resolve_unresolvable_versions(
+        declared,
+        &resolved_versions,
+        registry_path,
+        options.on_unresolvable,
+    )?;
+    // Members requiring incompatible versions of a crate resolve to several versions
+    if let Some(policy) = options.on_version_conflict {
+        resolved_versions = resolve_version_conflicts(&resolved_versions, policy)?;
+    }
+    let resolved_count = resolved_versions.len();
+
+    // Add the dependencies of dependencies, which count as unique dependencies too
+    let mut depths: BTreeMap<String, usize> = BTreeMap::new();
+    let mut transitive_count = 0;
+    if let Some(transitive) = options.transitive {
+        let graph = build_dependency_graph_from_lock(lock);
+        let all = collect_transitive_dependencies_from_graph(
+            &graph,
+            &resolved_versions,
+            registry_path,
+            &transitive,
+        );
+        transitive_count = all.len() - resolved_versions.len();
+        for dep in all.iter() {
+            unique_names.insert(dep.name.clone());
+        }
+        for (dep, depth) in compute_dependency_depths(&graph, &resolved_versions) {
+            depths
+                .entry(dep.name)
+                .and_modify(|known| *known = (*known).min(depth))
+                .or_insert(depth);
+        }
+        resolved_versions = all;
+    }
+
+    // Crates redirected by [patch] sections are generated from their patch sources
+    let mut patches = HashMap::new();
+    for cargo_toml_path in cargo_toml_files {
+        patches.extend(parse_patches(cargo_toml_path)?);
+    }
+    if !patches.is_empty() {
+        resolved_versions = apply_patches(&resolved_versions, &patches);
+    }
+
+    // Workspace members are part of the project rather than registry crates
+    let members = workspace_members(cargo_toml_files);
+    let mut dependencies = DependencyCollection::new();
+    let mut member_names = Vec::new();
+    for dep in resolved_versions.iter() {
+        if !is_workspace_member_dep(dep, &members) {
+            dependencies.add(dep.clone());
+            continue;
+        }
+
+        member_names.push(dep.name.clone());
+        if options.include_members {
+            let member = members.iter().find(|member| {
+                normalize_crate_name(&member.name) == normalize_crate_name(&dep.name)
+            });
+            dependencies
+                .add(member.map_or_else(|| dep.clone(), |member| member.local_dependency(dep)));
+        } else {
+            unique_names
+                .retain(|name| normalize_crate_name(name) != normalize_crate_name(&dep.name));
+        }
+    }
+
+    Ok(ResolvedDependencies {
+        dependencies,
+        unique_names,
+        depths,
+        resolved_count,
+        transitive_count,
+        patched_count: patches.len(),
+        member_names,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extract_dependency_info;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_resolve_project_dependencies_applies_patches_and_conflicts() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let local = temp_dir.path().join("local-serde");
+        fs::create_dir_all(&local)?;
+        fs::write(
+            local.join("Cargo.toml"),
+            "[package]\nname = \"serde\"\nversion = \"1.0.200\"\n",
+        )?;
+        let manifest = temp_dir.path().join("Cargo.toml");
+        fs::write(
+            &manifest,
+            "[package]\nname = \"app\"\nversion = \"0.1.0\"\n\n\
+             [dependencies]\nserde = \"1\"\nrand = \"0.8\"\n\n\
+             [patch.crates-io]\nserde = { path = \"local-serde\" }\n",
+        )?;
+        let lock = ParsedLock::parse(
+            "version = 3\n\n\
+             [[package]]\nname = \"rand\"\nversion = \"0.7.3\"\n\n\
+             [[package]]\nname = \"rand\"\nversion = \"0.8.5\"\n\n\
+             [[package]]\nname = \"serde\"\nversion = \"1.0.200\"\n",
+        )?;
+
+        let declared = extract_dependency_info(&manifest)?;
+        let options = ResolveOptions {
+            on_version_conflict: Some(VersionConflictPolicy::Highest),
+            ..Default::default()
+        };
+        let resolved =
+            resolve_project_dependencies(&[manifest], &declared, &lock, Path::new(""), &options)?;
+
+        assert_eq!(resolved.patched_count, 1);
+        let rand: Vec<_> = resolved
+            .dependencies
+            .iter()
+            .filter(|dep| dep.name == "rand")
+            .collect();
+        assert_eq!(rand.len(), 1);
+        assert_eq!(rand[0].version, "0.8.5");
+        let serde = resolved
+            .dependencies
+            .iter()
+            .find(|dep| dep.name == "serde")
+            .unwrap();
+        assert!(serde.local_source_path().is_some());
+        Ok(())
+    }
+}
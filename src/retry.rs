@@ -0,0 +1,138 @@
+//! Retrying of filesystem operations that can fail transiently.
+//!
+//! Registries on network filesystems (e.g. `CARGO_HOME` on NFS) occasionally fail
+//! reads with errors that succeed when simply tried again. The helpers here retry
+//! such operations with exponential backoff, while failing fast on errors that
+//! retrying cannot fix, like a missing file.
+
+use std::io;
+use std::thread;
+use std::time::Duration;
+
+/// Number of attempts used for registry and source reads
+pub(crate) const DEFAULT_RETRY_ATTEMPTS: u32 = 3;
+
+/// Delay before the first retry, doubled after every failed attempt
+const INITIAL_BACKOFF: Duration = Duration::from_millis(50);
+
+/// Errors that can tell whether retrying the failed operation may succeed.
+pub trait TransientError {
+    /// Returns true if the error is likely temporary and the operation may be retried
+    fn is_transient(&self) -> bool;
+}
+
+impl TransientError for io::Error {
+    fn is_transient(&self) -> bool {
+        matches!(
+            self.kind(),
+            io::ErrorKind::Interrupted
+                | io::ErrorKind::WouldBlock
+                | io::ErrorKind::TimedOut
+                | io::ErrorKind::ResourceBusy
+                | io::ErrorKind::StaleNetworkFileHandle
+                | io::ErrorKind::ConnectionReset
+                | io::ErrorKind::ConnectionAborted
+                | io::ErrorKind::NotConnected
+        )
+    }
+}
+
+impl TransientError for codebank::Error {
+    fn is_transient(&self) -> bool {
+        match self {
+            codebank::Error::Io(e) => e.is_transient(),
+            _ => false,
+        }
+    }
+}
+
+/// Runs an operation, retrying it with exponential backoff on transient errors.
+///
+/// The operation is attempted up to `attempts` times (at least once). Between
+/// attempts the helper sleeps, starting at 50ms and doubling each time. Permanent
+/// errors such as `NotFound` or `PermissionDenied` are returned immediately without
+/// retrying.
+///
+/// # Arguments
+///
+/// * `attempts` - Maximum number of times to run the operation
+/// * `f` - The operation to run
+///
+/// # Returns
+///
+/// * `Result<T, E>` - The first successful result, or the last error encountered
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use depbank::with_retries;
+/// use std::fs;
+///
+/// let content = with_retries(3, || fs::read_to_string("Cargo.toml")).unwrap();
+/// println!("{}", content);
+/// ```
+pub fn with_retries<T, E, F>(attempts: u32, mut f: F) -> Result<T, E>
+where
+    E: TransientError,
+    F: FnMut() -> Result<T, E>,
+{
+    let mut backoff = INITIAL_BACKOFF;
+    let mut attempt = 1;
+
+    loop {
+        match f() {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt < attempts && e.is_transient() => {
+                thread::sleep(backoff);
+                backoff *= 2;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_with_retries_recovers_from_transient_errors() {
+        let mut calls = 0;
+        let result = with_retries(3, || {
+            calls += 1;
+            if calls <= 2 {
+                Err(io::Error::new(io::ErrorKind::TimedOut, "network hiccup"))
+            } else {
+                Ok("content")
+            }
+        });
+
+        assert_eq!(result.unwrap(), "content");
+        assert_eq!(calls, 3);
+    }
+
+    #[test]
+    fn test_with_retries_gives_up_after_attempts() {
+        let mut calls = 0;
+        let result: Result<(), io::Error> = with_retries(2, || {
+            calls += 1;
+            Err(io::Error::new(io::ErrorKind::Interrupted, "still failing"))
+        });
+
+        assert!(result.is_err());
+        assert_eq!(calls, 2);
+    }
+
+    #[test]
+    fn test_with_retries_does_not_retry_permanent_errors() {
+        let mut calls = 0;
+        let result: Result<(), io::Error> = with_retries(5, || {
+            calls += 1;
+            Err(io::Error::new(io::ErrorKind::NotFound, "missing"))
+        });
+
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::NotFound);
+        assert_eq!(calls, 1);
+    }
+}
@@ -0,0 +1,356 @@
+//! Software Bills of Materials of a project's dependencies.
+//!
+//! The resolved dependencies, with their licenses read from the local manifests, can
+//! be exported in the two common SBOM formats, CycloneDX and SPDX, both as JSON, for
+//! security and compliance tools to consume. Components from crates.io are identified
+//! with cargo package URLs, e.g. `pkg:cargo/serde@1.0.219`.
+
+use crate::{Dependency, DependencySource};
+use serde_json::{Value, json};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The CycloneDX specification version of the generated documents
+const CYCLONEDX_SPEC_VERSION: &str = "1.5";
+
+/// The SPDX specification version of the generated documents
+const SPDX_VERSION: &str = "SPDX-2.3";
+
+/// A dependency listed in a Software Bill of Materials
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SbomComponent {
+    /// The dependency, with its version resolved from Cargo.lock
+    pub dependency: Dependency,
+    /// The license expression from the dependency's manifest, if known
+    pub license: Option<String>,
+}
+
+impl SbomComponent {
+    /// The cargo package URL of the component.
+    ///
+    /// `pkg:cargo` URLs name crates.io packages, so components from git, local paths
+    /// or other registries have none.
+    fn purl(&self) -> Option<String> {
+        (self.dependency.source_kind() == DependencySource::CratesIo).then(|| {
+            format!(
+                "pkg:cargo/{}@{}",
+                self.dependency.name, self.dependency.version
+            )
+        })
+    }
+}
+
+/// Builds a CycloneDX JSON document listing dependencies as components.
+///
+/// Each dependency becomes a `library` component with its name, version, package URL
+/// for crates.io dependencies and, when known, its license expression. The document
+/// has no timestamp or serial number, so the same dependencies always give the same
+/// document.
+///
+/// # Arguments
+///
+/// * `components` - The dependencies to list, with their licenses
+///
+/// # Returns
+///
+/// * `Value` - The CycloneDX document
+///
+/// # Examples
+///
+/// ```rust
+/// use depbank::{Dependency, SbomComponent, to_cyclonedx};
+///
+/// let components = vec![SbomComponent {
+///     dependency: Dependency::new("serde", "1.0.219")
+///         .with_source("registry+https://github.com/rust-lang/crates.io-index"),
+///     license: Some("MIT OR Apache-2.0".to_string()),
+/// }];
+/// let bom = to_cyclonedx(&components);
+/// assert_eq!(bom["bomFormat"], "CycloneDX");
+/// assert_eq!(bom["components"][0]["purl"], "pkg:cargo/serde@1.0.219");
+/// ```
+pub fn to_cyclonedx(components: &[SbomComponent]) -> Value {
+    let components: Vec<Value> = components
+        .iter()
+        .map(|component| {
+            let dependency = &component.dependency;
+            let purl = component.purl();
+            let mut value = json!({
+                "type": "library",
+                "bom-ref": purl
+                    .clone()
+                    .unwrap_or_else(|| format!("{}@{}", dependency.name, dependency.version)),
+                "name": dependency.name,
+                "version": dependency.version,
+            });
+            if let Some(purl) = purl {
+                value["purl"] = json!(purl);
+            }
+            if let Some(license) = &component.license {
+                value["licenses"] = json!([{ "expression": license }]);
+            }
+            value
+        })
+        .collect();
+
+    json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": CYCLONEDX_SPEC_VERSION,
+        "version": 1,
+        "metadata": {
+            "tools": {
+                "components": [{
+                    "type": "application",
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                }],
+            },
+        },
+        "components": components,
+    })
+}
+
+/// Builds an SPDX JSON document listing dependencies as packages.
+///
+/// Each dependency becomes a package described by the document, with its version,
+/// package URL for crates.io dependencies and, when known, its declared license. SPDX requires a creation time,
+/// which also makes the document namespace unique.
+///
+/// # Arguments
+///
+/// * `components` - The dependencies to list, with their licenses
+/// * `document_name` - The name of the document, e.g. the project name
+/// * `created` - When the document was created
+///
+/// # Returns
+///
+/// * `Value` - The SPDX document
+///
+/// # Examples
+///
+/// ```rust
+/// use depbank::{Dependency, SbomComponent, to_spdx};
+/// use std::time::SystemTime;
+///
+/// let components = vec![SbomComponent {
+///     dependency: Dependency::new("serde", "1.0.219"),
+///     license: None,
+/// }];
+/// let document = to_spdx(&components, "my-app", SystemTime::now());
+/// assert_eq!(document["packages"][0]["licenseDeclared"], "NOASSERTION");
+/// ```
+pub fn to_spdx(components: &[SbomComponent], document_name: &str, created: SystemTime) -> Value {
+    let created_secs = created
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+
+    let packages: Vec<Value> = components
+        .iter()
+        .map(|component| {
+            let dependency = &component.dependency;
+            let mut value = json!({
+                "name": dependency.name,
+                "SPDXID": spdx_package_id(dependency),
+                "versionInfo": dependency.version,
+                "downloadLocation": "NOASSERTION",
+                "filesAnalyzed": false,
+                "licenseConcluded": "NOASSERTION",
+                "licenseDeclared": component.license.as_deref().unwrap_or("NOASSERTION"),
+                "copyrightText": "NOASSERTION",
+            });
+            if let Some(purl) = component.purl() {
+                value["externalRefs"] = json!([{
+                    "referenceCategory": "PACKAGE-MANAGER",
+                    "referenceType": "purl",
+                    "referenceLocator": purl,
+                }]);
+            }
+            value
+        })
+        .collect();
+    let relationships: Vec<Value> = components
+        .iter()
+        .map(|component| {
+            json!({
+                "spdxElementId": "SPDXRef-DOCUMENT",
+                "relationshipType": "DESCRIBES",
+                "relatedSpdxElement": spdx_package_id(&component.dependency),
+            })
+        })
+        .collect();
+
+    json!({
+        "spdxVersion": SPDX_VERSION,
+        "dataLicense": "CC0-1.0",
+        "SPDXID": "SPDXRef-DOCUMENT",
+        "name": document_name,
+        "documentNamespace": format!(
+            "https://spdx.org/spdxdocs/{}-{}",
+            spdx_id_part(document_name),
+            created_secs
+        ),
+        "creationInfo": {
+            "created": format_utc_timestamp(created_secs),
+            "creators": [format!("Tool: {}-{}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))],
+        },
+        "packages": packages,
+        "relationships": relationships,
+    })
+}
+
+/// The SPDX identifier of a dependency's package
+fn spdx_package_id(dependency: &Dependency) -> String {
+    format!(
+        "SPDXRef-Package-{}-{}",
+        spdx_id_part(&dependency.name),
+        spdx_id_part(&dependency.version)
+    )
+}
+
+/// Replaces the characters SPDX identifiers cannot hold with `-`
+fn spdx_id_part(s: &str) -> String {
+    s.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+/// Formats seconds since the Unix epoch as a UTC timestamp, e.g. `2025-05-01T12:00:00Z`
+fn format_utc_timestamp(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let seconds_of_day = secs % 86_400;
+
+    // Convert days since the epoch to a civil date, in the proleptic Gregorian calendar
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds_of_day / 3_600,
+        seconds_of_day % 3_600 / 60,
+        seconds_of_day % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    const CRATES_IO: &str = "registry+https://github.com/rust-lang/crates.io-index";
+
+    fn components() -> Vec<SbomComponent> {
+        vec![
+            SbomComponent {
+                dependency: Dependency::new("serde_json", "1.0.140").with_source(CRATES_IO),
+                license: Some("MIT OR Apache-2.0".to_string()),
+            },
+            SbomComponent {
+                dependency: Dependency::new("unlicensed", "0.1.0+build.1").with_source(CRATES_IO),
+                license: None,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_to_cyclonedx() {
+        let bom = to_cyclonedx(&components());
+
+        assert_eq!(bom["bomFormat"], "CycloneDX");
+        assert_eq!(bom["specVersion"], "1.5");
+        let listed = bom["components"].as_array().unwrap();
+        assert_eq!(listed.len(), 2);
+        assert_eq!(listed[0]["name"], "serde_json");
+        assert_eq!(listed[0]["version"], "1.0.140");
+        assert_eq!(listed[0]["purl"], "pkg:cargo/serde_json@1.0.140");
+        assert_eq!(listed[0]["licenses"][0]["expression"], "MIT OR Apache-2.0");
+        assert_eq!(listed[1]["name"], "unlicensed");
+        assert!(listed[1].get("licenses").is_none());
+
+        // Without a timestamp, the document only depends on the components
+        assert_eq!(to_cyclonedx(&components()), bom);
+    }
+
+    #[test]
+    fn test_to_spdx() {
+        let created = UNIX_EPOCH + Duration::from_secs(1_746_100_800);
+        let document = to_spdx(&components(), "my_app", created);
+
+        assert_eq!(document["spdxVersion"], "SPDX-2.3");
+        assert_eq!(document["creationInfo"]["created"], "2025-05-01T12:00:00Z");
+        assert_eq!(
+            document["documentNamespace"],
+            "https://spdx.org/spdxdocs/my-app-1746100800"
+        );
+        let packages = document["packages"].as_array().unwrap();
+        assert_eq!(packages.len(), 2);
+        assert_eq!(packages[0]["name"], "serde_json");
+        assert_eq!(packages[0]["SPDXID"], "SPDXRef-Package-serde-json-1.0.140");
+        assert_eq!(packages[0]["licenseDeclared"], "MIT OR Apache-2.0");
+        assert_eq!(
+            packages[1]["SPDXID"],
+            "SPDXRef-Package-unlicensed-0.1.0-build.1"
+        );
+        assert_eq!(packages[1]["licenseDeclared"], "NOASSERTION");
+        assert_eq!(document["relationships"].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_non_registry_components_have_no_purl() {
+        let components = vec![
+            SbomComponent {
+                dependency: Dependency::new("vendored", "0.1.0")
+                    .with_source("git+https://github.com/example/vendored#abc123"),
+                license: None,
+            },
+            SbomComponent {
+                dependency: Dependency::new("local", "0.2.0"),
+                license: None,
+            },
+        ];
+
+        let bom = to_cyclonedx(&components);
+        let listed = bom["components"].as_array().unwrap();
+        assert!(
+            listed
+                .iter()
+                .all(|component| component.get("purl").is_none())
+        );
+        assert_eq!(listed[0]["bom-ref"], "vendored@0.1.0");
+
+        let document = to_spdx(&components, "my_app", UNIX_EPOCH);
+        let packages = document["packages"].as_array().unwrap();
+        assert!(
+            packages
+                .iter()
+                .all(|package| package.get("externalRefs").is_none())
+        );
+    }
+
+    #[test]
+    fn test_format_utc_timestamp() {
+        assert_eq!(format_utc_timestamp(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_utc_timestamp(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(format_utc_timestamp(1_735_689_599), "2024-12-31T23:59:59Z");
+    }
+}
@@ -0,0 +1,204 @@
+//! Breakdown of a dependency's size by source file.
+//!
+//! When a code bank is larger than expected, these show which of the dependency's
+//! files contribute the most, both in its sources and in the generated bank, to help
+//! decide what to leave out.
+
+use crate::{FileStats, TokenOptions, calculate_directory_tokens, calculate_tokens};
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Calculates the size and tokens of every file in a crate's source tree, largest
+/// first.
+///
+/// Hidden directories and `target` are skipped. Files with the same token count are
+/// ordered by path.
+///
+/// # Arguments
+///
+/// * `source_path` - Path to the crate's source directory
+/// * `extension` - Optional file extension filter (e.g., "rs")
+/// * `options` - Options controlling how tokens are counted
+///
+/// # Returns
+///
+/// * `Result<Vec<FileStats>>` - The stats of every file, by descending token count
+///
+/// # Errors
+///
+/// Returns an error if the source path is not a directory, or if a directory or
+/// file cannot be read or tokenized
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use depbank::{TokenOptions, analyze_source_sizes};
+/// use std::path::Path;
+///
+/// let crate_dir = Path::new("/path/to/registry/serde-1.0.219");
+/// let stats = analyze_source_sizes(crate_dir, Some("rs"), &TokenOptions::default()).unwrap();
+/// for stats in stats.iter().take(5) {
+///     println!("{}: {} tokens", stats.path.display(), stats.token_count);
+/// }
+/// ```
+pub fn analyze_source_sizes(
+    source_path: &Path,
+    extension: Option<&str>,
+    options: &TokenOptions,
+) -> Result<Vec<FileStats>> {
+    let mut dirs = vec![source_path.to_path_buf()];
+    collect_source_dirs(source_path, &mut dirs)?;
+
+    let mut files = Vec::new();
+    for dir in &dirs {
+        files.extend(calculate_directory_tokens(dir, extension, options)?.into_values());
+    }
+    files.sort_by(|a, b| {
+        b.token_count
+            .cmp(&a.token_count)
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    Ok(files)
+}
+
+/// Adds the subdirectories of a directory, recursively, skipping hidden ones and
+/// build output
+fn collect_source_dirs(dir: &Path, dirs: &mut Vec<PathBuf>) -> Result<()> {
+    let mut subdirs = Vec::new();
+    for entry in
+        fs::read_dir(dir).with_context(|| format!("Failed to read directory: {}", dir.display()))?
+    {
+        let path = entry?.path();
+        let skipped = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with('.') || name == "target");
+        if path.is_dir() && !skipped {
+            subdirs.push(path);
+        }
+    }
+    subdirs.sort();
+
+    for subdir in subdirs {
+        dirs.push(subdir.clone());
+        collect_source_dirs(&subdir, dirs)?;
+    }
+    Ok(())
+}
+
+/// Counts the tokens of each source file's section in a generated code bank.
+///
+/// Banks have a `## <path>` heading for every documented file, relative to the crate
+/// root. Content before the first file section, such as the package manifest, is not
+/// attributed to any file.
+///
+/// # Arguments
+///
+/// * `bank` - The content of a code bank
+/// * `options` - Options controlling how tokens are counted
+///
+/// # Returns
+///
+/// * `Result<BTreeMap<String, usize>>` - The tokens of each file's section, by path
+///
+/// # Errors
+///
+/// Returns an error if a section cannot be tokenized
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use depbank::{TokenOptions, bank_tokens_by_file};
+///
+/// let bank = "# Code Bank\n## src/lib.rs\n```rust\npub fn parse() {}\n```\n";
+/// let tokens = bank_tokens_by_file(bank, &TokenOptions::default()).unwrap();
+/// println!("src/lib.rs: {} tokens", tokens["src/lib.rs"]);
+/// ```
+pub fn bank_tokens_by_file(bank: &str, options: &TokenOptions) -> Result<BTreeMap<String, usize>> {
+    let mut sections: Vec<(String, String)> = Vec::new();
+    let mut in_code_block = false;
+    for line in bank.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") {
+            in_code_block = !in_code_block;
+        } else if !in_code_block
+            && let Some(path) = trimmed.strip_prefix("## ")
+            && path != "Package File"
+        {
+            sections.push((path.to_string(), String::new()));
+        }
+
+        if let Some((_, content)) = sections.last_mut() {
+            content.push_str(line);
+            content.push('\n');
+        }
+    }
+
+    sections
+        .into_iter()
+        .map(|(path, content)| Ok((path, calculate_tokens(&content, options)?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_analyze_source_sizes() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let crate_dir = temp_dir.path().join("bigdep-1.2.0");
+        fs::create_dir_all(crate_dir.join("src").join("tables"))?;
+        fs::create_dir_all(crate_dir.join(".github"))?;
+        fs::write(
+            crate_dir.join("Cargo.toml"),
+            "[package]\nname = \"bigdep\"\nversion = \"1.2.0\"\n",
+        )?;
+        fs::write(crate_dir.join("src").join("lib.rs"), "pub mod tables;\n")?;
+        let table: String = (0..200)
+            .map(|i| format!("    0x{:04x}, // code point {}\n", i, i))
+            .collect();
+        fs::write(
+            crate_dir.join("src").join("tables").join("mod.rs"),
+            format!("pub const TABLE: &[u32] = &[\n{}];\n", table),
+        )?;
+        fs::write(crate_dir.join(".github").join("ci.rs"), "fn main() {}\n")?;
+
+        let stats = analyze_source_sizes(&crate_dir, Some("rs"), &TokenOptions::default())?;
+        let paths: Vec<PathBuf> = stats
+            .iter()
+            .map(|stats| stats.path.strip_prefix(&crate_dir).unwrap().to_path_buf())
+            .collect();
+        // The largest file comes first, the manifest and hidden directories are skipped
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("src/tables/mod.rs"),
+                PathBuf::from("src/lib.rs")
+            ]
+        );
+        assert!(stats[0].token_count > stats[1].token_count);
+        assert!(stats[0].size_bytes > 200 * 20);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bank_tokens_by_file() -> Result<()> {
+        let bank = "# Code Bank\n## Package File\n```toml\n[package]\nname = \"bigdep\"\n```\n\
+## src/lib.rs\n```rust\npub mod tables;\n```\n\
+## src/tables/mod.rs\n```rust\n/// Docs\n## Not a heading\npub const TABLE: &[u32] = &[1, 2, 3, 4, 5, 6, 7, 8];\n```\n";
+
+        let tokens = bank_tokens_by_file(bank, &TokenOptions::default())?;
+        assert_eq!(
+            tokens.keys().collect::<Vec<_>>(),
+            vec!["src/lib.rs", "src/tables/mod.rs"]
+        );
+        assert!(tokens["src/tables/mod.rs"] > tokens["src/lib.rs"]);
+
+        Ok(())
+    }
+}
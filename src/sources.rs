@@ -0,0 +1,71 @@
+//! Walking a directory for its Rust source files.
+//!
+//! Usages are searched in a project's sources, deprecations in a crate's and binary
+//! targets below their entry points, all through [`collect_rust_files`], so they skip
+//! the same directories.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Collects the Rust source files under a directory, sorted by path.
+///
+/// Hidden directories and `target` directories are skipped, and so are directories
+/// that cannot be read.
+pub(crate) fn collect_rust_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    walk(dir, &mut files);
+    files.sort();
+    files
+}
+
+fn walk(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let path = entry.path();
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+
+        if path.is_dir() {
+            if !name.starts_with('.') && name != "target" {
+                walk(&path, files);
+            }
+        } else if path.extension().is_some_and(|ext| ext == "rs") {
+            files.push(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_collect_rust_files() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let root = temp_dir.path();
+        for dir in ["src/nested", "target/debug", ".git"] {
+            fs::create_dir_all(root.join(dir))?;
+        }
+        for file in [
+            "src/lib.rs",
+            "src/nested/mod.rs",
+            "src/notes.md",
+            "target/debug/build.rs",
+            ".git/hook.rs",
+        ] {
+            fs::write(root.join(file), "")?;
+        }
+
+        assert_eq!(
+            collect_rust_files(root),
+            vec![root.join("src/lib.rs"), root.join("src/nested/mod.rs")]
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,371 @@
+//! Detection of the build targets of a dependency's source tree.
+//!
+//! Code banks are normally generated from a crate's library, but tool dependencies
+//! pulled in as crates may only ship binaries. The helpers here inspect a crate
+//! directory (and its `Cargo.toml`, if any) to tell which targets it provides.
+
+use crate::ParsedManifest;
+use crate::sources::collect_rust_files;
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use syn::punctuated::Punctuated;
+use syn::{Meta, Token};
+
+/// Checks whether a crate directory provides a library target.
+///
+/// A crate has a library when its manifest declares a `[lib]` target whose source file
+/// exists, or when it follows the conventional `src/lib.rs` layout.
+///
+/// # Arguments
+///
+/// * `crate_dir` - Path to the crate's source directory
+///
+/// # Returns
+///
+/// * `bool` - True if the crate has a library target
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use depbank::has_lib_target;
+/// use std::path::Path;
+///
+/// if !has_lib_target(Path::new("/path/to/registry/ripgrep-14.1.0")) {
+///     println!("binary-only crate");
+/// }
+/// ```
+pub fn has_lib_target(crate_dir: &Path) -> bool {
+    manifest_has_lib_target(crate_dir, &read_manifest(crate_dir))
+}
+
+/// Checks whether a crate directory provides a library target, given its parsed
+/// manifest
+pub(crate) fn manifest_has_lib_target(crate_dir: &Path, manifest: &ParsedManifest) -> bool {
+    crate_dir.join(lib_path(manifest)).is_file()
+}
+
+/// Checks whether a crate's library can be built without the standard library.
+///
+/// A library is `no_std` when its root file has a `#![no_std]` attribute, or one
+/// applied conditionally with `#![cfg_attr(..., no_std)]`, as crates with an
+/// optional `std` feature do.
+///
+/// # Arguments
+///
+/// * `crate_dir` - Path to the crate's source directory
+///
+/// # Returns
+///
+/// * `bool` - True if the crate has a library that supports `no_std`
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use depbank::is_no_std;
+/// use std::path::Path;
+///
+/// if is_no_std(Path::new("/path/to/registry/libm-0.2.11")) {
+///     println!("usable without std");
+/// }
+/// ```
+pub fn is_no_std(crate_dir: &Path) -> bool {
+    manifest_is_no_std(crate_dir, &read_manifest(crate_dir))
+}
+
+/// Checks whether a crate's library can be built without the standard library, given
+/// its parsed manifest
+pub(crate) fn manifest_is_no_std(crate_dir: &Path, manifest: &ParsedManifest) -> bool {
+    let Ok(source) = fs::read_to_string(crate_dir.join(lib_path(manifest))) else {
+        return false;
+    };
+
+    // Sources syn cannot parse are not assumed to be no_std
+    let Ok(syntax) = syn::parse_file(&source) else {
+        return false;
+    };
+    syntax.attrs.iter().any(|attr| is_no_std_meta(&attr.meta))
+}
+
+/// Checks whether an attribute is `no_std`, possibly applied through (nested)
+/// `cfg_attr(predicate, ...)` attributes
+fn is_no_std_meta(meta: &Meta) -> bool {
+    if meta.path().is_ident("no_std") {
+        return matches!(meta, Meta::Path(_));
+    }
+    let Meta::List(list) = meta else {
+        return false;
+    };
+    if !list.path.is_ident("cfg_attr") {
+        return false;
+    }
+
+    // The predicate comes first, followed by the attributes it applies
+    list.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)
+        .is_ok_and(|metas| metas.iter().skip(1).any(is_no_std_meta))
+}
+
+/// The path of a crate's library root, from the manifest's `[lib]` target or the
+/// conventional `src/lib.rs`
+fn lib_path(manifest: &ParsedManifest) -> PathBuf {
+    manifest
+        .section("lib")
+        .and_then(|lib| lib.get("path"))
+        .and_then(|path| path.as_str())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("src/lib.rs"))
+}
+
+/// Lists the Rust source files that make up a crate's binary targets.
+///
+/// Binary entry points are `src/main.rs`, files and directories under `src/bin`, and
+/// any `[[bin]]` targets with an explicit `path` in the manifest. Since modules of a
+/// binary live next to its entry point, every `.rs` file in the directories holding
+/// the entry points is included as well.
+///
+/// # Arguments
+///
+/// * `crate_dir` - Path to the crate's source directory
+///
+/// # Returns
+///
+/// * `Result<Vec<PathBuf>>` - Sorted source file paths, relative to `crate_dir`
+///
+/// # Errors
+///
+/// Returns an error if a source directory cannot be read
+pub fn binary_sources(crate_dir: &Path) -> Result<Vec<PathBuf>> {
+    manifest_binary_sources(crate_dir, &read_manifest(crate_dir))
+}
+
+/// Lists the Rust source files that make up a crate's binary targets, given its
+/// parsed manifest
+pub(crate) fn manifest_binary_sources(
+    crate_dir: &Path,
+    manifest: &ParsedManifest,
+) -> Result<Vec<PathBuf>> {
+    let mut entry_points = Vec::new();
+
+    let main_rs = crate_dir.join("src").join("main.rs");
+    if main_rs.is_file() {
+        entry_points.push(main_rs);
+    }
+
+    let bin_dir = crate_dir.join("src").join("bin");
+    if bin_dir.is_dir() {
+        entry_points.push(bin_dir);
+    }
+
+    if let Some(bins) = manifest.section("bin").and_then(|bins| bins.as_array()) {
+        for bin in bins {
+            if let Some(path) = bin.get("path").and_then(|path| path.as_str()) {
+                let path = crate_dir.join(path);
+                if path.is_file() {
+                    entry_points.push(path);
+                }
+            }
+        }
+    }
+
+    let mut sources = Vec::new();
+    for entry_point in &entry_points {
+        let dir = if entry_point.is_dir() {
+            entry_point.as_path()
+        } else {
+            entry_point.parent().unwrap_or(crate_dir)
+        };
+        sources.extend(collect_rust_files(dir));
+    }
+
+    let mut sources: Vec<PathBuf> = sources
+        .iter()
+        .filter_map(|path| path.strip_prefix(crate_dir).ok())
+        .map(Path::to_path_buf)
+        .collect();
+    sources.sort();
+    sources.dedup();
+
+    Ok(sources)
+}
+
+/// Finds the build script of a crate directory.
+///
+/// As in cargo, the manifest's `package.build` key names the build script, or turns
+/// it off with `false`. Without the key, a top-level `build.rs` is the build script.
+///
+/// # Arguments
+///
+/// * `crate_dir` - Path to the crate's source directory
+///
+/// # Returns
+///
+/// * `Option<PathBuf>` - The path of the build script relative to `crate_dir`, if
+///   the crate has one
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use depbank::build_script;
+/// use std::path::Path;
+///
+/// if let Some(script) = build_script(Path::new("/path/to/registry/openssl-sys-0.9.109")) {
+///     println!("build script: {}", script.display());
+/// }
+/// ```
+pub fn build_script(crate_dir: &Path) -> Option<PathBuf> {
+    manifest_build_script(crate_dir, &read_manifest(crate_dir))
+}
+
+/// Finds the build script of a crate directory, given its parsed manifest
+pub(crate) fn manifest_build_script(
+    crate_dir: &Path,
+    manifest: &ParsedManifest,
+) -> Option<PathBuf> {
+    let path = match manifest.package_field("build") {
+        Some(toml::Value::String(path)) => PathBuf::from(path),
+        Some(toml::Value::Boolean(false)) => return None,
+        _ => PathBuf::from("build.rs"),
+    };
+    crate_dir.join(&path).is_file().then_some(path)
+}
+
+/// Builds the bank section showing a crate's build script, if it has one
+pub(crate) fn build_script_section(crate_dir: &Path) -> Result<Option<String>> {
+    let Some(script) = build_script(crate_dir) else {
+        return Ok(None);
+    };
+    let path = crate_dir.join(&script);
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read build script: {}", path.display()))?;
+
+    Ok(Some(format!(
+        "## Build script: `{}`\n\nCargo runs this script before compiling the crate; it can \
+         read environment variables and set cfg flags.\n\n```rust\n{}\n```\n",
+        script.display(),
+        content.trim_end()
+    )))
+}
+
+/// Reads and parses the crate's Cargo.toml, empty if missing or invalid
+fn read_manifest(crate_dir: &Path) -> ParsedManifest {
+    ParsedManifest::read(crate_dir).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_has_lib_target() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let crate_dir = temp_dir.path();
+        fs::create_dir_all(crate_dir.join("src"))?;
+        fs::write(crate_dir.join("src").join("main.rs"), "fn main() {}\n")?;
+        assert!(!has_lib_target(crate_dir));
+
+        // A custom library path declared in the manifest
+        fs::write(
+            crate_dir.join("Cargo.toml"),
+            "[package]\nname = \"demo\"\n\n[lib]\npath = \"lib/demo.rs\"\n",
+        )?;
+        assert!(!has_lib_target(crate_dir));
+        fs::create_dir_all(crate_dir.join("lib"))?;
+        fs::write(crate_dir.join("lib").join("demo.rs"), "pub fn demo() {}\n")?;
+        assert!(has_lib_target(crate_dir));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_no_std() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let crate_dir = temp_dir.path();
+        fs::create_dir_all(crate_dir.join("src"))?;
+        let lib = crate_dir.join("src").join("lib.rs");
+
+        fs::write(&lib, "//! Docs.\n\npub fn api() {}\n")?;
+        assert!(!is_no_std(crate_dir));
+        fs::write(&lib, "// #![no_std] is not needed here\npub fn api() {}\n")?;
+        assert!(!is_no_std(crate_dir));
+        fs::write(&lib, "#![doc = \"Works in no_std too\"]\npub fn api() {}\n")?;
+        assert!(!is_no_std(crate_dir));
+        fs::write(
+            &lib,
+            "#![cfg_attr(feature = \"no_std\", deny(missing_docs))]\n",
+        )?;
+        assert!(!is_no_std(crate_dir));
+
+        fs::write(&lib, "//! Docs.\n#![no_std]\n")?;
+        assert!(is_no_std(crate_dir));
+        fs::write(
+            &lib,
+            "#![cfg_attr(\n    not(feature = \"std\"),\n    no_std\n)]\npub fn api() {}\n",
+        )?;
+        assert!(is_no_std(crate_dir));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_build_script() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let crate_dir = temp_dir.path();
+        fs::write(crate_dir.join("Cargo.toml"), "[package]\nname = \"demo\"\n")?;
+        assert_eq!(build_script(crate_dir), None);
+
+        // A top-level build.rs, unless the manifest names another script or none
+        fs::write(crate_dir.join("build.rs"), "fn main() {}\n")?;
+        assert_eq!(build_script(crate_dir), Some(PathBuf::from("build.rs")));
+        fs::write(
+            crate_dir.join("Cargo.toml"),
+            "[package]\nname = \"demo\"\nbuild = false\n",
+        )?;
+        assert_eq!(build_script(crate_dir), None);
+        fs::create_dir_all(crate_dir.join("tools"))?;
+        fs::write(crate_dir.join("tools").join("gen.rs"), "fn main() {}\n")?;
+        fs::write(
+            crate_dir.join("Cargo.toml"),
+            "[package]\nname = \"demo\"\nbuild = \"tools/gen.rs\"\n",
+        )?;
+        assert_eq!(build_script(crate_dir), Some(PathBuf::from("tools/gen.rs")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_binary_sources() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let crate_dir = temp_dir.path();
+        fs::create_dir_all(crate_dir.join("src").join("bin"))?;
+        fs::create_dir_all(crate_dir.join("tools"))?;
+        fs::write(
+            crate_dir.join("Cargo.toml"),
+            "[package]\nname = \"demo\"\n\n[[bin]]\nname = \"extra\"\npath = \"tools/extra.rs\"\n",
+        )?;
+        fs::write(
+            crate_dir.join("src").join("main.rs"),
+            "mod cli;\nfn main() {}\n",
+        )?;
+        fs::write(crate_dir.join("src").join("cli.rs"), "fn parse() {}\n")?;
+        fs::write(
+            crate_dir.join("src").join("bin").join("helper.rs"),
+            "fn main() {}\n",
+        )?;
+        fs::write(crate_dir.join("tools").join("extra.rs"), "fn main() {}\n")?;
+        fs::write(crate_dir.join("build.rs"), "fn main() {}\n")?;
+
+        let sources = binary_sources(crate_dir)?;
+        assert_eq!(
+            sources,
+            vec![
+                PathBuf::from("src/bin/helper.rs"),
+                PathBuf::from("src/cli.rs"),
+                PathBuf::from("src/main.rs"),
+                PathBuf::from("tools/extra.rs"),
+            ]
+        );
+
+        Ok(())
+    }
+}
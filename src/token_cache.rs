@@ -0,0 +1,153 @@
+//! Caching of token counts across runs.
+//!
+//! Tokenizing is the slow part of counting a directory's tokens, and repeated runs,
+//! such as in CI, mostly count files that did not change. The counts are cached in
+//! the directory's [`TOKEN_CACHE_FILE`], keyed on each file's name and content hash,
+//! so only new and changed files are tokenized again. Counts depend on how tokens
+//! are counted, so a cache written with other [`TokenOptions`], or with a tokenizer
+//! file that changed since, is not used. Only depbank output directories, which
+//! hold an `index.json`, are cached.
+
+use crate::fingerprint::fnv1a;
+use crate::{TokenOptions, TokenizerModel};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// The file caching the token counts of a directory's files, in that directory
+pub const TOKEN_CACHE_FILE: &str = ".tokens-cache.json";
+
+/// The cached token counts of a directory's files
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct TokenCache {
+    /// How the tokens were counted
+    counted_with: String,
+    /// The cached counts, by file name
+    files: BTreeMap<String, CachedCount>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedCount {
+    /// The FNV-1a hash of the file's content, in hexadecimal
+    hash: String,
+    token_count: usize,
+}
+
+impl TokenCache {
+    /// Loads the cache of a directory. A missing or unreadable cache, or one written
+    /// with other options, gives an empty cache
+    pub(crate) fn load(dir: &Path, options: &TokenOptions) -> Self {
+        let counted_with = counted_with(options);
+        fs::read_to_string(dir.join(TOKEN_CACHE_FILE))
+            .ok()
+            .and_then(|content| serde_json::from_str::<TokenCache>(&content).ok())
+            .filter(|cache| cache.counted_with == counted_with)
+            .unwrap_or(TokenCache {
+                counted_with,
+                files: BTreeMap::new(),
+            })
+    }
+
+    /// The cached token count of a file, if its content did not change
+    pub(crate) fn get(&self, file_name: &str, content: &str) -> Option<usize> {
+        self.files
+            .get(file_name)
+            .filter(|cached| cached.hash == content_hash(content))
+            .map(|cached| cached.token_count)
+    }
+
+    /// Caches the token count of a file
+    pub(crate) fn insert(&mut self, file_name: &str, content: &str, token_count: usize) {
+        self.files.insert(
+            file_name.to_string(),
+            CachedCount {
+                hash: content_hash(content),
+                token_count,
+            },
+        );
+    }
+
+    /// Writes the cache into a directory, dropping the files that no longer exist
+    pub(crate) fn save(mut self, dir: &Path) -> Result<()> {
+        self.files
+            .retain(|file_name, _| dir.join(file_name).is_file());
+        let path = dir.join(TOKEN_CACHE_FILE);
+        let content = serde_json::to_string_pretty(&self)?;
+        fs::write(&path, content)
+            .with_context(|| format!("Failed to write token cache: {}", path.display()))
+    }
+}
+
+/// Describes how tokens are counted, as far as it changes the counts. A tokenizer
+/// file is described by its content too, as it may be edited in place
+fn counted_with(options: &TokenOptions) -> String {
+    let tokenizer_hash = match &options.model {
+        TokenizerModel::Custom(path) => fs::read(path)
+            .map(|content| format!(" tokenizer_hash={:016x}", fnv1a(&content)))
+            .unwrap_or_default(),
+        TokenizerModel::Pretrained(_) => String::new(),
+    };
+    format!(
+        "{:?}{} special_tokens={} max_length={:?}",
+        options.model, tokenizer_hash, options.include_special_tokens, options.max_length
+    )
+}
+
+fn content_hash(content: &str) -> String {
+    format!("{:016x}", fnv1a(content.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_token_cache_round_trip() -> Result<()> {
+        let temp_dir = tempdir()?;
+        fs::write(temp_dir.path().join("serde.md"), "# serde\n")?;
+        let options = TokenOptions::default();
+
+        let mut cache = TokenCache::load(temp_dir.path(), &options);
+        assert_eq!(cache.get("serde.md", "# serde\n"), None);
+        cache.insert("serde.md", "# serde\n", 3);
+        cache.insert("removed.md", "# removed\n", 3);
+        cache.save(temp_dir.path())?;
+
+        let cache = TokenCache::load(temp_dir.path(), &options);
+        assert_eq!(cache.get("serde.md", "# serde\n"), Some(3));
+        // Changed and removed files are not served from the cache
+        assert_eq!(cache.get("serde.md", "# serde_json\n"), None);
+        assert_eq!(cache.get("removed.md", "# removed\n"), None);
+
+        // Counts are not reused for other counting options
+        let options = TokenOptions {
+            include_special_tokens: true,
+            ..Default::default()
+        };
+        let cache = TokenCache::load(temp_dir.path(), &options);
+        assert_eq!(cache.get("serde.md", "# serde\n"), None);
+
+        // Nor after a tokenizer file changes in place
+        let tokenizer = temp_dir.path().join("tokenizer.json");
+        fs::write(&tokenizer, "{\"version\": 1}")?;
+        let options = TokenOptions {
+            model: TokenizerModel::Custom(tokenizer.clone()),
+            ..Default::default()
+        };
+        let mut cache = TokenCache::load(temp_dir.path(), &options);
+        cache.insert("serde.md", "# serde\n", 4);
+        cache.save(temp_dir.path())?;
+        assert_eq!(
+            TokenCache::load(temp_dir.path(), &options).get("serde.md", "# serde\n"),
+            Some(4)
+        );
+        fs::write(&tokenizer, "{\"version\": 2}")?;
+        let cache = TokenCache::load(temp_dir.path(), &options);
+        assert_eq!(cache.get("serde.md", "# serde\n"), None);
+
+        Ok(())
+    }
+}
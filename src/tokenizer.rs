@@ -0,0 +1,385 @@
+//! Selection and loading of the tokenizer used to count tokens.
+//!
+//! Token counts default to the pretrained `bert-base-cased` tokenizer. Another
+//! pretrained model from the Hugging Face hub, or a local `tokenizer.json` file, can
+//! be chosen to match the model the code banks are meant for.
+//!
+//! Loading a tokenizer reads, or even downloads, its definition, so every token
+//! count in a process shares one instance per model, see [`shared_tokenizer`].
+
+use crate::DepBankError;
+use anyhow::Result;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
+use tokenizers::tokenizer::Tokenizer;
+use tokenizers::utils::truncation::TruncationParams;
+
+/// Name of the pretrained tokenizer used by default
+pub const DEFAULT_TOKENIZER_MODEL: &str = "bert-base-cased";
+
+/// The tokenizer used to count tokens
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TokenizerModel {
+    /// A pretrained tokenizer from the Hugging Face hub, by model name
+    Pretrained(String),
+    /// A tokenizer loaded from a local `tokenizer.json` file
+    Custom(PathBuf),
+}
+
+impl Default for TokenizerModel {
+    fn default() -> Self {
+        Self::Pretrained(DEFAULT_TOKENIZER_MODEL.to_string())
+    }
+}
+
+impl TokenizerModel {
+    /// Interprets a `--tokenizer` argument.
+    ///
+    /// Values naming an existing file or ending in `.json` are tokenizer files, any
+    /// other value is the name of a pretrained model.
+    pub fn parse(value: &str) -> Self {
+        let path = Path::new(value);
+        if path.is_file() || path.extension().is_some_and(|ext| ext == "json") {
+            Self::Custom(path.to_path_buf())
+        } else {
+            Self::Pretrained(value.to_string())
+        }
+    }
+}
+
+/// Loads the tokenizer for a model.
+///
+/// Truncation and padding configured in the tokenizer definition are disabled, so
+/// every token of long inputs is counted; see [`set_max_length`] to truncate instead.
+///
+/// # Arguments
+///
+/// * `model` - The tokenizer to load
+///
+/// # Returns
+///
+/// * `Result<Tokenizer>` - The loaded tokenizer
+///
+/// # Errors
+///
+/// Returns [`DepBankError::TokenizerLoad`] if a custom tokenizer file is missing or is
+/// not a tokenizer definition, and [`DepBankError::UnknownTokenizerModel`] if a
+/// pretrained model name is invalid or does not exist on the hub.
+pub fn load_tokenizer(model: &TokenizerModel) -> Result<Tokenizer> {
+    let mut tokenizer = match model {
+        TokenizerModel::Pretrained(name) => load_pretrained(name)?,
+        TokenizerModel::Custom(path) => load_custom(path)?,
+    };
+    set_max_length(&mut tokenizer, None)?;
+    tokenizer.with_padding(None);
+
+    Ok(tokenizer)
+}
+
+/// A model with the maximum length its inputs are truncated at
+type SharedTokenizerKey = (TokenizerModel, Option<usize>);
+
+/// The tokenizers loaded so far in this process
+#[derive(Default)]
+struct SharedTokenizers {
+    tokenizers: HashMap<SharedTokenizerKey, Arc<Tokenizer>>,
+    loads: HashMap<SharedTokenizerKey, usize>,
+}
+
+static SHARED_TOKENIZERS: OnceLock<Mutex<SharedTokenizers>> = OnceLock::new();
+
+/// Returns the tokenizer for a model, loading it on first use only.
+///
+/// The tokenizer is shared by every caller in the process, including the parallel
+/// token counts of generation and of the `tokens` command, so a model is loaded, or
+/// downloaded, once. A failed load is not remembered, and is retried on the next
+/// call.
+///
+/// # Arguments
+///
+/// * `model` - The tokenizer to load
+/// * `max_length` - The maximum number of tokens to truncate inputs at, see
+///   [`set_max_length`]
+///
+/// # Returns
+///
+/// * `Result<Arc<Tokenizer>>` - The shared tokenizer
+///
+/// # Errors
+///
+/// Returns the errors of [`load_tokenizer`] and [`set_max_length`]
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use depbank::{TokenizerModel, shared_tokenizer};
+///
+/// let tokenizer = shared_tokenizer(&TokenizerModel::default(), None).unwrap();
+/// // Served without loading the tokenizer again
+/// let same = shared_tokenizer(&TokenizerModel::default(), None).unwrap();
+/// assert!(std::sync::Arc::ptr_eq(&tokenizer, &same));
+/// ```
+pub fn shared_tokenizer(
+    model: &TokenizerModel,
+    max_length: Option<usize>,
+) -> Result<Arc<Tokenizer>> {
+    // The lock is held while loading, so concurrent callers wait for the first load
+    // instead of loading the same model again
+    let mut shared = SHARED_TOKENIZERS
+        .get_or_init(Mutex::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    let key = (model.clone(), max_length);
+    if let Some(tokenizer) = shared.tokenizers.get(&key) {
+        return Ok(Arc::clone(tokenizer));
+    }
+
+    let mut tokenizer = load_tokenizer(model)?;
+    set_max_length(&mut tokenizer, max_length)?;
+    let tokenizer = Arc::new(tokenizer);
+    *shared.loads.entry(key.clone()).or_default() += 1;
+    shared.tokenizers.insert(key, Arc::clone(&tokenizer));
+    Ok(tokenizer)
+}
+
+/// Number of times [`shared_tokenizer`] loaded a model with a maximum length
+#[cfg(test)]
+pub(crate) fn shared_tokenizer_loads(model: &TokenizerModel, max_length: Option<usize>) -> usize {
+    SHARED_TOKENIZERS
+        .get_or_init(Mutex::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .loads
+        .get(&(model.clone(), max_length))
+        .copied()
+        .unwrap_or_default()
+}
+
+/// Sets the maximum number of tokens a tokenizer produces for an input.
+///
+/// Longer inputs are truncated like a model with this context length would, so the
+/// count shows how much of an input fits. `None` disables truncation.
+///
+/// # Arguments
+///
+/// * `tokenizer` - The tokenizer to configure
+/// * `max_length` - The maximum number of tokens, including special tokens
+///
+/// # Errors
+///
+/// Returns an error if the maximum is too small for the tokenizer's special tokens
+pub fn set_max_length(tokenizer: &mut Tokenizer, max_length: Option<usize>) -> Result<()> {
+    let truncation = max_length.map(|max_length| TruncationParams {
+        max_length,
+        ..Default::default()
+    });
+    tokenizer
+        .with_truncation(truncation)
+        .map_err(|e| anyhow::anyhow!("Failed to configure tokenizer truncation: {}", e))?;
+
+    Ok(())
+}
+
+fn load_pretrained(name: &str) -> Result<Tokenizer> {
+    if !is_valid_model_name(name) {
+        return Err(DepBankError::UnknownTokenizerModel {
+            name: name.to_string(),
+            reason: "not a valid model name, expected `name` or `owner/name`".to_string(),
+        }
+        .into());
+    }
+
+    Tokenizer::from_pretrained(name, None).map_err(|e| {
+        if http_status(&*e) == Some(404) {
+            DepBankError::UnknownTokenizerModel {
+                name: name.to_string(),
+                reason: "no such model on the Hugging Face hub".to_string(),
+            }
+            .into()
+        } else {
+            anyhow::anyhow!("Failed to load tokenizer {}: {}", name, e)
+        }
+    })
+}
+
+/// The HTTP status of the failed request behind an error, if a request failed with
+/// an error status.
+///
+/// The hub client wraps the `ureq` error of the request, possibly boxed and behind
+/// retries, so the whole chain of sources is searched.
+fn http_status(error: &(dyn std::error::Error + 'static)) -> Option<u16> {
+    std::iter::successors(Some(error), |error| error.source()).find_map(|error| {
+        let error = error.downcast_ref::<ureq::Error>().or_else(|| {
+            error
+                .downcast_ref::<Box<ureq::Error>>()
+                .map(|error| &**error)
+        })?;
+        match error {
+            ureq::Error::Status(status, _) => Some(*status),
+            ureq::Error::Transport(_) => None,
+        }
+    })
+}
+
+fn load_custom(path: &Path) -> Result<Tokenizer> {
+    let load_error = |reason: String| DepBankError::TokenizerLoad {
+        path: path.to_path_buf(),
+        reason,
+    };
+
+    // Check the file looks like a tokenizer definition first, since the tokenizers
+    // crate reports unrelated JSON with a confusing deserialization error
+    let content = fs::read_to_string(path).map_err(|e| load_error(e.to_string()))?;
+    let json: serde_json::Value = serde_json::from_str(&content)
+        .map_err(|e| load_error(format!("not a valid JSON file ({})", e)))?;
+    if json.get("model").is_none() {
+        return Err(
+            load_error("not a tokenizer file, it has no \"model\" definition".to_string()).into(),
+        );
+    }
+
+    Ok(content
+        .parse::<Tokenizer>()
+        .map_err(|e| load_error(e.to_string()))?)
+}
+
+/// Checks a name against the `name` or `owner/name` form of hub model ids
+fn is_valid_model_name(name: &str) -> bool {
+    let parts: Vec<&str> = name.split('/').collect();
+    parts.len() <= 2
+        && parts.iter().all(|part| {
+            !part.is_empty()
+                && part
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_tokenizer_model_parse() {
+        assert_eq!(
+            TokenizerModel::parse("gpt2"),
+            TokenizerModel::Pretrained("gpt2".to_string())
+        );
+        assert_eq!(
+            TokenizerModel::parse("models/tokenizer.json"),
+            TokenizerModel::Custom(PathBuf::from("models/tokenizer.json"))
+        );
+    }
+
+    #[test]
+    fn test_http_status() {
+        /// An error wrapping a boxed request error, as the hub client does
+        #[derive(Debug)]
+        struct RequestError(Box<ureq::Error>);
+
+        impl std::fmt::Display for RequestError {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "request error: {}", self.0)
+            }
+        }
+
+        impl std::error::Error for RequestError {
+            fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+                Some(&self.0)
+            }
+        }
+
+        let not_found = ureq::Response::new(404, "Not Found", "").unwrap();
+        let error: Box<dyn std::error::Error + Send + Sync> =
+            Box::new(RequestError(Box::new(ureq::Error::Status(404, not_found))));
+        assert_eq!(http_status(&*error), Some(404));
+
+        // A 404 in the message alone is not a status
+        let error: Box<dyn std::error::Error + Send + Sync> = "got 404 bytes".into();
+        assert_eq!(http_status(&*error), None);
+    }
+
+    #[test]
+    fn test_load_malformed_tokenizer_file() -> Result<()> {
+        let temp_dir = tempdir()?;
+
+        // A JSON file that is not a tokenizer
+        let package_json = temp_dir.path().join("package.json");
+        fs::write(&package_json, r#"{"name": "demo", "version": "1.0.0"}"#)?;
+        let err = load_tokenizer(&TokenizerModel::Custom(package_json.clone())).unwrap_err();
+        match err.downcast_ref::<DepBankError>() {
+            Some(DepBankError::TokenizerLoad { path, reason }) => {
+                assert_eq!(path, &package_json);
+                assert!(reason.contains("not a tokenizer file"));
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+
+        // A file that is not JSON at all
+        let broken = temp_dir.path().join("tokenizer.json");
+        fs::write(&broken, "{ not json")?;
+        let err = load_tokenizer(&TokenizerModel::Custom(broken)).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<DepBankError>(),
+            Some(DepBankError::TokenizerLoad { .. })
+        ));
+
+        // A missing file
+        let missing = temp_dir.path().join("missing.json");
+        let err = load_tokenizer(&TokenizerModel::Custom(missing)).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<DepBankError>(),
+            Some(DepBankError::TokenizerLoad { .. })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_shared_tokenizer_loaded_once() -> Result<()> {
+        use crate::{
+            Dependency, DependencyCollection, TokenOptions, build_generation_report,
+            calculate_directory_tokens,
+        };
+
+        let temp_dir = tempdir()?;
+        let bank = temp_dir.path().join("serde.md");
+        fs::write(
+            &bank,
+            "# Code Bank\n## src/lib.rs\n```rust\npub fn to_string() {}\n```\n",
+        )?;
+        let mut dependencies = DependencyCollection::new();
+        dependencies.add(Dependency::new("serde", "1.0.219"));
+
+        // Counting the tokens of generated banks, then of a directory, on several threads
+        let report =
+            build_generation_report(&dependencies, &HashMap::from([("serde".to_string(), bank)]))?;
+        let stats =
+            calculate_directory_tokens(temp_dir.path(), Some("md"), &TokenOptions::default())?;
+        assert_eq!(stats["serde"].token_count, report.banks[0].token_count);
+
+        // Other tests count tokens with the same model concurrently, and share it too
+        assert_eq!(shared_tokenizer_loads(&TokenizerModel::default(), None), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_unknown_model_name() {
+        let err =
+            load_tokenizer(&TokenizerModel::Pretrained("not a model!".to_string())).unwrap_err();
+        match err.downcast_ref::<DepBankError>() {
+            Some(DepBankError::UnknownTokenizerModel { name, .. }) => {
+                assert_eq!(name, "not a model!");
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+        assert!(
+            err.to_string()
+                .contains("Unknown pretrained tokenizer model")
+        );
+    }
+}
@@ -0,0 +1,318 @@
+//! Discovery of transitive dependencies from Cargo.lock.
+//!
+//! Cargo.lock records the dependency edges of every locked package, but not their
+//! kind. To keep the transitive set to the code a project runs, the kind of each edge
+//! is looked up in the depending crate's manifest, and edges that are only
+//! dev-dependencies are not followed unless asked for.
+
+use crate::{
+    CargoDepSpec, CargoToml, DependencyCollection, DependencyGraph, ParsedLock,
+    build_dependency_graph_from_lock, normalize_crate_name,
+};
+use anyhow::Result;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs;
+use std::path::Path;
+
+/// Options controlling which dependency edges are followed
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TransitiveOptions {
+    /// Also follow the dev-dependency edges of dependencies, pulling in the crates
+    /// they only need for their tests, examples and benchmarks
+    pub include_dev: bool,
+}
+
+/// Collects the dependencies reachable from a set of direct dependencies.
+///
+/// The graph is walked along the `dependencies` edges of the packages in Cargo.lock.
+/// Normal and build dependency edges are followed; an edge whose target is only a
+/// `[dev-dependencies]` entry of the depending crate's manifest is skipped unless
+/// `options.include_dev` is set. Edges of crates whose manifest cannot be read from
+/// the registry are all followed.
+///
+/// # Arguments
+///
+/// * `cargo_lock_path` - Path to the Cargo.lock file
+/// * `direct` - The direct dependencies, with versions resolved from Cargo.lock
+/// * `registry_path` - Path to the cargo registry directory holding the manifests
+/// * `options` - Which edges to follow
+///
+/// # Returns
+///
+/// * `Result<DependencyCollection>` - The direct dependencies followed by the
+///   transitive ones, with their sources from Cargo.lock
+///
+/// # Errors
+///
+/// Returns an error if the Cargo.lock file cannot be read or parsed
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use depbank::{
+///     DependencyCollection, TransitiveOptions, collect_transitive_dependencies,
+///     resolve_registry_path,
+/// };
+///
+/// let direct = DependencyCollection::new();
+/// let registry_path = resolve_registry_path().unwrap();
+/// let all = collect_transitive_dependencies(
+///     "Cargo.lock",
+///     &direct,
+///     &registry_path,
+///     &TransitiveOptions::default(),
+/// )
+/// .unwrap();
+/// println!("{} dependencies in total", all.len());
+/// ```
+pub fn collect_transitive_dependencies<P: AsRef<Path>>(
+    cargo_lock_path: P,
+    direct: &DependencyCollection,
+    registry_path: &Path,
+    options: &TransitiveOptions,
+) -> Result<DependencyCollection> {
+    Ok(collect_transitive_dependencies_from_graph(
+        &build_dependency_graph_from_lock(&ParsedLock::read(cargo_lock_path)?),
+        direct,
+        registry_path,
+        options,
+    ))
+}
+
+/// Collects the dependencies reachable from a set of direct dependencies, from
+/// Cargo.lock content held in memory.
+///
+/// This is the same as [`collect_transitive_dependencies`], but takes the lockfile
+/// content directly instead of a path.
+///
+/// # Arguments
+///
+/// * `content` - The content of a Cargo.lock file
+/// * `direct` - The direct dependencies, with versions resolved from Cargo.lock
+/// * `registry_path` - Path to the cargo registry directory holding the manifests
+/// * `options` - Which edges to follow
+///
+/// # Returns
+///
+/// * `Result<DependencyCollection>` - The direct dependencies followed by the
+///   transitive ones, with their sources from Cargo.lock
+///
+/// # Errors
+///
+/// Returns an error if the content cannot be parsed as a valid Cargo.lock
+pub fn collect_transitive_dependencies_from_str(
+    content: &str,
+    direct: &DependencyCollection,
+    registry_path: &Path,
+    options: &TransitiveOptions,
+) -> Result<DependencyCollection> {
+    Ok(collect_transitive_dependencies_from_graph(
+        &build_dependency_graph_from_lock(&ParsedLock::parse(content)?),
+        direct,
+        registry_path,
+        options,
+    ))
+}
+
+/// Collects the dependencies reachable from a set of direct dependencies in the
+/// dependency graph of a Cargo.lock.
+///
+/// This is the same as [`collect_transitive_dependencies`], for a graph that other
+/// steps of an analysis use as well, e.g. to compute the depth of each dependency.
+///
+/// # Arguments
+///
+/// * `graph` - The dependency graph of the Cargo.lock
+/// * `direct` - The direct dependencies, with versions resolved from Cargo.lock
+/// * `registry_path` - Path to the cargo registry directory holding the manifests
+/// * `options` - Which edges to follow
+///
+/// # Returns
+///
+/// * `DependencyCollection` - The direct dependencies followed by the transitive
+///   ones, with their sources from Cargo.lock
+pub fn collect_transitive_dependencies_from_graph(
+    graph: &DependencyGraph,
+    direct: &DependencyCollection,
+    registry_path: &Path,
+    options: &TransitiveOptions,
+) -> DependencyCollection {
+    let mut collected = DependencyCollection::new();
+    let mut visited = HashSet::new();
+    let mut queue = VecDeque::new();
+
+    for dep in direct.iter() {
+        collected.add(dep.clone());
+        if let Some(index) = graph.find(&dep.name, &dep.version)
+            && visited.insert(index)
+        {
+            queue.push_back(index);
+        }
+    }
+
+    while let Some(index) = queue.pop_front() {
+        let dev_only = if options.include_dev {
+            HashSet::new()
+        } else {
+            dev_only_dependencies(&graph.package(index).get_registry_path(registry_path))
+        };
+
+        for &target in graph.dependencies(index) {
+            let dep = graph.package(target);
+            if dev_only.contains(&normalize_crate_name(&dep.name)) || !visited.insert(target) {
+                continue;
+            }
+            if !direct.contains_name(&dep.name) {
+                collected.add(dep.clone());
+            }
+            queue.push_back(target);
+        }
+    }
+
+    collected
+}
+
+/// Reads the normalized names of the packages a crate only declares as
+/// dev-dependencies, i.e. not also as normal or build dependencies
+fn dev_only_dependencies(crate_dir: &Path) -> HashSet<String> {
+    let Some(manifest) = fs::read_to_string(crate_dir.join("Cargo.toml"))
+        .ok()
+        .and_then(|content| toml::from_str::<CargoToml>(&content).ok())
+    else {
+        return HashSet::new();
+    };
+
+    let package_names = |deps: &HashMap<String, CargoDepSpec>| -> HashSet<String> {
+        deps.iter()
+            .map(|(name, spec)| {
+                // Renamed dependencies are locked under their package name
+                let package = match spec {
+                    CargoDepSpec::Detailed(table) => table
+                        .get("package")
+                        .and_then(|package| package.as_str())
+                        .unwrap_or(name),
+                    CargoDepSpec::Simple(_) | CargoDepSpec::Other(_) => name,
+                };
+                normalize_crate_name(package)
+            })
+            .collect()
+    };
+
+    let runtime: HashSet<String> = package_names(&manifest.dependencies)
+        .into_iter()
+        .chain(package_names(&manifest.build_dependencies))
+        .collect();
+    package_names(&manifest.dev_dependencies)
+        .into_iter()
+        .filter(|name| !runtime.contains(name))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Dependency;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_collect_transitive_dependencies_skips_dev_edges() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let cargo_lock_path = temp_dir.path().join("Cargo.lock");
+        fs::write(
+            &cargo_lock_path,
+            r#"version = 4
+
+[[package]]
+name = "app"
+version = "0.1.0"
+dependencies = ["http-client"]
+
+[[package]]
+name = "http-client"
+version = "1.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+dependencies = ["bytes", "mock-server", "url 2.5.0"]
+
+[[package]]
+name = "bytes"
+version = "1.6.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "url"
+version = "1.7.2"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "url"
+version = "2.5.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "mock-server"
+version = "0.3.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+dependencies = ["fake-data"]
+
+[[package]]
+name = "fake-data"
+version = "0.1.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#,
+        )?;
+
+        // http-client only needs mock-server for its tests
+        let registry_path = temp_dir.path().join("registry");
+        let crate_dir = registry_path.join("http-client-1.0.0");
+        fs::create_dir_all(&crate_dir)?;
+        fs::write(
+            crate_dir.join("Cargo.toml"),
+            r#"[package]
+name = "http-client"
+version = "1.0.0"
+
+[dependencies]
+bytes = "1"
+url = "2"
+
+[dev-dependencies]
+mock_server = "0.3"
+"#,
+        )?;
+
+        let mut direct = DependencyCollection::new();
+        direct.add(Dependency::new("http-client", "1.0.0"));
+
+        let collected = collect_transitive_dependencies(
+            &cargo_lock_path,
+            &direct,
+            &registry_path,
+            &TransitiveOptions::default(),
+        )?;
+        let names: Vec<(&str, &str)> = collected
+            .iter()
+            .map(|dep| (dep.name.as_str(), dep.version.as_str()))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("http-client", "1.0.0"),
+                ("bytes", "1.6.0"),
+                ("url", "2.5.0")
+            ]
+        );
+
+        // The dev edge, and what it leads to, are followed on request
+        let collected = collect_transitive_dependencies(
+            &cargo_lock_path,
+            &direct,
+            &registry_path,
+            &TransitiveOptions { include_dev: true },
+        )?;
+        assert!(collected.contains_name("mock-server"));
+        assert!(collected.contains_name("fake-data"));
+        assert_eq!(collected.len(), 5);
+
+        Ok(())
+    }
+}
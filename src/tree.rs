@@ -0,0 +1,441 @@
+//! The dependency graph recorded in Cargo.lock, and its rendering as a tree.
+//!
+//! Cargo.lock lists the dependencies of every locked package, which is enough to show
+//! how a project's dependencies pull in the others, as `cargo tree` does, without
+//! fetching anything. The lockfile does not record the kind of the edges, so the
+//! dev-dependencies of dependencies show up in the tree too.
+
+use crate::{Dependency, DependencyCollection, ParsedLock, normalize_crate_name};
+use anyhow::Result;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::Path;
+
+/// The packages locked in a Cargo.lock and the dependency edges between them
+#[derive(Debug, Clone, Default)]
+pub struct DependencyGraph {
+    /// The locked packages, with their sources
+    packages: Vec<Dependency>,
+    /// The indexes of the packages each package depends on, sorted by name
+    edges: Vec<Vec<usize>>,
+}
+
+impl DependencyGraph {
+    /// Returns the number of packages in the graph
+    pub fn len(&self) -> usize {
+        self.packages.len()
+    }
+
+    /// Returns true if the graph has no packages
+    pub fn is_empty(&self) -> bool {
+        self.packages.is_empty()
+    }
+
+    /// Returns the package at an index of the graph
+    pub fn package(&self, index: usize) -> &Dependency {
+        &self.packages[index]
+    }
+
+    /// Returns the indexes of the packages a package depends on
+    pub fn dependencies(&self, index: usize) -> &[usize] {
+        &self.edges[index]
+    }
+
+    /// Finds the index of a locked package by name and version
+    pub fn find(&self, name: &str, version: &str) -> Option<usize> {
+        let name = normalize_crate_name(name);
+        self.packages.iter().position(|package| {
+            normalize_crate_name(&package.name) == name && package.version == version
+        })
+    }
+}
+
+/// Builds the dependency graph of the packages locked in a Cargo.lock file.
+///
+/// # Arguments
+///
+/// * `cargo_lock_path` - Path to the Cargo.lock file
+///
+/// # Returns
+///
+/// * `Result<DependencyGraph>` - The locked packages and their dependency edges
+///
+/// # Errors
+///
+/// Returns an error if the Cargo.lock file cannot be read or parsed
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use depbank::build_dependency_graph;
+///
+/// let graph = build_dependency_graph("Cargo.lock").unwrap();
+/// println!("{} locked packages", graph.len());
+/// ```
+pub fn build_dependency_graph<P: AsRef<Path>>(cargo_lock_path: P) -> Result<DependencyGraph> {
+    Ok(build_dependency_graph_from_lock(&ParsedLock::read(
+        cargo_lock_path,
+    )?))
+}
+
+/// Builds the dependency graph of the packages locked in Cargo.lock content held in
+/// memory.
+///
+/// This is the same as [`build_dependency_graph`], but takes the lockfile content
+/// directly instead of a path. Edges to packages missing from the lockfile are
+/// dropped.
+///
+/// # Arguments
+///
+/// * `content` - The content of a Cargo.lock file
+///
+/// # Returns
+///
+/// * `Result<DependencyGraph>` - The locked packages and their dependency edges
+///
+/// # Errors
+///
+/// Returns an error if the content cannot be parsed as a valid Cargo.lock
+pub fn build_dependency_graph_from_str(content: &str) -> Result<DependencyGraph> {
+    Ok(build_dependency_graph_from_lock(&ParsedLock::parse(
+        content,
+    )?))
+}
+
+/// Builds the dependency graph of the packages locked in an already parsed
+/// Cargo.lock.
+///
+/// This is the same as [`build_dependency_graph`], for a lockfile that other steps
+/// of an analysis read as well. Edges to packages missing from the lockfile are
+/// dropped.
+///
+/// # Arguments
+///
+/// * `cargo_lock` - The parsed Cargo.lock
+///
+/// # Returns
+///
+/// * `DependencyGraph` - The locked packages and their dependency edges
+pub fn build_dependency_graph_from_lock(cargo_lock: &ParsedLock) -> DependencyGraph {
+    let mut packages_by_name: HashMap<String, Vec<usize>> = HashMap::new();
+    for (index, package) in cargo_lock.package.iter().enumerate() {
+        packages_by_name
+            .entry(normalize_crate_name(&package.name))
+            .or_default()
+            .push(index);
+    }
+
+    let packages: Vec<Dependency> = cargo_lock
+        .package
+        .iter()
+        .map(|package| {
+            let mut dep = Dependency::new(&package.name, &package.version);
+            dep.source = package.source.clone();
+            dep
+        })
+        .collect();
+
+    let edges = cargo_lock
+        .package
+        .iter()
+        .map(|package| {
+            let mut targets: Vec<usize> = package
+                .dependencies
+                .iter()
+                .filter_map(|edge| {
+                    // `name version` edges pick one of several locked versions
+                    let mut parts = edge.split_whitespace();
+                    let name = normalize_crate_name(parts.next()?);
+                    let version = parts.next();
+                    packages_by_name
+                        .get(&name)?
+                        .iter()
+                        .copied()
+                        .find(|&candidate| {
+                            version.is_none_or(|version| packages[candidate].version == version)
+                        })
+                })
+                .collect();
+            targets.sort_by(|&a, &b| {
+                packages[a]
+                    .name
+                    .cmp(&packages[b].name)
+                    .then_with(|| packages[a].version.cmp(&packages[b].version))
+            });
+            targets.dedup();
+            targets
+        })
+        .collect();
+
+    DependencyGraph { packages, edges }
+}
+
+/// Renders the dependency tree of some root packages, like `cargo tree`.
+///
+/// Each root is printed unindented, followed by its dependencies with `├──` and `└──`
+/// connectors. A package whose dependencies were already shown earlier in the tree
+/// is marked with `(*)` instead of being expanded again, which also stops cycles.
+/// Roots that are not locked in the graph are printed without dependencies.
+///
+/// # Arguments
+///
+/// * `graph` - The dependency graph from Cargo.lock
+/// * `roots` - The packages to root the tree at, with versions resolved from Cargo.lock
+///
+/// # Returns
+///
+/// * `String` - The tree, one package per line as `name vVERSION`
+///
+/// # Examples
+///
+/// ```rust
+/// use depbank::{Dependency, DependencyCollection, build_dependency_graph_from_str, render_dependency_tree};
+///
+/// let lock = r#"
+/// [[package]]
+/// name = "serde"
+/// version = "1.0.219"
+/// dependencies = ["serde_derive"]
+///
+/// [[package]]
+/// name = "serde_derive"
+/// version = "1.0.219"
+/// "#;
+/// let graph = build_dependency_graph_from_str(lock).unwrap();
+/// let mut roots = DependencyCollection::new();
+/// roots.add(Dependency::new("serde", "1.0.219"));
+/// assert_eq!(
+///     render_dependency_tree(&graph, &roots),
+///     "serde v1.0.219\n└── serde_derive v1.0.219\n"
+/// );
+/// ```
+pub fn render_dependency_tree(graph: &DependencyGraph, roots: &DependencyCollection) -> String {
+    let mut output = String::new();
+    let mut expanded = HashSet::new();
+    for root in roots.iter() {
+        match graph.find(&root.name, &root.version) {
+            Some(index) => render_node(graph, index, "", &mut expanded, &mut output),
+            None => output.push_str(&format!("{} v{}\n", root.name, root.version)),
+        }
+    }
+    output
+}
+
+/// Computes how deep each dependency sits below the project, as the fewest edges
+/// between it and one of the project's direct dependencies.
+///
+/// # Arguments
+///
+/// * `graph` - The dependency graph of the project's Cargo.lock
+/// * `roots` - The project's direct dependencies, at depth 0
+///
+/// # Returns
+///
+/// * `HashMap<Dependency, usize>` - The minimum depth of every package reachable from
+///   the roots. Roots missing from the graph are at depth 0
+///
+/// # Examples
+///
+/// ```rust
+/// use depbank::{Dependency, DependencyCollection, build_dependency_graph_from_str, compute_dependency_depths};
+///
+/// let graph = build_dependency_graph_from_str(r#"
+/// [[package]]
+/// name = "serde"
+/// version = "1.0.219"
+/// dependencies = ["serde_derive"]
+///
+/// [[package]]
+/// name = "serde_derive"
+/// version = "1.0.219"
+/// "#).unwrap();
+/// let mut roots = DependencyCollection::new();
+/// roots.add(Dependency::new("serde", "1.0.219"));
+///
+/// let depths = compute_dependency_depths(&graph, &roots);
+/// assert_eq!(depths[&Dependency::new("serde_derive", "1.0.219")], 1);
+/// ```
+pub fn compute_dependency_depths(
+    graph: &DependencyGraph,
+    roots: &DependencyCollection,
+) -> HashMap<Dependency, usize> {
+    let mut depths = HashMap::new();
+    let mut queued = vec![false; graph.len()];
+    let mut queue = VecDeque::new();
+    for root in roots.iter() {
+        match graph.find(&root.name, &root.version) {
+            Some(index) if !queued[index] => {
+                queued[index] = true;
+                queue.push_back((index, 0));
+            }
+            Some(_) => {}
+            None => {
+                depths.insert(root.clone(), 0);
+            }
+        }
+    }
+
+    // Breadth-first, so each package is first reached through a shortest path
+    while let Some((index, depth)) = queue.pop_front() {
+        depths.insert(graph.package(index).clone(), depth);
+        for &dependency in graph.dependencies(index) {
+            if !queued[dependency] {
+                queued[dependency] = true;
+                queue.push_back((dependency, depth + 1));
+            }
+        }
+    }
+
+    depths
+}
+
+/// Renders a package and, unless already shown, its dependencies below it. The
+/// package's own line has already been prefixed with its connector.
+fn render_node(
+    graph: &DependencyGraph,
+    index: usize,
+    indent: &str,
+    expanded: &mut HashSet<usize>,
+    output: &mut String,
+) {
+    let package = graph.package(index);
+    let dependencies = graph.dependencies(index);
+    output.push_str(&format!("{} v{}", package.name, package.version));
+    if dependencies.is_empty() {
+        output.push('\n');
+        return;
+    }
+    if !expanded.insert(index) {
+        output.push_str(" (*)\n");
+        return;
+    }
+    output.push('\n');
+
+    for (position, &dependency) in dependencies.iter().enumerate() {
+        let last = position + 1 == dependencies.len();
+        output.push_str(indent);
+        output.push_str(if last { "└── " } else { "├── " });
+        let child_indent = format!("{}{}", indent, if last { "    " } else { "│   " });
+        render_node(graph, dependency, &child_indent, expanded, output);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    const CARGO_LOCK: &str = r#"version = 4
+
+[[package]]
+name = "app"
+version = "0.1.0"
+dependencies = ["http-client", "log 0.4.27", "url"]
+
+[[package]]
+name = "http-client"
+version = "1.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+dependencies = ["bytes", "url"]
+
+[[package]]
+name = "bytes"
+version = "1.10.1"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "url"
+version = "2.5.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+dependencies = ["idna", "log 0.3.9"]
+
+[[package]]
+name = "idna"
+version = "1.0.3"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "log"
+version = "0.3.9"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "log"
+version = "0.4.27"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#;
+
+    #[test]
+    fn test_build_dependency_graph() -> Result<()> {
+        let graph = build_dependency_graph_from_str(CARGO_LOCK)?;
+        assert_eq!(graph.len(), 7);
+
+        // `name version` edges resolve to the right locked version
+        let url = graph.find("url", "2.5.0").unwrap();
+        let names: Vec<String> = graph
+            .dependencies(url)
+            .iter()
+            .map(|&index| {
+                let package = graph.package(index);
+                format!("{} {}", package.name, package.version)
+            })
+            .collect();
+        assert_eq!(names, vec!["idna 1.0.3", "log 0.3.9"]);
+        assert!(graph.package(url).source.is_some());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_dependency_tree() -> Result<()> {
+        let graph = build_dependency_graph_from_str(CARGO_LOCK)?;
+        let mut roots = DependencyCollection::new();
+        roots.add(Dependency::new("http-client", "1.0.0"));
+        roots.add(Dependency::new("url", "2.5.0"));
+        roots.add(Dependency::new("unlocked", "0.1.0"));
+
+        let tree = render_dependency_tree(&graph, &roots);
+        assert_eq!(
+            tree,
+            "\
+http-client v1.0.0
+├── bytes v1.10.1
+└── url v2.5.0
+    ├── idna v1.0.3
+    └── log v0.3.9
+url v2.5.0 (*)
+unlocked v0.1.0
+"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_compute_dependency_depths() -> Result<()> {
+        let graph = build_dependency_graph_from_str(CARGO_LOCK)?;
+        let mut roots = DependencyCollection::new();
+        roots.add(Dependency::new("http-client", "1.0.0"));
+        roots.add(Dependency::new("url", "2.5.0"));
+        roots.add(Dependency::new("unlocked", "0.1.0"));
+
+        let depths: BTreeMap<String, usize> = compute_dependency_depths(&graph, &roots)
+            .into_iter()
+            .map(|(dep, depth)| (format!("{} {}", dep.name, dep.version), depth))
+            .collect();
+
+        // url is also reached through http-client, but is a direct dependency itself
+        assert_eq!(
+            depths,
+            BTreeMap::from([
+                ("bytes 1.10.1".to_string(), 1),
+                ("http-client 1.0.0".to_string(), 0),
+                ("idna 1.0.3".to_string(), 1),
+                ("log 0.3.9".to_string(), 1),
+                ("unlocked 0.1.0".to_string(), 0),
+                ("url 2.5.0".to_string(), 0),
+            ])
+        );
+
+        Ok(())
+    }
+}
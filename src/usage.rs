@@ -0,0 +1,270 @@
+//! Discovery of how a project uses its dependencies.
+//!
+//! A code bank documents a dependency's API, but showing how the current project
+//! already calls into it grounds the reader in the parts that matter. Usages are
+//! found by scanning the project's Rust sources for paths starting with the crate.
+
+use crate::sources::collect_rust_files;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Maximum number of snippets reported per crate
+pub const MAX_USAGE_SNIPPETS: usize = 5;
+
+/// Lines of context kept before and after each matching line
+const CONTEXT_LINES: usize = 2;
+
+/// A short excerpt of project source using a dependency
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UsageSnippet {
+    /// The file containing the usage, relative to the project root
+    pub file: PathBuf,
+    /// The 1-based line number of the first line referencing the crate
+    pub line: usize,
+    /// The referencing lines with a few lines of context
+    pub snippet: String,
+    /// Whether the snippet contains a `use` of the crate
+    pub is_import: bool,
+}
+
+/// The Rust sources of a project, read once to search usages of many crates
+#[derive(Debug, Clone, Default)]
+pub struct ProjectSources {
+    /// Each file's path relative to the project root, with its content, in path order
+    files: Vec<(PathBuf, String)>,
+}
+
+impl ProjectSources {
+    /// Reads the Rust sources of a project.
+    ///
+    /// Hidden directories and `target` are skipped, as are files that cannot be read.
+    ///
+    /// # Arguments
+    ///
+    /// * `project_root` - The root directory of the project
+    ///
+    /// # Returns
+    ///
+    /// * `ProjectSources` - The project's `.rs` files with their content
+    pub fn read(project_root: &Path) -> Self {
+        let files = collect_rust_files(project_root)
+            .into_iter()
+            .filter_map(|file| {
+                let content = fs::read_to_string(&file).ok()?;
+                let relative = file.strip_prefix(project_root).unwrap_or(&file);
+                Some((relative.to_path_buf(), content))
+            })
+            .collect();
+        Self { files }
+    }
+
+    /// Finds short snippets of the sources that use a crate.
+    ///
+    /// Every line mentioning a path rooted at the crate, like `use serde::Serialize;`
+    /// or `serde_json::to_string(&value)`, is reported with a few lines of context,
+    /// and nearby matches are merged into one snippet. Snippets with imports come
+    /// first, and at most [`MAX_USAGE_SNIPPETS`] are returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `crate_name` - The name of the crate, with hyphens or underscores
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<UsageSnippet>` - The usage snippets, in file and line order within
+    ///   imports and other usages
+    pub fn find_usages(&self, crate_name: &str) -> Vec<UsageSnippet> {
+        // Crates are referenced in code with underscores
+        let ident = crate_name.replace('-', "_");
+
+        let mut snippets: Vec<UsageSnippet> = self
+            .files
+            .iter()
+            .flat_map(|(file, content)| file_usages(file, content, &ident))
+            .collect();
+
+        // Imports show best how the crate is used, so keep them when truncating
+        snippets.sort_by_key(|snippet| !snippet.is_import);
+        snippets.truncate(MAX_USAGE_SNIPPETS);
+        snippets
+    }
+}
+
+/// Finds short snippets of the project's sources that use a crate.
+///
+/// This reads the project's sources on every call; to search usages of several
+/// crates, read them once with [`ProjectSources::read`].
+///
+/// # Arguments
+///
+/// * `project_root` - The root directory of the project
+/// * `crate_name` - The name of the crate, with hyphens or underscores
+///
+/// # Returns
+///
+/// * `Vec<UsageSnippet>` - The usage snippets, as returned by
+///   [`ProjectSources::find_usages`]
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use depbank::find_usages;
+/// use std::path::Path;
+///
+/// for usage in find_usages(Path::new("."), "serde") {
+///     println!("{}:{}\n{}", usage.file.display(), usage.line, usage.snippet);
+/// }
+/// ```
+pub fn find_usages(project_root: &Path, crate_name: &str) -> Vec<UsageSnippet> {
+    ProjectSources::read(project_root).find_usages(crate_name)
+}
+
+/// Renders usage snippets as a "Used in this project" markdown section.
+///
+/// # Arguments
+///
+/// * `crate_name` - The name of the crate the snippets use
+/// * `snippets` - The snippets, as returned by [`find_usages`]
+///
+/// # Returns
+///
+/// * `String` - The markdown section, with LF line endings
+pub fn usage_section(crate_name: &str, snippets: &[UsageSnippet]) -> String {
+    let mut section = format!(
+        "## Used in this project\n\nHow the project currently uses `{}`:\n",
+        crate_name
+    );
+    for snippet in snippets {
+        section.push_str(&format!(
+            "\n`{}:{}`\n\n```rust\n{}\n```\n",
+            snippet.file.display(),
+            snippet.line,
+            snippet.snippet
+        ));
+    }
+    section
+}
+
+/// Finds the snippets of one file, merging matches whose context overlaps
+fn file_usages(file: &Path, content: &str, ident: &str) -> Vec<UsageSnippet> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut snippets = Vec::new();
+    // The line range of the snippet being built, its first match and import flag
+    let mut current: Option<(usize, usize, usize, bool)> = None;
+
+    for (index, line) in lines.iter().enumerate() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("//") || !references_crate(line, ident) {
+            continue;
+        }
+        let is_import = trimmed.starts_with("use ") || trimmed.contains(" use ");
+        let start = index.saturating_sub(CONTEXT_LINES);
+        let end = (index + CONTEXT_LINES).min(lines.len() - 1);
+
+        current = match current {
+            Some((first, last, first_match, import)) if start <= last + 1 => {
+                Some((first, end, first_match, import || is_import))
+            }
+            previous => {
+                if let Some(range) = previous {
+                    snippets.push(build_snippet(file, &lines, range));
+                }
+                Some((start, end, index, is_import))
+            }
+        };
+    }
+    if let Some(range) = current {
+        snippets.push(build_snippet(file, &lines, range));
+    }
+
+    snippets
+}
+
+fn build_snippet(
+    file: &Path,
+    lines: &[&str],
+    (first, last, first_match, is_import): (usize, usize, usize, bool),
+) -> UsageSnippet {
+    UsageSnippet {
+        file: file.to_path_buf(),
+        line: first_match + 1,
+        snippet: lines[first..=last]
+            .join("\n")
+            .trim_matches('\n')
+            .to_string(),
+        is_import,
+    }
+}
+
+/// Checks whether a line contains a path rooted at the crate, like `ident::` or
+/// `::ident::`, but not one where the crate name is a later segment (`a::ident::`)
+fn references_crate(line: &str, ident: &str) -> bool {
+    let pattern = format!("{}::", ident);
+    line.match_indices(&pattern).any(|(index, _)| {
+        let before = &line[..index];
+        let before = before.strip_suffix("::").unwrap_or(before);
+        !before
+            .chars()
+            .next_back()
+            .is_some_and(|c| c.is_alphanumeric() || c == '_' || c == ':')
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_find_usages_in_fixture() {
+        let usages = find_usages(Path::new("fixtures/simple_project"), "serde");
+
+        assert_eq!(usages.len(), 1);
+        let usage = &usages[0];
+        assert_eq!(usage.file, Path::new("src").join("main.rs"));
+        assert_eq!(usage.line, 2);
+        assert!(usage.is_import);
+        assert!(
+            usage
+                .snippet
+                .contains("use serde::{Deserialize, Serialize};")
+        );
+
+        // Only a few lines of context are kept
+        assert!(usage.snippet.lines().count() <= 2 * CONTEXT_LINES + 1);
+
+        // A crate the fixture does not use has no usages
+        assert!(find_usages(Path::new("fixtures/simple_project"), "tokio").is_empty());
+    }
+
+    #[test]
+    fn test_find_usages_call_sites() -> std::io::Result<()> {
+        let temp_dir = tempdir()?;
+        fs::create_dir_all(temp_dir.path().join("src"))?;
+        fs::create_dir_all(temp_dir.path().join("target"))?;
+        fs::write(
+            temp_dir.path().join("src").join("lib.rs"),
+            "pub fn encode(value: &Value) -> String {\n    serde_json::to_string(value).unwrap()\n}\n\n\n\n\n// serde_json::from_str is not used\nfn nested() { other::serde_json::skip(); }\n",
+        )?;
+        fs::write(
+            temp_dir.path().join("target").join("generated.rs"),
+            "use serde_json::Value;\n",
+        )?;
+
+        let usages = find_usages(temp_dir.path(), "serde-json");
+
+        assert_eq!(usages.len(), 1);
+        assert_eq!(usages[0].line, 2);
+        assert!(!usages[0].is_import);
+        assert_eq!(
+            usages[0].snippet,
+            "pub fn encode(value: &Value) -> String {\n    serde_json::to_string(value).unwrap()\n}"
+        );
+
+        let section = usage_section("serde-json", &usages);
+        assert!(section.starts_with("## Used in this project"));
+        assert!(section.contains("`src/lib.rs:2`"));
+
+        Ok(())
+    }
+}
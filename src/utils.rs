@@ -1,62 +1,1361 @@
+use crate::cli::{GenerateArgs, ListFormat, OutputFormat, SbomFormat, Strategy, WatchArgs};
 use anyhow::{Context, Result};
+use clap::ValueEnum;
 use depbank::{
-    DependencyCollection, calculate_directory_tokens, calculate_file_tokens, collect_dependencies,
-    extract_dependency_info, find_cargo_lock, find_cargo_toml_files, generate_all_code_banks,
-    is_dependency_available, resolve_dependency_versions, resolve_registry_path,
+    BudgetPriority, ColorStyle, DEFAULT_BANK_IGNORED_DIRS, DepBankError, DepDiff, Dependency,
+    DependencyCollection, DependencyCsvRow, DependencyKind, DependencyMetadata, DiscoveryOptions,
+    GenerateOptions, GenerationOutcome, GenerationReport, INDEX_JSON_FILE, KNOWN_MODEL_PRICES,
+    MAX_USAGE_SNIPPETS, ManifestCache, OutputStream, PROGRESS_LOG_FILE, ParsedLock, ProjectSources,
+    ResolveOptions, ResolvedDependencies, SKIPPED_DEADLINE, SKIPPED_TOO_LARGE, SbomComponent,
+    StrategyComparison, TokenEstimate, TokenOptions, TransitiveOptions, UsageSnippet,
+    VersionConflictPolicy, Warning, WarningKind, aggregate_unique_tokens, analyze_source_sizes,
+    archive_mtime, bank_tokens_by_file, build_dependency_graph_from_lock, build_generation_report,
+    cached_yanked_status, calculate_directory_tokens, calculate_file_tokens, clean_output_dir,
+    collect_dependencies, collect_dependency_kinds, collect_dependency_provenance,
+    compare_strategies, compress_banks_over, crates_referenced_in, dependency_metadata,
+    diff_dependency_collections, emit_warning, estimate_cost, estimate_dependency_tokens,
+    estimate_ignore_savings, extract_dependency_info, filter_to_deps_list, find_cargo_lock,
+    find_cargo_toml_files, find_major_version_conflicts_from_lock, find_undocumented_banks,
+    find_version_mismatch, generate_all_to_archive, generate_code_bank_to_writer,
+    generate_code_banks_with_outcome, generate_self_code_bank, index_json, is_dependency_available,
+    is_version_yanked, load_deps_file, model_price_per_1k, normalize_crate_name,
+    options_fingerprint, order_by_priority, paint, project_fingerprint, project_name,
+    read_priority_file, read_progress_log, registry_source_dirs, relativize, remove_progress_log,
+    render_dependency_tree, resolve_dependency_versions, resolve_dependency_versions_from_lock,
+    resolve_project_dependencies, resolve_registry_path, resolve_version_conflicts,
+    select_within_budget, skip_oversized_dependencies, source_fingerprint, to_cyclonedx, to_spdx,
+    tokens_by_extension, unused_registry_crates, validate_against_parsed_lock, validate_cargo_lock,
+    validate_cargo_lock_str, watch_project, write_archive, write_archive_file,
+    write_dependency_csv, write_index_json, write_index_markdown, write_sidecar,
 };
-use std::collections::{HashMap, HashSet};
-use std::fmt::Write as _;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fs;
+use std::io::{self, Write};
+use std::ops::ControlFlow;
 use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+use std::thread;
+use std::time::{Instant, SystemTime};
+use tempfile::TempDir;
+
+/// The `--lock` value that reads the lockfile from stdin
+const STDIN_LOCK: &str = "-";
+
+/// The lockfile read from stdin, which can only be read once per run
+static STDIN_LOCK_CONTENT: OnceLock<String> = OnceLock::new();
+
+/// How a command finished
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandOutcome {
+    /// The command ran to completion
+    Completed,
+    /// The project declares no dependencies, so there was nothing to do
+    NoDependencies,
+}
+
+/// Prints the stable message reported for projects without dependencies
+fn report_no_dependencies(cargo_toml_count: usize) {
+    println!(
+        "No dependencies found in {} Cargo.toml file(s)",
+        cargo_toml_count
+    );
+}
+
+/// The depths of dependencies below a project by name, see
+/// [`compute_dependency_depths`]
+type DependencyDepths = BTreeMap<String, usize>;
+
+/// The code banks written into one output directory
+struct GeneratedBanks {
+    /// The directory the banks were written to
+    output_dir: PathBuf,
+    /// The generated banks with their token counts
+    report: GenerationReport,
+    /// Number of dependencies whose code bank could not be generated
+    failed: usize,
+}
+
+pub fn generate_command(args: &GenerateArgs) -> Result<CommandOutcome> {
+    if args.paths.len() > 1 && args.lock.is_some() {
+        return Err(anyhow::anyhow!(
+            "--lock can only be used with a single project path"
+        ));
+    }
+
+    // Find and analyze the dependencies of every project
+    let discovery = args.discovery.discovery_options_with_env();
+    let resolve = ProjectResolution::for_generate(args, &discovery, false);
+    let mut projects = Vec::with_capacity(args.paths.len());
+    let mut project_depths = HashMap::new();
+    let mut missing = 0;
+    for project_path in &args.paths {
+        println!("Analyzing project {}...", project_path.display());
+        let (dependencies, project_missing, depths) = analyze_dependencies(project_path, &resolve)?;
+        missing += project_missing;
+        project_depths.insert(project_path.clone(), depths);
+        projects.push((project_path.clone(), dependencies));
+    }
+    if args.per_project_output {
+        return generate_per_project(args, projects, project_depths, missing, &discovery);
+    }
+
+    let (mut available_deps, used_by) =
+        merge_project_dependencies(projects, args.on_version_conflict.into())?;
+    if available_deps.is_empty() {
+        // Only projects without any dependency get here, others fail in the analysis
+        return Ok(CommandOutcome::NoDependencies);
+    }
+    if args.paths.len() > 1 {
+        println!(
+            "{} unique dependencies across {} projects",
+            available_deps.len(),
+            args.paths.len()
+        );
+    }
+
+    if let Some(name) = &args.explain_size {
+        return explain_size(args, &available_deps, name);
+    }
+
+    // A dependency of several projects sits as deep as in the one needing it most directly
+    let mut depths = DependencyDepths::new();
+    for (name, depth) in project_depths.into_values().flatten() {
+        depths
+            .entry(name)
+            .and_modify(|known| *known = (*known).min(depth))
+            .or_insert(depth);
+    }
+    let options = bank_options(args, &available_deps, &used_by, &depths, false)?;
+
+    available_deps = select_dependencies(
+        args,
+        available_deps,
+        &options,
+        &args.paths,
+        &discovery,
+        false,
+    )?;
+    if args.report_savings {
+        report_ignore_savings(args, &available_deps)?;
+    }
+    if args.dry_run {
+        println!("Dry run enabled, skipping generation");
+        return check_missing_dependencies(args, missing);
+    }
+
+    if args.archive_only {
+        stream_code_banks_to_archive(args, &available_deps, &depths)?;
+        return check_missing_dependencies(args, missing);
+    }
+    let generated = write_code_banks(
+        args,
+        &options,
+        &available_deps,
+        &used_by,
+        &depths,
+        &args.paths,
+        &args.output,
+    )?;
+    print_generation_summary(args, &generated);
+    check_generated_banks(args, &generated)?;
+
+    check_missing_dependencies(args, missing)
+}
+
+/// Generates the code banks of each project into its own `<output>/<project name>`
+/// directory.
+///
+/// The projects are generated concurrently: as each one writes to a separate
+/// directory, they cannot overwrite each other's banks or indexes.
+fn generate_per_project(
+    args: &GenerateArgs,
+    projects: Vec<(PathBuf, DependencyCollection)>,
+    mut project_depths: HashMap<PathBuf, DependencyDepths>,
+    missing: usize,
+    discovery: &DiscoveryOptions,
+) -> Result<CommandOutcome> {
+    let mut selected = Vec::with_capacity(projects.len());
+    let mut output_dirs: HashMap<PathBuf, PathBuf> = HashMap::new();
+    for (project_path, dependencies) in projects {
+        if dependencies.is_empty() {
+            continue;
+        }
+
+        let output_dir = args.output.join(project_name(&project_path));
+        if let Some(other) = output_dirs.insert(output_dir.clone(), project_path.clone()) {
+            return Err(anyhow::anyhow!(
+                "Projects {} and {} would both write to {}",
+                other.display(),
+                project_path.display(),
+                output_dir.display()
+            ));
+        }
 
-// Constants for formatting strings
-const README_HEADER: &str = "# Code Bank Summary\n\n";
-const README_TABLE_HEADER: &str = "| Dependency | Version | Tokens | Size (bytes) |\n";
-const README_TABLE_SEPARATOR: &str = "|------------|---------|--------|-------------|\n";
-const README_TOKEN_SUMMARY_HEADER: &str = "\n## Token Summary\n\n";
-const README_ABOUT_HEADER: &str = "\n## About Code Banks\n\n";
-const README_ABOUT_P1: &str = "Code banks are generated summaries of your project's dependencies. ";
-const README_ABOUT_P2: &str = "They provide an overview of the code structure and key components ";
-const README_ABOUT_P3: &str =
-    "of each dependency, helping you understand the libraries your project uses ";
-const README_ABOUT_P4: &str = "without having to browse through the original source code.\n\n";
-const README_ABOUT_P5: &str =
-    "Each file contains a summary of the corresponding dependency's source code, ";
-const README_ABOUT_P6: &str = "including important types, functions, and structures.\n\n";
-const README_ABOUT_P7: &str = "Generated by [DepBank](https://github.com/tyrchen/depbank).\n";
-
-pub fn generate_command(project_path: &Path, output_dir: &Path, dry_run: bool) -> Result<()> {
-    println!("Analyzing project...");
-
-    // Find and analyze dependencies
-    let available_deps = analyze_dependencies(project_path, dry_run)?;
-
-    if dry_run {
+        let depths = project_depths.remove(&project_path).unwrap_or_default();
+        let project_paths = vec![project_path];
+        let used_by: BTreeMap<String, Vec<PathBuf>> = dependencies
+            .iter()
+            .map(|dep| (dep.name.clone(), project_paths.clone()))
+            .collect();
+        let options = bank_options(args, &dependencies, &used_by, &depths, false)?;
+        let dependencies = select_dependencies(
+            args,
+            dependencies,
+            &options,
+            &project_paths,
+            discovery,
+            false,
+        )?;
+        selected.push((
+            project_paths,
+            dependencies,
+            used_by,
+            depths,
+            options,
+            output_dir,
+        ));
+    }
+    if selected.is_empty() {
+        return Ok(CommandOutcome::NoDependencies);
+    }
+
+    if args.dry_run {
         println!("Dry run enabled, skipping generation");
-        return Ok(());
+        return check_missing_dependencies(args, missing);
+    }
+
+    println!(
+        "Generating code banks for {} projects into separate directories...",
+        selected.len()
+    );
+    let results: Vec<Result<GeneratedBanks>> = thread::scope(|scope| {
+        let handles: Vec<_> = selected
+            .iter()
+            .map(
+                |(project_paths, dependencies, used_by, depths, options, output_dir)| {
+                    scope.spawn(move || {
+                        write_code_banks(
+                            args,
+                            options,
+                            dependencies,
+                            used_by,
+                            depths,
+                            project_paths,
+                            output_dir,
+                        )
+                    })
+                },
+            )
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|_| Err(anyhow::anyhow!("Code bank generation panicked")))
+            })
+            .collect()
+    });
+
+    // Report every project before failing on any of them
+    let mut generated_projects = Vec::with_capacity(results.len());
+    let mut errors = Vec::new();
+    for ((project_paths, ..), result) in selected.iter().zip(results) {
+        match result {
+            Ok(generated) => {
+                print_generation_summary(args, &generated);
+                generated_projects.push(generated);
+            }
+            Err(e) => {
+                eprintln!(
+                    "Error generating the code banks of {}: {:#}",
+                    project_paths[0].display(),
+                    e
+                );
+                errors.push(e);
+            }
+        }
+    }
+    let failed = errors.len();
+    if let Some(first) = errors.into_iter().next() {
+        // The first error keeps its exit code
+        return Err(first.context(format!(
+            "Failed to generate the code banks of {} of {} projects",
+            failed,
+            selected.len()
+        )));
+    }
+    for generated in &generated_projects {
+        check_generated_banks(args, generated)?;
+    }
+
+    check_missing_dependencies(args, missing)
+}
+
+/// Generates the code banks straight into the `--archive` archive, see
+/// [`generate_all_to_archive`], without writing anything to the output directory
+fn stream_code_banks_to_archive(
+    args: &GenerateArgs,
+    dependencies: &DependencyCollection,
+    depths: &DependencyDepths,
+) -> Result<()> {
+    let archive = args
+        .archive
+        .as_deref()
+        .context("--archive-only needs an --archive path")?;
+    let mut options = args.generate_options()?;
+    options.dependency_depths.clone_from(depths);
+    let registry_path = resolve_registry_path()?;
+    println!("Streaming code banks into {}...", archive.display());
+    let outcome = write_archive_file(archive, archive_mtime()?, |writer| {
+        generate_all_to_archive(dependencies, &registry_path, writer, &options)
+    })?;
+    println!(
+        "Archived {} code banks into {}",
+        outcome.archived.len(),
+        archive.display()
+    );
+
+    let failed = outcome.failed.len();
+    if failed > 0 && (args.strict || outcome.archived.is_empty()) {
+        return Err(DepBankError::GenerationFailed { count: failed }.into());
+    }
+
+    Ok(())
+}
+
+/// Prints the tokens that the directories left out of the code banks save, for each
+/// dependency, most saved first
+fn report_ignore_savings(args: &GenerateArgs, dependencies: &DependencyCollection) -> Result<()> {
+    println!("Estimating the tokens saved by ignored directories (may take a while)...");
+    let options = args.generate_options()?;
+    let mut comparisons = estimate_ignore_savings(
+        dependencies,
+        &resolve_registry_path()?,
+        &options,
+        &TokenOptions::default(),
+    )?;
+    comparisons.sort_by(|a, b| {
+        a.difference()
+            .cmp(&b.difference())
+            .then_with(|| a.name.cmp(&b.name))
+    });
+
+    println!(
+        "\n{}",
+        heading(&format!(
+            "Tokens saved by ignoring {}:",
+            options.ignore_dirs.as_deref().map_or_else(
+                || DEFAULT_BANK_IGNORED_DIRS.join(", "),
+                |dirs| dirs.join(", ")
+            )
+        ))
+    );
+    for comparison in &comparisons {
+        println!(
+            "- {} {}: {} tokens ({} -> {})",
+            comparison.name,
+            comparison.version,
+            -comparison.difference(),
+            comparison.baseline_tokens,
+            comparison.candidate_tokens
+        );
+    }
+    let before: usize = comparisons.iter().map(|c| c.baseline_tokens).sum();
+    let after: usize = comparisons.iter().map(|c| c.candidate_tokens).sum();
+    println!(
+        "Total: {} tokens saved ({} -> {})",
+        before as i64 - after as i64,
+        before,
+        after
+    );
+
+    Ok(())
+}
+
+/// Lists the source files of one dependency by their tokens, in its sources and in
+/// its code bank, largest first
+fn explain_size(
+    args: &GenerateArgs,
+    dependencies: &DependencyCollection,
+    name: &str,
+) -> Result<CommandOutcome> {
+    let normalized = normalize_crate_name(name);
+    let dependency = dependencies
+        .iter()
+        .find(|dep| normalize_crate_name(&dep.name) == normalized)
+        .ok_or_else(|| anyhow::anyhow!("{} is not a dependency of the project", name))?;
+    let source_path = dependency.get_registry_path(&resolve_registry_path()?);
+
+    let token_options = TokenOptions::default();
+    let sources = analyze_source_sizes(&source_path, Some("rs"), &token_options)?;
+    // The bank's sections are found by their `## <path>` headings
+    let options = GenerateOptions {
+        heading_offset: 0,
+        ..args.generate_options()?
+    };
+    let mut bank = Vec::new();
+    generate_code_bank_to_writer(&source_path, &dependency.name, &options, &mut bank)?;
+    let bank = String::from_utf8(bank).context("Generated code bank is not valid UTF-8")?;
+    let bank_tokens = bank_tokens_by_file(&bank, &token_options)?;
+
+    println!(
+        "\nSource files of {} {} ({}), by tokens:",
+        dependency.name,
+        dependency.version,
+        source_path.display()
+    );
+    println!(
+        "{:<50} {:>10} {:>10} {:>10}",
+        "File", "Bytes", "Source", "Bank"
+    );
+    println!("{}", "-".repeat(83));
+    for stats in &sources {
+        let path = stats.path.strip_prefix(&source_path).unwrap_or(&stats.path);
+        let path = path.to_string_lossy().replace('\\', "/");
+        // Files left out of the bank, such as tests, have no bank tokens
+        let in_bank = bank_tokens
+            .get(&path)
+            .map_or_else(|| "-".to_string(), ToString::to_string);
+        println!(
+            "{:<50} {:>10} {:>10} {:>10}",
+            path, stats.size_bytes, stats.token_count, in_bank
+        );
+    }
+    println!("{}", "-".repeat(83));
+    println!(
+        "{:<50} {:>10} {:>10} {:>10}",
+        "Total",
+        sources.iter().map(|stats| stats.size_bytes).sum::<usize>(),
+        sources.iter().map(|stats| stats.token_count).sum::<usize>(),
+        bank_tokens.values().sum::<usize>()
+    );
+
+    Ok(CommandOutcome::Completed)
+}
+
+/// Narrows the dependencies down to those requested by `--imports-of`,
+/// `--deps-file`, `--fit-budget` and `--max-deps`
+fn select_dependencies(
+    args: &GenerateArgs,
+    dependencies: DependencyCollection,
+    options: &GenerateOptions,
+    project_paths: &[PathBuf],
+    discovery: &DiscoveryOptions,
+    quiet: bool,
+) -> Result<DependencyCollection> {
+    // Narrow down to the crates a specific file imports
+    let mut selected = match &args.imports_of {
+        Some(file) => filter_to_imports_of(&dependencies, file, quiet)?,
+        None => dependencies,
+    };
+    if let Some(file) = &args.deps_file {
+        selected = filter_to_deps_file(&selected, file, quiet)?;
+    }
+
+    let priority = match &args.priority {
+        Some(file) => read_priority_file(file)?,
+        None => Vec::new(),
+    };
+    if let Some(budget) = args.fit_budget {
+        let budget_priority = BudgetPriority {
+            smallest_first: args.smallest_first,
+            crates: priority.clone(),
+        };
+        selected = fit_to_budget(
+            &selected,
+            options,
+            budget,
+            &budget_priority,
+            project_paths,
+            discovery,
+            quiet,
+        )?;
+    }
+
+    // Generate the most important dependencies first
+    if !priority.is_empty() {
+        let mut ordered = DependencyCollection::new();
+        for dependency in order_by_priority(&selected, &priority) {
+            ordered.add(dependency);
+        }
+        selected = ordered;
     }
 
+    if let Some(max_deps) = args.max_deps
+        && selected.len() > max_deps
+    {
+        if !quiet {
+            let skipped: Vec<&str> = selected
+                .iter()
+                .skip(max_deps)
+                .map(|dep| dep.name.as_str())
+                .collect();
+            println!(
+                "Limited to the first {} of {} dependencies by --max-deps, skipping {}: {}",
+                max_deps,
+                selected.len(),
+                skipped.len(),
+                skipped.join(", ")
+            );
+        }
+        selected.truncate(max_deps);
+    }
+
+    Ok(selected)
+}
+
+/// Builds the options generating the banks of the dependencies: those given on the
+/// command line, with the dependencies' depths, and the yanked warnings and usage
+/// sections of `--check-yanked` and `--with-usage`.
+///
+/// Estimates for `--fit-budget` use the same options, so they count everything the
+/// written banks will hold.
+fn bank_options(
+    args: &GenerateArgs,
+    dependencies: &DependencyCollection,
+    used_by: &BTreeMap<String, Vec<PathBuf>>,
+    depths: &DependencyDepths,
+    quiet: bool,
+) -> Result<GenerateOptions> {
+    let mut options = args.generate_options()?;
+    options.dependency_depths.clone_from(depths);
+    if args.check_yanked {
+        options.yanked = find_yanked_dependencies(dependencies, quiet);
+    }
+    if args.with_usage {
+        // Each project's sources are read once for all the dependencies
+        let mut sources: HashMap<&Path, ProjectSources> = HashMap::new();
+        for project_path in used_by.values().flatten() {
+            sources
+                .entry(project_path.as_path())
+                .or_insert_with(|| ProjectSources::read(project_path));
+        }
+        for dep in dependencies.iter() {
+            let Some(projects) = used_by.get(&dep.name) else {
+                continue;
+            };
+            let usages = project_usages(projects, &sources, &dep.name);
+            if !usages.is_empty() {
+                options.usages.insert(dep.name.clone(), usages);
+            }
+        }
+    }
+    Ok(options)
+}
+
+/// Generates the code banks of the dependencies into an output directory, along with
+/// their indexes
+fn write_code_banks(
+    args: &GenerateArgs,
+    options: &GenerateOptions,
+    dependencies: &DependencyCollection,
+    used_by: &BTreeMap<String, Vec<PathBuf>>,
+    depths: &DependencyDepths,
+    project_paths: &[PathBuf],
+    output_dir: &Path,
+) -> Result<GeneratedBanks> {
     // Generate code banks
-    println!("Generating code banks...");
+    println!("Generating code banks into {}...", output_dir.display());
+    if args.clean {
+        let removed = clean_output_dir(output_dir)?;
+        println!(
+            "Removed {} files generated by a previous run",
+            removed.len()
+        );
+    }
     let registry_path = resolve_registry_path()?;
-    let code_bank_files = generate_all_code_banks(&available_deps, &registry_path, output_dir)?;
+    if args.resume {
+        let fingerprint = options_fingerprint(options);
+        let completed = read_progress_log(output_dir)?
+            .iter()
+            .filter(|entry| entry.options == fingerprint)
+            .count();
+        println!(
+            "Resuming: {} dependencies completed by a previous run with the same options",
+            completed
+        );
+    }
+    // Measuring the sources is much cheaper than generating giant banks to drop them
+    let kept;
+    let mut oversized = Vec::new();
+    let selected = match args.skip_over_bytes {
+        Some(max_bytes) => {
+            (kept, oversized) =
+                skip_oversized_dependencies(dependencies, &registry_path, max_bytes);
+            for (name, size) in &oversized {
+                println!(
+                    "Skipping {}: {} bytes of sources, over the {} bytes limit",
+                    name, size, max_bytes
+                );
+            }
+            &kept
+        }
+        None => dependencies,
+    };
+    let started = Instant::now();
+    let GenerationOutcome {
+        code_bank_files,
+        skipped,
+        durations,
+    } = generate_code_banks_with_outcome(selected, &registry_path, output_dir, options)?;
+    let generation_time = started.elapsed();
     println!("Generated {} code bank files", code_bank_files.len());
+    // Only an incomplete run leaves something to resume
+    if skipped.is_empty() && code_bank_files.len() >= selected.len() {
+        remove_progress_log(output_dir)?;
+    }
 
-    // Calculate tokens and generate README
-    generate_code_bank_readme(
-        output_dir,
-        project_path,
-        &available_deps,
-        code_bank_files.len(),
-    )?;
+    // The projects' own packages, versioned from their manifests in the report
+    let mut code_bank_files = code_bank_files;
+    let mut self_versions = HashMap::new();
+    if args.include_self {
+        for project_path in project_paths {
+            let (package, path) = generate_self_code_bank(project_path, output_dir, options)?;
+            println!(
+                "Generated the code bank of {} itself: {}",
+                package.name,
+                path.display()
+            );
+            code_bank_files.insert(package.name.clone(), path);
+            self_versions.insert(package.name, package.version);
+        }
+    }
+
+    // Compress the largest banks last, after all changes to their content
+    if let Some(threshold) = args.compress_over {
+        let compressed = compress_banks_over(&mut code_bank_files, threshold)?;
+        println!(
+            "Compressed {} code banks larger than {} bytes",
+            compressed, threshold
+        );
+    }
+
+    // Calculate tokens and summarize the generated code banks
+    println!("Calculating tokens for generated code banks (may take a while)...");
+    let mut report = build_generation_report(dependencies, &code_bank_files)?;
+    report.project_paths = project_paths.to_vec();
+    report.apply_aliases(&options.aliases);
+    report.record_durations(&durations, generation_time);
+    for bank in &mut report.banks {
+        if let Some(version) = self_versions.get(&bank.name) {
+            bank.version.clone_from(version);
+        }
+        bank.depth = depths.get(&bank.name).copied();
+    }
+    report.skipped = skipped
+        .iter()
+        .map(|name| (name.clone(), SKIPPED_DEADLINE.to_string()))
+        .chain(
+            oversized
+                .iter()
+                .map(|(name, _)| (name.clone(), SKIPPED_TOO_LARGE.to_string())),
+        )
+        .collect();
+    for project_path in project_paths {
+        // The lockfile was already required to resolve versions, if it was usable
+        if let Ok(cargo_lock) = read_cargo_lock(project_path, args.lock.as_deref()) {
+            let names = report.banks.iter().map(|bank| bank.name.as_str());
+            report
+                .major_version_conflicts
+                .extend(warn_major_version_conflicts(&cargo_lock, names));
+        }
+    }
+    // Docs-only banks hold prose instead of doc comments, and outlines no docs at all
+    if !options.docs_only && !options.outline {
+        report.undocumented = find_undocumented_banks(&report, args.min_doc_tokens)?;
+    }
+    if project_paths.len() > 1 {
+        for bank in &mut report.banks {
+            bank.used_by = used_by.get(&bank.name).cloned().unwrap_or_default();
+        }
+    }
+
+    if args.sidecar {
+        for bank in &report.banks {
+            write_sidecar(&bank.path, bank)?;
+        }
+    }
+
+    // Ownership has to be recorded while bank paths can still be resolved
+    report.track_owned_files(output_dir)?;
+    if args.relative_paths {
+        report.relativize_paths(output_dir);
+    }
+
+    if !args.no_index_md {
+        write_index_markdown(&report, output_dir)?;
+        write_index_json(&report, output_dir)?;
+    }
+
+    if let Some(archive) = &args.archive {
+        // The progress log only matters to the output directory it is in
+        let files: Vec<PathBuf> = report
+            .files
+            .iter()
+            .filter(|file| file.as_os_str() != PROGRESS_LOG_FILE && output_dir.join(file).is_file())
+            .cloned()
+            .collect();
+        // Times differ on every run, so the archived index leaves them out to keep
+        // the archive reproducible
+        let mut archived_report = report.clone();
+        archived_report.clear_durations();
+        let replaced = BTreeMap::from([(
+            PathBuf::from(INDEX_JSON_FILE),
+            index_json(&archived_report)?.into_bytes(),
+        )]);
+        let archive = write_archive(output_dir, &files, &replaced, archive, archive_mtime()?)?;
+        println!("Archived {} files into {}", files.len(), archive.display());
+    }
+
+    Ok(GeneratedBanks {
+        output_dir: output_dir.to_path_buf(),
+        report,
+        failed: dependencies
+            .len()
+            .saturating_sub(code_bank_files.len() + skipped.len() + oversized.len()),
+    })
+}
+
+/// Warns about the given crates when they are locked at several semver-incompatible
+/// versions, returning the versions of each conflicting crate
+fn warn_major_version_conflicts<'a>(
+    cargo_lock: &ParsedLock,
+    names: impl IntoIterator<Item = &'a str>,
+) -> BTreeMap<String, Vec<String>> {
+    let conflicts = find_major_version_conflicts_from_lock(cargo_lock);
+
+    let mut found = BTreeMap::new();
+    for name in names {
+        let Some(versions) = conflicts.get(name) else {
+            continue;
+        };
+        let versions: Vec<String> = versions.iter().map(ToString::to_string).collect();
+        emit_warning(Warning::new(
+            WarningKind::MajorVersionConflict,
+            Some(name),
+            format!(
+                "{} is locked at incompatible versions {}, only one may be documented",
+                name,
+                versions.join(", ")
+            ),
+        ));
+        found.insert(name.to_string(), versions);
+    }
+    found
+}
+
+/// Prints a concise summary of the code banks written into an output directory
+fn print_generation_summary(args: &GenerateArgs, generated: &GeneratedBanks) {
+    let report = &generated.report;
+    println!("\n{}", heading("Summary:"));
+    println!("- Generated {} code bank files", report.banks.len());
+    println!("- Total tokens: {}", report.total_tokens());
+    if !args.no_index_md {
+        println!("- Added README.md and index.json with summary and token information");
+    }
+    println!("- Output directory: {}", generated.output_dir.display());
+    let deadline_skipped = count_skipped(report, SKIPPED_DEADLINE);
+    if deadline_skipped > 0 {
+        println!(
+            "- Skipped {} dependencies, the deadline was reached",
+            deadline_skipped
+        );
+    }
+    let too_large = count_skipped(report, SKIPPED_TOO_LARGE);
+    if too_large > 0 {
+        println!(
+            "- Skipped {} dependencies with sources over --skip-over-bytes",
+            too_large
+        );
+    }
+    if !report.major_version_conflicts.is_empty() {
+        println!(
+            "- Crates locked at several major versions: {}",
+            report
+                .major_version_conflicts
+                .keys()
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+
+    if !report.undocumented.is_empty() {
+        println!(
+            "- Undocumented dependencies, with almost no doc comments: {}",
+            report.undocumented.join(", ")
+        );
+    }
+
+    let banks_by_tokens = report.banks_by_tokens();
+    let shown = args.top.unwrap_or(banks_by_tokens.len());
+    println!("\n{}", heading("Tokens by dependency:"));
+    for bank in banks_by_tokens.iter().take(shown) {
+        println!("- {}: {}", bank.name, bank.token_count);
+    }
+    if banks_by_tokens.len() > shown {
+        println!("- ... and {} more", banks_by_tokens.len() - shown);
+    }
+
+    if let Some(n) = args.timings {
+        println!("\n{}", heading("Slowest dependencies to generate:"));
+        for bank in report.slowest_banks(n) {
+            println!(
+                "- {}: {:.2}s",
+                bank.name,
+                bank.generation_secs.unwrap_or_default()
+            );
+        }
+        if let Some(total) = report.total_generation_secs {
+            println!("Total generation time: {:.2}s", total);
+        }
+    }
+}
+
+/// Fails if the generated code banks exceed the token budget, if generation failed
+/// for every dependency, or for any of them in strict mode, or if the deadline
+/// skipped some of them
+fn check_generated_banks(args: &GenerateArgs, generated: &GeneratedBanks) -> Result<()> {
+    if let Some(budget) = args.max_total_tokens {
+        let total = generated.report.total_tokens();
+        if total > budget {
+            return Err(DepBankError::TokenBudgetExceeded { total, budget }.into());
+        }
+    }
+
+    let failed = generated.failed;
+    if failed > 0 && (args.strict || generated.report.banks.is_empty()) {
+        return Err(DepBankError::GenerationFailed { count: failed }.into());
+    }
+
+    let deadline_skipped = count_skipped(&generated.report, SKIPPED_DEADLINE);
+    if let Some(deadline_secs) = args.deadline_secs
+        && deadline_skipped > 0
+    {
+        return Err(DepBankError::DeadlineExceeded {
+            count: deadline_skipped,
+            deadline_secs,
+        }
+        .into());
+    }
 
     Ok(())
 }
 
-fn analyze_dependencies(project_path: &Path, _dry_run: bool) -> Result<DependencyCollection> {
+/// Styles a section heading of the output
+fn heading(text: &str) -> String {
+    paint(text, ColorStyle::Heading, OutputStream::Stdout)
+}
+
+/// Counts the dependencies the report lists as skipped for a reason
+fn count_skipped(report: &GenerationReport, reason: &str) -> usize {
+    report
+        .skipped
+        .values()
+        .filter(|skipped| skipped.as_str() == reason)
+        .count()
+}
+
+/// Combines the dependencies of several projects, keeping one entry per crate.
+///
+/// When projects resolve a crate to different versions, `policy` picks the version
+/// kept. Alongside the merged dependencies, sorted by name, this returns the projects
+/// that need each crate.
+fn merge_project_dependencies(
+    projects: Vec<(PathBuf, DependencyCollection)>,
+    policy: VersionConflictPolicy,
+) -> Result<(DependencyCollection, BTreeMap<String, Vec<PathBuf>>)> {
+    let mut all = DependencyCollection::new();
+    let mut used_by: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+
+    for (project_path, dependencies) in projects {
+        for dep in dependencies.iter() {
+            let projects = used_by.entry(dep.name.clone()).or_default();
+            if !projects.contains(&project_path) {
+                projects.push(project_path.clone());
+            }
+            all.add(dep.clone());
+        }
+    }
+
+    let mut merged: Vec<Dependency> = resolve_version_conflicts(&all, policy)?
+        .iter()
+        .cloned()
+        .collect();
+    merged.sort_by(|a, b| a.name.cmp(&b.name));
+    let mut collection = DependencyCollection::new();
+    for dep in merged {
+        collection.add(dep);
+    }
+    Ok((collection, used_by))
+}
+
+/// Finds the usages of a crate in the projects that need it.
+///
+/// With several projects, snippet paths are prefixed with the project path, so they
+/// stay unambiguous.
+fn project_usages(
+    projects: &[PathBuf],
+    sources: &HashMap<&Path, ProjectSources>,
+    name: &str,
+) -> Vec<UsageSnippet> {
+    if let [project_path] = projects {
+        return sources[project_path.as_path()].find_usages(name);
+    }
+
+    let mut usages: Vec<UsageSnippet> = projects
+        .iter()
+        .flat_map(|project_path| {
+            sources[project_path.as_path()]
+                .find_usages(name)
+                .into_iter()
+                .map(move |usage| UsageSnippet {
+                    file: project_path.join(&usage.file),
+                    ..usage
+                })
+        })
+        .collect();
+    usages.sort_by_key(|usage| !usage.is_import);
+    usages.truncate(MAX_USAGE_SNIPPETS);
+    usages
+}
+
+/// Narrows the dependencies down to those whose code banks fit in a token budget,
+/// reporting the ones that were dropped
+fn fit_to_budget(
+    dependencies: &DependencyCollection,
+    options: &GenerateOptions,
+    budget: usize,
+    priority: &BudgetPriority,
+    project_paths: &[PathBuf],
+    discovery: &DiscoveryOptions,
+    quiet: bool,
+) -> Result<DependencyCollection> {
+    // Dependencies the projects declare themselves are kept first
+    let mut direct = HashSet::new();
+    for project_path in project_paths {
+        let cargo_toml_files = find_cargo_toml_files(project_path, discovery)?;
+        direct.extend(collect_dependencies(&cargo_toml_files)?);
+    }
+
+    if !quiet {
+        println!(
+            "Estimating code bank tokens to fit a budget of {}...",
+            budget
+        );
+    }
+    let estimates: Vec<TokenEstimate> = estimate_dependency_tokens(
+        dependencies,
+        &resolve_registry_path()?,
+        options,
+        &TokenOptions::default(),
+    )?
+    .into_iter()
+    .map(|(dependency, tokens)| TokenEstimate {
+        direct: direct.contains(&dependency.name),
+        dependency,
+        tokens,
+    })
+    .collect();
+    let selected = select_within_budget(&estimates, budget, priority);
+
+    let dropped: Vec<&TokenEstimate> = estimates
+        .iter()
+        .filter(|estimate| !selected.contains(&estimate.dependency))
+        .collect();
+    let selected_tokens: usize = estimates
+        .iter()
+        .filter(|estimate| selected.contains(&estimate.dependency))
+        .map(|estimate| estimate.tokens)
+        .sum();
+    if quiet {
+        return Ok(fitted(dependencies, &selected));
+    }
+    println!(
+        "Selected {} dependencies with about {} tokens within the budget",
+        selected.len(),
+        selected_tokens
+    );
+    if !dropped.is_empty() {
+        println!("Dropped {} dependencies:", dropped.len());
+        for estimate in dropped {
+            println!(
+                "- {} {} ({} tokens)",
+                estimate.dependency.name, estimate.dependency.version, estimate.tokens
+            );
+        }
+    }
+
+    Ok(fitted(dependencies, &selected))
+}
+
+/// Keeps the dependencies selected within a budget, in their original order
+fn fitted(dependencies: &DependencyCollection, selected: &[Dependency]) -> DependencyCollection {
+    let mut fitted = DependencyCollection::new();
+    for dep in dependencies.iter().filter(|dep| selected.contains(dep)) {
+        fitted.add(dep.clone());
+    }
+    fitted
+}
+
+/// Fails in strict mode if any dependency is missing from the local registry
+fn check_missing_dependencies(args: &GenerateArgs, missing: usize) -> Result<CommandOutcome> {
+    if args.strict && missing > 0 {
+        return Err(DepBankError::MissingDependencies { count: missing }.into());
+    }
+
+    Ok(CommandOutcome::Completed)
+}
+
+pub fn watch_command(args: &WatchArgs) -> Result<()> {
+    if args.lock.as_deref().is_some_and(is_stdin_lock) {
+        return Err(anyhow::anyhow!(
+            "watch cannot read Cargo.lock from stdin, since it regenerates when the lockfile changes; pass its path instead"
+        ));
+    }
+    let options = args.watch_options();
+    println!(
+        "Watching {} for changes (press Ctrl-C to stop)...",
+        args.path.display()
+    );
+
+    watch_project(&args.path, &options, |run| {
+        match run {
+            Ok(run) => {
+                for file in &run.changed {
+                    println!("\nChanged: {}", file.display());
+                }
+                println!(
+                    "Generated {} code bank files, {} reused from previous runs",
+                    run.regenerated.len(),
+                    run.banks.len().saturating_sub(run.regenerated.len())
+                );
+            }
+            // Keep watching, the next change may fix the problem
+            Err(e) => eprintln!("Error: {:#}", e),
+        }
+        ControlFlow::Continue(())
+    })
+}
+
+/// Keeps only the dependencies imported by the given Rust source file
+fn filter_to_imports_of(
+    dependencies: &DependencyCollection,
+    file: &Path,
+    quiet: bool,
+) -> Result<DependencyCollection> {
+    let imported = crates_referenced_in(file)?;
+
+    let mut filtered = DependencyCollection::new();
+    for dependency in dependencies.iter() {
+        // Crate names use underscores in code, while package names may use hyphens
+        if imported
+            .iter()
+            .any(|name| normalize_crate_name(name) == normalize_crate_name(&dependency.name))
+        {
+            filtered.add(dependency.clone());
+        }
+    }
+
+    if !quiet {
+        println!(
+            "{}/{} dependencies imported by {}",
+            filtered.len(),
+            dependencies.len(),
+            file.display()
+        );
+    }
+
+    if filtered.is_empty() {
+        return Err(anyhow::anyhow!(
+            "None of the available dependencies are imported by {}",
+            file.display()
+        ));
+    }
+
+    Ok(filtered)
+}
+
+/// Keeps the dependencies listed in a deps file, reporting the listed ones that are
+/// not among them
+fn filter_to_deps_file(
+    dependencies: &DependencyCollection,
+    file: &Path,
+    quiet: bool,
+) -> Result<DependencyCollection> {
+    let entries = load_deps_file(file)?;
+    let filtered = filter_to_deps_list(dependencies, &entries);
+    if quiet {
+        return Ok(filtered);
+    }
+
+    println!(
+        "{}/{} dependencies listed in {}",
+        filtered.len(),
+        dependencies.len(),
+        file.display()
+    );
+    let unmatched: Vec<String> = entries
+        .iter()
+        .filter(|entry| filter_to_deps_list(&filtered, std::slice::from_ref(entry)).is_empty())
+        .map(|(name, version)| match version {
+            Some(version) => format!("{}@{}", name, version),
+            None => name.clone(),
+        })
+        .collect();
+    if !unmatched.is_empty() {
+        println!(
+            "Skipping {} listed dependencies that are not resolved: {}",
+            unmatched.len(),
+            unmatched.join(", ")
+        );
+    }
+
+    Ok(filtered)
+}
+
+/// Uses the explicitly provided lockfile if any, otherwise discovers it from the project path
+fn locate_cargo_lock(project_path: &Path, lock: Option<&Path>) -> Result<PathBuf> {
+    match lock {
+        Some(lock_path) => validate_cargo_lock(lock_path),
+        None => find_cargo_lock(project_path),
+    }
+}
+
+/// Reads and parses the Cargo.lock of a project, from stdin when the lockfile is
+/// given as `-`
+fn read_cargo_lock(project_path: &Path, lock: Option<&Path>) -> Result<ParsedLock> {
+    if lock.is_some_and(is_stdin_lock) {
+        return ParsedLock::parse(&read_stdin_lock()?).context("Invalid Cargo.lock on stdin");
+    }
+
+    ParsedLock::read(locate_cargo_lock(project_path, lock)?)
+}
+
+fn is_stdin_lock(lock: &Path) -> bool {
+    lock == Path::new(STDIN_LOCK)
+}
+
+/// Reads the lockfile from stdin, once per run, so later reads get the same content
+fn read_stdin_lock() -> Result<String> {
+    if let Some(content) = STDIN_LOCK_CONTENT.get() {
+        return Ok(content.clone());
+    }
+
+    let content =
+        io::read_to_string(io::stdin()).context("Failed to read Cargo.lock from stdin")?;
+    validate_cargo_lock_str(&content).context("Invalid Cargo.lock on stdin")?;
+    Ok(STDIN_LOCK_CONTENT.get_or_init(|| content).clone())
+}
+
+/// Prints the fingerprint of the projects' dependencies and generation options.
+///
+/// The dependencies are resolved and selected as `generate` resolves and selects
+/// them, but without checking which are available in the local registry, so the
+/// fingerprint can be computed before fetching anything. Only the fingerprint is
+/// printed, for use as a cache key.
+pub fn fingerprint_command(args: &GenerateArgs) -> Result<CommandOutcome> {
+    if args.paths.len() > 1 && args.lock.is_some() {
+        return Err(anyhow::anyhow!(
+            "--lock can only be used with a single project path"
+        ));
+    }
+
+    let discovery = args.discovery.discovery_options_with_env();
+    let resolve = ProjectResolution::for_generate(args, &discovery, true);
+    let mut projects = Vec::with_capacity(args.paths.len());
+    let mut depths = DependencyDepths::new();
+    for project_path in &args.paths {
+        let resolved = resolve_project(project_path, &resolve)?.unwrap_or_default();
+        for (name, depth) in resolved.depths {
+            depths
+                .entry(name)
+                .and_modify(|known| *known = (*known).min(depth))
+                .or_insert(depth);
+        }
+        projects.push((project_path.clone(), resolved.dependencies));
+    }
+
+    let mut dependencies = DependencyCollection::new();
+    if args.per_project_output {
+        for (project_path, resolved) in projects {
+            let project_paths = vec![project_path];
+            let used_by: BTreeMap<String, Vec<PathBuf>> = resolved
+                .iter()
+                .map(|dep| (dep.name.clone(), project_paths.clone()))
+                .collect();
+            let options = bank_options(args, &resolved, &used_by, &depths, true)?;
+            let selected =
+                select_dependencies(args, resolved, &options, &project_paths, &discovery, true)?;
+            for dep in selected.iter() {
+                dependencies.add(dep.clone());
+            }
+        }
+    } else {
+        let (merged, used_by) =
+            merge_project_dependencies(projects, args.on_version_conflict.into())?;
+        let options = bank_options(args, &merged, &used_by, &depths, true)?;
+        dependencies = select_dependencies(args, merged, &options, &args.paths, &discovery, true)?;
+    }
+    if let Some(max_bytes) = args.skip_over_bytes {
+        let registry_path = resolve_registry_path().unwrap_or_default();
+        (dependencies, _) = skip_oversized_dependencies(&dependencies, &registry_path, max_bytes);
+    }
+
+    // The projects' own sources are generated, or searched for usages
+    let mut settings = BTreeMap::new();
+    if args.include_self || args.with_usage {
+        for project_path in &args.paths {
+            settings.insert(
+                format!("project {}", project_path.display()),
+                source_fingerprint(project_path),
+            );
+        }
+    }
+    settings.insert("include_self".to_string(), args.include_self.to_string());
+    settings.insert("with_usage".to_string(), args.with_usage.to_string());
+    settings.insert("check_yanked".to_string(), args.check_yanked.to_string());
+    settings.insert(
+        "per_project_output".to_string(),
+        args.per_project_output.to_string(),
+    );
+    if let Some(compress_over) = args.compress_over {
+        settings.insert("compress_over".to_string(), compress_over.to_string());
+    }
+
+    let mut options = args.generate_options()?;
+    options.dependency_depths = depths;
+    println!(
+        "{}",
+        project_fingerprint(&dependencies, &options, &settings)
+    );
+    Ok(CommandOutcome::Completed)
+}
+
+/// Writes a Software Bill of Materials of a project's resolved dependencies.
+///
+/// Licenses are read from the manifests in the local registry, and left out for
+/// dependencies that are not available there. The document is printed, or written
+/// to `output` if given.
+pub fn sbom_command(
+    project_path: &Path,
+    lock: Option<&Path>,
+    transitive: Option<TransitiveOptions>,
+    discovery: &DiscoveryOptions,
+    format: SbomFormat,
+    output: Option<&Path>,
+) -> Result<CommandOutcome> {
+    let resolved = resolve_project(
+        project_path,
+        &ProjectResolution::listing(lock, discovery, transitive),
+    )?
+    .unwrap_or_default()
+    .dependencies;
+
+    // The same dependency is resolved once per manifest declaring it
+    let mut dependencies: Vec<&Dependency> = resolved.iter().collect();
+    dependencies.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.version.cmp(&b.version)));
+    dependencies.dedup_by(|a, b| a.name == b.name && a.version == b.version);
+
+    let registry_path = resolve_registry_path().ok();
+    let mut manifests = ManifestCache::new();
+    let components: Vec<SbomComponent> = dependencies
+        .into_iter()
+        .map(|dependency| SbomComponent {
+            license: registry_metadata(dependency, registry_path.as_deref(), &mut manifests).1,
+            dependency: dependency.clone(),
+        })
+        .collect();
+
+    let document = match format {
+        SbomFormat::Cyclonedx => to_cyclonedx(&components),
+        SbomFormat::Spdx => to_spdx(&components, &project_name(project_path), SystemTime::now()),
+    };
+    let content = serde_json::to_string_pretty(&document)?;
+    match output {
+        Some(path) => {
+            fs::write(path, content + "\n")
+                .with_context(|| format!("Failed to write SBOM: {}", path.display()))?;
+            println!(
+                "Wrote an SBOM of {} dependencies to {}",
+                components.len(),
+                path.display()
+            );
+        }
+        None => println!("{}", content),
+    }
+
+    Ok(CommandOutcome::Completed)
+}
+
+/// How the dependencies of a project are found and resolved
+#[derive(Debug, Clone, Copy)]
+struct ProjectResolution<'a> {
+    /// The lockfile given explicitly, if any
+    lock: Option<&'a Path>,
+    /// How the project's manifests are discovered
+    discovery: &'a DiscoveryOptions,
+    /// How the declared dependencies are resolved
+    options: ResolveOptions,
+    /// Don't print the progress of the resolution
+    quiet: bool,
+}
+
+impl<'a> ProjectResolution<'a> {
+    /// The resolution of `generate`, which `fingerprint` repeats quietly
+    fn for_generate(args: &'a GenerateArgs, discovery: &'a DiscoveryOptions, quiet: bool) -> Self {
+        Self {
+            lock: args.lock.as_deref(),
+            discovery,
+            options: ResolveOptions {
+                transitive: args.transitive_options(),
+                include_members: args.include_members,
+                on_version_conflict: Some(args.on_version_conflict.into()),
+                on_unresolvable: args.on_unresolvable.into(),
+            },
+            quiet,
+        }
+    }
+
+    /// The quiet resolution of commands listing every resolved version, such as
+    /// `sbom` and `meta`
+    fn listing(
+        lock: Option<&'a Path>,
+        discovery: &'a DiscoveryOptions,
+        transitive: Option<TransitiveOptions>,
+    ) -> Self {
+        Self {
+            lock,
+            discovery,
+            options: ResolveOptions {
+                transitive,
+                ..Default::default()
+            },
+            quiet: true,
+        }
+    }
+}
+
+/// Resolves the dependencies of a project from its manifests and Cargo.lock.
+///
+/// Transitive dependencies are added, patches applied and workspace members left
+/// out or replaced by their local sources, as `resolution` asks, see
+/// [`resolve_project_dependencies`]. Returns `None` when the project declares no
+/// dependencies.
+fn resolve_project(
+    project_path: &Path,
+    resolution: &ProjectResolution,
+) -> Result<Option<ResolvedDependencies>> {
+    let quiet = resolution.quiet;
+
     // Find all Cargo.toml files
-    let cargo_toml_files = find_cargo_toml_files(project_path)?;
-    println!("Found {} Cargo.toml files", cargo_toml_files.len());
+    let cargo_toml_files = find_cargo_toml_files(project_path, resolution.discovery)?;
+    if !quiet {
+        println!("Found {} Cargo.toml files", cargo_toml_files.len());
+    }
 
     if cargo_toml_files.is_empty() {
         return Err(anyhow::anyhow!("No Cargo.toml files found"));
@@ -64,32 +1363,89 @@ fn analyze_dependencies(project_path: &Path, _dry_run: bool) -> Result<Dependenc
 
     // Extract dependencies from *all* found Cargo.toml files
     let mut dependency_info = DependencyCollection::new();
-    let mut unique_deps_for_reporting = HashSet::new(); // Track unique dep names for reporting
-
     for cargo_toml_path in &cargo_toml_files {
-        let file_deps = extract_dependency_info(cargo_toml_path)?;
-        for dep in file_deps.iter() {
-            // Add to the main collection
+        for dep in extract_dependency_info(cargo_toml_path)?.iter() {
             dependency_info.add(dep.clone());
-            // Track unique names for reporting count
-            unique_deps_for_reporting.insert(dep.name.clone());
         }
     }
 
-    println!(
-        "Found {} unique dependencies",
-        unique_deps_for_reporting.len()
-    );
+    if dependency_info.is_empty() {
+        if !quiet {
+            report_no_dependencies(cargo_toml_files.len());
+        }
+        return Ok(None);
+    }
+    if !quiet {
+        let unique_names: HashSet<&str> = dependency_info
+            .iter()
+            .map(|dep| dep.name.as_str())
+            .collect();
+        println!("Found {} unique dependencies", unique_names.len());
+    }
 
-    // Find Cargo.lock - should still be at the workspace root
-    let cargo_lock_path = find_cargo_lock(project_path)?;
-    println!("Found Cargo.lock");
+    // Find Cargo.lock - should still be at the workspace root unless given explicitly
+    let cargo_lock = read_cargo_lock(project_path, resolution.lock)?;
+    if !quiet {
+        println!("Found Cargo.lock");
+    }
 
-    // Resolve exact versions from Cargo.lock using the aggregated dependency info
-    let resolved_versions = resolve_dependency_versions(cargo_lock_path, &dependency_info)?;
-    println!("Resolved {} versions", resolved_versions.len());
+    // Without a registry, nothing can be resolved from it or read from its manifests
+    let registry_path = resolve_registry_path().unwrap_or_default();
+    let resolved = resolve_project_dependencies(
+        &cargo_toml_files,
+        &dependency_info,
+        &cargo_lock,
+        &registry_path,
+        &resolution.options,
+    )?;
+    if quiet {
+        return Ok(Some(resolved));
+    }
 
-    // Resolve registry path
+    println!("Resolved {} versions", resolved.resolved_count);
+    if resolution.options.transitive.is_some() {
+        println!(
+            "Found {} transitive dependencies",
+            resolved.transitive_count
+        );
+    }
+    if resolved.patched_count > 0 {
+        println!("Found {} patched dependencies", resolved.patched_count);
+    }
+    if !resolved.member_names.is_empty() {
+        if resolution.options.include_members {
+            println!(
+                "Generating {} workspace member(s) from their local sources: {}",
+                resolved.member_names.len(),
+                resolved.member_names.join(", ")
+            );
+        } else {
+            println!(
+                "Skipping {} workspace member(s), use --include-members to generate them: {}",
+                resolved.member_names.len(),
+                resolved.member_names.join(", ")
+            );
+        }
+    }
+
+    Ok(Some(resolved))
+}
+
+/// Resolves the dependencies of a project and keeps those available in the local
+/// registry, returning them with the number of missing ones and their depths
+fn analyze_dependencies(
+    project_path: &Path,
+    resolution: &ProjectResolution,
+) -> Result<(DependencyCollection, usize, DependencyDepths)> {
+    let Some(ResolvedDependencies {
+        dependencies: resolved_versions,
+        unique_names: unique_deps_for_reporting,
+        depths,
+        ..
+    }) = resolve_project(project_path, resolution)?
+    else {
+        return Ok((DependencyCollection::new(), 0, DependencyDepths::new()));
+    };
     let registry_path = resolve_registry_path()?;
 
     // Check which dependencies are available locally
@@ -98,11 +1454,25 @@ fn analyze_dependencies(project_path: &Path, _dry_run: bool) -> Result<Dependenc
     // the same dep name appears in multiple Cargo.toml files after version resolution.
     let mut added_names = HashSet::new();
     for dependency in resolved_versions.iter() {
-        if !added_names.contains(&dependency.name)
-            && is_dependency_available(&registry_path, dependency)
-        {
+        if added_names.contains(&dependency.name) {
+            continue;
+        }
+        if is_dependency_available(&registry_path, dependency) {
             available_deps.add(dependency.clone());
             added_names.insert(dependency.name.clone());
+        } else {
+            let mut message = format!(
+                "{} {} is not available in the local cargo registry",
+                dependency.name, dependency.version
+            );
+            if let Some(found) = find_version_mismatch(dependency, &registry_path) {
+                message.push_str(&format!(", only {} is", found.join(", ")));
+            }
+            emit_warning(Warning::new(
+                WarningKind::MissingDependency,
+                Some(&dependency.name),
+                message,
+            ));
         }
     }
 
@@ -112,170 +1482,155 @@ fn analyze_dependencies(project_path: &Path, _dry_run: bool) -> Result<Dependenc
         unique_deps_for_reporting.len() // Report against unique names found initially
     );
 
+    let missing = unique_deps_for_reporting
+        .len()
+        .saturating_sub(available_deps.len());
     if available_deps.is_empty() {
         // Adjust the error message to be more accurate in a workspace context
-        return Err(anyhow::anyhow!(
-            "No resolved dependencies available locally in the Cargo registry"
-        ));
+        return Err(
+            anyhow::Error::from(DepBankError::MissingDependencies { count: missing })
+                .context("No resolved dependencies available locally in the Cargo registry"),
+        );
     }
 
-    // Return the available dependencies
-    Ok(available_deps)
+    // Return the available dependencies along with the number of missing ones
+    Ok((available_deps, missing, depths))
 }
 
-fn generate_code_bank_readme(
-    output_dir: &Path,
-    project_path: &Path,
-    dependencies: &DependencyCollection,
-    code_bank_files_count: usize,
-) -> Result<()> {
-    println!("Calculating tokens for generated code banks (may take a while)...");
-    // Calculate tokens for generated code banks
-    let file_stats = calculate_directory_tokens(output_dir, Some("md"))?;
-
-    // Sort stats by token count
-    let mut stats_vec: Vec<_> = file_stats.iter().collect();
-    stats_vec.sort_by(|a, b| b.1.token_count.cmp(&a.1.token_count)); // Sort by token count (descending)
-
-    // Generate README content
-    let (readme_content, total_tokens) = create_readme_content(
-        project_path,
-        code_bank_files_count,
-        &stats_vec,
-        dependencies,
-        &file_stats,
-    );
-
-    // Write README.md to the output directory
-    let readme_path = output_dir.join("README.md");
-    fs::write(&readme_path, readme_content).with_context(|| {
-        format!(
-            "Failed to write README.md to file: {}",
-            readme_path.display()
-        )
-    })?;
-
-    // Print concise summary to console
-    println!("\nSummary:");
-    println!("- Generated {} code bank files", code_bank_files_count);
-    println!("- Total tokens: {}", total_tokens);
-    println!("- Added README.md with summary and token information");
-    println!("- Output directory: {}", output_dir.display());
-
-    Ok(())
-}
-
-fn create_readme_content(
-    project_path: &Path,
-    code_bank_files_count: usize,
-    stats_vec: &[(&String, &depbank::FileStats)],
-    dependencies: &DependencyCollection,
-    file_stats: &HashMap<String, depbank::FileStats>,
-) -> (String, usize) {
-    let mut total_tokens = 0;
-    let mut total_size = 0;
-    let mut readme_content = String::new();
-
-    // Add header
-    readme_content.push_str(README_HEADER);
-    write!(
-        readme_content,
-        "Generated for project: {}\n\n",
-        project_path.display()
-    )
-    .unwrap();
-
-    // Add dependency section
-    write!(
-        readme_content,
-        "## Dependencies ({} total)\n\n",
-        code_bank_files_count
-    )
-    .unwrap();
-
-    // Add dependency list with versions
-    readme_content.push_str(README_TABLE_HEADER);
-    readme_content.push_str(README_TABLE_SEPARATOR);
-
-    for (name, stats) in stats_vec {
-        let name_without_md = name.trim_end_matches(".md");
-        let version = dependencies
-            .get_version(name_without_md)
-            .map(|v| v.as_str())
-            .unwrap_or("unknown");
-
-        writeln!(
-            readme_content,
-            "| {} | {} | {} | {} |",
-            name_without_md, version, stats.token_count, stats.size_bytes
-        )
-        .unwrap();
-
-        total_tokens += stats.token_count;
-        total_size += stats.size_bytes;
+/// Finds the dependencies whose resolved version has been yanked from crates.io.
+///
+/// Versions are looked up in the crates.io index. Once the index cannot be reached,
+/// the remaining dependencies fall back to cargo's local copy of the index, and those
+/// it does not cover are skipped with a note.
+fn find_yanked_dependencies(dependencies: &DependencyCollection, quiet: bool) -> BTreeSet<String> {
+    let registry_path = resolve_registry_path().ok();
+    let mut online = std::env::var("CARGO_NET_OFFLINE").map_or(true, |value| value != "true");
+    let mut yanked = BTreeSet::new();
+    let mut unchecked = 0;
+
+    for dep in dependencies.iter() {
+        let status = if online {
+            is_version_yanked(&dep.name, &dep.version).ok()
+        } else {
+            None
+        };
+
+        // Stop querying the index after the first failure, assuming we are offline
+        online &= status.is_some();
+        let status = status.or_else(|| {
+            registry_path
+                .as_deref()
+                .and_then(|registry| cached_yanked_status(registry, &dep.name, &dep.version))
+        });
+
+        match status {
+            Some(true) => {
+                yanked.insert(dep.name.clone());
+            }
+            Some(false) => {}
+            None => unchecked += 1,
+        }
     }
 
-    // Add token summary
-    readme_content.push_str(README_TOKEN_SUMMARY_HEADER);
-    writeln!(readme_content, "- **Total tokens:** {}", total_tokens).unwrap();
-    writeln!(readme_content, "- **Total size:** {} bytes", total_size).unwrap();
-    writeln!(
-        readme_content,
-        "- **Number of files:** {}",
-        file_stats.len()
-    )
-    .unwrap();
-
-    // Add explanation of what code banks are
-    readme_content.push_str(README_ABOUT_HEADER);
-    readme_content.push_str(README_ABOUT_P1);
-    readme_content.push_str(README_ABOUT_P2);
-    readme_content.push_str(README_ABOUT_P3);
-    readme_content.push_str(README_ABOUT_P4);
-    readme_content.push_str(README_ABOUT_P5);
-    readme_content.push_str(README_ABOUT_P6);
-    readme_content.push_str(README_ABOUT_P7);
+    if unchecked > 0 && !quiet {
+        println!(
+            "Note: crates.io index unavailable, skipped yanked check for {} dependencies",
+            unchecked
+        );
+    }
 
-    (readme_content, total_tokens)
+    yanked
 }
 
-pub fn tokens_command(path: &Path, extension: Option<&str>) -> Result<()> {
-    if path.is_file() {
-        analyze_file_tokens(path)?;
+pub fn tokens_command(
+    path: &Path,
+    extension: Option<&str>,
+    dedup: bool,
+    options: &TokenOptions,
+    price_per_1k: Option<f64>,
+    relative_paths: bool,
+) -> Result<()> {
+    let shown = if relative_paths {
+        relativize(path, &std::env::current_dir()?)
+    } else {
+        path.to_path_buf()
+    };
+    let total_tokens = if path.is_file() {
+        analyze_file_tokens(path, &shown, options)?
     } else if path.is_dir() {
-        analyze_directory_tokens(path, extension)?;
+        analyze_directory_tokens(path, &shown, extension, dedup, options, relative_paths)?
     } else {
         return Err(anyhow::anyhow!(
             "Path does not exist or is not accessible: {}",
             path.display()
         ));
+    };
+
+    if let Some(rate) = price_per_1k {
+        println!(
+            "Estimated cost: ${:.4} (at ${} per 1K tokens)",
+            estimate_cost(total_tokens, rate),
+            rate
+        );
     }
 
     Ok(())
 }
 
-fn analyze_file_tokens(path: &Path) -> Result<()> {
+/// Looks up the default price of a model given with `--model`
+pub fn resolve_model_price(model: &str) -> Result<f64> {
+    model_price_per_1k(model).ok_or_else(|| {
+        let known: Vec<&str> = KNOWN_MODEL_PRICES.iter().map(|(name, _)| *name).collect();
+        anyhow::anyhow!(
+            "Unknown model '{}', use --price instead or one of: {}",
+            model,
+            known.join(", ")
+        )
+    })
+}
+
+fn analyze_file_tokens(path: &Path, shown: &Path, options: &TokenOptions) -> Result<usize> {
     // Calculate tokens for a single file
-    let token_count = calculate_file_tokens(path)?;
+    let token_count = calculate_file_tokens(path, options)?;
     let file_size = std::fs::metadata(path)?.len();
     println!(
         "{}: {} tokens, {} bytes",
-        path.display(),
+        shown.display(),
         token_count,
         file_size
     );
-    Ok(())
+    if let Some(max_length) = options.max_length
+        && token_count >= max_length
+    {
+        println!(
+            "The file reaches the {}-token limit, so it may not fit",
+            max_length
+        );
+    }
+    Ok(token_count)
 }
 
-fn analyze_directory_tokens(dir_path: &Path, extension: Option<&str>) -> Result<()> {
+fn analyze_directory_tokens(
+    dir_path: &Path,
+    shown: &Path,
+    extension: Option<&str>,
+    dedup: bool,
+    options: &TokenOptions,
+    relative_paths: bool,
+) -> Result<usize> {
     // Calculate tokens for all files in the directory
-    let file_stats = calculate_directory_tokens(dir_path, extension)?;
+    let mut file_stats = calculate_directory_tokens(dir_path, extension, options)?;
+    if relative_paths {
+        for stats in file_stats.values_mut() {
+            stats.relativize_path(dir_path);
+        }
+    }
 
     // Print token counts in a sorted manner
     let mut stats_vec: Vec<_> = file_stats.iter().collect();
-    stats_vec.sort_by(|a, b| b.1.token_count.cmp(&a.1.token_count)); // Sort by token count (descending)
+    stats_vec.sort_by_key(|s| std::cmp::Reverse(s.1.token_count)); // Sort by token count (descending)
 
-    let (total_tokens, total_size) = print_token_stats(dir_path, &stats_vec, file_stats.len());
+    let (total_tokens, total_size) = print_token_stats(shown, &stats_vec, file_stats.len());
 
     println!(
         "\nTotal: {} tokens, {} bytes across {} files",
@@ -284,7 +1639,56 @@ fn analyze_directory_tokens(dir_path: &Path, extension: Option<&str>) -> Result<
         file_stats.len()
     );
 
-    Ok(())
+    // Only worth a breakdown when the directory mixes kinds of files
+    let mut by_extension: Vec<(String, usize)> = tokens_by_extension(file_stats.values())
+        .into_iter()
+        .collect();
+    if by_extension.len() > 1 {
+        by_extension.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        println!("\n{}", heading("Tokens by extension:"));
+        for (extension, tokens) in by_extension {
+            let extension = if extension.is_empty() {
+                "(none)".to_string()
+            } else {
+                format!(".{}", extension)
+            };
+            println!("- {}: {} tokens", extension, tokens);
+        }
+    }
+    if let Some(max_length) = options.max_length {
+        let at_limit = stats_vec
+            .iter()
+            .filter(|(_, stats)| stats.token_count >= max_length)
+            .count();
+        println!(
+            "{} files reach the {}-token limit, so they may not fit",
+            at_limit, max_length
+        );
+    }
+
+    if dedup {
+        // Read the matched files again so the aggregate covers exactly the same set. The
+        // first copy of a repeated line is the one kept, so read them in path order
+        let mut matched: Vec<_> = file_stats.values().collect();
+        matched.sort_by(|a, b| a.path.cmp(&b.path));
+        let mut contents = Vec::with_capacity(matched.len());
+        for stats in matched {
+            // Relative paths are below the directory, and joining keeps absolute ones
+            let path = dir_path.join(&stats.path);
+            let content = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read file: {}", path.display()))?;
+            contents.push(content);
+        }
+
+        let unique_tokens = aggregate_unique_tokens(&contents)?;
+        println!(
+            "Deduplicated total (estimate): {} tokens ({} tokens of repeated content)",
+            unique_tokens,
+            total_tokens.saturating_sub(unique_tokens)
+        );
+    }
+
+    Ok(total_tokens)
 }
 
 fn print_token_stats(
@@ -309,28 +1713,459 @@ fn print_token_stats(
     (total_tokens, total_size)
 }
 
-pub fn list_command(project_path: &Path, detailed: bool) -> Result<()> {
-    // Find all Cargo.toml files
-    let cargo_toml_files = find_cargo_toml_files(project_path)?;
-    println!("Found {} Cargo.toml files", cargo_toml_files.len());
+/// A dependency as reported by `list --format json`
+#[derive(Serialize)]
+struct ListedDependency {
+    name: String,
+    /// Version resolved from Cargo.lock, only reported with `--detailed`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+    /// Whether the resolved version has been yanked, only reported with `--check-yanked`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    yanked: Option<bool>,
+    /// The manifests declaring the dependency
+    declared_in: Vec<PathBuf>,
+}
 
+/// Lists the dependencies of a project.
+///
+/// The `(min_version, max_version)` range restricts the listing to dependencies whose
+/// version resolved from Cargo.lock lies in `[min_version, max_version]`.
+pub fn list_command(
+    project_path: &Path,
+    detailed: bool,
+    lock: Option<&Path>,
+    check_yanked: bool,
+    (min_version, max_version): VersionRange,
+    discovery: &DiscoveryOptions,
+    format: ListFormat,
+) -> Result<CommandOutcome> {
+    // Find all Cargo.toml files
+    let cargo_toml_files = find_cargo_toml_files(project_path, discovery)?;
     if cargo_toml_files.is_empty() {
         return Err(anyhow::anyhow!("No Cargo.toml files found"));
     }
 
     // Collect all dependencies
     let dependencies = collect_dependencies(&cargo_toml_files)?;
+    if dependencies.is_empty() {
+        match format {
+            ListFormat::Json => println!("[]"),
+            ListFormat::Jsonl => {}
+            ListFormat::Csv => write_dependency_csv(&[], &mut io::stdout().lock())?,
+            ListFormat::Text => report_no_dependencies(cargo_toml_files.len()),
+        }
+        return Ok(CommandOutcome::NoDependencies);
+    }
+
+    let version_range =
+        (min_version.is_some() || max_version.is_some()).then_some((min_version, max_version));
+
+    if format == ListFormat::Csv {
+        let rows = dependency_csv_rows(project_path, &cargo_toml_files, lock, version_range)?;
+        write_dependency_csv(&rows, &mut io::stdout().lock())?;
+        return Ok(CommandOutcome::Completed);
+    }
+
+    if matches!(format, ListFormat::Json | ListFormat::Jsonl) {
+        let listed = list_dependencies_with_provenance(
+            project_path,
+            &cargo_toml_files,
+            detailed,
+            lock,
+            check_yanked,
+            version_range,
+        )?;
+        if format == ListFormat::Jsonl {
+            write_json_lines(listed, &mut io::stdout().lock())?;
+        } else {
+            println!("{}", serde_json::to_string_pretty(&listed)?);
+        }
+        return Ok(CommandOutcome::Completed);
+    }
+
+    println!("Found {} Cargo.toml files", cargo_toml_files.len());
     println!("\nFound {} unique dependencies:", dependencies.len());
 
     if detailed {
-        display_detailed_dependency_info(project_path, &cargo_toml_files)?;
+        display_detailed_dependency_info(
+            project_path,
+            &cargo_toml_files,
+            lock,
+            check_yanked,
+            version_range,
+        )?;
+    } else if let Some((min_version, max_version)) = version_range {
+        let resolved =
+            resolve_project_versions(&cargo_toml_files, &read_cargo_lock(project_path, lock)?)?
+                .filter_version_range(min_version, max_version);
+        display_versioned_dependency_list(&resolved);
     } else {
         display_simple_dependency_list(&dependencies);
     }
 
+    // Both versions may be mistaken for the one the project uses
+    if let Ok(cargo_lock) = read_cargo_lock(project_path, lock) {
+        let mut names: Vec<&str> = dependencies.iter().map(String::as_str).collect();
+        names.sort_unstable();
+        warn_major_version_conflicts(&cargo_lock, names);
+    }
+
+    Ok(CommandOutcome::Completed)
+}
+
+/// Lists the dependencies declared in a project's manifests, in sections by where
+/// they come from according to Cargo.lock
+pub fn list_by_source_command(
+    project_path: &Path,
+    lock: Option<&Path>,
+    (min_version, max_version): VersionRange,
+    discovery: &DiscoveryOptions,
+) -> Result<CommandOutcome> {
+    let cargo_toml_files = find_cargo_toml_files(project_path, discovery)?;
+    if cargo_toml_files.is_empty() {
+        return Err(anyhow::anyhow!("No Cargo.toml files found"));
+    }
+
+    let mut resolved =
+        resolve_project_versions(&cargo_toml_files, &read_cargo_lock(project_path, lock)?)?;
+    if min_version.is_some() || max_version.is_some() {
+        resolved = resolved.filter_version_range(min_version, max_version);
+    }
+    if resolved.is_empty() {
+        report_no_dependencies(cargo_toml_files.len());
+        return Ok(CommandOutcome::NoDependencies);
+    }
+
+    let groups = resolved.group_by_source();
+    println!("Found {} Cargo.toml files", cargo_toml_files.len());
+    for (source, dependencies) in groups {
+        let mut dependencies = dependencies;
+        dependencies.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.version.cmp(&b.version)));
+        // The same dependency is resolved once per manifest declaring it
+        dependencies.dedup();
+
+        println!(
+            "\n{}",
+            heading(&format!("{} ({}):", source.label(), dependencies.len()))
+        );
+        for dep in dependencies {
+            println!("- {} {}", dep.name, dep.version);
+        }
+    }
+
+    Ok(CommandOutcome::Completed)
+}
+
+/// Prints the dependency tree of a project from its Cargo.lock.
+///
+/// The tree is rooted at the dependencies declared in the project's manifests, with
+/// their versions resolved from Cargo.lock, in name order.
+pub fn list_tree_command(
+    project_path: &Path,
+    lock: Option<&Path>,
+    discovery: &DiscoveryOptions,
+) -> Result<CommandOutcome> {
+    let cargo_toml_files = find_cargo_toml_files(project_path, discovery)?;
+    if cargo_toml_files.is_empty() {
+        return Err(anyhow::anyhow!("No Cargo.toml files found"));
+    }
+
+    let cargo_lock = read_cargo_lock(project_path, lock)?;
+    let resolved = resolve_project_versions(&cargo_toml_files, &cargo_lock)?;
+    if resolved.is_empty() {
+        report_no_dependencies(cargo_toml_files.len());
+        return Ok(CommandOutcome::NoDependencies);
+    }
+
+    // The same dependency is resolved once per manifest declaring it
+    let mut roots: Vec<&Dependency> = resolved.iter().collect();
+    roots.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.version.cmp(&b.version)));
+    roots.dedup_by(|a, b| a.name == b.name && a.version == b.version);
+    let mut root_collection = DependencyCollection::new();
+    for root in roots {
+        root_collection.add(root.clone());
+    }
+
+    let graph = build_dependency_graph_from_lock(&cargo_lock);
+    print!("{}", render_dependency_tree(&graph, &root_collection));
+    Ok(CommandOutcome::Completed)
+}
+
+/// Lists the packages locked in a project's Cargo.lock, without reading its manifests.
+///
+/// This includes the transitive dependencies and the project's own packages. The
+/// `(min_version, max_version)` range works as in [`list_command`].
+pub fn list_locked_command(
+    project_path: &Path,
+    lock: Option<&Path>,
+    (min_version, max_version): VersionRange,
+    format: ListFormat,
+) -> Result<CommandOutcome> {
+    let mut packages = read_cargo_lock(project_path, lock)?.packages();
+    if min_version.is_some() || max_version.is_some() {
+        packages = packages.filter_version_range(min_version, max_version);
+    }
+    let mut sorted: Vec<&Dependency> = packages.iter().collect();
+    sorted.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.version.cmp(&b.version)));
+
+    match format {
+        ListFormat::Json => println!("{}", serde_json::to_string_pretty(&sorted)?),
+        ListFormat::Jsonl => write_json_lines(sorted, &mut io::stdout().lock())?,
+        ListFormat::Csv => {
+            let registry_path = resolve_registry_path().ok();
+            let mut manifests = ManifestCache::new();
+            let rows: Vec<DependencyCsvRow> = sorted
+                .iter()
+                .map(|dep| {
+                    let (available, license) =
+                        registry_metadata(dep, registry_path.as_deref(), &mut manifests);
+                    DependencyCsvRow {
+                        name: dep.name.clone(),
+                        version: dep.version.clone(),
+                        resolved_version: Some(dep.version.clone()),
+                        available,
+                        kind: String::new(),
+                        license,
+                    }
+                })
+                .collect();
+            write_dependency_csv(&rows, &mut io::stdout().lock())?;
+        }
+        ListFormat::Text => {
+            println!("Found {} locked packages:", sorted.len());
+            for dep in &sorted {
+                println!("- {} {}", dep.name, dep.version);
+            }
+        }
+    }
+
+    Ok(CommandOutcome::Completed)
+}
+
+/// Builds the CSV rows of the dependencies declared by a project.
+///
+/// Without a Cargo.lock, the resolved version, availability and license cells are
+/// left empty rather than failing.
+fn dependency_csv_rows(
+    project_path: &Path,
+    cargo_toml_files: &[PathBuf],
+    lock: Option<&Path>,
+    version_range: Option<VersionRange>,
+) -> Result<Vec<DependencyCsvRow>> {
+    let kinds = collect_dependency_kinds(cargo_toml_files)?;
+
+    // The first declared requirement of each dependency
+    let mut requirements: HashMap<String, String> = HashMap::new();
+    for cargo_toml in cargo_toml_files {
+        for dep in extract_dependency_info(cargo_toml)?.iter() {
+            requirements
+                .entry(dep.name.clone())
+                .or_insert_with(|| dep.version.clone());
+        }
+    }
+
+    let resolved = if lock.is_some() || find_cargo_lock(project_path).is_ok() {
+        resolve_project_versions(cargo_toml_files, &read_cargo_lock(project_path, lock)?)?
+    } else {
+        DependencyCollection::new()
+    };
+    let resolved = match version_range {
+        Some((min_version, max_version)) => resolved.filter_version_range(min_version, max_version),
+        None => resolved,
+    };
+
+    let registry_path = resolve_registry_path().ok();
+    let mut manifests = ManifestCache::new();
+    Ok(kinds
+        .into_iter()
+        .filter(|(name, _)| version_range.is_none() || resolved.contains_name(name))
+        .map(|(name, kinds)| {
+            let resolved_dep = resolved.get(&name);
+            let (available, license) = match resolved_dep {
+                Some(dep) => registry_metadata(dep, registry_path.as_deref(), &mut manifests),
+                None => (None, None),
+            };
+            DependencyCsvRow {
+                version: requirements.get(&name).cloned().unwrap_or_default(),
+                resolved_version: resolved_dep.map(|dep| dep.version.clone()),
+                available,
+                kind: kinds
+                    .iter()
+                    .map(DependencyKind::as_str)
+                    .collect::<Vec<_>>()
+                    .join(";"),
+                license,
+                name,
+            }
+        })
+        .collect())
+}
+
+/// Looks up whether a resolved dependency is in the local cargo registry and, if
+/// so, its license
+fn registry_metadata(
+    dep: &Dependency,
+    registry_path: Option<&Path>,
+    manifests: &mut ManifestCache,
+) -> (Option<bool>, Option<String>) {
+    let Some(registry_path) = registry_path else {
+        return (None, None);
+    };
+    if !is_dependency_available(registry_path, dep) {
+        return (Some(false), None);
+    }
+
+    let license = manifests
+        .get(registry_path, dep)
+        .license()
+        .map(str::to_string);
+    (Some(true), license)
+}
+
+/// Prints the metadata of a project's resolved dependencies as a JSON array sorted by
+/// name, or writes it to `output` if given.
+///
+/// Dependencies that are not in the local registry are listed with only what
+/// Cargo.lock records, see [`dependency_metadata`].
+pub fn meta_command(
+    project_path: &Path,
+    lock: Option<&Path>,
+    transitive: Option<TransitiveOptions>,
+    discovery: &DiscoveryOptions,
+    output: Option<&Path>,
+) -> Result<CommandOutcome> {
+    let resolved = resolve_project(
+        project_path,
+        &ProjectResolution::listing(lock, discovery, transitive),
+    )?
+    .unwrap_or_default()
+    .dependencies;
+
+    // The same dependency is resolved once per manifest declaring it
+    let mut dependencies: Vec<&Dependency> = resolved.iter().collect();
+    dependencies.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.version.cmp(&b.version)));
+    dependencies.dedup_by(|a, b| a.name == b.name && a.version == b.version);
+
+    let registry_path = resolve_registry_path()?;
+    let mut manifests = ManifestCache::new();
+    let metadata: Vec<DependencyMetadata> = dependencies
+        .into_iter()
+        .map(|dependency| dependency_metadata(dependency, &registry_path, &mut manifests))
+        .collect();
+
+    let content = serde_json::to_string_pretty(&metadata)?;
+    match output {
+        Some(path) => {
+            fs::write(path, content + "\n")
+                .with_context(|| format!("Failed to write metadata: {}", path.display()))?;
+            println!(
+                "Wrote the metadata of {} dependencies to {}",
+                metadata.len(),
+                path.display()
+            );
+        }
+        None => println!("{}", content),
+    }
+
+    Ok(CommandOutcome::Completed)
+}
+
+/// Lower and upper bound of the versions to list, see [`list_command`]
+pub type VersionRange<'a> = (Option<&'a semver::Version>, Option<&'a semver::Version>);
+
+/// Lists every dependency with the manifests declaring it, sorted by name.
+///
+/// With `detailed`, versions are resolved from the project's Cargo.lock for the
+/// dependencies of all manifests. With a version range, only the dependencies
+/// resolved to a version in the range are listed, with their versions.
+fn list_dependencies_with_provenance(
+    project_path: &Path,
+    cargo_toml_files: &[PathBuf],
+    detailed: bool,
+    lock: Option<&Path>,
+    check_yanked: bool,
+    version_range: Option<VersionRange>,
+) -> Result<Vec<ListedDependency>> {
+    let provenance = collect_dependency_provenance(cargo_toml_files)?;
+
+    let resolved = if detailed || version_range.is_some() {
+        resolve_project_versions(cargo_toml_files, &read_cargo_lock(project_path, lock)?)?
+    } else {
+        DependencyCollection::new()
+    };
+    let resolved = match version_range {
+        Some((min_version, max_version)) => resolved.filter_version_range(min_version, max_version),
+        None => resolved,
+    };
+
+    let yanked = if detailed && check_yanked {
+        Some(find_yanked_dependencies(&resolved, false))
+    } else {
+        None
+    };
+
+    Ok(provenance
+        .into_iter()
+        .filter(|(name, _)| version_range.is_none() || resolved.contains_name(name))
+        .map(|(name, declared_in)| {
+            let version = resolved.get_version(&name).cloned();
+            ListedDependency {
+                yanked: yanked
+                    .as_ref()
+                    .filter(|_| version.is_some())
+                    .map(|yanked| yanked.contains(&name)),
+                version,
+                name,
+                declared_in,
+            }
+        })
+        .collect())
+}
+
+/// Writes items as JSON Lines: one compact JSON object per line, flushed after each
+/// so that streaming consumers can process them as they are produced
+fn write_json_lines<T: Serialize>(
+    items: impl IntoIterator<Item = T>,
+    writer: &mut impl Write,
+) -> Result<()> {
+    for item in items {
+        serde_json::to_writer(&mut *writer, &item)?;
+        writeln!(writer)?;
+        writer.flush()?;
+    }
     Ok(())
 }
 
+/// Resolves the dependencies of all manifests to their versions in Cargo.lock
+fn resolve_project_versions(
+    cargo_toml_files: &[PathBuf],
+    cargo_lock: &ParsedLock,
+) -> Result<DependencyCollection> {
+    let mut dependency_info = DependencyCollection::new();
+    for cargo_toml in cargo_toml_files {
+        for dep in extract_dependency_info(cargo_toml)?.iter() {
+            dependency_info.add(dep.clone());
+        }
+    }
+    Ok(resolve_dependency_versions_from_lock(
+        cargo_lock,
+        &dependency_info,
+    ))
+}
+
+fn display_versioned_dependency_list(dependencies: &DependencyCollection) {
+    let mut sorted_deps: Vec<_> = dependencies.iter().collect();
+    sorted_deps.sort_by(|a, b| a.name.cmp(&b.name));
+    // The same dependency is resolved once per manifest declaring it
+    sorted_deps.dedup();
+
+    println!("\n{} dependencies in the version range:", sorted_deps.len());
+    for dep in sorted_deps {
+        println!("- {} {}", dep.name, dep.version);
+    }
+}
+
 fn display_simple_dependency_list(dependencies: &HashSet<String>) {
     // Sort dependencies for consistent output
     let mut sorted_deps: Vec<_> = dependencies.iter().collect();
@@ -345,12 +2180,21 @@ fn display_simple_dependency_list(dependencies: &HashSet<String>) {
 fn display_detailed_dependency_info(
     project_path: &Path,
     cargo_toml_files: &[PathBuf],
+    lock: Option<&Path>,
+    check_yanked: bool,
+    version_range: Option<VersionRange>,
 ) -> Result<()> {
     // For detailed view, show dependency info from each Cargo.toml
     display_dependency_specs_by_file(cargo_toml_files)?;
 
     // Try to resolve versions from Cargo.lock if available
-    display_cargo_lock_versions(project_path, cargo_toml_files)?;
+    display_cargo_lock_versions(
+        project_path,
+        cargo_toml_files,
+        lock,
+        check_yanked,
+        version_range,
+    )?;
 
     Ok(())
 }
@@ -378,25 +2222,64 @@ fn display_dependency_specs_by_file(cargo_toml_files: &[PathBuf]) -> Result<()>
     Ok(())
 }
 
-fn display_cargo_lock_versions(project_path: &Path, cargo_toml_files: &[PathBuf]) -> Result<()> {
-    if let Ok(cargo_lock_path) = find_cargo_lock(project_path) {
-        println!("\nFound Cargo.lock at: {}", cargo_lock_path.display());
+fn display_cargo_lock_versions(
+    project_path: &Path,
+    cargo_toml_files: &[PathBuf],
+    lock: Option<&Path>,
+    check_yanked: bool,
+    version_range: Option<VersionRange>,
+) -> Result<()> {
+    // An explicitly provided lockfile must be valid, a discovered one is optional
+    let cargo_lock = match lock {
+        Some(lock_path) => {
+            let parsed = read_cargo_lock(project_path, Some(lock_path))?;
+            if is_stdin_lock(lock_path) {
+                println!("\nRead Cargo.lock from stdin");
+            } else {
+                println!("\nFound Cargo.lock at: {}", lock_path.display());
+            }
+            Some(parsed)
+        }
+        None => match find_cargo_lock(project_path) {
+            Ok(cargo_lock_path) => {
+                let parsed = read_cargo_lock(project_path, Some(&cargo_lock_path))?;
+                println!("\nFound Cargo.lock at: {}", cargo_lock_path.display());
+                Some(parsed)
+            }
+            Err(_) => None,
+        },
+    };
 
+    if let Some(cargo_lock) = cargo_lock {
         // Extract dependencies from the first Cargo.toml for resolution
         let first_cargo_toml = &cargo_toml_files[0];
         let dependency_info = extract_dependency_info(first_cargo_toml)?;
 
         // Resolve exact versions from Cargo.lock
-        if let Ok(resolved_versions) =
-            resolve_dependency_versions(cargo_lock_path, &dependency_info)
-        {
-            println!("\nResolved dependency versions from Cargo.lock:");
+        let resolved_versions =
+            resolve_dependency_versions_from_lock(&cargo_lock, &dependency_info);
+        let resolved_versions = match version_range {
+            Some((min_version, max_version)) => {
+                resolved_versions.filter_version_range(min_version, max_version)
+            }
+            None => resolved_versions,
+        };
+        println!("\nResolved dependency versions from Cargo.lock:");
 
-            // Sort dependencies for consistent output
-            let mut sorted_resolved: Vec<_> = resolved_versions.iter().collect();
-            sorted_resolved.sort_by(|a, b| a.version.cmp(&b.version));
+        let yanked = if check_yanked {
+            find_yanked_dependencies(&resolved_versions, false)
+        } else {
+            BTreeSet::new()
+        };
+
+        // Sort dependencies for consistent output
+        let mut sorted_resolved: Vec<_> = resolved_versions.iter().collect();
+        sorted_resolved.sort_by(|a, b| a.version.cmp(&b.version));
 
-            for dep in sorted_resolved {
+        for dep in sorted_resolved {
+            if yanked.contains(&dep.name) {
+                println!("{}: {} (yanked)", dep.name, dep.version);
+            } else {
                 println!("{}: {}", dep.name, dep.version);
             }
         }
@@ -404,3 +2287,414 @@ fn display_cargo_lock_versions(project_path: &Path, cargo_toml_files: &[PathBuf]
 
     Ok(())
 }
+
+/// Reports the crates extracted in the local registry that a project does not use.
+///
+/// Every package of the project's lockfile counts as used, including transitive
+/// dependencies. This is a dry run only, the registry is never modified.
+pub fn registry_prune_command(
+    project_path: &Path,
+    lock: Option<&Path>,
+    format: OutputFormat,
+) -> Result<()> {
+    let used = read_cargo_lock(project_path, lock)?.packages();
+    let registry_paths = registry_source_dirs()?;
+    let unused = unused_registry_crates(&used, &registry_paths)?;
+
+    if format == OutputFormat::Json {
+        println!("{}", serde_json::to_string_pretty(&unused)?);
+        return Ok(());
+    }
+
+    println!(
+        "Found {} extracted crates not used by the project (dry run, nothing was deleted):",
+        unused.len()
+    );
+    for krate in &unused {
+        println!("- {} {}", krate.name, krate.version);
+    }
+
+    Ok(())
+}
+
+/// Checks that the dependencies declared in a project agree with its Cargo.lock.
+///
+/// Fails if any declared dependency is missing from the lockfile or has a version
+/// requirement no locked version satisfies.
+pub fn check_command(
+    project_path: &Path,
+    lock: Option<&Path>,
+    discovery: &DiscoveryOptions,
+    format: OutputFormat,
+) -> Result<CommandOutcome> {
+    let cargo_toml_files = find_cargo_toml_files(project_path, discovery)?;
+    if cargo_toml_files.is_empty() {
+        return Err(anyhow::anyhow!("No Cargo.toml files found"));
+    }
+
+    let mut declared = DependencyCollection::new();
+    for cargo_toml_path in &cargo_toml_files {
+        for dep in extract_dependency_info(cargo_toml_path)?.iter() {
+            declared.add(dep.clone());
+        }
+    }
+    if declared.is_empty() {
+        report_no_dependencies(cargo_toml_files.len());
+        return Ok(CommandOutcome::NoDependencies);
+    }
+
+    let inconsistencies =
+        validate_against_parsed_lock(&declared, &read_cargo_lock(project_path, lock)?);
+
+    if format == OutputFormat::Json {
+        println!("{}", serde_json::to_string_pretty(&inconsistencies)?);
+    } else if inconsistencies.is_empty() {
+        println!("All declared dependencies are consistent with Cargo.lock");
+    } else {
+        println!(
+            "Found {} inconsistencies with Cargo.lock:",
+            inconsistencies.len()
+        );
+        for inconsistency in &inconsistencies {
+            println!("- {}", inconsistency);
+        }
+    }
+
+    if !inconsistencies.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Cargo.lock is inconsistent with the declared dependencies, run `cargo update` to refresh it"
+        ));
+    }
+    Ok(CommandOutcome::Completed)
+}
+
+/// Totals of a strategy comparison, as printed in JSON
+#[derive(Serialize)]
+struct StrategyComparisonReport<'a> {
+    baseline: String,
+    candidate: String,
+    dependencies: &'a [StrategyComparison],
+    baseline_tokens: usize,
+    candidate_tokens: usize,
+    difference: i64,
+}
+
+/// Compares the tokens of a project's code banks under two strategies.
+///
+/// The banks are generated in memory only; nothing is written to disk.
+pub fn compare_strategies_command(
+    project_path: &Path,
+    lock: Option<&Path>,
+    (baseline, candidate): (Strategy, Strategy),
+    discovery: &DiscoveryOptions,
+    format: OutputFormat,
+) -> Result<CommandOutcome> {
+    let cargo_toml_files = find_cargo_toml_files(project_path, discovery)?;
+    if cargo_toml_files.is_empty() {
+        return Err(anyhow::anyhow!("No Cargo.toml files found"));
+    }
+
+    let mut dependencies = DependencyCollection::new();
+    for dep in
+        resolve_project_versions(&cargo_toml_files, &read_cargo_lock(project_path, lock)?)?.iter()
+    {
+        // The same dependency is resolved once per manifest declaring it
+        if !dependencies.contains_name(&dep.name) {
+            dependencies.add(dep.clone());
+        }
+    }
+    if dependencies.is_empty() {
+        report_no_dependencies(cargo_toml_files.len());
+        return Ok(CommandOutcome::NoDependencies);
+    }
+
+    let registry_path = resolve_registry_path()?;
+    let options = |strategy: Strategy| GenerateOptions {
+        strategy: strategy.into(),
+        ..Default::default()
+    };
+    let mut comparisons = compare_strategies(
+        &dependencies,
+        &registry_path,
+        &options(baseline),
+        &options(candidate),
+        &TokenOptions::default(),
+    )?;
+    comparisons.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let baseline_name = strategy_name(baseline);
+    let candidate_name = strategy_name(candidate);
+    let baseline_tokens: usize = comparisons.iter().map(|c| c.baseline_tokens).sum();
+    let candidate_tokens: usize = comparisons.iter().map(|c| c.candidate_tokens).sum();
+    let difference = candidate_tokens as i64 - baseline_tokens as i64;
+
+    if format == OutputFormat::Json {
+        let report = StrategyComparisonReport {
+            baseline: baseline_name,
+            candidate: candidate_name,
+            dependencies: &comparisons,
+            baseline_tokens,
+            candidate_tokens,
+            difference,
+        };
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(CommandOutcome::Completed);
+    }
+
+    let labels: Vec<String> = comparisons
+        .iter()
+        .map(|c| format!("{} {}", c.name, c.version))
+        .collect();
+    let width = labels
+        .iter()
+        .map(String::len)
+        .chain(["Dependency".len(), "Total".len()])
+        .max()
+        .unwrap_or_default();
+    println!(
+        "{:<width$}  {:>12}  {:>12}  {:>12}",
+        "Dependency", baseline_name, candidate_name, "difference"
+    );
+    for (label, comparison) in labels.iter().zip(&comparisons) {
+        println!(
+            "{:<width$}  {:>12}  {:>12}  {:>+12}",
+            label,
+            comparison.baseline_tokens,
+            comparison.candidate_tokens,
+            comparison.difference()
+        );
+    }
+    println!(
+        "{:<width$}  {:>12}  {:>12}  {:>+12}",
+        "Total", baseline_tokens, candidate_tokens, difference
+    );
+
+    Ok(CommandOutcome::Completed)
+}
+
+/// The name of a strategy as given on the command line
+fn strategy_name(strategy: Strategy) -> String {
+    strategy
+        .to_possible_value()
+        .map(|value| value.get_name().to_string())
+        .unwrap_or_default()
+}
+
+pub fn deps_diff_command(
+    project_path: &Path,
+    other_path: Option<&Path>,
+    against: Option<&str>,
+    format: OutputFormat,
+) -> Result<()> {
+    let diff = match (other_path, against) {
+        (Some(other_path), _) => {
+            let old = resolve_declared_versions(project_path)?;
+            let new = resolve_declared_versions(other_path)?;
+            diff_dependency_collections(&old, &new)
+        }
+        (None, Some(rev)) => {
+            // Materialize the manifests at the given revision to resolve them like a project
+            let checkout = checkout_manifests_at(project_path, rev)?;
+            let old = resolve_declared_versions(checkout.path())?;
+            let new = resolve_declared_versions(project_path)?;
+            diff_dependency_collections(&old, &new)
+        }
+        (None, None) => {
+            return Err(anyhow::anyhow!(
+                "Either a second project path or --against <REF> is required"
+            ));
+        }
+    };
+
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&diff)?),
+        OutputFormat::Text => display_dep_diff(&diff),
+    }
+
+    Ok(())
+}
+
+/// Resolves the exact versions of all dependencies declared in a project, one per name
+fn resolve_declared_versions(project_path: &Path) -> Result<DependencyCollection> {
+    let cargo_toml_files = find_cargo_toml_files(project_path, &DiscoveryOptions::default())?;
+    if cargo_toml_files.is_empty() {
+        return Err(anyhow::anyhow!(
+            "No Cargo.toml files found in {}",
+            project_path.display()
+        ));
+    }
+
+    let mut dependency_info = DependencyCollection::new();
+    for cargo_toml_path in &cargo_toml_files {
+        for dep in extract_dependency_info(cargo_toml_path)?.iter() {
+            dependency_info.add(dep.clone());
+        }
+    }
+
+    let cargo_lock_path = find_cargo_lock(project_path)?;
+    let resolved_versions = resolve_dependency_versions(cargo_lock_path, &dependency_info)?;
+
+    // The same dependency may be declared by several manifests
+    let mut unique = DependencyCollection::new();
+    for dep in resolved_versions.iter() {
+        if !unique.contains_name(&dep.name) {
+            unique.add(dep.clone());
+        }
+    }
+
+    Ok(unique)
+}
+
+/// Writes the Cargo.toml and Cargo.lock files of a project at a git revision into a temp dir
+fn checkout_manifests_at(project_path: &Path, rev: &str) -> Result<TempDir> {
+    let checkout = TempDir::new().context("Failed to create temporary directory")?;
+
+    // Paths are listed relative to the project directory inside the repository
+    let listing = run_git(project_path, &["ls-tree", "-r", "--name-only", rev])?;
+    let manifests: Vec<&str> = listing
+        .lines()
+        .filter(|path| path.ends_with("Cargo.toml") || path.ends_with("Cargo.lock"))
+        .collect();
+
+    if !manifests.iter().any(|path| path.ends_with("Cargo.toml")) {
+        return Err(anyhow::anyhow!(
+            "No Cargo.toml files found in {} at revision {}",
+            project_path.display(),
+            rev
+        ));
+    }
+
+    for manifest in &manifests {
+        let content = run_git(project_path, &["show", &format!("{}:./{}", rev, manifest)])?;
+        let target = checkout.path().join(manifest);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&target, content)?;
+    }
+
+    // Workspace members keep their lockfile at the repository root
+    if !manifests.contains(&"Cargo.lock")
+        && let Ok(content) = run_git(project_path, &["show", &format!("{}:Cargo.lock", rev)])
+    {
+        fs::write(checkout.path().join("Cargo.lock"), content)?;
+    }
+
+    Ok(checkout)
+}
+
+fn run_git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .context("Failed to run git")?;
+
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+fn display_dep_diff(diff: &DepDiff) {
+    if diff.is_empty() {
+        println!("No dependency changes");
+        return;
+    }
+
+    println!("Added ({}):", diff.added.len());
+    for dep in &diff.added {
+        println!("+ {} {}", dep.name, dep.version);
+    }
+
+    println!("\nRemoved ({}):", diff.removed.len());
+    for dep in &diff.removed {
+        println!("- {} {}", dep.name, dep.version);
+    }
+
+    println!("\nChanged ({}):", diff.changed.len());
+    for change in &diff.changed {
+        println!(
+            "~ {} {} -> {}",
+            change.name, change.old_version, change.new_version
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{Cli, Commands};
+    use clap::Parser;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_commands_report_empty_projects() -> Result<()> {
+        let project_path = Path::new("fixtures/empty_project");
+        let outcome = list_command(
+            project_path,
+            true,
+            None,
+            false,
+            (None, None),
+            &DiscoveryOptions::default(),
+            ListFormat::Text,
+        )?;
+        assert_eq!(outcome, CommandOutcome::NoDependencies);
+
+        let temp_dir = tempdir()?;
+        let output_dir = temp_dir.path().join("banks");
+        let cli = Cli::try_parse_from([
+            "depbank".as_ref(),
+            "generate".as_ref(),
+            "-p".as_ref(),
+            project_path.as_os_str(),
+            "-o".as_ref(),
+            output_dir.as_os_str(),
+        ])?;
+        let Commands::Generate(args) = cli.command else {
+            unreachable!()
+        };
+        assert_eq!(generate_command(&args)?, CommandOutcome::NoDependencies);
+        assert!(!output_dir.exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bank_options_include_usages() -> Result<()> {
+        let project_path = PathBuf::from("fixtures/simple_project");
+        let cli = Cli::try_parse_from([
+            "depbank".as_ref(),
+            "generate".as_ref(),
+            "-p".as_ref(),
+            project_path.as_os_str(),
+            "--with-usage".as_ref(),
+        ])?;
+        let Commands::Generate(args) = cli.command else {
+            unreachable!()
+        };
+        let mut dependencies = DependencyCollection::new();
+        dependencies.add(Dependency::new("serde", "1.0.197"));
+        dependencies.add(Dependency::new("tokio-test", "0.4.3"));
+        let used_by = dependencies
+            .iter()
+            .map(|dep| (dep.name.clone(), vec![project_path.clone()]))
+            .collect();
+        let depths = DependencyDepths::from([("serde".to_string(), 0)]);
+
+        // Budget estimates count the usage sections and notes the banks will hold
+        let options = bank_options(&args, &dependencies, &used_by, &depths, true)?;
+        assert_eq!(options.dependency_depths, depths);
+        assert_eq!(options.usages.keys().collect::<Vec<_>>(), vec!["serde"]);
+        assert!(options.yanked.is_empty());
+
+        Ok(())
+    }
+}
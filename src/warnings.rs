@@ -0,0 +1,159 @@
+//! Emission of the warnings reported while analyzing and generating.
+//!
+//! Warnings go to stderr, either as `Warning: ...` lines for people or, for CI
+//! systems parsing the output, as one JSON object per line. The format is set once
+//! at startup with [`set_warnings_format`] and applies to every warning emitted
+//! through [`emit_warning`].
+
+use crate::{ColorStyle, OutputStream, is_colored, paint};
+use serde::Serialize;
+use std::sync::RwLock;
+
+static WARNINGS_FORMAT: RwLock<WarningsFormat> = RwLock::new(WarningsFormat::Text);
+
+/// How warnings are written to stderr
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WarningsFormat {
+    /// `Warning: <message>` lines
+    #[default]
+    Text,
+    /// One JSON object per line, with `kind`, `dependency` and `message` fields
+    Json,
+}
+
+/// What a warning is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WarningKind {
+    /// A dependency is not available in the local cargo registry
+    MissingDependency,
+    /// The code bank of a dependency could not be generated
+    GenerationFailed,
+    /// The source of a `[patch]` entry was not found locally
+    PatchNotFound,
+    /// Workspace members, or projects combined into one output, use different versions
+    /// of a dependency
+    VersionConflict,
+    /// A crate is locked at several semver-incompatible versions
+    MajorVersionConflict,
+    /// A dependency's version is not valid semver
+    InvalidVersion,
+    /// A dependency declared without a version requirement is not in Cargo.lock
+    UnresolvableVersion,
+    /// No package of a declared dependency's name is in Cargo.lock
+    NotInLockfile,
+    /// Cargo.lock has packages of a declared dependency's name, but none of a version
+    /// its requirement allows
+    NoMatchingLockedVersion,
+    /// The sources found locally for a dependency are of another version than the
+    /// one locked in Cargo.lock
+    VersionMismatch,
+    /// The run's deadline passed before some code banks were generated
+    DeadlineExceeded,
+    /// The progress of a run could not be recorded for resuming
+    ProgressLog,
+    /// The token counts of a directory's files could not be cached
+    TokenCache,
+}
+
+/// A warning about a dependency or the run as a whole
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Warning {
+    /// What the warning is about
+    pub kind: WarningKind,
+    /// The dependency concerned, if any
+    pub dependency: Option<String>,
+    /// The human-readable message
+    pub message: String,
+}
+
+impl Warning {
+    /// Creates a warning
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - What the warning is about
+    /// * `dependency` - The name of the dependency concerned, if any
+    /// * `message` - The human-readable message
+    pub fn new(kind: WarningKind, dependency: Option<&str>, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            dependency: dependency.map(str::to_string),
+            message: message.into(),
+        }
+    }
+
+    /// Renders the warning as a single line in the given format
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use depbank::{Warning, WarningKind, WarningsFormat};
+    ///
+    /// let warning = Warning::new(WarningKind::PatchNotFound, Some("serde"), "Not found");
+    /// assert_eq!(warning.render(WarningsFormat::Text), "Warning: Not found");
+    /// ```
+    pub fn render(&self, format: WarningsFormat) -> String {
+        match format {
+            WarningsFormat::Text => format!("Warning: {}", self.message),
+            // Serializing plain strings and enums cannot fail
+            WarningsFormat::Json => serde_json::to_string(self).unwrap_or_default(),
+        }
+    }
+}
+
+/// Sets the format of all warnings emitted afterwards
+///
+/// # Examples
+///
+/// ```rust
+/// use depbank::{WarningsFormat, set_warnings_format};
+///
+/// set_warnings_format(WarningsFormat::Json);
+/// ```
+pub fn set_warnings_format(format: WarningsFormat) {
+    *WARNINGS_FORMAT.write().unwrap_or_else(|e| e.into_inner()) = format;
+}
+
+/// Writes a warning to stderr in the configured format
+pub fn emit_warning(warning: Warning) {
+    let format = *WARNINGS_FORMAT.read().unwrap_or_else(|e| e.into_inner());
+    match format {
+        WarningsFormat::Text if is_colored(OutputStream::Stderr) => eprintln!(
+            "{} {}",
+            paint("Warning:", ColorStyle::Warning, OutputStream::Stderr),
+            warning.message
+        ),
+        _ => eprintln!("{}", warning.render(format)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_missing_dependency_warning() {
+        let warning = Warning::new(
+            WarningKind::MissingDependency,
+            Some("serde"),
+            "serde 1.0.219 is not available in the local cargo registry",
+        );
+
+        assert_eq!(
+            warning.render(WarningsFormat::Text),
+            "Warning: serde 1.0.219 is not available in the local cargo registry"
+        );
+
+        let json: serde_json::Value =
+            serde_json::from_str(&warning.render(WarningsFormat::Json)).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "kind": "missing_dependency",
+                "dependency": "serde",
+                "message": "serde 1.0.219 is not available in the local cargo registry",
+            })
+        );
+    }
+}
@@ -0,0 +1,547 @@
+//! Keeping code banks up to date while a project changes.
+//!
+//! [`watch_project`] generates the banks of a project once, then watches its manifests,
+//! its lockfile and the sources of its local dependencies, and regenerates whenever
+//! they change. Dependencies are resolved as `generate` resolves them, see
+//! [`resolve_project_dependencies`]. Regeneration reuses the progress log of the output
+//! directory, so only dependencies whose resolved version changed, that were added or
+//! that are built from local sources are generated again.
+
+use crate::{
+    DependencyCollection, DiscoveryOptions, GenerateOptions, ParsedLock, ResolveOptions,
+    VersionConflictPolicy, extract_dependency_info, find_cargo_lock, find_cargo_toml_files,
+    generate_all_code_banks, is_dependency_available, read_progress_log,
+    resolve_project_dependencies, resolve_registry_path, validate_cargo_lock,
+};
+use anyhow::{Context, Result};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::Duration;
+
+/// Options for watching a project
+#[derive(Debug, Clone)]
+pub struct WatchOptions {
+    /// Output directory for the generated code banks
+    pub output_dir: PathBuf,
+    /// Path to the Cargo.lock to use instead of discovering it
+    pub lock: Option<PathBuf>,
+    /// Path to the cargo registry, found from `CARGO_HOME` when not given
+    pub registry_path: Option<PathBuf>,
+    /// How the project's manifests are discovered
+    pub discovery: DiscoveryOptions,
+    /// How the project's dependencies are resolved
+    pub resolve: ResolveOptions,
+    /// Options for generating the code banks
+    pub generate: GenerateOptions,
+    /// How long changes must settle before regenerating, so a burst of writes
+    /// triggers a single run
+    pub debounce: Duration,
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        Self {
+            output_dir: PathBuf::from(".codebank"),
+            lock: None,
+            registry_path: None,
+            discovery: DiscoveryOptions::default(),
+            resolve: ResolveOptions {
+                on_version_conflict: Some(VersionConflictPolicy::default()),
+                ..Default::default()
+            },
+            generate: GenerateOptions::default(),
+            debounce: Duration::from_millis(500),
+        }
+    }
+}
+
+/// The outcome of one generation run while watching
+#[derive(Debug, Clone, Default)]
+pub struct WatchRun {
+    /// The files whose change triggered the run, empty for the initial run
+    pub changed: Vec<PathBuf>,
+    /// Mapping of all dependency names to their code bank file paths
+    pub banks: HashMap<String, PathBuf>,
+    /// Names of the dependencies generated in this run, sorted; all others were reused
+    pub regenerated: Vec<String>,
+}
+
+/// Watches a project and regenerates its code banks whenever it changes.
+///
+/// The banks are generated once at the start, then again after every change to the
+/// project's Cargo.toml files, its Cargo.lock or the sources of its dependencies on
+/// local paths, such as crates patched to a path. Changes are debounced, and files
+/// replaced by a rename, as editors do when saving atomically, are picked up as well.
+///
+/// After each run, successful or not, `on_run` is called with its outcome. Watching
+/// continues until it returns [`ControlFlow::Break`].
+///
+/// # Arguments
+///
+/// * `project` - Path to the project root directory
+/// * `options` - Options for watching and generating
+/// * `on_run` - Called with the outcome of every run
+///
+/// # Returns
+///
+/// * `Result<()>` - Success once `on_run` stops watching
+///
+/// # Errors
+///
+/// Returns an error if the project has no Cargo.toml files or cannot be watched
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use depbank::{WatchOptions, watch_project};
+/// use std::ops::ControlFlow;
+/// use std::path::Path;
+///
+/// watch_project(Path::new("."), &WatchOptions::default(), |run| {
+///     match run {
+///         Ok(run) => println!("Regenerated {} code banks", run.regenerated.len()),
+///         Err(e) => eprintln!("Error: {:#}", e),
+///     }
+///     ControlFlow::Continue(())
+/// })
+/// .unwrap();
+/// ```
+pub fn watch_project<F>(project: &Path, options: &WatchOptions, mut on_run: F) -> Result<()>
+where
+    F: FnMut(&Result<WatchRun>) -> ControlFlow<()>,
+{
+    let mut changed = Vec::new();
+    let mut generate = options.generate.clone();
+
+    loop {
+        // Watch before generating, so changes made during a run are not missed. The
+        // watched files are refreshed every run, as manifests and local dependencies
+        // may come and go.
+        let dependencies = available_dependencies(project, options);
+        let local_dirs = dependencies
+            .as_ref()
+            .map(local_source_dirs)
+            .unwrap_or_default();
+        let watcher = ProjectWatcher::new(project, options, &local_dirs)?;
+
+        let run = dependencies
+            .and_then(|dependencies| regenerate(&dependencies, options, &generate))
+            .map(|(banks, regenerated)| WatchRun {
+                changed: std::mem::take(&mut changed),
+                banks,
+                regenerated,
+            });
+        if on_run(&run).is_break() {
+            return Ok(());
+        }
+
+        // Later runs only regenerate what changed
+        generate.resume = true;
+        changed = watcher.wait_for_change(options.debounce)?;
+    }
+}
+
+/// Generates the banks of the project's available dependencies, returning all banks
+/// and the sorted names of the dependencies generated in this run
+fn regenerate(
+    dependencies: &DependencyCollection,
+    options: &WatchOptions,
+    generate: &GenerateOptions,
+) -> Result<(HashMap<String, PathBuf>, Vec<String>)> {
+    // Dependencies the progress log already covers are reused, except those built
+    // from local sources, which may have changed
+    let completed: HashSet<(String, String)> = if generate.resume {
+        read_progress_log(&options.output_dir)?
+            .into_iter()
+            .filter(|entry| entry.path.exists())
+            .map(|entry| (entry.name, entry.version))
+            .collect()
+    } else {
+        HashSet::new()
+    };
+
+    let registry_path = registry_path(options)?;
+    let banks =
+        generate_all_code_banks(dependencies, &registry_path, &options.output_dir, generate)?;
+
+    let mut regenerated: Vec<String> = dependencies
+        .iter()
+        .filter(|dep| banks.contains_key(&dep.name))
+        .filter(|dep| {
+            dep.has_path_source() || !completed.contains(&(dep.name.clone(), dep.version.clone()))
+        })
+        .map(|dep| dep.name.clone())
+        .collect();
+    regenerated.sort();
+    regenerated.dedup();
+
+    Ok((banks, regenerated))
+}
+
+/// Resolves the project's dependencies as `generate` does and keeps those available
+/// in the local registry or on local paths
+fn available_dependencies(project: &Path, options: &WatchOptions) -> Result<DependencyCollection> {
+    let cargo_toml_files = find_cargo_toml_files(project, &options.discovery)?;
+    let mut dependency_info = DependencyCollection::new();
+    for cargo_toml in &cargo_toml_files {
+        for dep in extract_dependency_info(cargo_toml)?.iter() {
+            dependency_info.add(dep.clone());
+        }
+    }
+
+    let cargo_lock = ParsedLock::read(locate_lock(project, options.lock.as_deref())?)?;
+    let registry_path = registry_path(options)?;
+    let resolved = resolve_project_dependencies(
+        &cargo_toml_files,
+        &dependency_info,
+        &cargo_lock,
+        &registry_path,
+        &options.resolve,
+    )?;
+
+    let mut available = DependencyCollection::new();
+    for dep in resolved.dependencies.iter() {
+        if !available.contains_name(&dep.name) && is_dependency_available(&registry_path, dep) {
+            available.add(dep.clone());
+        }
+    }
+
+    Ok(available)
+}
+
+fn registry_path(options: &WatchOptions) -> Result<PathBuf> {
+    match &options.registry_path {
+        Some(path) => Ok(path.clone()),
+        None => resolve_registry_path(),
+    }
+}
+
+/// The source directories of the dependencies built from local paths
+fn local_source_dirs(dependencies: &DependencyCollection) -> Vec<PathBuf> {
+    dependencies
+        .iter()
+        .filter(|dep| dep.has_path_source())
+        .filter_map(|dep| dep.local_source_path())
+        .collect()
+}
+
+fn locate_lock(project: &Path, lock: Option<&Path>) -> Result<PathBuf> {
+    match lock {
+        Some(lock) => validate_cargo_lock(lock),
+        None => find_cargo_lock(project),
+    }
+}
+
+/// Watches the manifests and lockfile of a project, and the sources of its local
+/// dependencies.
+///
+/// The directories holding the files are watched rather than the files themselves,
+/// so that a file replaced by a rename is still noticed. The source directories of
+/// local dependencies are watched recursively, except for hidden and `target`
+/// directories.
+struct ProjectWatcher {
+    _watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<Event>>,
+    files: HashSet<PathBuf>,
+    source_dirs: Vec<PathBuf>,
+}
+
+impl ProjectWatcher {
+    fn new(project: &Path, options: &WatchOptions, local_dirs: &[PathBuf]) -> Result<Self> {
+        let mut files: Vec<PathBuf> = find_cargo_toml_files(project, &options.discovery)?;
+        if files.is_empty() {
+            return Err(anyhow::anyhow!(
+                "No Cargo.toml files found in {}",
+                project.display()
+            ));
+        }
+        if let Ok(lock) = locate_lock(project, options.lock.as_deref()) {
+            files.push(lock);
+        }
+
+        let (sender, events) = mpsc::channel();
+        let mut watcher =
+            notify::recommended_watcher(sender).context("Failed to create file watcher")?;
+
+        let mut watched_files = HashSet::new();
+        let mut watched_dirs = HashSet::new();
+        for file in files {
+            let Some(file) = canonical_file_path(&file) else {
+                continue;
+            };
+            let dir = file.parent().map(Path::to_path_buf).unwrap_or_default();
+            if watched_dirs.insert(dir.clone()) {
+                watcher
+                    .watch(&dir, RecursiveMode::NonRecursive)
+                    .with_context(|| format!("Failed to watch directory: {}", dir.display()))?;
+            }
+            watched_files.insert(file);
+        }
+
+        let mut source_dirs = Vec::new();
+        for dir in local_dirs {
+            let Ok(dir) = fs::canonicalize(dir) else {
+                continue;
+            };
+            if source_dirs.contains(&dir) {
+                continue;
+            }
+            watcher
+                .watch(&dir, RecursiveMode::Recursive)
+                .with_context(|| format!("Failed to watch directory: {}", dir.display()))?;
+            source_dirs.push(dir);
+        }
+
+        Ok(Self {
+            _watcher: watcher,
+            events,
+            files: watched_files,
+            source_dirs,
+        })
+    }
+
+    /// Blocks until a watched file changes, then waits for changes to settle for
+    /// `debounce` and returns all files that changed, sorted
+    fn wait_for_change(&self, debounce: Duration) -> Result<Vec<PathBuf>> {
+        let mut changed = HashSet::new();
+
+        while changed.is_empty() {
+            let event = self
+                .events
+                .recv()
+                .context("File watcher stopped unexpectedly")?;
+            self.collect_changes(event, &mut changed);
+        }
+
+        loop {
+            match self.events.recv_timeout(debounce) {
+                Ok(event) => self.collect_changes(event, &mut changed),
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(anyhow::anyhow!("File watcher stopped unexpectedly"));
+                }
+            }
+        }
+
+        let mut changed: Vec<PathBuf> = changed.into_iter().collect();
+        changed.sort();
+        Ok(changed)
+    }
+
+    fn collect_changes(&self, event: notify::Result<Event>, changed: &mut HashSet<PathBuf>) {
+        let Ok(event) = event else {
+            return;
+        };
+        if !matches!(
+            event.kind,
+            EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+        ) {
+            return;
+        }
+
+        // Renames report both the old and the new path, so an atomic save shows up
+        // as a change of the file it replaced
+        for path in event.paths {
+            if self.files.contains(&path) || self.is_local_source(&path) {
+                changed.insert(path);
+            }
+        }
+    }
+
+    /// Whether a path is in the sources of a local dependency, outside of its hidden
+    /// and `target` directories
+    fn is_local_source(&self, path: &Path) -> bool {
+        self.source_dirs.iter().any(|dir| {
+            path.strip_prefix(dir).is_ok_and(|relative| {
+                relative.components().all(|component| {
+                    let name = component.as_os_str().to_string_lossy();
+                    !name.starts_with('.') && name != "target"
+                })
+            })
+        })
+    }
+}
+
+/// Canonicalizes the directory of a file, keeping its file name, so that the path
+/// stays valid when the file itself is replaced
+fn canonical_file_path(file: &Path) -> Option<PathBuf> {
+    let dir = match file.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    Some(fs::canonicalize(dir).ok()?.join(file.file_name()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use tempfile::tempdir;
+
+    const MANIFEST: &str = "[package]\nname = \"demo\"\nversion = \"0.1.0\"\n\n\
+        [dependencies]\nanyhow = \"1\"\nsemver = \"1\"\n";
+    const SEMVER_LOCK: &str =
+        "version = 3\n\n[[package]]\nname = \"semver\"\nversion = \"1.0.28\"\n";
+    const ANYHOW_LOCK: &str = "\n[[package]]\nname = \"anyhow\"\nversion = \"1.0.98\"\n";
+
+    /// Writes a small crate into `dir`
+    fn write_crate(dir: &Path, name: &str, version: &str, source: &str) -> Result<()> {
+        fs::create_dir_all(dir.join("src"))?;
+        fs::write(
+            dir.join("Cargo.toml"),
+            format!("[package]\nname = \"{name}\"\nversion = \"{version}\"\n"),
+        )?;
+        fs::write(dir.join("src/lib.rs"), source)?;
+        Ok(())
+    }
+
+    /// Watches `project` on another thread, sending the outcome of the initial run
+    /// and of the first run after a change
+    fn spawn_watch(
+        project: &Path,
+        options: WatchOptions,
+    ) -> (
+        thread::JoinHandle<Result<()>>,
+        Receiver<std::result::Result<WatchRun, String>>,
+    ) {
+        let (runs_tx, runs) = mpsc::channel();
+        let project = project.to_path_buf();
+        let watch_thread = thread::spawn(move || {
+            watch_project(&project, &options, |run| {
+                let run = run.as_ref().map_err(|e| format!("{:#}", e)).cloned();
+                let initial = run.as_ref().is_ok_and(|run| run.changed.is_empty());
+                runs_tx.send(run).unwrap();
+                if initial {
+                    ControlFlow::Continue(())
+                } else {
+                    ControlFlow::Break(())
+                }
+            })
+        });
+        (watch_thread, runs)
+    }
+
+    fn next_run(runs: &Receiver<std::result::Result<WatchRun, String>>) -> Result<WatchRun> {
+        runs.recv_timeout(Duration::from_secs(60))?
+            .map_err(anyhow::Error::msg)
+    }
+
+    #[test]
+    fn test_watch_project_regenerates_on_lockfile_change() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let registry = temp_dir.path().join("registry");
+        write_crate(
+            &registry.join("semver-1.0.28"),
+            "semver",
+            "1.0.28",
+            "/// A parsed version\npub struct Version;\n",
+        )?;
+        write_crate(
+            &registry.join("anyhow-1.0.98"),
+            "anyhow",
+            "1.0.98",
+            "/// An error\npub struct Error;\n",
+        )?;
+        let project = temp_dir.path().join("project");
+        fs::create_dir_all(&project)?;
+        fs::write(project.join("Cargo.toml"), MANIFEST)?;
+        // Only semver is locked at first
+        fs::write(project.join("Cargo.lock"), SEMVER_LOCK)?;
+
+        let options = WatchOptions {
+            output_dir: temp_dir.path().join("output"),
+            registry_path: Some(registry),
+            debounce: Duration::from_millis(200),
+            ..Default::default()
+        };
+        let (watch_thread, runs) = spawn_watch(&project, options);
+
+        let initial = next_run(&runs)?;
+        assert_eq!(initial.regenerated, vec!["semver"]);
+
+        // Lock anyhow as well, saving atomically through a rename like editors do
+        let staged = project.join(".Cargo.lock.tmp");
+        fs::write(&staged, format!("{}{}", SEMVER_LOCK, ANYHOW_LOCK))?;
+        fs::rename(&staged, project.join("Cargo.lock"))?;
+
+        let update = next_run(&runs)?;
+        assert_eq!(
+            update.changed,
+            vec![fs::canonicalize(&project)?.join("Cargo.lock")]
+        );
+        // Only the new dependency is generated, the semver bank is reused
+        assert_eq!(update.regenerated, vec!["anyhow"]);
+        assert!(update.banks.contains_key("semver"));
+        assert!(update.banks["anyhow"].exists());
+
+        watch_thread.join().unwrap()?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_watch_project_regenerates_patched_sources() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let registry = temp_dir.path().join("registry");
+        write_crate(
+            &registry.join("semver-1.0.28"),
+            "semver",
+            "1.0.28",
+            "/// A parsed version\npub struct Version;\n",
+        )?;
+        let project = temp_dir.path().join("project");
+        fs::create_dir_all(&project)?;
+        write_crate(
+            &temp_dir.path().join("local-anyhow"),
+            "anyhow",
+            "1.0.98",
+            "/// An error\npub struct Error;\n",
+        )?;
+        fs::write(
+            project.join("Cargo.toml"),
+            format!(
+                "{}\n[patch.crates-io]\nanyhow = {{ path = \"../local-anyhow\" }}\n",
+                MANIFEST
+            ),
+        )?;
+        fs::write(
+            project.join("Cargo.lock"),
+            format!("{}{}", SEMVER_LOCK, ANYHOW_LOCK),
+        )?;
+
+        let output_dir = temp_dir.path().join("output");
+        let options = WatchOptions {
+            output_dir: output_dir.clone(),
+            registry_path: Some(registry),
+            debounce: Duration::from_millis(200),
+            ..Default::default()
+        };
+        let (watch_thread, runs) = spawn_watch(&project, options);
+
+        let initial = next_run(&runs)?;
+        assert_eq!(initial.regenerated, vec!["anyhow", "semver"]);
+
+        // Editing the patched crate regenerates it, though its version is unchanged
+        let lib = temp_dir.path().join("local-anyhow/src/lib.rs");
+        fs::write(
+            &lib,
+            "/// An error\npub struct Error;\n\n/// Adds context\npub fn context() {}\n",
+        )?;
+
+        let update = next_run(&runs)?;
+        assert_eq!(
+            update.changed,
+            vec![fs::canonicalize(temp_dir.path())?.join("local-anyhow/src/lib.rs")]
+        );
+        assert_eq!(update.regenerated, vec!["anyhow"]);
+        assert!(fs::read_to_string(&update.banks["anyhow"])?.contains("pub fn context"));
+
+        watch_thread.join().unwrap()?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,252 @@
+//! Detection of yanked crate versions.
+//!
+//! A locked version may have been yanked from crates.io after the lockfile was
+//! written, which is worth knowing when documenting it. The yanked status is looked
+//! up in the crates.io sparse index, or in cargo's local copy of the index when the
+//! network is not available.
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Base URL of the crates.io sparse index
+pub const CRATES_IO_SPARSE_INDEX: &str = "https://index.crates.io";
+
+/// Timeout for index requests
+const INDEX_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Checks whether a crate version has been yanked from crates.io.
+///
+/// This queries the crates.io sparse index and therefore needs network access.
+///
+/// # Arguments
+///
+/// * `name` - The name of the crate
+/// * `version` - The version to check
+///
+/// # Returns
+///
+/// * `Result<bool>` - True if the version has been yanked
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The index cannot be reached
+/// - The crate or version does not exist in the index
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use depbank::is_version_yanked;
+///
+/// if is_version_yanked("serde", "1.0.197").unwrap() {
+///     println!("serde 1.0.197 has been yanked");
+/// }
+/// ```
+pub fn is_version_yanked(name: &str, version: &str) -> Result<bool> {
+    is_version_yanked_at(CRATES_IO_SPARSE_INDEX, name, version)
+}
+
+/// Checks whether a crate version has been yanked, using the given sparse index.
+///
+/// This is the same as [`is_version_yanked`], but queries the sparse index at
+/// `index_url`, e.g. a mirror of crates.io.
+///
+/// # Arguments
+///
+/// * `index_url` - Base URL of the sparse index
+/// * `name` - The name of the crate
+/// * `version` - The version to check
+///
+/// # Returns
+///
+/// * `Result<bool>` - True if the version has been yanked
+pub fn is_version_yanked_at(index_url: &str, name: &str, version: &str) -> Result<bool> {
+    let url = format!(
+        "{}/{}",
+        index_url.trim_end_matches('/'),
+        index_file_path(name)
+    );
+
+    let content = ureq::get(&url)
+        .timeout(INDEX_REQUEST_TIMEOUT)
+        .call()
+        .with_context(|| format!("Failed to query the registry index for {}", name))?
+        .into_string()
+        .with_context(|| format!("Failed to read the registry index entry for {}", name))?;
+
+    yanked_status(&content, version)
+        .ok_or_else(|| anyhow::anyhow!("Version {} of {} not found in index", version, name))
+}
+
+/// Looks up whether a crate version is yanked in cargo's local copy of the index.
+///
+/// Cargo caches the index entries of crates it has resolved next to the extracted
+/// sources. The cache may be outdated, so this is only a fallback for when the index
+/// cannot be reached.
+///
+/// # Arguments
+///
+/// * `registry_path` - Path to the cargo registry source directory, as returned by
+///   [`resolve_registry_path`](crate::resolve_registry_path)
+/// * `name` - The name of the crate
+/// * `version` - The version to check
+///
+/// # Returns
+///
+/// * `Option<bool>` - The cached yanked status, or `None` if the version is not cached
+pub fn cached_yanked_status(registry_path: &Path, name: &str, version: &str) -> Option<bool> {
+    let cache_file = index_cache_dir(registry_path)?.join(index_file_path(name));
+    let content = fs::read(cache_file).ok()?;
+
+    yanked_status(&String::from_utf8_lossy(&content), version)
+}
+
+/// Returns the path of a crate's file within a registry index.
+///
+/// Crates with names of one to three characters live in `1/`, `2/` and `3/{first
+/// character}/`, all others in `{first two}/{next two}/`, with names in lowercase.
+fn index_file_path(name: &str) -> String {
+    let name = name.to_ascii_lowercase();
+    match name.len() {
+        1 => format!("1/{}", name),
+        2 => format!("2/{}", name),
+        3 => format!("3/{}/{}", &name[..1], name),
+        _ => format!("{}/{}/{}", &name[..2], &name[2..4], name),
+    }
+}
+
+/// Locates cargo's index cache matching a registry source directory.
+///
+/// Sources live in `<cargo home>/registry/src/<index>` and the cache of the same
+/// index in `<cargo home>/registry/index/<index>/.cache`.
+fn index_cache_dir(registry_path: &Path) -> Option<PathBuf> {
+    let index_name = registry_path.file_name()?;
+    let registry_dir = registry_path.parent()?.parent()?;
+    let cache_dir = registry_dir.join("index").join(index_name).join(".cache");
+
+    cache_dir.is_dir().then_some(cache_dir)
+}
+
+/// Finds the yanked status of a version in the entries of an index file.
+///
+/// Index files hold one JSON object per version. The sparse index separates them
+/// with newlines and cargo's local cache with NUL bytes, so both are accepted.
+fn yanked_status(index_content: &str, version: &str) -> Option<bool> {
+    index_content
+        .split(['\n', '\0'])
+        .filter(|entry| entry.starts_with('{'))
+        .filter_map(|entry| serde_json::from_str::<serde_json::Value>(entry).ok())
+        .find(|entry| entry.get("vers").and_then(|v| v.as_str()) == Some(version))
+        .map(|entry| {
+            entry
+                .get("yanked")
+                .and_then(|yanked| yanked.as_bool())
+                .unwrap_or(false)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::thread;
+    use tempfile::tempdir;
+
+    const SERDE_INDEX: &str = concat!(
+        r#"{"name":"serde","vers":"1.0.196","deps":[],"cksum":"a","features":{},"yanked":false}"#,
+        "\n",
+        r#"{"name":"serde","vers":"1.0.197","deps":[],"cksum":"b","features":{},"yanked":true}"#,
+        "\n",
+    );
+
+    /// Serves a single sparse index response and returns the server's base URL
+    fn serve_index_once(body: &'static str) -> Result<String> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let url = format!("http://{}", listener.local_addr()?);
+
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            assert!(request_line.starts_with("GET /se/rd/serde "));
+
+            // Skip the request headers
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                line.clear();
+            }
+
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )
+            .unwrap();
+        });
+
+        Ok(url)
+    }
+
+    #[test]
+    fn test_index_file_path() {
+        assert_eq!(index_file_path("a"), "1/a");
+        assert_eq!(index_file_path("cc"), "2/cc");
+        assert_eq!(index_file_path("syn"), "3/s/syn");
+        assert_eq!(index_file_path("Serde"), "se/rd/serde");
+    }
+
+    #[test]
+    fn test_is_version_yanked_with_mocked_index() -> Result<()> {
+        let url = serve_index_once(SERDE_INDEX)?;
+        assert!(is_version_yanked_at(&url, "serde", "1.0.197")?);
+
+        let url = serve_index_once(SERDE_INDEX)?;
+        assert!(!is_version_yanked_at(&url, "serde", "1.0.196")?);
+
+        let url = serve_index_once(SERDE_INDEX)?;
+        assert!(is_version_yanked_at(&url, "serde", "9.9.9").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cached_yanked_status() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let registry_dir = temp_dir.path().join("registry");
+        let registry_path = registry_dir.join("src").join("index.crates.io-1234");
+        let cache_dir = registry_dir
+            .join("index")
+            .join("index.crates.io-1234")
+            .join(".cache")
+            .join("se")
+            .join("rd");
+        fs::create_dir_all(&registry_path)?;
+        fs::create_dir_all(&cache_dir)?;
+
+        // Cargo's cache uses a binary header and NUL-separated entries
+        let cache = format!(
+            "\u{3}\u{2}\0\0\0etag\01.0.196\0{}\01.0.197\0{}\0",
+            SERDE_INDEX.lines().next().unwrap(),
+            SERDE_INDEX.lines().nth(1).unwrap()
+        );
+        fs::write(cache_dir.join("serde"), cache)?;
+
+        assert_eq!(
+            cached_yanked_status(&registry_path, "serde", "1.0.197"),
+            Some(true)
+        );
+        assert_eq!(
+            cached_yanked_status(&registry_path, "serde", "1.0.196"),
+            Some(false)
+        );
+        assert_eq!(cached_yanked_status(&registry_path, "serde", "2.0.0"), None);
+        assert_eq!(cached_yanked_status(&registry_path, "tokio", "1.0.0"), None);
+
+        Ok(())
+    }
+}
@@ -1,7 +1,49 @@
 use anyhow::Result;
-use std::process::Command;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
 use tempfile::tempdir;
 
+/// The crates.io packages that the `simple_project` fixture depends on directly
+const SIMPLE_PROJECT_DEPENDENCIES: [(&str, &str); 3] = [
+    ("anyhow", "1.0.80"),
+    ("serde", "1.0.197"),
+    ("tokio-test", "0.4.3"),
+];
+
+/// Creates a cargo home under `root` whose registry holds the given crates.
+///
+/// Each crate gets a minimal manifest and a `src/lib.rs` with one documented `Mock`
+/// item; tests needing other contents overwrite them in [`mock_crate_dir`].
+fn mock_cargo_home(root: &Path, crates: &[(&str, &str)]) -> Result<PathBuf> {
+    let cargo_home = root.join("cargo-home");
+    for (name, version) in crates {
+        let crate_dir = mock_crate_dir(&cargo_home, name, version);
+        std::fs::create_dir_all(crate_dir.join("src"))?;
+        std::fs::write(
+            crate_dir.join("Cargo.toml"),
+            format!(
+                "[package]\nname = \"{}\"\nversion = \"{}\"\n",
+                name, version
+            ),
+        )?;
+        std::fs::write(
+            crate_dir.join("src").join("lib.rs"),
+            "/// A mock item.\npub struct Mock;\n",
+        )?;
+    }
+    Ok(cargo_home)
+}
+
+/// The directory of a crate in the registry of a [`mock_cargo_home`]
+fn mock_crate_dir(cargo_home: &Path, name: &str, version: &str) -> PathBuf {
+    cargo_home
+        .join("registry")
+        .join("src")
+        .join("index.crates.io-mock")
+        .join(format!("{}-{}", name, version))
+}
+
 #[test]
 fn test_help_command() -> Result<()> {
     let output = Command::new("cargo")
@@ -15,7 +57,7 @@ fn test_help_command() -> Result<()> {
 
     // Verify help output contains expected text
     assert!(stdout.contains("Generate code banks and calculate tokens for Rust dependencies"));
-    assert!(stdout.contains("Usage: depbank <COMMAND>"));
+    assert!(stdout.contains("Usage: depbank [OPTIONS] <COMMAND>"));
     assert!(stdout.contains("Commands:"));
     assert!(stdout.contains("generate"));
     assert!(stdout.contains("tokens"));
@@ -66,6 +108,421 @@ fn test_list_detailed_command_with_fixture() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_list_with_explicit_manifests() -> Result<()> {
+    let output = Command::new("cargo")
+        .args([
+            "run",
+            "--",
+            "list",
+            "-p",
+            "fixtures/workspace_project",
+            "--manifest",
+            "fixtures/workspace_project/Cargo.toml",
+            "--manifest",
+            "fixtures/workspace_project/utils/Cargo.toml",
+        ])
+        .output()?;
+
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(output.status.success());
+    assert!(stdout.contains("Found 2 Cargo.toml files"));
+    assert!(stdout.contains("chrono"));
+    // core/Cargo.toml was not given, so its dependencies are not listed
+    assert!(!stdout.contains("tokio"));
+
+    // Manifests must be named Cargo.toml
+    let output = Command::new("cargo")
+        .args([
+            "run",
+            "--",
+            "list",
+            "-p",
+            "fixtures/workspace_project",
+            "--manifest",
+            "fixtures/README.md",
+        ])
+        .output()?;
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)?.contains("not named Cargo.toml"));
+
+    Ok(())
+}
+
+#[test]
+fn test_fingerprint_command() -> Result<()> {
+    let fingerprint = |lock: &str, extra: &[&str]| -> Result<String> {
+        let mut args = vec![
+            "run",
+            "--",
+            "fingerprint",
+            "-p",
+            "fixtures/simple_project",
+            "--lock",
+            lock,
+        ];
+        args.extend(extra);
+        let output = Command::new("cargo").args(&args).output()?;
+        assert!(output.status.success());
+        Ok(String::from_utf8(output.stdout)?.trim().to_string())
+    };
+
+    // Only the hash is printed, and it is the same on every run
+    let first = fingerprint("fixtures/simple_project/Cargo.lock", &[])?;
+    assert_eq!(first.len(), 16);
+    assert_eq!(
+        fingerprint("fixtures/simple_project/Cargo.lock", &[])?,
+        first
+    );
+
+    // A different generation option changes it
+    assert_ne!(
+        fingerprint(
+            "fixtures/simple_project/Cargo.lock",
+            &["--strategy", "full"]
+        )?,
+        first
+    );
+
+    // So does a different locked version
+    let temp_dir = tempdir()?;
+    let lock_path = temp_dir.path().join("Cargo.lock");
+    let lock = std::fs::read_to_string("fixtures/simple_project/Cargo.lock")?;
+    std::fs::write(&lock_path, lock.replace("1.0.80", "1.0.81"))?;
+    assert_ne!(fingerprint(lock_path.to_str().unwrap(), &[])?, first);
+
+    // And so do the selection of the dependencies and the sections appended to banks
+    let lock = "fixtures/simple_project/Cargo.lock";
+    assert_ne!(fingerprint(lock, &["--max-deps", "1"])?, first);
+    assert_ne!(fingerprint(lock, &["--with-usage"])?, first);
+    let deps_file = temp_dir.path().join("deps.txt");
+    std::fs::write(&deps_file, "serde\n")?;
+    let selected = fingerprint(lock, &["--deps-file", deps_file.to_str().unwrap()])?;
+    assert_ne!(selected, first);
+    assert_eq!(selected.len(), 16);
+
+    Ok(())
+}
+
+#[test]
+fn test_generate_with_cargo_home() -> Result<()> {
+    // A mock registry in a custom cargo home, holding one of the fixture's dependencies
+    let temp_dir = tempdir()?;
+    let cargo_home = mock_cargo_home(temp_dir.path(), &[("anyhow", "1.0.80")])?;
+    std::fs::write(
+        mock_crate_dir(&cargo_home, "anyhow", "1.0.80")
+            .join("src")
+            .join("lib.rs"),
+        "/// A mock error type.\npub struct MockError;\n",
+    )?;
+
+    // Run the built binary directly, as cargo itself would use the mock cargo home
+    let output_dir = temp_dir.path().join("banks");
+    let output = Command::new(env!("CARGO_BIN_EXE_depbank"))
+        .args(["generate", "-p", "fixtures/simple_project", "-o"])
+        .arg(&output_dir)
+        .env("CARGO_HOME", &cargo_home)
+        .output()?;
+
+    assert!(output.status.success());
+    let bank = std::fs::read_to_string(output_dir.join("anyhow.md"))?;
+    assert!(bank.contains("MockError"));
+    // serde is not in the mock registry
+    assert!(String::from_utf8(output.stderr)?.contains("serde"));
+
+    Ok(())
+}
+
+#[test]
+fn test_generate_include_self() -> Result<()> {
+    // A mock registry holding one of the fixture's dependencies
+    let temp_dir = tempdir()?;
+    let cargo_home = mock_cargo_home(temp_dir.path(), &[("anyhow", "1.0.80")])?;
+
+    let output_dir = temp_dir.path().join("banks");
+    let output = Command::new(env!("CARGO_BIN_EXE_depbank"))
+        .args([
+            "generate",
+            "-p",
+            "fixtures/simple_project",
+            "--include-self",
+            "-o",
+        ])
+        .arg(&output_dir)
+        .env("CARGO_HOME", &cargo_home)
+        .output()?;
+
+    assert!(output.status.success());
+    // The project's own bank sits next to its dependencies' banks
+    let bank = std::fs::read_to_string(output_dir.join("simple_project.md"))?;
+    assert!(bank.contains("struct Person"));
+    assert!(output_dir.join("anyhow.md").exists());
+    // A completed run leaves no progress log to resume from
+    assert!(!output_dir.join(".progress.jsonl").exists());
+
+    let index: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(output_dir.join("index.json"))?)?;
+    let own = index["banks"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|bank| bank["name"] == "simple_project")
+        .unwrap();
+    assert_eq!(own["version"], "0.1.0");
+
+    Ok(())
+}
+
+#[test]
+fn test_unlocked_dependency_warnings() -> Result<()> {
+    // Two members declaring the same misspelled crate, and a renamed dependency
+    let temp_dir = tempdir()?;
+    let workspace = temp_dir.path().join("workspace");
+    for member in ["first", "second"] {
+        std::fs::create_dir_all(workspace.join(member).join("src"))?;
+        std::fs::write(
+            workspace.join(member).join("Cargo.toml"),
+            format!(
+                "[package]\nname = \"{}\"\nversion = \"0.1.0\"\n\n[dependencies]\n\
+                 serde_jsno = \"1\"\nerrors = {{ package = \"anyhow\", version = \"1\" }}\n",
+                member
+            ),
+        )?;
+        std::fs::write(workspace.join(member).join("src").join("lib.rs"), "")?;
+    }
+    std::fs::write(
+        workspace.join("Cargo.toml"),
+        "[workspace]\nmembers = [\"first\", \"second\"]\n",
+    )?;
+    std::fs::write(
+        workspace.join("Cargo.lock"),
+        "version = 4\n\n[[package]]\nname = \"anyhow\"\nversion = \"1.0.80\"\n",
+    )?;
+    let cargo_home = mock_cargo_home(temp_dir.path(), &[("anyhow", "1.0.80")])?;
+
+    let output = Command::new(env!("CARGO_BIN_EXE_depbank"))
+        .args(["generate", "--dry-run", "-p"])
+        .arg(&workspace)
+        .arg("-o")
+        .arg(temp_dir.path().join("banks"))
+        .env("CARGO_HOME", &cargo_home)
+        .output()?;
+    assert!(output.status.success());
+
+    // The misspelled crate is reported once, the renamed one resolves to its package
+    let stderr = String::from_utf8(output.stderr)?;
+    assert_eq!(
+        stderr
+            .matches("Cargo.lock has no package named serde_jsno")
+            .count(),
+        1
+    );
+    assert!(!stderr.contains("errors"));
+
+    Ok(())
+}
+
+#[test]
+fn test_generate_on_version_conflict() -> Result<()> {
+    // A workspace whose members pin incompatible versions of the same crate
+    let temp_dir = tempdir()?;
+    let workspace = temp_dir.path().join("workspace");
+    for (member, requirement) in [("first", "1"), ("second", "2.9")] {
+        std::fs::create_dir_all(workspace.join(member).join("src"))?;
+        std::fs::write(
+            workspace.join(member).join("Cargo.toml"),
+            format!(
+                "[package]\nname = \"{}\"\nversion = \"0.1.0\"\n\n[dependencies]\nbitflags = \"{}\"\n",
+                member, requirement
+            ),
+        )?;
+        std::fs::write(workspace.join(member).join("src").join("lib.rs"), "")?;
+    }
+    std::fs::write(
+        workspace.join("Cargo.toml"),
+        "[workspace]\nmembers = [\"first\", \"second\"]\n",
+    )?;
+    let registry = "registry+https://github.com/rust-lang/crates.io-index";
+    std::fs::write(
+        workspace.join("Cargo.lock"),
+        format!(
+            "version = 4\n\n[[package]]\nname = \"bitflags\"\nversion = \"1.3.2\"\nsource = \"{registry}\"\n\n\
+[[package]]\nname = \"bitflags\"\nversion = \"2.9.0\"\nsource = \"{registry}\"\n\n\
+[[package]]\nname = \"first\"\nversion = \"0.1.0\"\ndependencies = [\"bitflags 1.3.2\"]\n\n\
+[[package]]\nname = \"second\"\nversion = \"0.1.0\"\ndependencies = [\"bitflags 2.9.0\"]\n"
+        ),
+    )?;
+
+    // Both versions in a mock registry, telling them apart by their items
+    let versions = [("1.3.2", "FlagsV1"), ("2.9.0", "FlagsV2")];
+    let crates: Vec<(&str, &str)> = versions
+        .iter()
+        .map(|(version, _)| ("bitflags", *version))
+        .collect();
+    let cargo_home = mock_cargo_home(temp_dir.path(), &crates)?;
+    for (version, item) in versions {
+        std::fs::write(
+            mock_crate_dir(&cargo_home, "bitflags", version)
+                .join("src")
+                .join("lib.rs"),
+            format!("/// Mock flags.\npub struct {};\n", item),
+        )?;
+    }
+
+    let generate = |policy: &str| -> Result<(std::process::Output, std::path::PathBuf)> {
+        let output_dir = temp_dir.path().join(policy);
+        let output = Command::new(env!("CARGO_BIN_EXE_depbank"))
+            .args(["generate", "--on-version-conflict", policy, "-p"])
+            .arg(&workspace)
+            .arg("-o")
+            .arg(&output_dir)
+            .env("CARGO_HOME", &cargo_home)
+            .output()?;
+        Ok((output, output_dir))
+    };
+
+    let (output, output_dir) = generate("highest")?;
+    assert!(output.status.success());
+    let bank = std::fs::read_to_string(output_dir.join("bitflags.md"))?;
+    assert!(bank.contains("FlagsV2"));
+    assert!(
+        String::from_utf8(output.stderr)?.contains(
+            "bitflags is used at versions 1.3.2, 2.9.0, generating the code bank for 2.9.0"
+        )
+    );
+
+    let (output, output_dir) = generate("lowest")?;
+    assert!(output.status.success());
+    let bank = std::fs::read_to_string(output_dir.join("bitflags.md"))?;
+    assert!(bank.contains("FlagsV1"));
+
+    let (output, output_dir) = generate("error")?;
+    assert!(!output.status.success());
+    assert!(!output_dir.exists());
+    assert!(
+        String::from_utf8(output.stderr)?
+            .contains("bitflags is used at conflicting versions 1.3.2, 2.9.0")
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_list_tree() -> Result<()> {
+    let output = Command::new("cargo")
+        .args([
+            "run",
+            "--",
+            "list",
+            "-p",
+            "fixtures/simple_project",
+            "--tree",
+        ])
+        .output()?;
+
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(output.status.success());
+    // serde pulls in syn through serde_derive
+    assert!(stdout.contains(
+        "serde v1.0.197\n└── serde_derive v1.0.197\n    ├── proc-macro2 v1.0.79\n    ├── quote v1.0.35\n    └── syn v2.0.52\n"
+    ));
+    assert!(stdout.starts_with("anyhow v1.0.80\n"));
+
+    Ok(())
+}
+
+#[test]
+fn test_sbom_command() -> Result<()> {
+    let output = Command::new("cargo")
+        .args(["run", "--", "sbom", "-p", "fixtures/simple_project"])
+        .output()?;
+
+    assert!(output.status.success());
+    let bom: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(bom["bomFormat"], "CycloneDX");
+    let purls: Vec<&str> = bom["components"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|component| component["purl"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        purls,
+        vec![
+            "pkg:cargo/anyhow@1.0.80",
+            "pkg:cargo/serde@1.0.197",
+            "pkg:cargo/tokio-test@0.4.3"
+        ]
+    );
+
+    // SPDX lists the same dependencies as packages
+    let temp_dir = tempdir()?;
+    let sbom_path = temp_dir.path().join("sbom.spdx.json");
+    let output = Command::new("cargo")
+        .args([
+            "run",
+            "--",
+            "sbom",
+            "-p",
+            "fixtures/simple_project",
+            "-f",
+            "spdx",
+            "-o",
+        ])
+        .arg(&sbom_path)
+        .output()?;
+    assert!(output.status.success());
+    let document: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&sbom_path)?)?;
+    assert_eq!(document["spdxVersion"], "SPDX-2.3");
+    assert_eq!(document["name"], "simple_project");
+    assert_eq!(document["packages"].as_array().unwrap().len(), 3);
+
+    Ok(())
+}
+
+#[test]
+fn test_meta_command() -> Result<()> {
+    // Only serde is in the mock registry, the other dependencies are unavailable
+    let temp_dir = tempdir()?;
+    let cargo_home = mock_cargo_home(temp_dir.path(), &[("serde", "1.0.197")])?;
+    let crate_dir = mock_crate_dir(&cargo_home, "serde", "1.0.197");
+    std::fs::write(
+        crate_dir.join("Cargo.toml"),
+        "[package]\nname = \"serde\"\nversion = \"1.0.197\"\nedition = \"2018\"\n\
+license = \"MIT OR Apache-2.0\"\n\n[features]\nstd = []\nderive = []\n",
+    )?;
+    std::fs::write(
+        crate_dir.join("src").join("lib.rs"),
+        "#![no_std]\n/// A mock item.\npub struct Mock;\n",
+    )?;
+
+    let output = Command::new(env!("CARGO_BIN_EXE_depbank"))
+        .args(["meta", "-p", "fixtures/simple_project"])
+        .env("CARGO_HOME", &cargo_home)
+        .output()?;
+
+    assert!(output.status.success());
+    let metadata: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    let dependencies = metadata.as_array().unwrap();
+    assert_eq!(dependencies.len(), 3);
+    let serde = dependencies
+        .iter()
+        .find(|dependency| dependency["name"] == "serde")
+        .unwrap();
+    assert_eq!(serde["version"], "1.0.197");
+    assert_eq!(serde["license"], "MIT OR Apache-2.0");
+    assert_eq!(serde["edition"], "2018");
+    assert_eq!(serde["features"], serde_json::json!(["derive", "std"]));
+    assert_eq!(serde["no_std"], true);
+    let anyhow = dependencies
+        .iter()
+        .find(|dependency| dependency["name"] == "anyhow")
+        .unwrap();
+    assert_eq!(anyhow["available"], false);
+
+    Ok(())
+}
+
 #[test]
 fn test_tokens_command() -> Result<()> {
     // Create a temporary test file
@@ -89,6 +546,41 @@ fn test_tokens_command() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_tokens_command_relative_paths() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let docs = temp_dir.path().join("docs");
+    std::fs::create_dir_all(&docs)?;
+    std::fs::write(docs.join("guide.md"), "A short guide for token counting.")?;
+
+    // Given absolute, the directory and file are printed relative to the current one
+    let output = Command::new(env!("CARGO_BIN_EXE_depbank"))
+        .arg("tokens")
+        .arg(&docs)
+        .arg("--relative-paths")
+        .current_dir(temp_dir.path())
+        .output()?;
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.contains("Token counts for files in docs:"));
+    assert!(!stdout.contains(&temp_dir.path().display().to_string()));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_depbank"))
+        .arg("tokens")
+        .arg(docs.join("guide.md"))
+        .arg("--relative-paths")
+        .current_dir(temp_dir.path())
+        .output()?;
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.starts_with(&format!(
+        "{}: ",
+        Path::new("docs").join("guide.md").display()
+    )));
+
+    Ok(())
+}
+
 #[test]
 fn test_generate_dry_run() -> Result<()> {
     // The project might not have local dependencies available
@@ -121,15 +613,33 @@ fn test_empty_project() -> Result<()> {
 
     let stdout = String::from_utf8(output.stdout)?;
 
-    // Should succeed but report no or few dependencies
     assert!(output.status.success());
-
-    // Either it will say "0 unique dependencies" or "Found 0 dependencies"
-    // or something similar
-    assert!(
-        stdout.contains("0") && (stdout.contains("dependencies") || stdout.contains("Cargo.toml"))
+    assert_eq!(
+        stdout.trim(),
+        "No dependencies found in 1 Cargo.toml file(s)"
     );
 
+    // Generation succeeds without creating an empty output directory
+    let temp_dir = tempdir()?;
+    let output_dir = temp_dir.path().join("banks");
+    let output = Command::new("cargo")
+        .args([
+            "run",
+            "--",
+            "generate",
+            "-p",
+            "fixtures/empty_project",
+            "-o",
+        ])
+        .arg(&output_dir)
+        .output()?;
+
+    let stdout = String::from_utf8(output.stdout)?;
+
+    assert!(output.status.success());
+    assert!(stdout.contains("No dependencies found in 1 Cargo.toml file(s)"));
+    assert!(!output_dir.exists());
+
     Ok(())
 }
 
@@ -171,3 +681,969 @@ fn test_workspace_project() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_list_with_explicit_lock() -> Result<()> {
+    // Place the lockfile somewhere discovery would never find it
+    let temp_dir = tempdir()?;
+    let lock_dir = temp_dir.path().join("locks");
+    std::fs::create_dir(&lock_dir)?;
+    let lock_path = lock_dir.join("Cargo.lock");
+    std::fs::copy("fixtures/simple_project/Cargo.lock", &lock_path)?;
+
+    let output = Command::new("cargo")
+        .args([
+            "run",
+            "--",
+            "list",
+            "-p",
+            "fixtures/simple_project",
+            "-d",
+            "--lock",
+            lock_path.to_str().unwrap(),
+        ])
+        .output()?;
+
+    let stdout = String::from_utf8(output.stdout)?;
+
+    // Verify command executed successfully and used the explicit lockfile
+    assert!(output.status.success());
+    assert!(stdout.contains(&format!("Found Cargo.lock at: {}", lock_path.display())));
+    assert!(stdout.contains("Resolved dependency versions from Cargo.lock"));
+
+    // A missing explicit lockfile is an error rather than falling back to discovery
+    let output = Command::new("cargo")
+        .args([
+            "run",
+            "--",
+            "list",
+            "-p",
+            "fixtures/simple_project",
+            "-d",
+            "--lock",
+            lock_dir.join("missing.lock").to_str().unwrap(),
+        ])
+        .output()?;
+    assert!(!output.status.success());
+
+    Ok(())
+}
+
+#[test]
+fn test_list_with_lock_from_stdin() -> Result<()> {
+    let lock = std::fs::read("fixtures/simple_project/Cargo.lock")?;
+    let run = |args: &[&str]| -> Result<std::process::Output> {
+        let mut child = Command::new("cargo")
+            .args(["run", "--", "list"])
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        child.stdin.take().unwrap().write_all(&lock)?;
+        Ok(child.wait_with_output()?)
+    };
+
+    // The locked packages, without any project
+    let output = run(&["--from-lock", "--lock", "-"])?;
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(output.status.success());
+    assert!(stdout.contains("Found 9 locked packages"));
+    assert!(stdout.contains("- serde_derive 1.0.197"));
+
+    // The declared dependencies, resolved with the piped lockfile
+    let output = run(&["-p", "fixtures/simple_project", "-d", "--lock", "-"])?;
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(output.status.success());
+    assert!(stdout.contains("Read Cargo.lock from stdin"));
+    assert!(stdout.contains("serde: 1.0.197"));
+
+    Ok(())
+}
+
+#[test]
+fn test_watch_rejects_lock_from_stdin() -> Result<()> {
+    let output = Command::new(env!("CARGO_BIN_EXE_depbank"))
+        .args(["watch", "-p", "fixtures/simple_project", "--lock", "-"])
+        .stdin(Stdio::null())
+        .output()?;
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)?.contains("cannot read Cargo.lock from stdin"));
+
+    Ok(())
+}
+
+#[test]
+fn test_deps_diff_between_projects() -> Result<()> {
+    let output = Command::new("cargo")
+        .args([
+            "run",
+            "--",
+            "deps-diff",
+            "fixtures/simple_project",
+            "fixtures/complex_project",
+            "--format",
+            "json",
+        ])
+        .output()?;
+
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(output.status.success());
+
+    let diff: serde_json::Value = serde_json::from_str(&stdout)?;
+    let added: Vec<&str> = diff["added"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|dep| dep["name"].as_str().unwrap())
+        .collect();
+
+    // Dependencies only declared by the complex project are added
+    assert!(added.contains(&"clap"));
+    assert!(added.contains(&"thiserror"));
+    // Shared dependencies at the same version are not reported
+    assert!(!added.contains(&"anyhow"));
+    assert!(diff["removed"].as_array().unwrap().is_empty());
+    assert!(diff["changed"].as_array().unwrap().is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_deps_diff_against_git_revision() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let repo = temp_dir.path();
+    let git = |args: &[&str]| -> Result<()> {
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(repo)
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .status()?;
+        assert!(status.success());
+        Ok(())
+    };
+
+    // Commit a project, then bump a dependency in the working tree
+    std::fs::write(
+        repo.join("Cargo.toml"),
+        "[package]\nname = \"demo\"\nversion = \"0.1.0\"\n\n[dependencies]\nserde = \"1.0\"\n",
+    )?;
+    std::fs::write(
+        repo.join("Cargo.lock"),
+        "version = 4\n\n[[package]]\nname = \"serde\"\nversion = \"1.0.150\"\n",
+    )?;
+    git(&["init", "-q"])?;
+    git(&["add", "."])?;
+    git(&["commit", "-q", "-m", "initial"])?;
+    std::fs::write(
+        repo.join("Cargo.lock"),
+        "version = 4\n\n[[package]]\nname = \"serde\"\nversion = \"1.0.197\"\n",
+    )?;
+
+    let output = Command::new("cargo")
+        .args([
+            "run",
+            "--",
+            "deps-diff",
+            repo.to_str().unwrap(),
+            "--against",
+            "HEAD",
+        ])
+        .output()?;
+
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(output.status.success());
+    assert!(stdout.contains("~ serde 1.0.150 -> 1.0.197"));
+
+    Ok(())
+}
+
+#[test]
+fn test_list_json_declared_in() -> Result<()> {
+    let output = Command::new("cargo")
+        .args([
+            "run",
+            "--",
+            "list",
+            "-p",
+            "fixtures/workspace_project",
+            "--detailed",
+            "--format",
+            "json",
+        ])
+        .output()?;
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout)?;
+    let listed: serde_json::Value = serde_json::from_str(&stdout)?;
+    let log = listed
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|dep| dep["name"] == "log")
+        .unwrap();
+
+    // `log` is declared by both workspace members
+    let declared_in: Vec<&str> = log["declared_in"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|path| path.as_str().unwrap())
+        .collect();
+    assert_eq!(declared_in.len(), 2);
+    assert!(declared_in.iter().any(|path| path.contains("core")));
+    assert!(declared_in.iter().any(|path| path.contains("utils")));
+    assert_eq!(log["version"], "0.4.21");
+
+    Ok(())
+}
+
+#[test]
+fn test_list_jsonl_format() -> Result<()> {
+    let output = Command::new("cargo")
+        .args([
+            "run",
+            "--",
+            "list",
+            "-p",
+            "fixtures/workspace_project",
+            "--detailed",
+            "--format",
+            "jsonl",
+        ])
+        .output()?;
+
+    assert!(output.status.success());
+
+    // Every line is a standalone JSON object, with no enclosing array
+    let stdout = String::from_utf8(output.stdout)?;
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert!(!lines.is_empty());
+    let listed: Vec<serde_json::Value> = lines
+        .iter()
+        .map(|line| serde_json::from_str(line))
+        .collect::<Result<_, _>>()?;
+    assert!(listed.iter().all(serde_json::Value::is_object));
+    let log = listed.iter().find(|dep| dep["name"] == "log").unwrap();
+    assert_eq!(log["version"], "0.4.21");
+
+    // Locked packages stream the same way
+    let output = Command::new("cargo")
+        .args([
+            "run",
+            "--",
+            "list",
+            "-p",
+            "fixtures/simple_project",
+            "--from-lock",
+            "--format",
+            "jsonl",
+        ])
+        .output()?;
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)?;
+    for line in stdout.lines() {
+        let package: serde_json::Value = serde_json::from_str(line)?;
+        assert!(package["name"].is_string());
+    }
+    assert_eq!(stdout.lines().count(), 9);
+
+    Ok(())
+}
+
+#[test]
+fn test_exit_code_missing_dependencies() -> Result<()> {
+    // None of the fixture's locked versions are in the local registry
+    let output = Command::new("cargo")
+        .args([
+            "run",
+            "--",
+            "generate",
+            "-p",
+            "fixtures/simple_project",
+            "--dry-run",
+        ])
+        .output()?;
+
+    assert_eq!(output.status.code(), Some(2));
+
+    Ok(())
+}
+
+#[test]
+fn test_follow_path_deps_cycle() -> Result<()> {
+    // alpha and beta depend on each other through path dependencies
+    let output = Command::new(env!("CARGO_BIN_EXE_depbank"))
+        .args([
+            "list",
+            "-p",
+            "fixtures/path_cycle_project",
+            "--follow-path-deps",
+        ])
+        .output()?;
+
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr)?;
+    assert!(
+        stderr.contains("dependency cycle: beta -> alpha -> beta")
+            || stderr.contains("dependency cycle: alpha -> beta -> alpha")
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_color_never() -> Result<()> {
+    // The fixture's missing dependencies give warnings and an error
+    let run = |color: &str| {
+        Command::new(env!("CARGO_BIN_EXE_depbank"))
+            .args([
+                "--color",
+                color,
+                "generate",
+                "-p",
+                "fixtures/simple_project",
+                "--dry-run",
+            ])
+            .output()
+    };
+
+    let output = run("never")?;
+    let stderr = String::from_utf8(output.stderr)?;
+    assert!(stderr.contains("Warning:"));
+    assert!(!stderr.contains('\x1b'));
+    assert!(!String::from_utf8(output.stdout)?.contains('\x1b'));
+
+    // Color is forced even when piping
+    let output = run("always")?;
+    assert!(String::from_utf8(output.stderr)?.contains("\x1b[1;33mWarning:\x1b[0m"));
+
+    Ok(())
+}
+
+#[test]
+fn test_exit_code_token_budget_exceeded() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let cargo_home = mock_cargo_home(temp_dir.path(), &[("semver", "1.0.28")])?;
+    let project = temp_dir.path().join("project");
+    std::fs::create_dir_all(&project)?;
+    std::fs::write(
+        project.join("Cargo.toml"),
+        "[package]\nname = \"demo\"\nversion = \"0.1.0\"\n\n[dependencies]\nsemver = \"1\"\n",
+    )?;
+    std::fs::write(
+        project.join("Cargo.lock"),
+        "version = 3\n\n[[package]]\nname = \"semver\"\nversion = \"1.0.28\"\n",
+    )?;
+
+    let output = Command::new(env!("CARGO_BIN_EXE_depbank"))
+        .args([
+            "generate",
+            "-p",
+            project.to_str().unwrap(),
+            "-o",
+            temp_dir.path().join("output").to_str().unwrap(),
+            "--max-total-tokens",
+            "10",
+        ])
+        .env("CARGO_HOME", &cargo_home)
+        .output()?;
+
+    let stderr = String::from_utf8(output.stderr)?;
+    assert_eq!(output.status.code(), Some(4));
+    assert!(stderr.contains("exceeding the budget of 10"));
+
+    Ok(())
+}
+
+#[test]
+fn test_generate_compress_over() -> Result<()> {
+    // Two mock crates: semver's bank is far larger than itoa's
+    let temp_dir = tempdir()?;
+    let cargo_home = mock_cargo_home(temp_dir.path(), &[("semver", "1.0.28"), ("itoa", "1.0.15")])?;
+    let semver_source: String = (0..400)
+        .map(|i| format!("/// Compares version number {i}.\npub fn compare_{i}(left: u64, right: u64) -> bool {{\n    left == right\n}}\n"))
+        .collect();
+    std::fs::write(
+        mock_crate_dir(&cargo_home, "semver", "1.0.28")
+            .join("src")
+            .join("lib.rs"),
+        semver_source,
+    )?;
+    let project = temp_dir.path().join("project");
+    let output_dir = temp_dir.path().join("output");
+    std::fs::create_dir_all(&project)?;
+    std::fs::write(
+        project.join("Cargo.toml"),
+        "[package]\nname = \"demo\"\nversion = \"0.1.0\"\n\n[dependencies]\nsemver = \"1\"\nitoa = \"1\"\n",
+    )?;
+    std::fs::write(
+        project.join("Cargo.lock"),
+        "version = 3\n\n[[package]]\nname = \"semver\"\nversion = \"1.0.28\"\n\n[[package]]\nname = \"itoa\"\nversion = \"1.0.15\"\n",
+    )?;
+
+    let output = Command::new(env!("CARGO_BIN_EXE_depbank"))
+        .args([
+            "generate",
+            "-p",
+            project.to_str().unwrap(),
+            "-o",
+            output_dir.to_str().unwrap(),
+            "--compress-over",
+            "10000",
+        ])
+        .env("CARGO_HOME", &cargo_home)
+        .output()?;
+    assert!(output.status.success());
+
+    // Only the bank above the threshold is compressed
+    assert!(output_dir.join("semver.md.gz").is_file());
+    assert!(!output_dir.join("semver.md").exists());
+    assert!(output_dir.join("itoa.md").is_file());
+    assert!(!output_dir.join("itoa.md.gz").exists());
+
+    // The index links to both kinds of banks
+    let index = std::fs::read_to_string(output_dir.join("README.md"))?;
+    assert!(index.contains("[semver](semver.md.gz)"));
+    assert!(index.contains("(gzip: "));
+    assert!(index.contains("[itoa](itoa.md)"));
+
+    Ok(())
+}
+
+#[test]
+fn test_generate_multiple_projects() -> Result<()> {
+    // Two projects sharing serde, with crates from a mock registry
+    let temp_dir = tempdir()?;
+    let cargo_home = mock_cargo_home(temp_dir.path(), &[("serde", "1.0.219"), ("itoa", "1.0.15")])?;
+    let output_dir = temp_dir.path().join("output");
+    let projects = [("api", ""), ("worker", "itoa = \"1\"\n")];
+    for (name, extra_dependency) in projects {
+        let project = temp_dir.path().join(name);
+        std::fs::create_dir_all(&project)?;
+        std::fs::write(
+            project.join("Cargo.toml"),
+            format!(
+                "[package]\nname = \"{}\"\nversion = \"0.1.0\"\n\n[dependencies]\nserde = \"1\"\n{}",
+                name, extra_dependency
+            ),
+        )?;
+        std::fs::write(
+            project.join("Cargo.lock"),
+            "version = 3\n\n[[package]]\nname = \"serde\"\nversion = \"1.0.219\"\n\n[[package]]\nname = \"itoa\"\nversion = \"1.0.15\"\n",
+        )?;
+    }
+    let api = temp_dir.path().join("api");
+    let worker = temp_dir.path().join("worker");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_depbank"))
+        .args([
+            "generate",
+            "-p",
+            api.to_str().unwrap(),
+            "-p",
+            worker.to_str().unwrap(),
+            "-o",
+            output_dir.to_str().unwrap(),
+        ])
+        .env("CARGO_HOME", &cargo_home)
+        .output()?;
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.contains("2 unique dependencies across 2 projects"));
+
+    // serde is generated once and attributed to both projects
+    let banks: Vec<_> = std::fs::read_dir(&output_dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .filter(|name| name.ends_with(".md") && name != "README.md")
+        .collect();
+    assert_eq!(banks.len(), 2);
+
+    let index = std::fs::read_to_string(output_dir.join("README.md"))?;
+    let serde_row = index
+        .lines()
+        .find(|line| line.starts_with("| [serde]"))
+        .unwrap();
+    assert!(serde_row.ends_with(&format!(" {}, {} |", api.display(), worker.display())));
+    let itoa_row = index
+        .lines()
+        .find(|line| line.starts_with("| [itoa]"))
+        .unwrap();
+    assert!(itoa_row.ends_with(&format!(" {} |", worker.display())));
+
+    Ok(())
+}
+
+#[test]
+fn test_generate_per_project_output() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let cargo_home = mock_cargo_home(temp_dir.path(), &[("serde", "1.0.219"), ("itoa", "1.0.15")])?;
+    let output_dir = temp_dir.path().join("output");
+    let projects = [("api", ""), ("worker", "itoa = \"1\"\n")];
+    for (name, extra_dependency) in projects {
+        let project = temp_dir.path().join(name);
+        std::fs::create_dir_all(&project)?;
+        std::fs::write(
+            project.join("Cargo.toml"),
+            format!(
+                "[package]\nname = \"{}-service\"\nversion = \"0.1.0\"\n\n[dependencies]\nserde = \"1\"\n{}",
+                name, extra_dependency
+            ),
+        )?;
+        std::fs::write(
+            project.join("Cargo.lock"),
+            "version = 3\n\n[[package]]\nname = \"serde\"\nversion = \"1.0.219\"\n\n[[package]]\nname = \"itoa\"\nversion = \"1.0.15\"\n",
+        )?;
+    }
+
+    let output = Command::new(env!("CARGO_BIN_EXE_depbank"))
+        .args([
+            "generate",
+            "-p",
+            temp_dir.path().join("api").to_str().unwrap(),
+            "-p",
+            temp_dir.path().join("worker").to_str().unwrap(),
+            "-o",
+            output_dir.to_str().unwrap(),
+            "--per-project-output",
+        ])
+        .env("CARGO_HOME", &cargo_home)
+        .output()?;
+    assert!(output.status.success());
+
+    // Each project gets its own directory, named after its package
+    let banks = |project: &str| -> Result<Vec<String>> {
+        let mut banks: Vec<String> = std::fs::read_dir(output_dir.join(project))?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .filter(|name| name.ends_with(".md") && name != "README.md")
+            .collect();
+        banks.sort();
+        Ok(banks)
+    };
+    assert_eq!(banks("api-service")?, vec!["serde.md"]);
+    assert_eq!(banks("worker-service")?, vec!["itoa.md", "serde.md"]);
+    assert!(output_dir.join("api-service").join("README.md").exists());
+    assert!(
+        output_dir
+            .join("worker-service")
+            .join("index.json")
+            .exists()
+    );
+    assert!(!output_dir.join("serde.md").exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_generate_per_project_output_reports_every_project() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let cargo_home = mock_cargo_home(temp_dir.path(), &[("anyhow", "1.0.80")])?;
+    for name in ["api", "worker"] {
+        let project = temp_dir.path().join(name);
+        std::fs::create_dir_all(project.join("src"))?;
+        std::fs::write(
+            project.join("Cargo.toml"),
+            format!(
+                "[package]\nname = \"{}-service\"\nversion = \"0.1.0\"\n\n[dependencies]\nanyhow = \"1\"\n",
+                name
+            ),
+        )?;
+        std::fs::write(project.join("src").join("lib.rs"), "")?;
+        std::fs::write(
+            project.join("Cargo.lock"),
+            "version = 3\n\n[[package]]\nname = \"anyhow\"\nversion = \"1.0.80\"\n\
+source = \"registry+https://github.com/rust-lang/crates.io-index\"\n",
+        )?;
+    }
+    // A file in the way of the first project's output directory makes it fail
+    let output_dir = temp_dir.path().join("output");
+    std::fs::create_dir_all(&output_dir)?;
+    std::fs::write(output_dir.join("api-service"), "")?;
+
+    let output = Command::new(env!("CARGO_BIN_EXE_depbank"))
+        .args(["generate", "--per-project-output", "-p"])
+        .arg(temp_dir.path().join("api"))
+        .arg("-p")
+        .arg(temp_dir.path().join("worker"))
+        .arg("-o")
+        .arg(&output_dir)
+        .env("CARGO_HOME", &cargo_home)
+        .output()?;
+
+    // The other project is still generated and reported before the run fails
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr)?;
+    assert!(stderr.contains("Error generating the code banks of"));
+    assert!(stderr.contains("Failed to generate the code banks of 1 of 2 projects"));
+    let stdout = String::from_utf8(output.stdout)?;
+    assert_eq!(stdout.matches("- Output directory:").count(), 1);
+    assert!(output_dir.join("worker-service").join("anyhow.md").exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_list_csv_format() -> Result<()> {
+    let output = Command::new("cargo")
+        .args([
+            "run",
+            "--",
+            "list",
+            "-p",
+            "fixtures/simple_project",
+            "--format",
+            "csv",
+        ])
+        .output()?;
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout)?;
+    let rows: Vec<Vec<&str>> = stdout
+        .lines()
+        .map(|line| line.split(',').collect())
+        .collect();
+    assert_eq!(
+        rows[0],
+        vec![
+            "name",
+            "version",
+            "resolved_version",
+            "available",
+            "kind",
+            "license"
+        ]
+    );
+    // Every row has all the columns, even when the license is unknown
+    assert!(rows.iter().all(|row| row.len() == 6));
+    let tokio_test = rows.iter().find(|row| row[0] == "tokio-test").unwrap();
+    assert_eq!(tokio_test[1..3], ["0.4", "0.4.3"]);
+    assert_eq!(tokio_test[4], "dev");
+
+    Ok(())
+}
+
+#[test]
+fn test_ignore_dirs_from_env() -> Result<()> {
+    // A project without dependencies, with a vendored crate that has some
+    let temp_dir = tempdir()?;
+    let project = temp_dir.path();
+    std::fs::write(
+        project.join("Cargo.toml"),
+        "[package]\nname = \"app\"\nversion = \"0.1.0\"\n",
+    )?;
+    let vendored = project.join("vendor").join("lib");
+    std::fs::create_dir_all(&vendored)?;
+    std::fs::write(
+        vendored.join("Cargo.toml"),
+        "[package]\nname = \"lib\"\nversion = \"0.1.0\"\n\n[dependencies]\nserde = \"1.0\"\n",
+    )?;
+
+    let generate = |env: Option<&str>, args: &[&str]| -> Result<String> {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_depbank"));
+        command
+            .args(["generate", "--dry-run", "-p"])
+            .arg(project)
+            .args(args)
+            .env_remove("DEPBANK_IGNORE_DIRS");
+        if let Some(value) = env {
+            command.env("DEPBANK_IGNORE_DIRS", value);
+        }
+        let output = command.output()?;
+        Ok(String::from_utf8(output.stdout)?)
+    };
+
+    // The environment keeps the vendored crate out of the search
+    let stdout = generate(Some("third_party, vendor"), &[])?;
+    assert!(stdout.contains("No dependencies found in 1 Cargo.toml file(s)"));
+
+    // Without it, or when --ignore-dir is given, the vendored crate is searched
+    assert!(!generate(None, &[])?.contains("No dependencies found"));
+    let stdout = generate(Some("vendor"), &["--ignore-dir", "third_party"])?;
+    assert!(!stdout.contains("No dependencies found"));
+
+    Ok(())
+}
+
+#[test]
+fn test_bank_ignore_dirs_from_env() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let cargo_home = mock_cargo_home(temp_dir.path(), &SIMPLE_PROJECT_DEPENDENCIES)?;
+    let fixtures = mock_crate_dir(&cargo_home, "anyhow", "1.0.80").join("fixtures");
+    std::fs::create_dir_all(&fixtures)?;
+    std::fs::write(
+        fixtures.join("data.rs"),
+        "/// A fixture.\npub fn fixture_data() {}\n",
+    )?;
+
+    let anyhow_bank = |env: &str, args: &[&str]| -> Result<String> {
+        let output_dir = temp_dir.path().join("banks");
+        let output = Command::new(env!("CARGO_BIN_EXE_depbank"))
+            .args(["generate", "-p", "fixtures/simple_project", "-o"])
+            .arg(&output_dir)
+            .args(args)
+            .env("CARGO_HOME", &cargo_home)
+            .env("DEPBANK_IGNORE_DIRS", env)
+            .output()?;
+        assert!(output.status.success());
+        let bank = std::fs::read_to_string(output_dir.join("anyhow.md"))?;
+        std::fs::remove_dir_all(&output_dir)?;
+        Ok(bank)
+    };
+
+    // The directories of the environment are left out of the banks
+    assert!(anyhow_bank("", &[])?.contains("fixture_data"));
+    assert!(!anyhow_bank("vendor,fixtures", &[])?.contains("fixture_data"));
+
+    // --bank-ignore-dir takes precedence over the environment
+    let bank = anyhow_bank("fixtures", &["--bank-ignore-dir", "tests"])?;
+    assert!(bank.contains("fixture_data"));
+
+    Ok(())
+}
+
+#[test]
+fn test_generate_report_savings_of_bank_ignore_dirs() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let cargo_home = mock_cargo_home(temp_dir.path(), &SIMPLE_PROJECT_DEPENDENCIES)?;
+    // Only anyhow has a directory that --bank-ignore-dir leaves out
+    let fixtures = mock_crate_dir(&cargo_home, "anyhow", "1.0.80").join("fixtures");
+    std::fs::create_dir_all(&fixtures)?;
+    let functions: String = (0..50)
+        .map(|index| format!("/// Fixture {index}.\npub fn fixture_{index}() {{}}\n"))
+        .collect();
+    std::fs::write(fixtures.join("data.rs"), functions)?;
+
+    let output = Command::new(env!("CARGO_BIN_EXE_depbank"))
+        .args([
+            "generate",
+            "--dry-run",
+            "--report-savings",
+            "--bank-ignore-dir",
+            "fixtures",
+            "-p",
+            "fixtures/simple_project",
+        ])
+        .env("CARGO_HOME", &cargo_home)
+        .output()?;
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.contains("Tokens saved by ignoring fixtures:"));
+    let anyhow = stdout
+        .lines()
+        .find(|line| line.starts_with("- anyhow 1.0.80: "))
+        .unwrap();
+    let saved: i64 = anyhow["- anyhow 1.0.80: ".len()..]
+        .split(' ')
+        .next()
+        .unwrap()
+        .parse()?;
+    assert!(saved > 0);
+    assert!(stdout.contains("- serde 1.0.197: 0 tokens"));
+
+    Ok(())
+}
+
+#[test]
+fn test_generate_max_deps() -> Result<()> {
+    // A mock registry holding all the fixture's direct dependencies
+    let temp_dir = tempdir()?;
+    let cargo_home = mock_cargo_home(temp_dir.path(), &SIMPLE_PROJECT_DEPENDENCIES)?;
+
+    let output_dir = temp_dir.path().join("banks");
+    let output = Command::new(env!("CARGO_BIN_EXE_depbank"))
+        .args([
+            "generate",
+            "-p",
+            "fixtures/simple_project",
+            "--max-deps",
+            "2",
+            "-o",
+        ])
+        .arg(&output_dir)
+        .env("CARGO_HOME", &cargo_home)
+        .output()?;
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.contains("Limited to the first 2 of 3 dependencies by --max-deps, skipping 1"));
+    let banks: Vec<String> = std::fs::read_dir(&output_dir)?
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .filter(|name| name.ends_with(".md") && name != "README.md")
+        .collect();
+    assert_eq!(banks.len(), 2);
+
+    Ok(())
+}
+
+#[test]
+fn test_generate_transitive_depths() -> Result<()> {
+    // app -> outer -> inner, all in a mock registry
+    let temp_dir = tempdir()?;
+    let project = temp_dir.path().join("app");
+    std::fs::create_dir_all(project.join("src"))?;
+    std::fs::write(
+        project.join("Cargo.toml"),
+        "[package]\nname = \"app\"\nversion = \"0.1.0\"\n\n[dependencies]\nouter = \"1.0\"\n",
+    )?;
+    std::fs::write(project.join("src").join("lib.rs"), "")?;
+    std::fs::write(
+        project.join("Cargo.lock"),
+        "version = 4\n\n\
+[[package]]\nname = \"app\"\nversion = \"0.1.0\"\ndependencies = [\"outer\"]\n\n\
+[[package]]\nname = \"inner\"\nversion = \"1.0.0\"\n\
+source = \"registry+https://github.com/rust-lang/crates.io-index\"\n\n\
+[[package]]\nname = \"outer\"\nversion = \"1.0.0\"\n\
+source = \"registry+https://github.com/rust-lang/crates.io-index\"\n\
+dependencies = [\"inner\"]\n",
+    )?;
+
+    let cargo_home = mock_cargo_home(temp_dir.path(), &[("outer", "1.0.0"), ("inner", "1.0.0")])?;
+    std::fs::write(
+        mock_crate_dir(&cargo_home, "outer", "1.0.0").join("Cargo.toml"),
+        "[package]\nname = \"outer\"\nversion = \"1.0.0\"\n\n[dependencies]\ninner = \"1.0\"\n",
+    )?;
+
+    let output_dir = temp_dir.path().join("banks");
+    let output = Command::new(env!("CARGO_BIN_EXE_depbank"))
+        .args(["generate", "--transitive", "-p"])
+        .arg(&project)
+        .arg("-o")
+        .arg(&output_dir)
+        .env("CARGO_HOME", &cargo_home)
+        .output()?;
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let outer = std::fs::read_to_string(output_dir.join("outer.md"))?;
+    assert!(outer.contains("> **Depth:** 0 (direct dependency)"));
+    let inner = std::fs::read_to_string(output_dir.join("inner.md"))?;
+    assert!(inner.contains("> **Depth:** 1 (transitive dependency)"));
+
+    let index: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(output_dir.join("index.json"))?)?;
+    let depths: Vec<(String, u64)> = index["banks"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|bank| {
+            (
+                bank["name"].as_str().unwrap().to_string(),
+                bank["depth"].as_u64().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(depths, [("inner".to_string(), 1), ("outer".to_string(), 0)]);
+
+    Ok(())
+}
+
+#[test]
+fn test_generate_archive_only() -> Result<()> {
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    // A mock registry holding all the fixture's direct dependencies
+    let temp_dir = tempdir()?;
+    let cargo_home = mock_cargo_home(temp_dir.path(), &SIMPLE_PROJECT_DEPENDENCIES)?;
+
+    let output_dir = temp_dir.path().join("banks");
+    let archive = temp_dir.path().join("banks.tar.gz");
+    let output = Command::new(env!("CARGO_BIN_EXE_depbank"))
+        .args([
+            "generate",
+            "-p",
+            "fixtures/simple_project",
+            "--archive-only",
+            "-o",
+        ])
+        .arg(&output_dir)
+        .arg("--archive")
+        .arg(&archive)
+        .env("CARGO_HOME", &cargo_home)
+        .output()?;
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.contains("Archived 3 code banks into"));
+    assert!(!output_dir.exists());
+
+    let mut tar = Vec::new();
+    GzDecoder::new(std::fs::read(&archive)?.as_slice()).read_to_end(&mut tar)?;
+    let tar = String::from_utf8_lossy(&tar);
+    for entry in ["anyhow.md\0", "serde.md\0", "tokio-test.md\0"] {
+        assert!(tar.contains(entry), "missing {}", entry);
+    }
+    assert!(tar.contains("pub struct Mock"));
+    assert!(!tar.contains("README.md\0"));
+
+    // Options relying on the output directory are rejected rather than ignored
+    let output = Command::new(env!("CARGO_BIN_EXE_depbank"))
+        .args([
+            "generate",
+            "-p",
+            "fixtures/simple_project",
+            "--archive-only",
+            "--timings",
+        ])
+        .arg("--archive")
+        .arg(&archive)
+        .env("CARGO_HOME", &cargo_home)
+        .output()?;
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)?.contains("cannot be used with"));
+
+    Ok(())
+}
+
+#[test]
+fn test_generate_deps_file() -> Result<()> {
+    // A mock registry holding all the fixture's direct dependencies
+    let temp_dir = tempdir()?;
+    let cargo_home = mock_cargo_home(temp_dir.path(), &SIMPLE_PROJECT_DEPENDENCIES)?;
+    let deps_file = temp_dir.path().join("deps.txt");
+    std::fs::write(
+        &deps_file,
+        "# Changed upstream\nserde@1.0.197\ntokio_test\nanyhow@0.1.0\nrand\n",
+    )?;
+
+    let output_dir = temp_dir.path().join("banks");
+    let output = Command::new(env!("CARGO_BIN_EXE_depbank"))
+        .args(["generate", "-p", "fixtures/simple_project", "--deps-file"])
+        .arg(&deps_file)
+        .arg("-o")
+        .arg(&output_dir)
+        .env("CARGO_HOME", &cargo_home)
+        .output()?;
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.contains("2/3 dependencies listed in"));
+    assert!(
+        stdout.contains("Skipping 2 listed dependencies that are not resolved: anyhow@0.1.0, rand")
+    );
+    let mut banks: Vec<String> = std::fs::read_dir(&output_dir)?
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .filter(|name| name.ends_with(".md") && name != "README.md")
+        .collect();
+    banks.sort();
+    assert_eq!(banks, ["serde.md", "tokio-test.md"]);
+
+    Ok(())
+}